@@ -11,4 +11,8 @@ fn main() {
     println!("\nFor JSON files:");
     println!("Central: \"{}\"", central);
     println!("Network: \"{}\"", network);
+    
+    // Master key for encrypting token issuer keys (TOKEN_KEY_MASTER_KEY / token_key_master_key.txt)
+    let master_key: [u8; 32] = rand::random();
+    println!("\nToken key master key: {}", hex::encode(master_key));
 }
\ No newline at end of file
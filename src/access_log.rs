@@ -0,0 +1,74 @@
+//! One structured line per request on the `access` log target, e.g.
+//! `method=GET path=/v1/api/users/7xKX…gAsU/transactions status=200 duration_ms=12`.
+//! Paths and queries go through the log redaction, so wallet addresses and emails in them
+//! are masked unless LOG_REDACTION=off.
+
+use std::future::{ready, Ready};
+use std::time::Instant;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::LocalBoxFuture;
+use crate::utils::redaction::redact;
+
+pub struct AccessLog;
+
+impl<S, B> Transform<S, ServiceRequest> for AccessLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = AccessLogMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AccessLogMiddleware { service }))
+    }
+}
+
+pub struct AccessLogMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AccessLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        let path = match req.query_string() {
+            "" => req.path().to_string(),
+            query => format!("{}?{}", req.path(), query),
+        };
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let response = response.await;
+            let duration_ms = started.elapsed().as_millis();
+            match &response {
+                Ok(response) => log::info!(
+                    target: "access",
+                    "method={} path={} status={} duration_ms={}",
+                    method, redact(&path), response.status().as_u16(), duration_ms
+                ),
+                Err(e) => log::warn!(
+                    target: "access",
+                    "method={} path={} status={} duration_ms={} error=\"{}\"",
+                    method, redact(&path), e.as_response_error().status_code().as_u16(), duration_ms, redact(&e.to_string())
+                ),
+            }
+            response
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
+use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
+use crate::models::{ApiError, Role, UsedSignature};
+use crate::models::cause::Cause;
+use crate::request_digest::RequestBodyDigest;
+use crate::services::MongoDBService;
+use crate::utils::wallet_signature::{signing_message, verify_wallet_signature};
+
+pub const WALLET_ADDRESS_HEADER: &str = "X-Wallet-Address";
+pub const WALLET_TIMESTAMP_HEADER: &str = "X-Wallet-Timestamp";
+pub const WALLET_SIGNATURE_HEADER: &str = "X-Wallet-Signature";
+
+/// How far a signed timestamp may drift from server time, in seconds
+pub const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+/// A wallet that proved ownership of its address by signing
+/// "METHOD:path?query:timestamp:body_hash". Use as a handler argument to require
+/// authentication; the request is rejected with 401 before the handler runs if the
+/// signature is missing, invalid or was already used.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub wallet_address: String,
+    pub roles: Vec<Role>,
+}
+
+impl AuthenticatedUser {
+    pub fn has_role(&self, role: &Role) -> bool {
+        self.roles.contains(role)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.has_role(&Role::Admin)
+    }
+
+    /// Admins pass every role check
+    pub fn require_role(&self, role: Role) -> Result<(), ApiError> {
+        if self.is_admin() || self.has_role(&role) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!("Requires {} role", role)))
+        }
+    }
+
+    /// Allow the wallet itself or an admin to act on `wallet_address`
+    pub fn require_self_or_admin(&self, wallet_address: &str) -> Result<(), ApiError> {
+        if self.is_admin() || self.wallet_address == wallet_address {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden("Cannot act on another wallet".to_string()))
+        }
+    }
+
+    /// Cause owners can manage only their own causes
+    pub fn can_manage_cause(&self, cause: &Cause) -> bool {
+        self.is_admin() || cause.owner_address.as_deref() == Some(self.wallet_address.as_str())
+    }
+
+    pub fn require_cause_manager(&self, cause: &Cause) -> Result<(), ApiError> {
+        if self.can_manage_cause(cause) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden("Only the cause owner or an admin can manage this cause".to_string()))
+        }
+    }
+}
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Result<&'a str, ApiError> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ApiError::Unauthorized(format!("Missing {} header", name)))
+}
+
+/// Wallets listed in ADMIN_WALLET_ADDRESSES (comma-separated) are always admins,
+/// so the first admin can be bootstrapped without touching the database
+fn is_configured_admin(wallet_address: &str) -> bool {
+    std::env::var("ADMIN_WALLET_ADDRESSES")
+        .map(|list| list.split(',').any(|a| a.trim() == wallet_address))
+        .unwrap_or(false)
+}
+
+/// Check the signature covers this exact request, returning the wallet and its signature
+fn verify_request(req: &HttpRequest) -> Result<(String, String), ApiError> {
+    let wallet_address = header(req, WALLET_ADDRESS_HEADER)?.to_string();
+    let timestamp: i64 = header(req, WALLET_TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| ApiError::Unauthorized("Invalid timestamp".to_string()))?;
+    let signature = header(req, WALLET_SIGNATURE_HEADER)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    if (now - timestamp).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(ApiError::Unauthorized("Signature expired".to_string()));
+    }
+
+    // The body is hashed by the RequestDigest middleware before extractors consume it
+    let body_hash = req.extensions().get::<RequestBodyDigest>()
+        .map(|digest| digest.0.clone())
+        .ok_or_else(|| ApiError::InternalError("Request body digest is not configured".to_string()))?;
+    let path_and_query = match req.query_string() {
+        "" => req.path().to_string(),
+        query => format!("{}?{}", req.path(), query),
+    };
+    let message = signing_message(req.method().as_str(), &path_and_query, timestamp, &body_hash);
+    verify_wallet_signature(&wallet_address, message.as_bytes(), signature)
+        .map_err(ApiError::Unauthorized)?;
+
+    let signature = signature.strip_prefix("0x").unwrap_or(signature).to_lowercase();
+    Ok((wallet_address, signature))
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let verified = verify_request(req);
+        let mongodb = req.app_data::<web::Data<MongoDBService>>().cloned();
+
+        Box::pin(async move {
+            let (wallet_address, signature) = verified?;
+            let mongodb = mongodb
+                .ok_or_else(|| ApiError::InternalError("MongoDB service not configured".to_string()))?;
+
+            // A captured request can't be sent again while its timestamp is still fresh
+            let used = UsedSignature {
+                id: None,
+                signature,
+                wallet_address: wallet_address.clone(),
+                used_at: chrono::Utc::now(),
+            };
+            if !mongodb.claim_request_signature(&used).await? {
+                return Err(ApiError::Unauthorized("Signature already used".to_string()));
+            }
+
+            let mut roles = match mongodb.get_user_by_wallet(&wallet_address).await? {
+                Some(user) => user.effective_roles(),
+                None => vec![Role::User],
+            };
+            if is_configured_admin(&wallet_address) && !roles.contains(&Role::Admin) {
+                roles.push(Role::Admin);
+            }
+
+            Ok(AuthenticatedUser { wallet_address, roles })
+        })
+    }
+}
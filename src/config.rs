@@ -1,12 +1,20 @@
-use std::{env, path::PathBuf, fs, str::FromStr};
+use std::{collections::HashMap, env, path::PathBuf, fs, str::FromStr, time::Duration};
 use delta_executor_sdk::base::crypto::{Ed25519PrivKey, Ed25519PubKey, read_keypair};
-use log::{info, debug};
+use log::{info, warn, debug};
+use serde::Serialize;
+use crate::utils::cron::CronSchedule;
+use crate::utils::payment_calculator::ON_CHAIN_UNITS_PER_TOKEN;
 
 pub struct KeyConfig {
     pub central_vault_keypair: Ed25519PrivKey,
     pub central_vault_pubkey: Ed25519PubKey,
     pub network_goods_vault_keypair: Ed25519PrivKey,
     pub network_goods_vault_pubkey: Ed25519PubKey,
+    pub escrow_vault_keypair: Ed25519PrivKey,
+    pub escrow_vault_pubkey: Ed25519PubKey,
+    pub matching_vault_keypair: Option<Ed25519PrivKey>,  // pays matching pool matches; unset disables matching
+    pub token_key_master_key: [u8; 32],
+    pub api_signing_key: Option<[u8; 32]>,  // signs responses when set
 }
 
 impl KeyConfig {
@@ -21,11 +29,56 @@ impl KeyConfig {
             "network_goods_vault_keypair.json"
         )?;
 
+        // Escrowed payments are held in their own vault so they never mix with the platform's
+        // holdings; outside production, deployments that haven't provisioned one fall back to
+        // the central vault
+        let escrow_configured = env::var("ESCROW_VAULT_PRIVATE_KEY").is_ok()
+            || PathBuf::from("escrow_vault_keypair.json").exists();
+        let (escrow_vault_keypair, escrow_vault_pubkey) = if escrow_configured {
+            load_keypair("ESCROW_VAULT_PRIVATE_KEY", "escrow_vault_keypair.json")?
+        } else if env::var("ENVIRONMENT").as_deref() == Ok("production") {
+            return Err("ESCROW_VAULT_PRIVATE_KEY or escrow_vault_keypair.json must be set in production".into());
+        } else {
+            warn!("No escrow vault configured, escrowed payments will be held in the central vault");
+            (central_vault_keypair.clone(), central_vault_pubkey)
+        };
+
+        // Matches are paid out of a vault sponsors fund with the causes' tokens, never minted
+        let matching_configured = env::var("MATCHING_VAULT_PRIVATE_KEY").is_ok()
+            || PathBuf::from("matching_vault_keypair.json").exists();
+        let matching_vault_keypair = if matching_configured {
+            Some(load_keypair("MATCHING_VAULT_PRIVATE_KEY", "matching_vault_keypair.json")?.0)
+        } else {
+            warn!("No matching vault configured, donations will not be matched from matching pools");
+            None
+        };
+
+        let token_key_master_key = load_master_key(
+            "TOKEN_KEY_MASTER_KEY",
+            "token_key_master_key.txt"
+        )?;
+
+        // Response signing is optional and uses its own key, so partners never hold a
+        // key that can also move the central vault's funds
+        let api_signing_configured = env::var("API_SIGNING_PRIVATE_KEY").is_ok()
+            || PathBuf::from("api_signing_key.txt").exists();
+        let api_signing_key = if api_signing_configured {
+            Some(load_master_key("API_SIGNING_PRIVATE_KEY", "api_signing_key.txt")?)
+        } else {
+            info!("No API signing key configured, responses will not be signed");
+            None
+        };
+
         Ok(KeyConfig {
             central_vault_keypair,
             central_vault_pubkey,
             network_goods_vault_keypair,
             network_goods_vault_pubkey,
+            escrow_vault_keypair,
+            escrow_vault_pubkey,
+            matching_vault_keypair,
+            token_key_master_key,
+            api_signing_key,
         })
     }
 }
@@ -64,11 +117,713 @@ fn load_keypair_from_json(json_file_path: &str) -> Result<(Ed25519PrivKey, Ed255
     Ok((private_key, public_key))
 }
 
+/// Load a 32-byte secret in 64-char hex, such as the master key used to wrap token issuer keys
+fn load_master_key(env_var_name: &str, file_path: &str) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    if let Ok(hex_str) = env::var(env_var_name) {
+        info!("Loading {} from environment variable", env_var_name);
+        return parse_master_key(&hex_str).map_err(|e| format!("Invalid key in {}: {}", env_var_name, e).into());
+    }
+
+    info!("Environment variable {} not found, falling back to file: {}", env_var_name, file_path);
+    let path = PathBuf::from(file_path);
+    if !path.exists() {
+        return Err(format!("Master key file not found: {}", file_path).into());
+    }
+
+    let contents = fs::read_to_string(&path)?;
+    parse_master_key(&contents).map_err(|e| format!("Invalid key in {}: {}", file_path, e).into())
+}
+
+fn parse_master_key(hex_str: &str) -> Result<[u8; 32], String> {
+    let hex_str = hex_str.trim();
+    let hex_str = hex_str.strip_prefix("0x").unwrap_or(hex_str);
+
+    let bytes = hex::decode(hex_str).map_err(|e| format!("Invalid hex format: {}", e))?;
+    bytes.try_into().map_err(|b: Vec<u8>| format!("Master key must be 32 bytes, got {}", b.len()))
+}
+
+/// Payment methods offered by Checkout and embedded card forms. Apple Pay and Google Pay
+/// are card wallets: they show up when `card` is enabled and the domain is registered with Stripe.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct PaymentMethodConfig {
+    pub payment_method_types: Vec<String>,
+    pub payment_method_configuration: Option<String>,  // pmc_... set in the Stripe dashboard; overrides the types
+    pub apple_pay: bool,
+    pub google_pay: bool,
+    pub publishable_key: Option<String>,
+}
+
+impl PaymentMethodConfig {
+    /// Read STRIPE_PAYMENT_METHOD_TYPES (default "card"), STRIPE_PAYMENT_METHOD_CONFIGURATION,
+    /// STRIPE_WALLETS (default "apple_pay,google_pay", "none" disables) and STRIPE_PUBLISHABLE_KEY
+    pub fn from_env() -> Self {
+        let non_empty = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self::parse(
+            non_empty("STRIPE_PAYMENT_METHOD_TYPES").as_deref(),
+            non_empty("STRIPE_PAYMENT_METHOD_CONFIGURATION"),
+            non_empty("STRIPE_WALLETS").as_deref(),
+            non_empty("STRIPE_PUBLISHABLE_KEY"),
+        )
+    }
+
+    fn parse(types: Option<&str>, configuration: Option<String>, wallets: Option<&str>, publishable_key: Option<String>) -> Self {
+        let list = |value: &str| -> Vec<String> {
+            value.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect()
+        };
+        let payment_method_types = list(types.unwrap_or("card"));
+        let wallets = list(wallets.unwrap_or("apple_pay,google_pay"));
+        // Without card (and no dashboard configuration to decide), wallets can't be offered
+        let cards = configuration.is_some() || payment_method_types.iter().any(|t| t == "card");
+
+        Self {
+            apple_pay: cards && wallets.iter().any(|w| w == "apple_pay"),
+            google_pay: cards && wallets.iter().any(|w| w == "google_pay"),
+            payment_method_types,
+            payment_method_configuration: configuration,
+            publishable_key,
+        }
+    }
+}
+
+/// How a cause's connected account in a given country is set up
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectServiceAgreement {
+    Full,       // takes card payments and transfers
+    Recipient,  // only receives transfers; Stripe's cross-border payouts for countries the platform can't onboard fully
+}
+
+/// Countries causes can onboard their Stripe connected accounts in
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectConfig {
+    countries: std::collections::BTreeMap<String, ConnectServiceAgreement>,
+}
+
+impl ConnectConfig {
+    /// Read STRIPE_CONNECT_COUNTRIES (default "US") and STRIPE_CONNECT_RECIPIENT_COUNTRIES
+    /// (default none), comma-separated ISO country codes. A country in both is onboarded fully.
+    pub fn from_env() -> Self {
+        let non_empty = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self::parse(
+            non_empty("STRIPE_CONNECT_COUNTRIES").as_deref(),
+            non_empty("STRIPE_CONNECT_RECIPIENT_COUNTRIES").as_deref(),
+        )
+    }
+
+    pub(crate) fn parse(full: Option<&str>, recipient: Option<&str>) -> Self {
+        let list = |value: &str| -> Vec<String> {
+            value.split(',').map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty()).collect()
+        };
+        let mut countries = std::collections::BTreeMap::new();
+        for country in list(recipient.unwrap_or("")) {
+            countries.insert(country, ConnectServiceAgreement::Recipient);
+        }
+        for country in list(full.unwrap_or("US")) {
+            countries.insert(country, ConnectServiceAgreement::Full);
+        }
+        Self { countries }
+    }
+
+    /// None if causes can't onboard in `country`
+    pub fn service_agreement(&self, country: &str) -> Option<ConnectServiceAgreement> {
+        self.countries.get(&country.to_uppercase()).copied()
+    }
+
+    pub fn countries(&self) -> Vec<&str> {
+        self.countries.keys().map(String::as_str).collect()
+    }
+}
+
+/// Webhook signing secrets from a comma-separated list, current first.
+/// During rotation set e.g. `whsec_new,whsec_old` and drop the old one once it stops matching.
+pub fn parse_webhook_secrets(value: &str) -> Vec<String> {
+    value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+/// Connection pool and timeout settings for the shared outbound HTTP client
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    pub tcp_keepalive: Duration,
+    pub connect_timeout: Duration,
+    pub timeout: Duration,  // whole request, unless the caller sets its own
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout: Duration::from_secs(90),
+            tcp_keepalive: Duration::from_secs(60),
+            connect_timeout: Duration::from_secs(2),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// Read HTTP_POOL_MAX_IDLE_PER_HOST, HTTP_POOL_IDLE_TIMEOUT_SECS, HTTP_TCP_KEEPALIVE_SECS,
+    /// HTTP_CONNECT_TIMEOUT_MS and HTTP_TIMEOUT_MS, keeping the default for any that are
+    /// unset or invalid
+    pub fn from_env() -> Self {
+        let number = |name: &str| env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            pool_max_idle_per_host: number("HTTP_POOL_MAX_IDLE_PER_HOST").map(|n| n as usize).unwrap_or(defaults.pool_max_idle_per_host),
+            pool_idle_timeout: number("HTTP_POOL_IDLE_TIMEOUT_SECS").map(Duration::from_secs).unwrap_or(defaults.pool_idle_timeout),
+            tcp_keepalive: number("HTTP_TCP_KEEPALIVE_SECS").map(Duration::from_secs).unwrap_or(defaults.tcp_keepalive),
+            connect_timeout: number("HTTP_CONNECT_TIMEOUT_MS").map(Duration::from_millis).unwrap_or(defaults.connect_timeout),
+            timeout: number("HTTP_TIMEOUT_MS").map(Duration::from_millis).unwrap_or(defaults.timeout),
+        }
+    }
+
+    /// Build the client shared by every outbound caller. Clones share the connection pool.
+    pub fn build_client(&self) -> Result<reqwest::Client, reqwest::Error> {
+        reqwest::Client::builder()
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+            .tcp_nodelay(true)
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .build()
+    }
+}
+
+/// Timeouts, retries and circuit breaker settings for calls to the executor
+#[derive(Debug, Clone)]
+pub struct ExecutorPolicy {
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_base_delay: Duration,
+    pub retry_max_delay: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+    pub balance_cache_ttl: Duration,  // how long a fetched vault is reused, zero disables
+}
+
+impl Default for ExecutorPolicy {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(10),
+            max_retries: 2,
+            retry_base_delay: Duration::from_millis(200),
+            retry_max_delay: Duration::from_secs(2),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+            balance_cache_ttl: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ExecutorPolicy {
+    /// Read EXECUTOR_TIMEOUT_MS, EXECUTOR_MAX_RETRIES,
+    /// EXECUTOR_RETRY_BASE_MS, EXECUTOR_BREAKER_THRESHOLD, EXECUTOR_BREAKER_COOLDOWN_SECS and
+    /// EXECUTOR_BALANCE_CACHE_MS (at most 5s), keeping the default for any that are unset or invalid
+    pub fn from_env() -> Self {
+        let number = |name: &str| env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            request_timeout: number("EXECUTOR_TIMEOUT_MS").map(Duration::from_millis).unwrap_or(defaults.request_timeout),
+            max_retries: number("EXECUTOR_MAX_RETRIES").map(|n| n.min(10) as u32).unwrap_or(defaults.max_retries),
+            retry_base_delay: number("EXECUTOR_RETRY_BASE_MS").map(Duration::from_millis).unwrap_or(defaults.retry_base_delay),
+            breaker_threshold: number("EXECUTOR_BREAKER_THRESHOLD").map(|n| n.clamp(1, u32::MAX as u64) as u32).unwrap_or(defaults.breaker_threshold),
+            breaker_cooldown: number("EXECUTOR_BREAKER_COOLDOWN_SECS").map(Duration::from_secs).unwrap_or(defaults.breaker_cooldown),
+            balance_cache_ttl: number("EXECUTOR_BALANCE_CACHE_MS").map(|ms| Duration::from_millis(ms.min(5000))).unwrap_or(defaults.balance_cache_ttl),
+            ..defaults
+        }
+    }
+}
+
+/// How payment bundles are shaped before they go on-chain
+#[derive(Debug, Clone)]
+pub struct BundlePolicy {
+    pub dust_threshold: f64,  // smallest token amount worth paying; smaller legs are folded into others, zero disables
+}
+
+impl Default for BundlePolicy {
+    fn default() -> Self {
+        // One on-chain unit; anything less rounds to nothing
+        Self { dust_threshold: 1.0 / ON_CHAIN_UNITS_PER_TOKEN }
+    }
+}
+
+impl BundlePolicy {
+    /// Read PAYMENT_DUST_THRESHOLD, keeping the default if it's unset or invalid
+    pub fn from_env() -> Self {
+        Self::parse(env::var("PAYMENT_DUST_THRESHOLD").ok().as_deref())
+    }
+
+    fn parse(dust_threshold: Option<&str>) -> Self {
+        let defaults = Self::default();
+        Self {
+            dust_threshold: dust_threshold
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .unwrap_or(defaults.dust_threshold),
+        }
+    }
+}
+
+/// Response header on every response of a sandbox deployment
+pub const SANDBOX_HEADER: &str = "X-Index-Sandbox";
+
+/// Database of a live deployment
+pub const LIVE_DATABASE: &str = "index_wallets";
+
+/// A platform-level sandbox partners integrate against: Stripe test keys, a test executor and
+/// a database of its own, so nothing it does touches live money or data
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxConfig {
+    pub enabled: bool,
+    pub database: String,               // the sandbox's own database
+    pub executor_url: Option<String>,   // the test executor
+}
+
+impl SandboxConfig {
+    /// Read SANDBOX_MODE ("true" or "1"), SANDBOX_MONGODB_DATABASE (default
+    /// index_wallets_sandbox) and SANDBOX_EXECUTOR_URL
+    pub fn from_env() -> Self {
+        let non_empty = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self::parse(
+            non_empty("SANDBOX_MODE").as_deref(),
+            non_empty("SANDBOX_MONGODB_DATABASE"),
+            non_empty("SANDBOX_EXECUTOR_URL"),
+        )
+    }
+
+    fn parse(mode: Option<&str>, database: Option<String>, executor_url: Option<String>) -> Self {
+        Self {
+            enabled: mode.map_or(false, |v| matches!(v.trim().to_lowercase().as_str(), "true" | "1")),
+            database: database.map(|d| d.trim().to_string()).unwrap_or_else(|| format!("{}_sandbox", LIVE_DATABASE)),
+            executor_url: executor_url.map(|u| u.trim().to_string()),
+        }
+    }
+
+    /// The database this deployment uses
+    pub fn database(&self) -> &str {
+        if self.enabled { &self.database } else { LIVE_DATABASE }
+    }
+
+    /// The executor a sandbox uses instead of EXECUTOR_URL
+    pub fn executor_url(&self) -> Option<&str> {
+        self.executor_url.as_deref().filter(|_| self.enabled)
+    }
+
+    /// Refuse to start with keys or services of the other mode: a sandbox with live Stripe
+    /// keys, the live database or the live executor, or a production deployment with
+    /// Stripe test keys
+    pub fn check(&self, environment: Option<&str>, stripe_keys: &[&str], live_executor_url: Option<&str>) -> Result<(), String> {
+        let production = environment == Some("production");
+        if !self.enabled {
+            if production && stripe_keys.iter().any(|key| key.contains("_test_")) {
+                return Err("Stripe test keys can't be used in production outside SANDBOX_MODE".to_string());
+            }
+            return Ok(());
+        }
+        if stripe_keys.iter().any(|key| key.contains("_live_")) {
+            return Err("Live Stripe keys can't be used in SANDBOX_MODE".to_string());
+        }
+        if self.database == LIVE_DATABASE {
+            return Err("SANDBOX_MODE can't use the live database".to_string());
+        }
+        if production {
+            match self.executor_url() {
+                None => return Err("SANDBOX_EXECUTOR_URL must be set in SANDBOX_MODE in production".to_string()),
+                Some(url) if Some(url) == live_executor_url => return Err("SANDBOX_EXECUTOR_URL can't be the live EXECUTOR_URL".to_string()),
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Origins allowed to call the API from a browser in development, for local frontends
+const DEV_CORS_ORIGINS: &[&str] = &["http://localhost:3000", "http://localhost:5173", "http://localhost:8081", "http://127.0.0.1:3000"];
+
+/// Which browser origins may call the API, and with which methods and request headers
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,  // exact origins, e.g. https://app.example.org; "*" allows any
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+}
+
+impl CorsConfig {
+    /// Read CORS_ALLOWED_ORIGINS, CORS_ALLOWED_METHODS and CORS_ALLOWED_HEADERS, all
+    /// comma-separated. Without CORS_ALLOWED_ORIGINS, development allows the local frontends
+    /// and production (ENVIRONMENT=production) allows no browser origins.
+    pub fn from_env() -> Self {
+        let non_empty = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self::parse(
+            non_empty("ENVIRONMENT").as_deref(),
+            non_empty("CORS_ALLOWED_ORIGINS").as_deref(),
+            non_empty("CORS_ALLOWED_METHODS").as_deref(),
+            non_empty("CORS_ALLOWED_HEADERS").as_deref(),
+        )
+    }
+
+    fn parse(environment: Option<&str>, origins: Option<&str>, methods: Option<&str>, headers: Option<&str>) -> Self {
+        let list = |value: &str| -> Vec<String> {
+            value.split(',').map(|s| s.trim().trim_end_matches('/').to_string()).filter(|s| !s.is_empty()).collect()
+        };
+        let allowed_origins = match origins {
+            Some(origins) => list(origins),
+            None if environment == Some("production") => Vec::new(),
+            None => DEV_CORS_ORIGINS.iter().map(|o| o.to_string()).collect(),
+        };
+        Self {
+            allowed_origins,
+            allowed_methods: list(methods.unwrap_or("GET,POST,PUT,PATCH,DELETE,OPTIONS")).into_iter().map(|m| m.to_uppercase()).collect(),
+            allowed_headers: list(headers.unwrap_or(
+                "accept,content-type,api-version,if-none-match,x-wallet-address,x-wallet-timestamp,x-wallet-signature"
+            )).into_iter().map(|h| h.to_lowercase()).collect(),
+        }
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    /// Whether a request's `Origin` may call the API. Origins are compared exactly, ignoring case.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allows_any_origin() || self.allowed_origins.iter().any(|o| o.eq_ignore_ascii_case(origin))
+    }
+
+    /// Embedded donate buttons call `/embed/*` from causes' own sites, so any origin may;
+    /// those endpoints check the origin against the embed token instead
+    pub fn is_embed_path(path: &str) -> bool {
+        let path = match path.strip_prefix("/v") {
+            Some(versioned) => versioned.trim_start_matches(|c: char| c.is_ascii_digit()),
+            None => path,
+        };
+        path.starts_with("/embed/")
+    }
+}
+
+/// Largest request bodies accepted, in bytes. Larger ones are refused with 413 before
+/// they're read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyLimits {
+    pub json: usize,     // JSON request bodies, e.g. `payer_balances` in a supplement
+    pub payload: usize,  // raw bodies, i.e. Stripe webhooks
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self { json: 64 * 1024, payload: 256 * 1024 }
+    }
+}
+
+impl BodyLimits {
+    /// Read JSON_BODY_LIMIT_BYTES and PAYLOAD_LIMIT_BYTES, keeping the default for either
+    /// that is unset, invalid or zero
+    pub fn from_env() -> Self {
+        Self::parse(
+            env::var("JSON_BODY_LIMIT_BYTES").ok().as_deref(),
+            env::var("PAYLOAD_LIMIT_BYTES").ok().as_deref(),
+        )
+    }
+
+    fn parse(json: Option<&str>, payload: Option<&str>) -> Self {
+        let bytes = |value: Option<&str>| value.and_then(|v| v.trim().parse::<usize>().ok()).filter(|n| *n > 0);
+        let defaults = Self::default();
+        Self {
+            json: bytes(json).unwrap_or(defaults.json),
+            payload: bytes(payload).unwrap_or(defaults.payload),
+        }
+    }
+}
+
+/// Background jobs: cron expressions that replace a job's interval, and the lease electing
+/// the one replica that runs them
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerConfig {
+    pub schedules: HashMap<String, CronSchedule>,  // by job name
+    pub leader_election: bool,  // off for a single replica, which then always runs jobs
+    pub lease: Duration,        // how long a leader that stops renewing keeps the lease
+}
+
+impl SchedulerConfig {
+    /// Read JOB_SCHEDULES (`name=expression` pairs separated by `;`, e.g.
+    /// `reconciliation=0 3 * * *;voucher_expiry=*/5 * * * *`), JOB_LEADER_ELECTION (default
+    /// on) and JOB_LEADER_LEASE_SECS (default 30)
+    pub fn from_env() -> Self {
+        Self::parse(
+            env::var("JOB_SCHEDULES").ok().as_deref(),
+            env::var("JOB_LEADER_ELECTION").ok().as_deref(),
+            env::var("JOB_LEADER_LEASE_SECS").ok().as_deref(),
+        )
+    }
+
+    fn parse(schedules: Option<&str>, leader_election: Option<&str>, lease_secs: Option<&str>) -> Self {
+        let mut parsed = HashMap::new();
+        for entry in schedules.unwrap_or_default().split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=').map(|(name, expression)| (name.trim(), expression.parse::<CronSchedule>())) {
+                Some((name, Ok(schedule))) if !name.is_empty() => {
+                    parsed.insert(name.to_string(), schedule);
+                }
+                Some((name, Err(e))) => warn!("Ignoring schedule of job {}: {}", name, e),
+                _ => warn!("Ignoring JOB_SCHEDULES entry \"{}\", expected name=expression", entry),
+            }
+        }
+        Self {
+            schedules: parsed,
+            leader_election: leader_election.map_or(true, |v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | "off")),
+            lease: Duration::from_secs(lease_secs.and_then(|v| v.trim().parse::<u64>().ok()).filter(|n| *n > 0).unwrap_or(30)),
+        }
+    }
+}
+
+/// The platform's public keys, published for clients and auditors to check on-chain
+/// transfers and signed responses against
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedKeys {
+    pub central_vault: String,
+    pub network_goods_vault: String,
+    pub escrow_vault: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matching_vault: Option<String>,
+    pub signing_keys: Vec<PublishedSigningKey>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PublishedSigningKey {
+    pub version: u32,
+    pub algorithm: &'static str,
+    pub public_key: String,  // base58
+    pub current: bool,  // retired keys stay listed so older signatures can still be checked
+}
+
+impl PublishedKeys {
+    /// Read API_SIGNING_PREVIOUS_KEYS, the base58 public keys of retired API signing keys,
+    /// comma-separated oldest first
+    pub fn from_env(keys: &KeyConfig, signing_public_key: Option<String>) -> Self {
+        Self {
+            central_vault: keys.central_vault_pubkey.to_string(),
+            network_goods_vault: keys.network_goods_vault_pubkey.to_string(),
+            escrow_vault: keys.escrow_vault_pubkey.to_string(),
+            matching_vault: keys.matching_vault_keypair.as_ref().map(|keypair| keypair.pub_key().to_string()),
+            signing_keys: signing_key_versions(&env::var("API_SIGNING_PREVIOUS_KEYS").unwrap_or_default(), signing_public_key),
+        }
+    }
+
+    pub fn current_signing_key(&self) -> Option<&PublishedSigningKey> {
+        self.signing_keys.iter().find(|key| key.current)
+    }
+}
+
+/// Number the signing keys from 1, oldest first, with the current key last. A retired key
+/// that is also the current one is only listed once, as current.
+fn signing_key_versions(previous: &str, current: Option<String>) -> Vec<PublishedSigningKey> {
+    let mut public_keys: Vec<String> = previous.split(',')
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty() && Some(key) != current.as_ref())
+        .collect();
+    let current_index = current.map(|key| {
+        public_keys.push(key);
+        public_keys.len() - 1
+    });
+    public_keys.into_iter().enumerate()
+        .map(|(index, public_key)| PublishedSigningKey {
+            version: index as u32 + 1,
+            algorithm: "ed25519",
+            public_key,
+            current: Some(index) == current_index,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::env;
 
+    #[test]
+    fn test_parse_master_key() {
+        let key = parse_master_key("0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef\n").unwrap();
+        assert_eq!(key[0], 0x12);
+        assert_eq!(key[31], 0xef);
+    }
+
+    #[test]
+    fn test_parse_master_key_invalid_length() {
+        let result = parse_master_key("1234567890abcdef");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("must be 32 bytes"));
+    }
+
+    #[test]
+    fn test_signing_key_versions() {
+        let keys = signing_key_versions("old1, old2,", Some("new".to_string()));
+        let listed: Vec<(u32, &str, bool)> = keys.iter().map(|k| (k.version, k.public_key.as_str(), k.current)).collect();
+        assert_eq!(listed, vec![(1, "old1", false), (2, "old2", false), (3, "new", true)]);
+    }
+
+    #[test]
+    fn test_signing_key_versions_without_current_key() {
+        let keys = signing_key_versions("old1", None);
+        assert_eq!(keys.len(), 1);
+        assert!(!keys[0].current);
+        assert!(signing_key_versions("", None).is_empty());
+    }
+
+    #[test]
+    fn test_signing_key_versions_current_not_repeated() {
+        let keys = signing_key_versions("old1,new", Some("new".to_string()));
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[1].public_key, "new");
+        assert!(keys[1].current);
+    }
+
+    #[test]
+    fn test_payment_method_config_defaults() {
+        let config = PaymentMethodConfig::parse(None, None, None, None);
+        assert_eq!(config.payment_method_types, vec!["card".to_string()]);
+        assert!(config.apple_pay && config.google_pay);
+
+        let config = PaymentMethodConfig::parse(Some("card, Link"), None, Some("google_pay"), None);
+        assert_eq!(config.payment_method_types, vec!["card".to_string(), "link".to_string()]);
+        assert!(!config.apple_pay && config.google_pay);
+    }
+
+    #[test]
+    fn test_connect_config() {
+        let config = ConnectConfig::parse(None, None);
+        assert_eq!(config.countries(), vec!["US"]);
+        assert_eq!(config.service_agreement("us"), Some(ConnectServiceAgreement::Full));
+        assert_eq!(config.service_agreement("GB"), None);
+
+        let config = ConnectConfig::parse(Some("us, gb"), Some("MX,GB"));
+        assert_eq!(config.countries(), vec!["GB", "MX", "US"]);
+        assert_eq!(config.service_agreement("GB"), Some(ConnectServiceAgreement::Full));
+        assert_eq!(config.service_agreement("mx"), Some(ConnectServiceAgreement::Recipient));
+    }
+
+    #[test]
+    fn test_payment_method_config_wallets_need_cards() {
+        let config = PaymentMethodConfig::parse(Some("us_bank_account"), None, None, None);
+        assert!(!config.apple_pay && !config.google_pay);
+
+        let config = PaymentMethodConfig::parse(Some("us_bank_account"), Some("pmc_123".to_string()), Some("none"), None);
+        assert!(!config.apple_pay && !config.google_pay);
+    }
+
+    #[test]
+    fn test_parse_webhook_secrets() {
+        assert_eq!(parse_webhook_secrets("whsec_new"), vec!["whsec_new".to_string()]);
+        assert_eq!(parse_webhook_secrets(" whsec_new , whsec_old,"), vec!["whsec_new".to_string(), "whsec_old".to_string()]);
+        assert!(parse_webhook_secrets("").is_empty());
+    }
+
+    #[test]
+    fn test_bundle_policy() {
+        assert_eq!(BundlePolicy::parse(None).dust_threshold, 0.01);
+        assert_eq!(BundlePolicy::parse(Some(" 0.5 ")).dust_threshold, 0.5);
+        assert_eq!(BundlePolicy::parse(Some("0")).dust_threshold, 0.0);
+        assert_eq!(BundlePolicy::parse(Some("-1")).dust_threshold, 0.01);
+        assert_eq!(BundlePolicy::parse(Some("lots")).dust_threshold, 0.01);
+    }
+
+    #[test]
+    fn test_sandbox_config() {
+        let live = SandboxConfig::parse(None, None, Some("https://test-executor".to_string()));
+        assert!(!live.enabled);
+        assert_eq!(live.database(), "index_wallets");
+        assert_eq!(live.executor_url(), None);
+
+        let sandbox = SandboxConfig::parse(Some("true"), None, Some("https://test-executor".to_string()));
+        assert!(sandbox.enabled);
+        assert_eq!(sandbox.database(), "index_wallets_sandbox");
+        assert_eq!(sandbox.executor_url(), Some("https://test-executor"));
+        assert!(SandboxConfig::parse(Some("1"), None, None).enabled);
+        assert!(!SandboxConfig::parse(Some("no"), None, None).enabled);
+    }
+
+    #[test]
+    fn test_sandbox_guards() {
+        let sandbox = SandboxConfig::parse(Some("true"), None, Some("https://test-executor".to_string()));
+        assert!(sandbox.check(Some("production"), &["sk_test_abc", "pk_test_abc"], Some("https://executor")).is_ok());
+        assert!(sandbox.check(None, &["sk_live_abc"], None).is_err());
+        assert!(sandbox.check(Some("production"), &["sk_test_abc"], Some("https://test-executor")).is_err());
+        assert!(SandboxConfig::parse(Some("true"), None, None).check(Some("production"), &["sk_test_abc"], None).is_err());
+        assert!(SandboxConfig::parse(Some("true"), Some("index_wallets".to_string()), None).check(None, &[], None).is_err());
+
+        let live = SandboxConfig::parse(None, None, None);
+        assert!(live.check(Some("production"), &["sk_live_abc"], None).is_ok());
+        assert!(live.check(Some("production"), &["sk_test_abc"], None).is_err());
+        assert!(live.check(None, &["sk_test_abc"], None).is_ok());
+    }
+
+    #[test]
+    fn test_cors_config_origins_by_environment() {
+        let dev = CorsConfig::parse(None, None, None, None);
+        assert!(dev.allows_origin("http://localhost:3000"));
+        assert!(!dev.allows_origin("https://evil.example"));
+
+        let production = CorsConfig::parse(Some("production"), None, None, None);
+        assert!(production.allowed_origins.is_empty());
+        assert!(!production.allows_origin("http://localhost:3000"));
+
+        let configured = CorsConfig::parse(Some("production"), Some("https://app.example.org/, https://partner.example"), None, None);
+        assert!(configured.allows_origin("https://APP.example.org"));
+        assert!(configured.allows_origin("https://partner.example"));
+        assert!(!configured.allows_origin("https://app.example.org.evil"));
+        assert!(CorsConfig::parse(Some("production"), Some("*"), None, None).allows_origin("https://anything.example"));
+    }
+
+    #[test]
+    fn test_cors_embed_paths() {
+        assert!(CorsConfig::is_embed_path("/v1/embed/donate"));
+        assert!(CorsConfig::is_embed_path("/embed/donate"));
+        assert!(!CorsConfig::is_embed_path("/v1/causes/donate"));
+        assert!(!CorsConfig::is_embed_path("/vendors/embed/donate"));
+    }
+
+    #[test]
+    fn test_cors_config_methods_and_headers() {
+        let config = CorsConfig::parse(None, None, Some("get, post"), Some("Content-Type,X-Wallet-Address"));
+        assert_eq!(config.allowed_methods, vec!["GET".to_string(), "POST".to_string()]);
+        assert_eq!(config.allowed_headers, vec!["content-type".to_string(), "x-wallet-address".to_string()]);
+        assert!(CorsConfig::parse(None, None, None, None).allowed_headers.contains(&"x-wallet-signature".to_string()));
+    }
+
+    #[test]
+    fn test_body_limits() {
+        assert_eq!(BodyLimits::parse(None, None), BodyLimits::default());
+        assert_eq!(BodyLimits::parse(Some(" 1024 "), Some("2048")), BodyLimits { json: 1024, payload: 2048 });
+        assert_eq!(BodyLimits::parse(Some("0"), Some("big")).json, 64 * 1024);
+        assert_eq!(BodyLimits::parse(Some("0"), Some("big")).payload, 256 * 1024);
+    }
+
+    #[test]
+    fn test_scheduler_config() {
+        let defaults = SchedulerConfig::parse(None, None, None);
+        assert!(defaults.schedules.is_empty());
+        assert!(defaults.leader_election);
+        assert_eq!(defaults.lease, Duration::from_secs(30));
+
+        let config = SchedulerConfig::parse(
+            Some(" reconciliation = 0 3 * * * ; voucher_expiry=*/5 * * * *;broken=* *;=0 0 * * *;"),
+            Some("false"),
+            Some("90"),
+        );
+        assert_eq!(config.schedules.len(), 2);
+        assert_eq!(config.schedules["reconciliation"].to_string(), "0 3 * * *");
+        assert_eq!(config.schedules["voucher_expiry"].to_string(), "*/5 * * * *");
+        assert!(!config.leader_election);
+        assert_eq!(config.lease, Duration::from_secs(90));
+        assert_eq!(SchedulerConfig::parse(None, Some("yes"), Some("0")), defaults);
+    }
+
+    #[test]
+    fn test_parse_master_key_invalid_format() {
+        let result = parse_master_key("not_hex_at_all_this_is_invalid_string_zzz");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid hex format"));
+    }
+
     // TODO: Implement load_keypair_from_hex function and uncomment these tests
     /*
     #[test]
@@ -0,0 +1,222 @@
+//! GraphQL view of users, balances, causes, tokens and activity, so the app can fetch a
+//! wallet screen in one round trip. Resolvers read through the same services as the REST
+//! handlers and apply the same visibility rules.
+
+use std::collections::HashMap;
+use actix_web::web;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Json, Object, Result, Schema, SimpleObject};
+use crate::auth::AuthenticatedUser;
+use crate::handlers::wallet_activities;
+use crate::models::{Cause, Token, User};
+use crate::models::payment::{ActivityItem, TransactionHistoryQuery};
+use crate::services::{CauseService, MongoDBService, TokenInfo, WalletService};
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Deepest nesting and most fields a single query may ask for
+const MAX_QUERY_DEPTH: usize = 8;
+const MAX_QUERY_COMPLEXITY: usize = 500;
+
+pub fn build_schema(
+    db: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+    cause_service: web::Data<CauseService>,
+) -> ApiSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .data(wallet_service)
+        .data(cause_service)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A user by wallet address
+    async fn user(&self, ctx: &Context<'_>, wallet_address: String) -> Result<Option<UserObject>> {
+        let db = ctx.data::<web::Data<MongoDBService>>()?;
+        let Some(user) = db.get_user_by_wallet(&wallet_address).await? else {
+            return Ok(None);
+        };
+        // Email is private to the user and admins
+        let show_email = ctx.data::<Option<AuthenticatedUser>>().ok()
+            .and_then(Option::as_ref)
+            .map_or(false, |auth| auth.require_self_or_admin(&wallet_address).is_ok());
+        Ok(Some(UserObject { user, show_email }))
+    }
+
+    /// Displayed causes, as listed by GET /causes
+    async fn causes(&self, ctx: &Context<'_>) -> Result<Vec<CauseObject>> {
+        let causes = ctx.data::<web::Data<CauseService>>()?.get_all_causes().await?;
+        Ok(causes.into_iter().map(CauseObject::from).collect())
+    }
+
+    async fn tokens(&self, ctx: &Context<'_>) -> Result<Vec<TokenObject>> {
+        let tokens = ctx.data::<web::Data<MongoDBService>>()?.get_all_tokens().await?;
+        Ok(tokens.into_iter().map(TokenObject::from).collect())
+    }
+
+    /// Token balances of a wallet, keyed by token, without looking up its user
+    async fn balances(&self, ctx: &Context<'_>, wallet_address: String) -> Result<Json<HashMap<String, TokenInfo>>> {
+        balances(ctx, &wallet_address).await
+    }
+
+    /// Payments and deposits of a wallet, newest first
+    async fn activity(&self, ctx: &Context<'_>, wallet_address: String, limit: Option<usize>) -> Result<Json<Vec<ActivityItem>>> {
+        activity(ctx, &wallet_address, limit).await
+    }
+}
+
+pub struct UserObject {
+    user: User,
+    show_email: bool,
+}
+
+#[Object(name = "User")]
+impl UserObject {
+    async fn wallet_address(&self) -> &str {
+        &self.user.wallet_address
+    }
+
+    async fn username(&self) -> &str {
+        &self.user.username
+    }
+
+    async fn display_name(&self) -> Option<&str> {
+        self.user.display_name.as_deref()
+    }
+
+    async fn avatar_url(&self) -> Option<&str> {
+        self.user.avatar_url.as_deref()
+    }
+
+    async fn user_type(&self) -> &str {
+        &self.user.user_type
+    }
+
+    async fn is_verified(&self) -> bool {
+        self.user.is_verified
+    }
+
+    /// Only returned to the user themselves and admins
+    async fn email(&self) -> Option<&str> {
+        if self.show_email { self.user.email.as_deref() } else { None }
+    }
+
+    async fn balances(&self, ctx: &Context<'_>) -> Result<Json<HashMap<String, TokenInfo>>> {
+        balances(ctx, &self.user.wallet_address).await
+    }
+
+    /// Every token with this user's valuation of it, as GET /wallet/{address}/valuations
+    async fn valuations(&self, ctx: &Context<'_>) -> Result<Vec<Valuation>> {
+        let tokens = ctx.data::<web::Data<MongoDBService>>()?.get_all_tokens().await?;
+        let preferences = &self.user.preferences.0;
+        Ok(tokens.into_iter().map(|token| {
+            let token_symbol = token.token_symbol.unwrap_or_default();
+            Valuation {
+                current_valuation: preferences.get_f64(&token_symbol).unwrap_or(0.0),
+                has_set: preferences.contains_key(&token_symbol),
+                token_name: token.token_name,
+                token_image_url: token.token_image_url,
+                token_symbol,
+            }
+        }).collect())
+    }
+
+    async fn activity(&self, ctx: &Context<'_>, limit: Option<usize>) -> Result<Json<Vec<ActivityItem>>> {
+        activity(ctx, &self.user.wallet_address, limit).await
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Valuation {
+    token_name: String,
+    token_symbol: String,
+    token_image_url: Option<String>,
+    current_valuation: f64,
+    has_set: bool,
+}
+
+/// The public fields of a cause
+#[derive(SimpleObject)]
+#[graphql(name = "Cause")]
+pub struct CauseObject {
+    id: Option<String>,
+    name: String,
+    organization: String,
+    description: String,
+    long_description: String,
+    token_name: String,
+    token_symbol: String,
+    token_image_url: Option<String>,
+    cause_image_url: Option<String>,
+    amount_donated: f64,
+    tokens_purchased: f64,
+    current_price: f64,
+    featured: bool,
+}
+
+impl From<Cause> for CauseObject {
+    fn from(cause: Cause) -> Self {
+        Self {
+            id: cause.id.map(|id| id.to_hex()),
+            name: cause.name,
+            organization: cause.organization,
+            description: cause.description,
+            long_description: cause.long_description,
+            token_name: cause.token_name,
+            token_symbol: cause.token_symbol,
+            token_image_url: cause.token_image_url,
+            cause_image_url: cause.cause_image_url,
+            amount_donated: cause.amount_donated,
+            tokens_purchased: cause.tokens_purchased,
+            current_price: cause.current_price,
+            featured: cause.featured,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Token")]
+pub struct TokenObject {
+    token_id: String,
+    token_name: String,
+    token_symbol: Option<String>,
+    token_image_url: Option<String>,
+    market_valuation: f64,
+}
+
+impl From<Token> for TokenObject {
+    fn from(token: Token) -> Self {
+        Self {
+            token_id: token.token_id,
+            token_name: token.token_name,
+            token_symbol: token.token_symbol,
+            token_image_url: token.token_image_url,
+            market_valuation: token.market_valuation,
+        }
+    }
+}
+
+/// A wallet without a vault yet has no balances, as in the REST handler
+async fn balances(ctx: &Context<'_>, wallet_address: &str) -> Result<Json<HashMap<String, TokenInfo>>> {
+    let wallet_service = ctx.data::<web::Data<WalletService>>()?;
+    let pubkey = WalletService::parse_public_key(wallet_address)?;
+    match wallet_service.get_vault(&pubkey).await? {
+        Some(vault) => Ok(Json(wallet_service.map_vault_tokens(&vault).await?)),
+        None => Ok(Json(HashMap::new())),
+    }
+}
+
+async fn activity(ctx: &Context<'_>, wallet_address: &str, limit: Option<usize>) -> Result<Json<Vec<ActivityItem>>> {
+    let db = ctx.data::<web::Data<MongoDBService>>()?;
+    let mut activities = wallet_activities(db, wallet_address, &TransactionHistoryQuery::default()).await?;
+    activities.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(Json(activities.into_iter()
+        .map(|(_, item)| item)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()))
+}
@@ -0,0 +1,287 @@
+//! The payment operations of the REST API over gRPC, for POS partners that prefer protobuf
+//! contracts (proto/payments.proto). Each call goes through the same code as its REST route.
+
+use std::net::SocketAddr;
+use actix_web::web;
+use tonic::{Request, Response, Status};
+use crate::config::BundlePolicy;
+use crate::handlers::{create_payment_code, payment_status, submit_signed_transaction, supplement_payment};
+use crate::models::{
+    ApiError, CreatePaymentRequest, DiscountConsumption, OnChainAmount, PaymentStatus, SupplementPaymentRequest,
+    TokenBalance, TokenPayment, TokenValuation,
+};
+use crate::models::payment::{PaymentStatusResponse, ProcessSignedTransactionRequest};
+use crate::services::{EscrowService, MongoDBService, PushService, SharedState, WalletService};
+
+pub mod proto {
+    tonic::include_proto!("index_wallets.v1");
+}
+
+use proto::payments_server::{Payments, PaymentsServer};
+
+pub struct PaymentsService {
+    db: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+    push_service: web::Data<PushService>,
+    escrow_service: web::Data<EscrowService>,
+    shared_state: web::Data<SharedState>,
+    bundle_policy: web::Data<BundlePolicy>,
+}
+
+impl PaymentsService {
+    pub fn new(
+        db: web::Data<MongoDBService>,
+        wallet_service: web::Data<WalletService>,
+        push_service: web::Data<PushService>,
+        escrow_service: web::Data<EscrowService>,
+        shared_state: web::Data<SharedState>,
+        bundle_policy: web::Data<BundlePolicy>,
+    ) -> Self {
+        Self { db, wallet_service, push_service, escrow_service, shared_state, bundle_policy }
+    }
+
+    /// Serve on `addr` until the process exits
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        log::info!("Starting gRPC server at {}", addr);
+        tonic::transport::Server::builder()
+            .add_service(PaymentsServer::new(self))
+            .serve(addr)
+            .await
+    }
+}
+
+#[tonic::async_trait]
+impl Payments for PaymentsService {
+    async fn create_payment(&self, request: Request<proto::CreatePaymentRequest>) -> Result<Response<proto::CreatePaymentResponse>, Status> {
+        let request = request.into_inner();
+        let payment_request = CreatePaymentRequest {
+            vendor_address: request.vendor_address,
+            vendor_name: request.vendor_name,
+            price_usd: request.price_usd,
+            vendor_valuations: non_empty(request.vendor_valuations.into_iter().map(TokenValuation::from).collect()),
+            is_verified: request.is_verified,
+            escrow: request.escrow,
+            escrow_hold_hours: request.escrow_hold_hours,
+            splits: Vec::new(),
+            manual_capture: false,
+            capture_window_minutes: None,
+        };
+        // Calls aren't signed, so overriding the vendor's valuations is left to the REST API
+        let created = create_payment_code(&payment_request, None, &self.db, &self.escrow_service).await?;
+        Ok(Response::new(proto::CreatePaymentResponse {
+            payment_id: created.payment_id,
+            vendor_name: created.vendor_name,
+            price_usd: created.price_usd,
+            payment_code: created.payment_code,
+        }))
+    }
+
+    async fn supplement_payment(&self, request: Request<proto::SupplementPaymentRequest>) -> Result<Response<proto::SupplementPaymentResponse>, Status> {
+        let client = format!("ip:{}", request.remote_addr().map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string()));
+        let request = request.into_inner();
+        let supplement = SupplementPaymentRequest {
+            payer_address: request.payer_address,
+            payer_username: request.payer_username,
+            payer_balances: request.payer_balances.into_iter().map(TokenBalance::from).collect(),
+            promo_code: request.promo_code,
+        };
+        let response = supplement_payment(
+            &request.payment_id,
+            &supplement,
+            &client,
+            &self.db,
+            &self.wallet_service,
+            &self.bundle_policy,
+            &self.shared_state,
+        ).await?;
+        Ok(Response::new(proto::SupplementPaymentResponse {
+            payment_id: response.payment_id,
+            vendor_address: response.vendor_address,
+            vendor_name: response.vendor_name,
+            status: payment_state(&response.status) as i32,
+            price_usd: response.price_usd,
+            created_at: response.created_at,
+            payment_bundle: response.payment_bundle.into_iter().map(Into::into).collect(),
+            on_chain_amounts: response.on_chain_amounts.into_iter().map(Into::into).collect(),
+            unsigned_transaction: response.unsigned_transaction,
+            vendor_valuations: response.vendor_valuations.unwrap_or_default().into_iter().map(Into::into).collect(),
+            discount_consumption: response.discount_consumption.unwrap_or_default().into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn submit_signed_transaction(&self, request: Request<proto::SubmitSignedTransactionRequest>) -> Result<Response<proto::SubmitSignedTransactionResponse>, Status> {
+        let request = request.into_inner();
+        let signed = ProcessSignedTransactionRequest {
+            payment_id: request.payment_id,
+            signed_transaction: request.signed_transaction,
+            vendor_address: request.vendor_address,
+            vendor_name: request.vendor_name,
+            payer_address: request.payer_address,
+            price_usd: request.price_usd,
+            payment_bundle: request.payment_bundle.into_iter().map(TokenPayment::from).collect(),
+            computed_payment: None,
+            vendor_valuations: non_empty(request.vendor_valuations.into_iter().map(TokenValuation::from).collect()),
+            discount_consumption: non_empty(request.discount_consumption.into_iter().map(DiscountConsumption::from).collect()),
+        };
+        let submitted = submit_signed_transaction(
+            &signed.payment_id,
+            &signed,
+            &self.db,
+            &self.wallet_service,
+            &self.push_service,
+            &self.escrow_service,
+            &self.shared_state,
+        ).await?;
+        Ok(Response::new(proto::SubmitSignedTransactionResponse {
+            payment: Some(submitted.payment.into()),
+            status_update_error: submitted.status_update_error,
+        }))
+    }
+
+    async fn get_payment_status(&self, request: Request<proto::GetPaymentStatusRequest>) -> Result<Response<proto::PaymentStatus>, Status> {
+        let status = payment_status(&request.into_inner().payment_id, &self.db).await?;
+        Ok(Response::new(status.into()))
+    }
+
+    /// A wallet without a vault yet has no balances, as over REST
+    async fn get_balances(&self, request: Request<proto::GetBalancesRequest>) -> Result<Response<proto::GetBalancesResponse>, Status> {
+        let pubkey = WalletService::parse_public_key(&request.into_inner().wallet_address)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let vault = self.wallet_service.get_vault(&pubkey).await.map_err(ApiError::from)?;
+        let balances = match vault {
+            Some(vault) => self.wallet_service.map_vault_tokens(&vault).await
+                .map_err(|e| Status::internal(format!("Error mapping vault tokens: {}", e)))?
+                .into_iter()
+                .map(|(token_key, info)| proto::Balance {
+                    token_key,
+                    symbol: info.metadata.symbol,
+                    name: info.metadata.name,
+                    balance: info.balance,
+                    market_valuation: info.metadata.market_valuation,
+                    token_image_url: info.metadata.token_image_url,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(Response::new(proto::GetBalancesResponse { balances }))
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        match &error {
+            ApiError::ValidationError(_) | ApiError::InvalidFields(_) => Status::invalid_argument(error.to_string()),
+            ApiError::NotFound(_) => Status::not_found(error.to_string()),
+            ApiError::Unauthorized(_) => Status::unauthenticated(error.to_string()),
+            ApiError::Forbidden(_) => Status::permission_denied(error.to_string()),
+            ApiError::DuplicateUser(_) | ApiError::DuplicateError(_) => Status::already_exists(error.to_string()),
+            ApiError::Conflict(_) => Status::aborted(error.to_string()),
+            ApiError::InsufficientBalance(_) | ApiError::DiscountBudgetExhausted(_) => Status::failed_precondition(error.to_string()),
+            ApiError::ServiceUnavailable(_) => Status::unavailable(error.to_string()),
+            ApiError::TooManyRequests(_) | ApiError::PayloadTooLarge(_) => Status::resource_exhausted(error.to_string()),
+            ApiError::DatabaseError(_) => Status::internal("Internal server error"),
+            ApiError::StripeError(_) | ApiError::InternalError(_) => Status::internal(error.to_string()),
+        }
+    }
+}
+
+/// Repeated fields can't be absent, so an empty list stands for none
+fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    if items.is_empty() { None } else { Some(items) }
+}
+
+fn payment_state(status: &PaymentStatus) -> proto::PaymentState {
+    match status {
+        PaymentStatus::Created => proto::PaymentState::Created,
+        PaymentStatus::CustomerAssigned => proto::PaymentState::CustomerAssigned,
+        PaymentStatus::Calculated => proto::PaymentState::Calculated,
+        PaymentStatus::Submitted => proto::PaymentState::Submitted,
+        PaymentStatus::Completed => proto::PaymentState::Completed,
+        PaymentStatus::Failed => proto::PaymentState::Failed,
+        PaymentStatus::Authorized => proto::PaymentState::Authorized,
+        PaymentStatus::Voided => proto::PaymentState::Voided,
+    }
+}
+
+impl From<PaymentStatusResponse> for proto::PaymentStatus {
+    fn from(status: PaymentStatusResponse) -> Self {
+        Self {
+            payment_id: status.payment_id,
+            vendor_address: status.vendor_address,
+            vendor_name: status.vendor_name,
+            customer_address: status.customer_address,
+            status: payment_state(&status.status) as i32,
+            price_usd: status.price_usd,
+            created_at: status.created_at,
+            payment_bundle: status.payment_bundle.unwrap_or_default().into_iter().map(Into::into).collect(),
+            vendor_valuations: status.vendor_valuations.unwrap_or_default().into_iter().map(Into::into).collect(),
+            discount_consumption: status.discount_consumption.unwrap_or_default().into_iter().map(Into::into).collect(),
+            executor_tx_id: status.executor_tx_id,
+        }
+    }
+}
+
+impl From<proto::TokenValuation> for TokenValuation {
+    fn from(valuation: proto::TokenValuation) -> Self {
+        Self { token_key: valuation.token_key, symbol: valuation.symbol, valuation: valuation.valuation }
+    }
+}
+
+impl From<TokenValuation> for proto::TokenValuation {
+    fn from(valuation: TokenValuation) -> Self {
+        Self { token_key: valuation.token_key, symbol: valuation.symbol, valuation: valuation.valuation }
+    }
+}
+
+impl From<proto::TokenPayment> for TokenPayment {
+    fn from(payment: proto::TokenPayment) -> Self {
+        Self {
+            token_key: payment.token_key,
+            symbol: payment.symbol,
+            amount_to_pay: payment.amount_to_pay,
+            token_image_url: payment.token_image_url,
+        }
+    }
+}
+
+impl From<TokenPayment> for proto::TokenPayment {
+    fn from(payment: TokenPayment) -> Self {
+        Self {
+            token_key: payment.token_key,
+            symbol: payment.symbol,
+            amount_to_pay: payment.amount_to_pay,
+            token_image_url: payment.token_image_url,
+        }
+    }
+}
+
+impl From<proto::TokenBalance> for TokenBalance {
+    fn from(balance: proto::TokenBalance) -> Self {
+        Self {
+            token_key: balance.token_key,
+            symbol: balance.symbol,
+            name: balance.name,
+            balance: balance.balance,
+            average_valuation: balance.average_valuation,
+            token_image_url: balance.token_image_url,
+        }
+    }
+}
+
+impl From<proto::DiscountConsumption> for DiscountConsumption {
+    fn from(consumption: proto::DiscountConsumption) -> Self {
+        Self { token_key: consumption.token_key, symbol: consumption.symbol, amount_used: consumption.amount_used }
+    }
+}
+
+impl From<DiscountConsumption> for proto::DiscountConsumption {
+    fn from(consumption: DiscountConsumption) -> Self {
+        Self { token_key: consumption.token_key, symbol: consumption.symbol, amount_used: consumption.amount_used }
+    }
+}
+
+impl From<OnChainAmount> for proto::OnChainAmount {
+    fn from(amount: OnChainAmount) -> Self {
+        Self { token_key: amount.token_key, symbol: amount.symbol, amount: amount.amount }
+    }
+}
@@ -0,0 +1,126 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use crate::auth::{AuthenticatedUser, MAX_CLOCK_SKEW_SECS};
+use crate::handlers::message_handler::wallet_activities;
+use crate::services::{MongoDBService, WalletService};
+use crate::models::{ApiError, Account, LinkWalletRequest, AccountActivityItem};
+use crate::models::payment::TransactionHistoryQuery;
+use crate::utils::wallet_signature::{link_message, verify_wallet_signature};
+
+/// Create an account with the signed-in wallet as its primary wallet
+pub async fn create_account(
+    auth: AuthenticatedUser,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let account = db.create_account(&auth.wallet_address).await?;
+    log::info!("Created account {:?} for {}", account.id, auth.wallet_address);
+    Ok(HttpResponse::Created().json(account))
+}
+
+/// The account the signed-in wallet is linked to
+pub async fn get_my_account(
+    auth: AuthenticatedUser,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let account = db.get_account_by_wallet(&auth.wallet_address).await?
+        .ok_or_else(|| ApiError::NotFound("Wallet is not linked to an account".to_string()))?;
+    Ok(HttpResponse::Ok().json(account))
+}
+
+pub async fn get_account(
+    auth: AuthenticatedUser,
+    account_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let account = load_member_account(&auth, &db, &account_id).await?;
+    Ok(HttpResponse::Ok().json(account))
+}
+
+/// Link another wallet. The request is signed by a wallet already in the account, and
+/// the body carries the new wallet's signature over the link message, so both agree.
+pub async fn link_wallet(
+    auth: AuthenticatedUser,
+    account_id: web::Path<String>,
+    payload: web::Json<LinkWalletRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let account = load_member_account(&auth, &db, &account_id).await?;
+    let id = account.id.ok_or_else(|| ApiError::InternalError("Account has no ID".to_string()))?;
+
+    if (Utc::now().timestamp() - payload.timestamp).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(ApiError::ValidationError("Link signature expired".to_string()));
+    }
+    let message = link_message(&id.to_hex(), &payload.wallet_address, payload.timestamp);
+    verify_wallet_signature(&payload.wallet_address, message.as_bytes(), &payload.signature)
+        .map_err(ApiError::ValidationError)?;
+
+    let account = db.link_wallet(&id, &payload.wallet_address).await?;
+    log::info!("Linked {} to account {} (requested by {})", payload.wallet_address, id, auth.wallet_address);
+    Ok(HttpResponse::Ok().json(account))
+}
+
+/// Unlink a wallet. Any wallet in the account may do this; the primary wallet can't be unlinked.
+pub async fn unlink_wallet(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (account_id, wallet_address) = path.into_inner();
+    let account = load_member_account(&auth, &db, &account_id).await?;
+    let id = account.id.ok_or_else(|| ApiError::InternalError("Account has no ID".to_string()))?;
+
+    if account.primary_wallet == wallet_address {
+        return Err(ApiError::ValidationError("The primary wallet can't be unlinked".to_string()));
+    }
+    let account = db.unlink_wallet(&id, &wallet_address).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet {} is not linked to this account", wallet_address)))?;
+
+    log::info!("Unlinked {} from account {} (requested by {})", wallet_address, id, auth.wallet_address);
+    Ok(HttpResponse::Ok().json(account))
+}
+
+/// Balances of every linked wallet, and their sum per token
+pub async fn get_account_balances(
+    auth: AuthenticatedUser,
+    account_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+) -> Result<HttpResponse, ApiError> {
+    let account = load_member_account(&auth, &db, &account_id).await?;
+    let balances = wallet_service.account_balances(&account.wallet_addresses()).await?;
+    Ok(HttpResponse::Ok().json(balances))
+}
+
+/// Activity of every linked wallet, newest first, each entry tagged with its wallet.
+/// Transfers between two linked wallets show up once for each side.
+pub async fn get_account_transactions(
+    auth: AuthenticatedUser,
+    account_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let account = load_member_account(&auth, &db, &account_id).await?;
+
+    let mut activities = Vec::new();
+    for wallet_address in account.wallet_addresses() {
+        for (created_at, activity) in wallet_activities(&db, &wallet_address, &TransactionHistoryQuery::default()).await? {
+            activities.push((created_at, AccountActivityItem { wallet_address: wallet_address.clone(), activity }));
+        }
+    }
+    activities.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let activities: Vec<AccountActivityItem> = activities.into_iter().map(|(_, item)| item).collect();
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "activities": activities })))
+}
+
+/// Load an account the signed-in wallet belongs to; admins can load any
+async fn load_member_account(auth: &AuthenticatedUser, db: &MongoDBService, account_id: &str) -> Result<Account, ApiError> {
+    let object_id = ObjectId::parse_str(account_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid account ID: {}", e)))?;
+    let account = db.get_account(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Account {} not found", account_id)))?;
+    if !auth.is_admin() && !account.has_wallet(&auth.wallet_address) {
+        return Err(ApiError::Forbidden("Not a wallet of this account".to_string()));
+    }
+    Ok(account)
+}
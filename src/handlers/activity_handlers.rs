@@ -0,0 +1,18 @@
+use actix_web::{web, HttpResponse};
+use crate::auth::AuthenticatedUser;
+use crate::models::{ApiError, ActivityQuery};
+use crate::services::MongoDBService;
+
+/// A wallet's payments, deposits, transfers, refunds, redemptions and rewards, newest
+/// first, optionally narrowed to some of those types
+pub async fn get_wallet_activity(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    query: web::Query<ActivityQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let page = db.get_wallet_activity(&wallet_address, &query).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
@@ -0,0 +1,765 @@
+use actix_web::{web, HttpResponse};
+use log::{info, error};
+use serde_json::json;
+use crate::auth::AuthenticatedUser;
+use crate::handlers::job_handlers::job_accepted;
+use crate::handlers::purchase_webhook_handlers::credit_checkout_session;
+use crate::handlers::stripe_event_router::{StripeEventRouter, WebhookContext};
+use crate::services::{scheduler_status, CauseService, FeatureFlagService, FundingRoundService, JobService, MongoDBService, ReconciliationService, StripeApi, WebhookService, WebhookQueueService};
+use crate::utils::audit::snapshot;
+use crate::utils::report_period::{parse_report_date, day_bounds};
+use crate::models::cause::{ReviewCauseRequest, CauseDashboardQuery, CauseRequirementsQuery, BulkCauseRequest, FeatureCauseRequest, ReorderFeaturedRequest, DEFAULT_STUCK_DRAFT_HOURS, MAX_BULK_CAUSES};
+use crate::models::{ApiError, AuditLog, AuditAction, AuditLogQuery, Role, UpdateRolesRequest, ReconciliationIssueQuery, RunReconciliationRequest, ManualCreditRequest, ResolveCreditRequest, ProcessedStripeEvent, WebhookFailureQuery, WebhookFailureStatus, WebhookQueueQuery, StripeChargeStatus, StripeReconciliationQuery, StripeReconciliationReport, MatchingPool, MatchingPoolStatus, CreateMatchingPoolRequest, CreateFundingRoundRequest, FeatureFlagQuery, SetFeatureFlagRequest, JobKind};
+use mongodb::bson::oid::ObjectId;
+use std::str::FromStr;
+use stripe::{CheckoutSessionId, CheckoutSessionPaymentStatus};
+
+/// Longest date range a single Stripe reconciliation request may cover
+const MAX_STRIPE_RECONCILIATION_DAYS: i64 = 31;
+
+/// Failed credits listed for resolution at once
+const FAILED_CREDITS_LIMIT: i64 = 100;
+
+/// Get audit logs, newest first, with optional action/actor/resource filters
+pub async fn get_audit_logs(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    info!("Fetching audit logs: {:?}", query);
+    
+    match mongodb.get_audit_logs(&query).await {
+        Ok(page) => {
+            info!("Found {} audit logs (total {})", page.logs.len(), page.total);
+            Ok(HttpResponse::Ok().json(page))
+        },
+        Err(e) => {
+            error!("Error fetching audit logs: {}", e);
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to fetch audit logs",
+                "details": e.to_string()
+            })))
+        }
+    }
+}
+
+/// Replace the roles stored on a user record
+pub async fn update_user_roles(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    wallet_address: web::Path<String>,
+    payload: web::Json<UpdateRolesRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    info!("Admin {} setting roles for {}: {:?}", auth.wallet_address, wallet_address, payload.roles);
+    
+    let user = mongodb.get_user_by_wallet(&wallet_address).await?
+        .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)))?;
+    
+    mongodb.set_user_roles(&wallet_address, &payload.roles).await?;
+    
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::ConfigChanged,
+        "user_roles",
+        &wallet_address,
+        snapshot(&json!({ "roles": user.roles })),
+        snapshot(&json!({ "roles": payload.roles })),
+    );
+    if let Err(e) = mongodb.record_audit_log(entry).await {
+        error!("Failed to record audit log: {:?}", e);
+    }
+    
+    Ok(HttpResponse::Ok().json(json!({
+        "wallet_address": wallet_address.as_str(),
+        "roles": payload.roles
+    })))
+}
+
+/// Trigger a reconciliation run now instead of waiting for the scheduler. It runs as a job
+/// whose result is the run summary.
+pub async fn run_reconciliation(
+    auth: AuthenticatedUser,
+    reconciliation_service: web::Data<ReconciliationService>,
+    jobs: web::Data<JobService>,
+    payload: Option<web::Json<RunReconciliationRequest>>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let sample_size = payload.and_then(|p| p.sample_size);
+    info!("Admin {} triggered reconciliation (sample size: {:?})", auth.wallet_address, sample_size);
+    
+    let service = reconciliation_service.into_inner();
+    let job = jobs.start(JobKind::Reconciliation, &auth.wallet_address, move |progress| async move {
+        let run = service.run(sample_size, Some(&progress)).await?;
+        serde_json::to_value(run).map_err(|e| ApiError::InternalError(e.to_string()))
+    }).await?;
+    Ok(job_accepted(&job))
+}
+
+/// List balance discrepancies between MongoDB and executor vaults
+pub async fn get_reconciliation_issues(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    query: web::Query<ReconciliationIssueQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    
+    let issues = mongodb.get_reconciliation_issues(&query).await?;
+    info!("Found {} reconciliation issues", issues.len());
+    Ok(HttpResponse::Ok().json(issues))
+}
+
+/// Mark a reconciliation issue as investigated
+pub async fn resolve_reconciliation_issue(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    issue_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    
+    let object_id = ObjectId::parse_str(issue_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid issue ID: {}", e)))?;
+    if !mongodb.resolve_reconciliation_issue(&object_id).await? {
+        return Err(ApiError::NotFound(format!("Reconciliation issue {} not found", issue_id)));
+    }
+    
+    info!("Admin {} resolved reconciliation issue {}", auth.wallet_address, issue_id);
+    Ok(HttpResponse::Ok().json(json!({ "id": issue_id.as_str(), "resolved": true })))
+}
+
+/// Compare paid Stripe checkout sessions in a date range with our deposit records
+pub async fn get_stripe_reconciliation(
+    auth: AuthenticatedUser,
+    reconciliation_service: web::Data<ReconciliationService>,
+    stripe: web::Data<dyn StripeApi>,
+    query: web::Query<StripeReconciliationQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let today = chrono::Utc::now().date_naive();
+    let from = parse_report_date(query.from.as_deref(), today).map_err(ApiError::ValidationError)?;
+    let to = match query.to.as_deref() {
+        Some(_) => parse_report_date(query.to.as_deref(), today).map_err(ApiError::ValidationError)?,
+        None => from,
+    };
+    if to < from {
+        return Err(ApiError::ValidationError("'to' must not be before 'from'".to_string()));
+    }
+    if (to - from).num_days() >= MAX_STRIPE_RECONCILIATION_DAYS {
+        return Err(ApiError::ValidationError(format!(
+            "Date range may cover at most {} days", MAX_STRIPE_RECONCILIATION_DAYS
+        )));
+    }
+
+    let (start, _) = day_bounds(from);
+    let (_, end) = day_bounds(to);
+    info!("Admin {} running Stripe reconciliation for {} to {}", auth.wallet_address, from, to);
+
+    let charges = reconciliation_service.check_stripe_charges(stripe.get_ref(), start, end).await?;
+    let count = |status: StripeChargeStatus| charges.iter().filter(|c| c.status == status).count();
+    let report = StripeReconciliationReport {
+        from: from.to_string(),
+        to: to.to_string(),
+        sessions_checked: charges.len(),
+        credited: count(StripeChargeStatus::Credited),
+        missing_credit: count(StripeChargeStatus::MissingCredit),
+        no_wallet: count(StripeChargeStatus::NoWallet),
+        charges,
+    };
+    info!("Stripe reconciliation: {} sessions, {} missing credit", report.sessions_checked, report.missing_credit);
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Credit a paid checkout session whose webhook never landed
+pub async fn replay_stripe_session(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    stripe: web::Data<dyn StripeApi>,
+    session_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let id = CheckoutSessionId::from_str(session_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid checkout session ID: {}", e)))?;
+    let sess = stripe.retrieve_checkout_session(&id)
+        .await
+        .map_err(|e| ApiError::NotFound(format!("Checkout session {} not found: {}", session_id, e)))?;
+    if sess.payment_status != CheckoutSessionPaymentStatus::Paid {
+        return Err(ApiError::ValidationError(format!("Checkout session {} is not paid", session_id)));
+    }
+
+    let deposit = credit_checkout_session(&sess, &webhook_service, &mongodb)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to credit session: {}", e)))?
+        .ok_or_else(|| ApiError::ValidationError(format!(
+            "Checkout session {} is already credited or has no wallet address", session_id
+        )))?;
+
+    info!("Admin {} replayed checkout session {} for {}", auth.wallet_address, session_id, deposit.wallet_address);
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::ManualCredit,
+        "stripe_checkout_session",
+        session_id.as_str(),
+        None,
+        snapshot(&deposit),
+    );
+    if let Err(e) = mongodb.record_audit_log(entry).await {
+        error!("Failed to record audit log: {:?}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(deposit))
+}
+
+/// Credit a wallet by hand to fix a missed or failed webhook
+pub async fn create_manual_credit(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    payload: web::Json<ManualCreditRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    if payload.idempotency_key.trim().is_empty() {
+        return Err(ApiError::ValidationError("idempotency_key is required".to_string()));
+    }
+    if payload.reason.trim().is_empty() {
+        return Err(ApiError::ValidationError("reason is required".to_string()));
+    }
+    if payload.amount <= 0 {
+        return Err(ApiError::ValidationError("amount must be positive".to_string()));
+    }
+
+    let (deposit, credited) = webhook_service
+        .manual_credit(&payload, &auth.wallet_address)
+        .await?;
+
+    if !credited {
+        // Same key reused for a different credit is a client bug, not a retry
+        if deposit.wallet_address != payload.wallet_address
+            || deposit.token_symbol != payload.token_symbol
+            || deposit.amount_tokens_received != payload.amount as f64
+        {
+            return Err(ApiError::DuplicateError(format!(
+                "Idempotency key {} was already used for a different credit", payload.idempotency_key
+            )));
+        }
+        info!("Manual credit {} already applied, returning existing deposit", payload.idempotency_key);
+        return Ok(HttpResponse::Ok().json(deposit));
+    }
+
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::ManualCredit,
+        "wallet",
+        &deposit.wallet_address,
+        None,
+        snapshot(&deposit),
+    );
+    if let Err(e) = mongodb.record_audit_log(entry).await {
+        error!("Failed to record audit log: {:?}", e);
+    }
+
+    Ok(HttpResponse::Created().json(deposit))
+}
+
+/// Credits whose transfer failed and may or may not have landed, waiting to be resolved
+pub async fn get_failed_credits(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let failed = mongodb.get_failed_credit_reservations(FAILED_CREDITS_LIMIT).await?;
+    Ok(HttpResponse::Ok().json(failed))
+}
+
+/// Settle a failed credit after checking the executor: `credited` if the tokens reached the
+/// wallet, otherwise its key is released and the credit can be retried
+pub async fn resolve_failed_credit(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    credit_id: web::Path<String>,
+    payload: web::Json<ResolveCreditRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(credit_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid credit ID: {}", e)))?;
+    let (reservation, deposit) = webhook_service.resolve_credit(&object_id, &payload).await?;
+
+    info!("Admin {} resolved failed credit {} as {}", auth.wallet_address, reservation.key, if payload.credited { "credited" } else { "released" });
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::CreditResolved,
+        "wallet",
+        &reservation.wallet_address,
+        snapshot(&reservation),
+        deposit.as_ref().and_then(snapshot),
+    );
+    if let Err(e) = mongodb.record_audit_log(entry).await {
+        error!("Failed to record audit log: {:?}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({ "id": credit_id.as_str(), "credited": payload.credited, "deposit": deposit })))
+}
+
+/// Create a matching pool for donations to one or more causes
+pub async fn create_matching_pool(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    payload: web::Json<CreateMatchingPoolRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    if payload.name.trim().is_empty() || payload.sponsor.trim().is_empty() {
+        return Err(ApiError::ValidationError("name and sponsor are required".to_string()));
+    }
+    if !payload.match_ratio.is_finite() || payload.match_ratio <= 0.0 {
+        return Err(ApiError::ValidationError("match_ratio must be positive".to_string()));
+    }
+    if payload.cap_cents <= 0 || payload.per_donation_cap_cents.map_or(false, |cap| cap <= 0) {
+        return Err(ApiError::ValidationError("caps must be positive".to_string()));
+    }
+    let now = chrono::Utc::now().timestamp();
+    let starts_at = payload.starts_at.unwrap_or(now);
+    if payload.ends_at.map_or(false, |end| end <= starts_at) {
+        return Err(ApiError::ValidationError("ends_at must be after starts_at".to_string()));
+    }
+    if payload.cause_symbols.is_empty() {
+        return Err(ApiError::ValidationError("at least one cause is required".to_string()));
+    }
+
+    let mut cause_symbols = Vec::new();
+    for symbol in &payload.cause_symbols {
+        let symbol = symbol.trim().to_uppercase();
+        let cause = mongodb.get_cause_by_token_symbol(&symbol).await
+            .map_err(ApiError::DatabaseError)?
+            .filter(|cause| cause.token_symbol.eq_ignore_ascii_case(&symbol));
+        if cause.is_none() {
+            return Err(ApiError::ValidationError(format!("No cause with token symbol {}", symbol)));
+        }
+        if !cause_symbols.contains(&symbol) {
+            cause_symbols.push(symbol);
+        }
+    }
+
+    let mut pool = MatchingPool {
+        id: None,
+        name: payload.name.trim().to_string(),
+        sponsor: payload.sponsor.trim().to_string(),
+        cause_symbols,
+        match_ratio: payload.match_ratio,
+        cap_cents: payload.cap_cents,
+        per_donation_cap_cents: payload.per_donation_cap_cents,
+        matched_cents: 0,
+        status: MatchingPoolStatus::Active,
+        starts_at,
+        ends_at: payload.ends_at,
+        created_by: auth.wallet_address.clone(),
+        created_at: chrono::Utc::now(),
+    };
+    let pool_id = mongodb.create_matching_pool(&pool).await?;
+    pool.id = Some(pool_id);
+
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::MatchingPoolChanged,
+        "matching_pool",
+        &pool_id.to_hex(),
+        None,
+        snapshot(&pool),
+    );
+    if let Err(e) = mongodb.record_audit_log(entry).await {
+        error!("Failed to record audit log: {:?}", e);
+    }
+
+    info!("Admin {} created matching pool {} for {:?}", auth.wallet_address, pool_id, pool.cause_symbols);
+    Ok(HttpResponse::Created().json(pool))
+}
+
+/// Stop a matching pool; donations after this are no longer matched
+pub async fn close_matching_pool(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    pool_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(pool_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid pool ID: {}", e)))?;
+    let before = mongodb.get_matching_pool(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Matching pool {} not found", pool_id)))?;
+    mongodb.close_matching_pool(&object_id).await?;
+    let after = mongodb.get_matching_pool(&object_id).await?;
+
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::MatchingPoolChanged,
+        "matching_pool",
+        pool_id.as_str(),
+        snapshot(&before),
+        after.as_ref().and_then(snapshot),
+    );
+    if let Err(e) = mongodb.record_audit_log(entry).await {
+        error!("Failed to record audit log: {:?}", e);
+    }
+
+    info!("Admin {} closed matching pool {}", auth.wallet_address, pool_id);
+    Ok(HttpResponse::Ok().json(after))
+}
+
+/// Create a quadratic funding round over a set of causes
+pub async fn create_funding_round(
+    auth: AuthenticatedUser,
+    funding_round_service: web::Data<FundingRoundService>,
+    payload: web::Json<CreateFundingRoundRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let round = funding_round_service.create_round(&payload, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(round))
+}
+
+/// Close a round after its window: compute QF allocations and the payout report
+pub async fn close_funding_round(
+    auth: AuthenticatedUser,
+    funding_round_service: web::Data<FundingRoundService>,
+    round_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let object_id = ObjectId::parse_str(round_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid round ID: {}", e)))?;
+    let report = funding_round_service.close_round(&object_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Credit a closed round's payouts; retry to pick up failed ones
+pub async fn distribute_funding_round(
+    auth: AuthenticatedUser,
+    funding_round_service: web::Data<FundingRoundService>,
+    round_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let object_id = ObjectId::parse_str(round_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid round ID: {}", e)))?;
+    let report = funding_round_service.distribute_round(&object_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// List Stripe events whose processing failed
+pub async fn get_webhook_failures(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    query: web::Query<WebhookFailureQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let failures = mongodb.get_webhook_failures(&query).await?;
+    info!("Found {} webhook failures", failures.len());
+    Ok(HttpResponse::Ok().json(failures))
+}
+
+/// Queued, processing and failed Stripe events waiting on the webhook workers
+pub async fn get_webhook_queue(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    webhook_queue: web::Data<WebhookQueueService>,
+    query: web::Query<WebhookQueueQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let status = mongodb.get_webhook_queue_status(&query, webhook_queue.concurrency()).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Which webhook signing secrets are still matching, to tell when a rotated-out one can be removed
+pub async fn get_webhook_secret_status(
+    auth: AuthenticatedUser,
+    webhook_service: web::Data<WebhookService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    Ok(HttpResponse::Ok().json(webhook_service.secret_statuses()))
+}
+
+/// Reprocess a failed Stripe event after the underlying issue is fixed
+pub async fn replay_webhook_failure(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    cause_service: web::Data<CauseService>,
+    router: web::Data<StripeEventRouter>,
+    failure_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(failure_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid webhook failure ID: {}", e)))?;
+    let failure = mongodb.get_webhook_failure(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Webhook failure {} not found", failure_id)))?;
+    if failure.status == WebhookFailureStatus::Replayed {
+        return Err(ApiError::ValidationError(format!("Webhook failure {} was already replayed", failure_id)));
+    }
+
+    // The signature was verified when the event first arrived
+    let event: stripe::Event = serde_json::from_str(&failure.payload)
+        .map_err(|e| ApiError::InternalError(format!("Stored webhook payload is unreadable: {}", e)))?;
+
+    // Marking the event processed is a unique insert, so of two concurrent replays only one
+    // applies it; a failed replay takes the mark back off
+    let processed = ProcessedStripeEvent {
+        id: None,
+        event_id: failure.event_id.clone(),
+        event_type: failure.event_type.clone(),
+        endpoint: failure.endpoint,
+        processed_at: chrono::Utc::now(),
+    };
+    if !mongodb.mark_stripe_event_processed(&processed).await? {
+        return Err(ApiError::Conflict(format!("Webhook event {} was already processed", failure.event_id)));
+    }
+
+    info!("Admin {} replaying {} webhook event {} (attempt {})", auth.wallet_address, failure.endpoint, failure.event_id, failure.attempts + 1);
+    let ctx = WebhookContext { webhook_service, mongodb: mongodb.clone(), cause_service };
+    if let Err(e) = router.dispatch(failure.endpoint, &event, &ctx).await {
+        error!("Replay of webhook event {} failed: {:?}", failure.event_id, e);
+        mongodb.unmark_stripe_event_processed(&failure.event_id).await?;
+        mongodb.record_webhook_failure(failure.endpoint, &failure.event_id, &failure.event_type, &failure.payload, &e.to_string()).await?;
+        return Err(ApiError::InternalError(format!("Replay failed: {}", e)));
+    }
+
+    mongodb.mark_webhook_failure_replayed(&object_id).await?;
+    mongodb.mark_failed_webhook_job_processed(&failure.event_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "id": failure_id.as_str(),
+        "event_id": failure.event_id,
+        "status": WebhookFailureStatus::Replayed
+    })))
+}
+
+/// Causes that passed Stripe onboarding and are waiting for a moderator
+pub async fn get_cause_review_queue(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let causes = cause_service.get_review_queue().await?;
+    info!("{} causes awaiting review", causes.len());
+    Ok(HttpResponse::Ok().json(causes))
+}
+
+/// Stripe requirements of every cause checked so far, complete ones included, optionally
+/// only those in one `state`
+pub async fn get_cause_requirements(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    query: web::Query<CauseRequirementsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let causes = cause_service.get_causes_by_requirements(query.state, query.limit).await?;
+    Ok(HttpResponse::Ok().json(causes))
+}
+
+/// Causes by status, drafts stuck waiting on Stripe onboarding and failed causes
+pub async fn get_cause_dashboard(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    query: web::Query<CauseDashboardQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let stuck_hours = query.stuck_hours.unwrap_or(DEFAULT_STUCK_DRAFT_HOURS);
+    if stuck_hours < 0 {
+        return Err(ApiError::ValidationError("stuck_hours can't be negative".to_string()));
+    }
+    let dashboard = cause_service.lifecycle_dashboard(stuck_hours).await?;
+    Ok(HttpResponse::Ok().json(dashboard))
+}
+
+/// Retry, hide, show, feature or unfeature many causes at once, as a job whose result has
+/// each cause's outcome
+pub async fn bulk_cause_action(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    jobs: web::Data<JobService>,
+    payload: web::Json<BulkCauseRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    if payload.cause_ids.is_empty() || payload.cause_ids.len() > MAX_BULK_CAUSES {
+        return Err(ApiError::ValidationError(format!("cause_ids must list 1 to {} causes", MAX_BULK_CAUSES)));
+    }
+    info!("Admin {} applying {:?} to {} causes", auth.wallet_address, payload.action, payload.cause_ids.len());
+
+    let service = cause_service.into_inner();
+    let request = payload.into_inner();
+    let actor = auth.wallet_address.clone();
+    let job = jobs.start(JobKind::BulkCauseAction, &auth.wallet_address, move |progress| async move {
+        let results = service.bulk_action(request.action, &request.cause_ids, &actor, Some(&progress)).await;
+        let failed = results.iter().filter(|result| !result.ok).count();
+        Ok(json!({
+            "succeeded": results.len() - failed,
+            "failed": failed,
+            "results": results,
+        }))
+    }).await?;
+    Ok(job_accepted(&job))
+}
+
+/// Approve a cause; its token is minted and it goes live
+pub async fn approve_cause(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+    info!("Admin {} approving cause {}", auth.wallet_address, cause_id);
+
+    let cause = cause_service.approve_cause(&object_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(cause))
+}
+
+/// Reject a cause with a reason that is sent to the creator
+pub async fn reject_cause(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    payload: web::Json<ReviewCauseRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+    let reason = payload.reason.as_deref().map(str::trim).filter(|r| !r.is_empty())
+        .ok_or_else(|| ApiError::ValidationError("reason is required when rejecting a cause".to_string()))?;
+    info!("Admin {} rejecting cause {}: {}", auth.wallet_address, cause_id, reason);
+
+    let cause = cause_service.reject_cause(&object_id, &auth.wallet_address, reason).await?;
+    Ok(HttpResponse::Ok().json(cause))
+}
+
+/// Suspend a cause; it stops taking donations but its token stays spendable
+pub async fn suspend_cause(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+    info!("Admin {} suspending cause {}", auth.wallet_address, cause_id);
+
+    let cause = cause_service.suspend_cause(&object_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(cause))
+}
+
+/// Reinstate a suspended cause
+pub async fn reinstate_cause(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+    info!("Admin {} reinstating cause {}", auth.wallet_address, cause_id);
+
+    let cause = cause_service.reinstate_cause(&object_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(cause))
+}
+
+/// Archive a cause; it is hidden from listings but kept in history
+pub async fn archive_cause(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+    info!("Admin {} archiving cause {}", auth.wallet_address, cause_id);
+
+    let cause = cause_service.archive_cause(&object_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(cause))
+}
+
+/// Feature a cause, optionally at a rank and until a time after which it drops off
+pub async fn feature_cause(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    payload: web::Json<FeatureCauseRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let object_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+    info!("Admin {} featuring cause {} (rank {:?}, until {:?})", auth.wallet_address, cause_id, payload.featured_rank, payload.featured_until);
+
+    let cause = cause_service.feature_cause(&object_id, payload.featured_rank, payload.featured_until, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(cause))
+}
+
+/// Set the order featured causes are shown in
+pub async fn reorder_featured_causes(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    payload: web::Json<ReorderFeaturedRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    if payload.cause_ids.is_empty() || payload.cause_ids.len() > MAX_BULK_CAUSES {
+        return Err(ApiError::ValidationError(format!("cause_ids must list 1 to {} causes", MAX_BULK_CAUSES)));
+    }
+    let object_ids = payload.cause_ids.iter()
+        .map(|id| ObjectId::parse_str(id).map_err(|e| ApiError::ValidationError(format!("Invalid cause ID {}: {}", id, e))))
+        .collect::<Result<Vec<_>, _>>()?;
+    info!("Admin {} reordering {} featured causes", auth.wallet_address, object_ids.len());
+
+    let causes = cause_service.reorder_featured_causes(&object_ids, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(causes))
+}
+
+/// Every feature flag with whether it's on, for this deployment's environment or `environment`
+pub async fn get_feature_flags(
+    auth: AuthenticatedUser,
+    feature_flags: web::Data<FeatureFlagService>,
+    query: web::Query<FeatureFlagQuery>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let environment = query.environment.as_deref().unwrap_or(feature_flags.environment());
+    Ok(HttpResponse::Ok().json(feature_flags.list(environment).await?))
+}
+
+/// Switch a feature on or off for an environment
+pub async fn set_feature_flag(
+    auth: AuthenticatedUser,
+    name: web::Path<String>,
+    feature_flags: web::Data<FeatureFlagService>,
+    payload: web::Json<SetFeatureFlagRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let environment = payload.environment.as_deref().map(str::trim).filter(|e| !e.is_empty())
+        .unwrap_or(feature_flags.environment())
+        .to_string();
+    let flag = feature_flags.set(&name, &environment, payload.enabled, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(flag))
+}
+
+/// Every scheduled job's schedule, run counts and last run, and the replica running them
+pub async fn get_scheduled_jobs(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    Ok(HttpResponse::Ok().json(scheduler_status(&mongodb).await?))
+}
@@ -0,0 +1,87 @@
+use actix_web::{web, HttpResponse};
+use log::info;
+use mongodb::bson::oid::ObjectId;
+use crate::auth::AuthenticatedUser;
+use crate::handlers::cause_handlers::cause_with_role;
+use crate::models::cause::TeamRole;
+use crate::models::{ApiError, CreateCampaignRequest, UpdateCampaignRequest};
+use crate::services::{CampaignService, CauseService};
+use crate::utils::validation::Validate;
+
+fn cause_object_id(cause_id: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(cause_id).map_err(|e| ApiError::ValidationError(format!("Invalid cause ID format: {}", e)))
+}
+
+/// Start a campaign under the cause (cause editors or admin)
+pub async fn create_campaign(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    campaign_service: web::Data<CampaignService>,
+    cause_id: web::Path<String>,
+    request: web::Json<CreateCampaignRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let cause = cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Editor).await?;
+    let campaign = campaign_service.create(&cause, &request, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(campaign))
+}
+
+/// The cause's campaigns, newest first, ended ones included
+pub async fn get_campaigns(
+    campaign_service: web::Data<CampaignService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let cause_id = cause_object_id(&cause_id)?;
+    Ok(HttpResponse::Ok().json(campaign_service.list(&cause_id).await?))
+}
+
+pub async fn get_campaign(
+    campaign_service: web::Data<CampaignService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (cause_id, campaign_id) = path.into_inner();
+    let campaign = campaign_service.get(&cause_object_id(&cause_id)?, &campaign_id).await?;
+    Ok(HttpResponse::Ok().json(campaign))
+}
+
+/// Change a campaign, or end it with `status: "ended"` (cause editors or admin)
+pub async fn update_campaign(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    campaign_service: web::Data<CampaignService>,
+    path: web::Path<(String, String)>,
+    request: web::Json<UpdateCampaignRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let (cause_id, campaign_id) = path.into_inner();
+    cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Editor).await?;
+    let campaign = campaign_service.get(&cause_object_id(&cause_id)?, &campaign_id).await?;
+    let campaign = campaign_service.update(campaign, &request).await?;
+    info!("{} updated campaign {} of cause {}", auth.wallet_address, campaign_id, cause_id);
+    Ok(HttpResponse::Ok().json(campaign))
+}
+
+/// Delete a campaign nobody has donated to yet (cause editors or admin)
+pub async fn delete_campaign(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    campaign_service: web::Data<CampaignService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (cause_id, campaign_id) = path.into_inner();
+    cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Editor).await?;
+    let campaign = campaign_service.get(&cause_object_id(&cause_id)?, &campaign_id).await?;
+    campaign_service.delete(&campaign).await?;
+    info!("{} deleted campaign {} of cause {}", auth.wallet_address, campaign_id, cause_id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Raised so far against the campaign's goal
+pub async fn get_campaign_progress(
+    campaign_service: web::Data<CampaignService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (cause_id, campaign_id) = path.into_inner();
+    let campaign = campaign_service.get(&cause_object_id(&cause_id)?, &campaign_id).await?;
+    Ok(HttpResponse::Ok().json(campaign_service.progress(campaign).await?))
+}
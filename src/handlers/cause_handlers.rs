@@ -1,12 +1,20 @@
-use actix_web::{web, HttpResponse, Responder, error::ErrorInternalServerError};
+use actix_web::{web, HttpRequest, HttpResponse, Responder, error::ErrorInternalServerError};
+use actix_web::http::header::ETAG;
 use mongodb::bson::oid::ObjectId;
 use log::{info, error};
 
-use crate::models::ApiError;
-use crate::services::CauseService;
+use crate::models::{ApiError, JobKind, Role, PayoutEventQuery};
+use crate::models::payment::{CauseDonationsQuery, DonationAttribution};
+use crate::models::cause::{Cause, CauseListQuery, TeamRole, InviteTeamMemberRequest, UpdateTeamMemberRequest, AcceptTeamInviteRequest, CausePayoutRequest};
+use crate::services::{CauseService, JobService};
+use crate::handlers::job_handlers::job_accepted;
+use crate::auth::AuthenticatedUser;
+use crate::response_caching::{is_fresh, not_modified};
+use crate::utils::etag::listing_etag;
+use crate::utils::validation::{self, FieldErrors, Validate};
 
 // Re-export the request/response structs from the service
-pub use crate::services::cause_service::{CreateCauseRequest, CreateCauseResponse, UpdateCauseRequest};
+pub use crate::services::cause_service::{CreateCauseRequest, CreateCauseResponse, UpdateCauseRequest, MIN_DONATION_CENTS, MAX_DONATION_CENTS};
 
 // Request struct for creating a donation checkout session
 #[derive(serde::Deserialize)]
@@ -14,6 +22,34 @@ pub struct CreateDonationSessionRequest {
     pub cause_id: String,
     pub amount_cents: i64, // Amount in cents (e.g., 10000 = $100)
     pub user_wallet_address: String,
+    pub referrer: Option<String>,     // e.g. from a `?ref=` donate link
+    pub campaign_id: Option<String>,  // one of the cause's campaigns
+}
+
+impl Validate for CreateDonationSessionRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("cause_id", ObjectId::parse_str(&self.cause_id).map_err(|_| "Must be a cause ID".to_string()));
+        errors.check("amount_cents", validation::cents_between(self.amount_cents, MIN_DONATION_CENTS, MAX_DONATION_CENTS));
+        errors.check("user_wallet_address", validation::wallet_address(&self.user_wallet_address));
+        if let Some(referrer) = &self.referrer {
+            errors.check("referrer", validation::referrer(referrer));
+        }
+        if let Some(campaign_id) = &self.campaign_id {
+            errors.check("campaign_id", ObjectId::parse_str(campaign_id).map_err(|_| "Must be a campaign ID".to_string()));
+        }
+        errors.into_result()
+    }
+}
+
+impl CreateDonationSessionRequest {
+    pub fn attribution(&self) -> DonationAttribution {
+        DonationAttribution {
+            referrer: self.referrer.as_deref().map(str::trim).map(str::to_string),
+            campaign_id: self.campaign_id.clone(),
+            fundraiser_id: None,
+        }
+    }
 }
 
 // Response struct for checkout session
@@ -21,18 +57,24 @@ pub struct CreateDonationSessionRequest {
 pub struct CreateDonationSessionResponse {
     pub checkout_url: String,
     pub session_id: String,
+    pub apple_pay: bool,  // Wallet buttons Checkout may show; see GET /donations/payment-methods
+    pub google_pay: bool,
 }
 
 // Create a new cause
 pub async fn create_cause(
+    auth: AuthenticatedUser,
     cause_service: web::Data<CauseService>,
     cause_data: web::Json<CreateCauseRequest>,
 ) -> actix_web::Result<impl Responder> {
     info!("Creating new cause: {}", cause_data.name);
     info!("Organization: {}, Email: {}", cause_data.organization, cause_data.creator_email);
     
+    let mut cause_data = cause_data.into_inner();
+    cause_data.owner_address = Some(auth.wallet_address.clone());
+    
     info!("Calling cause service to create cause...");
-    match cause_service.create_cause(cause_data.into_inner()).await {
+    match cause_service.create_cause(cause_data).await {
         Ok(response) => {
             info!("Successfully created cause draft");
             Ok(HttpResponse::Created().json(response))
@@ -46,6 +88,7 @@ pub async fn create_cause(
                         message: msg,
                     }))
                 },
+                ApiError::InvalidFields(_) => Err(e.into()),
                 ApiError::DuplicateError(msg) => {
                     Ok(HttpResponse::Conflict().json(ErrorResponse { 
                         error: "duplicate_error".to_string(),
@@ -79,7 +122,8 @@ pub async fn get_cause(
     match cause_service.get_cause_by_id(&object_id).await {
         Ok(cause) => {
             info!("Found cause: {}", cause.name);
-            Ok(HttpResponse::Ok().json(cause))
+            let etag = listing_etag(1, cause.updated_at.timestamp_millis());
+            Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(cause))
         },
         Err(e) => match e {
             ApiError::NotFound(msg) => {
@@ -94,16 +138,164 @@ pub async fn get_cause(
     }
 }
 
+// List recent donations to a cause, newest first
+pub async fn get_cause_donations(
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    query: web::Query<CauseDonationsQuery>,
+) -> actix_web::Result<impl Responder> {
+    let object_id = match ObjectId::parse_str(cause_id.as_ref()) {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Invalid cause ID format: {}", e);
+            return Ok(HttpResponse::BadRequest().body(format!("Invalid cause ID format: {}", e)));
+        }
+    };
+    
+    match cause_service.get_cause_donations(&object_id, &query).await {
+        Ok(page) => Ok(HttpResponse::Ok().json(page)),
+        Err(ApiError::NotFound(msg)) => Ok(HttpResponse::NotFound().body(msg)),
+        Err(ApiError::ValidationError(msg)) => Ok(HttpResponse::BadRequest().body(msg)),
+        Err(e) => {
+            error!("Error retrieving donations for cause {}: {}", cause_id, e);
+            Err(ErrorInternalServerError(e.to_string()))
+        }
+    }
+}
+
+/// Donation totals by referrer, for the cause's team or an admin
+pub async fn get_cause_analytics(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let cause = cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Viewer).await?;
+    Ok(HttpResponse::Ok().json(cause_service.get_cause_analytics(&cause).await?))
+}
+
+/// Payouts to the cause's bank account and balance changes, newest first, for the cause's
+/// team or an admin
+pub async fn get_cause_payouts(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    query: web::Query<PayoutEventQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let cause = cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Viewer).await?;
+    Ok(HttpResponse::Ok().json(cause_service.get_payout_events(&cause, query.limit).await?))
+}
+
+/// Pay out part of the cause's available balance now (owners, signed)
+pub async fn create_cause_payout(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    request: web::Json<CausePayoutRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let cause = managed_cause(&auth, &cause_service, &cause_id).await?;
+    let payout = cause_service.create_payout(&cause, &request, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "payout_id": payout.id.to_string(),
+        "amount_cents": payout.amount,
+        "currency": payout.currency.to_string(),
+        "arrival_date": payout.arrival_date,
+        "status": payout.status,
+    })))
+}
+
+/// The cause's owner, organization and team (the team, signed)
+pub async fn get_cause_team(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let cause = cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Viewer).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "owner_address": cause.owner_address,
+        "organization_id": cause.organization_id,
+        "members": cause.team,
+    })))
+}
+
+/// Invite someone to the team by email (owners, signed)
+pub async fn invite_team_member(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    request: web::Json<InviteTeamMemberRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let cause = managed_cause(&auth, &cause_service, &cause_id).await?;
+    let member = cause_service.invite_team_member(&cause, &request, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(member))
+}
+
+/// Change a member's role (owners, signed)
+pub async fn update_team_member(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    path: web::Path<(String, String)>,
+    request: web::Json<UpdateTeamMemberRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let (cause_id, email) = path.into_inner();
+    let cause = managed_cause(&auth, &cause_service, &cause_id).await?;
+    cause_service.set_team_member_role(&cause, &email, request.role, &auth.wallet_address).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Remove a member or withdraw an invite (owners, signed)
+pub async fn remove_team_member(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (cause_id, email) = path.into_inner();
+    let cause = managed_cause(&auth, &cause_service, &cause_id).await?;
+    cause_service.remove_team_member(&cause, &email, &auth.wallet_address).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Join the team with the signed-in wallet, using the email and token from the invite link (signed)
+pub async fn accept_team_invite(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    request: web::Json<AcceptTeamInviteRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let cause_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID format: {}", e)))?;
+    let cause = cause_service.get_cause_by_id(&cause_id).await?;
+    let member = cause_service.accept_team_invite(&cause, &request.email, &request.token, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(member))
+}
+
+/// Causes the caller has joined the team of (signed)
+pub async fn get_my_team_causes(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(cause_service.get_team_causes(&auth.wallet_address).await?))
+}
+
 // Get all causes (only displayed ones)
 pub async fn get_all_causes(
+    req: HttpRequest,
     cause_service: web::Data<CauseService>,
 ) -> actix_web::Result<impl Responder> {
     info!("Getting all displayed causes");
     
+    let etag = cause_service.listed_causes_etag().await
+        .map_err(|e| ErrorInternalServerError(e.to_string()))?;
+    if is_fresh(&req, &etag) {
+        return Ok(not_modified(&etag));
+    }
+    
     match cause_service.get_all_causes().await {
         Ok(causes) => {
             info!("Retrieved {} displayed causes", causes.len());
-            Ok(HttpResponse::Ok().json(causes))
+            Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(causes))
         },
         Err(e) => {
             error!("Failed to retrieve causes: {}", e);
@@ -112,6 +304,20 @@ pub async fn get_all_causes(
     }
 }
 
+/// Displayed causes a page at a time, as summaries; API version 2's `GET /causes`
+pub async fn list_causes(
+    req: HttpRequest,
+    cause_service: web::Data<CauseService>,
+    query: web::Query<CauseListQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let etag = cause_service.listed_causes_etag().await?;
+    if is_fresh(&req, &etag) {
+        return Ok(not_modified(&etag));
+    }
+    let page = cause_service.list_causes(&query).await?;
+    Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(page))
+}
+
 // Get featured causes
 pub async fn get_featured_causes(
     cause_service: web::Data<CauseService>,
@@ -132,8 +338,10 @@ pub async fn get_featured_causes(
 
 // Get all causes (admin - unfiltered)
 pub async fn get_all_causes_admin(
+    auth: AuthenticatedUser,
     cause_service: web::Data<CauseService>,
 ) -> actix_web::Result<impl Responder> {
+    auth.require_role(Role::Admin)?;
     info!("Getting all causes (unfiltered - admin)");
     
     match cause_service.get_all_causes_unfiltered().await {
@@ -150,6 +358,7 @@ pub async fn get_all_causes_admin(
 
 // Update a cause
 pub async fn update_cause(
+    auth: AuthenticatedUser,
     cause_service: web::Data<CauseService>,
     cause_id: web::Path<String>,
     update_data: web::Json<UpdateCauseRequest>,
@@ -164,7 +373,18 @@ pub async fn update_cause(
         }
     };
     
-    match cause_service.update_cause(&object_id, update_data.into_inner()).await {
+    let update_data = update_data.into_inner();
+    if let Some(response) = check_cause_access(&auth, &cause_service, &object_id, TeamRole::Editor).await? {
+        return Ok(response);
+    }
+    if update_data.touches_admin_fields() && !auth.is_admin() {
+        return Ok(HttpResponse::Forbidden().json(ErrorResponse {
+            error: "forbidden".to_string(),
+            message: "Only admins can change status, listing or payment fields".to_string(),
+        }));
+    }
+    
+    match cause_service.update_cause(&object_id, update_data, &auth.wallet_address).await {
         Ok(success) => {
             if success {
                 info!("Successfully updated cause");
@@ -183,6 +403,7 @@ pub async fn update_cause(
 
 // Delete a cause
 pub async fn delete_cause(
+    auth: AuthenticatedUser,
     cause_service: web::Data<CauseService>,
     cause_id: web::Path<String>,
 ) -> actix_web::Result<impl Responder> {
@@ -196,7 +417,11 @@ pub async fn delete_cause(
         }
     };
     
-    match cause_service.delete_cause(&object_id).await {
+    if let Some(response) = check_cause_access(&auth, &cause_service, &object_id, TeamRole::Owner).await? {
+        return Ok(response);
+    }
+    
+    match cause_service.delete_cause(&object_id, &auth.wallet_address).await {
         Ok(success) => {
             if success {
                 info!("Successfully deleted cause");
@@ -213,6 +438,56 @@ pub async fn delete_cause(
     }
 }
 
+// Resume a failed cause creation from the step that failed, as a job whose result is the cause
+pub async fn retry_cause_creation(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    jobs: web::Data<JobService>,
+    cause_id: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    info!("Retrying creation of cause with ID: {}", cause_id);
+    
+    let object_id = match ObjectId::parse_str(cause_id.as_ref()) {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Invalid cause ID format: {}", e);
+            return Ok(HttpResponse::BadRequest().body(format!("Invalid cause ID format: {}", e)));
+        }
+    };
+    
+    if let Some(response) = check_cause_access(&auth, &cause_service, &object_id, TeamRole::Owner).await? {
+        return Ok(response);
+    }
+    
+    match cause_service.claim_cause_resume(&object_id).await {
+        Ok(Some(cause)) => Ok(HttpResponse::Ok().json(cause)),
+        Ok(None) => {
+            let service = cause_service.into_inner();
+            let job = jobs.start(JobKind::CauseRetry, &auth.wallet_address, move |_| async move {
+                let cause = service.continue_cause_creation(&object_id).await?;
+                info!("Cause {} creation resumed, status: {}", object_id, cause.status);
+                serde_json::to_value(cause).map_err(|e| ApiError::InternalError(e.to_string()))
+            }).await?;
+            Ok(job_accepted(&job))
+        },
+        Err(ApiError::ValidationError(msg)) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "validation_error".to_string(),
+            message: msg,
+        })),
+        Err(ApiError::DuplicateError(msg)) => Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "retry_in_progress".to_string(),
+            message: msg,
+        })),
+        Err(e) => {
+            error!("Failed to retry cause creation: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "retry_failed".to_string(),
+                message: e.to_string(),
+            }))
+        }
+    }
+}
+
 // Get cause by token name
 pub async fn get_cause_by_token_name(
     cause_service: web::Data<CauseService>,
@@ -289,6 +564,50 @@ pub async fn get_cause_by_token_symbol(
 }
 
 
+// Returns an error response if the cause doesn't exist or the caller lacks `role` on it
+async fn check_cause_access(
+    auth: &AuthenticatedUser,
+    cause_service: &CauseService,
+    cause_id: &ObjectId,
+    role: TeamRole,
+) -> actix_web::Result<Option<HttpResponse>> {
+    match cause_with_role(auth, cause_service, &cause_id.to_hex(), role).await {
+        Ok(_) => Ok(None),
+        Err(ApiError::Forbidden(msg)) => {
+            info!("Wallet {} is not allowed to modify cause {}", auth.wallet_address, cause_id);
+            Ok(Some(HttpResponse::Forbidden().json(ErrorResponse {
+                error: "forbidden".to_string(),
+                message: msg,
+            })))
+        },
+        Err(ApiError::NotFound(msg)) => Ok(Some(HttpResponse::NotFound().body(msg))),
+        Err(e) => {
+            error!("Error retrieving cause: {}", e);
+            Err(ErrorInternalServerError(e.to_string()))
+        }
+    }
+}
+
+/// The cause at `cause_id`, if the caller has at least `role` on it. Admins have every role.
+pub(crate) async fn cause_with_role(auth: &AuthenticatedUser, cause_service: &CauseService, cause_id: &str, role: TeamRole) -> Result<Cause, ApiError> {
+    let cause_id = ObjectId::parse_str(cause_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID format: {}", e)))?;
+    let cause = cause_service.get_cause_by_id(&cause_id).await?;
+    if auth.can_manage_cause(&cause) {
+        return Ok(cause);
+    }
+    match cause_service.team_role(&cause, &auth.wallet_address).await? {
+        Some(granted) if granted >= role => Ok(cause),
+        _ => Err(ApiError::Forbidden(format!("Requires the {} role on this cause's team", role))),
+    }
+}
+
+/// The cause at `cause_id`, if the caller may manage it: its owner, its organization's
+/// admins, owners on its team, or an admin
+pub(crate) async fn managed_cause(auth: &AuthenticatedUser, cause_service: &CauseService, cause_id: &str) -> Result<Cause, ApiError> {
+    cause_with_role(auth, cause_service, cause_id, TeamRole::Owner).await
+}
+
 // Error response struct
 #[derive(serde::Serialize)]
 struct ErrorResponse {
@@ -357,14 +676,17 @@ pub async fn get_onboarding_link(
     }
 }
 
-// Check account status
+// Check account status. The connected account's requirements are KYC details, so only the
+// cause's team, its managers and admins see them.
 pub async fn check_account_status(
+    auth: AuthenticatedUser,
     cause_service: web::Data<CauseService>,
     cause_id: web::Path<String>,
 ) -> actix_web::Result<impl Responder> {
     info!("Checking account status for cause: {}", cause_id);
     
-    match cause_service.get_account_status(&cause_id).await {
+    let cause = cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Viewer).await?;
+    match cause_service.get_account_status(&cause).await {
         Ok(status) => Ok(HttpResponse::Ok().json(status)),
         Err(e) => {
             error!("Failed to get account status: {}", e);
@@ -375,12 +697,6 @@ pub async fn check_account_status(
                         message: msg,
                     }))
                 },
-                ApiError::NotFound(msg) => {
-                    Ok(HttpResponse::NotFound().json(ErrorResponse { 
-                        error: "not_found".to_string(),
-                        message: msg,
-                    }))
-                },
                 _ => Err(ErrorInternalServerError(e.to_string()))
             }
         }
@@ -414,6 +730,115 @@ pub async fn get_draft_status(
     }
 }
 
+// Keep an unfinished draft alive while Stripe onboarding takes longer than expected
+pub async fn extend_draft(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    draft_id: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    info!("Extending draft: {}", draft_id);
+    
+    let draft = match cause_service.get_draft(&draft_id).await {
+        Ok(draft) => draft,
+        Err(ApiError::NotFound(msg)) => return Ok(HttpResponse::NotFound().body(msg)),
+        Err(ApiError::ValidationError(msg)) => return Ok(HttpResponse::BadRequest().body(msg)),
+        Err(e) => {
+            error!("Error retrieving draft: {}", e);
+            return Err(ErrorInternalServerError(e.to_string()));
+        }
+    };
+    
+    let is_owner = draft.owner_address.as_deref() == Some(auth.wallet_address.as_str());
+    if !is_owner && !auth.is_admin() {
+        return Ok(HttpResponse::Forbidden().json(ErrorResponse {
+            error: "forbidden".to_string(),
+            message: "Only the draft creator or an admin can extend this draft".to_string(),
+        }));
+    }
+    
+    match cause_service.extend_draft(&draft).await {
+        Ok(expires_at) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "draft_id": draft_id.as_str(),
+            "expires_at": expires_at,
+        }))),
+        Err(ApiError::ValidationError(msg)) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "validation_error".to_string(),
+            message: msg,
+        })),
+        Err(e) => {
+            error!("Failed to extend draft: {}", e);
+            Err(ErrorInternalServerError(e.to_string()))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct VerifyDraftEmailRequest {
+    pub token: String,
+}
+
+// Confirm the creator's email from the signed link sent on draft creation
+pub async fn verify_draft_email(
+    cause_service: web::Data<CauseService>,
+    draft_id: web::Path<String>,
+    request: web::Json<VerifyDraftEmailRequest>,
+) -> actix_web::Result<impl Responder> {
+    info!("Verifying creator email for draft: {}", draft_id);
+    
+    match cause_service.verify_draft_email(&draft_id, &request.token).await {
+        Ok(()) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "draft_id": draft_id.as_str(),
+            "email_verified": true,
+        }))),
+        Err(ApiError::NotFound(msg)) => Ok(HttpResponse::NotFound().body(msg)),
+        Err(ApiError::ValidationError(msg)) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "invalid_verification".to_string(),
+            message: msg,
+        })),
+        Err(e) => {
+            error!("Failed to verify draft email: {}", e);
+            Err(ErrorInternalServerError(e.to_string()))
+        }
+    }
+}
+
+// Send a new verification link, e.g. after the first one expired
+pub async fn resend_draft_verification(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    draft_id: web::Path<String>,
+) -> actix_web::Result<impl Responder> {
+    let draft = match cause_service.get_draft(&draft_id).await {
+        Ok(draft) => draft,
+        Err(ApiError::NotFound(msg)) => return Ok(HttpResponse::NotFound().body(msg)),
+        Err(ApiError::ValidationError(msg)) => return Ok(HttpResponse::BadRequest().body(msg)),
+        Err(e) => {
+            error!("Error retrieving draft: {}", e);
+            return Err(ErrorInternalServerError(e.to_string()));
+        }
+    };
+    
+    let is_owner = draft.owner_address.as_deref() == Some(auth.wallet_address.as_str());
+    if !is_owner && !auth.is_admin() {
+        return Ok(HttpResponse::Forbidden().json(ErrorResponse {
+            error: "forbidden".to_string(),
+            message: "Only the draft creator or an admin can request a new link".to_string(),
+        }));
+    }
+    
+    match cause_service.resend_verification_email(&draft).await {
+        Ok(()) => Ok(HttpResponse::Accepted().finish()),
+        Err(ApiError::ValidationError(msg)) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "validation_error".to_string(),
+            message: msg,
+        })),
+        Err(e) => {
+            error!("Failed to resend verification email: {}", e);
+            Err(ErrorInternalServerError(e.to_string()))
+        }
+    }
+}
+
 // Find drafts by email
 pub async fn find_drafts_by_email(
     cause_service: web::Data<CauseService>,
@@ -430,15 +855,18 @@ pub async fn find_drafts_by_email(
     }
 }
 
-// Create donation checkout session
+// Create donation checkout session. The wallet's saved cards are only offered when the
+// wallet signed the request.
 pub async fn create_donation_session(
+    auth: Option<AuthenticatedUser>,
     cause_service: web::Data<CauseService>,
-    stripe_client: web::Data<stripe::Client>,
     request: web::Json<CreateDonationSessionRequest>,
 ) -> actix_web::Result<impl Responder> {
     info!("Creating donation session for cause {} with amount {} cents", 
         request.cause_id, request.amount_cents);
     
+    request.validate().map_err(ApiError::from)?;
+    
     // Get the cause
     let cause_id = match ObjectId::parse_str(&request.cause_id) {
         Ok(id) => id,
@@ -466,6 +894,13 @@ pub async fn create_donation_session(
         }
     };
     
+    if !cause.accepts_donations() {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "cause_not_accepting_donations".to_string(),
+            message: format!("This cause is {} and is not accepting donations", cause.status),
+        }));
+    }
+    
     // Get connected account ID
     let connected_account_id = match &cause.stripe_account_id {
         Some(id) => id.clone(),
@@ -482,12 +917,16 @@ pub async fn create_donation_session(
         &cause,
         &connected_account_id,
         request.amount_cents,
-        &request.user_wallet_address,
+        Some(&request.user_wallet_address),
+        auth.is_some_and(|auth| auth.wallet_address == request.user_wallet_address),
+        &request.attribution(),
     ).await {
         Ok((session_id, checkout_url)) => {
             Ok(HttpResponse::Ok().json(CreateDonationSessionResponse {
                 checkout_url,
                 session_id,
+                apple_pay: cause_service.payment_methods().apple_pay,
+                google_pay: cause_service.payment_methods().google_pay,
             }))
         },
         Err(e) => {
@@ -513,17 +952,10 @@ pub async fn validate_cause_name(
     let name = request.value.trim();
     
     match cause_service.validate_cause_name(name).await {
-        Ok(is_valid) => {
-            let response = if is_valid {
-                ValidationResponse {
-                    valid: true,
-                    message: None,
-                }
-            } else {
-                ValidationResponse {
-                    valid: false,
-                    message: Some("This cause name is already taken".to_string()),
-                }
+        Ok(problem) => {
+            let response = ValidationResponse {
+                valid: problem.is_none(),
+                message: problem,
             };
             Ok(HttpResponse::Ok().json(response))
         },
@@ -542,21 +974,10 @@ pub async fn validate_token_symbol(
     let symbol = request.value.trim();
     
     match cause_service.validate_token_symbol(symbol).await {
-        Ok(is_valid) => {
-            let response = if is_valid {
-                ValidationResponse {
-                    valid: true,
-                    message: None,
-                }
-            } else {
-                ValidationResponse {
-                    valid: false,
-                    message: Some(if symbol.len() < 2 || symbol.len() > 5 || !symbol.to_uppercase().chars().all(|c| c.is_ascii_uppercase()) {
-                        "Token symbol must be 2-5 uppercase letters".to_string()
-                    } else {
-                        "This token symbol is already taken".to_string()
-                    }),
-                }
+        Ok(problem) => {
+            let response = ValidationResponse {
+                valid: problem.is_none(),
+                message: problem,
             };
             Ok(HttpResponse::Ok().json(response))
         },
@@ -575,17 +996,10 @@ pub async fn validate_token_name(
     let name = request.value.trim();
     
     match cause_service.validate_token_name(name).await {
-        Ok(is_valid) => {
-            let response = if is_valid {
-                ValidationResponse {
-                    valid: true,
-                    message: None,
-                }
-            } else {
-                ValidationResponse {
-                    valid: false,
-                    message: Some("This token name is already taken".to_string()),
-                }
+        Ok(problem) => {
+            let response = ValidationResponse {
+                valid: problem.is_none(),
+                message: problem,
             };
             Ok(HttpResponse::Ok().json(response))
         },
@@ -0,0 +1,82 @@
+use actix_web::{web, HttpResponse};
+use delta_executor_sdk::base::crypto::Ed25519PubKey;
+use serde_json::json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::auth::AuthenticatedUser;
+use crate::models::{ApiError, ContactEntry, SaveContactRequest};
+use crate::services::MongoDBService;
+
+/// A user's saved contacts with each contact's current username and profile
+pub async fn list_contacts(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let contacts = db.get_contacts(&wallet_address).await?;
+    let addresses: Vec<String> = contacts.iter().map(|c| c.contact_address.clone()).collect();
+    let users: HashMap<String, _> = db.get_users_by_wallets(&addresses).await?
+        .into_iter()
+        .filter(|user| user.deleted_at.is_none())
+        .map(|user| (user.wallet_address.clone(), user))
+        .collect();
+
+    let entries: Vec<ContactEntry> = contacts.into_iter()
+        .map(|contact| {
+            let user = users.get(&contact.contact_address);
+            ContactEntry {
+                username: user.map(|u| u.username.clone()),
+                display_name: user.and_then(|u| u.display_name.clone()),
+                avatar_url: user.and_then(|u| u.avatar_url.clone()),
+                contact_address: contact.contact_address,
+                nickname: contact.nickname,
+                created_at: contact.created_at,
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// Save a counterparty under a nickname, or rename an existing contact
+pub async fn save_contact(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    payload: web::Json<SaveContactRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, contact_address) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+
+    Ed25519PubKey::from_str(&contact_address)
+        .map_err(|_| ApiError::ValidationError(format!("Invalid wallet address: {}", contact_address)))?;
+    if contact_address == wallet_address {
+        return Err(ApiError::ValidationError("Cannot save yourself as a contact".to_string()));
+    }
+    let nickname = payload.nickname.trim();
+    if nickname.is_empty() || nickname.chars().count() > 50 {
+        return Err(ApiError::ValidationError("Nickname must be between 1 and 50 characters".to_string()));
+    }
+
+    let contact = db.save_contact(&wallet_address, &contact_address, nickname).await?;
+    Ok(HttpResponse::Ok().json(contact))
+}
+
+pub async fn delete_contact(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, contact_address) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+
+    if !db.delete_contact(&wallet_address, &contact_address).await? {
+        return Err(ApiError::NotFound(format!("Contact {} not found", contact_address)));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "status": "success",
+        "contact_address": contact_address
+    })))
+}
@@ -0,0 +1,190 @@
+use actix_web::{web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use std::str::FromStr;
+use crate::auth::AuthenticatedUser;
+use crate::services::{MongoDBService, DisputeService, FeatureFlagService};
+use crate::models::{
+    ApiError, Role, Dispute, DisputeStatus, OpenDisputeRequest, RespondToDisputeRequest, ResolveDisputeRequest, ResolveDisputeRefundRequest, DisputeQuery,
+    LiabilityQuery, MAX_DISPUTE_TEXT_CHARS,
+};
+
+/// The paying customer flags a completed payment. Any wallet linked to the payer's
+/// account may dispute it.
+pub async fn open_dispute(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    payload: web::Json<OpenDisputeRequest>,
+    db: web::Data<MongoDBService>,
+    dispute_service: web::Data<DisputeService>,
+) -> Result<HttpResponse, ApiError> {
+    let reason = required_text("reason", &payload.reason)?;
+    let details = optional_text("details", payload.details.as_deref())?;
+
+    let payment_id = db.resolve_payment_code(&payment_id).await?;
+    let payment = db.get_payment(&payment_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", payment_id)))?;
+    let customer_address = payment.customer_address.clone()
+        .ok_or_else(|| ApiError::Conflict("Payment has no customer".to_string()))?;
+    if customer_address != auth.wallet_address && !db.wallets_share_account(&customer_address, &auth.wallet_address).await? {
+        return Err(ApiError::Forbidden("Only the paying customer can dispute this payment".to_string()));
+    }
+
+    let dispute = dispute_service.open(&payment, &customer_address, reason, details).await?;
+    Ok(HttpResponse::Created().json(dispute))
+}
+
+/// Disputes a wallet raised as customer or received as vendor, newest first
+pub async fn get_user_disputes(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    query: web::Query<DisputeQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    let disputes = db.get_disputes(Some(&wallet_address), parse_status(&query)?).await?;
+    Ok(HttpResponse::Ok().json(disputes))
+}
+
+/// A dispute, for either side or an admin
+pub async fn get_dispute(
+    auth: AuthenticatedUser,
+    dispute_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let dispute = load_dispute(&db, &dispute_id).await?;
+    if !auth.is_admin() && auth.wallet_address != dispute.customer_address && auth.wallet_address != dispute.vendor_address {
+        return Err(ApiError::Forbidden("Cannot view another wallet's dispute".to_string()));
+    }
+    Ok(HttpResponse::Ok().json(dispute))
+}
+
+/// The vendor answers a dispute before it's resolved
+pub async fn respond_to_dispute(
+    auth: AuthenticatedUser,
+    dispute_id: web::Path<String>,
+    payload: web::Json<RespondToDisputeRequest>,
+    db: web::Data<MongoDBService>,
+    dispute_service: web::Data<DisputeService>,
+) -> Result<HttpResponse, ApiError> {
+    let response = required_text("response", &payload.response)?;
+    let dispute = load_dispute(&db, &dispute_id).await?;
+    auth.require_self_or_admin(&dispute.vendor_address)?;
+    let dispute = dispute_service.respond(&dispute, response).await?;
+    Ok(HttpResponse::Ok().json(dispute))
+}
+
+/// Disputes awaiting a decision (or any `status`), oldest first
+pub async fn get_dispute_queue(
+    auth: AuthenticatedUser,
+    query: web::Query<DisputeQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let disputes = match parse_status(&query)? {
+        Some(status) => db.get_disputes(None, Some(status)).await?,
+        None => {
+            let mut disputes = db.get_disputes(None, Some(DisputeStatus::Open)).await?;
+            disputes.extend(db.get_disputes(None, Some(DisputeStatus::VendorResponded)).await?);
+            disputes.sort_by_key(|d| d.created_at);
+            disputes
+        },
+    };
+    Ok(HttpResponse::Ok().json(disputes))
+}
+
+/// Decide a dispute: `refund: true` pays the customer back, `false` upholds the payment
+pub async fn resolve_dispute(
+    auth: AuthenticatedUser,
+    dispute_id: web::Path<String>,
+    payload: web::Json<ResolveDisputeRequest>,
+    db: web::Data<MongoDBService>,
+    dispute_service: web::Data<DisputeService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    if payload.refund {
+        feature_flags.require("refunds_enabled")?;
+    }
+    let note = optional_text("note", payload.note.as_deref())?;
+    let dispute = load_dispute(&db, &dispute_id).await?;
+    let dispute = dispute_service.resolve(&dispute, payload.refund, note, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(dispute))
+}
+
+pub async fn retry_dispute_refund(
+    auth: AuthenticatedUser,
+    dispute_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    dispute_service: web::Data<DisputeService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    feature_flags.require("refunds_enabled")?;
+    let dispute = load_dispute(&db, &dispute_id).await?;
+    let dispute = dispute_service.retry_refund(&dispute, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(dispute))
+}
+
+/// Settle a refund whose transfer may have landed, after checking the executor (admin)
+pub async fn resolve_dispute_refund(
+    auth: AuthenticatedUser,
+    dispute_id: web::Path<String>,
+    payload: web::Json<ResolveDisputeRefundRequest>,
+    db: web::Data<MongoDBService>,
+    dispute_service: web::Data<DisputeService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let dispute = load_dispute(&db, &dispute_id).await?;
+    let dispute = dispute_service.resolve_refund(&dispute, &payload, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(dispute))
+}
+
+/// Central vault refunds vendors haven't paid back, oldest first
+pub async fn get_vendor_liabilities(
+    auth: AuthenticatedUser,
+    query: web::Query<LiabilityQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let disputes = db.get_outstanding_liabilities(query.vendor.as_deref()).await?;
+    Ok(HttpResponse::Ok().json(disputes))
+}
+
+pub async fn settle_vendor_liability(
+    auth: AuthenticatedUser,
+    dispute_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    dispute_service: web::Data<DisputeService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let dispute = load_dispute(&db, &dispute_id).await?;
+    let dispute = dispute_service.settle_liability(&dispute, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(dispute))
+}
+
+fn parse_status(query: &DisputeQuery) -> Result<Option<DisputeStatus>, ApiError> {
+    query.status.as_deref()
+        .map(DisputeStatus::from_str)
+        .transpose()
+        .map_err(ApiError::ValidationError)
+}
+
+fn required_text(field: &str, text: &str) -> Result<String, ApiError> {
+    optional_text(field, Some(text))?
+        .ok_or_else(|| ApiError::ValidationError(format!("{} is required", field)))
+}
+
+fn optional_text(field: &str, text: Option<&str>) -> Result<Option<String>, ApiError> {
+    let text = text.map(str::trim).filter(|t| !t.is_empty());
+    if text.map_or(false, |t| t.chars().count() > MAX_DISPUTE_TEXT_CHARS) {
+        return Err(ApiError::ValidationError(format!("{} must be at most {} characters", field, MAX_DISPUTE_TEXT_CHARS)));
+    }
+    Ok(text.map(str::to_string))
+}
+
+async fn load_dispute(db: &MongoDBService, dispute_id: &str) -> Result<Dispute, ApiError> {
+    let object_id = ObjectId::parse_str(dispute_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid dispute ID: {}", e)))?;
+    db.get_dispute(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Dispute {} not found", dispute_id)))
+}
@@ -0,0 +1,199 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::{error, info, warn};
+use std::str::FromStr;
+use stripe::{CheckoutSessionId, CheckoutSessionPaymentStatus, CheckoutSessionStatus};
+use crate::auth::AuthenticatedUser;
+use crate::handlers::purchase_webhook_handlers::{credit_unclaimed_deposit, session_wallet_address};
+use mongodb::bson::oid::ObjectId;
+use crate::services::{CauseService, MongoDBService, PaymentIntentService, SharedState, StripeApi, WebhookService};
+use crate::models::{ApiError, Role};
+use crate::utils::validation::Validate;
+use crate::models::payment::{DonationSessionResponse, DonationSessionStatus, CreatePaymentIntentRequest, ConfirmPaymentIntentRequest, PaymentIntentResponse, PaymentMethodsResponse, ClaimLinkRequest, ClaimDepositsRequest, ClaimDepositsResponse};
+
+/// Claim links that can be requested per email, and per client IP, each hour
+const CLAIM_LINKS_PER_EMAIL_PER_HOUR: u32 = 3;
+const CLAIM_LINKS_PER_IP_PER_HOUR: u32 = 20;
+
+/// Verify a checkout session for the success page instead of trusting `?session_id`.
+/// Only the paying wallet (or an admin) may look a session up.
+pub async fn get_donation_session(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    stripe: web::Data<dyn StripeApi>,
+    session_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = CheckoutSessionId::from_str(session_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid checkout session ID: {}", e)))?;
+    let sess = stripe.retrieve_checkout_session(&id)
+        .await
+        .map_err(|e| ApiError::NotFound(format!("Checkout session {} not found: {}", session_id, e)))?;
+
+    match session_wallet_address(&sess) {
+        Some(wallet) => auth.require_self_or_admin(wallet)?,
+        None => auth.require_role(Role::Admin)?,
+    }
+
+    let deposit = mongodb.get_deposit_by_session_id(sess.id.as_str()).await?;
+    let status = if deposit.is_some() {
+        DonationSessionStatus::Credited
+    } else if sess.payment_status == CheckoutSessionPaymentStatus::Paid {
+        DonationSessionStatus::Processing
+    } else if sess.status == Some(CheckoutSessionStatus::Expired) {
+        DonationSessionStatus::Expired
+    } else {
+        DonationSessionStatus::Unpaid
+    };
+    info!("Checkout session {} status for {}: {:?}", session_id, auth.wallet_address, status);
+
+    Ok(HttpResponse::Ok().json(DonationSessionResponse {
+        session_id: sess.id.to_string(),
+        status,
+        amount_cents: sess.amount_total.unwrap_or(0),
+        token_symbol: sess.metadata.as_ref().and_then(|m| m.get("token_symbol")).cloned(),
+        deposit,
+    }))
+}
+
+/// Payment methods enabled for donations and top-ups, so the frontend knows which wallet buttons to offer
+pub async fn get_payment_methods(
+    cause_service: web::Data<CauseService>,
+) -> Result<HttpResponse, ApiError> {
+    let config = cause_service.payment_methods();
+    Ok(HttpResponse::Ok().json(PaymentMethodsResponse {
+        publishable_key: config.publishable_key.clone(),
+        payment_method_types: config.payment_method_types.clone(),
+        apple_pay: config.apple_pay,
+        google_pay: config.google_pay,
+        country: "US".to_string(),
+        currency: "usd".to_string(),
+    }))
+}
+
+/// Create a PaymentIntent for an embedded card form (donation or USD top-up). The wallet's
+/// saved cards are only offered when the wallet signed the request.
+pub async fn create_payment_intent(
+    auth: Option<AuthenticatedUser>,
+    cause_service: web::Data<CauseService>,
+    payment_intent_service: web::Data<PaymentIntentService>,
+    request: web::Json<CreatePaymentIntentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let wallet_signed = auth.is_some_and(|auth| auth.wallet_address == request.user_wallet_address);
+
+    let intent = match &request.cause_id {
+        Some(cause_id) => {
+            let cause_id = ObjectId::parse_str(cause_id)
+                .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID: {}", e)))?;
+            let cause = cause_service.get_cause_by_id(&cause_id).await?;
+            if !cause.accepts_donations() {
+                return Err(ApiError::ValidationError(format!("This cause is {} and is not accepting donations", cause.status)));
+            }
+            let attribution = request.attribution();
+            cause_service.check_attribution(&cause, &attribution).await?;
+            payment_intent_service.create_donation_intent(&cause, request.amount_cents, &request.user_wallet_address, wallet_signed, &attribution).await?
+        }
+        None => payment_intent_service.create_topup_intent(request.amount_cents, &request.user_wallet_address, wallet_signed).await?,
+    };
+
+    Ok(HttpResponse::Ok().json(PaymentIntentResponse {
+        payment_intent_id: intent.id.to_string(),
+        client_secret: intent.client_secret,
+        status: intent.status.as_str().to_string(),
+        amount_cents: intent.amount,
+    }))
+}
+
+/// Confirm an embedded PaymentIntent with the payment method the form collected.
+/// Only the paying wallet (or an admin) may confirm.
+pub async fn confirm_payment_intent(
+    auth: AuthenticatedUser,
+    payment_intent_service: web::Data<PaymentIntentService>,
+    payment_intent_id: web::Path<String>,
+    request: web::Json<ConfirmPaymentIntentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let intent = payment_intent_service.retrieve(&payment_intent_id).await?;
+    match intent.metadata.get("user_wallet_address") {
+        Some(wallet) => auth.require_self_or_admin(wallet)?,
+        None => auth.require_role(Role::Admin)?,
+    }
+
+    let confirmed = payment_intent_service
+        .confirm(&intent, &request.payment_method_id, request.return_url.as_deref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(PaymentIntentResponse {
+        payment_intent_id: confirmed.id.to_string(),
+        client_secret: confirmed.client_secret,
+        status: confirmed.status.as_str().to_string(),
+        amount_cents: confirmed.amount,
+    }))
+}
+
+/// Send a fresh claim link for donations paid without a wallet. Answers the same whether
+/// or not anything is held for the email, so it can't be used to look donors up. Rate
+/// limited per email and per client IP, so it can't be used to flood an inbox either.
+pub async fn request_claim_link(
+    req: HttpRequest,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    shared_state: web::Data<SharedState>,
+    request: web::Json<ClaimLinkRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    webhook_service.require_claim_links()?;
+
+    let ip = req.connection_info().realip_remote_addr().unwrap_or("unknown").to_string();
+    shared_state.check_rate_limit(&format!("claim_link_ip:{}", ip), CLAIM_LINKS_PER_IP_PER_HOUR, 3600).await?;
+    let email = request.email.trim().to_lowercase();
+    shared_state.check_rate_limit(&format!("claim_link_email:{}", email), CLAIM_LINKS_PER_EMAIL_PER_HOUR, 3600).await?;
+
+    let held = mongodb.get_unclaimed_deposits(&request.email).await?;
+    if held.is_empty() {
+        info!("Claim link requested for an email with no unclaimed deposits");
+    } else if let Err(e) = webhook_service.send_claim_link(&request.email).await {
+        error!("Failed to send claim link: {}", e);
+    }
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Credit every deposit held for an email to the signing wallet, given the token from the
+/// emailed claim link. Each deposit is reserved before it's credited, so a deposit is never
+/// credited twice. One whose transfer the executor refused goes back to unclaimed and can be
+/// claimed again; one that may have been credited anyway is left failed for an admin.
+pub async fn claim_deposits(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    request: web::Json<ClaimDepositsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    webhook_service.verify_claim_token(&request.email, &request.token)?;
+
+    let mut deposits = Vec::new();
+    let mut failed = 0;
+    for unclaimed in mongodb.get_unclaimed_deposits(&request.email).await? {
+        let Some(id) = unclaimed.id else { continue };
+        if !mongodb.reserve_unclaimed_deposit(&id, &auth.wallet_address).await? {
+            continue;
+        }
+        match credit_unclaimed_deposit(&unclaimed, &auth.wallet_address, &webhook_service, &mongodb).await {
+            Ok(deposit) => {
+                mongodb.finish_unclaimed_deposit_claim(&id, true).await?;
+                deposits.extend(deposit);
+            }
+            Err(e) if e.credited_nothing() => {
+                warn!("Failed to credit held payment {} to {}: {}", unclaimed.payment_id, auth.wallet_address, e);
+                mongodb.finish_unclaimed_deposit_claim(&id, false).await?;
+                failed += 1;
+            }
+            Err(e) => {
+                error!("Crediting held payment {} to {} may have landed, leaving it for reconciliation: {}", unclaimed.payment_id, auth.wallet_address, e);
+                mongodb.fail_unclaimed_deposit_claim(&id, &e.to_string()).await?;
+                failed += 1;
+            }
+        }
+    }
+
+    info!("{} claimed {} held deposits ({} failed)", auth.wallet_address, deposits.len(), failed);
+    Ok(HttpResponse::Ok().json(ClaimDepositsResponse { deposits, failed }))
+}
@@ -0,0 +1,65 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::ORIGIN;
+use log::info;
+use mongodb::bson::oid::ObjectId;
+use crate::auth::AuthenticatedUser;
+use crate::handlers::cause_handlers::cause_with_role;
+use crate::models::cause::TeamRole;
+use crate::models::{ApiError, CreateEmbedTokenRequest, EmbedDonationRequest, EmbedDonationResponse};
+use crate::services::{CauseService, MongoDBService};
+use crate::utils::validation::Validate;
+
+/// Issue a token for a donate button on one of the cause's sites (cause editors or admin)
+pub async fn create_embed_token(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    cause_id: web::Path<String>,
+    request: web::Json<CreateEmbedTokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let cause = cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Editor).await?;
+    let token = cause_service.create_embed_token(&cause, &request, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(token))
+}
+
+/// The cause's live embed tokens (cause editors or admin)
+pub async fn get_embed_tokens(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    mongodb: web::Data<MongoDBService>,
+    cause_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Editor).await?;
+    Ok(HttpResponse::Ok().json(mongodb.get_embed_tokens(&cause_id).await?))
+}
+
+/// Stop a donate button working, e.g. when a site is retired (cause editors or admin)
+pub async fn revoke_embed_token(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    mongodb: web::Data<MongoDBService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (cause_id, token_id) = path.into_inner();
+    cause_with_role(&auth, &cause_service, &cause_id, TeamRole::Editor).await?;
+    let token_id = ObjectId::parse_str(&token_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid embed token ID: {}", e)))?;
+    if !mongodb.revoke_embed_token(&cause_id, &token_id).await? {
+        return Err(ApiError::NotFound(format!("Embed token {} not found", token_id)));
+    }
+    info!("{} revoked embed token {} of cause {}", auth.wallet_address, token_id, cause_id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Start a donation from an embedded donate button. Unsigned: the embed token and the
+/// browser's Origin header stand in for the wallet signature.
+pub async fn create_embed_donation(
+    req: HttpRequest,
+    cause_service: web::Data<CauseService>,
+    request: web::Json<EmbedDonationRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let origin = req.headers().get(ORIGIN).and_then(|v| v.to_str().ok());
+    let (session_id, checkout_url) = cause_service.create_embed_donation(&request, origin).await?;
+    Ok(HttpResponse::Ok().json(EmbedDonationResponse { checkout_url, session_id }))
+}
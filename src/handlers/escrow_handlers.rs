@@ -0,0 +1,92 @@
+use actix_web::{web, HttpResponse};
+use crate::auth::AuthenticatedUser;
+use crate::services::{MongoDBService, EscrowService, FeatureFlagService};
+use crate::models::{ApiError, Role, EscrowStatus, EscrowOutcome};
+
+/// The vendor releases held funds to themselves, e.g. once the goods have shipped.
+/// Disputed funds can't be captured until an admin resolves the dispute.
+pub async fn capture_escrow(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
+) -> Result<HttpResponse, ApiError> {
+    let (vendor_address, payment_id) = path.into_inner();
+    let payment_id = vendor_payment(&auth, &db, &vendor_address, &payment_id).await?;
+    let payment = escrow_service.settle(&payment_id, EscrowOutcome::Capture, &[EscrowStatus::Held], &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(payment))
+}
+
+/// The vendor sends held funds back to the customer, disputed or not
+pub async fn refund_escrow(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    feature_flags.require("refunds_enabled")?;
+    let (vendor_address, payment_id) = path.into_inner();
+    let payment_id = vendor_payment(&auth, &db, &vendor_address, &payment_id).await?;
+    let payment = escrow_service.settle(&payment_id, EscrowOutcome::Refund, &[EscrowStatus::Held, EscrowStatus::Disputed], &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(payment))
+}
+
+/// Stop held funds releasing automatically while a problem is looked into
+pub async fn freeze_escrow(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let payment = escrow_service.freeze(&db.resolve_payment_code(&payment_id).await?).await?;
+    Ok(HttpResponse::Ok().json(payment))
+}
+
+/// Resolve held or frozen funds in the vendor's favour. Also retries a failed settlement,
+/// once the executor shows its transfer didn't land.
+pub async fn admin_capture_escrow(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let payment = escrow_service.settle(
+        &db.resolve_payment_code(&payment_id).await?,
+        EscrowOutcome::Capture,
+        &[EscrowStatus::Held, EscrowStatus::Disputed, EscrowStatus::Failed],
+        &auth.wallet_address,
+    ).await?;
+    Ok(HttpResponse::Ok().json(payment))
+}
+
+/// Resolve held or frozen funds in the customer's favour, or retry a failed settlement that didn't land
+pub async fn admin_refund_escrow(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Admin)?;
+    feature_flags.require("refunds_enabled")?;
+    let payment = escrow_service.settle(
+        &db.resolve_payment_code(&payment_id).await?,
+        EscrowOutcome::Refund,
+        &[EscrowStatus::Held, EscrowStatus::Disputed, EscrowStatus::Failed],
+        &auth.wallet_address,
+    ).await?;
+    Ok(HttpResponse::Ok().json(payment))
+}
+
+/// ID of the payment a code refers to, made to `vendor_address`, which the signed-in wallet must be
+async fn vendor_payment(auth: &AuthenticatedUser, db: &MongoDBService, vendor_address: &str, payment_id: &str) -> Result<String, ApiError> {
+    auth.require_self_or_admin(vendor_address)?;
+    let payment_id = db.resolve_payment_code(payment_id).await?;
+    db.get_payment(&payment_id).await?
+        .filter(|payment| payment.vendor_address == vendor_address)
+        .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", payment_id)))?;
+    Ok(payment_id)
+}
@@ -0,0 +1,30 @@
+use actix_web::{web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use crate::services::{FundingRoundService, MongoDBService};
+use crate::models::ApiError;
+
+#[derive(Debug, Deserialize)]
+pub struct FundingRoundQuery {
+    pub status: Option<String>,   // open | closed | distributed
+}
+
+/// List funding rounds, newest first
+pub async fn get_funding_rounds(
+    mongodb: web::Data<MongoDBService>,
+    query: web::Query<FundingRoundQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let rounds = mongodb.get_funding_rounds(query.status.as_deref()).await?;
+    Ok(HttpResponse::Ok().json(rounds))
+}
+
+/// A round with its allocations and payout report
+pub async fn get_funding_round(
+    funding_round_service: web::Data<FundingRoundService>,
+    round_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let object_id = ObjectId::parse_str(round_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid round ID: {}", e)))?;
+    let report = funding_round_service.report(&object_id).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -0,0 +1,110 @@
+use actix_web::{web, HttpResponse};
+use log::info;
+use mongodb::bson::oid::ObjectId;
+use crate::auth::AuthenticatedUser;
+use crate::handlers::cause_handlers::CreateDonationSessionResponse;
+use crate::models::{ApiError, CreateFundraiserRequest, UpdateFundraiserRequest, FundraiserDonationRequest, FundraiserStatus, LeaderboardQuery};
+use crate::models::payment::DonationAttribution;
+use crate::services::{CauseService, FundraiserService};
+use crate::utils::validation::Validate;
+
+/// Start a personal fundraising page for a cause (signed)
+pub async fn create_fundraiser(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    fundraiser_service: web::Data<FundraiserService>,
+    request: web::Json<CreateFundraiserRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let cause_id = ObjectId::parse_str(&request.cause_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID format: {}", e)))?;
+    let cause = cause_service.get_cause_by_id(&cause_id).await?;
+    let page = fundraiser_service.create(&cause, &request, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(page))
+}
+
+pub async fn get_fundraiser(
+    fundraiser_service: web::Data<FundraiserService>,
+    slug: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(fundraiser_service.get(&slug).await?))
+}
+
+/// Change a page, or close it with `status: "closed"` (its owner or an admin, signed)
+pub async fn update_fundraiser(
+    auth: AuthenticatedUser,
+    fundraiser_service: web::Data<FundraiserService>,
+    slug: web::Path<String>,
+    request: web::Json<UpdateFundraiserRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let page = fundraiser_service.get(&slug).await?;
+    auth.require_self_or_admin(&page.owner_address)?;
+    let page = fundraiser_service.update(page, &request).await?;
+    info!("{} updated fundraiser {}", auth.wallet_address, page.slug);
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// Raised so far through the page against its goal
+pub async fn get_fundraiser_progress(
+    fundraiser_service: web::Data<FundraiserService>,
+    slug: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let page = fundraiser_service.get(&slug).await?;
+    Ok(HttpResponse::Ok().json(fundraiser_service.progress(page).await?))
+}
+
+/// Donate to the page's cause through the page. The checkout is the cause's own, on its
+/// connected account; the page is only recorded as where the donation came from. Anyone may
+/// donate, but the wallet's saved cards are only offered when the wallet signed the request.
+pub async fn donate_to_fundraiser(
+    auth: Option<AuthenticatedUser>,
+    cause_service: web::Data<CauseService>,
+    fundraiser_service: web::Data<FundraiserService>,
+    slug: web::Path<String>,
+    request: web::Json<FundraiserDonationRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let page = fundraiser_service.get(&slug).await?;
+    if page.status != FundraiserStatus::Active {
+        return Err(ApiError::ValidationError(format!("Fundraiser {} is closed", page.slug)));
+    }
+    let cause = cause_service.get_cause_by_id(&FundraiserService::cause_id(&page)?).await?;
+    if !cause.accepts_donations() {
+        return Err(ApiError::ValidationError(format!("This cause is {} and is not accepting donations", cause.status)));
+    }
+    let connected_account_id = cause.stripe_account_id.clone()
+        .ok_or_else(|| ApiError::ValidationError("This cause does not have a connected Stripe account".to_string()))?;
+    let attribution = DonationAttribution {
+        referrer: None,
+        campaign_id: None,
+        fundraiser_id: page.id.map(|id| id.to_hex()),
+    };
+    let (session_id, checkout_url) = cause_service.create_donation_checkout_session(
+        &cause,
+        &connected_account_id,
+        request.amount_cents,
+        request.user_wallet_address.as_deref(),
+        auth.is_some_and(|auth| Some(auth.wallet_address.as_str()) == request.user_wallet_address.as_deref()),
+        &attribution,
+    ).await?;
+    Ok(HttpResponse::Ok().json(CreateDonationSessionResponse {
+        checkout_url,
+        session_id,
+        apple_pay: cause_service.payment_methods().apple_pay,
+        google_pay: cause_service.payment_methods().google_pay,
+    }))
+}
+
+/// The cause's fundraiser pages ranked by amount raised
+pub async fn get_fundraiser_leaderboard(
+    cause_service: web::Data<CauseService>,
+    fundraiser_service: web::Data<FundraiserService>,
+    cause_id: web::Path<String>,
+    query: web::Query<LeaderboardQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let cause_id = ObjectId::parse_str(cause_id.as_str())
+        .map_err(|e| ApiError::ValidationError(format!("Invalid cause ID format: {}", e)))?;
+    let cause = cause_service.get_cause_by_id(&cause_id).await?;
+    Ok(HttpResponse::Ok().json(fundraiser_service.leaderboard(&cause, query.limit).await?))
+}
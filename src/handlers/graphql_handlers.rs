@@ -0,0 +1,22 @@
+use actix_web::{web, HttpResponse};
+use async_graphql::http::GraphiQLSource;
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use crate::auth::AuthenticatedUser;
+use crate::graphql::ApiSchema;
+
+/// Run a GraphQL query. Signing the request is optional, as on the REST user endpoint,
+/// and only decides whether private fields such as email are returned.
+pub async fn graphql(
+    auth: Option<AuthenticatedUser>,
+    schema: web::Data<ApiSchema>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    schema.execute(request.into_inner().data(auth)).await.into()
+}
+
+/// GraphiQL, for exploring the schema from a browser
+pub async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/v1/graphql").finish())
+}
@@ -0,0 +1,15 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use crate::services::ExecutorClient;
+use crate::utils::circuit_breaker::BreakerState;
+
+/// Liveness plus the executor circuit breaker. Stays 200 while the breaker is open:
+/// restarting this service doesn't bring the executor back.
+pub async fn health(executor_client: web::Data<ExecutorClient>) -> HttpResponse {
+    let executor = executor_client.breaker_status();
+    let status = if executor.state == BreakerState::Closed { "ok" } else { "degraded" };
+    HttpResponse::Ok().json(json!({
+        "status": status,
+        "executor": executor
+    }))
+}
@@ -0,0 +1,90 @@
+use actix_web::{web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use std::str::FromStr;
+use crate::auth::AuthenticatedUser;
+use crate::services::{MongoDBService, InvoiceService};
+use crate::models::{ApiError, Invoice, InvoiceStatus, CreateInvoiceRequest, InvoiceQuery, PayInvoiceResponse, Role};
+
+/// Bill a customer. The signed-in wallet is the vendor, and must have the Vendor role.
+pub async fn create_invoice(
+    auth: AuthenticatedUser,
+    payload: web::Json<CreateInvoiceRequest>,
+    invoice_service: web::Data<InvoiceService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Vendor)?;
+    let invoice = invoice_service.issue(&auth.wallet_address, &payload).await?;
+    Ok(HttpResponse::Created().json(invoice))
+}
+
+/// Invoices a wallet received (`direction=incoming`, the default) or issued (`outgoing`)
+pub async fn get_user_invoices(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    query: web::Query<InvoiceQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let incoming = match query.direction.as_deref() {
+        None | Some("incoming") => true,
+        Some("outgoing") => false,
+        Some(other) => return Err(ApiError::ValidationError(format!("Invalid direction '{}', expected incoming or outgoing", other))),
+    };
+    let status = query.status.as_deref()
+        .map(InvoiceStatus::from_str)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+
+    let invoices = db.get_invoices(&wallet_address, incoming, status).await?;
+    Ok(HttpResponse::Ok().json(invoices))
+}
+
+/// An invoice, for the vendor, the customer or an admin
+pub async fn get_invoice(
+    auth: AuthenticatedUser,
+    invoice_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let invoice = load_invoice(&db, &invoice_id).await?;
+    if !auth.is_admin() && auth.wallet_address != invoice.customer_address && auth.wallet_address != invoice.vendor_address {
+        return Err(ApiError::Forbidden("Cannot view another wallet's invoice".to_string()));
+    }
+    Ok(HttpResponse::Ok().json(invoice))
+}
+
+/// Start paying an invoice. Any wallet linked to the customer's account may pay.
+pub async fn pay_invoice(
+    auth: AuthenticatedUser,
+    invoice_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    invoice_service: web::Data<InvoiceService>,
+) -> Result<HttpResponse, ApiError> {
+    let invoice = load_invoice(&db, &invoice_id).await?;
+    if invoice.customer_address != auth.wallet_address
+        && !db.wallets_share_account(&invoice.customer_address, &auth.wallet_address).await? {
+        return Err(ApiError::Forbidden("Only the invoiced customer can pay this invoice".to_string()));
+    }
+    let payment_id = invoice_service.pay(&invoice, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(PayInvoiceResponse { invoice, payment_id }))
+}
+
+/// The vendor withdraws an invoice
+pub async fn cancel_invoice(
+    auth: AuthenticatedUser,
+    invoice_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    invoice_service: web::Data<InvoiceService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_role(Role::Vendor)?;
+    let invoice = load_invoice(&db, &invoice_id).await?;
+    auth.require_self_or_admin(&invoice.vendor_address)?;
+    let invoice = invoice_service.cancel(&invoice).await?;
+    Ok(HttpResponse::Ok().json(invoice))
+}
+
+async fn load_invoice(db: &MongoDBService, invoice_id: &str) -> Result<Invoice, ApiError> {
+    let object_id = ObjectId::parse_str(invoice_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid invoice ID: {}", e)))?;
+    db.get_invoice(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Invoice {} not found", invoice_id)))
+}
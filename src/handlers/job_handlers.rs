@@ -0,0 +1,24 @@
+use actix_web::{http::header::LOCATION, web, HttpResponse};
+use crate::auth::AuthenticatedUser;
+use crate::models::{ApiError, Job, JobAccepted};
+use crate::services::JobService;
+
+/// A job's status, progress and, once finished, its result or error. Only the wallet that
+/// started it and admins can see it.
+pub async fn get_job(
+    auth: AuthenticatedUser,
+    jobs: web::Data<JobService>,
+    job_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let job = jobs.get(&job_id).await?
+        .filter(|job| auth.require_self_or_admin(&job.created_by).is_ok())
+        .ok_or_else(|| ApiError::NotFound(format!("Job {} not found", job_id)))?;
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// 202 pointing at the job a request started
+pub(crate) fn job_accepted(job: &Job) -> HttpResponse {
+    HttpResponse::Accepted()
+        .insert_header((LOCATION, job.status_url()))
+        .json(JobAccepted::from(job))
+}
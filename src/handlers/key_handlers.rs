@@ -0,0 +1,30 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use crate::config::PublishedKeys;
+use crate::models::ApiError;
+use crate::response_signing::ResponseSigner;
+use crate::utils::response_signature::RESPONSE_SIGNATURE_HEADER;
+
+/// The key responses are signed with and how to check a signature
+pub async fn get_signing_key(
+    signer: Option<web::Data<ResponseSigner>>,
+    keys: web::Data<PublishedKeys>,
+) -> Result<HttpResponse, ApiError> {
+    let signer = signer.ok_or_else(|| ApiError::NotFound("Responses are not signed on this server".to_string()))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "algorithm": "ed25519",
+        "public_key": signer.public_key(),
+        "version": keys.current_signing_key().map(|key| key.version),
+        "header": RESPONSE_SIGNATURE_HEADER,
+        "header_format": "t=<unix seconds>,key=<base58 public key>,sig=<hex signature>",
+        "signed_message": "<t>:<METHOD>:<path>:<raw response body>",
+    })))
+}
+
+/// GET /.well-known/index-wallets-keys: the vault keys on-chain transfers come from and
+/// every API signing key, current and retired, by version
+pub async fn get_published_keys(keys: web::Data<PublishedKeys>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=3600"))
+        .json(keys.get_ref())
+}
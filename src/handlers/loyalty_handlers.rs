@@ -0,0 +1,159 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use std::collections::HashSet;
+use crate::auth::AuthenticatedUser;
+use crate::services::MongoDBService;
+use crate::models::{ApiError, LoyaltyProgram, LoyaltyRedemption, UpdateLoyaltyProgramRequest, RedeemLoyaltyRequest, MAX_LOYALTY_REWARDS};
+use crate::models::payment::PaymentState;
+
+/// Most points a vendor can give per USD
+const MAX_POINTS_PER_USD: f64 = 1000.0;
+
+/// A vendor's loyalty scheme and its rewards, for customers browsing what they can earn
+pub async fn get_loyalty_program(
+    vendor_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let program = db.get_loyalty_program(&vendor_address).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Vendor {} has no loyalty program", vendor_address)))?;
+    Ok(HttpResponse::Ok().json(program))
+}
+
+/// Set up or change a vendor's loyalty scheme. Disabling it stops points accruing;
+/// customers keep the points they have.
+pub async fn update_loyalty_program(
+    auth: AuthenticatedUser,
+    vendor_address: web::Path<String>,
+    payload: web::Json<UpdateLoyaltyProgramRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    let payload = payload.into_inner();
+
+    if !payload.points_per_usd.is_finite() || !(0.0..=MAX_POINTS_PER_USD).contains(&payload.points_per_usd) {
+        return Err(ApiError::ValidationError(format!("points_per_usd must be between 0 and {}", MAX_POINTS_PER_USD)));
+    }
+    if payload.rewards.len() > MAX_LOYALTY_REWARDS {
+        return Err(ApiError::ValidationError(format!("At most {} rewards can be offered", MAX_LOYALTY_REWARDS)));
+    }
+    let mut reward_ids = HashSet::new();
+    let mut rewards = Vec::with_capacity(payload.rewards.len());
+    for mut reward in payload.rewards {
+        reward.reward_id = reward.reward_id.trim().to_lowercase();
+        reward.name = reward.name.trim().to_string();
+        let valid_id = (1..=32).contains(&reward.reward_id.len())
+            && reward.reward_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid_id {
+            return Err(ApiError::ValidationError(format!("Invalid reward_id '{}', use 1-32 letters, digits and '-'", reward.reward_id)));
+        }
+        if !reward_ids.insert(reward.reward_id.clone()) {
+            return Err(ApiError::ValidationError(format!("Reward '{}' is listed more than once", reward.reward_id)));
+        }
+        if !(1..=50).contains(&reward.name.chars().count()) {
+            return Err(ApiError::ValidationError("Reward names must be between 1 and 50 characters".to_string()));
+        }
+        if reward.points_cost <= 0 {
+            return Err(ApiError::ValidationError("Reward points_cost must be greater than zero".to_string()));
+        }
+        if !reward.discount_usd.is_finite() || reward.discount_usd <= 0.0 {
+            return Err(ApiError::ValidationError("Reward discount_usd must be greater than zero".to_string()));
+        }
+        rewards.push(reward);
+    }
+
+    let existing = db.get_loyalty_program(&vendor_address).await?;
+    let program = LoyaltyProgram {
+        id: existing.and_then(|p| p.id),
+        vendor_address: vendor_address.to_string(),
+        enabled: payload.enabled,
+        points_per_usd: payload.points_per_usd,
+        rewards,
+        updated_at: Utc::now().timestamp(),
+    };
+    db.save_loyalty_program(&program).await?;
+
+    log::info!("Loyalty program for {} saved (enabled: {}, {} points/USD, {} rewards)",
+        vendor_address, program.enabled, program.points_per_usd, program.rewards.len());
+    Ok(HttpResponse::Ok().json(program))
+}
+
+/// A customer's points with every vendor
+pub async fn get_user_loyalty(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    let accounts = db.get_customer_loyalty_accounts(&wallet_address).await?;
+    Ok(HttpResponse::Ok().json(accounts))
+}
+
+/// The vendor applies a customer's reward to a payment code before the customer pays it.
+/// The discount comes off when the customer supplements the payment, and the points are
+/// deducted once it completes.
+pub async fn redeem_loyalty_reward(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    payload: web::Json<RedeemLoyaltyRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (vendor_address, payment_id) = path.into_inner();
+    auth.require_self_or_admin(&vendor_address)?;
+    let payment_id = db.resolve_payment_code(&payment_id).await?;
+
+    let program = db.get_loyalty_program(&vendor_address).await?
+        .filter(|program| program.enabled)
+        .ok_or_else(|| ApiError::ValidationError("Loyalty program is not enabled".to_string()))?;
+    let reward = program.reward(payload.reward_id.trim()).cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("Reward {} not found", payload.reward_id)))?;
+
+    let payment = db.get_payment(&payment_id).await?
+        .filter(|payment| payment.vendor_address == vendor_address)
+        .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", payment_id)))?;
+    if payment.state(Utc::now().timestamp()) != PaymentState::Active {
+        return Err(ApiError::Conflict("Rewards can only be applied to payments that haven't been paid".to_string()));
+    }
+    if payment.customer_address.as_deref().map_or(false, |customer| customer != payload.customer_address) {
+        return Err(ApiError::ValidationError("Payment is assigned to another customer".to_string()));
+    }
+    if reward.discount_usd > payment.price_usd {
+        return Err(ApiError::ValidationError("Reward is worth more than the payment".to_string()));
+    }
+
+    let points = db.get_loyalty_account(&vendor_address, &payload.customer_address).await?
+        .map_or(0, |account| account.points);
+    if points < reward.points_cost {
+        return Err(ApiError::ValidationError(format!("Customer has {} points, the reward needs {}", points, reward.points_cost)));
+    }
+
+    let redemption = LoyaltyRedemption {
+        reward_id: reward.reward_id,
+        customer_address: payload.customer_address.clone(),
+        points_cost: reward.points_cost,
+        discount_usd: reward.discount_usd,
+        redeemed_at: Utc::now().timestamp(),
+        points_deducted: false,
+    };
+    let payment = db.set_loyalty_redemption(&payment_id, &vendor_address, &redemption).await?
+        .ok_or_else(|| ApiError::Conflict("Payment already has a reward applied or is being paid".to_string()))?;
+
+    log::info!("Vendor {} applied reward {} for {} to payment {}", vendor_address, redemption.reward_id, redemption.customer_address, payment_id);
+    Ok(HttpResponse::Ok().json(payment))
+}
+
+/// Take a reward back off a payment that hasn't been signed yet
+pub async fn remove_loyalty_reward(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (vendor_address, payment_id) = path.into_inner();
+    auth.require_self_or_admin(&vendor_address)?;
+    let payment_id = db.resolve_payment_code(&payment_id).await?;
+
+    if !db.clear_loyalty_redemption(&payment_id, &vendor_address).await? {
+        return Err(ApiError::NotFound(format!("No removable reward on payment {}", payment_id)));
+    }
+    log::info!("Vendor {} removed the loyalty reward from payment {}", vendor_address, payment_id);
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "success", "payment_id": payment_id })))
+}
@@ -0,0 +1,61 @@
+use actix_web::{web, HttpResponse};
+use log::info;
+use mongodb::bson::oid::ObjectId;
+use crate::services::MongoDBService;
+use crate::models::{ApiError, MatchingPool, MatchingPoolQuery, MatchingPoolSummary};
+
+/// Most match events returned for a pool
+const MAX_MATCH_EVENTS: i64 = 100;
+
+/// List matching pools, optionally for one cause token or status
+pub async fn get_matching_pools(
+    mongodb: web::Data<MongoDBService>,
+    query: web::Query<MatchingPoolQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let pools = mongodb.get_matching_pools(&query).await?;
+    let mut summaries = Vec::with_capacity(pools.len());
+    for pool in pools {
+        summaries.push(summarize(&mongodb, pool).await?);
+    }
+    Ok(HttpResponse::Ok().json(summaries))
+}
+
+/// Pool status: budget used and remaining, number of matched donations
+pub async fn get_matching_pool(
+    mongodb: web::Data<MongoDBService>,
+    pool_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = load_pool(&mongodb, &pool_id).await?;
+    Ok(HttpResponse::Ok().json(summarize(&mongodb, pool).await?))
+}
+
+/// Most recent donations matched from a pool
+pub async fn get_matching_pool_matches(
+    mongodb: web::Data<MongoDBService>,
+    pool_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let pool = load_pool(&mongodb, &pool_id).await?;
+    let pool_id = pool.id.ok_or_else(|| ApiError::InternalError("Matching pool has no ID".to_string()))?;
+    let events = mongodb.get_match_events(&pool_id, MAX_MATCH_EVENTS).await?;
+    info!("Found {} matches for pool {}", events.len(), pool_id);
+    Ok(HttpResponse::Ok().json(events))
+}
+
+async fn load_pool(mongodb: &MongoDBService, pool_id: &str) -> Result<MatchingPool, ApiError> {
+    let object_id = ObjectId::parse_str(pool_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid pool ID: {}", e)))?;
+    mongodb.get_matching_pool(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Matching pool {} not found", pool_id)))
+}
+
+async fn summarize(mongodb: &MongoDBService, pool: MatchingPool) -> Result<MatchingPoolSummary, ApiError> {
+    let match_count = match &pool.id {
+        Some(id) => mongodb.count_match_events(id).await?,
+        None => 0,
+    };
+    Ok(MatchingPoolSummary {
+        remaining_cents: pool.remaining_cents(),
+        match_count,
+        pool,
+    })
+}
@@ -1,18 +1,28 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use delta_executor_sdk::base::crypto::Ed25519PubKey;
 use delta_executor_sdk::base::vaults::{VaultId, TokenKind, ReadableVault};
 use delta_executor_sdk::base::verifiable::debit_allowance::{DebitAllowance, SignedDebitAllowance};
 use delta_executor_sdk::base::verifiable::VerifiableType;
 use delta_executor_sdk::base::core::Shard;
 use serde_json::json;
-use crate::models::{Message, User, CreateUserRequest, Preferences, ApiError, Payment, CreatePaymentRequest, PaymentStatus, PaymentIdResponse, SupplementPaymentRequest, SupplementPaymentResponse, TokenPayment, TransactionRecord, TokenValuation, DepositRecord};
-use crate::models::payment::{PaymentStatusResponse, ProcessSignedTransactionRequest, TransactionHistoryResponse, TransactionHistoryItem, TransactionDirection, ActivityItem};
-use crate::utils::{calculate_vendor_valuations, calculate_payment_bundle, apply_discounts_to_payment, calculate_post_payment_valuations, verify_sufficient_funds_after_discounts};
-use crate::utils::payment_code::normalize_payment_code;
-use crate::services::{MongoDBService, TokenService, WalletService};
+use crate::models::{Message, User, CreateUserRequest, Preferences, ApiError, Payment, CreatePaymentRequest, PaymentStatus, CreatePaymentBatchRequest, PaymentBatchItemResult, PaymentBatchResponse, MAX_PAYMENT_BATCH_SIZE, UpdateProfileRequest, UsernameAvailability, USERNAME_CHANGE_COOLDOWN_SECS, PaymentIdResponse, SupplementPaymentRequest, SupplementPaymentResponse, SplitLeg, PaymentAuthorization, HeldAuthorization, DEFAULT_CAPTURE_WINDOW_MINUTES, PaymentCodeNamespace, TokenPayment, OnChainAmount, TransactionRecord, TokenValuation, DepositRecord, AuditLog, AuditAction, AppliedPromo, EscrowStatus, PaymentEscrow, JobKind};
+use crate::models::payment::{PaymentStatusResponse, ProcessSignedTransactionRequest, TransactionHistoryResponse, TransactionHistoryItem, TransactionHistoryQuery, ActivityItem};
+use crate::utils::{calculate_vendor_valuations, calculate_payment_bundle, apply_discounts_to_payment, calculate_post_payment_valuations, verify_sufficient_funds_after_discounts, spread_discount, check_valuation_override, apply_valuation_overrides, reconcile_on_chain_amounts, to_on_chain_units};
+use crate::utils::payment_code::{generate_code, SCOPED_PAYMENT_ID_LENGTH};
+use crate::utils::payment_split::{split_fractions, split_bundle};
+use crate::utils::signed_payload::{find_messages, payload_hash};
+use crate::utils::profile::{validate_username, username_key, validate_display_name, validate_avatar_url, validate_email};
+use crate::services::{MongoDBService, TokenService, WalletService, VaultProvisioningService, CauseService, PushService, EscrowService, AuthorizationService, JobService, SharedEvent, SharedState};
+use crate::handlers::job_handlers::job_accepted;
+use crate::auth::AuthenticatedUser;
+use crate::config::BundlePolicy;
+use crate::utils::audit::snapshot;
+use crate::utils::validation::Validate;
+use crate::utils::redaction::redact;
 use ed25519_dalek::SigningKey;
 use chrono::Utc;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
 use rand::rngs::OsRng;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use mongodb::bson::Document;
@@ -21,6 +31,12 @@ use log;
 use std::str::FromStr;
 use std::collections::BTreeMap;
 
+/// Supplements a payer can request per minute, across every instance
+const SUPPLEMENT_LIMIT_PER_MINUTE: u32 = 30;
+
+/// Times a new payment's codes are drawn before giving up on clashes
+const PAYMENT_CODE_ATTEMPTS: u32 = 3;
+
 pub async fn hello() -> impl Responder {
     HttpResponse::Ok().json(Message {
         content: "Hello, World!".to_string(),
@@ -42,43 +58,357 @@ pub async fn echo(msg: web::Json<Message>) -> impl Responder {
 pub async fn create_user(
     user_data: web::Json<CreateUserRequest>,
     db: web::Data<MongoDBService>,
+    vault_provisioning: web::Data<VaultProvisioningService>,
 ) -> Result<HttpResponse, ApiError> {
+    user_data.validate()?;
+    
     // Use the new method that handles both user and vendor creation
     let created_user = db.create_user_with_vendor_if_needed(user_data.into_inner()).await?;
     
+    // So the new wallet's balances resolve before anything is credited to it
+    vault_provisioning.provision_in_background(created_user.wallet_address.clone());
+    
     // Return the created user (vendor record is created automatically if needed)
     Ok(HttpResponse::Created().json(created_user))
 }
 
 pub async fn get_user(
+    auth: Option<AuthenticatedUser>,
     wallet_address: web::Path<String>,
     db: web::Data<MongoDBService>,
 ) -> Result<HttpResponse, ApiError> {
     match db.get_user_by_wallet(&wallet_address).await? {
-        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        Some(mut user) => {
+            // Email is private to the user and admins
+            if !auth.map_or(false, |auth| auth.require_self_or_admin(&wallet_address).is_ok()) {
+                user.email = None;
+            }
+            Ok(HttpResponse::Ok().json(user))
+        },
         None => Err(ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)))
     }
 }
 
+/// Edit a user's profile. Omitted fields are unchanged and an empty string clears an
+/// optional one; usernames must be unique and can change once per cooldown.
+pub async fn update_user_profile(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    payload: web::Json<UpdateProfileRequest>,
+    db: web::Data<MongoDBService>,
+    cause_service: web::Data<CauseService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    let user = db.get_user_by_wallet(&wallet_address).await?
+        .filter(|user| user.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)))?;
+    let now = Utc::now().timestamp();
+    let mut set = Document::new();
+    let mut unset = Document::new();
+    
+    if let Some(username) = &payload.username {
+        let username = validate_username(username).map_err(ApiError::ValidationError)?;
+        if username != user.username {
+            cause_service.name_filter().check(&username).map_err(ApiError::ValidationError)?;
+            if let Some(changed_at) = user.username_changed_at {
+                let allowed_at = changed_at + USERNAME_CHANGE_COOLDOWN_SECS;
+                if now < allowed_at && !auth.is_admin() {
+                    let allowed_at = chrono::DateTime::from_timestamp(allowed_at, 0).unwrap_or_default();
+                    return Err(ApiError::ValidationError(format!("Username can be changed again after {}", allowed_at.to_rfc3339())));
+                }
+            }
+            if !db.is_username_available(&username, Some(&wallet_address)).await? {
+                return Err(ApiError::DuplicateError(format!("Username {} is already taken", username)));
+            }
+            set.insert("username_key", username_key(&username));
+            set.insert("username", username);
+            set.insert("username_changed_at", now);
+        }
+    }
+    
+    let optional_fields = [
+        ("display_name", payload.display_name.as_deref().map(validate_display_name)),
+        ("avatar_url", payload.avatar_url.as_deref().map(validate_avatar_url)),
+        ("email", payload.email.as_deref().map(validate_email)),
+    ];
+    for (field, value) in optional_fields {
+        match value {
+            Some(Ok(Some(value))) => { set.insert(field, value); },
+            Some(Ok(None)) => { unset.insert(field, ""); },
+            Some(Err(msg)) => return Err(ApiError::ValidationError(msg)),
+            None => {},
+        }
+    }
+    
+    let updated = db.update_user_profile(&wallet_address, set, unset).await?;
+    log::info!("Updated profile of {} (requested by {})", redact(&wallet_address), redact(&auth.wallet_address));
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+/// Whether a username is valid and free, for sign-up and profile forms
+pub async fn check_username(
+    username: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    cause_service: web::Data<CauseService>,
+) -> Result<HttpResponse, ApiError> {
+    let valid = validate_username(&username)
+        .and_then(|name| cause_service.name_filter().check(&name).map(|_| name));
+    
+    let availability = match valid {
+        Ok(name) if db.is_username_available(&name, None).await? => UsernameAvailability { username: name, available: true, reason: None },
+        Ok(name) => UsernameAvailability { username: name, available: false, reason: Some("Username is already taken".to_string()) },
+        Err(reason) => UsernameAvailability { username: username.to_string(), available: false, reason: Some(reason) },
+    };
+    Ok(HttpResponse::Ok().json(availability))
+}
+
+/// Export everything stored for a wallet as a JSON archive, built by a job whose result is
+/// the archive
+pub async fn export_user_data(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    jobs: web::Data<JobService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    log::info!("Exporting data for {} (requested by {})", redact(&wallet_address), redact(&auth.wallet_address));
+    
+    let db = db.into_inner();
+    let wallet_address = wallet_address.into_inner();
+    let job = jobs.start(JobKind::UserExport, &auth.wallet_address, move |_| async move {
+        let export = db.export_user_data(&wallet_address).await?;
+        if export.user.is_none() && export.payments.is_empty() && export.deposits.is_empty() {
+            return Err(ApiError::NotFound(format!("No data stored for wallet address {}", wallet_address)));
+        }
+        serde_json::to_value(export).map_err(|e| ApiError::InternalError(e.to_string()))
+    }).await?;
+    Ok(job_accepted(&job))
+}
+
+/// Anonymize a user's personal data while keeping financial records intact
+pub async fn delete_user(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    log::info!("Anonymizing user {} (requested by {})", redact(&wallet_address), redact(&auth.wallet_address));
+    
+    if db.get_user_by_wallet(&wallet_address).await?.is_none() {
+        return Err(ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)));
+    }
+    
+    let summary = db.anonymize_user(&wallet_address).await?;
+    
+    // Only record which account was erased, not the data that was removed
+    let entry = AuditLog::new(
+        &auth.wallet_address,
+        AuditAction::UserAnonymized,
+        "user",
+        &wallet_address,
+        None,
+        snapshot(&summary),
+    );
+    if let Err(e) = db.record_audit_log(entry).await {
+        log::error!("Failed to record audit log: {:?}", e);
+    }
+    
+    log::info!("Anonymized user {}: {:?}", redact(&wallet_address), summary);
+    Ok(HttpResponse::Ok().json(summary))
+}
+
 
 pub async fn create_payment(
+    auth: Option<AuthenticatedUser>,
     payment_request: web::Json<CreatePaymentRequest>,
     db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
 ) -> Result<HttpResponse, ApiError> {
-    log::info!("Received payment request: {:?}", payment_request);
+    let created = create_payment_code(&payment_request, auth.as_ref(), &db, &escrow_service).await?;
+    Ok(HttpResponse::Created().json(created))
+}
 
-    // Create payment with generated ID and current timestamp
-    let payment_id = db.generate_payment_id();
-    log::info!("Generated payment ID: {}", payment_id);
+/// Store a new payment code for a vendor. Shared by the REST and gRPC APIs. Anyone can
+/// create one at the vendor's own valuations, but overriding them takes the vendor's or an
+/// admin's signature.
+pub async fn create_payment_code(
+    payment_request: &CreatePaymentRequest,
+    auth: Option<&AuthenticatedUser>,
+    db: &MongoDBService,
+    escrow_service: &EscrowService,
+) -> Result<PaymentIdResponse, ApiError> {
+    log::info!("Creating payment of ${} for vendor {}", payment_request.price_usd, redact(&payment_request.vendor_address));
 
-    
-    let payment = Payment {
-        id: None,
-        payment_id: payment_id.clone(),
-        vendor_address: payment_request.vendor_address.clone(),
+    payment_request.validate()?;
+    if payment_request.vendor_valuations.as_ref().is_some_and(|overrides| !overrides.is_empty()) {
+        auth.ok_or_else(|| ApiError::Unauthorized("Overriding the vendor's valuations requires a signed request".to_string()))?
+            .require_self_or_admin(&payment_request.vendor_address)?;
+    }
+    check_valuation_overrides(db, payment_request.vendor_valuations.as_deref()).await?;
+    let escrow = if payment_request.escrow {
+        Some(escrow_service.terms(payment_request.escrow_hold_hours)?)
+    } else {
+        None
+    };
+
+    let namespace = payment_code_namespace(db, &payment_request.vendor_address).await?;
+    let mut payment = new_payment(String::new(), payment_request, escrow, None);
+
+    // Codes are random, so a clash with an existing payment is possible; draw new ones and retry
+    for attempt in 1..=PAYMENT_CODE_ATTEMPTS {
+        assign_payment_code(db, &mut payment, namespace.as_ref());
+        log::info!("Generated payment ID: {}", payment.payment_id);
+        match db.create_payment(payment.clone()).await {
+            Ok(_) => break,
+            Err(ApiError::Conflict(_)) if attempt < PAYMENT_CODE_ATTEMPTS => {
+                log::warn!("Payment code clash for vendor {}, retrying with a new code", redact(&payment_request.vendor_address));
+            },
+            Err(e) => {
+                log::error!("Failed to create payment: {:?}", e);
+                return Err(e);
+            }
+        }
+    }
+
+    // Store the payment but return ID and other requested fields
+    log::info!("Payment created successfully with ID: {}", payment.payment_id);
+    Ok(PaymentIdResponse { 
+        payment_code: payment.payment_code(),
+        payment_id: payment.payment_id,
         vendor_name: payment_request.vendor_name.clone(),
-        recepient_verified: payment_request.is_verified, 
         price_usd: payment_request.price_usd,
+    })
+}
+
+/// The vendor's own payment code namespace, if they have claimed one
+async fn payment_code_namespace(db: &MongoDBService, vendor_address: &str) -> Result<Option<PaymentCodeNamespace>, ApiError> {
+    Ok(db.get_user_by_wallet(vendor_address).await?.and_then(|user| user.payment_code_namespace))
+}
+
+/// Draw a new payment's codes: a global five-character code, or for vendors with their own
+/// namespace a short code in it and a global ID too long to guess
+fn assign_payment_code(db: &MongoDBService, payment: &mut Payment, namespace: Option<&PaymentCodeNamespace>) {
+    match namespace {
+        Some(namespace) => {
+            payment.payment_id = generate_code(SCOPED_PAYMENT_ID_LENGTH);
+            payment.vendor_slug = Some(namespace.slug.clone());
+            payment.short_code = Some(generate_code(namespace.code_length));
+        },
+        None => payment.payment_id = db.generate_payment_id(),
+    }
+}
+
+
+/// Create many payment codes for the signed-in vendor at once, e.g. invoices to a
+/// customer list. Invalid payments are reported per item and the rest are created,
+/// unless `atomic` is set, in which case nothing is created if any is invalid. The
+/// valid payments are always inserted together or not at all.
+pub async fn create_payment_batch(
+    auth: AuthenticatedUser,
+    batch: web::Json<CreatePaymentBatchRequest>,
+    db: web::Data<MongoDBService>,
+    escrow_service: web::Data<EscrowService>,
+) -> Result<HttpResponse, ApiError> {
+    if batch.payments.is_empty() || batch.payments.len() > MAX_PAYMENT_BATCH_SIZE {
+        return Err(ApiError::ValidationError(format!("A batch must have between 1 and {} payments", MAX_PAYMENT_BATCH_SIZE)));
+    }
+
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    let mut results = Vec::with_capacity(batch.payments.len());
+    let mut payments = Vec::new();
+    for (index, request) in batch.payments.iter().enumerate() {
+        let payment = check_batch_payment(&auth, request, &db, &escrow_service).await
+            .map(|escrow| new_payment(String::new(), request, escrow, Some(&batch_id)));
+        match payment {
+            Ok(payment) => {
+                payments.push((index, payment));
+                results.push(PaymentBatchItemResult { index, payment_id: None, payment_code: None, error: None });
+            },
+            Err(e) => results.push(PaymentBatchItemResult { index, payment_id: None, payment_code: None, error: Some(e.to_string()) }),
+        }
+    }
+    let failed = batch.payments.len() - payments.len();
+    if payments.is_empty() || (batch.atomic && failed > 0) {
+        return Ok(HttpResponse::BadRequest().json(PaymentBatchResponse { batch_id: None, created: 0, failed, results }));
+    }
+
+    let mut namespaces = HashMap::new();
+    for (_, payment) in &payments {
+        if let Entry::Vacant(entry) = namespaces.entry(payment.vendor_address.clone()) {
+            entry.insert(payment_code_namespace(&db, &payment.vendor_address).await?);
+        }
+    }
+
+    // Codes are random, so a clash with an existing payment is possible; draw new ones and retry
+    for attempt in 1..=PAYMENT_CODE_ATTEMPTS {
+        let mut codes = HashSet::new();
+        for (_, payment) in payments.iter_mut() {
+            let namespace = namespaces.get(&payment.vendor_address).and_then(Option::as_ref);
+            assign_payment_code(&db, payment, namespace);
+            while !codes.insert(payment.payment_code()) {
+                assign_payment_code(&db, payment, namespace);
+            }
+        }
+        let batch_payments: Vec<Payment> = payments.iter().map(|(_, payment)| payment.clone()).collect();
+        match db.create_payment_batch(&batch_id, &batch_payments).await {
+            Ok(()) => break,
+            Err(ApiError::Conflict(_)) if attempt < PAYMENT_CODE_ATTEMPTS => {
+                log::warn!("Payment code clash in batch {}, retrying with new codes", batch_id);
+            },
+            Err(e) => return Err(e),
+        }
+    }
+
+    for (index, payment) in &payments {
+        results[*index].payment_id = Some(payment.payment_id.clone());
+        results[*index].payment_code = Some(payment.payment_code());
+    }
+    log::info!("Created {} payments in batch {} for {} ({} invalid)", payments.len(), batch_id, redact(&auth.wallet_address), failed);
+    Ok(HttpResponse::Created().json(PaymentBatchResponse {
+        batch_id: Some(batch_id),
+        created: payments.len(),
+        failed,
+        results,
+    }))
+}
+
+/// Validate one payment of a batch, returning its escrow terms if it asked for escrow
+async fn check_batch_payment(auth: &AuthenticatedUser, request: &CreatePaymentRequest, db: &MongoDBService, escrow_service: &EscrowService) -> Result<Option<PaymentEscrow>, ApiError> {
+    auth.require_self_or_admin(&request.vendor_address)?;
+    request.validate()?;
+    check_valuation_overrides(db, request.vendor_valuations.as_deref()).await?;
+    if request.escrow {
+        Ok(Some(escrow_service.terms(request.escrow_hold_hours)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Per-payment valuations must name known tokens, once each, and stay within sane bounds
+/// of the token's market valuation
+async fn check_valuation_overrides(db: &MongoDBService, overrides: Option<&[TokenValuation]>) -> Result<(), ApiError> {
+    let mut seen = HashSet::new();
+    for valuation in overrides.unwrap_or_default() {
+        if !seen.insert(valuation.symbol.as_str()) {
+            return Err(ApiError::ValidationError(format!("Token {} has more than one valuation", valuation.symbol)));
+        }
+        let token = db.get_token_by_symbol(&valuation.symbol).await?
+            .ok_or_else(|| ApiError::ValidationError(format!("Unknown token {}", valuation.symbol)))?;
+        check_valuation_override(valuation.valuation, token.market_valuation)
+            .map_err(|e| ApiError::ValidationError(format!("{}: {}", valuation.symbol, e)))?;
+    }
+    Ok(())
+}
+
+/// A new payment code waiting for a customer
+fn new_payment(payment_id: String, request: &CreatePaymentRequest, escrow: Option<PaymentEscrow>, batch_id: Option<&str>) -> Payment {
+    Payment {
+        id: None,
+        payment_id,
+        vendor_address: request.vendor_address.clone(),
+        vendor_name: request.vendor_name.clone(),
+        recepient_verified: request.is_verified, 
+        price_usd: request.price_usd,
         customer_address: None,
         customer_username: None,
         status: PaymentStatus::Created,
@@ -86,57 +416,83 @@ pub async fn create_payment(
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64,
-        vendor_valuations: payment_request.vendor_valuations.clone(),
+        vendor_valuations: request.vendor_valuations.clone(),
         discount_consumption: None,
         computed_payment: None,
         initial_payment_bundle: None,
-    };
-
-    log::info!("Creating payment in database: {:?}", payment);
-
-    // Store the payment but return ID and other requested fields
-    match db.create_payment(payment).await {
-        Ok(_) => {
-            log::info!("Payment created successfully with ID: {}", payment_id);
-            Ok(HttpResponse::Created().json(PaymentIdResponse { 
-                payment_id,
-                vendor_name: payment_request.vendor_name.clone(),
-                price_usd: payment_request.price_usd,
-            }))
-        },
-        Err(e) => {
-            log::error!("Failed to create payment: {:?}", e);
-            Err(e)
-        }
+        executor_tx_id: None,
+        submitted_at: None,
+        finality_checked_at: None,
+        failure_reason: None,
+        payment_request_id: None,
+        loyalty_redemption: None,
+        loyalty_points_earned: None,
+        promo: None,
+        escrow,
+        schedule_id: None,
+        batch_id: batch_id.map(str::to_string),
+        invoice_id: None,
+        valuation_overrides: request.vendor_valuations.clone(),
+        budget_consumed: false,
+        splits: request.splits.clone(),
+        split_legs: Vec::new(),
+        authorization: request.manual_capture.then(|| {
+            PaymentAuthorization::new(request.capture_window_minutes.unwrap_or(DEFAULT_CAPTURE_WINDOW_MINUTES))
+        }),
+        vendor_slug: None,
+        short_code: None,
     }
 }
 
-
 pub async fn supplement_transaction(
+    req: HttpRequest,
+    auth: Option<AuthenticatedUser>,
     payment_id: web::Path<String>,
     supplement_data: web::Json<SupplementPaymentRequest>,
     db: web::Data<MongoDBService>,
     wallet_service: web::Data<WalletService>,
+    bundle_policy: web::Data<BundlePolicy>,
+    shared_state: web::Data<SharedState>,
 ) -> Result<HttpResponse, ApiError> {
-    // Normalize the payment code to handle common input errors
-    let normalized_payment_id = normalize_payment_code(&payment_id);
-    
-    log::info!(
-        "Supplementing transaction. Payment ID: {} (normalized: {}), Payer Address: {}", 
-        payment_id, 
-        normalized_payment_id,
-        supplement_data.payer_address
-    );
+    // The payer address in the body is whatever the caller says, so it can't be what's limited
+    let client = match auth {
+        Some(auth) => format!("wallet:{}", auth.wallet_address),
+        None => format!("ip:{}", req.connection_info().realip_remote_addr().unwrap_or("unknown")),
+    };
+    let response = supplement_payment(&payment_id, &supplement_data, &client, &db, &wallet_service, &bundle_policy, &shared_state).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Assign the payer to a payment and work out the bundle they sign. Shared by the REST
+/// and gRPC APIs, which rate limit it per `client`: the signing wallet, or the caller's IP.
+pub async fn supplement_payment(
+    payment_id: &str,
+    supplement_data: &SupplementPaymentRequest,
+    client: &str,
+    db: &MongoDBService,
+    wallet_service: &WalletService,
+    bundle_policy: &BundlePolicy,
+    shared_state: &SharedState,
+) -> Result<SupplementPaymentResponse, ApiError> {
+    shared_state.check_rate_limit(&format!("supplement:{}", client), SUPPLEMENT_LIMIT_PER_MINUTE, 60).await?;
+    
+    // Normalize the payment code to handle common input errors; vendor-scoped codes are looked up
+    let normalized_payment_id = db.resolve_payment_code(payment_id).await?;
+    
+    log::info!("Supplementing payment {} for payer {}", normalized_payment_id, redact(&supplement_data.payer_address));
+    
+    // A held authorization is captured against the payer's next nonce, and anything else
+    // they sign would take it; they can pay again once it's captured or voided
+    if db.has_pending_capture(&supplement_data.payer_address).await? {
+        return Err(ApiError::Conflict("You have an authorized payment awaiting capture; it must be captured or voided before paying again".to_string()));
+    }
     
     let payment = match db.update_payment_with_payer(
         &normalized_payment_id,
         supplement_data.payer_address.clone(),
         supplement_data.payer_username.clone(),
     ).await {
-        Ok(payment) => {
-            log::info!("Successfully updated payment: {:?}", payment);
-            payment
-        },
+        Ok(payment) => payment,
         Err(e) => {
             log::error!("Failed to update payment: {:?}", e);
             return Err(e);
@@ -152,21 +508,57 @@ pub async fn supplement_transaction(
         }
     };
 
-    log::info!("Vendor preferences: {:?}", vendor_preferences);
-    log::info!("Payer balances: {:?}", supplement_data.payer_balances);
-    log::info!("Payment amount: {}", payment.price_usd);
+    // A promo code comes off the price before the vendor's token discounts are worked out.
+    // Its use is only counted once the payment completes.
+    let promo = match supplement_data.promo_code.as_deref().map(str::trim).filter(|code| !code.is_empty()) {
+        Some(code) => {
+            let promo_code = db.get_promo_code(&payment.vendor_address, code).await?
+                .ok_or_else(|| ApiError::ValidationError(format!("Unknown promo code {}", code)))?;
+            promo_code.check_usable(Utc::now().timestamp()).map_err(ApiError::ValidationError)?;
+            Some(AppliedPromo {
+                code: promo_code.code.clone(),
+                discount_usd: promo_code.discount_for(payment.price_usd),
+                consumed: false,
+            })
+        },
+        None => None,
+    };
+    let price_usd = payment.price_usd - promo.as_ref().map_or(0.0, |promo| promo.discount_usd);
+
+    log::debug!("Payment amount: {} (after promo: {}) across {} payer balances", payment.price_usd, price_usd, supplement_data.payer_balances.len());
     
-    let (vendor_valuations, discount_consumption) = 
-        calculate_vendor_valuations(&vendor_preferences, &supplement_data.payer_balances, payment.price_usd);
+    // The payer's own say in which tokens to spend
+    let spending_weights = db.get_user_by_wallet(&supplement_data.payer_address).await?
+        .map(|payer| payer.spending_weights)
+        .unwrap_or_default();
+    
+    let (mut vendor_valuations, mut discount_consumption) = 
+        calculate_vendor_valuations(&vendor_preferences, &supplement_data.payer_balances, &spending_weights, price_usd);
+    
+    // The vendor's valuations for this payment win over their preferences, without
+    // touching their discount budgets
+    let mut override_consumption = match &payment.valuation_overrides {
+        Some(overrides) => apply_valuation_overrides(
+            &mut vendor_valuations,
+            &mut discount_consumption,
+            overrides,
+            &supplement_data.payer_balances,
+            &spending_weights,
+            price_usd,
+        ),
+        None => Vec::new(),
+    };
     
-    log::info!("Calculated vendor valuations: {:?}", vendor_valuations);
-    log::info!("Calculated discount consumption: {:?}", discount_consumption);
+    log::debug!("Calculated vendor valuations: {:?}", vendor_valuations);
+    log::debug!("Calculated discount consumption: {:?}", discount_consumption);
 
     // Calculate proportional payments before discounts
     let initial_payment_bundle = match calculate_payment_bundle(
         &supplement_data.payer_balances,
         &vendor_valuations,
-        payment.price_usd,
+        &spending_weights,
+        bundle_policy.dust_threshold,
+        price_usd,
     ) {
         Ok(bundle) => bundle,
         Err(e) => {
@@ -179,6 +571,10 @@ pub async fn supplement_transaction(
         }
     };
     
+    // Tokens folded away as dust aren't paid, so there's nothing to discount on them
+    discount_consumption.retain(|d| initial_payment_bundle.iter().any(|p| p.token_key == d.token_key));
+    override_consumption.retain(|d| initial_payment_bundle.iter().any(|p| p.token_key == d.token_key));
+    
     // Clone for final payment calculation
     let mut payment_bundle = initial_payment_bundle.clone();
     
@@ -192,11 +588,40 @@ pub async fn supplement_transaction(
         return Err(ApiError::InternalError("Failed to apply discounts".to_string()));
     }
 
+    // Likewise for the vendor's per-payment valuations, kept out of the stored consumption
+    if let Err(e) = apply_discounts_to_payment(
+        &mut payment_bundle,
+        &override_consumption,
+        &supplement_data.payer_balances,
+    ) {
+        log::error!("Failed to apply valuation overrides: {}", e);
+        return Err(ApiError::InternalError("Failed to apply discounts".to_string()));
+    }
+
+    // A loyalty reward the vendor applied comes off on top of the vendor's token discounts.
+    // It isn't part of the stored consumption, which draws down the vendor's budgets.
+    if let Some(redemption) = &payment.loyalty_redemption {
+        if redemption.customer_address != supplement_data.payer_address {
+            return Err(ApiError::ValidationError("This payment has a loyalty reward for another customer".to_string()));
+        }
+        let points = db.get_loyalty_account(&payment.vendor_address, &redemption.customer_address).await?
+            .map_or(0, |account| account.points);
+        if points < redemption.points_cost {
+            return Err(ApiError::ValidationError("Not enough loyalty points for the applied reward".to_string()));
+        }
+        let reward_consumption = spread_discount(&payment_bundle, &supplement_data.payer_balances, redemption.discount_usd);
+        if let Err(e) = apply_discounts_to_payment(&mut payment_bundle, &reward_consumption, &supplement_data.payer_balances) {
+            log::error!("Failed to apply loyalty reward: {}", e);
+            return Err(ApiError::InternalError("Failed to apply discounts".to_string()));
+        }
+        log::info!("Applied loyalty reward {} (${}) to payment {}", redemption.reward_id, redemption.discount_usd, payment.payment_id);
+    }
+
     // Verify sufficient funds after discounts/premiums
     let actual_cost = match verify_sufficient_funds_after_discounts(
         &payment_bundle,
         &supplement_data.payer_balances,
-        payment.price_usd,
+        price_usd,
     ) {
         Ok(cost) => {
             log::info!("Payment feasible. Original price: ${:.2}, Actual cost after adjustments: ${:.2}", 
@@ -209,13 +634,24 @@ pub async fn supplement_transaction(
         }
     };
 
+    // Round to what will be signed, keeping the bundle worth what it was at the vendor's valuations
+    let on_chain_amounts: Vec<OnChainAmount> = reconcile_on_chain_amounts(&mut payment_bundle, &supplement_data.payer_balances, &vendor_valuations)
+        .into_iter()
+        .zip(&payment_bundle)
+        .map(|(amount, payment)| OnChainAmount {
+            token_key: payment.token_key.clone(),
+            symbol: payment.symbol.clone(),
+            amount,
+        })
+        .collect();
+
     // Clone for response before moving into database update
     let vendor_valuations_for_response = vendor_valuations.clone();
     let discount_consumption_for_response = discount_consumption.clone();
 
     // Update payment with calculated data (including initial bundle)
     if let Err(e) = db.update_payment_with_calculations(
-        &payment_id,
+        &normalized_payment_id,
         vendor_valuations,
         discount_consumption,
         payment_bundle.clone(),
@@ -224,18 +660,28 @@ pub async fn supplement_transaction(
         log::error!("Failed to update payment with calculations: {:?}", e);
         return Err(e);
     }
+    db.set_payment_promo(&normalized_payment_id, promo.as_ref()).await?;
 
-    // Generate unsigned transaction
+    // A split payment pays each recipient their share of every token, in whole units
+    let payment = if payment.splits.is_empty() {
+        payment
+    } else {
+        let fractions = split_fractions(payment.price_usd, &payment.splits).map_err(ApiError::ValidationError)?;
+        let split_legs = split_bundle(&payment_bundle, &payment.vendor_address, &payment.splits, &fractions);
+        db.set_payment_split_legs(&normalized_payment_id, &split_legs).await?;
+        Payment { split_legs, ..payment }
+    };
+
+    // Generate unsigned transaction; escrowed payments are paid into the escrow vault
     let unsigned_transaction = match generate_unsigned_transaction(
-        wallet_service.get_ref(),
+        wallet_service,
         &supplement_data.payer_address,
-        &payment.vendor_address,
-        &payment_bundle
+        &credited_legs(&payment, &payment_bundle),
     ).await {
         Ok(tx) => tx,
         Err(e) => {
             log::error!("Failed to generate unsigned transaction: {}", e);
-            return Err(ApiError::InternalError(format!("Failed to generate transaction: {}", e)));
+            return Err(e);
         }
     };
 
@@ -249,29 +695,115 @@ pub async fn supplement_transaction(
         price_usd: payment.price_usd,
         created_at: payment.created_at,
         payment_bundle, // Easy display for UI 
+        on_chain_amounts,
         unsigned_transaction,
         vendor_valuations: Some(vendor_valuations_for_response),
         discount_consumption: Some(discount_consumption_for_response),
+        promo,
+        split_legs: payment.split_legs,
     };
 
-    log::info!("Returning calculated payment: {:?}", response);
-    Ok(HttpResponse::Ok().json(response))
+    log::info!("Calculated payment {}: {} tokens, ${:.2}", response.payment_id, response.payment_bundle.len(), actual_cost);
+    Ok(response)
 }
 
 pub async fn process_signed_transaction(
     payment_id: web::Path<String>, 
     supplement_data: web::Json<ProcessSignedTransactionRequest>, 
     db: web::Data<MongoDBService>,
-    wallet_service: web::Data<WalletService>
+    wallet_service: web::Data<WalletService>,
+    push_service: web::Data<PushService>,
+    escrow_service: web::Data<EscrowService>,
+    shared_state: web::Data<SharedState>,
 ) -> Result<HttpResponse, ApiError> { 
+    let submitted = submit_signed_transaction(&payment_id, &supplement_data, &db, &wallet_service, &push_service, &escrow_service, &shared_state).await?;
+    match submitted.status_update_error {
+        None => Ok(HttpResponse::Ok().json(submitted.payment)),
+        // Transaction was submitted successfully, but payment status update failed
+        // Return partial success with transaction details from request data
+        Some(error) => Ok(HttpResponse::Ok().json(json!({
+            "status": "partial_success",
+            "message": "Transaction submitted successfully but payment status update failed",
+            "error": error,
+            "transaction": submitted.payment
+        }))),
+    }
+}
+
+/// The vendor captures an authorized two-phase payment, submitting the payer's held allowances
+pub async fn capture_payment(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+    push_service: web::Data<PushService>,
+    shared_state: web::Data<SharedState>,
+) -> Result<HttpResponse, ApiError> {
+    let payment = db.get_payment_by_id(&db.resolve_payment_code(&payment_id).await?).await?;
+    auth.require_self_or_admin(&payment.vendor_address)?;
+    let submitted = capture_authorized_payment(&payment, &db, &wallet_service, &push_service, &shared_state).await?;
+    match submitted.status_update_error {
+        None => Ok(HttpResponse::Ok().json(submitted.payment)),
+        Some(error) => Ok(HttpResponse::Ok().json(json!({
+            "status": "partial_success",
+            "message": "Transaction submitted successfully but payment status update failed",
+            "error": error,
+            "transaction": submitted.payment
+        }))),
+    }
+}
+
+/// The vendor voids an authorized two-phase payment; the payer's held allowances are dropped
+pub async fn void_payment(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    authorization_service: web::Data<AuthorizationService>,
+) -> Result<HttpResponse, ApiError> {
+    let payment = db.get_payment_by_id(&db.resolve_payment_code(&payment_id).await?).await?;
+    auth.require_self_or_admin(&payment.vendor_address)?;
+    let voided = authorization_service.void(&payment.payment_id, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(voided))
+}
+
+/// A signed transaction accepted by the executor. `status_update_error` is set when the
+/// payment's new status couldn't be recorded, in which case `payment` still says Calculated.
+pub struct SubmittedPayment {
+    pub payment: PaymentStatusResponse,
+    pub status_update_error: Option<String>,
+}
+
+/// Check a payer's signed allowances against the supplemented payment and submit them to
+/// the executor. Shared by the REST and gRPC APIs.
+pub async fn submit_signed_transaction(
+    payment_id: &str,
+    supplement_data: &ProcessSignedTransactionRequest,
+    db: &MongoDBService,
+    wallet_service: &WalletService,
+    push_service: &PushService,
+    escrow_service: &EscrowService,
+    shared_state: &SharedState,
+) -> Result<SubmittedPayment, ApiError> {
     log::info!("Processing signed transaction for payment ID: {}", payment_id);
-    log::info!("Full request body: {:?}", supplement_data);
     
-    // Verify payment ID matches
-    if payment_id.to_string() != supplement_data.payment_id {
+    // Verify payment ID matches; either may be given as a payment code
+    let payment_id = db.resolve_payment_code(payment_id).await?;
+    if payment_id != db.resolve_payment_code(&supplement_data.payment_id).await? {
         log::error!("Payment ID mismatch: {} vs {}", payment_id, supplement_data.payment_id);
         return Err(ApiError::ValidationError("Payment ID mismatch".to_string()));
     }
+    let payment_id = payment_id.as_str();
+    
+    // The signed allowance must pay exactly what supplement computed, from the recorded
+    // customer to the vendor; the client's copy of the bundle is only echoed back
+    let stored_payment = db.get_payment(payment_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", payment_id)))?;
+    check_signed_allowances(&supplement_data.signed_transaction, &stored_payment)?;
+    
+    // An escrowed payment must not be paid to the vendor directly
+    if stored_payment.escrow.is_some() {
+        escrow_service.check_signed_transaction(&supplement_data.signed_transaction)?;
+    }
     
     // Submit the signed transaction to the executor
     let signed_debit_allowances = match serde_json::from_str::<Vec<SignedDebitAllowance>>(&supplement_data.signed_transaction) {
@@ -282,6 +814,176 @@ pub async fn process_signed_transaction(
         }
     };
     
+    // Two-phase payments are held until the vendor captures them
+    if let Some(authorization) = &stored_payment.authorization {
+        return hold_signed_transaction(payment_id, supplement_data, &stored_payment, authorization, db, shared_state).await;
+    }
+    
+    submit_to_executor(supplement_data, &stored_payment, signed_debit_allowances, db, wallet_service, push_service, shared_state).await
+}
+
+/// Hold a two-phase payment's checked allowances, unsubmitted, for the capture window.
+/// Nothing is reserved on chain: the payer's other supplements are refused meanwhile so the
+/// allowances' nonce stays free, but funds they move elsewhere can still leave the
+/// authorization uncapturable.
+async fn hold_signed_transaction(
+    payment_id: &str,
+    supplement_data: &ProcessSignedTransactionRequest,
+    stored_payment: &Payment,
+    authorization: &PaymentAuthorization,
+    db: &MongoDBService,
+    shared_state: &SharedState,
+) -> Result<SubmittedPayment, ApiError> {
+    let now = Utc::now().timestamp();
+    let capture_nonce = signed_nonce(&supplement_data.signed_transaction)
+        .ok_or_else(|| ApiError::ValidationError("Signed transaction has no debit allowances".to_string()))?;
+    let held = HeldAuthorization {
+        id: None,
+        payment_id: payment_id.to_string(),
+        signed_transaction: supplement_data.signed_transaction.clone(),
+        created_at: now,
+    };
+    db.hold_authorization(&held).await?;
+    
+    let expires_at = now + authorization.capture_window_minutes * 60;
+    let authorized = db.authorize_payment(payment_id, now, expires_at, capture_nonce).await;
+    if !matches!(authorized, Ok(true)) {
+        if let Err(e) = db.take_held_authorization(payment_id).await {
+            log::error!("Failed to drop held allowances of payment {}: {}", payment_id, e);
+        }
+        return Err(authorized.err().unwrap_or_else(|| ApiError::Conflict(format!("Payment is {}", stored_payment.status))));
+    }
+    
+    log::info!("Authorized payment {}, capturable until {}", payment_id, expires_at);
+    shared_state.publish(SharedEvent::PaymentStatus { payment_id: payment_id.to_string(), status: PaymentStatus::Authorized });
+    Ok(SubmittedPayment {
+        payment: PaymentStatusResponse {
+            payment_id: payment_id.to_string(),
+            vendor_address: stored_payment.vendor_address.clone(),
+            vendor_name: stored_payment.vendor_name.clone(),
+            customer_address: stored_payment.customer_address.clone(),
+            status: PaymentStatus::Authorized,
+            price_usd: stored_payment.price_usd,
+            created_at: stored_payment.created_at,
+            payment_bundle: Some(supplement_data.payment_bundle.clone()),
+            computed_payment: stored_payment.computed_payment.clone(),
+            vendor_valuations: stored_payment.vendor_valuations.clone(),
+            discount_consumption: stored_payment.discount_consumption.clone(),
+            executor_tx_id: None,
+        },
+        status_update_error: None,
+    })
+}
+
+/// The vendor captures an authorized two-phase payment within its window: the held
+/// allowances are submitted like any signed payment. If the executor refuses them they're
+/// held again, so capture can be retried or voided until the window closes. If it doesn't
+/// answer, the payment is already Submitted for the finality poller and stays that way.
+pub async fn capture_authorized_payment(
+    payment: &Payment,
+    db: &MongoDBService,
+    wallet_service: &WalletService,
+    push_service: &PushService,
+    shared_state: &SharedState,
+) -> Result<SubmittedPayment, ApiError> {
+    let payment_id = payment.payment_id.as_str();
+    if payment.status != PaymentStatus::Authorized {
+        return Err(ApiError::Conflict(format!("Payment is {}", payment.status)));
+    }
+    let expires_at = payment.authorization.as_ref().and_then(|authorization| authorization.expires_at).unwrap_or(0);
+    if Utc::now().timestamp() > expires_at {
+        return Err(ApiError::Conflict("The authorization has expired".to_string()));
+    }
+    let held = db.take_held_authorization(payment_id).await?
+        .ok_or_else(|| ApiError::Conflict("The payment is already being captured or voided".to_string()))?;
+    
+    let result = async {
+        let signed_debit_allowances = serde_json::from_str::<Vec<SignedDebitAllowance>>(&held.signed_transaction)
+            .map_err(|e| ApiError::InternalError(format!("Invalid held transaction: {}", e)))?;
+        let request = ProcessSignedTransactionRequest {
+            payment_id: payment_id.to_string(),
+            signed_transaction: held.signed_transaction.clone(),
+            vendor_address: payment.vendor_address.clone(),
+            vendor_name: payment.vendor_name.clone(),
+            payer_address: payment.customer_address.clone().unwrap_or_default(),
+            price_usd: payment.price_usd,
+            payment_bundle: payment.computed_payment.clone().unwrap_or_default(),
+            computed_payment: payment.computed_payment.clone(),
+            vendor_valuations: payment.vendor_valuations.clone(),
+            discount_consumption: payment.discount_consumption.clone(),
+        };
+        submit_to_executor(&request, payment, signed_debit_allowances, db, wallet_service, push_service, shared_state).await
+    }.await;
+    
+    match &result {
+        Ok(_) => {
+            if let Err(e) = db.mark_payment_captured(payment_id).await {
+                log::error!("Failed to record capture of payment {}: {}", payment_id, e);
+            }
+        },
+        Err(e) => {
+            let still_authorized = db.get_payment(payment_id).await
+                .map(|payment| payment.map_or(false, |payment| payment.status == PaymentStatus::Authorized))
+                .unwrap_or(true);
+            if still_authorized {
+                if let Err(e) = db.hold_authorization(&held).await {
+                    log::error!("Failed to hold allowances of payment {} again after a failed capture: {}", payment_id, e);
+                }
+                // The payer spent the nonce or the funds since authorizing
+                if let ApiError::Conflict(_) | ApiError::InsufficientBalance(_) = e {
+                    return Err(ApiError::Conflict(format!("The authorization can no longer be captured ({}); void it and ask the customer to pay again", e)));
+                }
+            }
+        },
+    }
+    result
+}
+
+/// Submit checked allowances to the executor and track the payment until finality
+async fn submit_to_executor(
+    supplement_data: &ProcessSignedTransactionRequest,
+    stored_payment: &Payment,
+    signed_debit_allowances: Vec<SignedDebitAllowance>,
+    db: &MongoDBService,
+    wallet_service: &WalletService,
+    push_service: &PushService,
+    shared_state: &SharedState,
+) -> Result<SubmittedPayment, ApiError> {
+    let payment_id = stored_payment.payment_id.as_str();
+    let payment_bundle = stored_payment.computed_payment.clone().unwrap_or_default();
+    let escrow_vault = stored_payment.escrow.as_ref().map(|escrow| escrow.vault_address.clone());
+    
+    // The same signed allowance is never submitted twice
+    let allowance_hashes = signed_debit_allowances.iter()
+        .map(payload_hash)
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| ApiError::InternalError(format!("Failed to hash signed transaction: {}", e)))?;
+    db.record_submitted_allowances(&allowance_hashes, &payment_id).await?;
+    
+    // Draw the vendor's discount budgets before the transfer goes out, so a budget that
+    // another payment used up meanwhile fails here and the payer can supplement again
+    if stored_payment.recepient_verified {
+        if let Err(e) = db.consume_discount_budgets(&stored_payment).await {
+            if let Err(e) = db.forget_submitted_allowances(&allowance_hashes).await {
+                log::error!("Failed to forget signed allowances of payment {}: {}", payment_id, e);
+            }
+            return Err(e);
+        }
+    }
+    // Likewise the promo code's use, so it can't be used more than its max_uses, and the
+    // points of a loyalty reward, so they can't pay for two rewards
+    let taken = match db.consume_payment_promo(&stored_payment).await {
+        Ok(()) => db.deduct_loyalty_points(&stored_payment).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = taken {
+        release_signing_holds(db, &stored_payment).await;
+        if let Err(e) = db.forget_submitted_allowances(&allowance_hashes).await {
+            log::error!("Failed to forget signed allowances of payment {}: {}", payment_id, e);
+        }
+        return Err(e);
+    }
+    
     log::info!("Submitting {} signed debit allowances", signed_debit_allowances.len());
     
     // Convert to VerifiableType and submit
@@ -290,11 +992,20 @@ pub async fn process_signed_transaction(
         .map(|allowance| VerifiableType::DebitAllowance(allowance))
         .collect();
     
-    match wallet_service.submit_verifiables(verifiables).await {
-        Ok(_) => {
-            log::info!("Successfully submitted transaction for payment ID: {}", payment_id);
+    let result = wallet_service.submit_verifiables(verifiables).await;
+    // Even a failed submission may have reached the executor
+    let touched: Vec<Ed25519PubKey> = [Some(&supplement_data.payer_address), Some(&supplement_data.vendor_address), escrow_vault.as_ref()]
+        .into_iter()
+        .flatten()
+        .chain(stored_payment.split_legs.iter().map(|leg| &leg.recipient_address))
+        .filter_map(|address| WalletService::parse_public_key(address).ok())
+        .collect();
+    wallet_service.invalidate_balances(&touched);
+    
+    match result {
+        Ok(executor_tx_id) => {
+            log::info!("Successfully submitted transaction for payment ID: {} (executor tx: {:?})", payment_id, executor_tx_id);
             
-            // Get the payment to check if recipient is verified
             let payment = match db.get_payment_by_id(&payment_id).await {
                 Ok(payment) => Some(payment),
                 Err(e) => {
@@ -304,343 +1015,243 @@ pub async fn process_signed_transaction(
                 }
             };
             
-            // Update payment status to completed
-            match db.update_payment_status(&payment_id, PaymentStatus::Completed).await {
-                Ok(_) => {
-                    log::info!("Updated payment status to Completed for payment ID: {}", payment_id);
-                    
-                    // Check if recipient is verified before doing any post-processing
-                    let is_recipient_verified = payment.as_ref().map(|p| p.recepient_verified).unwrap_or(false);
-                    log::info!("Payment verification status check - payment exists: {}, is_verified: {}", 
-                        payment.is_some(), is_recipient_verified);
-                    
-                    if !is_recipient_verified {
-                        log::info!("Recipient not verified (is_verified={}), skipping all post-transaction processing", is_recipient_verified);
-                        return Ok(HttpResponse::Ok().json(PaymentStatusResponse {
-                            payment_id: payment_id.clone(),
-                            vendor_address: supplement_data.vendor_address.clone(),
-                            vendor_name: supplement_data.vendor_name.clone(),
-                            customer_address: Some(supplement_data.payer_address.clone()),
-                            status: PaymentStatus::Completed,
-                            created_at: payment.as_ref().map(|p| p.created_at).unwrap_or(chrono::Utc::now().timestamp()),
-                            price_usd: supplement_data.price_usd,
-                            payment_bundle: Some(supplement_data.payment_bundle.clone()),
-                            computed_payment: Some(supplement_data.payment_bundle.clone()),
-                            vendor_valuations: supplement_data.vendor_valuations.clone(),
-                            discount_consumption: supplement_data.discount_consumption.clone(),
-                        }));
-                    }
-                    
-                    // Perform post-transaction processing only for verified recipients
-                    log::info!("✅ Recipient is verified (is_verified=true), performing post-transaction processing");
-                    
-                    // 1. Update vendor preferences (subtract discount amounts consumed)
-                    log::info!("Step 1: Updating vendor preferences after payment completion");
-                    
-                    // Get the payment to retrieve vendor address and discount consumption data
-                    match db.get_payment_by_id(&payment_id).await {
-                        Ok(payment) => {
-                            if let Some(discount_consumption) = &payment.discount_consumption {
-                                log::info!("  Found discount consumption data with {} items", discount_consumption.len());
-                                for dc in discount_consumption {
-                                    log::info!("    - Token {} consumed {} discount/premium", dc.symbol, dc.amount_used);
-                                }
-                                // Update VENDOR's preferences with consumed discounts (NO effective valuations)
-                                if let Err(e) = db.update_user_preferences_after_payment(
-                                    &payment.vendor_address,  // Use vendor address, not payer!
-                                    discount_consumption,
-                                    None,  // Don't update effective valuations in preferences
-                                ).await {
-                                    log::error!("Failed to update vendor preferences after payment: {}", e);
-                                    // Don't fail the transaction, just log the error
-                                } else {
-                                    log::info!("  ✅ Successfully updated vendor preferences");
-                                }
-                            } else {
-                                log::info!("  No discount consumption to update");
-                            }
-                        },
-                        Err(e) => {
-                            log::error!("Failed to retrieve payment for discount consumption: {}", e);
-                        }
-                    }
-                    
-                    // 2. Flatten each token payment into multiple transactions to save
-                    log::info!("Step 2: Processing payment bundle with {} token payments", supplement_data.payment_bundle.len());
-                    for token_payment in &supplement_data.payment_bundle {
-                        log::info!("  - Token payment: {} {} (token_key: {})", token_payment.amount_to_pay, token_payment.symbol, token_payment.token_key);
-                        // This would be implemented in a future phase
-                    }
-                    
-                    // 3. Update token market values
-                    log::info!("Step 3: Updating market values for tokens used in transaction");
-                    
-                    // Task 1: Create flattened transaction records with effective valuations
-                    match db.get_payment_by_id(&payment_id).await {
-                        Ok(payment) => {
-                            if let Some(initial_bundle) = &payment.initial_payment_bundle {
-                                let mut effective_valuations = Vec::new();
-                                
-                                for final_payment in &supplement_data.payment_bundle {
-                                    if let Some(initial_payment) = initial_bundle.iter()
-                                        .find(|p| p.token_key == final_payment.token_key) {
-                                        
-                                        if final_payment.amount_to_pay > 0.0 {
-                                            let effective_val = initial_payment.amount_to_pay / final_payment.amount_to_pay;
-                                            effective_valuations.push((final_payment.symbol.clone(), effective_val));
-                                        }
-                                    }
-                                }
-                                
-                                if let Err(e) = create_transaction_records_with_effective_valuations(
-                                    &db,
-                                    &supplement_data.payment_bundle,
-                                    &effective_valuations,
-                                    &payment_id
-                                ).await {
-                                    log::error!("Failed to create transaction records: {}", e);
-                                }
-                            } else {
-                                // Missing data, use vendor valuations if available
-                                if let Some(vendor_valuations) = &payment.vendor_valuations {
-                                    if let Err(e) = create_transaction_records_with_vendor_valuations(
-                                        &db,
-                                        &supplement_data.payment_bundle,
-                                        vendor_valuations,
-                                        &payment_id
-                                    ).await {
-                                        log::error!("Failed to create transaction records: {}", e);
-                                    }
-                                } else {
-                                    // No valuations at all, use simple records
-                                    if let Err(e) = create_transaction_records_simple(
-                                        &db,
-                                        &supplement_data.payment_bundle,
-                                        &payment_id
-                                    ).await {
-                                        log::error!("Failed to create transaction records: {}", e);
-                                    }
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            log::error!("Failed to get payment for transaction records: {}", e);
-                            // Fallback to simple records
-                            if let Err(e) = create_transaction_records_simple(
-                                &db,
-                                &supplement_data.payment_bundle,
-                                &payment_id
-                            ).await {
-                                log::error!("Failed to create transaction records: {}", e);
-                            }
+            let response = |status: PaymentStatus| PaymentStatusResponse {
+                payment_id: payment_id.to_string(),
+                vendor_address: supplement_data.vendor_address.clone(),
+                vendor_name: supplement_data.vendor_name.clone(),
+                customer_address: Some(supplement_data.payer_address.clone()),
+                status,
+                created_at: payment.as_ref().map(|p| p.created_at).unwrap_or(chrono::Utc::now().timestamp()),
+                price_usd: supplement_data.price_usd,
+                payment_bundle: Some(supplement_data.payment_bundle.clone()),
+                computed_payment: Some(payment_bundle.clone()),
+                vendor_valuations: supplement_data.vendor_valuations.clone(),
+                discount_consumption: supplement_data.discount_consumption.clone(),
+                executor_tx_id: executor_tx_id.clone(),
+            };
+            
+            // Accepted is not executed: hold the payment as Submitted and let the settlement
+            // poller complete it (or mark it Failed) once the executor reports finality
+            let update = match &executor_tx_id {
+                Some(tx_id) => db.mark_payment_submitted(&payment_id, tx_id, &payment_bundle).await
+                    .map(|_| PaymentStatus::Submitted),
+                // Without an ID there is nothing to poll, so complete on acceptance as before
+                None => db.update_payment_status(&payment_id, PaymentStatus::Completed).await
+                    .map(|_| PaymentStatus::Completed),
+            };
+            
+            match update {
+                Ok(status) => {
+                    log::info!("Updated payment status to {} for payment ID: {}", status, payment_id);
+                    shared_state.publish(SharedEvent::PaymentStatus { payment_id: payment_id.to_string(), status: status.clone() });
+                    if status == PaymentStatus::Completed {
+                        if let Some(payment) = &payment {
+                            apply_completed_payment(db, payment, &payment_bundle).await;
+                            push_service.payment_received(payment).await;
                         }
                     }
-                    
-                    // Task 2: Update market prices
-                    log::info!("Step 3b: Calling update_market_prices for {} tokens", supplement_data.payment_bundle.len());
-                    if let Err(e) = update_market_prices(&db, &supplement_data.payment_bundle).await {
-                        log::error!("Failed to update market prices: {}", e);
-                        // Don't fail the whole transaction for this
-                    } else {
-                        log::info!("✅ Successfully updated market prices");
-                    }
-                    
-                    // Task 3: Update vendor preferences (we'll skip this for now due to storage complexity)
-                    log::info!("Vendor preference updates skipped due to data format complexities");
-                    
-                    // Return success response with full transaction details using request data
-                    let response = PaymentStatusResponse {
-                        payment_id: payment_id.to_string(),
-                        vendor_address: supplement_data.vendor_address.clone(),
-                        vendor_name: supplement_data.vendor_name.clone(),
-                        customer_address: Some(supplement_data.payer_address.clone()),
-                        status: PaymentStatus::Completed, // Updated status
-                        price_usd: supplement_data.price_usd,
-                        created_at: chrono::Utc::now().timestamp(),
-                        payment_bundle: Some(supplement_data.payment_bundle.clone()),
-                        computed_payment: Some(supplement_data.payment_bundle.clone()),
-                        vendor_valuations: None, // Could add if needed
-                        discount_consumption: None, // Could add if needed
-                    };
-                    
-                    Ok(HttpResponse::Ok().json(response))
+                    Ok(SubmittedPayment { payment: response(status), status_update_error: None })
                 },
                 Err(e) => {
                     log::error!("Failed to update payment status: {}", e);
-                    // Transaction was submitted successfully, but payment status update failed
-                    // Return partial success with transaction details from request data
-                    let response = PaymentStatusResponse {
-                        payment_id: payment_id.to_string(),
-                        vendor_address: supplement_data.vendor_address.clone(),
-                        vendor_name: supplement_data.vendor_name.clone(),
-                        customer_address: Some(supplement_data.payer_address.clone()),
-                        status: PaymentStatus::Calculated, // Status wasn't updated due to error
-                        price_usd: supplement_data.price_usd,
-                        created_at: chrono::Utc::now().timestamp(),
-                        payment_bundle: Some(supplement_data.payment_bundle.clone()),
-                        computed_payment: Some(supplement_data.payment_bundle.clone()),
-                        vendor_valuations: None,
-                        discount_consumption: None,
-                    };
-                    
-                    Ok(HttpResponse::Ok().json(json!({
-                        "status": "partial_success",
-                        "message": "Transaction submitted successfully but payment status update failed",
-                        "error": format!("Failed to update payment status: {}", e),
-                        "transaction": response
-                    })))
+                    Ok(SubmittedPayment {
+                        payment: response(PaymentStatus::Calculated), // Status wasn't updated due to error
+                        status_update_error: Some(format!("Failed to update payment status: {}", e)),
+                    })
                 }
             }
         },
+        // A capture can't go back to Authorized, where it could be voided, if the executor
+        // may have applied it. It keeps its budgets and allowances and is settled from the
+        // payer's nonce by the finality poller.
+        Err(e) if stored_payment.authorization.is_some() && !e.is_rejection() => {
+            log::error!("Capture of payment {} may have reached the executor: {}", payment_id, e);
+            if db.mark_capture_unconfirmed(payment_id, &payment_bundle).await? {
+                shared_state.publish(SharedEvent::PaymentStatus { payment_id: payment_id.to_string(), status: PaymentStatus::Submitted });
+            }
+            Err(ApiError::ServiceUnavailable("The executor didn't confirm the capture; the payment completes once it settles".to_string()))
+        },
         Err(e) => {
-            log::error!("Failed to submit transaction: {}", e);
-            Err(ApiError::InternalError(format!("Failed to submit transaction: {}", e)))
+            // Rejections (insufficient balance, stale nonce) become 4xx, an unreachable executor 503
+            log::error!("Failed to submit transaction for payment {}: {}", payment_id, e);
+            release_signing_holds(db, &stored_payment).await;
+            if let Err(e) = db.forget_submitted_allowances(&allowance_hashes).await {
+                log::error!("Failed to forget signed allowances of payment {}: {}", payment_id, e);
+            }
+            Err(e.into())
         }
     }
 }
 
+/// Give back what signing took for a payment that didn't go through: the vendor's discount
+/// budgets, the promo code use and the loyalty reward's points. Each is only given back if
+/// it was taken.
+pub async fn release_signing_holds(db: &MongoDBService, payment: &Payment) {
+    let payment_id = payment.payment_id.as_str();
+    if let Err(e) = db.release_discount_budgets(payment).await {
+        log::error!("Failed to release discount budgets of payment {}: {}", payment_id, e);
+    }
+    if let Err(e) = db.release_payment_promo(payment).await {
+        log::error!("Failed to release promo code use of payment {}: {}", payment_id, e);
+    }
+    if let Err(e) = db.refund_loyalty_points(payment).await {
+        log::error!("Failed to give back loyalty points of payment {}: {}", payment_id, e);
+    }
+}
+
+/// Post-transaction processing for a completed payment: consume the vendor's discounts,
+/// record the flattened token transactions and update market prices. Runs once the
+/// transfer is final, and only for verified recipients.
+pub async fn apply_completed_payment(db: &MongoDBService, payment: &Payment, payment_bundle: &[TokenPayment]) {
+    let payment_id = payment.payment_id.as_str();
+    if let Some(request_id) = &payment.payment_request_id {
+        if let Err(e) = db.mark_payment_request_paid(request_id, payment_id).await {
+            log::error!("Failed to mark payment request {} paid by {}: {}", request_id, payment_id, e);
+        }
+    }
+    if let Some(invoice_id) = &payment.invoice_id {
+        if let Err(e) = db.mark_invoice_paid(invoice_id, payment_id).await {
+            log::error!("Failed to mark invoice {} paid by {}: {}", invoice_id, payment_id, e);
+        }
+    }
+    if let Err(e) = db.settle_loyalty(payment).await {
+        log::error!("Failed to settle loyalty points for payment {}: {}", payment_id, e);
+    }
+    if let Some(escrow) = &payment.escrow {
+        // The tokens are in the escrow vault now; start the hold before auto-release
+        let now = Utc::now().timestamp();
+        let held = mongodb::bson::doc! { "held_at": now, "release_at": now + escrow.hold_hours * 60 * 60 };
+        if let Err(e) = db.transition_payment_escrow(payment_id, &[EscrowStatus::AwaitingPayment], EscrowStatus::Held, held).await {
+            log::error!("Failed to hold escrow of payment {}: {}", payment_id, e);
+        }
+    }
+    if !payment.recepient_verified {
+        log::info!("Recipient not verified for payment {}, skipping all post-transaction processing", payment_id);
+        return;
+    }
+    log::info!("✅ Recipient is verified, performing post-transaction processing for payment {}", payment_id);
+    
+    // 1. Vendor discount budgets are drawn at signing; this only catches payments signed
+    // before that, and is a no-op for the rest
+    log::info!("Step 1: Drawing vendor discount budgets if not drawn at signing");
+    if let Err(e) = db.consume_discount_budgets(payment).await {
+        log::error!("Failed to draw vendor discount budgets after payment {}: {}", payment_id, e);
+        // Don't fail the transaction, just log the error
+    } else if let Err(e) = db.settle_discount_budgets(payment).await {
+        log::error!("Failed to settle vendor discount budgets of payment {}: {}", payment_id, e);
+    }
+    
+    // 2. Create flattened transaction records, one per token paid to each recipient
+    log::info!("Step 2: Processing payment bundle with {} token payments", payment_bundle.len());
+    let effective_valuations = payment.initial_payment_bundle.as_ref().map(|initial_bundle| {
+        let mut effective_valuations = Vec::new();
+        
+        for final_payment in payment_bundle {
+            if let Some(initial_payment) = initial_bundle.iter()
+                .find(|p| p.token_key == final_payment.token_key) {
+                
+                if final_payment.amount_to_pay > 0.0 {
+                    let effective_val = initial_payment.amount_to_pay / final_payment.amount_to_pay;
+                    effective_valuations.push((final_payment.symbol.clone(), effective_val));
+                }
+            }
+        }
+        effective_valuations
+    });
+    let recipient_legs = if payment.split_legs.is_empty() {
+        vec![SplitLeg { recipient_address: payment.vendor_address.clone(), payment_bundle: payment_bundle.to_vec() }]
+    } else {
+        payment.split_legs.clone()
+    };
+    for leg in &recipient_legs {
+        let records = if let Some(effective_valuations) = &effective_valuations {
+            create_transaction_records_with_effective_valuations(db, &leg.payment_bundle, effective_valuations, payment_id, &leg.recipient_address).await
+        } else if let Some(vendor_valuations) = &payment.vendor_valuations {
+            // Missing data, use vendor valuations if available
+            create_transaction_records_with_vendor_valuations(db, &leg.payment_bundle, vendor_valuations, payment_id, &leg.recipient_address).await
+        } else {
+            // No valuations at all, use simple records
+            create_transaction_records_simple(db, &leg.payment_bundle, payment_id, &leg.recipient_address).await
+        };
+        if let Err(e) = records {
+            log::error!("Failed to create transaction records for {}: {}", redact(&leg.recipient_address), e);
+        }
+    }
+    
+    // 3. Update token market values
+    log::info!("Step 3: Calling update_market_prices for {} tokens", payment_bundle.len());
+    if let Err(e) = update_market_prices(db, payment_bundle).await {
+        log::error!("Failed to update market prices: {}", e);
+        // Don't fail the whole transaction for this
+    } else {
+        log::info!("✅ Successfully updated market prices");
+    }
+}
 
 pub async fn get_payment_status(
     payment_id: web::Path<String>,
     db: web::Data<MongoDBService>,
 ) -> Result<HttpResponse, ApiError> {
-    // Normalize the payment code to handle common input errors
-    let normalized_payment_id = normalize_payment_code(&payment_id);
-    
-    // Commented out for less verbose logging during polling
-    // log::info!("=== PAYMENT STATUS REQUEST ===");
-    // log::info!("Requested payment ID: {} (normalized: {})", payment_id, normalized_payment_id);
-
-    let payment = match db.get_payment(&normalized_payment_id).await {
-        Ok(Some(payment)) => {
-            // Minimal logging for polling
-            // log::debug!("Payment {} found with status {:?}", payment.payment_id, payment.status);
-            
-            payment
-        },
-        Ok(None) => {
-            // log::error!("❌ Payment not found for ID: {}", payment_id);
-            return Err(ApiError::NotFound(format!("Payment with ID {} not found", payment_id)));
-        },
-        Err(e) => {
-            // log::error!("❌ Database error retrieving payment {}: {}", payment_id, e);
-            return Err(e);
-        }
-    };
+    Ok(HttpResponse::Ok().json(payment_status(&payment_id, &db).await?))
+}
 
+/// A payment's status by code. Shared by the REST and gRPC APIs. Clients poll this, so it
+/// logs nothing itself; the access log has each request.
+pub async fn payment_status(payment_id: &str, db: &MongoDBService) -> Result<PaymentStatusResponse, ApiError> {
+    // Normalize the payment code to handle common input errors; vendor-scoped codes are looked up
+    let normalized_payment_id = db.resolve_payment_code(payment_id).await?;
+    let payment = db.get_payment(&normalized_payment_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Payment with ID {} not found", payment_id)))?;
 
-    // Vendor valuations logging commented out for less noise
-    // if let Some(ref valuations) = payment.vendor_valuations {
-    //     log::info!("✅ Payment has {} vendor valuations", valuations.len());
-    //     for (i, val) in valuations.iter().enumerate() {
-    //         log::info!("  Valuation {}: {} = {}", i, val.symbol, val.valuation);
-    //     }
-    // } else {
-    //     log::info!("⚠️ Payment has no vendor valuations");
-    // }
-
-    let response = PaymentStatusResponse {
-        payment_id: payment.payment_id.clone(),
-        vendor_address: payment.vendor_address.clone(),
-        vendor_name: payment.vendor_name.clone(),
-        customer_address: payment.customer_address.clone(),
-        status: payment.status.clone(),
+    Ok(PaymentStatusResponse {
+        payment_id: payment.payment_id,
+        vendor_address: payment.vendor_address,
+        vendor_name: payment.vendor_name,
+        customer_address: payment.customer_address,
+        status: payment.status,
         price_usd: payment.price_usd,
         created_at: payment.created_at,
         payment_bundle: payment.computed_payment.clone(),
-        computed_payment: payment.computed_payment.clone(),
-        vendor_valuations: payment.vendor_valuations.clone(),
-        discount_consumption: payment.discount_consumption.clone(),
-    };
-
-    // Response logging commented out for less noise during polling
-    // log::info!("=== PAYMENT STATUS RESPONSE ===");
-    // log::info!("Response payment_id: {}", response.payment_id);
-    // log::info!("Response status: {:?}", response.status);
-    // log::info!("Response has payment_bundle: {}", response.payment_bundle.is_some());
-    // log::info!("Response has computed_payment: {}", response.computed_payment.is_some());
-    // log::info!("Response has vendor_valuations: {}", response.vendor_valuations.is_some());
-    // log::info!("Response has discount_consumption: {}", response.discount_consumption.is_some());
-    // 
-    // if let Some(ref bundle) = response.payment_bundle {
-    //     log::info!("Payment bundle contains {} tokens", bundle.len());
-    // }
-    // 
-    // log::info!("Sending HTTP 200 OK response");
-    Ok(HttpResponse::Ok().json(response))
+        computed_payment: payment.computed_payment,
+        vendor_valuations: payment.vendor_valuations,
+        discount_consumption: payment.discount_consumption,
+        executor_tx_id: payment.executor_tx_id,
+    })
 }
 
-// Helper function to generate unsigned transaction from payment bundle
-async fn generate_unsigned_transaction(
-    wallet_service: &WalletService,
-    payer_address: &str,
-    vendor_address: &str,
-    payment_bundle: &[TokenPayment],
-) -> Result<String, String> {
-    log::info!("Generating unsigned transaction for payer: {}, vendor: {}", payer_address, vendor_address);
-    
-    // Parse payer and vendor addresses
-    let payer_pubkey = match Ed25519PubKey::from_str(payer_address) {
-        Ok(pk) => pk,
-        Err(e) => return Err(format!("Invalid payer address format: {}", e)),
-    };
-    
-    let vendor_pubkey = match Ed25519PubKey::from_str(vendor_address) {
-        Ok(pk) => pk,
-        Err(e) => return Err(format!("Invalid vendor address format: {}", e)),
-    };
-    
-    // Create a list to hold all debit allowances
-    let mut debit_allowances = Vec::with_capacity(payment_bundle.len());
-    
-    // Get the payer's vault to check current nonce
-    let payer_vault = match wallet_service.get_vault(&payer_pubkey).await {
-        Ok(Some(vault)) => vault,
-        Ok(None) => return Err(format!("Vault not found for payer address: {}", payer_pubkey)),
-        Err(e) => return Err(format!("Failed to get payer vault: {}", e)),
-    };
-    
-    // Get current nonce from the vault
-    let current_nonce = payer_vault.nonce();
-    
-    // Default shard ID (using 1 as in the example)
-    let shard = Shard::from(1u64);
-    
-    // Create vault IDs for payer and vendor
-    let from_vault_id = VaultId::new(payer_pubkey, shard);
-    let to_vault_id = VaultId::new(vendor_pubkey, shard);
-    
+/// The allowances map paying a bundle: each token, by its vault, to its amount in on-chain units
+fn bundle_allowances(payment_bundle: &[TokenPayment]) -> Result<BTreeMap<TokenKind, u64>, ApiError> {
     // Create allowances map for all tokens
     let mut allowances = BTreeMap::new();
     
     // Process each token payment
-    for (index, token_payment) in payment_bundle.iter().enumerate() {
-        log::info!("Processing token payment: {:?}", token_payment);
+    for token_payment in payment_bundle {
+        log::debug!("Processing token payment: {:?}", token_payment);
         
         // Parse token key (format: "pubkey,shard")
         let token_parts: Vec<&str> = token_payment.token_key.split(',').collect();
         if token_parts.len() != 2 {
-            return Err(format!("Invalid token key format: {}", token_payment.token_key));
+            return Err(ApiError::ValidationError(format!("Invalid token key format: {}", token_payment.token_key)));
         }
         
         // Parse token pubkey
         let token_pubkey = match Ed25519PubKey::from_str(token_parts[0]) {
             Ok(pk) => pk,
-            Err(e) => return Err(format!("Invalid token pubkey: {}", e)),
+            Err(e) => return Err(ApiError::ValidationError(format!("Invalid token pubkey: {}", e))),
         };
         
         // Parse shard ID
         let token_shard_id = match token_parts[1].parse::<u64>() {
             Ok(id) => Shard::from(id),
-            Err(e) => return Err(format!("Invalid shard ID: {}", e)),
+            Err(e) => return Err(ApiError::ValidationError(format!("Invalid shard ID: {}", e))),
         };
         
         // Create token vault ID
         let token_vault_id = VaultId::new(token_pubkey, token_shard_id);
         
-        // Convert floating point amount to integer (multiply by 100 and round)
+        // Convert floating point amount to integer on-chain units
         // For example: 3.89 -> 389
-        let amount = (token_payment.amount_to_pay * 100.0).round() as u64;
+        let amount = to_on_chain_units(token_payment.amount_to_pay);
         
         // Add this token to the allowances map
         allowances.insert(TokenKind::NonNative(token_vault_id), amount);
@@ -648,18 +1259,130 @@ async fn generate_unsigned_transaction(
         log::info!("Added token to allowances: token_id={}, amount={}", token_vault_id, amount);
     }
     
-    // Create a single debit allowance with all token allowances
-    let debit_allowance = DebitAllowance {
-        debited: from_vault_id,
-        credited: to_vault_id,
-        new_nonce: current_nonce + 1, // Incrementing the current nonce
-        allowances,
+    Ok(allowances)
+}
+
+/// Who the payer's allowances credit and with what: each recipient of a split payment,
+/// or else the vendor (or the escrow vault) with the whole bundle
+fn credited_legs(payment: &Payment, payment_bundle: &[TokenPayment]) -> Vec<SplitLeg> {
+    if !payment.split_legs.is_empty() {
+        return payment.split_legs.clone();
+    }
+    let credited_address = payment.escrow.as_ref().map_or(&payment.vendor_address, |escrow| &escrow.vault_address);
+    vec![SplitLeg { recipient_address: credited_address.clone(), payment_bundle: payment_bundle.to_vec() }]
+}
+
+/// Reject a signed transaction that doesn't pay what supplement computed for the payment:
+/// one debit allowance from the recorded customer to each recipient (the vendor or the
+/// escrow vault, unless the payment is split), for every token of their share and nothing
+/// else, to within a unit of rounding
+fn check_signed_allowances(signed_transaction: &str, payment: &Payment) -> Result<(), ApiError> {
+    let computed_payment = payment.computed_payment.as_deref()
+        .ok_or_else(|| ApiError::Conflict("Payment hasn't been supplemented yet".to_string()))?;
+    let customer_address = payment.customer_address.as_deref()
+        .ok_or_else(|| ApiError::Conflict("Payment has no customer yet".to_string()))?;
+    let vault = |address: &str| Ed25519PubKey::from_str(address)
+        .map(|pubkey| VaultId::new(pubkey, Shard::from(1u64)))
+        .map_err(|e| ApiError::InternalError(format!("Invalid stored address {}: {}", address, e)));
+    let debited = vault(customer_address)?;
+    let mut expected = credited_legs(payment, computed_payment).iter()
+        .map(|leg| Ok((vault(&leg.recipient_address)?, bundle_allowances(&leg.payment_bundle)?)))
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    let payload: serde_json::Value = serde_json::from_str(signed_transaction)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid signed transaction format: {}", e)))?;
+    let messages = find_messages(&payload, &["debited", "credited", "allowances"]);
+    if messages.len() != expected.len() {
+        return Err(ApiError::ValidationError(format!("Signed transaction must hold exactly {} debit allowance(s), one per recipient", expected.len())));
+    }
+    for message in messages {
+        let allowance: DebitAllowance = serde_json::from_value(message.clone())
+            .map_err(|e| ApiError::ValidationError(format!("Invalid debit allowance: {}", e)))?;
+
+        if allowance.debited != debited {
+            return Err(ApiError::ValidationError("Signed transaction must be paid from the payment's customer".to_string()));
+        }
+        // Each recipient is paid once, so a matched leg is taken off the list
+        let leg = expected.iter().position(|(credited, _)| *credited == allowance.credited)
+            .ok_or_else(|| ApiError::ValidationError("Signed transaction must pay the payment's vendor".to_string()))?;
+        let (_, amounts) = expected.swap_remove(leg);
+        let amounts_match = allowance.allowances.len() == amounts.len()
+            && amounts.iter().all(|(token, amount)| {
+                allowance.allowances.get(token).map_or(false, |signed| signed.abs_diff(*amount) <= 1)
+            });
+        if !amounts_match {
+            log::warn!("Signed allowances of payment {} don't match its computed payment: {:?} vs {:?}", payment.payment_id, allowance.allowances, amounts);
+            return Err(ApiError::ValidationError("Signed amounts don't match the calculated payment, supplement it again".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// The payer's nonce once every debit allowance of a signed transaction is applied
+fn signed_nonce(signed_transaction: &str) -> Option<i64> {
+    let payload: serde_json::Value = serde_json::from_str(signed_transaction).ok()?;
+    find_messages(&payload, &["debited", "credited", "allowances"]).into_iter()
+        .filter_map(|message| serde_json::from_value::<DebitAllowance>(message.clone()).ok())
+        .map(|allowance| allowance.new_nonce as i64)
+        .max()
+}
+
+// Helper function to generate unsigned transaction from payment bundle: one debit
+// allowance per credited leg, each with the next nonce
+async fn generate_unsigned_transaction(
+    wallet_service: &WalletService,
+    payer_address: &str,
+    legs: &[SplitLeg],
+) -> Result<String, ApiError> {
+    log::info!("Generating unsigned transaction for payer: {}, {} recipient(s)", redact(&payer_address), legs.len());
+    
+    // Parse payer address
+    let payer_pubkey = match Ed25519PubKey::from_str(payer_address) {
+        Ok(pk) => pk,
+        Err(e) => return Err(ApiError::ValidationError(format!("Invalid payer address format: {}", e))),
     };
     
-    log::info!("Created debit allowance: debited={}, credited={}", 
-              debit_allowance.debited, debit_allowance.credited);
+    // Create a list to hold all debit allowances
+    let mut debit_allowances = Vec::with_capacity(legs.len());
+    
+    // Get the payer's vault straight from the executor: a cached nonce may already be used,
+    // and allowances signed against it would be refused
+    let payer_vault = match wallet_service.fetch_vault(&payer_pubkey).await {
+        Ok(Some(vault)) => vault,
+        Ok(None) => return Err(ApiError::NotFound(format!("Vault not found for payer address: {}", payer_pubkey))),
+        Err(e) => {
+            log::error!("Failed to get payer vault: {}", e);
+            return Err(e.into());
+        }
+    };
+    
+    // Get current nonce from the vault
+    let current_nonce = payer_vault.nonce();
+    
+    // Default shard ID (using 1 as in the example)
+    let shard = Shard::from(1u64);
+    
+    let from_vault_id = VaultId::new(payer_pubkey, shard);
     
-    debit_allowances.push(debit_allowance);
+    for (leg_nonce, leg) in (current_nonce + 1..).zip(legs) {
+        let recipient_pubkey = match Ed25519PubKey::from_str(&leg.recipient_address) {
+            Ok(pk) => pk,
+            Err(e) => return Err(ApiError::ValidationError(format!("Invalid recipient address format: {}", e))),
+        };
+        
+        // Create a debit allowance with all of the recipient's token allowances
+        let debit_allowance = DebitAllowance {
+            debited: from_vault_id,
+            credited: VaultId::new(recipient_pubkey, shard),
+            new_nonce: leg_nonce, // Incrementing the current nonce once per allowance
+            allowances: bundle_allowances(&leg.payment_bundle)?,
+        };
+        
+        log::info!("Created debit allowance: debited={}, credited={}", 
+                  debit_allowance.debited, debit_allowance.credited);
+        
+        debit_allowances.push(debit_allowance);
+    }
     
     // Serialize the list of debit allowances to JSON
     match serde_json::to_string(&debit_allowances) {
@@ -667,7 +1390,7 @@ async fn generate_unsigned_transaction(
             log::info!("Generated unsigned transaction JSON: {}", json);
             Ok(json)
         },
-        Err(e) => Err(format!("Failed to serialize debit allowances: {}", e)),
+        Err(e) => Err(ApiError::InternalError(format!("Failed to serialize debit allowances: {}", e))),
     }
 }
 
@@ -706,7 +1429,8 @@ async fn generate_unsigned_transaction(
 async fn create_transaction_records_simple(
     db: &MongoDBService,
     payment_bundle: &[TokenPayment],
-    payment_id: &str
+    payment_id: &str,
+    recipient_address: &str,
 ) -> Result<(), ApiError> {
     log::info!("Creating transaction records for payment {}", payment_id);
     log::info!("Payment bundle has {} tokens", payment_bundle.len());
@@ -721,6 +1445,7 @@ async fn create_transaction_records_simple(
             effective_valuation: 1.0, // Default valuation - will be improved in future iteration
             timestamp: Utc::now(),
             payment_id: payment_id.to_string(),
+            recipient_address: Some(recipient_address.to_string()),
         };
         
         match db.create_transaction_record(record).await {
@@ -801,7 +1526,8 @@ async fn create_transaction_records_with_effective_valuations(
     db: &MongoDBService,
     payment_bundle: &[TokenPayment],
     effective_valuations: &[(String, f64)],
-    payment_id: &str
+    payment_id: &str,
+    recipient_address: &str,
 ) -> Result<(), ApiError> {
     log::info!("Creating transaction records with effective valuations for payment {}", payment_id);
     log::info!("Payment bundle has {} tokens", payment_bundle.len());
@@ -822,6 +1548,7 @@ async fn create_transaction_records_with_effective_valuations(
             effective_valuation, // Use the calculated effective valuation
             timestamp: Utc::now(),
             payment_id: payment_id.to_string(),
+            recipient_address: Some(recipient_address.to_string()),
         };
         
         match db.create_transaction_record(record).await {
@@ -839,7 +1566,8 @@ async fn create_transaction_records_with_vendor_valuations(
     db: &MongoDBService,
     payment_bundle: &[TokenPayment],
     vendor_valuations: &[TokenValuation],
-    payment_id: &str
+    payment_id: &str,
+    recipient_address: &str,
 ) -> Result<(), ApiError> {
     log::info!("Creating transaction records with vendor valuations for payment {}", payment_id);
     log::info!("Payment bundle has {} tokens", payment_bundle.len());
@@ -860,6 +1588,7 @@ async fn create_transaction_records_with_vendor_valuations(
             effective_valuation, // Use vendor's valuation (without discount effects)
             timestamp: Utc::now(),
             payment_id: payment_id.to_string(),
+            recipient_address: Some(recipient_address.to_string()),
         };
         
         match db.create_transaction_record(record).await {
@@ -874,58 +1603,24 @@ async fn create_transaction_records_with_vendor_valuations(
 }
 
 
-pub async fn get_user_transaction_history(
+/// Checkouts a user has started that are not credited yet
+pub async fn get_pending_deposits(
     user_address: web::Path<String>,
     db: web::Data<MongoDBService>,
 ) -> Result<HttpResponse, ApiError> {
-    log::info!("Getting transaction history for user: {}", user_address);
+    let pending = db.get_pending_deposits(&user_address).await?;
+    log::info!("Found {} pending deposits for user {}", pending.len(), redact(&user_address));
+    Ok(HttpResponse::Ok().json(pending))
+}
 
-    // Get both payments and deposits
-    let payments = db.get_user_transaction_history(&user_address).await?;
-    let deposits = db.get_user_deposits(&user_address).await?;
-    
-    // Convert payments to ActivityItems
-    let mut activities: Vec<(i64, ActivityItem)> = payments
-        .into_iter()
-        .map(|payment| {
-            // Determine direction, counterparty address and username
-            let (direction, counterparty_address, counterparty_username) = if payment.vendor_address == *user_address {
-                // User is the vendor (received payment)
-                (
-                    TransactionDirection::Received, 
-                    payment.customer_address.clone().unwrap_or("Unknown".to_string()),
-                    payment.customer_username.clone()
-                )
-            } else {
-                // User is the customer (sent payment)
-                // For sent transactions, the vendor_name is effectively the username
-                (
-                    TransactionDirection::Sent, 
-                    payment.vendor_address.clone(),
-                    Some(payment.vendor_name.clone())
-                )
-            };
+pub async fn get_user_transaction_history(
+    user_address: web::Path<String>,
+    query: web::Query<TransactionHistoryQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    log::info!("Getting transaction history for user: {}", redact(&user_address));
 
-            let transaction_item = TransactionHistoryItem {
-                payment_id: payment.payment_id,
-                direction,
-                counterparty_address,
-                counterparty_username,
-                vendor_name: payment.vendor_name,
-                status: payment.status,
-                price_usd: payment.price_usd,
-                created_at: payment.created_at,
-                computed_payment: payment.computed_payment,
-            };
-            
-            (payment.created_at, ActivityItem::Transaction(transaction_item))
-        })
-        .collect();
-    
-    // Convert deposits to ActivityItems and add to the list
-    for deposit in deposits {
-        activities.push((deposit.created_at, ActivityItem::Deposit(deposit)));
-    }
+    let mut activities = wallet_activities(&db, &user_address, &query).await?;
     
     // Sort by timestamp descending (newest first)
     activities.sort_by(|a, b| b.0.cmp(&a.0));
@@ -938,26 +1633,51 @@ pub async fn get_user_transaction_history(
     };
     
     log::info!("Returning {} activities for user {}", 
-              response.activities.len(), user_address);
+              response.activities.len(), redact(&user_address));
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// A wallet's payments and deposits matching `query` as activity items with their timestamps, unsorted
+pub async fn wallet_activities(db: &MongoDBService, user_address: &str, query: &TransactionHistoryQuery) -> Result<Vec<(i64, ActivityItem)>, ApiError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(ApiError::ValidationError("from must be before to".to_string()));
+        }
+    }
+    // Get both payments and deposits
+    let (payments, deposits) = db.get_filtered_history(user_address, query).await?;
+    
+    // Convert payments to ActivityItems
+    let mut activities: Vec<(i64, ActivityItem)> = payments
+        .into_iter()
+        .map(|payment| (payment.created_at, ActivityItem::Transaction(TransactionHistoryItem::for_wallet(payment, user_address))))
+        .collect();
+    
+    // Convert deposits to ActivityItems and add to the list
+    for deposit in deposits {
+        activities.push((deposit.created_at, ActivityItem::Deposit(deposit)));
+    }
+    
+    Ok(activities)
+}
+
 pub async fn delete_payment(
+    auth: AuthenticatedUser,
     db: web::Data<MongoDBService>,
     payment_id: web::Path<String>,
-    req: web::Json<DeletePaymentRequest>,
 ) -> Result<HttpResponse, ApiError> {
-    log::info!("Deleting payment {} by vendor {}", payment_id.as_str(), req.vendor_address);
+    log::info!("Deleting payment {} by {}", payment_id.as_str(), redact(&auth.wallet_address));
     
-    db.delete_payment(payment_id.as_str(), &req.vendor_address).await?;
+    // Admins may cancel on behalf of the vendor; everyone else must be the vendor
+    let vendor_address = if auth.is_admin() {
+        db.get_payment_by_id(payment_id.as_str()).await?.vendor_address
+    } else {
+        auth.wallet_address.clone()
+    };
+    db.delete_payment(payment_id.as_str(), &vendor_address).await?;
     
     Ok(HttpResponse::Ok().json(json!({
         "message": "Payment cancelled successfully"
     })))
 }
 
-#[derive(serde::Deserialize)]
-pub struct DeletePaymentRequest {
-    pub vendor_address: String,
-}
-
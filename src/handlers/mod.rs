@@ -3,8 +3,36 @@ pub mod vault_handler;
 pub mod cause_handlers;
 pub mod webhook_handlers;
 pub mod purchase_webhook_handlers;
+pub mod stripe_event_router;
 pub mod wallet_handlers;
 pub mod vendor_handlers;
+pub mod admin_handlers;
+pub mod matching_pool_handlers;
+pub mod funding_round_handlers;
+pub mod donation_handlers;
+pub mod contact_handlers;
+pub mod payment_request_handlers;
+pub mod account_handlers;
+pub mod notification_handlers;
+pub mod review_handlers;
+pub mod loyalty_handlers;
+pub mod promo_code_handlers;
+pub mod voucher_handlers;
+pub mod escrow_handlers;
+pub mod dispute_handlers;
+pub mod payment_schedule_handlers;
+pub mod invoice_handlers;
+pub mod preference_handlers;
+pub mod graphql_handlers;
+pub mod key_handlers;
+pub mod token_handlers;
+pub mod activity_handlers;
+pub mod job_handlers;
+pub mod embed_handlers;
+pub mod campaign_handlers;
+pub mod fundraiser_handlers;
+pub mod organization_handlers;
+pub mod health_handler;
 
 pub use message_handler::*;
 pub use vault_handler::*;
@@ -0,0 +1,72 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use crate::auth::AuthenticatedUser;
+use crate::models::{ApiError, NotificationPreferences, RegisterDeviceRequest};
+use crate::services::MongoDBService;
+
+/// Longest push token accepted; FCM and APNs tokens are well under this
+const MAX_DEVICE_TOKEN_LEN: usize = 4096;
+
+/// A user's notification settings; everything is on until changed
+pub async fn get_notification_preferences(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let user = db.get_user_by_wallet(&wallet_address).await?
+        .filter(|user| user.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)))?;
+    Ok(HttpResponse::Ok().json(user.notification_preferences))
+}
+
+/// Replace a user's notification settings. Omitted channels and events are turned on.
+pub async fn update_notification_preferences(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    payload: web::Json<NotificationPreferences>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let preferences = db.update_notification_preferences(&wallet_address, &payload).await?;
+    log::info!("Updated notification preferences of {}: {:?}", wallet_address, preferences);
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+/// Register a device's FCM (android) or APNs (ios) token to receive the wallet's notifications
+pub async fn register_device(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    payload: web::Json<RegisterDeviceRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let token = payload.token.trim();
+    if token.is_empty() || token.len() > MAX_DEVICE_TOKEN_LEN || token.contains(char::is_whitespace) {
+        return Err(ApiError::ValidationError("Invalid device token".to_string()));
+    }
+
+    let device = db.register_device_token(&wallet_address, token, payload.platform).await?;
+    log::info!("Registered {:?} device for {}", device.platform, wallet_address);
+    Ok(HttpResponse::Ok().json(device))
+}
+
+/// Stop sending notifications to a device, e.g. on sign-out
+pub async fn unregister_device(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, token) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+
+    if !db.remove_device_token(&wallet_address, &token).await? {
+        return Err(ApiError::NotFound("Device token not registered for this wallet".to_string()));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "status": "success"
+    })))
+}
@@ -0,0 +1,105 @@
+use actix_web::{web, HttpResponse};
+use log::info;
+use crate::auth::AuthenticatedUser;
+use crate::models::{ApiError, Organization, CreateOrganizationRequest, OrganizationAdminRequest};
+use crate::services::OrganizationService;
+use crate::services::cause_service::CreateCauseRequest;
+use crate::utils::validation::Validate;
+
+/// The organization at `organization_id`, if the caller is one of its admins or an admin
+async fn administered_organization(auth: &AuthenticatedUser, organization_service: &OrganizationService, organization_id: &str) -> Result<Organization, ApiError> {
+    let organization = organization_service.get(organization_id).await?;
+    if !auth.is_admin() && !organization.is_admin(&auth.wallet_address) {
+        return Err(ApiError::Forbidden("Only the organization's admins can manage it".to_string()));
+    }
+    Ok(organization)
+}
+
+/// Create an organization with the caller as its first admin, and start its Stripe onboarding (signed)
+pub async fn create_organization(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    request: web::Json<CreateOrganizationRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let created = organization_service.create(&request, &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+/// Organizations the caller administers (signed)
+pub async fn get_my_organizations(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(organization_service.list_for_wallet(&auth.wallet_address).await?))
+}
+
+pub async fn get_organization(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    organization_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let organization = administered_organization(&auth, &organization_service, &organization_id).await?;
+    Ok(HttpResponse::Ok().json(organization))
+}
+
+/// A fresh Stripe onboarding link for an organization that hasn't finished onboarding
+pub async fn create_organization_onboarding_link(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    organization_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let organization = administered_organization(&auth, &organization_service, &organization_id).await?;
+    let onboarding_url = organization_service.onboarding_link(organization).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "onboarding_url": onboarding_url })))
+}
+
+pub async fn add_organization_admin(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    organization_id: web::Path<String>,
+    request: web::Json<OrganizationAdminRequest>,
+) -> Result<HttpResponse, ApiError> {
+    request.validate()?;
+    let organization = administered_organization(&auth, &organization_service, &organization_id).await?;
+    let organization = organization_service.add_admin(&organization, &request.wallet_address).await?;
+    info!("{} added {} as an admin of organization {}", auth.wallet_address, request.wallet_address, organization.name);
+    Ok(HttpResponse::Ok().json(organization))
+}
+
+/// Remove an admin, including the caller. The last admin can't be removed.
+pub async fn remove_organization_admin(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (organization_id, wallet_address) = path.into_inner();
+    let organization = administered_organization(&auth, &organization_service, &organization_id).await?;
+    let organization = organization_service.remove_admin(&organization, &wallet_address).await?;
+    info!("{} removed {} as an admin of organization {}", auth.wallet_address, wallet_address, organization.name);
+    Ok(HttpResponse::Ok().json(organization))
+}
+
+/// Create a cause under the organization, paid out to its connected account. The caller
+/// owns the cause; the organization's country, business type and contact email are used.
+/// It goes to review like any other cause, without waiting for Stripe onboarding.
+pub async fn create_organization_cause(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    organization_id: web::Path<String>,
+    request: web::Json<CreateCauseRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let organization = administered_organization(&auth, &organization_service, &organization_id).await?;
+    let cause = organization_service.create_cause(&organization, request.into_inner(), &auth.wallet_address).await?;
+    Ok(HttpResponse::Created().json(cause))
+}
+
+/// The organization's causes with their combined totals and recent payouts
+pub async fn get_organization_dashboard(
+    auth: AuthenticatedUser,
+    organization_service: web::Data<OrganizationService>,
+    organization_id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let organization = administered_organization(&auth, &organization_service, &organization_id).await?;
+    Ok(HttpResponse::Ok().json(organization_service.dashboard(organization).await?))
+}
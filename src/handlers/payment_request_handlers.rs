@@ -0,0 +1,266 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use std::str::FromStr;
+use crate::auth::AuthenticatedUser;
+use crate::services::{MongoDBService, PushService};
+use crate::models::{
+    ApiError, User, Payment, PaymentStatus, PaymentRequest, PaymentRequestStatus, CreatePaymentRequestRequest,
+    PaymentRequestQuery, AcceptPaymentRequestResponse, DEFAULT_PAYMENT_REQUEST_TTL_HOURS, MAX_PAYMENT_REQUEST_TTL_HOURS,
+};
+use crate::models::payment::PaymentState;
+use crate::utils::redaction::redact;
+
+/// Longest note a requester can attach
+const MAX_NOTE_CHARS: usize = 140;
+
+/// Ask another user for money. The signed-in wallet is the requester.
+pub async fn create_payment_request(
+    auth: AuthenticatedUser,
+    payload: web::Json<CreatePaymentRequestRequest>,
+    db: web::Data<MongoDBService>,
+    push_service: web::Data<PushService>,
+) -> Result<HttpResponse, ApiError> {
+    if !payload.amount_usd.is_finite() || payload.amount_usd <= 0.0 {
+        return Err(ApiError::ValidationError("Amount must be greater than zero".to_string()));
+    }
+    if payload.payer_address == auth.wallet_address {
+        return Err(ApiError::ValidationError("Cannot request a payment from yourself".to_string()));
+    }
+    let note = payload.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    if note.map_or(false, |n| n.chars().count() > MAX_NOTE_CHARS) {
+        return Err(ApiError::ValidationError(format!("Note must be at most {} characters", MAX_NOTE_CHARS)));
+    }
+    let ttl_hours = payload.expires_in_hours.unwrap_or(DEFAULT_PAYMENT_REQUEST_TTL_HOURS);
+    if !(1..=MAX_PAYMENT_REQUEST_TTL_HOURS).contains(&ttl_hours) {
+        return Err(ApiError::ValidationError(format!("expires_in_hours must be between 1 and {}", MAX_PAYMENT_REQUEST_TTL_HOURS)));
+    }
+
+    let requester = active_user(&db, &auth.wallet_address).await?;
+    active_user(&db, &payload.payer_address).await?;
+
+    let now = Utc::now().timestamp();
+    let request = db.create_payment_request(PaymentRequest {
+        id: None,
+        requester_address: requester.wallet_address,
+        requester_username: requester.username,
+        payer_address: payload.payer_address.clone(),
+        amount_usd: payload.amount_usd,
+        note: note.map(str::to_string),
+        status: PaymentRequestStatus::Pending,
+        created_at: now,
+        expires_at: now + ttl_hours * 60 * 60,
+        payment_id: None,
+        responded_at: None,
+    }).await?;
+
+    log::info!("Payment request for ${} from {} to {}", request.amount_usd, redact(&request.payer_address), redact(&request.requester_address));
+    push_service.payment_request_received(&request).await;
+    Ok(HttpResponse::Created().json(request))
+}
+
+/// Requests a user received (`direction=incoming`, the default) or sent (`outgoing`)
+pub async fn get_user_payment_requests(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    query: web::Query<PaymentRequestQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let incoming = match query.direction.as_deref() {
+        None | Some("incoming") => true,
+        Some("outgoing") => false,
+        Some(other) => return Err(ApiError::ValidationError(format!("Invalid direction '{}', expected incoming or outgoing", other))),
+    };
+    let status = query.status.as_deref()
+        .map(PaymentRequestStatus::from_str)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+
+    db.expire_payment_requests(Utc::now().timestamp()).await?;
+    let requests = db.get_payment_requests(&wallet_address, incoming, status).await?;
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+/// Start paying a request. Creates a payment code with the requester as vendor and the
+/// signed-in wallet already assigned as payer; the wallet then supplements and signs it as
+/// usual. Any wallet linked to the payer's account may pay. Accepting again returns the
+/// same code while it's still payable.
+pub async fn accept_payment_request(
+    auth: AuthenticatedUser,
+    request_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let now = Utc::now().timestamp();
+    db.expire_payment_requests(now).await?;
+    let request = load_request(&db, &request_id).await?;
+    require_payer(&auth, &db, &request).await?;
+
+    match request.status {
+        PaymentRequestStatus::Pending => {},
+        PaymentRequestStatus::Accepted if request.expires_at > now => {
+            if let Some(payment_id) = &request.payment_id {
+                if let Some(payment) = db.get_payment(payment_id).await? {
+                    match payment.state(now) {
+                        PaymentState::Processing | PaymentState::Completed => {
+                            return Ok(HttpResponse::Ok().json(AcceptPaymentRequestResponse {
+                                payment_id: payment.payment_id,
+                                request,
+                            }));
+                        },
+                        PaymentState::Active if payment.customer_address.as_deref() == Some(auth.wallet_address.as_str()) => {
+                            return Ok(HttpResponse::Ok().json(AcceptPaymentRequestResponse {
+                                payment_id: payment.payment_id,
+                                request,
+                            }));
+                        },
+                        // Accepted from another linked wallet; pay from this one instead
+                        PaymentState::Active => db.delete_payment(payment_id, &request.requester_address).await?,
+                        _ => {},
+                    }
+                }
+            }
+        },
+        PaymentRequestStatus::Accepted => return Err(ApiError::Conflict("Payment request has expired".to_string())),
+        ref status => return Err(ApiError::Conflict(format!("Payment request is already {}", status))),
+    }
+
+    let payer_username = match db.get_user_by_wallet(&auth.wallet_address).await? {
+        Some(user) => user.username,
+        None => active_user(&db, &request.payer_address).await?.username,
+    };
+    let requester = db.get_user_by_wallet(&request.requester_address).await?;
+    let payment_id = db.generate_payment_id();
+    db.create_payment(Payment {
+        id: None,
+        payment_id: payment_id.clone(),
+        vendor_address: request.requester_address.clone(),
+        vendor_name: request.requester_username.clone(),
+        price_usd: request.amount_usd,
+        customer_address: Some(auth.wallet_address.clone()),
+        customer_username: Some(payer_username),
+        status: PaymentStatus::Created,
+        created_at: now,
+        vendor_valuations: None,
+        discount_consumption: None,
+        computed_payment: None,
+        initial_payment_bundle: None,
+        recepient_verified: requester.map_or(false, |r| r.is_verified),
+        executor_tx_id: None,
+        submitted_at: None,
+        finality_checked_at: None,
+        failure_reason: None,
+        payment_request_id: Some(request_id.to_string()),
+        loyalty_redemption: None,
+        loyalty_points_earned: None,
+        promo: None,
+        escrow: None,
+        schedule_id: None,
+        batch_id: None,
+        invoice_id: None,
+        valuation_overrides: None,
+        budget_consumed: false,
+        splits: Vec::new(),
+        split_legs: Vec::new(),
+        authorization: None,
+        vendor_slug: None,
+        short_code: None,
+    }).await?;
+
+    let id = request.id.ok_or_else(|| ApiError::InternalError("Payment request has no ID".to_string()))?;
+    let accepted = db.transition_payment_request(
+        &id,
+        &[PaymentRequestStatus::Pending, PaymentRequestStatus::Accepted],
+        PaymentRequestStatus::Accepted,
+        Some(&payment_id),
+    ).await?;
+
+    match accepted {
+        Some(request) => {
+            log::info!("Payment request {} accepted with payment {}", request_id, payment_id);
+            Ok(HttpResponse::Ok().json(AcceptPaymentRequestResponse { request, payment_id }))
+        },
+        None => {
+            // Declined, cancelled or accepted elsewhere in the meantime
+            db.delete_payment(&payment_id, &request.requester_address).await?;
+            Err(ApiError::Conflict("Payment request was updated, please reload it".to_string()))
+        },
+    }
+}
+
+/// The payer turns a request down
+pub async fn decline_payment_request(
+    auth: AuthenticatedUser,
+    request_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    push_service: web::Data<PushService>,
+) -> Result<HttpResponse, ApiError> {
+    let request = load_request(&db, &request_id).await?;
+    require_payer(&auth, &db, &request).await?;
+    close_request(&db, &push_service, request, PaymentRequestStatus::Declined).await
+}
+
+/// The requester withdraws a request
+pub async fn cancel_payment_request(
+    auth: AuthenticatedUser,
+    request_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    push_service: web::Data<PushService>,
+) -> Result<HttpResponse, ApiError> {
+    let request = load_request(&db, &request_id).await?;
+    auth.require_self_or_admin(&request.requester_address)?;
+    close_request(&db, &push_service, request, PaymentRequestStatus::Cancelled).await
+}
+
+/// Decline or cancel an open request, dropping its unpaid payment code if it was accepted.
+/// Fails if the payer has already signed.
+async fn close_request(db: &MongoDBService, push_service: &PushService, request: PaymentRequest, to: PaymentRequestStatus) -> Result<HttpResponse, ApiError> {
+    if !matches!(request.status, PaymentRequestStatus::Pending | PaymentRequestStatus::Accepted) {
+        return Err(ApiError::Conflict(format!("Payment request is already {}", request.status)));
+    }
+    if let Some(payment_id) = &request.payment_id {
+        if let Some(payment) = db.get_payment(payment_id).await? {
+            if matches!(payment.status, PaymentStatus::Submitted | PaymentStatus::Completed) {
+                return Err(ApiError::Conflict("Payment request is already being paid".to_string()));
+            }
+            db.delete_payment(payment_id, &request.requester_address).await?;
+        }
+    }
+
+    let id = request.id.ok_or_else(|| ApiError::InternalError("Payment request has no ID".to_string()))?;
+    let closed = db.transition_payment_request(
+        &id,
+        &[PaymentRequestStatus::Pending, PaymentRequestStatus::Accepted],
+        to,
+        None,
+    ).await?
+    .ok_or_else(|| ApiError::Conflict("Payment request was updated, please reload it".to_string()))?;
+
+    log::info!("Payment request {} {}", id, closed.status);
+    push_service.payment_request_closed(&closed).await;
+    Ok(HttpResponse::Ok().json(closed))
+}
+
+/// The requested payer, or another wallet linked to the payer's account
+async fn require_payer(auth: &AuthenticatedUser, db: &MongoDBService, request: &PaymentRequest) -> Result<(), ApiError> {
+    if request.payer_address == auth.wallet_address
+        || db.wallets_share_account(&request.payer_address, &auth.wallet_address).await? {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden("Only the requested payer can respond to this request".to_string()))
+    }
+}
+
+async fn load_request(db: &MongoDBService, request_id: &str) -> Result<PaymentRequest, ApiError> {
+    let object_id = ObjectId::parse_str(request_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid payment request ID: {}", e)))?;
+    db.get_payment_request(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Payment request {} not found", request_id)))
+}
+
+async fn active_user(db: &MongoDBService, wallet_address: &str) -> Result<User, ApiError> {
+    db.get_user_by_wallet(wallet_address).await?
+        .filter(|user| user.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)))
+}
@@ -0,0 +1,112 @@
+use actix_web::{web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use std::str::FromStr;
+use crate::auth::AuthenticatedUser;
+use crate::services::{FeatureFlagService, MongoDBService, PaymentScheduleService};
+use crate::models::{
+    ApiError, PaymentSchedule, ScheduleStatus, CreatePaymentScheduleRequest, UpdatePaymentScheduleRequest,
+    PaymentScheduleQuery, PaySchedulePaymentResponse,
+};
+
+/// Schedule a one-off or recurring payment to a vendor. The signed-in wallet pays.
+pub async fn create_payment_schedule(
+    auth: AuthenticatedUser,
+    payload: web::Json<CreatePaymentScheduleRequest>,
+    schedule_service: web::Data<PaymentScheduleService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    feature_flags.require("recurring_payments")?;
+    let schedule = schedule_service.create(&auth.wallet_address, &payload).await?;
+    Ok(HttpResponse::Created().json(schedule))
+}
+
+/// Schedules a wallet pays (`direction=outgoing`, the default) or is paid by (`incoming`)
+pub async fn get_user_payment_schedules(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    query: web::Query<PaymentScheduleQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let outgoing = match query.direction.as_deref() {
+        None | Some("outgoing") => true,
+        Some("incoming") => false,
+        Some(other) => return Err(ApiError::ValidationError(format!("Invalid direction '{}', expected outgoing or incoming", other))),
+    };
+    let status = query.status.as_deref()
+        .map(ScheduleStatus::from_str)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+
+    let schedules = db.get_payment_schedules(&wallet_address, outgoing, status).await?;
+    Ok(HttpResponse::Ok().json(schedules))
+}
+
+/// A schedule, for the customer, the vendor or an admin
+pub async fn get_payment_schedule(
+    auth: AuthenticatedUser,
+    schedule_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = load_schedule(&db, &schedule_id).await?;
+    if !auth.is_admin() && auth.wallet_address != schedule.customer_address && auth.wallet_address != schedule.vendor_address {
+        return Err(ApiError::Forbidden("Cannot view another wallet's payment schedule".to_string()));
+    }
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+/// Change the amount, timing or note, or pause and resume with `paused`
+pub async fn update_payment_schedule(
+    auth: AuthenticatedUser,
+    schedule_id: web::Path<String>,
+    payload: web::Json<UpdatePaymentScheduleRequest>,
+    db: web::Data<MongoDBService>,
+    schedule_service: web::Data<PaymentScheduleService>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = load_schedule(&db, &schedule_id).await?;
+    auth.require_self_or_admin(&schedule.customer_address)?;
+    let schedule = schedule_service.update(&schedule, &payload).await?;
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+/// Either side can stop a schedule, even while recurring payments are switched off
+pub async fn cancel_payment_schedule(
+    auth: AuthenticatedUser,
+    schedule_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    schedule_service: web::Data<PaymentScheduleService>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = load_schedule(&db, &schedule_id).await?;
+    if auth.wallet_address != schedule.vendor_address {
+        auth.require_self_or_admin(&schedule.customer_address)?;
+    }
+    let schedule = schedule_service.cancel(&schedule).await?;
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+/// Get the payment code for the latest run to supplement and sign. Any wallet linked to
+/// the customer's account may pay it.
+pub async fn pay_payment_schedule(
+    auth: AuthenticatedUser,
+    schedule_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    schedule_service: web::Data<PaymentScheduleService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    feature_flags.require("recurring_payments")?;
+    let schedule = load_schedule(&db, &schedule_id).await?;
+    if schedule.customer_address != auth.wallet_address
+        && !db.wallets_share_account(&schedule.customer_address, &auth.wallet_address).await? {
+        return Err(ApiError::Forbidden("Only the paying customer can pay this schedule".to_string()));
+    }
+    let payment_id = schedule_service.pay(&schedule, &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(PaySchedulePaymentResponse { schedule, payment_id }))
+}
+
+async fn load_schedule(db: &MongoDBService, schedule_id: &str) -> Result<PaymentSchedule, ApiError> {
+    let object_id = ObjectId::parse_str(schedule_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid payment schedule ID: {}", e)))?;
+    db.get_payment_schedule(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Payment schedule {} not found", schedule_id)))
+}
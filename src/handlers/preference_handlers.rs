@@ -0,0 +1,171 @@
+use actix_web::{web, HttpResponse};
+use mongodb::bson::{self, Document};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use crate::auth::AuthenticatedUser;
+use crate::models::{ApiError, PreferenceChange, PreferenceChangeSource, UpdatePreferencesRequest, SavePreferenceTemplateRequest, PreferenceLedgerQuery, SpendingWeights};
+use crate::services::MongoDBService;
+use crate::utils::preferences::{validate_preferences, validate_spending_weights, validate_template_name};
+
+/// Replace all of a user's token preferences at once
+pub async fn update_preferences(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    payload: web::Json<UpdatePreferencesRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let change = set_preferences(&db, &auth, &wallet_address, &payload.preferences, PreferenceChangeSource::Bulk, None).await?;
+    Ok(HttpResponse::Ok().json(change))
+}
+
+/// A user's latest preference changes, newest first
+pub async fn get_preference_history(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let changes = db.get_preference_changes(&wallet_address).await?;
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+/// How a vendor's discount budgets were granted and spent, newest first. Filter by
+/// `symbol` or `kind` (grant or consumption).
+pub async fn get_preference_ledger(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    query: web::Query<PreferenceLedgerQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let page = db.get_preference_ledger(&wallet_address, &query).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+pub async fn list_preference_templates(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let templates = db.get_preference_templates(&wallet_address).await?;
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+/// Save a set of preferences under a name, or overwrite the template with that name
+pub async fn save_preference_template(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    payload: web::Json<SavePreferenceTemplateRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, name) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let name = validate_template_name(&name).map_err(ApiError::ValidationError)?;
+    validate_preferences(&payload.preferences, &known_symbols(&db).await?).map_err(ApiError::ValidationError)?;
+
+    let template = db.save_preference_template(&wallet_address, &name, &payload.preferences).await?;
+    Ok(HttpResponse::Ok().json(template))
+}
+
+pub async fn delete_preference_template(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, name) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+
+    if !db.delete_preference_template(&wallet_address, name.trim()).await? {
+        return Err(ApiError::NotFound(format!("Preference template {} not found", name)));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "status": "success",
+        "name": name.trim()
+    })))
+}
+
+/// Replace the user's preferences with a saved template
+pub async fn apply_preference_template(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, name) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let template = db.get_preference_template(&wallet_address, name.trim()).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Preference template {} not found", name)))?;
+    let change = set_preferences(&db, &auth, &wallet_address, &template.preferences, PreferenceChangeSource::Template, Some(template.name)).await?;
+    Ok(HttpResponse::Ok().json(change))
+}
+
+/// How readily the user spends each token when paying; unlisted tokens weigh 1
+pub async fn get_spending_weights(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    let user = db.get_user_by_wallet(&wallet_address).await?
+        .filter(|user| user.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)))?;
+    Ok(HttpResponse::Ok().json(SpendingWeights { weights: user.spending_weights }))
+}
+
+/// Replace the user's spending weights; empty goes back to paying proportionally
+pub async fn update_spending_weights(
+    auth: AuthenticatedUser,
+    wallet_address: web::Path<String>,
+    payload: web::Json<SpendingWeights>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+
+    validate_spending_weights(&payload.weights, &known_symbols(&db).await?).map_err(ApiError::ValidationError)?;
+    db.update_spending_weights(&wallet_address, &payload.weights).await?;
+    Ok(HttpResponse::Ok().json(payload.into_inner()))
+}
+
+/// Validate and store a full preference map, recording the change
+async fn set_preferences(
+    db: &MongoDBService,
+    auth: &AuthenticatedUser,
+    wallet_address: &str,
+    preferences: &BTreeMap<String, f64>,
+    source: PreferenceChangeSource,
+    template_name: Option<String>,
+) -> Result<PreferenceChange, ApiError> {
+    validate_preferences(preferences, &known_symbols(db).await?).map_err(ApiError::ValidationError)?;
+
+    let after: Document = bson::to_document(preferences)
+        .map_err(|e| ApiError::InternalError(format!("Failed to serialize preferences: {}", e)))?;
+    let before = db.replace_user_preferences(wallet_address, after.clone()).await?;
+    let change = PreferenceChange {
+        id: None,
+        wallet_address: wallet_address.to_string(),
+        source,
+        template_name,
+        changed_by: auth.wallet_address.clone(),
+        before,
+        after,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    db.record_preference_change(&change).await?;
+    log::info!("Preferences of {} replaced by {} ({} tokens)", wallet_address, auth.wallet_address, preferences.len());
+    Ok(change)
+}
+
+async fn known_symbols(db: &MongoDBService) -> Result<HashSet<String>, ApiError> {
+    Ok(db.get_all_tokens().await?
+        .into_iter()
+        .filter_map(|token| token.token_symbol)
+        .collect())
+}
@@ -0,0 +1,114 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use mongodb::bson::Document;
+use crate::auth::AuthenticatedUser;
+use crate::services::MongoDBService;
+use crate::models::{ApiError, PromoCode, PromoDiscountType, CreatePromoCodeRequest, UpdatePromoCodeRequest};
+
+pub async fn list_promo_codes(
+    auth: AuthenticatedUser,
+    vendor_address: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    let promo_codes = db.get_promo_codes(&vendor_address).await?;
+    Ok(HttpResponse::Ok().json(promo_codes))
+}
+
+pub async fn create_promo_code(
+    auth: AuthenticatedUser,
+    vendor_address: web::Path<String>,
+    payload: web::Json<CreatePromoCodeRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    let now = Utc::now().timestamp();
+
+    let code = payload.code.trim().to_uppercase();
+    let valid_code = (3..=20).contains(&code.len())
+        && code.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if !valid_code {
+        return Err(ApiError::ValidationError("Code must be 3-20 letters, digits, '-' or '_'".to_string()));
+    }
+    let valid_value = payload.value.is_finite() && payload.value > 0.0 && match payload.discount_type {
+        PromoDiscountType::Percentage => payload.value <= 100.0,
+        PromoDiscountType::FixedUsd => true,
+    };
+    if !valid_value {
+        return Err(ApiError::ValidationError(format!("Invalid {} value {}", payload.discount_type, payload.value)));
+    }
+    validate_limits(payload.max_uses, payload.expires_at, now)?;
+
+    let promo_code = db.create_promo_code(PromoCode {
+        id: None,
+        vendor_address: vendor_address.to_string(),
+        code,
+        discount_type: payload.discount_type.clone(),
+        value: payload.value,
+        max_uses: payload.max_uses,
+        uses: 0,
+        expires_at: payload.expires_at,
+        active: true,
+        created_at: now,
+        updated_at: now,
+    }).await?;
+
+    log::info!("Vendor {} created promo code {}", vendor_address, promo_code.code);
+    Ok(HttpResponse::Created().json(promo_code))
+}
+
+/// Turn a code on or off, or change its usage limit or expiry
+pub async fn update_promo_code(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    payload: web::Json<UpdatePromoCodeRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (vendor_address, code) = path.into_inner();
+    auth.require_self_or_admin(&vendor_address)?;
+    let now = Utc::now().timestamp();
+    validate_limits(payload.max_uses, payload.expires_at, now)?;
+
+    let mut set = Document::new();
+    if let Some(active) = payload.active {
+        set.insert("active", active);
+    }
+    if let Some(max_uses) = payload.max_uses {
+        set.insert("max_uses", max_uses);
+    }
+    if let Some(expires_at) = payload.expires_at {
+        set.insert("expires_at", expires_at);
+    }
+    set.insert("updated_at", now);
+
+    let promo_code = db.update_promo_code(&vendor_address, &code, set).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Promo code {} not found", code)))?;
+    log::info!("Vendor {} updated promo code {}", vendor_address, promo_code.code);
+    Ok(HttpResponse::Ok().json(promo_code))
+}
+
+/// Payments that already applied the code keep their discount
+pub async fn delete_promo_code(
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let (vendor_address, code) = path.into_inner();
+    auth.require_self_or_admin(&vendor_address)?;
+
+    if !db.delete_promo_code(&vendor_address, &code).await? {
+        return Err(ApiError::NotFound(format!("Promo code {} not found", code)));
+    }
+    log::info!("Vendor {} deleted promo code {}", vendor_address, code);
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "success", "code": code.trim().to_uppercase() })))
+}
+
+fn validate_limits(max_uses: Option<i64>, expires_at: Option<i64>, now: i64) -> Result<(), ApiError> {
+    if max_uses.map_or(false, |max_uses| max_uses <= 0) {
+        return Err(ApiError::ValidationError("max_uses must be greater than zero".to_string()));
+    }
+    if expires_at.map_or(false, |expires_at| expires_at <= now) {
+        return Err(ApiError::ValidationError("expires_at must be in the future".to_string()));
+    }
+    Ok(())
+}
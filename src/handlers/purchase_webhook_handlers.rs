@@ -1,190 +1,451 @@
-use actix_web::{web, HttpRequest, HttpResponse};
 use log::{info, error};
-use stripe::{Webhook, EventObject, EventType};
+use mongodb::bson::doc;
+use stripe::{Event, EventObject, CheckoutSession, CheckoutSessionPaymentStatus, Metadata, PaymentIntent};
 
+use crate::handlers::stripe_event_router::{EventHandlerResult, WebhookContext};
 use crate::services::{WebhookService, MongoDBService};
-use crate::models::{WebhookError, DepositRecord};
-
-pub async fn handle_stripe_purchases_webhook(
-    req: HttpRequest,
-    payload: web::Bytes,
-    webhook_service: web::Data<WebhookService>,
-    mongodb_service: web::Data<MongoDBService>,
-) -> HttpResponse {
-    info!("=== STRIPE PURCHASES WEBHOOK RECEIVED ===");
-    match process_stripe_purchases_webhook(&req, &payload, webhook_service, mongodb_service).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(e) => {
-            error!("Purchases webhook error: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Webhook error: {:?}", e))
+use crate::models::{WebhookError, CreditReservation, CreditReservationStatus, DepositRecord, DonationAttribution, PendingDepositStatus, UnclaimedDeposit, UnclaimedDepositStatus};
+
+/// `flow` metadata value marking PaymentIntents created for embedded card forms
+pub const EMBEDDED_PAYMENT_FLOW: &str = "payment_intent";
+
+// Card payments (including Apple Pay / Google Pay) are paid on completion;
+// delayed methods complete unpaid and are credited on async_payment_succeeded
+pub fn on_checkout_session_paid<'a>(event: &'a Event, ctx: &'a WebhookContext) -> EventHandlerResult<'a> {
+    Box::pin(async move {
+        if let EventObject::CheckoutSession(sess) = &event.data.object {
+            if sess.payment_status == CheckoutSessionPaymentStatus::Unpaid {
+                info!("checkout session {} completed but payment is still pending, waiting", sess.id);
+            } else {
+                credit_checkout_session(sess, &ctx.webhook_service, &ctx.mongodb).await?;
+            }
+        }
+        Ok(())
+    })
+}
+
+pub fn on_checkout_session_async_payment_failed<'a>(event: &'a Event, ctx: &'a WebhookContext) -> EventHandlerResult<'a> {
+    Box::pin(async move {
+        if let EventObject::CheckoutSession(sess) = &event.data.object {
+            info!("checkout session {} delayed payment failed", sess.id);
+            // Never going to be credited, so stop showing it as processing
+            ctx.mongodb.resolve_pending_deposit(sess.id.as_str(), PendingDepositStatus::Expired).await
+                .map_err(|e| WebhookError::DatabaseError(e.to_string()))?;
         }
+        Ok(())
+    })
+}
+
+pub fn on_checkout_session_expired<'a>(event: &'a Event, ctx: &'a WebhookContext) -> EventHandlerResult<'a> {
+    Box::pin(async move {
+        if let EventObject::CheckoutSession(sess) = &event.data.object {
+            info!("checkout session {} expired", sess.id);
+            ctx.mongodb.resolve_pending_deposit(sess.id.as_str(), PendingDepositStatus::Expired).await
+                .map_err(|e| WebhookError::DatabaseError(e.to_string()))?;
+        }
+        Ok(())
+    })
+}
+
+pub fn on_payment_intent_succeeded<'a>(event: &'a Event, ctx: &'a WebhookContext) -> EventHandlerResult<'a> {
+    Box::pin(async move {
+        if let EventObject::PaymentIntent(pi) = &event.data.object {
+            // Checkout sessions create PaymentIntents too; those are credited by
+            // checkout.session.completed, so only embedded-form intents are handled here
+            if pi.metadata.get("flow").map(String::as_str) == Some(EMBEDDED_PAYMENT_FLOW) {
+                credit_payment_intent(pi, &ctx.webhook_service, &ctx.mongodb).await?;
+            } else {
+                info!("payment_intent.succeeded {} belongs to a checkout session, skipping", pi.id);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Credit the wallet for a completed checkout session and record the deposit. The credit
+/// is reserved under the session ID before tokens move, so concurrent deliveries, webhook
+/// retries and admin replays never credit twice. Returns the new deposit, if any.
+pub async fn credit_checkout_session(
+    sess: &CheckoutSession,
+    webhook_service: &WebhookService,
+    mongodb_service: &MongoDBService,
+) -> Result<Option<DepositRecord>, WebhookError> {
+    let session_id = &sess.id;
+
+    if let Some(existing) = mongodb_service.get_deposit_by_session_id(session_id.as_str()).await
+        .map_err(|e| WebhookError::DatabaseError(e.to_string()))? {
+        info!("Session {} already credited (deposit {:?}), skipping", session_id, existing.id);
+        mark_pending_completed(session_id.as_str(), mongodb_service).await;
+        return Ok(None);
+    }
+
+    info!("received checkout.session.completed → {}", session_id);
+    let empty = Metadata::new();
+    let deposit = credit_stripe_payment(
+        StripePayment::CheckoutSession(session_id.as_str()),
+        sess.metadata.as_ref().unwrap_or(&empty),
+        session_wallet_address(sess).unwrap_or("none"),
+        sess.amount_total.unwrap_or(0),
+        session_email(sess),
+        webhook_service,
+        mongodb_service,
+    ).await?;
+    if deposit.is_some() {
+        mark_pending_completed(session_id.as_str(), mongodb_service).await;
     }
+    Ok(deposit)
 }
 
-async fn process_stripe_purchases_webhook(
-    req: &HttpRequest,
-    payload: &web::Bytes,
-    webhook_service: web::Data<WebhookService>,
-    mongodb_service: web::Data<MongoDBService>,
-) -> Result<(), WebhookError> {
-    let payload_str = std::str::from_utf8(payload.as_ref())
-        .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
-
-    let stripe_signature = get_header_value(&req, "Stripe-Signature")
-        .ok_or_else(|| WebhookError::MissingSignature)?;
-
-    let event = Webhook::construct_event(
-        payload_str,
-        stripe_signature,
-        webhook_service.get_stripe_purchases_secret(),
-    )?;
-
-    match event.type_ {
-        EventType::CheckoutSessionCompleted => {
-            if let EventObject::CheckoutSession(sess) = event.data.object {
-                let session_id = &sess.id;
-
-                // Get user's wallet address from metadata or client reference ID
-                // Payment links with custom fields will populate the metadata
-                let client_ref = sess
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("user_wallet_address"))
-                    .map(String::as_str)
-                    .or_else(|| sess.client_reference_id.as_deref())
-                    .unwrap_or("none");
-
-                // Get total amount
-                let total = sess
-                    .amount_total
-                    .unwrap_or(0);
-
-                // Get token symbol from metadata
-                let token_symbol = sess
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("token_symbol"))
-                    .map(String::as_str)
-                    .unwrap_or("unknown");
-                
-                // Also get token name for logging
-                let token_name = sess
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("token_name"))
-                    .map(String::as_str)
-                    .unwrap_or("unknown");
-
-                info!("received checkout.session.completed → {}", session_id);
-                info!("from id: {}", client_ref);
-                info!("for amount: {} cents", total);
-                info!("for token: {} ({})", token_name, token_symbol);
-                
-                // Check if this is a USD topup
-                // USD payments without a connected account are topups
-                let is_usd = token_symbol == "USD";
-                let has_connected_account = sess
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("connected_account_id"))
-                    .is_some();
-                let is_topup = is_usd && !has_connected_account;
-                
-                // Save deposit record
-                let amount_usd = total as f64 / 100.0;
-                let tokens_received = if is_topup {
-                    info!("Payment type: USD topup - full amount credited to user");
-                    total as f64 // USD 1:1
-                } else {
-                    // Calculate fee split for logging (donations only)
-                    let platform_fee = (total as f64 * 0.05).round() as i64;
-                    let amount_to_cause = total - platform_fee;
-                    info!("Payment type: Donation");
-                    info!("platform fee: {} cents (5%)", platform_fee);
-                    info!("amount to cause: {} cents (95%)", amount_to_cause);
-
-                    // With destination charges, Stripe automatically handles the transfer
-                    // No manual transfer needed - the connected account receives funds minus our 5% fee
-                    let connected_account_id = sess
-                        .metadata
-                        .as_ref()
-                        .and_then(|m| m.get("connected_account_id"))
-                        .map(String::as_str);
-                        
-                    if let Some(account_id) = connected_account_id {
-                        info!("Payment uses destination charges - Stripe will automatically transfer {} cents to account {}", amount_to_cause, account_id);
-                    }
-                    
-                    // For donations, we need to calculate tokens received based on bonding curve
-                    // This will be filled in by the credit_account_with_fee_split response
-                    0.0 // Placeholder - actual amount set after token minting
-                };
-
-                // Only process if we have a valid wallet address
-                if client_ref != "none" && !client_ref.is_empty() {
-                    let actual_tokens_received = if is_topup {
-                        // For USD topups, credit 1:1 without fees
-                        info!("Processing USD topup - no fees applied");
-                        webhook_service.credit_account(
-                            token_symbol,
-                            total,
-                            client_ref,
-                        ).await?;
-                        total as f64
-                    } else {
-                        // For donations, apply fee split
-                        info!("Processing donation - applying 5% platform fee");
-                        webhook_service.credit_account_with_fee_split(
-                            token_symbol,
-                            total,
-                            client_ref,
-                        ).await?
-                    };
-                    
-                    // Get token image URL
-                    let token_image_url = if token_symbol != "USD" && token_symbol != "unknown" {
-                        match mongodb_service.get_cause_by_token_symbol(token_symbol).await {
-                            Ok(Some(cause)) => cause.token_image_url,
-                            _ => None
-                        }
-                    } else {
-                        None // USD deposits don't have an image
-                    };
-                    
-                    // Save deposit record
-                    let deposit = DepositRecord {
-                        id: None,
-                        wallet_address: client_ref.to_string(),
-                        token_symbol: token_symbol.to_string(),
-                        token_image_url,
-                        amount_deposited_usd: amount_usd,
-                        amount_tokens_received: actual_tokens_received,
-                        created_at: chrono::Utc::now().timestamp(),
-                    };
-                    
-                    if let Err(e) = mongodb_service.save_deposit_record(deposit).await {
-                        error!("Failed to save deposit record: {:?}", e);
-                        // Don't fail the webhook, just log
-                    }
-                } else {
-                    error!("No wallet address provided for session {}, skipping token distribution", session_id);
-                }
+/// Credit the wallet for a succeeded embedded-form PaymentIntent. Same fee split as
+/// checkout sessions; the credit is reserved under the PaymentIntent ID before tokens
+/// move, so concurrent deliveries and retries never credit twice.
+pub async fn credit_payment_intent(
+    pi: &PaymentIntent,
+    webhook_service: &WebhookService,
+    mongodb_service: &MongoDBService,
+) -> Result<Option<DepositRecord>, WebhookError> {
+    let intent_id = &pi.id;
+
+    if let Some(existing) = mongodb_service.get_deposit_by_payment_intent_id(intent_id.as_str()).await
+        .map_err(|e| WebhookError::DatabaseError(e.to_string()))? {
+        info!("PaymentIntent {} already credited (deposit {:?}), skipping", intent_id, existing.id);
+        return Ok(None);
+    }
+
+    info!("received payment_intent.succeeded → {}", intent_id);
+    let wallet = pi.metadata.get("user_wallet_address").map(String::as_str).unwrap_or("none");
+    credit_stripe_payment(
+        StripePayment::PaymentIntent(intent_id.as_str()),
+        &pi.metadata,
+        wallet,
+        pi.amount_received,
+        pi.receipt_email.as_deref(),
+        webhook_service,
+        mongodb_service,
+    ).await
+}
+
+/// The Stripe object that paid for a deposit
+#[derive(Clone, Copy)]
+enum StripePayment<'a> {
+    CheckoutSession(&'a str),
+    PaymentIntent(&'a str),
+}
+
+impl StripePayment<'_> {
+    fn id(&self) -> &str {
+        match self {
+            StripePayment::CheckoutSession(id) | StripePayment::PaymentIntent(id) => id,
+        }
+    }
+
+    /// Key the payment's credit is reserved under
+    fn reservation_key(&self) -> String {
+        match self {
+            StripePayment::CheckoutSession(id) => format!("checkout:{}", id),
+            StripePayment::PaymentIntent(id) => format!("payment_intent:{}", id),
+        }
+    }
+}
+
+// Shared by checkout sessions and PaymentIntents once they are known to be uncredited
+async fn credit_stripe_payment(
+    payment: StripePayment<'_>,
+    metadata: &Metadata,
+    client_ref: &str,
+    total: i64,
+    donor_email: Option<&str>,
+    webhook_service: &WebhookService,
+    mongodb_service: &MongoDBService,
+) -> Result<Option<DepositRecord>, WebhookError> {
+    let payment_id = payment.id();
+
+    // Get token symbol from metadata
+    let token_symbol = metadata
+        .get("token_symbol")
+        .map(String::as_str)
+        .unwrap_or("unknown");
+
+    // Also get token name for logging
+    let token_name = metadata
+        .get("token_name")
+        .map(String::as_str)
+        .unwrap_or("unknown");
+
+    info!("from id: {}", client_ref);
+    info!("for amount: {} cents", total);
+    info!("for token: {} ({})", token_name, token_symbol);
+
+    // Check if this is a USD topup
+    // USD payments without a connected account are topups
+    let is_usd = token_symbol == "USD";
+    let connected_account_id = metadata
+        .get("connected_account_id")
+        .map(String::as_str);
+    let is_topup = is_usd && connected_account_id.is_none();
+
+    // Save deposit record
+    let amount_usd = total as f64 / 100.0;
+    if is_topup {
+        info!("Payment type: USD topup - full amount credited to user");
+    } else {
+        // Calculate fee split for logging (donations only)
+        let platform_fee = (total as f64 * 0.05).round() as i64;
+        let amount_to_cause = total - platform_fee;
+        info!("Payment type: Donation");
+        info!("platform fee: {} cents (5%)", platform_fee);
+        info!("amount to cause: {} cents (95%)", amount_to_cause);
+
+        // With destination charges, Stripe automatically handles the transfer
+        // No manual transfer needed - the connected account receives funds minus our 5% fee
+        if let Some(account_id) = connected_account_id {
+            info!("Payment uses destination charges - Stripe will automatically transfer {} cents to account {}", amount_to_cause, account_id);
+        }
+    }
+
+    // Only process if we have a valid wallet address
+    if client_ref != "none" && !client_ref.is_empty() {
+        let reservation = CreditReservation::new(payment.reservation_key(), client_ref, token_symbol, total);
+        if !reserve_stripe_credit(&reservation, mongodb_service).await? {
+            return Ok(None);
+        }
+
+        let credited = if is_topup {
+            // For USD topups, credit 1:1 without fees
+            info!("Processing USD topup - no fees applied");
+            webhook_service.credit_account(
+                token_symbol,
+                total,
+                client_ref,
+            ).await
+        } else {
+            // For donations, apply fee split
+            info!("Processing donation - applying 5% platform fee");
+            webhook_service.credit_account_with_fee_split(
+                token_symbol,
+                total,
+                client_ref,
+            ).await
+        };
+        let receipt = match credited {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                settle_failed_stripe_credit(&reservation.key, &e, mongodb_service).await;
+                return Err(e);
+            }
+        };
+        let settled = mongodb_service.transition_credit_reservation(
+            &reservation.key,
+            CreditReservationStatus::Pending,
+            CreditReservationStatus::Credited,
+            doc! { "executor_tx_id": receipt.executor_tx_id.clone() },
+        ).await;
+        if let Err(e) = settled {
+            error!("Failed to mark credit {} credited: {:?}", reservation.key, e);
+        }
+
+        // Get token image URL
+        let token_image_url = if token_symbol != "USD" && token_symbol != "unknown" {
+            match mongodb_service.get_cause_by_token_symbol(token_symbol).await {
+                Ok(Some(cause)) => cause.token_image_url,
+                _ => None
             }
+        } else {
+            None // USD deposits don't have an image
+        };
+
+        // Save deposit record
+        let (stripe_session_id, stripe_payment_intent_id) = match payment {
+            StripePayment::CheckoutSession(id) => (Some(id.to_string()), None),
+            StripePayment::PaymentIntent(id) => (None, Some(id.to_string())),
+        };
+        let attribution = DonationAttribution::from_metadata(metadata);
+        let deposit = DepositRecord {
+            id: None,
+            wallet_address: client_ref.to_string(),
+            token_symbol: token_symbol.to_string(),
+            token_image_url,
+            amount_deposited_usd: amount_usd,
+            amount_tokens_received: receipt.tokens,
+            created_at: chrono::Utc::now().timestamp(),
+            stripe_session_id,
+            stripe_payment_intent_id,
+            executor_tx_id: receipt.executor_tx_id,
+            manual_credit: None,
+            referrer: attribution.referrer,
+            campaign_id: attribution.campaign_id,
+            fundraiser_id: attribution.fundraiser_id,
+        };
+
+        if let Err(e) = mongodb_service.save_deposit_record(deposit.clone()).await {
+            error!("Failed to save deposit record: {:?}", e);
+            // Don't fail the webhook, just log
         }
-        EventType::PaymentIntentSucceeded => {
-            if let EventObject::PaymentIntent(pi) = event.data.object {
-                info!("received payment_intent.succeeded → {}", pi.id);
-                info!("amount: {} {}", pi.amount, pi.currency);
-                
-                // For now, just log it. You can add token crediting logic here later
+        webhook_service.deposit_credited(&deposit).await;
+
+        // The donor is already credited, so a matching problem must not fail the webhook
+        if !is_topup {
+            match donor_email {
+                Some(email) => webhook_service.send_donation_receipt(email, &deposit, &receipt, payment_id).await,
+                None => info!("No donor email for payment {}, not sending a receipt", payment_id),
+            }
+            if let Err(e) = webhook_service.apply_matching_pools(payment_id, token_symbol, total, client_ref).await {
+                error!("Failed to apply matching pools for payment {}: {:?}", payment_id, e);
+            }
+            if let Err(e) = webhook_service.record_round_contributions(payment_id, token_symbol, total, client_ref).await {
+                error!("Failed to record funding round contributions for payment {}: {:?}", payment_id, e);
             }
         }
-        other => info!("unhandled stripe event type in purchases webhook: {:?}", other),
+        Ok(Some(deposit))
+    } else if let Some(email) = donor_email {
+        hold_unclaimed_deposit(payment, metadata, total, email, webhook_service, mongodb_service).await?;
+        Ok(None)
+    } else {
+        error!("No wallet address or email for payment {}, skipping token distribution", payment_id);
+        Ok(None)
+    }
+}
+
+/// Reserve a Stripe payment's credit before any tokens move, so concurrent or retried
+/// deliveries can't credit it twice. Returns false if it's already credited, or failed and
+/// waiting for an admin; fails while another delivery is still crediting it, so Stripe retries.
+async fn reserve_stripe_credit(reservation: &CreditReservation, mongodb_service: &MongoDBService) -> Result<bool, WebhookError> {
+    let db_err = |e: crate::models::ApiError| WebhookError::DatabaseError(e.to_string());
+    if mongodb_service.reserve_credit(reservation).await.map_err(db_err)? {
+        return Ok(true);
     }
+    match mongodb_service.get_credit_reservation(&reservation.key).await.map_err(db_err)?.map(|existing| existing.status) {
+        Some(CreditReservationStatus::Credited) => {
+            info!("Credit {} already applied, skipping", reservation.key);
+            Ok(false)
+        },
+        Some(CreditReservationStatus::Failed) => {
+            error!("Credit {} failed earlier and is waiting for an admin to resolve it, skipping", reservation.key);
+            Ok(false)
+        },
+        // Still being credited, or released since the insert: either way try again later
+        Some(CreditReservationStatus::Pending) | None => Err(WebhookError::CreditInProgress(reservation.key.clone())),
+    }
+}
+
+/// Release a failed credit's reservation when no tokens moved, so a retry can credit it;
+/// otherwise hold it as failed for an admin
+async fn settle_failed_stripe_credit(key: &str, err: &WebhookError, mongodb_service: &MongoDBService) {
+    let settled = if err.credited_nothing() {
+        mongodb_service.release_credit_reservation(key, CreditReservationStatus::Pending).await.map(|_| ())
+    } else {
+        error!("Credit {} may have landed, holding it for an admin: {}", key, err);
+        mongodb_service.transition_credit_reservation(
+            key,
+            CreditReservationStatus::Pending,
+            CreditReservationStatus::Failed,
+            doc! { "failure_reason": err.to_string() },
+        ).await.map(|_| ())
+    };
+    if let Err(e) = settled {
+        error!("Failed to settle credit {} after a failed transfer: {:?}", key, e);
+    }
+}
 
+/// Keep a payment nobody can be credited for yet against the donor's email, and send them
+/// a link to claim it
+async fn hold_unclaimed_deposit(
+    payment: StripePayment<'_>,
+    metadata: &Metadata,
+    total: i64,
+    email: &str,
+    webhook_service: &WebhookService,
+    mongodb_service: &MongoDBService,
+) -> Result<(), WebhookError> {
+    let (stripe_session_id, stripe_payment_intent_id) = match payment {
+        StripePayment::CheckoutSession(id) => (Some(id.to_string()), None),
+        StripePayment::PaymentIntent(id) => (None, Some(id.to_string())),
+    };
+    let unclaimed = UnclaimedDeposit {
+        id: None,
+        payment_id: payment.id().to_string(),
+        stripe_session_id,
+        stripe_payment_intent_id,
+        email: email.trim().to_lowercase(),
+        token_symbol: metadata.get("token_symbol").cloned().unwrap_or_else(|| "unknown".to_string()),
+        amount_cents: total,
+        metadata: metadata.clone(),
+        status: UnclaimedDepositStatus::Unclaimed,
+        created_at: chrono::Utc::now().timestamp(),
+        claimed_by: None,
+        claimed_at: None,
+        failure_reason: None,
+    };
+    let created = mongodb_service.create_unclaimed_deposit(&unclaimed).await
+        .map_err(|e| WebhookError::DatabaseError(e.to_string()))?;
+    if !created {
+        info!("Payment {} is already held for its donor, skipping", payment.id());
+        return Ok(());
+    }
+    info!("No wallet address for payment {}, holding {} cents of {} until it's claimed", payment.id(), total, unclaimed.token_symbol);
+    if let Err(e) = webhook_service.send_claim_link(email).await {
+        error!("Failed to send claim link for payment {}: {}", payment.id(), e);
+    }
     Ok(())
 }
 
-fn get_header_value<'b>(req: &'b HttpRequest, key: &'b str) -> Option<&'b str> {
-    req.headers().get(key)?.to_str().ok()
-}
\ No newline at end of file
+/// Credit a held deposit to the wallet claiming it, as its webhook would have. The caller
+/// reserves it first; payments that were credited some other way in the meantime are skipped.
+pub async fn credit_unclaimed_deposit(
+    unclaimed: &UnclaimedDeposit,
+    wallet_address: &str,
+    webhook_service: &WebhookService,
+    mongodb_service: &MongoDBService,
+) -> Result<Option<DepositRecord>, WebhookError> {
+    let (payment, existing) = match (&unclaimed.stripe_session_id, &unclaimed.stripe_payment_intent_id) {
+        (Some(session_id), _) => (
+            StripePayment::CheckoutSession(session_id),
+            mongodb_service.get_deposit_by_session_id(session_id).await,
+        ),
+        (None, Some(intent_id)) => (
+            StripePayment::PaymentIntent(intent_id),
+            mongodb_service.get_deposit_by_payment_intent_id(intent_id).await,
+        ),
+        (None, None) => return Err(WebhookError::InvalidPayload(format!("Unclaimed deposit for {} has no Stripe payment", unclaimed.payment_id))),
+    };
+    if let Some(existing) = existing.map_err(|e| WebhookError::DatabaseError(e.to_string()))? {
+        info!("Payment {} already credited (deposit {:?}), skipping claim", unclaimed.payment_id, existing.id);
+        return Ok(None);
+    }
+
+    info!("Crediting held payment {} to {}", unclaimed.payment_id, wallet_address);
+    credit_stripe_payment(
+        payment,
+        &unclaimed.metadata,
+        wallet_address,
+        unclaimed.amount_cents,
+        Some(&unclaimed.email),
+        webhook_service,
+        mongodb_service,
+    ).await
+}
+
+// The pending entry only drives the wallet's "processing" row, so failures are just logged
+async fn mark_pending_completed(session_id: &str, mongodb_service: &MongoDBService) {
+    if let Err(e) = mongodb_service.resolve_pending_deposit(session_id, PendingDepositStatus::Completed).await {
+        error!("Failed to complete pending deposit for session {}: {:?}", session_id, e);
+    }
+}
+
+/// Email the donor entered at checkout, or the one the session was created with
+pub fn session_email(sess: &CheckoutSession) -> Option<&str> {
+    sess.customer_details
+        .as_ref()
+        .and_then(|details| details.email.as_deref())
+        .or(sess.customer_email.as_deref())
+}
+
+/// Wallet a checkout session pays into: metadata (set by our own sessions and by
+/// payment links with custom fields) or else the client reference ID
+pub fn session_wallet_address(sess: &CheckoutSession) -> Option<&str> {
+    sess.metadata
+        .as_ref()
+        .and_then(|m| m.get("user_wallet_address"))
+        .map(String::as_str)
+        .or(sess.client_reference_id.as_deref())
+}
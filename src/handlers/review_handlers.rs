@@ -0,0 +1,54 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use crate::auth::AuthenticatedUser;
+use crate::services::MongoDBService;
+use crate::models::{ApiError, PaymentStatus, Review, CreateReviewRequest, ReviewQuery, MAX_REVIEW_COMMENT_CHARS};
+
+/// Rate the vendor of a completed payment. Only the paying customer can review, once per payment.
+pub async fn create_review(
+    auth: AuthenticatedUser,
+    payment_id: web::Path<String>,
+    payload: web::Json<CreateReviewRequest>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    if !(1..=5).contains(&payload.rating) {
+        return Err(ApiError::ValidationError("Rating must be between 1 and 5".to_string()));
+    }
+    let comment = payload.comment.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    if comment.map_or(false, |c| c.chars().count() > MAX_REVIEW_COMMENT_CHARS) {
+        return Err(ApiError::ValidationError(format!("Comment must be at most {} characters", MAX_REVIEW_COMMENT_CHARS)));
+    }
+
+    let payment = db.get_payment(&payment_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", payment_id)))?;
+    if payment.customer_address.as_deref() != Some(auth.wallet_address.as_str()) {
+        return Err(ApiError::Forbidden("Only the paying customer can review this payment".to_string()));
+    }
+    if payment.status != PaymentStatus::Completed {
+        return Err(ApiError::Conflict("Only completed payments can be reviewed".to_string()));
+    }
+
+    let review = db.create_review(Review {
+        id: None,
+        payment_id: payment.payment_id,
+        vendor_address: payment.vendor_address,
+        customer_address: auth.wallet_address.clone(),
+        customer_username: payment.customer_username,
+        rating: payload.rating,
+        comment: comment.map(str::to_string),
+        created_at: Utc::now().timestamp(),
+    }).await?;
+
+    log::info!("{} rated vendor {} {}/5 for payment {}", review.customer_address, review.vendor_address, review.rating, review.payment_id);
+    Ok(HttpResponse::Created().json(review))
+}
+
+/// A vendor's reviews, newest first, with the overall rating
+pub async fn get_vendor_reviews(
+    vendor_address: web::Path<String>,
+    query: web::Query<ReviewQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let page = db.get_vendor_reviews(&vendor_address, &query).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
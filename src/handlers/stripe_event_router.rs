@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::future::LocalBoxFuture;
+use log::{info, error};
+use stripe::{Event, EventObject, EventType};
+
+use crate::handlers::{purchase_webhook_handlers, webhook_handlers};
+use crate::models::{WebhookError, WebhookEndpoint, ProcessedStripeEvent, WebhookJob, WebhookJobStatus};
+use crate::services::{CauseService, MongoDBService, WebhookService};
+
+/// Services available to event handlers
+pub struct WebhookContext {
+    pub webhook_service: web::Data<WebhookService>,
+    pub mongodb: web::Data<MongoDBService>,
+    pub cause_service: web::Data<CauseService>,
+}
+
+pub type EventHandlerResult<'a> = LocalBoxFuture<'a, Result<(), WebhookError>>;
+pub type EventHandler = for<'a> fn(&'a Event, &'a WebhookContext) -> EventHandlerResult<'a>;
+
+/// Routing table from (endpoint, event type) to handler. Signature verification,
+/// deduplication, queueing and logging happen here, and retries and failure recording
+/// in the webhook workers, so handlers only apply the event.
+#[derive(Default)]
+pub struct StripeEventRouter {
+    routes: HashMap<(WebhookEndpoint, EventType), EventHandler>,
+}
+
+impl StripeEventRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on(mut self, endpoint: WebhookEndpoint, event_type: EventType, handler: EventHandler) -> Self {
+        if self.routes.insert((endpoint, event_type), handler).is_some() {
+            panic!("Duplicate {} webhook handler for {:?}", endpoint, event_type);
+        }
+        self
+    }
+
+    /// Verify, deduplicate and queue one webhook delivery for the webhook workers,
+    /// answering 202 once it's stored. Deliveries that can't be verified or stored are
+    /// answered with a 500 so Stripe retries them.
+    pub async fn handle(&self, endpoint: WebhookEndpoint, req: &HttpRequest, payload: &web::Bytes, ctx: &WebhookContext) -> HttpResponse {
+        info!("=== STRIPE {} WEBHOOK RECEIVED ===", endpoint.to_string().to_uppercase());
+        match self.accept(endpoint, req, payload, ctx).await {
+            Ok(_) => HttpResponse::Accepted().finish(),
+            Err(e) => {
+                error!("{} webhook error: {:?}", endpoint, e);
+                HttpResponse::InternalServerError().body(format!("Webhook error: {:?}", e))
+            }
+        }
+    }
+
+    async fn accept(&self, endpoint: WebhookEndpoint, req: &HttpRequest, payload: &web::Bytes, ctx: &WebhookContext) -> Result<(), WebhookError> {
+        let payload_str = std::str::from_utf8(payload.as_ref())
+            .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+
+        let stripe_signature = req.headers().get("Stripe-Signature")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(WebhookError::MissingSignature)?;
+
+        let event = ctx.webhook_service.construct_event(endpoint, payload_str, stripe_signature)?;
+        let event_id = event.id.to_string();
+        let event_type = format!("{:?}", event.type_);
+
+        let now = chrono::Utc::now().timestamp();
+        let job = WebhookJob {
+            id: None,
+            event_id: event_id.clone(),
+            event_type: event_type.clone(),
+            endpoint,
+            payload: payload_str.to_string(),
+            ordering_key: ordering_key(&event),
+            status: WebhookJobStatus::Queued,
+            attempts: 0,
+            last_error: None,
+            lease_owner: None,
+            lease_expires_at: None,
+            next_attempt_at: now,
+            created_at: now,
+            updated_at: now,
+        };
+        // Stripe delivers at least once. Jobs are unique by event ID and kept after they're
+        // applied, so inserting one is the dedup: a redelivery finds it and is acknowledged.
+        let queued = ctx.mongodb.enqueue_webhook_job(&job).await
+            .map_err(|e| WebhookError::DatabaseError(e.to_string()))?;
+        if queued {
+            info!("{} event {} ({}) queued for {}", endpoint, event_id, event_type, job.ordering_key);
+        } else {
+            info!("{} event {} ({}) already received, skipping", endpoint, event_id, event_type);
+        }
+        Ok(())
+    }
+
+    /// Apply a queued event a worker has claimed. Returns whether a handler was registered;
+    /// failures are left to the caller to retry or record.
+    pub async fn apply(&self, job: &WebhookJob, ctx: &WebhookContext) -> Result<bool, WebhookError> {
+        // The signature was verified when the event was queued
+        let event: Event = serde_json::from_str(&job.payload)
+            .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+        self.dispatch(job.endpoint, &event, ctx).await
+    }
+
+    /// Run the handler registered for an already-verified event. Shared by the webhooks and
+    /// admin replay. Returns whether a handler was registered.
+    pub async fn dispatch(&self, endpoint: WebhookEndpoint, event: &Event, ctx: &WebhookContext) -> Result<bool, WebhookError> {
+        match self.routes.get(&(endpoint, event.type_)) {
+            Some(handler) => {
+                handler(event, ctx).await?;
+                Ok(true)
+            }
+            None => {
+                info!("unhandled stripe event type in {} webhook: {:?}", endpoint, event.type_);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Record a successfully applied event. A failure here only means a redelivery is
+/// processed again, which the handlers' own idempotency makes safe.
+pub async fn mark_processed(endpoint: WebhookEndpoint, event_id: &str, event_type: &str, ctx: &WebhookContext) {
+    let processed = ProcessedStripeEvent {
+        id: None,
+        event_id: event_id.to_string(),
+        event_type: event_type.to_string(),
+        endpoint,
+        processed_at: chrono::Utc::now(),
+    };
+    if let Err(e) = ctx.mongodb.mark_stripe_event_processed(&processed).await {
+        error!("Failed to mark stripe event {} processed: {:?}", event_id, e);
+    }
+}
+
+/// Events with the same key are applied in the order they arrived: the wallet a purchase
+/// credits, or else the Stripe object the event is about
+fn ordering_key(event: &Event) -> String {
+    match &event.data.object {
+        EventObject::CheckoutSession(sess) => purchase_webhook_handlers::session_wallet_address(sess)
+            .map(str::to_string)
+            .unwrap_or_else(|| sess.id.to_string()),
+        EventObject::PaymentIntent(pi) => pi.metadata.get("user_wallet_address")
+            .cloned()
+            .unwrap_or_else(|| pi.id.to_string()),
+        EventObject::Account(account) => account.id.to_string(),
+        EventObject::Payout(_) | EventObject::Balance(_) => event.account.as_ref()
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| event.id.to_string()),
+        _ => event.id.to_string(),
+    }
+}
+
+/// Every Stripe event the backend handles, by endpoint
+pub fn stripe_event_router() -> StripeEventRouter {
+    StripeEventRouter::new()
+        // Connect: cause onboarding
+        .on(WebhookEndpoint::Connect, EventType::AccountUpdated, webhook_handlers::on_account_updated)
+        // Connect: causes' payouts
+        .on(WebhookEndpoint::Connect, EventType::PayoutPaid, webhook_handlers::on_payout_event)
+        .on(WebhookEndpoint::Connect, EventType::PayoutFailed, webhook_handlers::on_payout_event)
+        .on(WebhookEndpoint::Connect, EventType::BalanceAvailable, webhook_handlers::on_payout_event)
+        // Purchases: donations and top-ups
+        .on(WebhookEndpoint::Purchases, EventType::CheckoutSessionCompleted, purchase_webhook_handlers::on_checkout_session_paid)
+        .on(WebhookEndpoint::Purchases, EventType::CheckoutSessionAsyncPaymentSucceeded, purchase_webhook_handlers::on_checkout_session_paid)
+        .on(WebhookEndpoint::Purchases, EventType::CheckoutSessionAsyncPaymentFailed, purchase_webhook_handlers::on_checkout_session_async_payment_failed)
+        .on(WebhookEndpoint::Purchases, EventType::CheckoutSessionExpired, purchase_webhook_handlers::on_checkout_session_expired)
+        .on(WebhookEndpoint::Purchases, EventType::PaymentIntentSucceeded, purchase_webhook_handlers::on_payment_intent_succeeded)
+}
+
+pub async fn handle_stripe_webhook(
+    req: HttpRequest,
+    payload: web::Bytes,
+    router: web::Data<StripeEventRouter>,
+    webhook_service: web::Data<WebhookService>,
+    mongodb: web::Data<MongoDBService>,
+    cause_service: web::Data<CauseService>,
+) -> HttpResponse {
+    let ctx = WebhookContext { webhook_service, mongodb, cause_service };
+    router.handle(WebhookEndpoint::Connect, &req, &payload, &ctx).await
+}
+
+pub async fn handle_stripe_purchases_webhook(
+    req: HttpRequest,
+    payload: web::Bytes,
+    router: web::Data<StripeEventRouter>,
+    webhook_service: web::Data<WebhookService>,
+    mongodb: web::Data<MongoDBService>,
+    cause_service: web::Data<CauseService>,
+) -> HttpResponse {
+    let ctx = WebhookContext { webhook_service, mongodb, cause_service };
+    router.handle(WebhookEndpoint::Purchases, &req, &payload, &ctx).await
+}
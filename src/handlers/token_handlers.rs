@@ -1,10 +1,14 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::ETAG;
 use log::{info, error};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use crate::services::TokenService;
+use crate::services::{MongoDBService, TokenService, WalletService};
 use delta_executor_sdk::base::crypto::Ed25519PrivKey;
-use crate::models::ApiError;
+use crate::models::{ApiError, TokenHolders, TokenHoldersQuery};
+use crate::models::token::{DEFAULT_TOP_HOLDERS, MAX_TOP_HOLDERS};
+use crate::utils::holders::holder_distribution;
+use crate::response_caching::{is_fresh, not_modified};
 
 #[derive(Deserialize)]
 pub struct CreateTokenRequest {
@@ -24,6 +28,19 @@ pub struct CreateTokenResponse {
     pub image_url: Option<String>,
 }
 
+/// Every token, tagged with an ETag so clients can revalidate instead of refetching
+pub async fn list_tokens(
+    req: HttpRequest,
+    mongodb: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let etag = mongodb.tokens_etag().await?;
+    if is_fresh(&req, &etag) {
+        return Ok(not_modified(&etag));
+    }
+    let tokens = mongodb.get_all_tokens().await?;
+    Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(tokens))
+}
+
 /// Create a new token with initial supply
 pub async fn create_token(
     token_service: web::Data<TokenService>,
@@ -67,4 +84,36 @@ pub async fn create_token(
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+/// How many user wallets hold a token and how concentrated it is among the largest holders,
+/// from the holdings projection
+pub async fn get_token_holders(
+    mongodb: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+    symbol: web::Path<String>,
+    query: web::Query<TokenHoldersQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let top = query.top.unwrap_or(DEFAULT_TOP_HOLDERS);
+    if top > MAX_TOP_HOLDERS {
+        return Err(ApiError::ValidationError(format!("top must be at most {}", MAX_TOP_HOLDERS)));
+    }
+    let token = mongodb.get_token_by_symbol(&symbol).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Token not found: {}", symbol)))?;
+
+    let holdings = wallet_service.token_holdings(&token.token_id).await?;
+    let balances: Vec<u64> = holdings.iter().map(|holding| holding.balance as u64).collect();
+    // As of the projection's latest change, which is what the figures reflect
+    let computed_at = holdings.iter().map(|holding| holding.updated_at).max()
+        .unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let (holder_count, total_held, top_holders) = holder_distribution(&balances, top);
+    Ok(HttpResponse::Ok().json(TokenHolders {
+        token_symbol: symbol.into_inner(),
+        token_id: token.token_id,
+        holder_count,
+        total_held,
+        top_holders_share: top_holders.iter().map(|holder| holder.share).sum(),
+        top_holders,
+        computed_at,
+    }))
+}
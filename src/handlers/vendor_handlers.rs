@@ -1,7 +1,15 @@
+use std::collections::HashMap;
 use actix_web::{web, HttpResponse};
 use log::{info, error};
 use serde_json::json;
-use crate::services::MongoDBService;
+use crate::auth::AuthenticatedUser;
+use crate::services::{MongoDBService, VendorService};
+use mongodb::bson::{self, Document};
+use crate::models::{ApiError, DailySettlementReport, DailyReportQuery, GeoPoint, NearbyVendor, NearbyVendorsQuery, UpdateVendorProfileRequest, User, VendorOnboardingRequest, PaymentCodeNamespace, PaymentCodeNamespaceRequest, DEFAULT_SHORT_CODE_LENGTH};
+use crate::utils::validation::Validate;
+use crate::utils::report_period::{parse_report_date, day_bounds};
+use crate::utils::geo::{validate_coordinates, validate_opening_hours, haversine_distance_m};
+use crate::models::payment::VendorPaymentsQuery;
 
 /// Get all partnered vendors
 pub async fn get_partnered_vendors(mongodb: web::Data<MongoDBService>) -> HttpResponse {
@@ -20,4 +28,281 @@ pub async fn get_partnered_vendors(mongodb: web::Data<MongoDBService>) -> HttpRe
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Default and largest search radius for the nearby directory, in meters
+const DEFAULT_NEARBY_RADIUS_M: f64 = 5_000.0;
+const MAX_NEARBY_RADIUS_M: f64 = 50_000.0;
+const MAX_NEARBY_RESULTS: i64 = 100;
+
+/// Vendors accepting index wallet payments around a point, closest first
+pub async fn get_nearby_vendors(
+    mongodb: web::Data<MongoDBService>,
+    query: web::Query<NearbyVendorsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    validate_coordinates(query.lat, query.lng).map_err(ApiError::ValidationError)?;
+    let radius = query.radius.unwrap_or(DEFAULT_NEARBY_RADIUS_M);
+    if !radius.is_finite() || radius <= 0.0 || radius > MAX_NEARBY_RADIUS_M {
+        return Err(ApiError::ValidationError(format!("Radius must be between 0 and {} meters", MAX_NEARBY_RADIUS_M)));
+    }
+    let category = query.category.as_deref().map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty());
+
+    let vendors = mongodb.get_nearby_vendors(query.lat, query.lng, radius, category.as_deref(), MAX_NEARBY_RESULTS).await?;
+    let vendors: Vec<NearbyVendor> = vendors.into_iter()
+        .filter_map(|vendor| {
+            let location = vendor.location.as_ref()?;
+            let distance_m = haversine_distance_m(query.lat, query.lng, location.lat(), location.lng()).round();
+            Some(NearbyVendor { vendor, distance_m })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(vendors))
+}
+
+/// Update a vendor's directory profile. Setting a location lists the vendor in the nearby
+/// directory; an empty `hours` list clears the opening hours.
+pub async fn update_vendor_profile(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    vendor_address: web::Path<String>,
+    payload: web::Json<UpdateVendorProfileRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    let payload = payload.into_inner();
+    let mut set = Document::new();
+    let mut unset = Document::new();
+
+    for (field, value, max_chars) in [
+        ("business_name", payload.business_name, 100),
+        ("description", payload.description, 500),
+    ] {
+        if let Some(value) = value {
+            let value = value.trim();
+            if value.chars().count() > max_chars {
+                return Err(ApiError::ValidationError(format!("{} must be at most {} characters", field, max_chars)));
+            }
+            if value.is_empty() {
+                unset.insert(field, "");
+            } else {
+                set.insert(field, value);
+            }
+        }
+    }
+    if let Some(category) = payload.category {
+        let category = category.trim().to_lowercase();
+        if category.chars().count() > 40 {
+            return Err(ApiError::ValidationError("category must be at most 40 characters".to_string()));
+        }
+        if category.is_empty() {
+            unset.insert("category", "");
+        } else {
+            set.insert("category", category);
+        }
+    }
+    match (payload.lat, payload.lng) {
+        (Some(lat), Some(lng)) => {
+            validate_coordinates(lat, lng).map_err(ApiError::ValidationError)?;
+            let location = bson::to_bson(&GeoPoint::new(lat, lng))
+                .map_err(|e| ApiError::InternalError(format!("Failed to serialize location: {}", e)))?;
+            set.insert("location", location);
+        },
+        (None, None) => {},
+        _ => return Err(ApiError::ValidationError("lat and lng must be given together".to_string())),
+    }
+    if let Some(hours) = payload.hours {
+        let hours = validate_opening_hours(&hours).map_err(ApiError::ValidationError)?;
+        if hours.is_empty() {
+            unset.insert("hours", "");
+        } else {
+            let hours = bson::to_bson(&hours)
+                .map_err(|e| ApiError::InternalError(format!("Failed to serialize hours: {}", e)))?;
+            set.insert("hours", hours);
+        }
+    }
+
+    let vendor = mongodb.update_vendor_profile(&vendor_address, set, unset).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Vendor {} not found", vendor_address)))?;
+    info!("Updated directory profile for vendor {}", vendor_address);
+    Ok(HttpResponse::Ok().json(vendor))
+}
+
+/// List a vendor's payments for the POS order queue and end-of-day reconciliation
+pub async fn get_vendor_payments(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    vendor_address: web::Path<String>,
+    query: web::Query<VendorPaymentsQuery>,
+) -> HttpResponse {
+    if let Err(e) = auth.require_self_or_admin(&vendor_address) {
+        return HttpResponse::Forbidden().json(json!({
+            "error": "Forbidden",
+            "details": e.to_string()
+        }));
+    }
+    info!("Fetching payments for vendor {}: {:?}", vendor_address, query);
+    
+    match mongodb.get_vendor_payments(&vendor_address, &query).await {
+        Ok(page) => {
+            info!("Found {} payments for vendor {}", page.payments.len(), vendor_address);
+            HttpResponse::Ok().json(page)
+        },
+        Err(ApiError::ValidationError(msg)) => {
+            HttpResponse::BadRequest().json(json!({
+                "error": "Invalid query",
+                "details": msg
+            }))
+        },
+        Err(e) => {
+            error!("Error fetching vendor payments: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to fetch vendor payments",
+                "details": e.to_string()
+            }))
+        }
+    }
+}
+
+/// End-of-day settlement report; past days are served from cache
+pub async fn get_vendor_daily_report(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    vendor_address: web::Path<String>,
+    query: web::Query<DailyReportQuery>,
+) -> HttpResponse {
+    if let Err(e) = auth.require_self_or_admin(&vendor_address) {
+        return HttpResponse::Forbidden().json(json!({
+            "error": "Forbidden",
+            "details": e.to_string()
+        }));
+    }
+    
+    let today = chrono::Utc::now().date_naive();
+    let date = match parse_report_date(query.date.as_deref(), today) {
+        Ok(date) => date,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(json!({
+                "error": "Invalid date",
+                "details": msg
+            }));
+        }
+    };
+    let date_str = date.format("%Y-%m-%d").to_string();
+    info!("Building daily report for vendor {} on {}", vendor_address, date_str);
+    
+    match build_or_load_daily_report(&mongodb, &vendor_address, &date_str, date < today, day_bounds(date)).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            error!("Error building daily report: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to build daily report",
+                "details": e.to_string()
+            }))
+        }
+    }
+}
+
+// Today's report is always rebuilt since payments are still coming in
+async fn build_or_load_daily_report(
+    mongodb: &MongoDBService,
+    vendor_address: &str,
+    date: &str,
+    day_closed: bool,
+    (period_start, period_end): (i64, i64),
+) -> Result<DailySettlementReport, ApiError> {
+    if day_closed {
+        if let Some(mut report) = mongodb.get_cached_daily_report(vendor_address, date).await? {
+            revalue_daily_report(mongodb, &mut report).await?;
+            return Ok(report);
+        }
+    }
+    
+    let report = mongodb.build_daily_report(vendor_address, date, period_start, period_end).await?;
+    
+    if day_closed {
+        if let Err(e) = mongodb.cache_daily_report(&report).await {
+            error!("Failed to cache daily report for {} on {}: {}", vendor_address, date, e);
+        }
+    }
+    Ok(report)
+}
+
+/// Value a cached report's receipts at the tokens' current market valuations; the day's
+/// receipts don't change, but the market does
+async fn revalue_daily_report(mongodb: &MongoDBService, report: &mut DailySettlementReport) -> Result<(), ApiError> {
+    let token_keys: Vec<String> = report.tokens.iter().map(|token| token.token_key.clone()).collect();
+    let valuations: HashMap<String, f64> = mongodb.get_tokens_by_ids(&token_keys).await?
+        .into_iter()
+        .map(|token| (token.token_id, token.market_valuation))
+        .collect();
+    for token in &mut report.tokens {
+        token.current_market_valuation = valuations.get(&token.token_key).copied().unwrap_or(1.0);
+        token.current_market_value = token.gross_receipts * token.current_market_valuation;
+    }
+    report.market_valued_at = chrono::Utc::now().timestamp();
+    Ok(())
+}
+
+async fn vendor_user(mongodb: &MongoDBService, vendor_address: &str) -> Result<User, ApiError> {
+    let user = mongodb.get_user_by_wallet(vendor_address).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Vendor {} not found", vendor_address)))?;
+    if user.user_type != "vendor" {
+        return Err(ApiError::Forbidden(format!("{} is not a vendor", vendor_address)));
+    }
+    Ok(user)
+}
+
+/// Start or resume Stripe onboarding for a vendor, creating their Express account the first
+/// time. The email defaults to the one on the vendor's profile.
+pub async fn start_vendor_onboarding(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    vendor_service: web::Data<VendorService>,
+    vendor_address: web::Path<String>,
+    payload: web::Json<VendorOnboardingRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    payload.validate()?;
+    let user = vendor_user(&mongodb, &vendor_address).await?;
+    let (stripe_account, onboarding_url) = vendor_service.start_onboarding(&user, &payload).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "onboarding_url": onboarding_url,
+        "stripe_account": stripe_account,
+    })))
+}
+
+/// A vendor's connected account, refreshed from Stripe
+pub async fn get_vendor_stripe_status(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    vendor_service: web::Data<VendorService>,
+    vendor_address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    let user = vendor_user(&mongodb, &vendor_address).await?;
+    let stripe_account = user.stripe_account
+        .ok_or_else(|| ApiError::NotFound("The vendor hasn't connected a Stripe account".to_string()))?;
+    let stripe_account = vendor_service.refresh_account(stripe_account).await?;
+    Ok(HttpResponse::Ok().json(stripe_account))
+}
+
+/// Claim a slug for the vendor's payment codes, or change it or the code length. New
+/// payments are then entered as `{slug}-{short_code}`; the global codes of earlier payments
+/// keep working.
+pub async fn set_payment_code_namespace(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    vendor_address: web::Path<String>,
+    payload: web::Json<PaymentCodeNamespaceRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&vendor_address)?;
+    payload.validate()?;
+    vendor_user(&mongodb, &vendor_address).await?;
+
+    let namespace = PaymentCodeNamespace {
+        slug: payload.slug.trim().to_lowercase(),
+        code_length: payload.code_length.unwrap_or(DEFAULT_SHORT_CODE_LENGTH),
+        updated_at: chrono::Utc::now().timestamp(),
+    };
+    mongodb.set_payment_code_namespace(&vendor_address, &namespace).await?;
+    info!("Vendor {} now has payment codes under {}", vendor_address, namespace.slug);
+    Ok(HttpResponse::Ok().json(namespace))
+}
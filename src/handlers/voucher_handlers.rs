@@ -0,0 +1,96 @@
+use actix_web::{web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use std::str::FromStr;
+use crate::auth::AuthenticatedUser;
+use crate::services::{FeatureFlagService, MongoDBService, VoucherService};
+use crate::models::{ApiError, Role, Voucher, VoucherStatus, VoucherFunding, VoucherPreview, VoucherQuery, CreateVoucherRequest, FundVoucherRequest};
+use crate::utils::payment_code::normalize_voucher_code;
+
+/// Issue a voucher. Admins issue from the central vault's holdings and the voucher is
+/// active straight away; vendors get back a `funding_transaction` to sign and submit to
+/// `POST /vouchers/{id}/fund`.
+pub async fn create_voucher(
+    auth: AuthenticatedUser,
+    payload: web::Json<CreateVoucherRequest>,
+    voucher_service: web::Data<VoucherService>,
+    feature_flags: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, ApiError> {
+    feature_flags.require("vouchers")?;
+    let funding = if auth.is_admin() {
+        VoucherFunding::CentralVault
+    } else {
+        auth.require_role(Role::Vendor)?;
+        VoucherFunding::Issuer
+    };
+    let voucher = voucher_service.issue(&auth.wallet_address, funding, &payload).await?;
+    Ok(HttpResponse::Created().json(voucher))
+}
+
+/// Vouchers the signed-in wallet issued, optionally filtered by `status`
+pub async fn get_issued_vouchers(
+    auth: AuthenticatedUser,
+    query: web::Query<VoucherQuery>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let status = query.status.as_deref()
+        .map(VoucherStatus::from_str)
+        .transpose()
+        .map_err(ApiError::ValidationError)?;
+    let vouchers = db.get_issued_vouchers(&auth.wallet_address, status).await?;
+    Ok(HttpResponse::Ok().json(vouchers))
+}
+
+/// What a code is worth, for anyone holding it
+pub async fn get_voucher(
+    code: web::Path<String>,
+    db: web::Data<MongoDBService>,
+) -> Result<HttpResponse, ApiError> {
+    let voucher = db.get_voucher_by_code(&normalize_voucher_code(&code)).await?
+        .ok_or_else(|| ApiError::NotFound("Voucher not found".to_string()))?;
+    Ok(HttpResponse::Ok().json(VoucherPreview::from(&voucher)))
+}
+
+/// Redeem a code into the signed-in wallet
+pub async fn redeem_voucher(
+    auth: AuthenticatedUser,
+    code: web::Path<String>,
+    voucher_service: web::Data<VoucherService>,
+) -> Result<HttpResponse, ApiError> {
+    let voucher = voucher_service.redeem(&normalize_voucher_code(&code), &auth.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(VoucherPreview::from(&voucher)))
+}
+
+/// Submit the issuer's signed transfer into escrow
+pub async fn fund_voucher(
+    auth: AuthenticatedUser,
+    voucher_id: web::Path<String>,
+    payload: web::Json<FundVoucherRequest>,
+    db: web::Data<MongoDBService>,
+    voucher_service: web::Data<VoucherService>,
+) -> Result<HttpResponse, ApiError> {
+    let voucher = load_voucher(&db, &voucher_id).await?;
+    auth.require_self_or_admin(&voucher.issuer_address)?;
+    let voucher = voucher_service.fund(&voucher, &payload.signed_transaction).await?;
+    Ok(HttpResponse::Ok().json(voucher))
+}
+
+/// Withdraw an unredeemed voucher, refunding a vendor-funded one to the vendor
+pub async fn cancel_voucher(
+    auth: AuthenticatedUser,
+    voucher_id: web::Path<String>,
+    db: web::Data<MongoDBService>,
+    voucher_service: web::Data<VoucherService>,
+) -> Result<HttpResponse, ApiError> {
+    let voucher = load_voucher(&db, &voucher_id).await?;
+    auth.require_self_or_admin(&voucher.issuer_address)?;
+    let voucher = voucher_service.cancel(&voucher).await?;
+    log::info!("Voucher {} cancelled by {}", voucher_id, auth.wallet_address);
+    Ok(HttpResponse::Ok().json(voucher))
+}
+
+async fn load_voucher(db: &MongoDBService, voucher_id: &str) -> Result<Voucher, ApiError> {
+    let object_id = ObjectId::parse_str(voucher_id)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid voucher ID: {}", e)))?;
+    db.get_voucher(&object_id).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Voucher {} not found", voucher_id)))
+}
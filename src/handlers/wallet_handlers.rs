@@ -1,11 +1,17 @@
-use actix_web::{web, HttpResponse};
-use log::{info, error};
+use actix_web::{web, HttpResponse, ResponseError};
+use log::{info, warn, error};
 use serde_json::json;
 use serde::{Serialize, Deserialize};
-use crate::services::{WalletService, MongoDBService, TokenService};
+use crate::services::{WalletService, WalletError, ExecutorError, MongoDBService, TokenService, CauseService, StripeCustomerService, VaultProvisioningService};
 use crate::models::token::{TokenValuation, TokenValuationsResponse, UpdateValuationRequest};
 use crate::models::error::ApiError;
+use crate::models::{UpdatePrivacyRequest, PreferenceChange, PreferenceChangeSource};
+use crate::models::payment::{CreateTopupSessionRequest, TopupSessionResponse};
+use crate::auth::AuthenticatedUser;
 
+/// Set to "projection" when balances come from the holdings projection because the
+/// executor couldn't be reached
+pub const BALANCES_SOURCE_HEADER: &str = "X-Balances-Source";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserTokenResponse {
@@ -16,8 +22,14 @@ pub struct UserTokenResponse {
     pub token_image_url: String,
 }
 
-/// Get user balances
-pub async fn get_user_balances(wallet_address: web::Path<String>, wallet_service: web::Data<WalletService>) -> HttpResponse {
+/// Get user balances. A wallet without a vault yet has no balances rather than a 404; its
+/// vault is only provisioned when the wallet itself asks, since that spends a transfer.
+pub async fn get_user_balances(
+    auth: Option<AuthenticatedUser>,
+    wallet_address: web::Path<String>,
+    wallet_service: web::Data<WalletService>,
+    vault_provisioning: web::Data<VaultProvisioningService>,
+) -> HttpResponse {
     // Parse the public key
     let pubkey = match WalletService::parse_public_key(&wallet_address) {
         Ok(pk) => pk,
@@ -40,12 +52,29 @@ pub async fn get_user_balances(wallet_address: web::Path<String>, wallet_service
             }
         },
         Ok(None) => {
-            error!("Vault not found for public key: {}", pubkey);
-            HttpResponse::NotFound().body(format!("Vault not found for public key: {}", pubkey))
+            // Provisioning at sign-up may still be running, or the user predates it
+            if auth.is_some_and(|auth| auth.wallet_address == *wallet_address) {
+                info!("No vault yet for public key: {}, provisioning", pubkey);
+                vault_provisioning.provision_in_background(wallet_address.to_string());
+            }
+            HttpResponse::Ok().json(json!({}))
+        },
+        // The executor is briefly out of reach: answer from the holdings projection instead
+        Err(WalletError::Executor(ExecutorError::Unavailable(reason))) => {
+            warn!("Executor unavailable ({}), serving projected balances for {}", reason, pubkey);
+            match wallet_service.projected_balances(&wallet_address).await {
+                Ok(token_info) => HttpResponse::Ok()
+                    .insert_header((BALANCES_SOURCE_HEADER, "projection"))
+                    .json(token_info),
+                Err(e) => {
+                    error!("Error reading projected balances: {:?}", e);
+                    ApiError::from(e).error_response()
+                }
+            }
         },
         Err(e) => {
             error!("Error getting vault: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error getting vault: {}", e))
+            ApiError::from(e).error_response()
         }
     }
 }
@@ -106,15 +135,36 @@ pub async fn get_user_valuations(
 
 /// Update token valuation for a user
 pub async fn update_user_valuation(
+    auth: AuthenticatedUser,
     mongodb: web::Data<MongoDBService>,
     wallet_address: web::Path<String>,
     payload: web::Json<UpdateValuationRequest>,
 ) -> HttpResponse {
     info!("Updating token valuation for user: {}", wallet_address);
 
+    if let Err(e) = auth.require_self_or_admin(&wallet_address) {
+        return HttpResponse::Forbidden().json(json!({
+            "error": "Forbidden",
+            "details": e.to_string()
+        }));
+    }
+
     match mongodb.update_user_valuation(&wallet_address, &payload.symbol, payload.valuation).await {
-        Ok(_) => {
+        Ok((before, after)) => {
             info!("Successfully updated valuation for user {} and token {}", wallet_address, payload.symbol);
+            let change = PreferenceChange {
+                id: None,
+                wallet_address: wallet_address.to_string(),
+                source: PreferenceChangeSource::Single,
+                template_name: None,
+                changed_by: auth.wallet_address.clone(),
+                before,
+                after,
+                created_at: chrono::Utc::now().timestamp(),
+            };
+            if let Err(e) = mongodb.record_preference_change(&change).await {
+                error!("Failed to record preference change for {}: {}", wallet_address, e);
+            }
             HttpResponse::Ok().json(json!({
                 "status": "success",
                 "message": "Successfully updated valuation"
@@ -161,7 +211,7 @@ pub async fn get_vault(wallet_address: web::Path<String>, wallet_service: web::D
         },
         Err(e) => {
             error!("Error getting vault: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error getting vault: {}", e))
+            ApiError::from(e).error_response()
         }
     }
 }
@@ -179,6 +229,7 @@ pub async fn get_user_info(
             HttpResponse::Ok().json(json!({
                 "username": user.username,
                 "wallet_address": user.wallet_address,
+                "donate_anonymously": user.donate_anonymously,
                 "exists": true
             }))
         },
@@ -197,4 +248,89 @@ pub async fn get_user_info(
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Set whether the user's donations are shown by username on cause pages
+pub async fn update_privacy(
+    auth: AuthenticatedUser,
+    mongodb: web::Data<MongoDBService>,
+    wallet_address: web::Path<String>,
+    payload: web::Json<UpdatePrivacyRequest>,
+) -> HttpResponse {
+    if let Err(e) = auth.require_self_or_admin(&wallet_address) {
+        return HttpResponse::Forbidden().json(json!({
+            "error": "Forbidden",
+            "details": e.to_string()
+        }));
+    }
+
+    match mongodb.set_donate_anonymously(&wallet_address, payload.donate_anonymously).await {
+        Ok(_) => {
+            info!("Set donate_anonymously={} for user {}", payload.donate_anonymously, wallet_address);
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "donate_anonymously": payload.donate_anonymously
+            }))
+        },
+        Err(ApiError::NotFound(msg)) => {
+            HttpResponse::NotFound().json(json!({
+                "error": "Not found",
+                "details": msg
+            }))
+        },
+        Err(e) => {
+            error!("Failed to update privacy settings: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to update privacy settings",
+                "details": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Start a Stripe checkout to top up the wallet with USD
+pub async fn create_topup_session(
+    auth: AuthenticatedUser,
+    cause_service: web::Data<CauseService>,
+    wallet_address: web::Path<String>,
+    payload: web::Json<CreateTopupSessionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    WalletService::parse_public_key(&wallet_address)
+        .map_err(|e| ApiError::ValidationError(format!("Invalid wallet address: {}", e)))?;
+
+    let (session_id, checkout_url) = cause_service
+        .create_topup_checkout_session(&wallet_address, payload.amount_cents)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TopupSessionResponse {
+        checkout_url,
+        session_id,
+    }))
+}
+
+/// Cards saved from earlier donations and top-ups
+pub async fn list_payment_methods(
+    auth: AuthenticatedUser,
+    customer_service: web::Data<StripeCustomerService>,
+    wallet_address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require_self_or_admin(&wallet_address)?;
+    let methods = customer_service.list_payment_methods(&wallet_address).await?;
+    Ok(HttpResponse::Ok().json(methods))
+}
+
+/// Forget a saved card
+pub async fn detach_payment_method(
+    auth: AuthenticatedUser,
+    customer_service: web::Data<StripeCustomerService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (wallet_address, payment_method_id) = path.into_inner();
+    auth.require_self_or_admin(&wallet_address)?;
+    customer_service.detach_payment_method(&wallet_address, &payment_method_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "status": "success",
+        "payment_method_id": payment_method_id
+    })))
+}
@@ -1,105 +1,154 @@
-use actix_web::{web, HttpRequest, HttpResponse};
+use std::collections::BTreeMap;
 use log::{info, error};
-use stripe::{Webhook, EventObject, EventType};
+use stripe::{Balance, Event, EventObject, EventType};
 
-use crate::services::{WebhookService, CauseService};
-use crate::models::WebhookError;
+use crate::handlers::stripe_event_router::{EventHandlerResult, WebhookContext};
+use crate::models::{PayoutEvent, PayoutEventKind, WebhookError};
+use crate::services::cause_service::account_requirements;
 
-pub async fn handle_stripe_webhook(
-    req: HttpRequest,
-    payload: web::Bytes,
-    webhook_service: web::Data<WebhookService>,
-    cause_service: web::Data<CauseService>,
-) -> HttpResponse {
-    info!("=== STRIPE CONNECT WEBHOOK RECEIVED ===");
-    match process_stripe_webhook(&req, &payload, webhook_service, cause_service).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(e) => {
-            error!("Webhook error: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Webhook error: {:?}", e))
-        }
-    }
-}
-
-async fn process_stripe_webhook(
-    req: &HttpRequest,
-    payload: &web::Bytes,
-    webhook_service: web::Data<WebhookService>,
-    cause_service: web::Data<CauseService>,
-) -> Result<(), WebhookError> {
-    let payload_str = std::str::from_utf8(payload.as_ref())
-        .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
-
-    let stripe_signature = get_header_value(&req, "Stripe-Signature")
-        .ok_or_else(|| WebhookError::MissingSignature)?;
-
-    let event = Webhook::construct_event(
-        payload_str,
-        stripe_signature,
-        webhook_service.get_stripe_secret(),
-    )?;
-
-    match event.type_ {
-        EventType::AccountUpdated => {
-            if let EventObject::Account(account) = event.data.object {
-                info!("received account.updated for account: {}", account.id);
-                info!("  charges_enabled: {:?}", account.charges_enabled);
-                info!("  details_submitted: {:?}", account.details_submitted);
-                info!("  payouts_enabled: {:?}", account.payouts_enabled);
+/// account.updated: create the cause once its connected account finishes onboarding,
+/// track organizations' and vendors' onboarding, and track when payouts are enabled
+pub fn on_account_updated<'a>(event: &'a Event, ctx: &'a WebhookContext) -> EventHandlerResult<'a> {
+    Box::pin(async move {
+        if let EventObject::Account(account) = &event.data.object {
+            info!("received account.updated for account: {}", account.id);
+            info!("  charges_enabled: {:?}", account.charges_enabled);
+            info!("  details_submitted: {:?}", account.details_submitted);
+            info!("  payouts_enabled: {:?}", account.payouts_enabled);
+            
+            // Check if onboarding is complete
+            if ctx.cause_service.onboarded(account) {
+                
+                info!("Account {} is fully onboarded!", account.id);
                 
-                // Check if onboarding is complete
-                if account.charges_enabled.unwrap_or(false) && 
-                   account.details_submitted.unwrap_or(false) {
-                    
-                    info!("Account {} is fully onboarded!", account.id);
-                    
-                    // Get draft_id from metadata
-                    if let Some(metadata) = account.metadata {
-                        if let Some(draft_id) = metadata.get("draft_id") {
-                            info!("Found draft_id in metadata: {}", draft_id);
-                            
-                            // Complete cause creation
-                            match cause_service.complete_cause_from_draft(draft_id).await {
-                                Ok(cause) => {
-                                    info!("Successfully created cause from draft: {}", cause.name);
-                                },
-                                Err(e) => {
-                                    error!("Failed to create cause from draft: {:?}", e);
-                                    // Don't fail the webhook - we can retry manually
-                                }
+                // Get draft_id from metadata
+                if let Some(metadata) = &account.metadata {
+                    if let Some(draft_id) = metadata.get("draft_id") {
+                        info!("Found draft_id in metadata: {}", draft_id);
+                        
+                        // Complete cause creation
+                        match ctx.cause_service.complete_cause_from_draft(draft_id).await {
+                            Ok(cause) => {
+                                info!("Successfully created cause from draft: {}", cause.name);
+                            },
+                            Err(e) => {
+                                error!("Failed to create cause from draft: {:?}", e);
+                                // Don't fail the webhook - we can retry manually
                             }
-                        } else {
-                            info!("No draft_id found in metadata for account {}", account.id);
                         }
+                    } else {
+                        info!("No draft_id found in metadata for account {}", account.id);
                     }
-                } else {
-                    info!("Account {} not fully onboarded yet", account.id);
                 }
+            } else {
+                info!("Account {} not fully onboarded yet", account.id);
+            }
+            
+            // Organizations onboard once; their causes are created against the account afterwards
+            if account.metadata.as_ref().map_or(false, |metadata| metadata.contains_key("organization_id")) {
+                match ctx.mongodb.set_organization_onboarded(&account.id.to_string(), ctx.cause_service.onboarded(account)).await {
+                    Ok(true) => info!("Updated onboarding status of organization with account {}", account.id),
+                    Ok(false) => info!("No organization found for account {}", account.id),
+                    Err(e) => error!("Failed to update organization onboarding status: {:?}", e),
+                }
+            }
+            
+            // Vendors' accounts are stored on their user record
+            if account.metadata.as_ref().map_or(false, |metadata| metadata.contains_key("vendor_address")) {
+                match ctx.mongodb.update_vendor_stripe_status(
+                    &account.id.to_string(),
+                    ctx.cause_service.onboarded(account),
+                    account.charges_enabled.unwrap_or(false),
+                    account.payouts_enabled.unwrap_or(false),
+                    &account_requirements(account),
+                ).await {
+                    Ok(true) => info!("Updated Stripe status of vendor with account {}", account.id),
+                    Ok(false) => info!("No vendor found for account {}", account.id),
+                    Err(e) => error!("Failed to update vendor Stripe status: {:?}", e),
+                }
+            }
+            
+            // Keep what Stripe still needs current for the cause page and admins
+            if let Err(e) = ctx.cause_service.update_causes_requirements(account).await {
+                error!("Failed to update causes with Stripe requirements: {:?}", e);
+            }
+            
+            // Always check for payouts_enabled updates (can happen after onboarding)
+            if account.payouts_enabled.unwrap_or(false) {
+                info!("Account {} has payouts_enabled", account.id);
                 
-                // Always check for payouts_enabled updates (can happen after onboarding)
-                if account.payouts_enabled.unwrap_or(false) {
-                    info!("Account {} has payouts_enabled", account.id);
-                    
-                    // Update any existing causes with this account ID
-                    match cause_service.update_causes_payouts_status(&account.id.to_string(), true).await {
-                        Ok(count) => {
-                            if count > 0 {
-                                info!("Updated {} causes with payouts_enabled status", count);
-                            }
-                        },
-                        Err(e) => {
-                            error!("Failed to update causes with payouts status: {:?}", e);
+                // Update any existing causes with this account ID
+                match ctx.cause_service.update_causes_payouts_status(&account.id.to_string(), true).await {
+                    Ok(count) => {
+                        if count > 0 {
+                            info!("Updated {} causes with payouts_enabled status", count);
                         }
+                    },
+                    Err(e) => {
+                        error!("Failed to update causes with payouts status: {:?}", e);
                     }
                 }
             }
         }
-        other => info!("unhandled stripe connect event type: {:?}", other),
-    }
+        Ok(())
+    })
+}
+
+/// payout.paid, payout.failed and balance.available on a connected account: record them
+/// against the account's causes, emailing their creators about failed payouts. A balance
+/// is recorded once per currency it holds.
+pub fn on_payout_event<'a>(event: &'a Event, ctx: &'a WebhookContext) -> EventHandlerResult<'a> {
+    Box::pin(async move {
+        let Some(account_id) = event.account.as_ref().map(|id| id.to_string()) else {
+            info!("{:?} event {} is for the platform account, ignoring", event.type_, event.id);
+            return Ok(());
+        };
+        let new_event = |kind: PayoutEventKind, amount_cents: i64, currency: String| PayoutEvent {
+            id: None,
+            event_id: event.id.to_string(),
+            kind,
+            stripe_account_id: account_id.clone(),
+            cause_ids: Vec::new(),
+            payout_id: None,
+            amount_cents,
+            currency,
+            arrival_date: None,
+            failure_code: None,
+            failure_message: None,
+            created_at: event.created,
+        };
+        let payout_events = match &event.data.object {
+            EventObject::Payout(payout) => {
+                let kind = if event.type_ == EventType::PayoutFailed { PayoutEventKind::PayoutFailed } else { PayoutEventKind::PayoutPaid };
+                vec![PayoutEvent {
+                    payout_id: Some(payout.id.to_string()),
+                    arrival_date: Some(payout.arrival_date),
+                    failure_code: payout.failure_code.clone(),
+                    failure_message: payout.failure_message.clone(),
+                    ..new_event(kind, payout.amount, payout.currency.to_string())
+                }]
+            },
+            EventObject::Balance(balance) => available_by_currency(balance).into_iter()
+                .map(|(currency, amount_cents)| new_event(PayoutEventKind::BalanceAvailable, amount_cents, currency))
+                .collect(),
+            _ => return Err(WebhookError::InvalidPayload(format!("{:?} event {} has no payout or balance", event.type_, event.id))),
+        };
 
-    Ok(())
+        for payout_event in payout_events {
+            info!("received {} for account {}: {} {}", payout_event.kind, payout_event.stripe_account_id, payout_event.amount_cents, payout_event.currency);
+            ctx.cause_service.record_payout_event(payout_event).await
+                .map_err(|e| WebhookError::DatabaseError(e.to_string()))?;
+        }
+        Ok(())
+    })
 }
 
-fn get_header_value<'b>(req: &'b HttpRequest, key: &'b str) -> Option<&'b str> {
-    req.headers().get(key)?.to_str().ok()
+/// A balance's available amounts summed per currency, since an account holding several
+/// currencies reports each separately
+fn available_by_currency(balance: &Balance) -> BTreeMap<String, i64> {
+    let mut totals = BTreeMap::new();
+    for amount in &balance.available {
+        *totals.entry(amount.currency.to_string()).or_insert(0) += amount.amount;
+    }
+    totals
 }
@@ -0,0 +1,16 @@
+//! The backend's modules, shared by the server binary and the integration tests in `tests/`
+
+pub mod models;
+pub mod handlers;
+pub mod routes;
+pub mod services;
+pub mod utils;
+pub mod config;
+pub mod auth;
+pub mod seed;
+pub mod graphql;
+pub mod grpc;
+pub mod response_signing;
+pub mod response_caching;
+pub mod access_log;
+pub mod request_digest;
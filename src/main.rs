@@ -6,10 +6,11 @@ use actix_web::{
     HttpServer, 
     web, 
     HttpRequest,
-    HttpResponse, 
     Responder,
     error::{ErrorInternalServerError, ErrorBadRequest},
-    middleware::DefaultHeaders
+    ResponseError,
+    middleware::{Compress, Condition, DefaultHeaders, ErrorHandlers},
+    http::StatusCode,
 };
 use actix_cors::Cors;
 use actix_web::web::Bytes;
@@ -21,23 +22,17 @@ use delta_executor_sdk::base::core::Shard;
 use delta_executor_sdk::base::vaults::{VaultId, TokenKind, Vault, ReadableVault};
 use delta_executor_sdk::base::verifiable::{debit_allowance::{DebitAllowance, SignedDebitAllowance}, VerifiableType};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-mod models;
-mod handlers;
-mod routes;
-mod services;
-mod utils;
-mod config;
-use services::{MongoDBService, TokenService, WalletService, CauseService, WebhookService};
-use config::KeyConfig;
+use index_wallets_backend::{models, handlers, routes, services, utils, config, auth, seed, graphql, grpc, response_signing, access_log, request_digest};
+use actix_web::dev::Service;
+use response_signing::ResponseSigner;
+use access_log::AccessLog;
+use request_digest::RequestDigest;
+use utils::response_signature::RESPONSE_SIGNATURE_HEADER;
+use services::{ExecutorClient, MongoDBService, TokenService, WalletService, CauseService, WebhookService, ReconciliationService, EmailService, DraftReminderService, FundingRoundService, PaymentIntentService, StripeCustomerService, PaymentFinalityService, VaultProvisioningService, PushService, VoucherService, EscrowService, AuthorizationService, DisputeService, PaymentScheduleService, InvoiceService, WebhookQueueService, FeatureFlagService, JobScheduler, JobService, CampaignService, FundraiserService, OrganizationService, VendorService, SharedState, StripeApi, LiveStripe};
+use config::{KeyConfig, PublishedKeys, PaymentMethodConfig, ConnectConfig, ExecutorPolicy, HttpClientConfig, BundlePolicy, BodyLimits, CorsConfig, SandboxConfig, SchedulerConfig, SANDBOX_HEADER, parse_webhook_secrets};
+use utils::name_filter::NameFilter;
 use stripe::Client;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SignedTransaction {
-    signed_debit_allowance: SignedDebitAllowance,
-}
-
-
 async fn initialize_usd_token(token_service: &TokenService) -> Result<(), Box<dyn std::error::Error>> {
     info!("Checking if USD token exists...");
     
@@ -96,10 +91,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         error!("STRIPE_SECRET_KEY not found in environment: {}", e);
         "".to_string()
     });
-    let stripe_webhook_secret = env::var("STRIPE_WEBHOOK_SECRET").unwrap_or_else(|_| "".to_string());
-    let stripe_purchases_webhook_secret = env::var("STRIPE_PURCHASES_WEBHOOK_SECRET").unwrap_or_else(|_| "".to_string());
+    // Comma-separated so a new secret can be added before the old one is removed
+    let stripe_webhook_secrets = parse_webhook_secrets(&env::var("STRIPE_WEBHOOK_SECRET").unwrap_or_default());
+    let stripe_purchases_webhook_secrets = parse_webhook_secrets(&env::var("STRIPE_PURCHASES_WEBHOOK_SECRET").unwrap_or_default());
 
     env_logger::init_from_env(env_logger::Env::new().default_filter_or(log_level));
+    // Wallet addresses and emails are masked in logs unless LOG_REDACTION=off
+    utils::redaction::set_enabled(env::var("LOG_REDACTION").map_or(true, |v| !v.trim().eq_ignore_ascii_case("off")));
     
     // Log Stripe configuration status
     if stripe_api.is_empty() {
@@ -112,9 +110,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
     
+    // A sandbox must only use test keys, its own database and the test executor, and
+    // production only live keys
+    let sandbox = SandboxConfig::from_env();
+    let stripe_publishable_key = env::var("STRIPE_PUBLISHABLE_KEY").unwrap_or_default();
+    if let Err(e) = sandbox.check(
+        env::var("ENVIRONMENT").ok().as_deref(),
+        &[stripe_api.as_str(), stripe_publishable_key.as_str()],
+        env::var("EXECUTOR_URL").ok().as_deref(),
+    ) {
+        error!("Refusing to start: {}", e);
+        return Err(e.into());
+    }
+    if sandbox.enabled {
+        log::warn!("Running in SANDBOX_MODE with database {}", sandbox.database());
+    }
+    
     let mongodb = MongoDBService::init()
         .await
         .expect("Failed to initialize MongoDB");
+    services::run_migrations(&mongodb)
+        .await
+        .expect("Failed to apply database migrations");
     let mongodb_data = web::Data::new(mongodb);
     
     // Load keypairs from environment variables or JSON files
@@ -123,61 +140,467 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     info!("Central vault pubkey: {}", key_config.central_vault_pubkey);
     info!("Network goods vault pubkey: {}", key_config.network_goods_vault_pubkey);
+    info!("Escrow vault pubkey: {}", key_config.escrow_vault_pubkey);
+    
+    let response_signer = key_config.api_signing_key.as_ref().map(|key| web::Data::new(ResponseSigner::new(key)));
+    if let Some(signer) = &response_signer {
+        info!("Signing responses with API signing key: {}", signer.public_key());
+    }
+    let published_keys = web::Data::new(PublishedKeys::from_env(
+        &key_config,
+        response_signer.as_ref().map(|signer| signer.public_key()),
+    ));
+
+    // One pooled HTTP client for all outbound calls (Stripe uses its own)
+    let http_config = HttpClientConfig::from_env();
+    info!("Outbound HTTP client: {:?}", http_config);
+    let http_client = http_config.build_client()
+        .expect("Failed to build HTTP client");
+
+    // Rate limits and events shared between replicas through Redis when REDIS_URL is set
+    let shared_state = SharedState::connect(env::var("REDIS_URL").ok().as_deref()).await
+        .expect("Failed to set up shared state");
+    let shared_state_data = web::Data::new(shared_state.clone());
 
-    let wallet_service = web::Data::new(WalletService::new(mongodb_data.clone()));
+    // One client for all executor calls so they share a circuit breaker
+    let executor_client = ExecutorClient::new(http_client.clone(), ExecutorPolicy::from_env(), shared_state.clone());
+    executor_client.start_cache_sync();
+    let executor_client_data = web::Data::new(executor_client.clone());
+
+    let wallet_service = web::Data::new(WalletService::new(mongodb_data.clone(), executor_client.clone()));
     
     let token_service = web::Data::new(TokenService::new(
         mongodb_data.clone(),
-        key_config.central_vault_keypair.clone()
+        key_config.central_vault_keypair.clone(),
+        key_config.token_key_master_key,
+        executor_client
     ));
     
     initialize_usd_token(&token_service).await?;
     
-    let stripe_client = stripe::Client::new(&stripe_api);
-    let stripe_client_arc = Arc::new(stripe_client.clone());
-    let stripe_client_data = web::Data::new(stripe_client);
+    // `--seed` fills a local database with demo data instead of starting the server
+    if env::args().any(|arg| arg == "--seed") {
+        seed::run(&mongodb_data, &token_service, &key_config.central_vault_keypair).await?;
+        return Ok(());
+    }
+    
+    let vault_provisioning_service = web::Data::new(VaultProvisioningService::new(
+        wallet_service.clone(),
+        token_service.clone(),
+        key_config.central_vault_keypair.clone(),
+    ));
+    
+    let stripe: Arc<dyn StripeApi> = Arc::new(LiveStripe::new(Client::new(&stripe_api)));
+    let stripe_data: web::Data<dyn StripeApi> = web::Data::from(stripe.clone());
+
+    let email_service = web::Data::new(EmailService::new(http_client.clone()));
+    
+    // Notifications are queued by handlers and sent by push and email in batches; 0 disables delivery
+    let push_service = web::Data::new(PushService::new(mongodb_data.clone(), email_service.clone(), http_client)
+        .expect("Failed to load push notification credentials"));
+    let push_flush_interval = env::var("PUSH_FLUSH_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(1000);
+    if push_flush_interval > 0 {
+        push_service.get_ref().clone().start_scheduler(std::time::Duration::from_millis(push_flush_interval));
+    }
+
+    // Name filter: built-in lists, plus env overrides, plus the blocked_words collection
+    let mut name_filter = NameFilter::with_defaults();
+    name_filter.extend_from_env();
+    match mongodb_data.get_blocked_words().await {
+        Ok(words) => {
+            info!("Loaded {} blocked words from database", words.len());
+            for blocked in words {
+                name_filter.add(blocked.kind, &blocked.word);
+            }
+        }
+        Err(e) => error!("Failed to load blocked words, using built-in lists only: {}", e),
+    }
+
+    let payment_methods = PaymentMethodConfig::from_env();
+    info!("Checkout payment methods: {:?}, configuration: {:?}, Apple Pay: {}, Google Pay: {}",
+        payment_methods.payment_method_types,
+        payment_methods.payment_method_configuration,
+        payment_methods.apple_pay,
+        payment_methods.google_pay
+    );
+
+    let stripe_customer_service = web::Data::new(StripeCustomerService::new(
+        Arc::new(mongodb_data.get_ref().clone()),
+        stripe.clone(),
+    ));
 
     let cause_service = web::Data::new(CauseService::new(
         Arc::new(mongodb_data.get_ref().clone()),
         Arc::new(token_service.get_ref().clone()),
-        stripe_client_arc.clone()
-    ));
+        stripe.clone(),
+        email_service.clone().into_inner(),
+        name_filter,
+        payment_methods.clone(),
+        stripe_customer_service.clone().into_inner(),
+        ConnectConfig::from_env(),
+    ).expect("Failed to load cause service configuration"));
 
+    // Feature switches for this environment, reloaded so toggles on one replica reach the others
+    let feature_flags = web::Data::new(FeatureFlagService::new(mongodb_data.clone(), &FeatureFlagService::environment_from_env()));
+    feature_flags.refresh().await.expect("Failed to load feature flags");
+    let feature_flag_refresh = env::var("FEATURE_FLAG_REFRESH_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(30);
+    if feature_flag_refresh > 0 {
+        feature_flags.get_ref().clone().start_refresh(std::time::Duration::from_secs(feature_flag_refresh));
+    }
+    
     let webhook_service = web::Data::new(WebhookService::new(
-        stripe_webhook_secret,
-        stripe_purchases_webhook_secret,
+        stripe_webhook_secrets,
+        stripe_purchases_webhook_secrets,
         Arc::new(token_service.get_ref().clone()),
         Arc::new(mongodb_data.get_ref().clone()),
         key_config.central_vault_keypair.clone(),
-        key_config.network_goods_vault_keypair.clone()
+        key_config.network_goods_vault_keypair.clone(),
+        key_config.matching_vault_keypair.clone(),
+        push_service.clone().into_inner(),
+        email_service.clone().into_inner(),
+    ));
+    
+    let reconciliation_service = web::Data::new(ReconciliationService::new(
+        mongodb_data.clone(),
+        wallet_service.clone(),
+        env::var("RECONCILIATION_TOLERANCE").ok().and_then(|v| v.parse().ok()).unwrap_or(1),
+    ));
+    
+    let payment_intent_service = web::Data::new(PaymentIntentService::new(
+        stripe.clone(),
+        payment_methods,
+        stripe_customer_service.clone().into_inner(),
+    ));
+    
+    let funding_round_service = web::Data::new(FundingRoundService::new(
+        mongodb_data.clone(),
+        webhook_service.clone(),
+    ));
+    
+    // Periodic sweeps run on whichever replica holds the scheduler lease. Each job's interval
+    // comes from its own variable (0 disables it) unless JOB_SCHEDULES gives it a cron expression.
+    let interval_secs = |name: &str, default: u64| std::time::Duration::from_secs(
+        env::var(name).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default)
+    );
+    let mut scheduler = JobScheduler::new(mongodb_data.clone(), SchedulerConfig::from_env());
+    
+    // Matches whose transfer failed keep their pool budget and are paid on a later run
+    let service = webhook_service.clone();
+    scheduler.register("match_retry", interval_secs("MATCH_RETRY_INTERVAL_SECS", 300), move || {
+        let service = service.clone();
+        async move { service.retry_failed_matches().await; Ok(()) }
+    });
+    
+    // Round payouts a crashed distribution left processing are failed for the next run, or held
+    // for an admin when their credit may have landed
+    let service = funding_round_service.clone();
+    scheduler.register("round_payout_recovery", interval_secs("ROUND_PAYOUT_RECOVERY_INTERVAL_SECS", 300), move || {
+        let service = service.clone();
+        async move {
+            let recovered = service.recover_stuck_payouts().await?;
+            if recovered > 0 {
+                info!("Recovered {} round payouts left processing", recovered);
+            }
+            Ok(())
+        }
+    });
+    
+    // Admins can still trigger runs manually
+    let sample_size = env::var("RECONCILIATION_SAMPLE_SIZE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .or(Some(100));
+    let service = reconciliation_service.clone();
+    scheduler.register("reconciliation", interval_secs("RECONCILIATION_INTERVAL_SECS", 3600), move || {
+        let service = service.clone();
+        async move {
+            let run = service.run(sample_size, None).await?;
+            info!(
+                "Reconciliation {} checked {} wallets, found {} issues, corrected {} projected holdings",
+                run.run_id, run.wallets_checked, run.issues_found, run.holdings_corrected
+            );
+            Ok(())
+        }
+    });
+    
+    // Failed cause creations are retried with backoff; disabled, they're left to POST /causes/{id}/retry
+    let service = cause_service.clone();
+    scheduler.register("cause_retry", interval_secs("CAUSE_RETRY_INTERVAL_SECS", 60), move || {
+        let service = service.clone();
+        async move { service.retry_due_causes().await; Ok(()) }
+    });
+    
+    // Scheduled features are dropped once their featured_until passes; disabled, they're
+    // filtered out of GET /causes/featured without being unset
+    let service = cause_service.clone();
+    scheduler.register("featured_expiry", interval_secs("FEATURED_EXPIRY_INTERVAL_SECS", 300), move || {
+        let service = service.clone();
+        async move { service.expire_featured_causes().await; Ok(()) }
+    });
+    
+    // Reminders go out this many hours before a cause draft expires; 0 disables them
+    let draft_reminder_hours = env::var("DRAFT_REMINDER_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(6);
+    if draft_reminder_hours > 0 {
+        let service = DraftReminderService::new(
+            mongodb_data.clone(),
+            email_service.clone(),
+            chrono::Duration::hours(draft_reminder_hours),
+        );
+        scheduler.register("draft_reminders", std::time::Duration::from_secs(15 * 60), move || {
+            let service = service.clone();
+            async move { service.send_due_reminders().await; Ok(()) }
+        });
+    }
+    
+    // Payments accepted by the executor stay Submitted until polled to finality
+    let service = PaymentFinalityService::new(
+        mongodb_data.clone(),
+        wallet_service.clone(),
+        push_service.clone(),
+        shared_state_data.clone(),
+    );
+    scheduler.register("payment_finality", interval_secs("EXECUTOR_STATUS_POLL_SECS", 10), move || {
+        let service = service.clone();
+        async move { service.check_submitted_payments().await; Ok(()) }
+    });
+    
+    let voucher_service = web::Data::new(VoucherService::new(
+        mongodb_data.clone(),
+        token_service.clone(),
+        wallet_service.clone(),
+        key_config.central_vault_keypair.clone(),
+    ));
+    
+    // Expired vouchers are refunded to their issuer
+    let service = voucher_service.clone();
+    scheduler.register("voucher_expiry", interval_secs("VOUCHER_EXPIRY_INTERVAL_SECS", 300), move || {
+        let service = service.clone();
+        async move { service.expire_vouchers().await; Ok(()) }
+    });
+    
+    let escrow_service = web::Data::new(EscrowService::new(
+        mongodb_data.clone(),
+        token_service.clone(),
+        key_config.escrow_vault_keypair.clone(),
+    ));
+    
+    // Held escrows past their hold period are captured for the vendor
+    let service = escrow_service.clone();
+    scheduler.register("escrow_release", interval_secs("ESCROW_RELEASE_INTERVAL_SECS", 300), move || {
+        let service = service.clone();
+        async move { service.release_due().await; Ok(()) }
+    });
+    
+    let authorization_service = web::Data::new(AuthorizationService::new(
+        mongodb_data.clone(),
+        shared_state_data.clone(),
+    ));
+    
+    // Two-phase payments the vendor didn't capture in time are voided
+    let service = authorization_service.clone();
+    scheduler.register("authorization_expiry", interval_secs("AUTHORIZATION_EXPIRY_INTERVAL_SECS", 60), move || {
+        let service = service.clone();
+        async move { service.void_expired().await; Ok(()) }
+    });
+    
+    let dispute_service = web::Data::new(DisputeService::new(
+        mongodb_data.clone(),
+        token_service.clone(),
+        escrow_service.clone(),
+        push_service.clone(),
+        key_config.central_vault_keypair.clone(),
+    ));
+    
+    let payment_schedule_service = web::Data::new(PaymentScheduleService::new(
+        mongodb_data.clone(),
+        push_service.clone(),
+    ));
+    
+    // Due runs of payment schedules get their payment code, unless recurring payments are off
+    let service = payment_schedule_service.clone();
+    let flags = feature_flags.clone();
+    scheduler.register("payment_schedules", interval_secs("PAYMENT_SCHEDULE_INTERVAL_SECS", 60), move || {
+        let service = service.clone();
+        let enabled = flags.is_enabled("recurring_payments");
+        async move {
+            if enabled {
+                service.run_due().await;
+            }
+            Ok(())
+        }
+    });
+    
+    let invoice_service = web::Data::new(InvoiceService::new(
+        mongodb_data.clone(),
+        push_service.clone(),
+    ));
+    
+    // Customers with overdue invoices are reminded
+    let service = invoice_service.clone();
+    scheduler.register("invoice_reminders", interval_secs("INVOICE_REMINDER_INTERVAL_SECS", 3600), move || {
+        let service = service.clone();
+        async move { service.send_reminders().await; Ok(()) }
+    });
+    scheduler.start();
+    
+    let bundle_policy = web::Data::new(BundlePolicy::from_env());
+    
+    // Long-running operations run as jobs clients poll at GET /jobs/{id}; any the last
+    // process left running will never finish
+    let job_service = web::Data::new(JobService::new(mongodb_data.clone()));
+    if let Err(e) = job_service.fail_stale_jobs().await {
+        error!("Failed to fail interrupted jobs: {}", e);
+    }
+    let campaign_service = web::Data::new(CampaignService::new(mongodb_data.clone()));
+    let fundraiser_service = web::Data::new(FundraiserService::new(mongodb_data.clone()));
+    let organization_service = web::Data::new(OrganizationService::new(mongodb_data.clone(), cause_service.clone()));
+    let vendor_service = web::Data::new(VendorService::new(mongodb_data.clone(), cause_service.clone(), stripe.clone()));
+    let body_limits = BodyLimits::from_env();
+    let cors_config = CorsConfig::from_env();
+    if cors_config.allows_any_origin() {
+        log::warn!("CORS allows any origin");
+    } else {
+        info!("CORS allows origins: {:?}", cors_config.allowed_origins);
+    }
+    
+    let graphql_schema = web::Data::new(graphql::build_schema(
+        mongodb_data.clone(),
+        wallet_service.clone(),
+        cause_service.clone(),
+    ));
+    
+    let stripe_event_router = web::Data::new(handlers::stripe_event_router::stripe_event_router());
+    
+    // Webhooks only queue Stripe events; these workers apply them
+    let webhook_worker_concurrency = env::var("WEBHOOK_WORKER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(4);
+    let webhook_queue_service = web::Data::new(WebhookQueueService::new(
+        stripe_event_router.clone(),
+        mongodb_data.clone(),
+        webhook_service.clone(),
+        cause_service.clone(),
+        webhook_worker_concurrency,
     ));
+    let webhook_queue_interval = env::var("WEBHOOK_QUEUE_POLL_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|ms| *ms > 0)
+        .unwrap_or(500);
+    webhook_queue_service.get_ref().clone().start_workers(std::time::Duration::from_millis(webhook_queue_interval));
+    
+    // Partners' payment calls over gRPC, through the same code as the REST routes
+    if let Some(grpc_port) = env::var("GRPC_PORT").ok().and_then(|v| v.parse::<u16>().ok()) {
+        // SERVER_HOST may be a hostname like localhost, as HttpServer::bind allows
+        let grpc_addr = tokio::net::lookup_host((host.as_str(), grpc_port)).await
+            .map_err(|e| format!("Can't resolve SERVER_HOST {}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("SERVER_HOST {} resolved to no address", host))?;
+        let payments = grpc::PaymentsService::new(
+            mongodb_data.clone(),
+            wallet_service.clone(),
+            push_service.clone(),
+            escrow_service.clone(),
+            shared_state_data.clone(),
+            bundle_policy.clone(),
+        );
+        actix_web::rt::spawn(async move {
+            if let Err(e) = payments.serve(grpc_addr).await {
+                error!("gRPC server stopped: {}", e);
+            }
+        });
+    }
     
     info!("Starting server at http://{}:{}", host, port);
     
     HttpServer::new(move || {
         // Configure CORS middleware
+        let origins = cors_config.clone();
         let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .expose_headers(vec!["content-type", "content-length", "accept"])
+            .allowed_origin_fn(move |origin, head| {
+                let origin = origin.to_str().unwrap_or_default();
+                let allowed = origins.allows_origin(origin) || CorsConfig::is_embed_path(head.uri.path());
+                if !allowed {
+                    log::warn!("CORS rejected request from origin {}", origin);
+                }
+                allowed
+            })
+            .allowed_methods(cors_config.allowed_methods.iter().map(String::as_str))
+            .allowed_headers(cors_config.allowed_headers.iter().map(String::as_str))
+            .expose_headers(vec!["content-type", "content-length", "accept", "etag", RESPONSE_SIGNATURE_HEADER, SANDBOX_HEADER])
             .max_age(3600);
 
+        let signer = response_signer.clone();
+        let signer_data = response_signer.clone();
         App::new()
+            // Inside the signer, so the JSON it writes is signed
+            .wrap(ErrorHandlers::new().handler(StatusCode::PAYLOAD_TOO_LARGE, models::error::payload_too_large))
             .wrap(cors)
+            .wrap_fn(move |req, srv| {
+                let signer = signer.clone();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await?;
+                    match signer {
+                        Some(signer) => signer.sign(response).await,
+                        None => Ok(response.map_into_boxed_body()),
+                    }
+                }
+            })
+            // Outside the signer, so signatures are over the uncompressed body
+            .wrap(Compress::default())
+            .wrap(AccessLog)
+            .wrap(RequestDigest)
+            .wrap(Condition::new(sandbox.enabled, DefaultHeaders::new().add((SANDBOX_HEADER, "true"))))
+            .configure(move |cfg| {
+                if let Some(signer) = signer_data {
+                    cfg.app_data(signer);
+                }
+            })
             .app_data(mongodb_data.clone())
             .app_data(wallet_service.clone())
             .app_data(token_service.clone())
             .app_data(cause_service.clone())
-            .app_data(stripe_client_data.clone())
+            .app_data(stripe_data.clone())
             .app_data(webhook_service.clone())
+            .app_data(stripe_event_router.clone())
+            .app_data(reconciliation_service.clone())
+            .app_data(funding_round_service.clone())
+            .app_data(payment_intent_service.clone())
+            .app_data(stripe_customer_service.clone())
+            .app_data(executor_client_data.clone())
+            .app_data(vault_provisioning_service.clone())
+            .app_data(push_service.clone())
+            .app_data(voucher_service.clone())
+            .app_data(escrow_service.clone())
+            .app_data(authorization_service.clone())
+            .app_data(dispute_service.clone())
+            .app_data(payment_schedule_service.clone())
+            .app_data(invoice_service.clone())
+            .app_data(bundle_policy.clone())
+            .app_data(feature_flags.clone())
+            .app_data(job_service.clone())
+            .app_data(campaign_service.clone())
+            .app_data(fundraiser_service.clone())
+            .app_data(organization_service.clone())
+            .app_data(vendor_service.clone())
+            .app_data(webhook_queue_service.clone())
+            .app_data(shared_state_data.clone())
+            .app_data(published_keys.clone())
+            .app_data(graphql_schema.clone())
+            .app_data(web::JsonConfig::default().limit(body_limits.json).error_handler(models::error::json_payload_error))
+            .app_data(web::PayloadConfig::new(body_limits.payload))
             .configure(routes::configure)
-            .route("/submit-signed-transaction", web::post().to(receive_signed))
-            .route("/health", web::get().to(|| async {
-                info!("Health check");
-                HttpResponse::Ok().body("OK")
-            }))
-            .route("/receive-signed", web::post().to(receive_signed))
     })
     .bind(format!("{host}:{port}"))?
     .run()
@@ -187,27 +610,3 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-
-async fn receive_signed(wallet_service: web::Data<WalletService>, payload: web::Json<SignedTransaction>) -> HttpResponse {
-    info!("Received signed debit allowance");
-    
-    // Submit the signed transaction
-    match wallet_service.submit_verifiables(vec![VerifiableType::DebitAllowance(payload.signed_debit_allowance.clone())]).await {
-        Ok(_) => {
-            info!("Successfully submitted debit allowance");
-            HttpResponse::Ok().json(json!({
-                "status": "success",
-                "message": "Debit allowance submitted successfully"
-            }))
-        },
-        Err(e) => {
-            error!("Failed to submit debit allowance: {}", e);
-            HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to submit debit allowance",
-                "details": e.to_string()
-            }))
-        }
-    }
-}
-
-
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::models::payment::ActivityItem;
+
+/// Most wallets that can be linked to one account
+pub const MAX_LINKED_WALLETS: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkedWallet {
+    pub wallet_address: String,
+    pub linked_at: i64,
+}
+
+/// Several wallets owned by one person. Each wallet keeps its own vault and history;
+/// the account only groups them. A wallet belongs to at most one account.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Account {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub primary_wallet: String,  // the wallet that created the account; can't be unlinked
+    pub wallets: Vec<LinkedWallet>,
+    pub created_at: i64,
+}
+
+impl Account {
+    pub fn has_wallet(&self, wallet_address: &str) -> bool {
+        self.wallets.iter().any(|w| w.wallet_address == wallet_address)
+    }
+
+    pub fn wallet_addresses(&self) -> Vec<String> {
+        self.wallets.iter().map(|w| w.wallet_address.clone()).collect()
+    }
+}
+
+/// Proof that the new wallet agrees to be linked: its signature over
+/// `link_message(account_id, wallet_address, timestamp)`
+#[derive(Debug, Deserialize)]
+pub struct LinkWalletRequest {
+    pub wallet_address: String,
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// An activity entry from one of the account's wallets
+#[derive(Debug, Serialize)]
+pub struct AccountActivityItem {
+    pub wallet_address: String,
+    #[serde(flatten)]
+    pub activity: ActivityItem,
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::models::payment::{ActivityItem, Payment};
+
+/// Activity types a feed can be filtered by: payments, deposits and every `ActivityKind`
+pub const ACTIVITY_TYPES: &[&str] = &["transaction", "deposit", "transfer", "refund", "redemption", "reward"];
+
+/// Token movements that aren't payments or deposits
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Transfer,    // the wallet sent tokens outside a payment, e.g. funding a voucher
+    Refund,      // tokens returned to the wallet
+    Redemption,  // a voucher redeemed into the wallet
+    Reward,      // tokens credited by a matching pool or funding round
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ActivityAmount {
+    pub token_symbol: String,
+    pub amount: f64,  // token units
+}
+
+impl ActivityAmount {
+    /// The tokens a payment moved
+    pub fn of_payment(payment: &Payment) -> Vec<Self> {
+        payment.computed_payment.iter().flatten()
+            .map(|token_payment| Self { token_symbol: token_payment.symbol.clone(), amount: token_payment.amount_to_pay })
+            .collect()
+    }
+}
+
+/// One token movement in a wallet's activity feed, recorded by the service that made it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivityEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub wallet_address: String,
+    pub kind: ActivityKind,
+    pub amounts: Vec<ActivityAmount>,
+    pub counterparty_address: Option<String>,
+    pub reference_type: String,  // what caused it, e.g. "voucher" or "dispute"
+    pub reference_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,
+    pub created_at: i64,
+}
+
+impl ActivityEvent {
+    pub fn new(
+        wallet_address: &str,
+        kind: ActivityKind,
+        amounts: Vec<ActivityAmount>,
+        reference_type: &str,
+        reference_id: &str,
+    ) -> Self {
+        Self {
+            id: None,
+            wallet_address: wallet_address.to_string(),
+            kind,
+            amounts,
+            counterparty_address: None,
+            reference_type: reference_type.to_string(),
+            reference_id: reference_id.to_string(),
+            executor_tx_id: None,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn counterparty(mut self, address: &str) -> Self {
+        self.counterparty_address = Some(address.to_string());
+        self
+    }
+
+    pub fn executor_tx_id(mut self, tx_id: Option<String>) -> Self {
+        self.executor_tx_id = tx_id;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    pub types: Option<String>,  // comma separated `ACTIVITY_TYPES`, all when absent
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,  // next_cursor from the previous page
+}
+
+impl ActivityQuery {
+    /// The requested types, checked against `ACTIVITY_TYPES`
+    pub fn types(&self) -> Result<Vec<&str>, String> {
+        let Some(types) = &self.types else {
+            return Ok(ACTIVITY_TYPES.to_vec());
+        };
+        let mut requested = Vec::new();
+        for activity_type in types.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if !ACTIVITY_TYPES.contains(&activity_type) {
+                return Err(format!("Invalid activity type '{}', expected one of {}", activity_type, ACTIVITY_TYPES.join(", ")));
+            }
+            requested.push(activity_type);
+        }
+        if requested.is_empty() {
+            return Err("At least one activity type is required".to_string());
+        }
+        Ok(requested)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityPage {
+    pub activities: Vec<ActivityItem>,
+    pub next_cursor: Option<String>,
+}
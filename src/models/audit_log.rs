@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId, Document};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AuditAction {
+    #[serde(rename = "cause_updated")]
+    CauseUpdated,
+    #[serde(rename = "cause_deleted")]
+    CauseDeleted,
+    #[serde(rename = "token_minted")]
+    TokenMinted,
+    #[serde(rename = "account_credited")]
+    AccountCredited,
+    #[serde(rename = "manual_credit")]
+    ManualCredit,
+    #[serde(rename = "refund")]
+    Refund,
+    #[serde(rename = "config_changed")]
+    ConfigChanged,
+    #[serde(rename = "user_anonymized")]
+    UserAnonymized,
+    #[serde(rename = "cause_reviewed")]
+    CauseReviewed,
+    #[serde(rename = "matching_pool_changed")]
+    MatchingPoolChanged,
+    #[serde(rename = "funding_round_changed")]
+    FundingRoundChanged,
+    #[serde(rename = "payment_failed")]
+    PaymentFailed,
+    #[serde(rename = "escrow_settled")]
+    EscrowSettled,
+    #[serde(rename = "cause_team_changed")]
+    CauseTeamChanged,
+    #[serde(rename = "payout_requested")]
+    PayoutRequested,
+    #[serde(rename = "payment_voided")]
+    PaymentVoided,
+    #[serde(rename = "credit_resolved")]
+    CreditResolved,
+    #[serde(rename = "liability_settled")]
+    LiabilitySettled,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditAction::CauseUpdated => write!(f, "cause_updated"),
+            AuditAction::CauseDeleted => write!(f, "cause_deleted"),
+            AuditAction::TokenMinted => write!(f, "token_minted"),
+            AuditAction::AccountCredited => write!(f, "account_credited"),
+            AuditAction::ManualCredit => write!(f, "manual_credit"),
+            AuditAction::Refund => write!(f, "refund"),
+            AuditAction::ConfigChanged => write!(f, "config_changed"),
+            AuditAction::UserAnonymized => write!(f, "user_anonymized"),
+            AuditAction::CauseReviewed => write!(f, "cause_reviewed"),
+            AuditAction::MatchingPoolChanged => write!(f, "matching_pool_changed"),
+            AuditAction::FundingRoundChanged => write!(f, "funding_round_changed"),
+            AuditAction::PaymentFailed => write!(f, "payment_failed"),
+            AuditAction::EscrowSettled => write!(f, "escrow_settled"),
+            AuditAction::CauseTeamChanged => write!(f, "cause_team_changed"),
+            AuditAction::PayoutRequested => write!(f, "payout_requested"),
+            AuditAction::PaymentVoided => write!(f, "payment_voided"),
+            AuditAction::CreditResolved => write!(f, "credit_resolved"),
+            AuditAction::LiabilitySettled => write!(f, "liability_settled"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditLog {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub actor: String,              // who performed the action ("system", "stripe_webhook", or the signing wallet)
+    pub action: AuditAction,
+    pub resource_type: String,      // "cause", "token", "wallet", ...
+    pub resource_id: String,
+    pub before: Option<Document>,   // snapshot before the change (None for creations)
+    pub after: Option<Document>,    // snapshot after the change (None for deletions)
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLog {
+    pub fn new(
+        actor: &str,
+        action: AuditAction,
+        resource_type: &str,
+        resource_id: &str,
+        before: Option<Document>,
+        after: Option<Document>,
+    ) -> Self {
+        Self {
+            id: None,
+            actor: actor.to_string(),
+            action,
+            resource_type: resource_type.to_string(),
+            resource_id: resource_id.to_string(),
+            before,
+            after,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub page: Option<u64>,
+    pub limit: Option<i64>,
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub resource_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogPage {
+    pub logs: Vec<AuditLog>,
+    pub page: u64,
+    pub limit: i64,
+    pub total: u64,
+}
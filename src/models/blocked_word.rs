@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum BlockedWordKind {
+    #[serde(rename = "reserved")]
+    Reserved,
+    #[serde(rename = "profanity")]
+    Profanity,
+}
+
+/// Extra word for the cause/token name filter, on top of the built-in lists
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockedWord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub word: String,
+    pub kind: BlockedWordKind,
+}
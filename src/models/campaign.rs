@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::utils::validation::{self, FieldErrors, Validate};
+
+/// Campaigns a cause can have that haven't ended
+pub const MAX_ACTIVE_CAMPAIGNS: usize = 20;
+pub const MAX_CAMPAIGN_NAME_CHARS: usize = 80;
+pub const MAX_CAMPAIGN_DESCRIPTION_CHARS: usize = 2000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignStatus {
+    Active,
+    Ended,  // closed early by the owner; keeps its totals but takes no more donations
+}
+
+impl std::fmt::Display for CampaignStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CampaignStatus::Active => write!(f, "active"),
+            CampaignStatus::Ended => write!(f, "ended"),
+        }
+    }
+}
+
+/// A named fundraiser under a cause, e.g. "Spring Gala". Donations to it buy the cause's
+/// token as usual and are also counted towards the campaign's own goal.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Campaign {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub cause_id: String,
+    pub name: String,  // unique within the cause
+    pub description: Option<String>,
+    pub goal_cents: i64,
+    pub starts_at: Option<i64>,  // open straight away when unset
+    pub ends_at: Option<i64>,    // open until ended when unset
+    pub status: CampaignStatus,
+    pub created_by: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Campaign {
+    pub fn accepts_donations(&self, now: i64) -> bool {
+        self.status == CampaignStatus::Active
+            && self.starts_at.map_or(true, |starts_at| now >= starts_at)
+            && self.ends_at.map_or(true, |ends_at| now < ends_at)
+    }
+}
+
+fn check_window(errors: &mut FieldErrors, starts_at: Option<i64>, ends_at: Option<i64>) {
+    if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+        if ends_at <= starts_at {
+            errors.add("ends_at", "Must be after starts_at");
+        }
+    }
+}
+
+fn check_name(errors: &mut FieldErrors, name: &str) {
+    errors.check("name", validation::required(name));
+    if name.trim().chars().count() > MAX_CAMPAIGN_NAME_CHARS {
+        errors.add("name", format!("Must be at most {} characters", MAX_CAMPAIGN_NAME_CHARS));
+    }
+}
+
+fn check_description(errors: &mut FieldErrors, description: &Option<String>) {
+    if description.as_ref().map_or(false, |d| d.chars().count() > MAX_CAMPAIGN_DESCRIPTION_CHARS) {
+        errors.add("description", format!("Must be at most {} characters", MAX_CAMPAIGN_DESCRIPTION_CHARS));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCampaignRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub goal_cents: i64,
+    pub starts_at: Option<i64>,
+    pub ends_at: Option<i64>,
+}
+
+impl Validate for CreateCampaignRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        check_name(&mut errors, &self.name);
+        check_description(&mut errors, &self.description);
+        if self.goal_cents <= 0 {
+            errors.add("goal_cents", "Must be greater than zero");
+        }
+        check_window(&mut errors, self.starts_at, self.ends_at);
+        errors.into_result()
+    }
+}
+
+/// Change any of a campaign's fields; `status: "ended"` closes it early
+#[derive(Debug, Deserialize)]
+pub struct UpdateCampaignRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub goal_cents: Option<i64>,
+    pub starts_at: Option<i64>,
+    pub ends_at: Option<i64>,
+    pub status: Option<CampaignStatus>,
+}
+
+impl Validate for UpdateCampaignRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if let Some(name) = &self.name {
+            check_name(&mut errors, name);
+        }
+        check_description(&mut errors, &self.description);
+        if self.goal_cents.map_or(false, |goal| goal <= 0) {
+            errors.add("goal_cents", "Must be greater than zero");
+        }
+        check_window(&mut errors, self.starts_at, self.ends_at);
+        errors.into_result()
+    }
+}
+
+/// How far a campaign has got towards its goal
+#[derive(Debug, Serialize)]
+pub struct CampaignProgress {
+    pub campaign: Campaign,
+    pub raised_cents: i64,
+    pub donations: i64,
+    pub donors: i64,
+    pub tokens: f64,
+    pub percent_of_goal: f64,
+    pub accepting_donations: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign(status: CampaignStatus, starts_at: Option<i64>, ends_at: Option<i64>) -> Campaign {
+        Campaign {
+            id: None,
+            cause_id: "cause".to_string(),
+            name: "Spring Gala".to_string(),
+            description: None,
+            goal_cents: 10_000,
+            starts_at,
+            ends_at,
+            status,
+            created_by: "owner".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn create(name: &str, goal_cents: i64, starts_at: Option<i64>, ends_at: Option<i64>) -> CreateCampaignRequest {
+        CreateCampaignRequest { name: name.to_string(), description: None, goal_cents, starts_at, ends_at }
+    }
+
+    fn update() -> UpdateCampaignRequest {
+        UpdateCampaignRequest { name: None, description: None, goal_cents: None, starts_at: None, ends_at: None, status: None }
+    }
+
+    /// Whether validation failed on `field`
+    fn rejects(result: Result<(), FieldErrors>, field: &str) -> bool {
+        result.map_or_else(|errors| errors.get(field).is_some(), |_| false)
+    }
+
+    #[test]
+    fn test_accepts_donations_within_its_window() {
+        assert!(campaign(CampaignStatus::Active, None, None).accepts_donations(100));
+        assert!(campaign(CampaignStatus::Active, Some(100), Some(200)).accepts_donations(100));
+        assert!(campaign(CampaignStatus::Active, Some(100), Some(200)).accepts_donations(199));
+        // Not yet started, or already over
+        assert!(!campaign(CampaignStatus::Active, Some(100), Some(200)).accepts_donations(99));
+        assert!(!campaign(CampaignStatus::Active, Some(100), Some(200)).accepts_donations(200));
+    }
+
+    #[test]
+    fn test_ended_campaign_refuses_donations() {
+        assert!(!campaign(CampaignStatus::Ended, None, None).accepts_donations(100));
+        assert!(!campaign(CampaignStatus::Ended, Some(0), Some(200)).accepts_donations(100));
+    }
+
+    #[test]
+    fn test_create_request_validation() {
+        assert!(create("Spring Gala", 10_000, Some(100), Some(200)).validate().is_ok());
+        assert!(create("Spring Gala", 10_000, None, None).validate().is_ok());
+        assert!(rejects(create("  ", 10_000, None, None).validate(), "name"));
+        assert!(rejects(create(&"n".repeat(MAX_CAMPAIGN_NAME_CHARS + 1), 10_000, None, None).validate(), "name"));
+        assert!(rejects(create("Spring Gala", 0, None, None).validate(), "goal_cents"));
+        assert!(rejects(create("Spring Gala", 10_000, Some(200), Some(200)).validate(), "ends_at"));
+
+        let long = CreateCampaignRequest { description: Some("d".repeat(MAX_CAMPAIGN_DESCRIPTION_CHARS + 1)), ..create("Spring Gala", 10_000, None, None) };
+        assert!(rejects(long.validate(), "description"));
+    }
+
+    #[test]
+    fn test_update_request_validation() {
+        assert!(update().validate().is_ok());
+        assert!(UpdateCampaignRequest { status: Some(CampaignStatus::Ended), ..update() }.validate().is_ok());
+        assert!(rejects(UpdateCampaignRequest { name: Some(String::new()), ..update() }.validate(), "name"));
+        assert!(rejects(UpdateCampaignRequest { goal_cents: Some(-1), ..update() }.validate(), "goal_cents"));
+        assert!(rejects(UpdateCampaignRequest { starts_at: Some(300), ends_at: Some(200), ..update() }.validate(), "ends_at"));
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::{self, oid::ObjectId};
 use chrono::{DateTime, Utc};
+use crate::utils::profile::validate_email;
+use crate::utils::validation::{self, FieldErrors, Validate};
 
 fn default_displayed() -> bool {
     true
@@ -18,6 +20,16 @@ pub enum CauseStatus {
     Active,
     #[serde(rename = "failed")]
     Failed,
+    #[serde(rename = "pending_review")]
+    PendingReview,
+    #[serde(rename = "rejected")]
+    Rejected,
+    /// Not taking donations, but its token is still spendable
+    #[serde(rename = "suspended")]
+    Suspended,
+    /// Retired; hidden from listings and lookups but kept for history
+    #[serde(rename = "archived")]
+    Archived,
 }
 
 impl std::fmt::Display for CauseStatus {
@@ -28,10 +40,483 @@ impl std::fmt::Display for CauseStatus {
             CauseStatus::TokenMinted => write!(f, "token_minted"),
             CauseStatus::Active => write!(f, "active"),
             CauseStatus::Failed => write!(f, "failed"),
+            CauseStatus::PendingReview => write!(f, "pending_review"),
+            CauseStatus::Rejected => write!(f, "rejected"),
+            CauseStatus::Suspended => write!(f, "suspended"),
+            CauseStatus::Archived => write!(f, "archived"),
         }
     }
 }
 
+/// Steps of cause creation, in order
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum CreationStep {
+    #[serde(rename = "connect_account")]
+    ConnectAccount,
+    #[serde(rename = "stripe_product")]
+    StripeProduct,
+    #[serde(rename = "stripe_price")]
+    StripePrice,
+    #[serde(rename = "review")]
+    Review,
+    #[serde(rename = "mint_token")]
+    MintToken,
+    #[serde(rename = "finalize")]
+    Finalize,
+    #[serde(rename = "done")]
+    Done,
+}
+
+impl CreationStep {
+    pub fn next(self) -> Self {
+        match self {
+            CreationStep::ConnectAccount => CreationStep::StripeProduct,
+            CreationStep::StripeProduct => CreationStep::StripePrice,
+            CreationStep::StripePrice => CreationStep::Review,
+            CreationStep::Review => CreationStep::MintToken,
+            CreationStep::MintToken => CreationStep::Finalize,
+            CreationStep::Finalize | CreationStep::Done => CreationStep::Done,
+        }
+    }
+}
+
+impl std::fmt::Display for CreationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreationStep::ConnectAccount => write!(f, "connect_account"),
+            CreationStep::StripeProduct => write!(f, "stripe_product"),
+            CreationStep::StripePrice => write!(f, "stripe_price"),
+            CreationStep::Review => write!(f, "review"),
+            CreationStep::MintToken => write!(f, "mint_token"),
+            CreationStep::Finalize => write!(f, "finalize"),
+            CreationStep::Done => write!(f, "done"),
+        }
+    }
+}
+
+/// Persisted progress of cause creation, so a failure resumes from the step
+/// that failed instead of creating Stripe objects or tokens a second time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreationSaga {
+    pub step: CreationStep,             // next step to run
+    pub attempts: i32,                  // failed attempts so far
+    pub last_error: Option<String>,
+    pub next_retry_at: Option<i64>,     // unix seconds, set after a failure
+    pub locked_until: Option<i64>,      // unix seconds, set while a runner owns the saga
+    pub stripe_price_id: Option<String>,
+    pub draft_id: Option<String>,       // draft to mark completed once the cause is active
+}
+
+impl CreationSaga {
+    pub fn new(draft_id: Option<String>) -> Self {
+        Self {
+            step: CreationStep::ConnectAccount,
+            attempts: 0,
+            last_error: None,
+            next_retry_at: None,
+            locked_until: None,
+            stripe_price_id: None,
+            draft_id,
+        }
+    }
+
+    /// Work out where a cause created before sagas were persisted got stuck
+    pub fn infer(cause: &Cause) -> Self {
+        // The old flow stored the product id only after its price was created,
+        // and predates moderation, so these causes skip review
+        let step = if cause.stripe_account_id.is_none() {
+            CreationStep::ConnectAccount
+        } else if cause.stripe_product_id.is_none() {
+            CreationStep::StripeProduct
+        } else if cause.token_id.is_none() {
+            CreationStep::MintToken
+        } else {
+            CreationStep::Finalize
+        };
+        Self { step, ..Self::new(None) }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ReviewDecision {
+    #[serde(rename = "approved")]
+    Approved,
+    #[serde(rename = "rejected")]
+    Rejected,
+}
+
+/// A moderator's decision on a submitted cause
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CauseReview {
+    pub decision: ReviewDecision,
+    pub reason: Option<String>,
+    pub reviewed_by: String,
+    pub reviewed_at: i64,  // unix seconds
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewCauseRequest {
+    pub reason: Option<String>,  // required when rejecting
+}
+
+/// Drafts waiting on Stripe onboarding longer than this are reported as stuck
+pub const DEFAULT_STUCK_DRAFT_HOURS: i64 = 24;
+
+#[derive(Debug, Deserialize)]
+pub struct CauseDashboardQuery {
+    pub stuck_hours: Option<i64>,  // default DEFAULT_STUCK_DRAFT_HOURS
+}
+
+/// Where causes are in their lifecycle, for admins to find stuck onboarding flows
+#[derive(Debug, Serialize)]
+pub struct CauseDashboard {
+    pub status_counts: std::collections::BTreeMap<String, u64>,
+    pub stuck_drafts: Vec<StuckDraft>,
+    pub failed_causes: Vec<FailedCause>,
+}
+
+/// A draft still in `StripePending` after the stuck threshold
+#[derive(Debug, Serialize)]
+pub struct StuckDraft {
+    pub id: String,
+    pub name: String,
+    pub token_symbol: String,
+    pub creator_email: String,
+    pub stripe_account_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub hours_waiting: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedCause {
+    pub id: String,
+    pub name: String,
+    pub token_symbol: String,
+    pub error_message: Option<String>,
+    pub failed_step: Option<CreationStep>,
+    pub attempts: i32,
+    pub next_retry_at: Option<i64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Cause> for FailedCause {
+    fn from(cause: Cause) -> Self {
+        Self {
+            id: cause.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: cause.name,
+            token_symbol: cause.token_symbol,
+            error_message: cause.error_message,
+            failed_step: cause.creation.as_ref().map(|saga| saga.step),
+            attempts: cause.creation.as_ref().map_or(0, |saga| saga.attempts),
+            next_retry_at: cause.creation.as_ref().and_then(|saga| saga.next_retry_at),
+            updated_at: cause.updated_at,
+        }
+    }
+}
+
+/// How far a cause's connected account is from having everything Stripe asks for, worst first
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementsState {
+    Disabled,       // Stripe has stopped charges or payouts; see disabled_reason
+    PastDue,        // overdue, which disables the account
+    CurrentlyDue,   // needed by current_deadline
+    EventuallyDue,  // needed once the account passes a volume threshold
+    Complete,
+}
+
+impl std::fmt::Display for RequirementsState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequirementsState::Disabled => write!(f, "disabled"),
+            RequirementsState::PastDue => write!(f, "past_due"),
+            RequirementsState::CurrentlyDue => write!(f, "currently_due"),
+            RequirementsState::EventuallyDue => write!(f, "eventually_due"),
+            RequirementsState::Complete => write!(f, "complete"),
+        }
+    }
+}
+
+/// What Stripe still needs from a cause's connected account, as field names like
+/// "individual.verification.document", from the last status check or `account.updated`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StripeRequirements {
+    pub state: RequirementsState,
+    pub currently_due: Vec<String>,
+    pub eventually_due: Vec<String>,
+    pub past_due: Vec<String>,
+    pub disabled_reason: Option<String>,
+    pub current_deadline: Option<i64>,
+    pub checked_at: i64,
+}
+
+impl StripeRequirements {
+    pub fn new(
+        currently_due: Vec<String>,
+        eventually_due: Vec<String>,
+        past_due: Vec<String>,
+        disabled_reason: Option<String>,
+        current_deadline: Option<i64>,
+        checked_at: i64,
+    ) -> Self {
+        let state = if disabled_reason.is_some() {
+            RequirementsState::Disabled
+        } else if !past_due.is_empty() {
+            RequirementsState::PastDue
+        } else if !currently_due.is_empty() {
+            RequirementsState::CurrentlyDue
+        } else if !eventually_due.is_empty() {
+            RequirementsState::EventuallyDue
+        } else {
+            RequirementsState::Complete
+        };
+        Self { state, currently_due, eventually_due, past_due, disabled_reason, current_deadline, checked_at }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CauseRequirementsQuery {
+    pub state: Option<RequirementsState>,  // every cause with requirements on record when unset
+    pub limit: Option<i64>,                // default 100, at most 500
+}
+
+/// A cause's outstanding Stripe requirements, for admins chasing incomplete onboarding
+#[derive(Debug, Serialize)]
+pub struct CauseRequirements {
+    pub id: String,
+    pub name: String,
+    pub token_symbol: String,
+    pub creator_email: String,
+    pub stripe_account_id: Option<String>,
+    pub requirements: StripeRequirements,
+}
+
+/// Most causes one bulk action can touch
+pub const MAX_BULK_CAUSES: usize = 100;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkCauseAction {
+    Retry,      // resume creation from the step that failed
+    Hide,
+    Show,
+    Feature,
+    Unfeature,
+}
+
+/// Feature a cause, optionally at a position and until a time
+#[derive(Debug, Deserialize)]
+pub struct FeatureCauseRequest {
+    pub featured_rank: Option<i32>,
+    pub featured_until: Option<i64>,  // unix seconds, must be in the future
+}
+
+/// Featured causes in the order they should be shown
+#[derive(Debug, Deserialize)]
+pub struct ReorderFeaturedRequest {
+    pub cause_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CauseListQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,  // next_cursor from the previous page
+}
+
+/// The fields of a cause a listing shows; the rest come from GET /causes/{id}
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CauseSummary {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    pub organization: String,
+    pub token_name: String,
+    pub token_symbol: String,
+    pub token_image_url: Option<String>,
+    pub cause_image_url: Option<String>,
+    pub amount_donated: f64,
+    pub tokens_purchased: f64,
+    pub current_price: f64,
+    #[serde(default)]
+    pub featured: bool,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CauseSummary {
+    /// Projection loading only the summary's fields
+    pub fn projection() -> bson::Document {
+        bson::doc! {
+            "name": 1, "organization": 1, "token_name": 1, "token_symbol": 1, "token_image_url": 1,
+            "cause_image_url": 1, "amount_donated": 1, "tokens_purchased": 1, "current_price": 1,
+            "featured": 1, "created_at": 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CauseListPage {
+    pub causes: Vec<CauseSummary>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkCauseRequest {
+    pub action: BulkCauseAction,
+    pub cause_ids: Vec<String>,
+}
+
+/// Outcome of a bulk action on one cause, in request order
+#[derive(Debug, Serialize)]
+pub struct BulkCauseResult {
+    pub cause_id: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Legal form of the organization behind a cause, as Stripe asks for it when onboarding
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BusinessType {
+    #[default]
+    Individual,
+    Company,
+    NonProfit,
+    GovernmentEntity,
+}
+
+/// Causes from before international onboarding are all US accounts
+pub fn default_country() -> String {
+    "US".to_string()
+}
+
+/// Team members a cause can have besides its owner
+pub const MAX_TEAM_MEMBERS: usize = 25;
+
+/// What a team member may do, each role including the ones before it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum TeamRole {
+    Viewer,  // donations, analytics and payouts
+    Editor,  // cause content, campaigns and embeds
+    Owner,   // the team, payouts and deleting the cause
+}
+
+impl std::fmt::Display for TeamRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TeamRole::Viewer => write!(f, "viewer"),
+            TeamRole::Editor => write!(f, "editor"),
+            TeamRole::Owner => write!(f, "owner"),
+        }
+    }
+}
+
+/// Someone invited by email to help run a cause. Their role applies once they accept the
+/// invite from a signed-in wallet.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TeamMember {
+    pub email: String,  // lowercase; unique within the cause
+    pub role: TeamRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_address: Option<String>,  // set when the invite is accepted
+    pub invited_by: String,
+    pub invited_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_at: Option<i64>,
+}
+
+/// A wallet's own entry on a cause's team, as it appears in the wallet's data export
+#[derive(Debug, Serialize)]
+pub struct TeamMembership {
+    pub cause_id: String,
+    pub cause_name: String,
+    #[serde(flatten)]
+    pub member: TeamMember,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteTeamMemberRequest {
+    pub email: String,
+    pub role: TeamRole,
+}
+
+impl Validate for InviteTeamMemberRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("email", validation::required(&self.email).and_then(|_| validate_email(&self.email)));
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTeamMemberRequest {
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptTeamInviteRequest {
+    pub email: String,
+    pub token: String,
+}
+
+impl Validate for AcceptTeamInviteRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("email", validation::required(&self.email));
+        errors.check("token", validation::required(&self.token));
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CausePayoutRequest {
+    pub amount_cents: i64,
+    #[serde(default = "default_payout_currency")]
+    pub currency: String,
+    pub idempotency_key: String,  // the caller's; retrying with it never pays out twice
+}
+
+fn default_payout_currency() -> String {
+    "usd".to_string()
+}
+
+impl Validate for CausePayoutRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if self.amount_cents <= 0 {
+            errors.add("amount_cents", "Must be positive");
+        }
+        if self.currency.len() != 3 || !self.currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            errors.add("currency", "Must be a three-letter currency code, e.g. usd");
+        }
+        let key = self.idempotency_key.trim();
+        if key.is_empty() || key.len() > 100 {
+            errors.add("idempotency_key", "Must be 1-100 characters");
+        }
+        errors.into_result()
+    }
+}
+
+/// A payout a cause's team requested. The amount is counted against what the cause raised
+/// before Stripe is asked, so causes sharing an organization's account can't pay out each
+/// other's donations.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CausePayout {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub cause_id: String,
+    pub idempotency_key: String,  // unique per cause
+    pub stripe_account_id: String,
+    pub amount_cents: i64,
+    pub currency: String,
+    pub requested_by: String,
+    pub payout_id: Option<String>,  // None until Stripe has created it
+    pub failure_reason: Option<String>,  // Stripe refused it and the amount was released
+    pub created_at: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Cause {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -57,14 +542,38 @@ pub struct Cause {
     pub cause_image_url: Option<String>,
     pub stripe_account_id: Option<String>,
     pub stripe_account_status: Option<String>,
+    #[serde(default = "default_country")]
+    pub country: String,  // of the connected account
+    #[serde(default)]
+    pub business_type: BusinessType,
     #[serde(default)]
     pub onboarding_completed: bool,
     #[serde(default)]
     pub payouts_enabled: bool,
+    #[serde(default)]
+    pub paid_out_cents: i64,  // USD paid out on request, counted before Stripe is asked
     #[serde(default = "default_displayed")]
     pub displayed: bool,
     #[serde(default)]
     pub featured: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub featured_rank: Option<i32>,  // position among featured causes, lowest first; unranked come after
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub featured_until: Option<i64>,  // unix seconds; the feature is dropped once this passes
+    #[serde(default)]
+    pub owner_address: Option<String>,  // wallet that created the cause and may edit it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<String>,  // its admins manage the cause too; it shares their Stripe account
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub team: Vec<TeamMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation: Option<CreationSaga>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<CauseReview>,
+    // Only ever written with `$set` and kept out of the public JSON: it's the account's KYC
+    // status, served by the status route to the cause's team and to admins
+    #[serde(default, skip_serializing)]
+    pub requirements: Option<StripeRequirements>,  // unknown until the account is first checked
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub created_at: chrono::DateTime<chrono::Utc>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
@@ -107,12 +616,72 @@ impl Cause {
             cause_image_url,
             stripe_account_id: None,
             stripe_account_status: None,
+            country: default_country(),
+            business_type: BusinessType::default(),
             onboarding_completed: false,
             payouts_enabled: false,
+            paid_out_cents: 0,
             displayed: true,
             featured: false,
+            featured_rank: None,
+            featured_until: None,
+            owner_address: None,
+            organization_id: None,
+            team: Vec::new(),
+            creation: None,
+            review: None,
+            requirements: None,
             created_at: now,
             updated_at: now,
         }
     }
+    
+    /// Suspended and archived causes take no new donations; their tokens stay spendable
+    pub fn accepts_donations(&self) -> bool {
+        !matches!(self.status, CauseStatus::Suspended | CauseStatus::Archived)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn state(currently_due: &[&str], eventually_due: &[&str], past_due: &[&str], disabled_reason: Option<&str>) -> RequirementsState {
+        StripeRequirements::new(
+            fields(currently_due),
+            fields(eventually_due),
+            fields(past_due),
+            disabled_reason.map(str::to_string),
+            None,
+            0,
+        ).state
+    }
+
+    #[test]
+    fn test_requirements_state_is_the_worst_outstanding() {
+        let document = "individual.verification.document";
+        let ssn = "individual.ssn_last_4";
+        assert_eq!(state(&[], &[], &[], None), RequirementsState::Complete);
+        assert_eq!(state(&[], &[ssn], &[], None), RequirementsState::EventuallyDue);
+        assert_eq!(state(&[document], &[ssn], &[], None), RequirementsState::CurrentlyDue);
+        assert_eq!(state(&[document], &[ssn], &[document], None), RequirementsState::PastDue);
+        assert_eq!(state(&[document], &[ssn], &[document], Some("requirements.past_due")), RequirementsState::Disabled);
+        // A disabled account with nothing listed is still disabled
+        assert_eq!(state(&[], &[], &[], Some("rejected.fraud")), RequirementsState::Disabled);
+    }
+
+    #[test]
+    fn test_requirements_stay_out_of_the_public_cause() {
+        let mut cause = Cause::new(
+            "Rivers".to_string(), "River Trust".to_string(), "Clean rivers".to_string(), String::new(),
+            "rivers@example.org".to_string(), "River".to_string(), "RIVER".to_string(), None, None,
+        );
+        cause.requirements = Some(StripeRequirements::new(fields(&["individual.id_number"]), vec![], vec![], None, None, 0));
+        let json = serde_json::to_value(&cause).unwrap();
+        assert!(json.get("requirements").is_none(), "{}", json);
+    }
 }
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::{self, oid::ObjectId};
 use chrono::{DateTime, Utc, Duration};
+use crate::models::cause::{BusinessType, default_country};
 
 mod option_datetime_as_bson {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -57,16 +58,40 @@ pub struct CauseDraft {
     pub token_image_url: Option<String>,
     pub cause_image_url: Option<String>,
     pub stripe_account_id: Option<String>,
+    #[serde(default = "default_country")]
+    pub country: String,
+    #[serde(default)]
+    pub business_type: BusinessType,
     pub status: DraftStatus,
     pub cause_id: Option<String>, // ID of the created cause if completed
+    #[serde(default)]
+    pub owner_address: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", with = "option_datetime_as_bson", default)]
     pub completed_at: Option<DateTime<Utc>>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub created_at: DateTime<Utc>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "option_datetime_as_bson", default)]
+    pub reminder_sent_at: Option<DateTime<Utc>>,
+    #[serde(default = "legacy_email_verified")]
+    pub email_verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none", with = "option_datetime_as_bson", default)]
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+// Drafts created before verification existed are already mid-onboarding; don't strand them
+fn legacy_email_verified() -> bool {
+    true
 }
 
+/// Drafts start with a one day TTL
+pub const DRAFT_TTL_DAYS: i64 = 1;
+/// Each extension pushes expiry this far past now (or the current expiry, if later)
+pub const DRAFT_EXTENSION_DAYS: i64 = 7;
+/// Drafts are never kept longer than this after creation
+pub const MAX_DRAFT_LIFETIME_DAYS: i64 = 30;
+
 impl CauseDraft {
     pub fn new(
         name: String,
@@ -92,11 +117,23 @@ impl CauseDraft {
             token_image_url,
             cause_image_url,
             stripe_account_id: None,
+            country: default_country(),
+            business_type: BusinessType::default(),
             status: DraftStatus::Draft,
             cause_id: None,
+            owner_address: None,
             completed_at: None,
             created_at: now,
-            expires_at: now + Duration::days(1), // Auto-expire after 1 day for incomplete drafts
+            expires_at: now + Duration::days(DRAFT_TTL_DAYS), // Auto-expire after 1 day for incomplete drafts
+            reminder_sent_at: None,
+            email_verified: false,
+            email_verified_at: None,
         }
     }
+
+    /// Expiry after one extension, capped at the maximum draft lifetime
+    pub fn extended_expiry(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let extended = self.expires_at.max(now) + Duration::days(DRAFT_EXTENSION_DAYS);
+        extended.min(self.created_at + Duration::days(MAX_DRAFT_LIFETIME_DAYS))
+    }
 }
\ No newline at end of file
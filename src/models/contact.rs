@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// Most contacts a single wallet can save
+pub const MAX_CONTACTS: u64 = 500;
+
+/// A counterparty a user saved for quick picks on the send/pay screens
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Contact {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub owner_address: String,
+    pub contact_address: String,
+    pub nickname: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveContactRequest {
+    pub nickname: String,
+}
+
+/// A saved contact with the contact's current public profile, if they have an account
+#[derive(Debug, Serialize)]
+pub struct ContactEntry {
+    pub contact_address: String,
+    pub nickname: String,
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: i64,
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::models::token::TokenPayment;
+
+/// How long after paying a customer can dispute a payment
+pub const DISPUTE_WINDOW_DAYS: i64 = 60;
+
+/// Longest reason, details, response or resolution note
+pub const MAX_DISPUTE_TEXT_CHARS: usize = 2000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum DisputeStatus {
+    #[serde(rename = "open")]
+    Open,
+    #[serde(rename = "vendor_responded")]
+    VendorResponded,
+    #[serde(rename = "resolved_refund")]
+    ResolvedRefund,  // decided for the customer, who is paid back
+    #[serde(rename = "resolved_upheld")]
+    ResolvedUpheld,  // decided for the vendor, who keeps the payment
+}
+
+impl DisputeStatus {
+    pub fn is_resolved(&self) -> bool {
+        matches!(self, DisputeStatus::ResolvedRefund | DisputeStatus::ResolvedUpheld)
+    }
+}
+
+impl std::fmt::Display for DisputeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisputeStatus::Open => write!(f, "open"),
+            DisputeStatus::VendorResponded => write!(f, "vendor_responded"),
+            DisputeStatus::ResolvedRefund => write!(f, "resolved_refund"),
+            DisputeStatus::ResolvedUpheld => write!(f, "resolved_upheld"),
+        }
+    }
+}
+
+impl std::str::FromStr for DisputeStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(DisputeStatus::Open),
+            "vendor_responded" => Ok(DisputeStatus::VendorResponded),
+            "resolved_refund" => Ok(DisputeStatus::ResolvedRefund),
+            "resolved_upheld" => Ok(DisputeStatus::ResolvedUpheld),
+            _ => Err(format!("Invalid dispute status '{}', expected open, vendor_responded, resolved_refund or resolved_upheld", s)),
+        }
+    }
+}
+
+/// Progress of the reverse transfer after a refund resolution
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum DisputeRefundStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "refunding")]
+    Refunding,  // submitted; if its outcome is unknown it stays here until an admin resolves it
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,  // an admin can retry it
+}
+
+impl std::fmt::Display for DisputeRefundStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisputeRefundStatus::Pending => write!(f, "pending"),
+            DisputeRefundStatus::Refunding => write!(f, "refunding"),
+            DisputeRefundStatus::Completed => write!(f, "completed"),
+            DisputeRefundStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Where the refund of a disputed payment comes from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum DisputeRefundSource {
+    #[serde(rename = "escrow")]
+    Escrow,        // funds still held for an escrowed payment
+    #[serde(rename = "central_vault")]
+    CentralVault,  // the vendor was already paid, so the platform pays the customer back and the vendor owes it
+}
+
+/// A customer's complaint about a completed payment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Dispute {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub payment_id: String,
+    pub customer_address: String,
+    pub vendor_address: String,
+    pub amount_usd: f64,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: DisputeStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_responded_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution_note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_status: Option<DisputeRefundStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_source: Option<DisputeRefundSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_tx_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_failure: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_liability: Option<Vec<TokenPayment>>,  // what the vendor owes for a central vault refund
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liability_settled_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liability_settled_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenDisputeRequest {
+    pub reason: String,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RespondToDisputeRequest {
+    pub response: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveDisputeRequest {
+    pub refund: bool,  // true pays the customer back, false upholds the payment
+    pub note: Option<String>,
+}
+
+/// An admin's finding on a refund whose transfer outcome is unknown
+#[derive(Debug, Deserialize)]
+pub struct ResolveDisputeRefundRequest {
+    pub refunded: bool,  // true if it landed, false to allow sending it again
+    pub refund_tx_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LiabilityQuery {
+    pub vendor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisputeQuery {
+    pub status: Option<String>,
+}
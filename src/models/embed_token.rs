@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::utils::validation::{self, FieldErrors, Validate};
+
+/// Live embed tokens a cause may have at once
+pub const MAX_EMBED_TOKENS: usize = 20;
+
+/// Lets a donate button embedded on a cause's own site create checkouts for that cause.
+/// The token ships in the site's HTML, so it's only honoured for requests from `origin`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbedToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub token: String,
+    pub cause_id: String,
+    pub origin: String,    // e.g. https://example.org
+    pub referrer: String,  // attribution for donations through it, unless the button sets its own
+    pub created_by: String,
+    pub created_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateEmbedTokenRequest {
+    pub origin: String,
+    pub referrer: Option<String>,  // defaults to the origin's host
+}
+
+impl Validate for CreateEmbedTokenRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("origin", validation::origin(&self.origin));
+        if let Some(referrer) = &self.referrer {
+            errors.check("referrer", validation::referrer(referrer));
+        }
+        errors.into_result()
+    }
+}
+
+/// Start a donation from an embedded button. Donors without a wallet pay with their email,
+/// and claim the tokens later with `POST /deposits/claim`.
+#[derive(Debug, Deserialize)]
+pub struct EmbedDonationRequest {
+    pub token: String,
+    pub amount_cents: i64,
+    pub user_wallet_address: Option<String>,
+    pub referrer: Option<String>,
+    pub campaign_id: Option<String>,
+}
+
+impl Validate for EmbedDonationRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("token", validation::required(&self.token));
+        if self.amount_cents <= 0 {
+            errors.add("amount_cents", "Must be greater than zero");
+        }
+        if let Some(wallet) = &self.user_wallet_address {
+            errors.check("user_wallet_address", validation::wallet_address(wallet));
+        }
+        if let Some(referrer) = &self.referrer {
+            errors.check("referrer", validation::referrer(referrer));
+        }
+        if let Some(campaign_id) = &self.campaign_id {
+            errors.check("campaign_id", ObjectId::parse_str(campaign_id).map_err(|_| "Must be a campaign ID".to_string()));
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbedDonationResponse {
+    pub checkout_url: String,
+    pub session_id: String,
+}
@@ -1,6 +1,11 @@
 use serde::Serialize;
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use actix_web::dev::ServiceResponse;
+use actix_web::error::{JsonPayloadError, PayloadError};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::middleware::ErrorHandlerResponse;
 use std::fmt;
+use crate::utils::validation::FieldErrors;
 
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -10,14 +15,31 @@ pub struct ErrorResponse {
     pub details: Option<String>,
 }
 
+/// A `VALIDATION_ERROR` naming what is wrong with each field
+#[derive(Debug, Serialize)]
+pub struct FieldErrorResponse {
+    pub code: String,
+    pub message: String,
+    pub fields: FieldErrors,
+}
+
 #[derive(Debug)]
 pub enum ApiError {
     DuplicateUser(String),
     DuplicateError(String),
     DatabaseError(mongodb::error::Error),
     ValidationError(String),
+    InvalidFields(FieldErrors),
     NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
     StripeError(String),
+    Conflict(String),
+    InsufficientBalance(String),
+    DiscountBudgetExhausted(String),  // supplement the payment again to recalculate
+    ServiceUnavailable(String),
+    TooManyRequests(String),
+    PayloadTooLarge(String),
     InternalError(String),
 }
 
@@ -28,13 +50,30 @@ impl fmt::Display for ApiError {
             ApiError::DuplicateError(msg) => write!(f, "Duplicate error: {}", msg),
             ApiError::DatabaseError(e) => write!(f, "Database error: {}", e),
             ApiError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
+            ApiError::InvalidFields(errors) => write!(f, "Validation error: {}", errors.summary()),
             ApiError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            ApiError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            ApiError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             ApiError::StripeError(msg) => write!(f, "Stripe error: {}", msg),
+            ApiError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            ApiError::InsufficientBalance(msg) => write!(f, "{}", msg),
+            ApiError::DiscountBudgetExhausted(msg) => write!(f, "Discount budget exhausted: {}", msg),
+            ApiError::ServiceUnavailable(msg) => write!(f, "Service unavailable: {}", msg),
+            ApiError::TooManyRequests(msg) => write!(f, "{}", msg),
+            ApiError::PayloadTooLarge(msg) => write!(f, "Payload too large: {}", msg),
             ApiError::InternalError(msg) => write!(f, "Internal error: {}", msg),
         }
     }
 }
 
+impl std::error::Error for ApiError {}
+
+impl From<FieldErrors> for ApiError {
+    fn from(errors: FieldErrors) -> Self {
+        ApiError::InvalidFields(errors)
+    }
+}
+
 impl ResponseError for ApiError {
     fn error_response(&self) -> HttpResponse {
         match self {
@@ -66,6 +105,13 @@ impl ResponseError for ApiError {
                     details: None,
                 })
             }
+            ApiError::InvalidFields(errors) => {
+                HttpResponse::BadRequest().json(FieldErrorResponse {
+                    code: "VALIDATION_ERROR".to_string(),
+                    message: self.to_string(),
+                    fields: errors.clone(),
+                })
+            }
             ApiError::NotFound(_) => {
                 HttpResponse::NotFound().json(ErrorResponse {
                     code: "NOT_FOUND".to_string(),
@@ -73,6 +119,20 @@ impl ResponseError for ApiError {
                     details: None,
                 })
             }
+            ApiError::Unauthorized(_) => {
+                HttpResponse::Unauthorized().json(ErrorResponse {
+                    code: "UNAUTHORIZED".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
+            ApiError::Forbidden(_) => {
+                HttpResponse::Forbidden().json(ErrorResponse {
+                    code: "FORBIDDEN".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
             ApiError::StripeError(_) => {
                 HttpResponse::BadGateway().json(ErrorResponse {
                     code: "STRIPE_ERROR".to_string(),
@@ -80,6 +140,48 @@ impl ResponseError for ApiError {
                     details: None,
                 })
             }
+            ApiError::Conflict(_) => {
+                HttpResponse::Conflict().json(ErrorResponse {
+                    code: "CONFLICT".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
+            ApiError::InsufficientBalance(_) => {
+                HttpResponse::UnprocessableEntity().json(ErrorResponse {
+                    code: "INSUFFICIENT_BALANCE".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
+            ApiError::DiscountBudgetExhausted(_) => {
+                HttpResponse::Conflict().json(ErrorResponse {
+                    code: "DISCOUNT_BUDGET_EXHAUSTED".to_string(),
+                    message: self.to_string(),
+                    details: Some("Supplement the payment again to recalculate it".to_string()),
+                })
+            }
+            ApiError::ServiceUnavailable(_) => {
+                HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                    code: "SERVICE_UNAVAILABLE".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
+            ApiError::TooManyRequests(_) => {
+                HttpResponse::TooManyRequests().json(ErrorResponse {
+                    code: "RATE_LIMITED".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
+            ApiError::PayloadTooLarge(_) => {
+                HttpResponse::PayloadTooLarge().json(ErrorResponse {
+                    code: "PAYLOAD_TOO_LARGE".to_string(),
+                    message: self.to_string(),
+                    details: None,
+                })
+            }
             ApiError::InternalError(_) => {
                 HttpResponse::InternalServerError().json(ErrorResponse {
                     code: "INTERNAL_ERROR".to_string(),
@@ -89,4 +191,42 @@ impl ResponseError for ApiError {
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+/// `web::JsonConfig` error handler: a body over the limit is a 413 `PAYLOAD_TOO_LARGE`
+/// saying what the limit is, and any other unreadable body a 400 `VALIDATION_ERROR`
+pub fn json_payload_error(error: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match error {
+        JsonPayloadError::OverflowKnownLength { length, limit } => {
+            ApiError::PayloadTooLarge(format!("body is {} bytes, the limit is {}", length, limit)).into()
+        }
+        JsonPayloadError::Overflow { limit } => {
+            ApiError::PayloadTooLarge(format!("body is over the {} byte limit", limit)).into()
+        }
+        other => ApiError::ValidationError(other.to_string()).into(),
+    }
+}
+
+/// Raw bodies (`web::Bytes`, `String`) are limited by `web::PayloadConfig`, which has no
+/// error handler of its own. Turns a body over its limit into a 413 `PAYLOAD_TOO_LARGE`,
+/// and passes any other error through.
+pub fn payload_error(error: actix_web::Error) -> actix_web::Error {
+    match error.as_error::<PayloadError>() {
+        Some(PayloadError::Overflow) => ApiError::PayloadTooLarge("body is over the payload limit".to_string()).into(),
+        _ => error,
+    }
+}
+
+/// `ErrorHandlers` handler for 413s, for the raw bodies handlers extract themselves: actix
+/// answers those in plain text, so they're replaced with the JSON `PAYLOAD_TOO_LARGE`.
+/// Responses that are JSON already are left alone.
+pub fn payload_too_large<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let is_json = res.response().headers().get(CONTENT_TYPE)
+        .map_or(false, |content_type| content_type.as_bytes().starts_with(b"application/json"));
+    if is_json {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+    let (req, _) = res.into_parts();
+    let response = ApiError::PayloadTooLarge("body is over the payload limit".to_string()).error_response();
+    Ok(ErrorHandlerResponse::Response(ServiceResponse::new(req, response).map_into_right_body()))
+}
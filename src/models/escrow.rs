@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// How long escrowed funds are held before they release to the vendor on their own
+pub const DEFAULT_ESCROW_HOLD_HOURS: i64 = 14 * 24;
+pub const MAX_ESCROW_HOLD_HOURS: i64 = 90 * 24;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum EscrowStatus {
+    #[serde(rename = "awaiting_payment")]
+    AwaitingPayment,  // customer hasn't paid into escrow yet
+    #[serde(rename = "held")]
+    Held,
+    #[serde(rename = "disputed")]
+    Disputed,   // held, and won't release automatically until resolved
+    #[serde(rename = "capturing")]
+    Capturing,  // transfer to the vendor in progress
+    #[serde(rename = "captured")]
+    Captured,
+    #[serde(rename = "refunding")]
+    Refunding,  // transfer back to the customer in progress
+    #[serde(rename = "refunded")]
+    Refunded,
+    #[serde(rename = "failed")]
+    Failed,     // a capture or refund may or may not have landed; left for an admin
+}
+
+impl std::fmt::Display for EscrowStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EscrowStatus::AwaitingPayment => write!(f, "awaiting_payment"),
+            EscrowStatus::Held => write!(f, "held"),
+            EscrowStatus::Disputed => write!(f, "disputed"),
+            EscrowStatus::Capturing => write!(f, "capturing"),
+            EscrowStatus::Captured => write!(f, "captured"),
+            EscrowStatus::Refunding => write!(f, "refunding"),
+            EscrowStatus::Refunded => write!(f, "refunded"),
+            EscrowStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Where escrowed funds end up
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum EscrowOutcome {
+    #[serde(rename = "capture")]
+    Capture,  // to the vendor
+    #[serde(rename = "refund")]
+    Refund,   // back to the customer
+}
+
+impl EscrowOutcome {
+    pub fn in_progress(&self) -> EscrowStatus {
+        match self {
+            EscrowOutcome::Capture => EscrowStatus::Capturing,
+            EscrowOutcome::Refund => EscrowStatus::Refunding,
+        }
+    }
+
+    pub fn settled(&self) -> EscrowStatus {
+        match self {
+            EscrowOutcome::Capture => EscrowStatus::Captured,
+            EscrowOutcome::Refund => EscrowStatus::Refunded,
+        }
+    }
+}
+
+/// Escrow terms of a payment the customer pays into the platform's escrow vault. The
+/// funds move on to the vendor when captured, or back to the customer when refunded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentEscrow {
+    pub status: EscrowStatus,
+    pub vault_address: String,  // escrow vault the customer's allowance credits
+    pub hold_hours: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_at: Option<i64>,  // captured automatically after this unless disputed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settled_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settled_by: Option<String>,  // wallet that captured or refunded; "system" on auto-release
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_tx_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitted_at: Option<i64>,  // set while a settlement transfer's outcome is unrecorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    pub updated_at: i64,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// A feature that can be switched per environment
+pub struct FeatureDefinition {
+    pub name: &'static str,
+    pub default_enabled: bool,  // without a stored flag; features still rolling out start off
+    pub description: &'static str,
+}
+
+/// Every feature flag. A feature that is on by default answers 503 while switched off; one
+/// still rolling out answers 404, as if its routes didn't exist yet.
+pub const FEATURES: &[FeatureDefinition] = &[
+    FeatureDefinition { name: "refunds_enabled", default_enabled: true, description: "Vendors and admins refunding escrowed and disputed payments" },
+    FeatureDefinition { name: "recurring_payments", default_enabled: true, description: "Creating, paying and running payment schedules" },
+    FeatureDefinition { name: "vouchers", default_enabled: true, description: "Creating vouchers" },
+];
+
+pub fn feature_definition(name: &str) -> Option<&'static FeatureDefinition> {
+    FEATURES.iter().find(|feature| feature.name == name)
+}
+
+/// A stored switch for one feature in one environment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeatureFlag {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub environment: String,  // ENVIRONMENT of the deployments it applies to, "sandbox" in SANDBOX_MODE
+    pub enabled: bool,
+    pub updated_by: String,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFeatureFlagRequest {
+    pub enabled: bool,
+    pub environment: Option<String>,  // this deployment's environment when absent
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeatureFlagQuery {
+    pub environment: Option<String>,
+}
+
+/// A feature as an environment sees it
+#[derive(Debug, Serialize)]
+pub struct FeatureFlagStatus {
+    pub name: String,
+    pub description: String,
+    pub environment: String,
+    pub enabled: bool,
+    pub default_enabled: bool,
+    pub updated_by: Option<String>,  // None while the default applies
+    pub updated_at: Option<i64>,
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum FundingRoundStatus {
+    #[serde(rename = "open")]
+    Open,
+    #[serde(rename = "closed")]
+    Closed,        // allocations computed, payouts not all credited yet
+    #[serde(rename = "distributed")]
+    Distributed,
+}
+
+impl std::fmt::Display for FundingRoundStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FundingRoundStatus::Open => write!(f, "open"),
+            FundingRoundStatus::Closed => write!(f, "closed"),
+            FundingRoundStatus::Distributed => write!(f, "distributed"),
+        }
+    }
+}
+
+/// Matching budget a cause received when the round closed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoundAllocation {
+    pub token_symbol: String,
+    pub contributor_count: i64,
+    pub contributed_cents: i64,
+    pub qf_score: f64,
+    pub matched_cents: i64,
+}
+
+/// A quadratic funding round. Donations to eligible causes inside the window are
+/// contributions; at close the matching budget is split by quadratic funding.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundingRound {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub cause_symbols: Vec<String>,
+    pub matching_budget_cents: i64,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub status: FundingRoundStatus,
+    #[serde(default)]
+    pub allocations: Option<Vec<RoundAllocation>>,  // set at close
+    pub closed_at: Option<i64>,
+    pub created_by: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// One donation counted towards a round. Unique per (round, checkout session).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoundContribution {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub round_id: ObjectId,
+    pub token_symbol: String,
+    pub contributor_wallet: String,
+    pub amount_cents: i64,
+    pub stripe_session_id: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum RoundPayoutStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "processing")]
+    Processing,    // claimed by a distribution run; left here if that run crashed mid-credit
+    #[serde(rename = "credited")]
+    Credited,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+impl std::fmt::Display for RoundPayoutStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoundPayoutStatus::Pending => write!(f, "pending"),
+            RoundPayoutStatus::Processing => write!(f, "processing"),
+            RoundPayoutStatus::Credited => write!(f, "credited"),
+            RoundPayoutStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A contributor's share of their cause's matched amount, paid out as cause tokens.
+/// Unique per (round, cause, wallet) so distribution can be retried safely.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoundPayout {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub round_id: ObjectId,
+    pub token_symbol: String,
+    pub wallet_address: String,
+    pub matched_cents: i64,
+    pub tokens_credited: f64,
+    pub status: RoundPayoutStatus,
+    pub error: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFundingRoundRequest {
+    pub name: String,
+    pub cause_symbols: Vec<String>,
+    pub matching_budget_cents: i64,
+    pub starts_at: i64,
+    pub ends_at: i64,
+}
+
+/// Payout report for a round
+#[derive(Debug, Serialize)]
+pub struct FundingRoundReport {
+    pub round: FundingRound,
+    pub total_contributed_cents: i64,
+    pub total_matched_cents: i64,
+    pub payouts: Vec<RoundPayout>,
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::utils::validation::{self, FieldErrors, Validate};
+
+/// Open pages a supporter can run at once
+pub const MAX_FUNDRAISERS_PER_WALLET: usize = 10;
+pub const MAX_FUNDRAISER_TITLE_CHARS: usize = 100;
+pub const MAX_FUNDRAISER_STORY_CHARS: usize = 5000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FundraiserStatus {
+    Active,
+    Closed,  // stays listed with its totals but takes no more donations
+}
+
+impl std::fmt::Display for FundraiserStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FundraiserStatus::Active => write!(f, "active"),
+            FundraiserStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// A supporter's personal page raising money for a cause. Donations through it go to the
+/// cause's connected account and buy the cause's token like any other; they're also
+/// credited to the page, which is what the leaderboard ranks.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundraiserPage {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub slug: String,  // unique, e.g. /fundraisers/run-for-rivers
+    pub cause_id: String,
+    pub owner_address: String,
+    pub title: String,
+    pub story: Option<String>,
+    pub goal_cents: i64,
+    pub status: FundraiserStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+fn check_title(errors: &mut FieldErrors, title: &str) {
+    errors.check("title", validation::required(title));
+    if title.trim().chars().count() > MAX_FUNDRAISER_TITLE_CHARS {
+        errors.add("title", format!("Must be at most {} characters", MAX_FUNDRAISER_TITLE_CHARS));
+    }
+}
+
+fn check_story(errors: &mut FieldErrors, story: &Option<String>) {
+    if story.as_ref().map_or(false, |story| story.chars().count() > MAX_FUNDRAISER_STORY_CHARS) {
+        errors.add("story", format!("Must be at most {} characters", MAX_FUNDRAISER_STORY_CHARS));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFundraiserRequest {
+    pub cause_id: String,
+    pub slug: String,
+    pub title: String,
+    pub story: Option<String>,
+    pub goal_cents: i64,
+}
+
+impl Validate for CreateFundraiserRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("cause_id", ObjectId::parse_str(&self.cause_id).map_err(|_| "Must be a cause ID".to_string()));
+        errors.check("slug", validation::slug(&self.slug));
+        check_title(&mut errors, &self.title);
+        check_story(&mut errors, &self.story);
+        if self.goal_cents <= 0 {
+            errors.add("goal_cents", "Must be greater than zero");
+        }
+        errors.into_result()
+    }
+}
+
+/// Change a page's text or goal, or close it with `status: "closed"`. The slug and cause
+/// are fixed once shared.
+#[derive(Debug, Deserialize)]
+pub struct UpdateFundraiserRequest {
+    pub title: Option<String>,
+    pub story: Option<String>,
+    pub goal_cents: Option<i64>,
+    pub status: Option<FundraiserStatus>,
+}
+
+impl Validate for UpdateFundraiserRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if let Some(title) = &self.title {
+            check_title(&mut errors, title);
+        }
+        check_story(&mut errors, &self.story);
+        if self.goal_cents.map_or(false, |goal| goal <= 0) {
+            errors.add("goal_cents", "Must be greater than zero");
+        }
+        errors.into_result()
+    }
+}
+
+/// Donate through a page; the page's cause receives it
+#[derive(Debug, Deserialize)]
+pub struct FundraiserDonationRequest {
+    pub amount_cents: i64,
+    pub user_wallet_address: Option<String>,  // without one, the donor claims the tokens by email
+}
+
+impl Validate for FundraiserDonationRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if self.amount_cents <= 0 {
+            errors.add("amount_cents", "Must be greater than zero");
+        }
+        if let Some(wallet) = &self.user_wallet_address {
+            errors.check("user_wallet_address", validation::wallet_address(wallet));
+        }
+        errors.into_result()
+    }
+}
+
+/// A page with what it has raised so far
+#[derive(Debug, Serialize)]
+pub struct FundraiserProgress {
+    pub page: FundraiserPage,
+    pub raised_cents: i64,
+    pub donations: i64,
+    pub donors: i64,
+    pub percent_of_goal: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    pub limit: Option<i64>,  // default 10, at most 100
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub slug: String,
+    pub title: String,
+    pub owner_address: String,
+    pub raised_cents: i64,
+    pub donations: i64,
+    pub goal_cents: i64,
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Our running view of one vault's balance of one token, kept from the transfers, mints
+/// and credits this backend submits so analytics and balance fallbacks don't need the
+/// executor. Best effort: a submission the executor later rejects, or a transfer made
+/// elsewhere, is only corrected when reconciliation reads the vault. The executor is
+/// always authoritative.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Holding {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<mongodb::bson::oid::ObjectId>,
+    pub wallet_address: String,        // vault public key, base58
+    pub token_id: String,              // "pubkey,shard" of the token issuer
+    pub balance: i64,                  // base units
+    pub updated_at: i64,
+    #[serde(default)]
+    pub reconciled_at: Option<i64>,    // last time this was set from the vault itself
+}
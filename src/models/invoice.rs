@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// Most line items on one invoice
+pub const MAX_INVOICE_LINE_ITEMS: usize = 50;
+
+/// Reminders sent for an overdue invoice before the vendor has to chase it themselves
+pub const MAX_INVOICE_REMINDERS: i64 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum InvoiceStatus {
+    #[serde(rename = "open")]
+    Open,  // waiting for the customer; overdue once past `due_at`
+    #[serde(rename = "paid")]
+    Paid,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl std::fmt::Display for InvoiceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvoiceStatus::Open => write!(f, "open"),
+            InvoiceStatus::Paid => write!(f, "paid"),
+            InvoiceStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::str::FromStr for InvoiceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(InvoiceStatus::Open),
+            "paid" => Ok(InvoiceStatus::Paid),
+            "cancelled" => Ok(InvoiceStatus::Cancelled),
+            _ => Err(format!("Invalid invoice status '{}', expected open, paid or cancelled", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceLineItem {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price_usd: f64,
+}
+
+/// A bill a vendor sends to one customer. The customer pays it through a regular payment
+/// code made for them, which marks the invoice paid once it completes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Invoice {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub vendor_address: String,
+    pub vendor_name: String,
+    pub customer_address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,  // the vendor's own invoice number
+    pub line_items: Vec<InvoiceLineItem>,
+    pub total_usd: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub due_at: i64,
+    pub status: InvoiceStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<String>,  // latest payment code made for paying it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paid_at: Option<i64>,
+    #[serde(default)]
+    pub reminders_sent: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reminder_at: Option<i64>,
+}
+
+impl Invoice {
+    pub fn is_overdue(&self, now: i64) -> bool {
+        self.status == InvoiceStatus::Open && self.due_at < now
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvoiceRequest {
+    pub customer_address: String,
+    pub reference: Option<String>,
+    pub line_items: Vec<InvoiceLineItem>,
+    pub due_at: i64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvoiceQuery {
+    pub direction: Option<String>,  // "incoming" (default, invoices to pay) or "outgoing"
+    pub status: Option<String>,
+}
+
+/// Returned when paying; pay it through /payments/{payment_id}/supplement and /sign
+#[derive(Debug, Serialize)]
+pub struct PayInvoiceResponse {
+    pub invoice: Invoice,
+    pub payment_id: String,
+}
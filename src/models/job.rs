@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Reconciliation,
+    BulkCauseAction,
+    CauseRetry,
+    UserExport,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A long-running operation a request started in the background, answering 202 with the
+/// job's ID rather than holding the connection open until it's done
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub job_id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub created_by: String,           // only they and admins can see it
+    pub completed: i64,               // items done so far, e.g. wallets reconciled
+    pub total: Option<i64>,           // None until the job knows how many there are
+    pub result: Option<serde_json::Value>,  // what the endpoint used to return, once succeeded
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,              // last progress or heartbeat; a running job that stops updating was interrupted
+    pub finished_at: Option<i64>,
+}
+
+impl Job {
+    /// Where to poll for the job, in every API version
+    pub fn status_url(&self) -> String {
+        format!("/v1/jobs/{}", self.job_id)
+    }
+}
+
+/// The 202 body of an endpoint that started a job
+#[derive(Debug, Serialize)]
+pub struct JobAccepted {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub status_url: String,
+}
+
+impl From<&Job> for JobAccepted {
+    fn from(job: &Job) -> Self {
+        Self { job_id: job.job_id.clone(), status: job.status, status_url: job.status_url() }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// Most rewards a vendor can offer at once
+pub const MAX_LOYALTY_REWARDS: usize = 20;
+
+/// A reward customers can spend points on: a fixed USD discount off one payment
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LoyaltyReward {
+    pub reward_id: String,  // vendor-chosen slug, e.g. "free-coffee"
+    pub name: String,
+    pub points_cost: i64,
+    pub discount_usd: f64,
+}
+
+/// A vendor's loyalty scheme
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoyaltyProgram {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub vendor_address: String,
+    pub enabled: bool,
+    pub points_per_usd: f64,
+    pub rewards: Vec<LoyaltyReward>,
+    pub updated_at: i64,
+}
+
+impl LoyaltyProgram {
+    /// Points earned for a payment, rounded down
+    pub fn points_for(&self, price_usd: f64) -> i64 {
+        if !self.enabled || price_usd <= 0.0 {
+            return 0;
+        }
+        (price_usd * self.points_per_usd).floor() as i64
+    }
+
+    pub fn reward(&self, reward_id: &str) -> Option<&LoyaltyReward> {
+        self.rewards.iter().find(|r| r.reward_id == reward_id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateLoyaltyProgramRequest {
+    pub enabled: bool,
+    pub points_per_usd: f64,
+    #[serde(default)]
+    pub rewards: Vec<LoyaltyReward>,
+}
+
+/// A customer's points with one vendor
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoyaltyAccount {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub vendor_address: String,
+    pub customer_address: String,
+    pub points: i64,
+    pub lifetime_points: i64,
+    pub updated_at: i64,
+}
+
+/// A reward applied to a payment. Its discount is taken off the bundle at supplement
+/// time; the points are deducted when the payment is signed, and given back if it fails.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoyaltyRedemption {
+    pub reward_id: String,
+    pub customer_address: String,
+    pub points_cost: i64,
+    pub discount_usd: f64,
+    pub redeemed_at: i64,
+    #[serde(default)]
+    pub points_deducted: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemLoyaltyRequest {
+    pub reward_id: String,
+    pub customer_address: String,
+}
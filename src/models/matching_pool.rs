@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum MatchingPoolStatus {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "exhausted")]
+    Exhausted,
+    #[serde(rename = "closed")]
+    Closed,
+}
+
+impl std::fmt::Display for MatchingPoolStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchingPoolStatus::Active => write!(f, "active"),
+            MatchingPoolStatus::Exhausted => write!(f, "exhausted"),
+            MatchingPoolStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// Sponsor money set aside to match donations to one or more causes. The sponsor funds the
+/// matching vault with the causes' tokens, matches are paid out of it, and the pool tracks
+/// how much of the budget is used.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchingPool {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub sponsor: String,
+    pub cause_symbols: Vec<String>,           // token symbols of the causes being matched
+    pub match_ratio: f64,                     // matched cents per donated cent, e.g. 1.0 for 1:1
+    pub cap_cents: i64,                       // total budget
+    pub per_donation_cap_cents: Option<i64>,  // most a single donation can draw
+    pub matched_cents: i64,                   // budget used so far
+    pub status: MatchingPoolStatus,
+    pub starts_at: i64,
+    pub ends_at: Option<i64>,
+    pub created_by: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl MatchingPool {
+    pub fn remaining_cents(&self) -> i64 {
+        (self.cap_cents - self.matched_cents).max(0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum MatchEventStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "credited")]
+    Credited,
+    #[serde(rename = "failed")]
+    Failed,  // budget stays reserved; retried by the sweep once nothing is known to have moved
+}
+
+impl std::fmt::Display for MatchEventStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchEventStatus::Pending => write!(f, "pending"),
+            MatchEventStatus::Credited => write!(f, "credited"),
+            MatchEventStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// One donation matched from a pool. Unique per (pool, checkout session).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub pool_id: ObjectId,
+    pub stripe_session_id: String,
+    pub token_symbol: String,
+    pub donor_wallet: String,
+    pub donation_cents: i64,
+    pub matched_cents: i64,
+    #[serde(default)]
+    pub units: i64,  // token units owed, quoted at the cause's price when matched
+    pub tokens_credited: f64,
+    pub status: MatchEventStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMatchingPoolRequest {
+    pub name: String,
+    pub sponsor: String,
+    pub cause_symbols: Vec<String>,
+    pub match_ratio: f64,
+    pub cap_cents: i64,
+    pub per_donation_cap_cents: Option<i64>,
+    pub starts_at: Option<i64>,  // defaults to now
+    pub ends_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchingPoolQuery {
+    pub cause_symbol: Option<String>,
+    pub status: Option<String>,   // active | exhausted | closed
+}
+
+/// Pool with its budget usage, for status endpoints
+#[derive(Debug, Serialize)]
+pub struct MatchingPoolSummary {
+    #[serde(flatten)]
+    pub pool: MatchingPool,
+    pub remaining_cents: i64,
+    pub match_count: u64,
+}
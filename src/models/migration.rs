@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// A migration that has been applied to this database, kept in `schema_migrations`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaMigration {
+    #[serde(rename = "_id")]
+    pub version: i32,
+    pub name: String,
+    pub summary: String,  // what it changed, e.g. "42 users backfilled"
+    pub applied_at: i64,
+}
@@ -8,13 +8,79 @@ pub mod cause;
 pub mod cause_draft;
 mod webhook;
 pub mod partnered_vendor;
+pub mod token_key;
+pub mod audit_log;
+pub mod settlement_report;
+pub mod reconciliation;
+pub mod webhook_failure;
+pub mod blocked_word;
+pub mod matching_pool;
+pub mod funding_round;
+pub mod contact;
+pub mod payment_request;
+pub mod account;
+pub mod notification;
+pub mod review;
+pub mod loyalty;
+pub mod promo_code;
+pub mod voucher;
+pub mod escrow;
+pub mod dispute;
+pub mod payment_schedule;
+pub mod invoice;
+pub mod preference_template;
+pub mod migration;
+pub mod holding;
+pub mod activity;
+pub mod feature_flag;
+pub mod scheduled_job;
+pub mod job;
+pub mod embed_token;
+pub mod campaign;
+pub mod fundraiser;
+pub mod payout_event;
+pub mod organization;
+pub mod request_signature;
 
 pub use message::Message;
 pub use key::KeyPair;
 pub use error::ApiError;
-pub use user::{User, CreateUserRequest, Preferences};
-pub use token::{Token, TokenValuation, DiscountConsumption, TokenPayment, TokenBalance, TransactionRecord};
-pub use payment::{Payment, PaymentStatus, CreatePaymentRequest, PaymentIdResponse, SupplementPaymentRequest, SupplementPaymentResponse, DepositRecord};
-pub use webhook::WebhookError;
+pub use user::{User, CreateUserRequest, Preferences, Role, UpdateRolesRequest, UserDataExport, AnonymizationSummary, UpdatePrivacyRequest, UpdateProfileRequest, UsernameAvailability, USERNAME_CHANGE_COOLDOWN_SECS, VendorStripeAccount, VendorOnboardingRequest, PaymentCodeNamespace, PaymentCodeNamespaceRequest, DEFAULT_SHORT_CODE_LENGTH};
+pub use token::{Token, TokenHolders, TopHolder, TokenHoldersQuery, TokenValuation, DiscountConsumption, TokenPayment, OnChainAmount, TokenBalance, TransactionRecord};
+pub use payment::{Payment, PaymentStatus, CreatePaymentRequest, CreatePaymentBatchRequest, PaymentBatchItemResult, PaymentBatchResponse, MAX_PAYMENT_BATCH_SIZE, PaymentSplit, SplitType, SplitLeg, MAX_PAYMENT_SPLITS, PaymentIdResponse, SupplementPaymentRequest, SupplementPaymentResponse, SubmittedAllowance, HeldAuthorization, PaymentAuthorization, DEFAULT_CAPTURE_WINDOW_MINUTES, MAX_CAPTURE_WINDOW_MINUTES, DepositRecord, DonationAttribution, CauseAnalytics, ReferrerTotals, ManualCredit, ManualCreditRequest, CreditReservation, CreditReservationStatus, ResolveCreditRequest, PendingDeposit, PendingDepositStatus, UnclaimedDeposit, UnclaimedDepositStatus, ClaimLinkRequest, ClaimDepositsRequest, ClaimDepositsResponse};
+pub use webhook::{WebhookError, WebhookEndpoint, WebhookSecretStatus};
 pub use cause_draft::{CauseDraft, DraftStatus};
-pub use partnered_vendor::PartneredVendor;
\ No newline at end of file
+pub use partnered_vendor::{PartneredVendor, GeoPoint, OpeningHours, UpdateVendorProfileRequest, NearbyVendorsQuery, NearbyVendor};
+pub use token_key::{TokenIssuerKey, EncryptedBlob};
+pub use audit_log::{AuditLog, AuditAction, AuditLogQuery, AuditLogPage};
+pub use settlement_report::{DailySettlementReport, TokenSettlement, DailyReportQuery};
+pub use reconciliation::{ReconciliationIssue, ReconciliationRun, ReconciliationIssueQuery, RunReconciliationRequest, StripeChargeStatus, StripeChargeCheck, StripeReconciliationReport, StripeReconciliationQuery};
+pub use webhook_failure::{WebhookFailure, WebhookFailureStatus, WebhookFailureQuery, ProcessedStripeEvent, WebhookJob, WebhookJobStatus, WebhookQueueQuery, WebhookQueueStatus, MAX_WEBHOOK_JOB_ATTEMPTS};
+pub use blocked_word::{BlockedWord, BlockedWordKind};
+pub use matching_pool::{MatchingPool, MatchingPoolStatus, MatchEvent, MatchEventStatus, CreateMatchingPoolRequest, MatchingPoolQuery, MatchingPoolSummary};
+pub use funding_round::{FundingRound, FundingRoundStatus, RoundAllocation, RoundContribution, RoundPayout, RoundPayoutStatus, CreateFundingRoundRequest, FundingRoundReport};
+pub use contact::{Contact, SaveContactRequest, ContactEntry, MAX_CONTACTS};
+pub use payment_request::{PaymentRequest, PaymentRequestStatus, CreatePaymentRequestRequest, PaymentRequestQuery, AcceptPaymentRequestResponse, DEFAULT_PAYMENT_REQUEST_TTL_HOURS, MAX_PAYMENT_REQUEST_TTL_HOURS};
+pub use account::{Account, LinkedWallet, LinkWalletRequest, AccountActivityItem, MAX_LINKED_WALLETS};
+pub use notification::{NotificationEvent, NotificationPreferences, EventToggles, DevicePlatform, DeviceToken, RegisterDeviceRequest, PushNotification};
+pub use review::{Review, CreateReviewRequest, VendorRating, ReviewQuery, ReviewPage, MAX_REVIEW_COMMENT_CHARS};
+pub use loyalty::{LoyaltyProgram, LoyaltyReward, LoyaltyAccount, LoyaltyRedemption, UpdateLoyaltyProgramRequest, RedeemLoyaltyRequest, MAX_LOYALTY_REWARDS};
+pub use promo_code::{PromoCode, PromoDiscountType, CreatePromoCodeRequest, UpdatePromoCodeRequest, AppliedPromo};
+pub use voucher::{Voucher, VoucherStatus, VoucherFunding, CreateVoucherRequest, FundVoucherRequest, VoucherQuery, VoucherPreview, DEFAULT_VOUCHER_TTL_DAYS, MAX_VOUCHER_TTL_DAYS};
+pub use escrow::{PaymentEscrow, EscrowStatus, EscrowOutcome, DEFAULT_ESCROW_HOLD_HOURS, MAX_ESCROW_HOLD_HOURS};
+pub use dispute::{Dispute, DisputeStatus, DisputeRefundStatus, DisputeRefundSource, OpenDisputeRequest, RespondToDisputeRequest, ResolveDisputeRequest, ResolveDisputeRefundRequest, LiabilityQuery, DisputeQuery, DISPUTE_WINDOW_DAYS, MAX_DISPUTE_TEXT_CHARS};
+pub use payment_schedule::{PaymentSchedule, ScheduleFrequency, ScheduleStatus, CreatePaymentScheduleRequest, UpdatePaymentScheduleRequest, PaymentScheduleQuery, PaySchedulePaymentResponse, MAX_PAYMENT_SCHEDULES};
+pub use invoice::{Invoice, InvoiceStatus, InvoiceLineItem, CreateInvoiceRequest, InvoiceQuery, PayInvoiceResponse, MAX_INVOICE_LINE_ITEMS, MAX_INVOICE_REMINDERS};
+pub use preference_template::{PreferenceTemplate, PreferenceChange, PreferenceChangeSource, UpdatePreferencesRequest, SavePreferenceTemplateRequest, PreferenceLedgerEntry, PreferenceLedgerKind, PreferenceLedgerQuery, PreferenceLedgerPage, SpendingWeights, MAX_PREFERENCE_TEMPLATES, PREFERENCE_HISTORY_LIMIT};
+pub use migration::SchemaMigration;
+pub use holding::Holding;
+pub use activity::{ActivityEvent, ActivityKind, ActivityAmount, ActivityQuery, ActivityPage, ACTIVITY_TYPES};
+pub use feature_flag::{FeatureFlag, FeatureDefinition, FeatureFlagStatus, SetFeatureFlagRequest, FeatureFlagQuery, FEATURES, feature_definition};
+pub use scheduled_job::{ScheduledJob, SchedulerLease, SchedulerStatus, JobRunStatus};
+pub use job::{Job, JobKind, JobStatus, JobAccepted};
+pub use embed_token::{EmbedToken, CreateEmbedTokenRequest, EmbedDonationRequest, EmbedDonationResponse, MAX_EMBED_TOKENS};
+pub use campaign::{Campaign, CampaignStatus, CampaignProgress, CreateCampaignRequest, UpdateCampaignRequest, MAX_ACTIVE_CAMPAIGNS};
+pub use fundraiser::{FundraiserPage, FundraiserStatus, FundraiserProgress, CreateFundraiserRequest, UpdateFundraiserRequest, FundraiserDonationRequest, LeaderboardQuery, LeaderboardEntry, MAX_FUNDRAISERS_PER_WALLET};
+pub use payout_event::{PayoutEvent, PayoutEventKind, PayoutEventQuery};
+pub use organization::{Organization, CreateOrganizationRequest, OrganizationAdminRequest, OrganizationCreated, OrganizationCause, OrganizationDashboard, MAX_ORGANIZATION_ADMINS};
+pub use request_signature::UsedSignature;
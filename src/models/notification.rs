@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use std::collections::HashMap;
+
+/// Things a user can be notified about
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationEvent {
+    #[serde(rename = "payment_received")]
+    PaymentReceived,
+    #[serde(rename = "deposit_credited")]
+    DepositCredited,
+    #[serde(rename = "payment_request_received")]
+    PaymentRequestReceived,
+    #[serde(rename = "payment_request_updated")]
+    PaymentRequestUpdated,  // a request you sent was paid, declined or cancelled
+    #[serde(rename = "dispute_updated")]
+    DisputeUpdated,  // a dispute on one of your payments was opened, answered or resolved
+    #[serde(rename = "scheduled_payment_due")]
+    ScheduledPaymentDue,  // a run of one of your payment schedules is ready to sign
+    #[serde(rename = "invoice_received")]
+    InvoiceReceived,
+    #[serde(rename = "invoice_overdue")]
+    InvoiceOverdue,  // reminder for an invoice you haven't paid by its due date
+}
+
+impl std::fmt::Display for NotificationEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationEvent::PaymentReceived => write!(f, "payment_received"),
+            NotificationEvent::DepositCredited => write!(f, "deposit_credited"),
+            NotificationEvent::PaymentRequestReceived => write!(f, "payment_request_received"),
+            NotificationEvent::PaymentRequestUpdated => write!(f, "payment_request_updated"),
+            NotificationEvent::DisputeUpdated => write!(f, "dispute_updated"),
+            NotificationEvent::ScheduledPaymentDue => write!(f, "scheduled_payment_due"),
+            NotificationEvent::InvoiceReceived => write!(f, "invoice_received"),
+            NotificationEvent::InvoiceOverdue => write!(f, "invoice_overdue"),
+        }
+    }
+}
+
+/// A user's notification settings, stored on the user. Channels and events are on
+/// unless turned off; an event is sent on every enabled channel.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NotificationPreferences {
+    #[serde(default = "enabled")]
+    pub email: bool,
+    #[serde(default = "enabled")]
+    pub push: bool,
+    #[serde(default)]
+    pub events: EventToggles,
+}
+
+/// On/off per event type
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EventToggles {
+    #[serde(default = "enabled")]
+    pub payment_received: bool,
+    #[serde(default = "enabled")]
+    pub deposit_credited: bool,
+    #[serde(default = "enabled")]
+    pub payment_request_received: bool,
+    #[serde(default = "enabled")]
+    pub payment_request_updated: bool,
+    #[serde(default = "enabled")]
+    pub dispute_updated: bool,
+    #[serde(default = "enabled")]
+    pub scheduled_payment_due: bool,
+    #[serde(default = "enabled")]
+    pub invoice_received: bool,
+    #[serde(default = "enabled")]
+    pub invoice_overdue: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self { email: true, push: true, events: EventToggles::default() }
+    }
+}
+
+impl Default for EventToggles {
+    fn default() -> Self {
+        Self {
+            payment_received: true,
+            deposit_credited: true,
+            payment_request_received: true,
+            payment_request_updated: true,
+            dispute_updated: true,
+            scheduled_payment_due: true,
+            invoice_received: true,
+            invoice_overdue: true,
+        }
+    }
+}
+
+impl EventToggles {
+    pub fn enabled(&self, event: NotificationEvent) -> bool {
+        match event {
+            NotificationEvent::PaymentReceived => self.payment_received,
+            NotificationEvent::DepositCredited => self.deposit_credited,
+            NotificationEvent::PaymentRequestReceived => self.payment_request_received,
+            NotificationEvent::PaymentRequestUpdated => self.payment_request_updated,
+            NotificationEvent::DisputeUpdated => self.dispute_updated,
+            NotificationEvent::ScheduledPaymentDue => self.scheduled_payment_due,
+            NotificationEvent::InvoiceReceived => self.invoice_received,
+            NotificationEvent::InvoiceOverdue => self.invoice_overdue,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub fn allows_push(&self, event: NotificationEvent) -> bool {
+        self.push && self.events.enabled(event)
+    }
+
+    pub fn allows_email(&self, event: NotificationEvent) -> bool {
+        self.email && self.events.enabled(event)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum DevicePlatform {
+    #[serde(rename = "android")]
+    Android,  // delivered through FCM
+    #[serde(rename = "ios")]
+    Ios,  // delivered through APNs
+}
+
+/// A push token registered by one of a wallet's devices
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeviceToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub wallet_address: String,
+    pub token: String,
+    pub platform: DevicePlatform,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterDeviceRequest {
+    pub token: String,
+    pub platform: DevicePlatform,
+}
+
+/// A notification waiting to be pushed to every device of a wallet. Queued in MongoDB so
+/// a restart doesn't lose it; a replica delivering a batch leases it until `lease_expires_at`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PushNotification {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub wallet_address: String,
+    pub event: NotificationEvent,
+    pub title: String,
+    pub body: String,
+    pub data: HashMap<String, String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub queued_at: DateTime<Utc>,  // dropped a day after, if never delivered
+    #[serde(default)]
+    pub lease: Option<String>,
+    #[serde(default)]
+    pub lease_expires_at: i64,
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::models::cause::{BusinessType, CauseStatus, default_country};
+use crate::models::PayoutEvent;
+use crate::utils::profile::validate_email;
+use crate::utils::validation::{self, FieldErrors, Validate};
+
+/// Wallets that can administer one organization
+pub const MAX_ORGANIZATION_ADMINS: usize = 20;
+
+/// An organization running several causes. It onboards with Stripe once; every cause it
+/// creates is paid out to the same connected account.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Organization {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,  // unique, case-insensitively
+    pub contact_email: String,
+    pub country: String,
+    pub business_type: BusinessType,
+    pub stripe_account_id: Option<String>,
+    pub onboarding_completed: bool,
+    pub admins: Vec<String>,  // wallets that can create and manage its causes; never empty
+    pub created_by: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Organization {
+    pub fn is_admin(&self, wallet_address: &str) -> bool {
+        self.admins.iter().any(|admin| admin == wallet_address)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationRequest {
+    pub name: String,
+    pub contact_email: String,  // also the Stripe account's email
+    #[serde(default = "default_country")]
+    pub country: String,
+    #[serde(default)]
+    pub business_type: BusinessType,
+}
+
+impl Validate for CreateOrganizationRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("name", validation::required(&self.name));
+        errors.check("contact_email", validation::required(&self.contact_email).and_then(|_| validate_email(&self.contact_email)));
+        if self.country.len() != 2 || !self.country.chars().all(|c| c.is_ascii_alphabetic()) {
+            errors.add("country", "Must be a two-letter country code, e.g. US");
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrganizationAdminRequest {
+    pub wallet_address: String,
+}
+
+impl Validate for OrganizationAdminRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("wallet_address", validation::wallet_address(&self.wallet_address));
+        errors.into_result()
+    }
+}
+
+/// A new organization and where its admin finishes Stripe onboarding
+#[derive(Debug, Serialize)]
+pub struct OrganizationCreated {
+    pub organization: Organization,
+    pub onboarding_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationCause {
+    pub cause_id: String,
+    pub name: String,
+    pub token_symbol: String,
+    pub status: CauseStatus,
+    pub amount_donated: f64,
+    pub tokens_purchased: f64,
+}
+
+/// Every cause of an organization and what they've raised together
+#[derive(Debug, Serialize)]
+pub struct OrganizationDashboard {
+    pub organization: Organization,
+    pub causes: Vec<OrganizationCause>,
+    pub total_donated: f64,
+    pub total_tokens_purchased: f64,
+    pub recent_payouts: Vec<PayoutEvent>,
+}
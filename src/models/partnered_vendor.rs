@@ -1,5 +1,36 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
+use super::review::VendorRating;
+
+/// GeoJSON point as stored for the 2dsphere index: coordinates are [lng, lat]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GeoPoint {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub coordinates: [f64; 2],
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lng: f64) -> Self {
+        Self { kind: "Point".to_string(), coordinates: [lng, lat] }
+    }
+
+    pub fn lat(&self) -> f64 {
+        self.coordinates[1]
+    }
+
+    pub fn lng(&self) -> f64 {
+        self.coordinates[0]
+    }
+}
+
+/// Opening time for one weekday, local time at the vendor, "HH:MM"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpeningHours {
+    pub day: String,  // "mon".."sun"
+    pub opens: String,
+    pub closes: String,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PartneredVendor {
@@ -10,6 +41,16 @@ pub struct PartneredVendor {
     pub description: Option<String>,
     pub google_maps_link: Option<String>,
     pub website_link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoPoint>,  // set to appear in the nearby directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hours: Vec<OpeningHours>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<VendorRating>,  // recomputed whenever a review is left
 }
 
 impl PartneredVendor {
@@ -27,6 +68,39 @@ impl PartneredVendor {
             description,
             google_maps_link,
             website_link,
+            business_name: None,
+            category: None,
+            location: None,
+            hours: Vec::new(),
+            rating: None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Directory fields a vendor can set. Omitted fields are unchanged; an empty
+/// `business_name` or `category` clears it, and `lat`/`lng` must come together.
+#[derive(Debug, Deserialize)]
+pub struct UpdateVendorProfileRequest {
+    pub business_name: Option<String>,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+    pub hours: Option<Vec<OpeningHours>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NearbyVendorsQuery {
+    pub lat: f64,
+    pub lng: f64,
+    pub radius: Option<f64>,  // meters
+    pub category: Option<String>,
+}
+
+/// A vendor in the nearby directory, closest first
+#[derive(Debug, Serialize)]
+pub struct NearbyVendor {
+    #[serde(flatten)]
+    pub vendor: PartneredVendor,
+    pub distance_m: f64,
+}
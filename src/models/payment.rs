@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::Document;
-use crate::models::{TokenBalance, TokenPayment, DiscountConsumption, TokenValuation};
+use crate::models::activity::{ActivityEvent, ActivityKind};
+use crate::utils::validation::{self, FieldErrors, Validate};
+use crate::utils::payment_code::scoped_payment_code;
+use crate::models::{TokenBalance, TokenPayment, OnChainAmount, DiscountConsumption, TokenValuation, LoyaltyRedemption, AppliedPromo, PaymentEscrow};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Payment {
@@ -20,6 +23,101 @@ pub struct Payment {
     pub initial_payment_bundle: Option<Vec<TokenPayment>>,  // Before discounts
     #[serde(default)]  // Will default to false for old records
     pub recepient_verified: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,  // executor transaction for the payer's debit allowances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitted_at: Option<i64>,  // when the allowances were handed to the executor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality_checked_at: Option<i64>,  // last time the finality poller asked the executor about it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,  // why the executor rejected the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_request_id: Option<String>,  // set when paying a user's payment request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loyalty_redemption: Option<LoyaltyRedemption>,  // reward the vendor applied to this payment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loyalty_points_earned: Option<i64>,  // set once loyalty is settled on completion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promo: Option<AppliedPromo>,  // promo code the customer entered at supplement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escrow: Option<PaymentEscrow>,  // set when the vendor asked for funds to be held
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_id: Option<String>,  // set for a run of a customer's payment schedule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,  // set when created with others through /payments/batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invoice_id: Option<String>,  // set when paying a vendor's invoice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valuation_overrides: Option<Vec<TokenValuation>>,  // vendor's one-off valuations, used instead of preferences
+    #[serde(default)]
+    pub budget_consumed: bool,  // discount_consumption was drawn from the vendor's budgets
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splits: Vec<PaymentSplit>,  // shares paid to other wallets; the vendor gets the rest
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split_legs: Vec<SplitLeg>,  // set at supplement for split payments, one per allowance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<PaymentAuthorization>,  // set when the vendor captures the payment themselves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_slug: Option<String>,  // with short_code, for vendors with their own code namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_code: Option<String>,   // unique per vendor_slug; entered as {vendor_slug}-{short_code}
+}
+
+/// Capture window of two-phase payments when the vendor doesn't choose one, and the longest
+pub const DEFAULT_CAPTURE_WINDOW_MINUTES: i64 = 24 * 60;
+pub const MAX_CAPTURE_WINDOW_MINUTES: i64 = 7 * 24 * 60;
+
+/// A two-phase payment: the customer's signed allowances are held, not submitted, until
+/// the vendor captures them within the window, or they're voided
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentAuthorization {
+    pub capture_window_minutes: i64,
+    pub authorized_at: Option<i64>,  // when the customer signed
+    pub expires_at: Option<i64>,     // voided by the scheduler if not captured by then
+    pub capture_nonce: Option<i64>,  // the payer's nonce once the held allowances are applied
+    pub captured_at: Option<i64>,
+    pub voided_at: Option<i64>,
+    pub voided_by: Option<String>,   // the vendor, an admin or "system" on expiry
+}
+
+impl PaymentAuthorization {
+    pub fn new(capture_window_minutes: i64) -> Self {
+        Self {
+            capture_window_minutes,
+            authorized_at: None,
+            expires_at: None,
+            capture_nonce: None,
+            captured_at: None,
+            voided_at: None,
+            voided_by: None,
+        }
+    }
+}
+
+/// Most wallets a payment can be split to besides the vendor
+pub const MAX_PAYMENT_SPLITS: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SplitType {
+    #[serde(rename = "percentage")]
+    Percentage,  // value is percent of the price, 0-100
+    #[serde(rename = "fixed_usd")]
+    FixedUsd,    // value is USD of the price before any promo
+}
+
+/// A share of a payment paid straight to another wallet, e.g. a marketplace's fee
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentSplit {
+    pub recipient_address: String,
+    pub split_type: SplitType,
+    pub value: f64,
+}
+
+/// What one recipient of a split payment is paid, as signed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SplitLeg {
+    pub recipient_address: String,
+    pub payment_bundle: Vec<TokenPayment>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,14 +125,104 @@ pub struct CreatePaymentRequest {
     pub vendor_address: String,
     pub vendor_name: String,
     pub price_usd: f64,
-    pub vendor_valuations: Option<Vec<TokenValuation>>,
+    pub vendor_valuations: Option<Vec<TokenValuation>>,  // per-payment overrides of the vendor's preferences
     #[serde(default)]  // Will default to false for old requests
     pub is_verified: bool,
+    #[serde(default)]
+    pub escrow: bool,  // hold the customer's tokens until the vendor captures them
+    #[serde(default)]
+    pub escrow_hold_hours: Option<i64>,  // auto-release after this long, default 14 days
+    #[serde(default)]
+    pub splits: Vec<PaymentSplit>,
+    #[serde(default)]
+    pub manual_capture: bool,  // hold the customer's signed allowances until the vendor captures them
+    #[serde(default)]
+    pub capture_window_minutes: Option<i64>,  // how long they can be captured, default 24 hours
+}
+
+impl Validate for CreatePaymentRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("vendor_address", validation::wallet_address(&self.vendor_address));
+        errors.check("vendor_name", validation::required(&self.vendor_name));
+        errors.check("price_usd", validation::positive_usd(self.price_usd));
+        if !self.splits.is_empty() {
+            errors.check("splits", self.check_splits());
+        }
+        if self.manual_capture && self.escrow {
+            errors.add("manual_capture", "Escrowed payments are already held until captured");
+        }
+        if let Some(window) = self.capture_window_minutes {
+            if !self.manual_capture {
+                errors.add("capture_window_minutes", "Only for payments with manual_capture");
+            } else if !(1..=MAX_CAPTURE_WINDOW_MINUTES).contains(&window) {
+                errors.add("capture_window_minutes", format!("Must be between 1 and {}", MAX_CAPTURE_WINDOW_MINUTES));
+            }
+        }
+        errors.into_result()
+    }
+}
+
+impl CreatePaymentRequest {
+    fn check_splits(&self) -> Result<(), String> {
+        if self.splits.len() > MAX_PAYMENT_SPLITS {
+            return Err(format!("At most {} splits", MAX_PAYMENT_SPLITS));
+        }
+        if self.escrow {
+            return Err("Escrowed payments can't be split".to_string());
+        }
+        let mut recipients = std::collections::HashSet::new();
+        for split in &self.splits {
+            validation::wallet_address(&split.recipient_address)?;
+            if split.recipient_address == self.vendor_address {
+                return Err("The vendor gets what the splits leave, so can't be a split recipient".to_string());
+            }
+            if !recipients.insert(split.recipient_address.as_str()) {
+                return Err(format!("{} has more than one split", split.recipient_address));
+            }
+            if !split.value.is_finite() || split.value <= 0.0 || (split.split_type == SplitType::Percentage && split.value > 100.0) {
+                return Err("Each split must be more than zero, and percentages at most 100".to_string());
+            }
+        }
+        crate::utils::payment_split::split_fractions(self.price_usd, &self.splits).map(|_| ())
+    }
+}
+
+/// Most payments a vendor can create in one batch
+pub const MAX_PAYMENT_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentBatchRequest {
+    pub payments: Vec<CreatePaymentRequest>,
+    #[serde(default)]
+    pub atomic: bool,  // create nothing if any payment is invalid
+}
+
+/// Outcome of one payment in a batch, in request order
+#[derive(Debug, Serialize)]
+pub struct PaymentBatchItemResult {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentBatchResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,  // none when nothing was created
+    pub created: usize,
+    pub failed: usize,
+    pub results: Vec<PaymentBatchItemResult>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PaymentIdResponse {
     pub payment_id: String,
+    pub payment_code: String,  // what the customer enters: {vendor_slug}-{short_code}, or the payment ID
     pub vendor_name: String,
     pub price_usd: f64,
 }
@@ -43,7 +231,9 @@ pub struct PaymentIdResponse {
 pub struct SupplementPaymentRequest {
     pub payer_address: String,
     pub payer_username: Option<String>,
-    pub payer_balances: Vec<TokenBalance>
+    pub payer_balances: Vec<TokenBalance>,
+    #[serde(default)]
+    pub promo_code: Option<String>,
 }
 
 
@@ -57,9 +247,14 @@ pub struct SupplementPaymentResponse {
     pub price_usd: f64,
     pub created_at: i64,
     pub payment_bundle: Vec<TokenPayment>,
+    pub on_chain_amounts: Vec<OnChainAmount>,  // payment_bundle as signed, index-aligned
     pub unsigned_transaction: String,
     pub vendor_valuations: Option<Vec<TokenValuation>>,
     pub discount_consumption: Option<Vec<DiscountConsumption>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promo: Option<AppliedPromo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split_legs: Vec<SplitLeg>,  // what each recipient is paid, when the payment is split
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,6 +271,28 @@ pub struct ProcessSignedTransactionRequest {
     pub discount_consumption: Option<Vec<DiscountConsumption>>,
 }
 
+/// The signed allowances of an authorized two-phase payment, held until it's captured or
+/// voided. Kept apart from the payment so they're never returned with it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HeldAuthorization {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<mongodb::bson::oid::ObjectId>,
+    pub payment_id: String,
+    pub signed_transaction: String,
+    pub created_at: i64,
+}
+
+/// A signed debit allowance that was submitted to the executor, so the same one can't be
+/// submitted again
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmittedAllowance {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<mongodb::bson::oid::ObjectId>,
+    pub hash: String,  // hex SHA-256 of the signed allowance
+    pub payment_id: String,
+    pub created_at: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PaymentStatusResponse {
     pub payment_id: String,
@@ -89,6 +306,8 @@ pub struct PaymentStatusResponse {
     pub computed_payment: Option<Vec<TokenPayment>>,
     pub vendor_valuations: Option<Vec<TokenValuation>>,
     pub discount_consumption: Option<Vec<DiscountConsumption>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -96,8 +315,74 @@ pub enum PaymentStatus {
     Created,
     CustomerAssigned,
     Calculated,
+    Submitted,  // sent to the executor, waiting for finality
     Completed,
     Failed,
+    Authorized,  // two-phase: signed and held until the vendor captures it
+    Voided,      // two-phase: not captured; the held allowances were discarded
+}
+
+/// Payments that haven't completed within this window are treated as expired
+pub const PAYMENT_CODE_TTL_SECS: i64 = 60 * 60;
+
+/// Lifecycle state shown to vendors, derived from status and age
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum PaymentState {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "processing")]
+    Processing,
+    #[serde(rename = "expired")]
+    Expired,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "authorized")]
+    Authorized,
+    #[serde(rename = "voided")]
+    Voided,
+}
+
+impl std::str::FromStr for PaymentState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(PaymentState::Active),
+            "processing" => Ok(PaymentState::Processing),
+            "expired" => Ok(PaymentState::Expired),
+            "completed" => Ok(PaymentState::Completed),
+            "failed" => Ok(PaymentState::Failed),
+            "authorized" => Ok(PaymentState::Authorized),
+            "voided" => Ok(PaymentState::Voided),
+            _ => Err(format!("Invalid payment status '{}', expected active, processing, expired, completed, failed, authorized or voided", s)),
+        }
+    }
+}
+
+impl Payment {
+    /// What the customer enters to pay: the vendor-scoped code when there is one
+    pub fn payment_code(&self) -> String {
+        match (&self.vendor_slug, &self.short_code) {
+            (Some(vendor_slug), Some(short_code)) => scoped_payment_code(vendor_slug, short_code),
+            _ => self.payment_id.clone(),
+        }
+    }
+
+    pub fn state(&self, now: i64) -> PaymentState {
+        match self.status {
+            PaymentStatus::Completed => PaymentState::Completed,
+            PaymentStatus::Failed => PaymentState::Failed,
+            // Signed, so the code's expiry gives way to the capture window
+            PaymentStatus::Authorized => PaymentState::Authorized,
+            PaymentStatus::Voided => PaymentState::Voided,
+            // Already signed and sent, so the payment code's expiry no longer applies
+            PaymentStatus::Submitted => PaymentState::Processing,
+            _ if now - self.created_at > PAYMENT_CODE_TTL_SECS => PaymentState::Expired,
+            _ => PaymentState::Active,
+        }
+    }
 }
 
 impl std::fmt::Display for PaymentStatus {
@@ -106,8 +391,11 @@ impl std::fmt::Display for PaymentStatus {
             PaymentStatus::Created => write!(f, "Created"),
             PaymentStatus::CustomerAssigned => write!(f, "CustomerAssigned"),
             PaymentStatus::Calculated => write!(f, "Calculated"),
+            PaymentStatus::Submitted => write!(f, "Submitted"),
             PaymentStatus::Completed => write!(f, "Completed"),
             PaymentStatus::Failed => write!(f, "Failed"),
+            PaymentStatus::Authorized => write!(f, "Authorized"),
+            PaymentStatus::Voided => write!(f, "Voided"),
         }
     }
 }
@@ -118,6 +406,28 @@ pub enum TransactionDirection {
     Received, // User was the vendor (vendor_address)
 }
 
+impl std::str::FromStr for TransactionDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sent" => Ok(TransactionDirection::Sent),
+            "received" => Ok(TransactionDirection::Received),
+            _ => Err(format!("Invalid direction '{}', expected sent or received", s)),
+        }
+    }
+}
+
+/// Filters on a wallet's transaction history. Deposits count as received and completed.
+#[derive(Debug, Deserialize, Default)]
+pub struct TransactionHistoryQuery {
+    pub from: Option<i64>,          // unix seconds, inclusive
+    pub to: Option<i64>,            // unix seconds, exclusive
+    pub token: Option<String>,      // token symbol paid or deposited
+    pub direction: Option<String>,  // sent | received
+    pub status: Option<String>,     // active | processing | expired | completed | failed | authorized | voided
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TransactionHistoryItem {
     pub payment_id: String,
@@ -129,6 +439,41 @@ pub struct TransactionHistoryItem {
     pub price_usd: f64,
     pub created_at: i64,
     pub computed_payment: Option<Vec<TokenPayment>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,
+}
+
+impl TransactionHistoryItem {
+    /// A payment as seen by one of its parties
+    pub fn for_wallet(payment: Payment, wallet_address: &str) -> Self {
+        let (direction, counterparty_address, counterparty_username) = if payment.vendor_address == wallet_address {
+            // The wallet is the vendor (received payment)
+            (
+                TransactionDirection::Received,
+                payment.customer_address.clone().unwrap_or("Unknown".to_string()),
+                payment.customer_username.clone(),
+            )
+        } else {
+            // The wallet is the customer (sent payment); the vendor name is effectively the username
+            (
+                TransactionDirection::Sent,
+                payment.vendor_address.clone(),
+                Some(payment.vendor_name.clone()),
+            )
+        };
+        Self {
+            payment_id: payment.payment_id,
+            direction,
+            counterparty_address,
+            counterparty_username,
+            vendor_name: payment.vendor_name,
+            status: payment.status,
+            price_usd: payment.price_usd,
+            created_at: payment.created_at,
+            computed_payment: payment.computed_payment,
+            executor_tx_id: payment.executor_tx_id,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -143,6 +488,25 @@ pub enum ActivityItem {
     Transaction(TransactionHistoryItem),
     #[serde(rename = "deposit")]
     Deposit(DepositRecord),
+    #[serde(rename = "transfer")]
+    Transfer(ActivityEvent),
+    #[serde(rename = "refund")]
+    Refund(ActivityEvent),
+    #[serde(rename = "redemption")]
+    Redemption(ActivityEvent),
+    #[serde(rename = "reward")]
+    Reward(ActivityEvent),
+}
+
+impl From<ActivityEvent> for ActivityItem {
+    fn from(event: ActivityEvent) -> Self {
+        match event.kind {
+            ActivityKind::Transfer => ActivityItem::Transfer(event),
+            ActivityKind::Refund => ActivityItem::Refund(event),
+            ActivityKind::Redemption => ActivityItem::Redemption(event),
+            ActivityKind::Reward => ActivityItem::Reward(event),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -155,4 +519,540 @@ pub struct DepositRecord {
     pub amount_deposited_usd: f64,
     pub amount_tokens_received: f64,
     pub created_at: i64, // Unix timestamp to match transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripe_session_id: Option<String>,  // checkout session that paid for this deposit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripe_payment_intent_id: Option<String>,  // set instead for embedded card form payments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,  // executor transaction that credited the wallet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manual_credit: Option<ManualCredit>,  // set when an admin credited the wallet by hand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<String>,  // the donate link or embedded button that brought the donor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<String>,  // campaign of the cause the donation counts towards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fundraiser_id: Option<String>,  // supporter's fundraiser page it was given through
+}
+
+/// Where a donation came from. Set when its checkout or PaymentIntent is created, carried
+/// through the Stripe metadata and stored on the deposit record.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DonationAttribution {
+    pub referrer: Option<String>,
+    pub campaign_id: Option<String>,
+    pub fundraiser_id: Option<String>,
+}
+
+impl DonationAttribution {
+    pub fn to_metadata(&self) -> Vec<(String, String)> {
+        [("referrer", &self.referrer), ("campaign_id", &self.campaign_id), ("fundraiser_id", &self.fundraiser_id)]
+            .into_iter()
+            .filter_map(|(key, value)| value.clone().map(|value| (key.to_string(), value)))
+            .collect()
+    }
+
+    pub fn from_metadata(metadata: &std::collections::HashMap<String, String>) -> Self {
+        Self {
+            referrer: metadata.get("referrer").cloned(),
+            campaign_id: metadata.get("campaign_id").cloned(),
+            fundraiser_id: metadata.get("fundraiser_id").cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum PendingDepositStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+impl std::fmt::Display for PendingDepositStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PendingDepositStatus::Pending => write!(f, "pending"),
+            PendingDepositStatus::Completed => write!(f, "completed"),
+            PendingDepositStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// A checkout session we created that has not been credited yet, so wallets can
+/// show a "processing" entry between payment and the webhook
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingDeposit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<mongodb::bson::oid::ObjectId>,
+    pub stripe_session_id: String,
+    pub wallet_address: String,
+    pub token_symbol: String,
+    pub cause_id: Option<String>,
+    pub amount_cents: i64,
+    pub status: PendingDepositStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub expires_at: i64,  // when Stripe expires the checkout session
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnclaimedDepositStatus {
+    Unclaimed,
+    Claiming,  // reserved by a claim while its tokens are credited
+    Claimed,
+    Failed,    // the credit may or may not have landed; left for an admin to reconcile
+}
+
+impl std::fmt::Display for UnclaimedDepositStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnclaimedDepositStatus::Unclaimed => write!(f, "unclaimed"),
+            UnclaimedDepositStatus::Claiming => write!(f, "claiming"),
+            UnclaimedDepositStatus::Claimed => write!(f, "claimed"),
+            UnclaimedDepositStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A paid donation or top-up that had no wallet to credit, held against the Stripe
+/// customer's email until they claim it with `POST /deposits/claim`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnclaimedDeposit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<mongodb::bson::oid::ObjectId>,
+    pub payment_id: String,  // checkout session or PaymentIntent ID
+    pub stripe_session_id: Option<String>,
+    pub stripe_payment_intent_id: Option<String>,
+    pub email: String,       // lowercased
+    pub token_symbol: String,
+    pub amount_cents: i64,
+    pub metadata: std::collections::HashMap<String, String>,  // the payment's Stripe metadata, to credit it as the webhook would have
+    pub status: UnclaimedDepositStatus,
+    pub created_at: i64,
+    pub claimed_by: Option<String>,  // wallet address
+    pub claimed_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+}
+
+fn claim_email(email: &str) -> Result<String, String> {
+    crate::utils::profile::validate_email(email)?.ok_or_else(|| "Required".to_string())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClaimLinkRequest {
+    pub email: String,
+}
+
+impl Validate for ClaimLinkRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("email", claim_email(&self.email));
+        errors.into_result()
+    }
+}
+
+/// Claim the deposits held for an email into the signing wallet
+#[derive(Debug, Deserialize)]
+pub struct ClaimDepositsRequest {
+    pub email: String,
+    pub token: String,  // from the emailed claim link
+}
+
+impl Validate for ClaimDepositsRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("email", claim_email(&self.email));
+        errors.check("token", validation::required(&self.token));
+        errors.into_result()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClaimDepositsResponse {
+    pub deposits: Vec<DepositRecord>,
+    pub failed: usize,  // left unclaimed to retry, or failed for an admin when the credit may have landed
+}
+
+/// Who issued a manual credit and why
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManualCredit {
+    pub idempotency_key: String,
+    pub reason: String,
+    pub credited_by: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ManualCreditRequest {
+    pub wallet_address: String,
+    pub token_symbol: String,
+    pub amount: i64,  // base units (cents for USD)
+    pub idempotency_key: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditReservationStatus {
+    Pending,   // reserved while the tokens are transferred
+    Credited,
+    Failed,    // the transfer failed and may or may not have landed; waits for an admin
+}
+
+impl std::fmt::Display for CreditReservationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreditReservationStatus::Pending => write!(f, "pending"),
+            CreditReservationStatus::Credited => write!(f, "credited"),
+            CreditReservationStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A credit claimed under a unique key before any tokens move, so a retry or a concurrent
+/// request can never credit it a second time. Failed credits keep their key until an admin
+/// resolves them, since a failed transfer may still have landed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreditReservation {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<mongodb::bson::oid::ObjectId>,
+    pub key: String,  // unique: "manual:<idempotency key>", "checkout:<session ID>", "payment_intent:<ID>" or "match:<match event ID>"
+    pub wallet_address: String,
+    pub token_symbol: String,
+    pub amount: i64,  // base units (cents for USD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manual_credit: Option<ManualCredit>,
+    pub status: CreditReservationStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor_tx_id: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl CreditReservation {
+    pub fn new(key: String, wallet_address: &str, token_symbol: &str, amount: i64) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id: None,
+            key,
+            wallet_address: wallet_address.to_string(),
+            token_symbol: token_symbol.to_string(),
+            amount,
+            manual_credit: None,
+            status: CreditReservationStatus::Pending,
+            failure_reason: None,
+            executor_tx_id: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn manual(request: &ManualCreditRequest, credited_by: &str) -> Self {
+        let mut reservation = Self::new(
+            format!("manual:{}", request.idempotency_key),
+            &request.wallet_address,
+            &request.token_symbol,
+            request.amount,
+        );
+        reservation.manual_credit = Some(ManualCredit {
+            idempotency_key: request.idempotency_key.clone(),
+            reason: request.reason.clone(),
+            credited_by: credited_by.to_string(),
+        });
+        reservation
+    }
+}
+
+/// An admin's verdict on a failed credit, after checking the executor
+#[derive(Debug, Deserialize)]
+pub struct ResolveCreditRequest {
+    pub credited: bool,  // true if the tokens did reach the wallet, false to release the key for a retry
+    pub executor_tx_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VendorPaymentsQuery {
+    pub status: Option<String>,   // active | processing | expired | completed | failed | authorized | voided
+    pub from: Option<i64>,        // unix seconds, inclusive
+    pub to: Option<i64>,          // unix seconds, exclusive
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,   // next_cursor from the previous page
+}
+
+#[derive(Debug, Serialize)]
+pub struct VendorPaymentItem {
+    #[serde(flatten)]
+    pub payment: Payment,
+    pub state: PaymentState,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VendorPaymentsPage {
+    pub payments: Vec<VendorPaymentItem>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CauseDonationsQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,   // next_cursor from the previous page
+}
+
+/// A single donation as shown on a cause page
+#[derive(Debug, Serialize)]
+pub struct CauseDonation {
+    pub amount_usd: f64,
+    pub tokens_received: f64,
+    pub created_at: i64,
+    pub donor_username: Option<String>,  // None for anonymous donors
+}
+
+#[derive(Debug, Serialize)]
+pub struct CauseDonationsPage {
+    pub donations: Vec<CauseDonation>,
+    pub next_cursor: Option<String>,
+}
+
+/// Donations that came through one referrer; `referrer` is None for direct donations
+#[derive(Debug, Serialize)]
+pub struct ReferrerTotals {
+    pub referrer: Option<String>,
+    pub donations: i64,
+    pub amount_usd: f64,
+    pub tokens: f64,
+}
+
+/// A cause's donations and where they came from, largest referrer first
+#[derive(Debug, Serialize)]
+pub struct CauseAnalytics {
+    pub cause_id: String,
+    pub token_symbol: String,
+    pub donations: i64,
+    pub amount_usd: f64,
+    pub tokens: f64,
+    pub by_referrer: Vec<ReferrerTotals>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum DonationSessionStatus {
+    #[serde(rename = "credited")]
+    Credited,      // paid and the deposit is recorded
+    #[serde(rename = "processing")]
+    Processing,    // paid, waiting for the webhook to credit tokens
+    #[serde(rename = "unpaid")]
+    Unpaid,
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+/// What the checkout success page should show for a session
+#[derive(Debug, Serialize)]
+pub struct DonationSessionResponse {
+    pub session_id: String,
+    pub status: DonationSessionStatus,
+    pub amount_cents: i64,
+    pub token_symbol: Option<String>,
+    pub deposit: Option<DepositRecord>,
+}
+
+/// Embedded card form payment: a donation when `cause_id` is set, otherwise a USD top-up
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentIntentRequest {
+    pub cause_id: Option<String>,
+    pub amount_cents: i64,
+    pub user_wallet_address: String,
+    pub referrer: Option<String>,     // donations only
+    pub campaign_id: Option<String>,  // donations only
+}
+
+impl Validate for CreatePaymentIntentRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if let Some(cause_id) = &self.cause_id {
+            errors.check("cause_id", mongodb::bson::oid::ObjectId::parse_str(cause_id).map_err(|_| "Must be a cause ID".to_string()));
+        }
+        if self.amount_cents <= 0 {
+            errors.add("amount_cents", "Must be greater than zero");
+        }
+        errors.check("user_wallet_address", validation::wallet_address(&self.user_wallet_address));
+        if let Some(referrer) = &self.referrer {
+            errors.check("referrer", validation::referrer(referrer));
+        }
+        if let Some(campaign_id) = &self.campaign_id {
+            errors.check("campaign_id", mongodb::bson::oid::ObjectId::parse_str(campaign_id).map_err(|_| "Must be a campaign ID".to_string()));
+        }
+        errors.into_result()
+    }
+}
+
+impl CreatePaymentIntentRequest {
+    pub fn attribution(&self) -> DonationAttribution {
+        DonationAttribution {
+            referrer: self.referrer.as_deref().map(str::trim).map(str::to_string),
+            campaign_id: self.campaign_id.clone(),
+            fundraiser_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPaymentIntentRequest {
+    pub payment_method_id: String,
+    pub return_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentResponse {
+    pub payment_intent_id: String,
+    pub client_secret: Option<String>,
+    pub status: String,
+    pub amount_cents: i64,
+}
+
+/// What the frontend needs to render Apple Pay / Google Pay buttons (Stripe Payment Request Button)
+#[derive(Debug, Serialize)]
+pub struct PaymentMethodsResponse {
+    pub publishable_key: Option<String>,
+    pub payment_method_types: Vec<String>,
+    pub apple_pay: bool,
+    pub google_pay: bool,
+    pub country: String,
+    pub currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTopupSessionRequest {
+    pub amount_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopupSessionResponse {
+    pub checkout_url: String,
+    pub session_id: String,
+}
+
+/// A card saved on the wallet's Stripe customer
+#[derive(Debug, Serialize)]
+pub struct SavedPaymentMethod {
+    pub id: String,
+    pub brand: Option<String>,
+    pub last4: Option<String>,
+    pub exp_month: Option<i64>,
+    pub exp_year: Option<i64>,
+    pub wallet: Option<String>,  // apple_pay / google_pay when the card came from a wallet
+}
+
+impl From<stripe::PaymentMethod> for SavedPaymentMethod {
+    fn from(method: stripe::PaymentMethod) -> Self {
+        let card = method.card.as_ref();
+        Self {
+            id: method.id.to_string(),
+            brand: card.map(|c| c.brand.clone()),
+            last4: card.map(|c| c.last4.clone()),
+            exp_month: card.map(|c| c.exp_month),
+            exp_year: card.map(|c| c.exp_year),
+            wallet: card.and_then(|c| c.wallet.as_ref()).map(|w| w.type_.as_str().to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const VENDOR: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+
+    fn request(fields: serde_json::Value) -> CreatePaymentRequest {
+        let mut request = serde_json::json!({
+            "vendor_address": VENDOR,
+            "vendor_name": "Corner Cafe",
+            "price_usd": 10.0,
+            "vendor_valuations": null,
+        });
+        request.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(request).unwrap()
+    }
+
+    fn payment(status: PaymentStatus, created_at: i64) -> Payment {
+        serde_json::from_value(serde_json::json!({
+            "payment_id": "ABC12",
+            "vendor_address": VENDOR,
+            "vendor_name": "Corner Cafe",
+            "price_usd": 10.0,
+            "customer_address": null,
+            "customer_username": null,
+            "status": status,
+            "created_at": created_at,
+            "vendor_valuations": null,
+            "discount_consumption": null,
+            "computed_payment": null,
+            "initial_payment_bundle": null,
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_capture_window_validation() {
+        assert!(request(serde_json::json!({ "manual_capture": true })).validate().is_ok());
+        assert!(request(serde_json::json!({ "manual_capture": true, "capture_window_minutes": MAX_CAPTURE_WINDOW_MINUTES })).validate().is_ok());
+
+        for window in [0, -5, MAX_CAPTURE_WINDOW_MINUTES + 1] {
+            let errors = request(serde_json::json!({ "manual_capture": true, "capture_window_minutes": window })).validate().unwrap_err();
+            assert!(errors.get("capture_window_minutes").is_some(), "{}", window);
+        }
+        let errors = request(serde_json::json!({ "capture_window_minutes": 60 })).validate().unwrap_err();
+        assert_eq!(errors.get("capture_window_minutes").unwrap(), ["Only for payments with manual_capture"]);
+        let errors = request(serde_json::json!({ "manual_capture": true, "escrow": true })).validate().unwrap_err();
+        assert!(errors.get("manual_capture").is_some());
+    }
+
+    #[test]
+    fn test_split_validation() {
+        const CAUSE: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+        const DRIVER: &str = "3333333333333333333333333333333333333333333333333333333333333333";
+        let split = |recipient: &str, value: f64| serde_json::json!({ "recipient_address": recipient, "split_type": "percentage", "value": value });
+        let rejects = |splits: serde_json::Value, escrow: bool| {
+            let errors = request(serde_json::json!({ "splits": splits, "escrow": escrow })).validate().unwrap_err();
+            assert!(errors.get("splits").is_some(), "{}", splits);
+        };
+
+        assert!(request(serde_json::json!({ "splits": [split(CAUSE, 10.0), split(DRIVER, 20.0)] })).validate().is_ok());
+        // The vendor, or a recipient twice
+        rejects(serde_json::json!([split(VENDOR, 10.0)]), false);
+        rejects(serde_json::json!([split(CAUSE, 10.0), split(CAUSE, 5.0)]), false);
+        // Escrow pays one vault, so can't be split
+        rejects(serde_json::json!([split(CAUSE, 10.0)]), true);
+        rejects(serde_json::json!([split("not-a-wallet", 10.0)]), false);
+        rejects(serde_json::json!([split(CAUSE, 0.0)]), false);
+        rejects(serde_json::json!([split(CAUSE, 60.0), split(DRIVER, 50.0)]), false);
+    }
+
+    #[test]
+    fn test_payment_state_from_str() {
+        assert_eq!(PaymentState::from_str("authorized").unwrap(), PaymentState::Authorized);
+        assert_eq!(PaymentState::from_str("voided").unwrap(), PaymentState::Voided);
+        assert_eq!(PaymentState::from_str("processing").unwrap(), PaymentState::Processing);
+        assert!(PaymentState::from_str("Authorized").is_err());
+        assert!(PaymentState::from_str("captured").is_err());
+    }
+
+    #[test]
+    fn test_payment_state() {
+        let now = 10 * PAYMENT_CODE_TTL_SECS;
+        let stale = now - PAYMENT_CODE_TTL_SECS - 1;
+        assert_eq!(payment(PaymentStatus::Created, now).state(now), PaymentState::Active);
+        assert_eq!(payment(PaymentStatus::Calculated, stale).state(now), PaymentState::Expired);
+        // Signed payments outlive the payment code
+        assert_eq!(payment(PaymentStatus::Authorized, stale).state(now), PaymentState::Authorized);
+        assert_eq!(payment(PaymentStatus::Submitted, stale).state(now), PaymentState::Processing);
+        assert_eq!(payment(PaymentStatus::Voided, stale).state(now), PaymentState::Voided);
+        assert_eq!(payment(PaymentStatus::Completed, stale).state(now), PaymentState::Completed);
+        assert_eq!(payment(PaymentStatus::Failed, now).state(now), PaymentState::Failed);
+    }
 }
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// How long a request stays open when the requester doesn't say
+pub const DEFAULT_PAYMENT_REQUEST_TTL_HOURS: i64 = 7 * 24;
+/// Longest a request can stay open
+pub const MAX_PAYMENT_REQUEST_TTL_HOURS: i64 = 30 * 24;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum PaymentRequestStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "accepted")]
+    Accepted,  // the payer started paying; a payment code exists
+    #[serde(rename = "paid")]
+    Paid,
+    #[serde(rename = "declined")]
+    Declined,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "expired")]
+    Expired,
+}
+
+impl std::fmt::Display for PaymentRequestStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentRequestStatus::Pending => write!(f, "pending"),
+            PaymentRequestStatus::Accepted => write!(f, "accepted"),
+            PaymentRequestStatus::Paid => write!(f, "paid"),
+            PaymentRequestStatus::Declined => write!(f, "declined"),
+            PaymentRequestStatus::Cancelled => write!(f, "cancelled"),
+            PaymentRequestStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+impl std::str::FromStr for PaymentRequestStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(PaymentRequestStatus::Pending),
+            "accepted" => Ok(PaymentRequestStatus::Accepted),
+            "paid" => Ok(PaymentRequestStatus::Paid),
+            "declined" => Ok(PaymentRequestStatus::Declined),
+            "cancelled" => Ok(PaymentRequestStatus::Cancelled),
+            "expired" => Ok(PaymentRequestStatus::Expired),
+            _ => Err(format!("Invalid payment request status '{}'", s)),
+        }
+    }
+}
+
+/// One user asking another for money. Accepting it creates a regular payment with
+/// the requester as vendor, which the payer then supplements and signs as usual.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentRequest {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub requester_address: String,
+    pub requester_username: String,
+    pub payer_address: String,
+    pub amount_usd: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub status: PaymentRequestStatus,
+    pub created_at: i64,
+    pub expires_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<String>,  // latest payment code made by accepting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub responded_at: Option<i64>,  // when it was accepted, declined or cancelled
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentRequestRequest {
+    pub payer_address: String,
+    pub amount_usd: f64,
+    pub note: Option<String>,
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaymentRequestQuery {
+    pub direction: Option<String>,  // "incoming" (default) or "outgoing"
+    pub status: Option<String>,
+}
+
+/// Returned when accepting; pay it through /payments/{payment_id}/supplement and /sign
+#[derive(Debug, Serialize)]
+pub struct AcceptPaymentRequestResponse {
+    pub request: PaymentRequest,
+    pub payment_id: String,
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// Most active or paused schedules a customer can have
+pub const MAX_PAYMENT_SCHEDULES: u64 = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ScheduleFrequency {
+    #[serde(rename = "once")]
+    Once,
+    #[serde(rename = "weekly")]
+    Weekly,
+    #[serde(rename = "biweekly")]
+    Biweekly,
+    #[serde(rename = "monthly")]
+    Monthly,  // same day of the month, or the month's last day when it's shorter
+}
+
+impl std::fmt::Display for ScheduleFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleFrequency::Once => write!(f, "once"),
+            ScheduleFrequency::Weekly => write!(f, "weekly"),
+            ScheduleFrequency::Biweekly => write!(f, "biweekly"),
+            ScheduleFrequency::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ScheduleStatus {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "paused")]
+    Paused,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "completed")]
+    Completed,  // a one-off ran, or the last run before `ends_at` did
+}
+
+impl std::fmt::Display for ScheduleStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleStatus::Active => write!(f, "active"),
+            ScheduleStatus::Paused => write!(f, "paused"),
+            ScheduleStatus::Cancelled => write!(f, "cancelled"),
+            ScheduleStatus::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+impl std::str::FromStr for ScheduleStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(ScheduleStatus::Active),
+            "paused" => Ok(ScheduleStatus::Paused),
+            "cancelled" => Ok(ScheduleStatus::Cancelled),
+            "completed" => Ok(ScheduleStatus::Completed),
+            _ => Err(format!("Invalid schedule status '{}', expected active, paused, cancelled or completed", s)),
+        }
+    }
+}
+
+/// A customer's standing order to pay a vendor, e.g. a weekly CSA box. When a run is due a
+/// payment code is created with the customer already assigned, and the customer is asked
+/// to supplement and sign it as usual.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentSchedule {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub customer_address: String,
+    pub vendor_address: String,
+    pub vendor_name: String,
+    pub amount_usd: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub frequency: ScheduleFrequency,
+    pub status: ScheduleStatus,
+    pub anchor_at: i64,  // first run; later runs are counted from here so months don't drift
+    pub occurrence: u32,  // runs since the anchor, including skipped ones
+    pub next_run_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<i64>,  // no runs after this
+    #[serde(default)]
+    pub payments_created: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_payment_id: Option<String>,  // code for the latest run, replaced if it expires unpaid
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentScheduleRequest {
+    pub vendor_address: String,
+    pub amount_usd: f64,
+    pub frequency: ScheduleFrequency,
+    pub start_at: Option<i64>,  // defaults to now
+    pub ends_at: Option<i64>,
+    pub note: Option<String>,
+}
+
+/// Change a schedule; fields left out are kept. `start_at` restarts the schedule from that time.
+#[derive(Debug, Deserialize)]
+pub struct UpdatePaymentScheduleRequest {
+    pub amount_usd: Option<f64>,
+    pub start_at: Option<i64>,
+    pub ends_at: Option<i64>,
+    pub note: Option<String>,
+    pub paused: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaymentScheduleQuery {
+    pub direction: Option<String>,  // "outgoing" (default, schedules you pay) or "incoming"
+    pub status: Option<String>,
+}
+
+/// Returned when paying a run; pay it through /payments/{payment_id}/supplement and /sign
+#[derive(Debug, Serialize)]
+pub struct PaySchedulePaymentResponse {
+    pub schedule: PaymentSchedule,
+    pub payment_id: String,
+}
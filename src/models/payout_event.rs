@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutEventKind {
+    PayoutPaid,        // payout.paid: money arrived in the cause's bank account
+    PayoutFailed,      // payout.failed: the bank rejected it and Stripe returned the money
+    BalanceAvailable,  // balance.available: donations cleared and can be paid out
+}
+
+impl std::fmt::Display for PayoutEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayoutEventKind::PayoutPaid => write!(f, "payout_paid"),
+            PayoutEventKind::PayoutFailed => write!(f, "payout_failed"),
+            PayoutEventKind::BalanceAvailable => write!(f, "balance_available"),
+        }
+    }
+}
+
+/// A payout or balance change on a cause's connected account, as reported by the Connect
+/// webhook. Kept so cause owners can see where their money is without the Stripe dashboard.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoutEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub event_id: String,  // the Stripe event, unique
+    pub kind: PayoutEventKind,
+    pub stripe_account_id: String,
+    pub cause_ids: Vec<String>,  // causes paid out to this account; usually one
+    pub payout_id: Option<String>,  // None for balance events
+    pub amount_cents: i64,  // the payout, or the available balance
+    pub currency: String,
+    pub arrival_date: Option<i64>,
+    pub failure_code: Option<String>,
+    pub failure_message: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PayoutEventQuery {
+    pub limit: Option<i64>,  // default 20, at most 100
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{Document, oid::ObjectId};
+
+/// Most preference templates a single vendor can save
+pub const MAX_PREFERENCE_TEMPLATES: u64 = 20;
+
+/// Preference changes returned by the history endpoint
+pub const PREFERENCE_HISTORY_LIMIT: i64 = 100;
+
+/// A named set of token preferences a vendor saved to apply in one go,
+/// e.g. "support local causes 5%"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreferenceTemplate {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub owner_address: String,
+    pub name: String,
+    pub preferences: BTreeMap<String, f64>,  // token symbol -> discount budget (negative for a premium), in USD
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum PreferenceChangeSource {
+    #[serde(rename = "single")]
+    Single,  // one token through /wallet/{address}/valuations
+    #[serde(rename = "bulk")]
+    Bulk,
+    #[serde(rename = "template")]
+    Template,
+}
+
+/// A change a user made to their token preferences, with the full map before and after
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreferenceChange {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub wallet_address: String,
+    pub source: PreferenceChangeSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_name: Option<String>,
+    pub changed_by: String,  // the wallet or admin that made the change
+    pub before: Document,
+    pub after: Document,
+    pub created_at: i64,
+}
+
+/// PUT body: the complete set of preferences, replacing whatever was there
+#[derive(Debug, Deserialize)]
+pub struct UpdatePreferencesRequest {
+    pub preferences: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SavePreferenceTemplateRequest {
+    pub preferences: BTreeMap<String, f64>,
+}
+
+/// PUT body for a payer's spending weights, replacing the old ones. Tokens weighted above 1
+/// are spent first, below 1 held back, and 0 only spent when nothing else covers the price.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendingWeights {
+    pub weights: HashMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum PreferenceLedgerKind {
+    #[serde(rename = "grant")]
+    Grant,  // the user set or changed a budget
+    #[serde(rename = "consumption")]
+    Consumption,  // a payment drew on it when signed
+    #[serde(rename = "release")]
+    Release,  // a payment that drew on it failed, so it was given back
+}
+
+impl std::str::FromStr for PreferenceLedgerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grant" => Ok(PreferenceLedgerKind::Grant),
+            "consumption" => Ok(PreferenceLedgerKind::Consumption),
+            "release" => Ok(PreferenceLedgerKind::Release),
+            _ => Err(format!("Invalid ledger entry kind '{}', expected grant, consumption or release", s)),
+        }
+    }
+}
+
+/// One movement of a single token's discount budget, in USD
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreferenceLedgerEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub wallet_address: String,
+    pub kind: PreferenceLedgerKind,
+    pub symbol: String,
+    pub amount: f64,  // after - before
+    pub before: f64,
+    pub after: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<String>,  // the payment that consumed or released it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PreferenceChangeSource>,  // how a grant was made
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreferenceLedgerQuery {
+    pub symbol: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,  // next_cursor from the previous page
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreferenceLedgerPage {
+    pub entries: Vec<PreferenceLedgerEntry>,
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum PromoDiscountType {
+    #[serde(rename = "percentage")]
+    Percentage,  // value is percent off, 0-100
+    #[serde(rename = "fixed_usd")]
+    FixedUsd,    // value is USD off
+}
+
+impl std::fmt::Display for PromoDiscountType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PromoDiscountType::Percentage => write!(f, "percentage"),
+            PromoDiscountType::FixedUsd => write!(f, "fixed_usd"),
+        }
+    }
+}
+
+/// A code a vendor hands out for money off a payment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromoCode {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub vendor_address: String,
+    pub code: String,  // stored uppercase; matched case-insensitively
+    pub discount_type: PromoDiscountType,
+    pub value: f64,
+    pub max_uses: Option<i64>,  // None for unlimited
+    pub uses: i64,  // signed payments that used the code, less the ones that failed
+    pub expires_at: Option<i64>,
+    pub active: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl PromoCode {
+    /// Why the code can't be used right now, if it can't
+    pub fn check_usable(&self, now: i64) -> Result<(), String> {
+        if !self.active {
+            return Err(format!("Promo code {} is no longer active", self.code));
+        }
+        if self.expires_at.map_or(false, |expires_at| expires_at <= now) {
+            return Err(format!("Promo code {} has expired", self.code));
+        }
+        if self.max_uses.map_or(false, |max_uses| self.uses >= max_uses) {
+            return Err(format!("Promo code {} has been used up", self.code));
+        }
+        Ok(())
+    }
+
+    /// USD off a payment of `price_usd`, never more than the price
+    pub fn discount_for(&self, price_usd: f64) -> f64 {
+        let discount = match self.discount_type {
+            PromoDiscountType::Percentage => price_usd * self.value / 100.0,
+            PromoDiscountType::FixedUsd => self.value,
+        };
+        discount.clamp(0.0, price_usd.max(0.0))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePromoCodeRequest {
+    pub code: String,
+    pub discount_type: PromoDiscountType,
+    pub value: f64,
+    pub max_uses: Option<i64>,
+    pub expires_at: Option<i64>,
+}
+
+/// Omitted fields are unchanged
+#[derive(Debug, Deserialize)]
+pub struct UpdatePromoCodeRequest {
+    pub active: Option<bool>,
+    pub max_uses: Option<i64>,
+    pub expires_at: Option<i64>,
+}
+
+/// A promo code applied to a payment at supplement time. The code's use is counted
+/// when the payment is signed, and given back if the payment fails.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppliedPromo {
+    pub code: String,
+    pub discount_usd: f64,
+    #[serde(default)]
+    pub consumed: bool,
+}
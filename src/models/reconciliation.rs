@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId};
+use chrono::{DateTime, Utc};
+
+/// A token balance where the executor vault disagrees with our deposit/payment records
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReconciliationIssue {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub run_id: String,
+    pub wallet_address: String,
+    pub token_key: String,
+    pub symbol: Option<String>,
+    pub expected_balance: i64,      // base units, from deposits and payments
+    pub actual_balance: i64,        // base units, from the executor vault
+    pub difference: i64,            // actual - expected
+    pub resolved: bool,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReconciliationRun {
+    pub run_id: String,
+    pub wallets_checked: usize,
+    pub wallets_failed: usize,     // vault could not be read
+    pub issues_found: usize,
+    pub holdings_corrected: usize, // projected balances reset to what the vault holds
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReconciliationIssueQuery {
+    pub wallet_address: Option<String>,
+    pub run_id: Option<String>,
+    pub resolved: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunReconciliationRequest {
+    pub sample_size: Option<usize>,  // None or 0 checks every wallet
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub enum StripeChargeStatus {
+    #[serde(rename = "credited")]
+    Credited,
+    #[serde(rename = "missing_credit")]
+    MissingCredit,
+    #[serde(rename = "no_wallet")]
+    NoWallet,
+}
+
+/// A paid Stripe checkout session and the deposit that credited it, if any
+#[derive(Debug, Serialize, Clone)]
+pub struct StripeChargeCheck {
+    pub session_id: String,
+    pub created: i64,
+    pub amount_total_cents: i64,
+    pub wallet_address: Option<String>,
+    pub token_symbol: Option<String>,
+    pub status: StripeChargeStatus,
+    pub deposit_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StripeReconciliationReport {
+    pub from: String,
+    pub to: String,
+    pub sessions_checked: usize,
+    pub credited: usize,
+    pub missing_credit: usize,
+    pub no_wallet: usize,
+    pub charges: Vec<StripeChargeCheck>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StripeReconciliationQuery {
+    pub from: Option<String>,   // YYYY-MM-DD, defaults to today
+    pub to: Option<String>,     // YYYY-MM-DD inclusive, defaults to `from`
+}
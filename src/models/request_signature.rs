@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId};
+use chrono::{DateTime, Utc};
+
+/// A wallet request signature that was accepted, so the same signed request can't be
+/// replayed. Kept until its timestamp would be rejected anyway.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsedSignature {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub signature: String,  // lowercase hex, unique
+    pub wallet_address: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub used_at: DateTime<Utc>,  // TTL-indexed
+}
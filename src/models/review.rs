@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// Longest comment a reviewer can leave
+pub const MAX_REVIEW_COMMENT_CHARS: usize = 1000;
+
+/// A customer's rating of a vendor, left for one completed payment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Review {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub payment_id: String,
+    pub vendor_address: String,
+    pub customer_address: String,
+    pub customer_username: Option<String>,  // cleared when the customer is anonymized
+    pub rating: i32,  // 1 to 5
+    pub comment: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReviewRequest {
+    pub rating: i32,
+    pub comment: Option<String>,
+}
+
+/// Average of a vendor's reviews, kept on the vendor profile
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VendorRating {
+    pub average: f64,
+    pub count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,  // next_cursor from the previous page
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewPage {
+    pub rating: Option<VendorRating>,
+    pub reviews: Vec<Review>,
+    pub next_cursor: Option<String>,
+}
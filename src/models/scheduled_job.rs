@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Held by the replica that runs scheduled jobs, which renews it while it's up
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulerLease {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub holder: String,  // instance ID of the leader
+    pub expires_at: i64,
+    #[serde(default)]
+    pub epoch: i64,  // fencing token, bumped each time the lease is taken rather than renewed
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobRunStatus {
+    Succeeded,
+    Failed,
+}
+
+/// A scheduled job's schedule and runs, across whichever replicas have led
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    #[serde(rename = "_id")]
+    pub name: String,
+    pub schedule: String,  // "every 300s", or the cron expression from JOB_SCHEDULES
+    pub running: bool,
+    pub runs: i64,
+    pub failures: i64,
+    pub last_started_at: Option<i64>,
+    pub last_finished_at: Option<i64>,
+    pub last_duration_ms: Option<i64>,
+    pub last_status: Option<JobRunStatus>,
+    pub last_error: Option<String>,
+    pub last_run_by: Option<String>,  // instance ID
+    pub next_run_at: Option<i64>,
+    #[serde(default)]
+    pub fence: Option<i64>,  // lease epoch of the last run; runs under an older one are refused
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulerStatus {
+    pub leader: Option<String>,  // None while no replica holds an unexpired lease
+    pub lease_expires_at: Option<i64>,
+    pub jobs: Vec<ScheduledJob>,
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// Per-token totals for one vendor-day
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenSettlement {
+    pub token_key: String,
+    pub symbol: String,
+    pub gross_receipts: f64,           // token units received after discounts
+    pub discounts_granted: f64,        // discount/premium consumed from vendor preferences
+    pub effective_usd_value: f64,      // value at the effective valuation at time of sale
+    pub current_market_valuation: f64,
+    pub current_market_value: f64,     // gross_receipts at today's market valuation
+    pub payment_count: u64,
+}
+
+/// End-of-day settlement report for a vendor. Reports for past days are cached from
+/// `generated_at`, but their market values are taken again whenever they're served, at
+/// `market_valued_at`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailySettlementReport {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub vendor_address: String,
+    pub date: String,                  // YYYY-MM-DD (UTC)
+    pub period_start: i64,
+    pub period_end: i64,
+    pub payment_count: u64,
+    pub total_price_usd: f64,
+    pub tokens: Vec<TokenSettlement>,
+    pub generated_at: i64,
+    #[serde(default)]
+    pub market_valued_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DailyReportQuery {
+    pub date: Option<String>,
+}
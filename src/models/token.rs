@@ -15,6 +15,8 @@ pub struct Token {
     pub market_valuation: f64, 
     pub total_allocated: u64,
     pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,  // unix seconds of the last change, unset until the token first changes
     pub stripe_product_id: String,
     pub token_image_url: Option<String>,
 }
@@ -23,6 +25,36 @@ fn default_market_valuation() -> f64 {
     1.0
 }
 
+/// Largest holders returned by GET /tokens/{symbol}/holders unless `top` says otherwise, and the most it may ask for
+pub const DEFAULT_TOP_HOLDERS: usize = 10;
+pub const MAX_TOP_HOLDERS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct TokenHoldersQuery {
+    pub top: Option<usize>,  // default DEFAULT_TOP_HOLDERS
+}
+
+/// How a token is spread across user wallets, for cause pages showing community size.
+/// Holders are not identified. Built from the holdings projection, so it can trail the
+/// executor until reconciliation catches up.
+#[derive(Debug, Serialize)]
+pub struct TokenHolders {
+    pub token_symbol: String,
+    pub token_id: String,
+    pub holder_count: usize,
+    pub total_held: u64,          // base units across every holder
+    pub top_holders: Vec<TopHolder>,
+    pub top_holders_share: f64,   // fraction of total_held with the top holders
+    pub computed_at: i64,         // latest change to the holdings it was built from
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TopHolder {
+    pub rank: usize,
+    pub balance: u64,
+    pub share: f64,  // fraction of the total held
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenValuation {
     pub token_key: String, 
@@ -59,6 +91,14 @@ pub struct TokenPayment {
     pub token_image_url: Option<String>,
 }
 
+/// A leg of a payment as it is signed: whole on-chain units of the token
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OnChainAmount {
+    pub token_key: String,
+    pub symbol: String,
+    pub amount: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TokenBalance {
     pub token_key: String,     // "address,chainId" from frontend
@@ -82,4 +122,6 @@ pub struct TransactionRecord {
     #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub timestamp: DateTime<Utc>,    // current time with BSON serialization
     pub payment_id: String,          // "SA0V"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient_address: Option<String>,  // who was paid; missing on older records
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// AES-256-GCM output, all fields base64 encoded
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EncryptedBlob {
+    pub ciphertext: String,
+    pub nonce: String,
+    pub tag: String,
+}
+
+/// Issuer keypair for a token, stored with envelope encryption:
+/// the private key is sealed with a per-token data key, and the data key
+/// is sealed with the master key from config.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenIssuerKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub token_id: String,          // "pubkey,shard" - same as Token.token_id
+    pub issuer_pubkey: String,
+    pub encrypted_private_key: EncryptedBlob,
+    pub wrapped_data_key: EncryptedBlob,
+    pub created_at: i64,
+}
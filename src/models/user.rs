@@ -1,10 +1,38 @@
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use mongodb::bson::{Document, oid::ObjectId};
+use crate::models::{Payment, DepositRecord, PartneredVendor, CauseDraft, Contact, Account, NotificationPreferences, Review, LoyaltyAccount, Dispute, PaymentSchedule, Invoice, PreferenceTemplate, PreferenceChange, PaymentRequest, ActivityEvent};
+use crate::models::cause::{Cause, TeamMembership, BusinessType, StripeRequirements, default_country};
+use crate::utils::profile::{validate_username, validate_email};
+use crate::utils::validation::{self, FieldErrors, Validate};
 
 fn default_user_type() -> String {
     "customer".to_string()
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Role {
+    #[serde(rename = "admin")]
+    Admin,
+    #[serde(rename = "cause_owner")]
+    CauseOwner,
+    #[serde(rename = "vendor")]
+    Vendor,
+    #[serde(rename = "user")]
+    User,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Admin => write!(f, "admin"),
+            Role::CauseOwner => write!(f, "cause_owner"),
+            Role::Vendor => write!(f, "vendor"),
+            Role::User => write!(f, "user"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Preferences(pub Document);
 
@@ -19,6 +47,196 @@ pub struct User {
     pub is_verified: bool,
     #[serde(default = "default_user_type")]  // Will default to "customer" for old records
     pub user_type: String, 
+    #[serde(default)]  // Will default to no stored roles for old records
+    pub roles: Vec<Role>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,  // set when the account was anonymized on request
+    #[serde(default)]  // Donations are attributed by username unless the user opts out
+    pub donate_anonymously: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripe_customer_id: Option<String>,  // created on first donation or top-up, holds saved cards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_key: Option<String>,  // lowercased username, unique; missing on older records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_changed_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,  // only shown to the user themselves and admins
+    #[serde(default)]
+    pub notification_preferences: NotificationPreferences,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub spending_weights: HashMap<String, f64>,  // token symbol -> how readily the user spends it; empty pays proportionally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripe_account: Option<VendorStripeAccount>,  // vendors only, once they start Stripe onboarding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_code_namespace: Option<PaymentCodeNamespace>,  // vendors only; new payments get short codes in it
+}
+
+/// Length of a vendor's short codes when they don't choose one, and the bounds they can choose in
+pub const DEFAULT_SHORT_CODE_LENGTH: usize = 6;
+pub const MIN_SHORT_CODE_LENGTH: usize = 4;
+pub const MAX_SHORT_CODE_LENGTH: usize = 12;
+
+/// A vendor's own payment codes: their payments are entered as `{slug}-{short_code}`, with
+/// short codes unique among the vendor's payments, rather than by a global five-character code
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentCodeNamespace {
+    pub slug: String,  // unique across vendors
+    pub code_length: usize,
+    pub updated_at: i64,
+}
+
+/// Claim a slug for the vendor's payment codes, or change it or the code length. Payments
+/// created before keep the codes they were given.
+#[derive(Debug, Deserialize)]
+pub struct PaymentCodeNamespaceRequest {
+    pub slug: String,
+    pub code_length: Option<usize>,
+}
+
+impl Validate for PaymentCodeNamespaceRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("slug", validation::slug(&self.slug));
+        if let Some(length) = self.code_length {
+            if !(MIN_SHORT_CODE_LENGTH..=MAX_SHORT_CODE_LENGTH).contains(&length) {
+                errors.add("code_length", format!("Must be between {} and {}", MIN_SHORT_CODE_LENGTH, MAX_SHORT_CODE_LENGTH));
+            }
+        }
+        errors.into_result()
+    }
+}
+
+/// A vendor's Stripe Express account, for taking card payments and cashing out. Kept current
+/// by the Connect webhook's account.updated, like causes' accounts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VendorStripeAccount {
+    pub account_id: String,
+    pub country: String,
+    pub business_type: BusinessType,
+    pub onboarding_completed: bool,
+    pub charges_enabled: bool,
+    pub payouts_enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requirements: Option<StripeRequirements>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VendorOnboardingRequest {
+    pub email: Option<String>,  // the Stripe account's; defaults to the profile email
+    #[serde(default = "default_country")]
+    pub country: String,
+    #[serde(default)]
+    pub business_type: BusinessType,
+}
+
+impl Validate for VendorOnboardingRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if let Some(email) = &self.email {
+            errors.check("email", validate_email(email));
+        }
+        if self.country.len() != 2 || !self.country.chars().all(|c| c.is_ascii_alphabetic()) {
+            errors.add("country", "Must be a two-letter country code, e.g. US");
+        }
+        errors.into_result()
+    }
+}
+
+/// Minimum time between username changes
+pub const USERNAME_CHANGE_COOLDOWN_SECS: i64 = 30 * 24 * 60 * 60;
+
+impl User {
+    /// Stored roles plus the ones implied for every user and by user_type
+    pub fn effective_roles(&self) -> Vec<Role> {
+        let mut roles = self.roles.clone();
+        if !roles.contains(&Role::User) {
+            roles.push(Role::User);
+        }
+        if self.user_type == "vendor" && !roles.contains(&Role::Vendor) {
+            roles.push(Role::Vendor);
+        }
+        roles
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrivacyRequest {
+    pub donate_anonymously: bool,
+}
+
+/// PATCH body: omitted fields are left alone, an empty string clears an optional field
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsernameAvailability {
+    pub username: String,
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,  // why the name can't be used, when it is invalid
+}
+
+/// Everything stored about a wallet, returned by the data export endpoint
+#[derive(Debug, Serialize)]
+pub struct UserDataExport {
+    pub exported_at: i64,
+    pub wallet_address: String,
+    pub user: Option<User>,
+    pub preferences: Option<Document>,
+    pub payments: Vec<Payment>,
+    pub deposits: Vec<DepositRecord>,
+    pub vendor: Option<PartneredVendor>,
+    pub causes: Vec<Cause>,
+    pub cause_drafts: Vec<CauseDraft>,
+    pub cause_teams: Vec<TeamMembership>,
+    pub contacts: Vec<Contact>,
+    pub account: Option<Account>,
+    pub reviews: Vec<Review>,
+    pub loyalty_accounts: Vec<LoyaltyAccount>,
+    pub disputes: Vec<Dispute>,
+    pub payment_schedules: Vec<PaymentSchedule>,
+    pub invoices: Vec<Invoice>,
+    pub preference_templates: Vec<PreferenceTemplate>,
+    pub preference_changes: Vec<PreferenceChange>,
+    pub payment_requests: Vec<PaymentRequest>,
+    pub activities: Vec<ActivityEvent>,
+}
+
+/// Counts of records touched when anonymizing an account
+#[derive(Debug, Serialize)]
+pub struct AnonymizationSummary {
+    pub wallet_address: String,
+    pub user_anonymized: bool,
+    pub payments_anonymized: u64,
+    pub vendor_anonymized: bool,
+    pub causes_anonymized: u64,
+    pub drafts_anonymized: u64,
+    pub team_memberships_removed: u64,
+    pub contacts_deleted: u64,
+    pub account_unlinked: bool,
+    pub reviews_anonymized: u64,
+    pub loyalty_accounts_deleted: u64,
+    pub disputes_anonymized: u64,
+    pub payment_schedules_cancelled: u64,
+    pub invoices_cancelled: u64,
+    pub preference_templates_deleted: u64,
+    pub payment_requests_anonymized: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRolesRequest {
+    pub roles: Vec<Role>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,4 +250,16 @@ pub struct CreateUserRequest {
     pub vendor_description: Option<String>,
     pub vendor_google_maps_link: Option<String>,
     pub vendor_website_link: Option<String>,
+}
+
+impl Validate for CreateUserRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("wallet_address", validation::wallet_address(&self.wallet_address));
+        errors.check("username", validate_username(&self.username));
+        if self.user_type != "customer" && self.user_type != "vendor" {
+            errors.add("user_type", "User type must be either 'customer' or 'vendor'");
+        }
+        errors.into_result()
+    }
 }
\ No newline at end of file
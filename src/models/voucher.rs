@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+/// How long a voucher can be redeemed when the issuer doesn't say
+pub const DEFAULT_VOUCHER_TTL_DAYS: i64 = 365;
+/// Longest a voucher can stay redeemable
+pub const MAX_VOUCHER_TTL_DAYS: i64 = 3 * 365;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum VoucherStatus {
+    #[serde(rename = "pending_funding")]
+    PendingFunding,  // waiting for the issuer's signed transfer into escrow
+    #[serde(rename = "funding")]
+    Funding,         // issuer's transfer into escrow in flight
+    #[serde(rename = "active")]
+    Active,          // tokens held in the central vault, code redeemable
+    #[serde(rename = "redeeming")]
+    Redeeming,       // transfer to the redeemer in flight
+    #[serde(rename = "redeemed")]
+    Redeemed,
+    #[serde(rename = "refunding")]
+    Refunding,       // expired or cancelled, transfer back to the issuer in flight or retrying
+    #[serde(rename = "refunded")]
+    Refunded,
+    #[serde(rename = "expired")]
+    Expired,         // expired unredeemed; platform-funded, so nothing to send back
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "failed")]
+    Failed,          // a transfer may or may not have landed; never retried automatically
+}
+
+impl std::fmt::Display for VoucherStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoucherStatus::PendingFunding => write!(f, "pending_funding"),
+            VoucherStatus::Funding => write!(f, "funding"),
+            VoucherStatus::Active => write!(f, "active"),
+            VoucherStatus::Redeeming => write!(f, "redeeming"),
+            VoucherStatus::Redeemed => write!(f, "redeemed"),
+            VoucherStatus::Refunding => write!(f, "refunding"),
+            VoucherStatus::Refunded => write!(f, "refunded"),
+            VoucherStatus::Expired => write!(f, "expired"),
+            VoucherStatus::Cancelled => write!(f, "cancelled"),
+            VoucherStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl std::str::FromStr for VoucherStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending_funding" => Ok(VoucherStatus::PendingFunding),
+            "funding" => Ok(VoucherStatus::Funding),
+            "active" => Ok(VoucherStatus::Active),
+            "redeeming" => Ok(VoucherStatus::Redeeming),
+            "redeemed" => Ok(VoucherStatus::Redeemed),
+            "refunding" => Ok(VoucherStatus::Refunding),
+            "refunded" => Ok(VoucherStatus::Refunded),
+            "expired" => Ok(VoucherStatus::Expired),
+            "cancelled" => Ok(VoucherStatus::Cancelled),
+            "failed" => Ok(VoucherStatus::Failed),
+            _ => Err(format!("Invalid voucher status '{}'", s)),
+        }
+    }
+}
+
+/// Where a voucher's escrowed tokens came from, and so where they go back to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum VoucherFunding {
+    #[serde(rename = "central_vault")]
+    CentralVault,  // issued by an admin from the platform's holdings
+    #[serde(rename = "issuer")]
+    Issuer,        // the issuing vendor transferred the tokens in
+}
+
+/// A prepaid gift card: tokens held in escrow in the central vault until someone
+/// redeems the code, or sent back to the issuer once it expires
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Voucher {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub code: String,
+    pub issuer_address: String,
+    pub funding: VoucherFunding,
+    pub token_symbol: String,
+    pub amount: f64,  // token units
+    pub note: Option<String>,
+    pub status: VoucherStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub expires_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_transaction: Option<String>,  // unsigned transfer into escrow for the issuer to sign
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_tx_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redeemed_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redeemed_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redemption_tx_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund_tx_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+}
+
+impl Voucher {
+    /// Amount in the executor's base units (hundredths of a token), as payments use
+    pub fn units(&self) -> u64 {
+        (self.amount * 100.0).round() as u64
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateVoucherRequest {
+    pub token_symbol: String,
+    pub amount: f64,
+    pub expires_in_days: Option<i64>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FundVoucherRequest {
+    pub signed_transaction: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VoucherQuery {
+    pub status: Option<String>,
+}
+
+/// What someone holding a code gets to see before redeeming it
+#[derive(Debug, Serialize)]
+pub struct VoucherPreview {
+    pub code: String,
+    pub token_symbol: String,
+    pub amount: f64,
+    pub note: Option<String>,
+    pub status: VoucherStatus,
+    pub expires_at: i64,
+}
+
+impl From<&Voucher> for VoucherPreview {
+    fn from(voucher: &Voucher) -> Self {
+        Self {
+            code: voucher.code.clone(),
+            token_symbol: voucher.token_symbol.clone(),
+            amount: voucher.amount,
+            note: voucher.note.clone(),
+            status: voucher.status.clone(),
+            expires_at: voucher.expires_at,
+        }
+    }
+}
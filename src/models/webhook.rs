@@ -1,4 +1,5 @@
 use thiserror::Error;
+use serde::{Deserialize, Serialize};
 use stripe;
 
 #[derive(Error, Debug)]
@@ -20,4 +21,65 @@ pub enum WebhookError {
     
     #[error("Token transfer failed: {0}")]
     TokenTransferError(String),
+
+    /// The executor didn't confirm or refuse the transfer, so the tokens may have moved
+    #[error("Token transfer outcome unknown: {0}")]
+    TransferOutcomeUnknown(String),
+
+    /// Another delivery of the same payment is crediting it right now; retry later
+    #[error("Credit already in progress: {0}")]
+    CreditInProgress(String),
+
+    /// The wallet was credited but the platform's share of the tokens wasn't sent
+    #[error("Platform fee transfer failed after crediting the wallet: {0}")]
+    FeeTransferError(String),
+    
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+impl WebhookError {
+    /// Whether the failure certainly left the wallet uncredited, so the payment can be
+    /// credited again. Unknown transfers and database failures may have come after tokens moved.
+    pub fn credited_nothing(&self) -> bool {
+        matches!(
+            self,
+            WebhookError::StripeError(_)
+                | WebhookError::InvalidPayload(_)
+                | WebhookError::MissingSignature
+                | WebhookError::InvalidAmount(_)
+                | WebhookError::InvalidPublicKey(_)
+                | WebhookError::TokenTransferError(_)
+                | WebhookError::CreditInProgress(_)
+        )
+    }
+}
+
+/// The two Stripe webhook endpoints, each with its own signing secrets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEndpoint {
+    #[serde(rename = "connect")]
+    Connect,
+    #[serde(rename = "purchases")]
+    Purchases,
+}
+
+impl std::fmt::Display for WebhookEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookEndpoint::Connect => write!(f, "connect"),
+            WebhookEndpoint::Purchases => write!(f, "purchases"),
+        }
+    }
+}
+
+/// Which configured secrets are still verifying events, so an old one can be
+/// removed once Stripe has stopped signing with it. Counts are since process start.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookSecretStatus {
+    pub endpoint: WebhookEndpoint,
+    pub index: usize,          // 0 is the current secret
+    pub hint: String,          // last characters only, never the secret itself
+    pub matches: u64,
+    pub last_matched_at: Option<i64>,
 }
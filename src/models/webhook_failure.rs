@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId};
+use chrono::{DateTime, Utc};
+use crate::models::WebhookEndpoint;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum WebhookFailureStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "replayed")]
+    Replayed,
+}
+
+impl std::fmt::Display for WebhookFailureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookFailureStatus::Pending => write!(f, "pending"),
+            WebhookFailureStatus::Replayed => write!(f, "replayed"),
+        }
+    }
+}
+
+/// A verified Stripe event whose processing failed, kept so it can be replayed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookFailure {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub event_id: String,
+    pub event_type: String,
+    #[serde(default = "default_endpoint")]  // Failures recorded before connect events were kept are all purchases
+    pub endpoint: WebhookEndpoint,
+    pub payload: String,        // raw event body, signature already checked
+    pub error: String,          // most recent failure
+    pub attempts: i32,
+    pub status: WebhookFailureStatus,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub first_failed_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_failed_at: DateTime<Utc>,
+}
+
+fn default_endpoint() -> WebhookEndpoint {
+    WebhookEndpoint::Purchases
+}
+
+/// A Stripe event that was applied successfully, so redeliveries can be skipped
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProcessedStripeEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub event_id: String,
+    pub event_type: String,
+    pub endpoint: WebhookEndpoint,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub processed_at: DateTime<Utc>,  // TTL-indexed; Stripe stops retrying long before it expires
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookFailureQuery {
+    pub status: Option<String>,   // pending | replayed
+    pub limit: Option<i64>,
+}
+
+/// Times a queued event is attempted before it's left failed for replay
+pub const MAX_WEBHOOK_JOB_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum WebhookJobStatus {
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "processing")]
+    Processing,
+    #[serde(rename = "failed")]
+    Failed,  // out of attempts; also recorded as a webhook failure
+    #[serde(rename = "processed")]
+    Processed,  // applied; kept until the TTL on `processed_at` removes it
+}
+
+impl std::fmt::Display for WebhookJobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookJobStatus::Queued => write!(f, "queued"),
+            WebhookJobStatus::Processing => write!(f, "processing"),
+            WebhookJobStatus::Failed => write!(f, "failed"),
+            WebhookJobStatus::Processed => write!(f, "processed"),
+        }
+    }
+}
+
+/// A verified Stripe event accepted for processing by the webhook workers. Marked processed
+/// once it's applied; events with the same ordering key are applied in arrival order.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookJob {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub event_id: String,
+    pub event_type: String,
+    pub endpoint: WebhookEndpoint,
+    pub payload: String,        // raw event body, signature already checked
+    pub ordering_key: String,   // usually the wallet credited
+    pub status: WebhookJobStatus,
+    pub attempts: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_owner: Option<String>,      // worker processing it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_expires_at: Option<i64>,    // after which another worker may take it over
+    pub next_attempt_at: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookQueueQuery {
+    pub status: Option<String>,   // queued | processing | failed | processed
+    pub limit: Option<i64>,
+}
+
+/// How far behind the webhook workers are
+#[derive(Debug, Serialize)]
+pub struct WebhookQueueStatus {
+    pub concurrency: usize,
+    pub queued: u64,
+    pub processing: u64,
+    pub failed: u64,
+    pub oldest_queued_at: Option<i64>,
+    pub jobs: Vec<WebhookJob>,
+}
@@ -0,0 +1,66 @@
+//! Hashes the body of every wallet-signed request before it reaches the handlers, so
+//! `AuthenticatedUser` can check the signature covers it. The body is buffered and handed
+//! back unchanged for the handler's own extractors.
+
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::Bytes;
+use actix_web::{Error, HttpMessage};
+use futures_util::future::LocalBoxFuture;
+use crate::auth::WALLET_SIGNATURE_HEADER;
+use crate::models::error::payload_error;
+use crate::utils::wallet_signature::body_digest;
+
+/// The signed request's body hash, from `utils::wallet_signature::body_digest`
+#[derive(Debug, Clone)]
+pub struct RequestBodyDigest(pub String);
+
+pub struct RequestDigest;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestDigest
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestDigestMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestDigestMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequestDigestMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestDigestMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            // Unsigned requests are streamed through untouched
+            if req.headers().contains_key(WALLET_SIGNATURE_HEADER) {
+                let body = req.extract::<Bytes>().await.map_err(payload_error)?;
+                req.extensions_mut().insert(RequestBodyDigest(body_digest(&body)));
+                req.set_payload(Payload::from(body));
+            }
+            service.call(req).await
+        })
+    }
+}
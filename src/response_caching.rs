@@ -0,0 +1,89 @@
+//! Conditional GETs for listings clients poll. Handlers say which version they serve with an
+//! `ETag`; this adds `Cache-Control` and answers a matching `If-None-Match` with a bodiless
+//! 304, so an unchanged listing isn't sent again.
+
+use std::future::{ready, Ready};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderValue, CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{Error, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use crate::utils::etag::etag_matches;
+
+/// How long a client may reuse a listing before revalidating it
+pub const LISTING_CACHE_CONTROL: &str = "public, max-age=60";
+
+/// Whether the client already holds the version tagged `etag`, so a handler can answer
+/// 304 before loading what it would send
+pub fn is_fresh(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |if_none_match| etag_matches(if_none_match, etag))
+}
+
+pub fn not_modified(etag: &str) -> HttpResponse {
+    HttpResponse::NotModified().insert_header((ETAG, etag)).finish()
+}
+
+pub struct ConditionalGet;
+
+impl<S, B> Transform<S, ServiceRequest> for ConditionalGet
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = ConditionalGetMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ConditionalGetMiddleware { service }))
+    }
+}
+
+pub struct ConditionalGetMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for ConditionalGetMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let is_get = req.method() == Method::GET;
+        let if_none_match = req.headers().get(IF_NONE_MATCH).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let mut response = response.await?;
+            let status = response.status();
+            if !is_get || !(status == StatusCode::OK || status == StatusCode::NOT_MODIFIED) {
+                return Ok(response.map_into_boxed_body());
+            }
+            response.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static(LISTING_CACHE_CONTROL));
+
+            let etag = response.headers().get(ETAG).and_then(|value| value.to_str().ok()).map(str::to_string);
+            match (etag, if_none_match) {
+                (Some(etag), Some(if_none_match)) if status == StatusCode::OK && etag_matches(&if_none_match, &etag) => {
+                    let (request, _) = response.into_parts();
+                    let mut not_modified = not_modified(&etag);
+                    not_modified.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static(LISTING_CACHE_CONTROL));
+                    Ok(ServiceResponse::new(request, not_modified))
+                },
+                _ => Ok(response.map_into_boxed_body()),
+            }
+        })
+    }
+}
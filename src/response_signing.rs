@@ -0,0 +1,61 @@
+//! Optional signing of every HTTP response with the API signing key, so partners can check a
+//! response came from the platform. The scheme is in `utils::response_signature`.
+
+use actix_web::body::{self, BodySize, BoxBody, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::Error;
+use ed25519_dalek::SigningKey;
+use crate::utils::response_signature::{sign_response, RESPONSE_SIGNATURE_HEADER};
+
+pub struct ResponseSigner {
+    signing_key: SigningKey,
+}
+
+impl ResponseSigner {
+    pub fn new(secret: &[u8; 32]) -> Self {
+        Self { signing_key: SigningKey::from_bytes(secret) }
+    }
+
+    /// The base58 public key partners verify signatures with
+    pub fn public_key(&self) -> String {
+        bs58::encode(self.signing_key.verifying_key().to_bytes()).into_string()
+    }
+
+    /// Add `X-Index-Signature` to a response, signing the path it answered with its query
+    /// string. Streamed bodies, which are never complete, are passed through unsigned.
+    pub async fn sign<B>(&self, response: ServiceResponse<B>) -> Result<ServiceResponse<BoxBody>, Error>
+    where
+        B: MessageBody + 'static,
+    {
+        if let BodySize::Stream = response.response().body().size() {
+            return Ok(response.map_into_boxed_body());
+        }
+        let (request, response) = response.into_parts();
+        let (mut response, response_body) = response.into_parts();
+        let bytes = match body::to_bytes(response_body).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                let e: Box<dyn std::error::Error> = e.into();
+                return Err(actix_web::error::ErrorInternalServerError(e.to_string()));
+            }
+        };
+
+        let path_and_query = match request.query_string() {
+            "" => request.path().to_string(),
+            query => format!("{}?{}", request.path(), query),
+        };
+        let header = sign_response(
+            &self.signing_key,
+            chrono::Utc::now().timestamp(),
+            request.method().as_str(),
+            &path_and_query,
+            &bytes,
+        );
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(RESPONSE_SIGNATURE_HEADER.as_bytes()), HeaderValue::from_str(&header)) {
+            response.headers_mut().insert(name, value);
+        }
+        Ok(ServiceResponse::new(request, response.set_body(bytes).map_into_boxed_body()))
+    }
+}
+
@@ -0,0 +1,15 @@
+use actix_web::web;
+use crate::handlers::account_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/accounts")
+            .route("", web::post().to(account_handlers::create_account))
+            .route("/me", web::get().to(account_handlers::get_my_account))
+            .route("/{id}", web::get().to(account_handlers::get_account))
+            .route("/{id}/wallets", web::post().to(account_handlers::link_wallet))
+            .route("/{id}/wallets/{wallet_address}", web::delete().to(account_handlers::unlink_wallet))
+            .route("/{id}/balances", web::get().to(account_handlers::get_account_balances))
+            .route("/{id}/transactions", web::get().to(account_handlers::get_account_transactions))
+    );
+}
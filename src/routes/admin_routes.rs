@@ -0,0 +1,50 @@
+use actix_web::web;
+use crate::handlers::{admin_handlers, escrow_handlers, dispute_handlers};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .route("/audit-logs", web::get().to(admin_handlers::get_audit_logs))
+            .route("/users/{wallet_address}/roles", web::put().to(admin_handlers::update_user_roles))
+            .route("/reconciliation/run", web::post().to(admin_handlers::run_reconciliation))
+            .route("/reconciliation/issues", web::get().to(admin_handlers::get_reconciliation_issues))
+            .route("/reconciliation/issues/{id}/resolve", web::post().to(admin_handlers::resolve_reconciliation_issue))
+            .route("/causes/review-queue", web::get().to(admin_handlers::get_cause_review_queue))
+            .route("/causes/dashboard", web::get().to(admin_handlers::get_cause_dashboard))
+            .route("/causes/requirements", web::get().to(admin_handlers::get_cause_requirements))
+            .route("/causes/bulk", web::post().to(admin_handlers::bulk_cause_action))
+            .route("/causes/featured/order", web::put().to(admin_handlers::reorder_featured_causes))
+            .route("/causes/{id}/approve", web::post().to(admin_handlers::approve_cause))
+            .route("/causes/{id}/reject", web::post().to(admin_handlers::reject_cause))
+            .route("/causes/{id}/suspend", web::post().to(admin_handlers::suspend_cause))
+            .route("/causes/{id}/reinstate", web::post().to(admin_handlers::reinstate_cause))
+            .route("/causes/{id}/archive", web::post().to(admin_handlers::archive_cause))
+            .route("/causes/{id}/feature", web::post().to(admin_handlers::feature_cause))
+            .route("/credits", web::post().to(admin_handlers::create_manual_credit))
+            .route("/credits/failed", web::get().to(admin_handlers::get_failed_credits))
+            .route("/credits/{id}/resolve", web::post().to(admin_handlers::resolve_failed_credit))
+            .route("/payments/{payment_id}/escrow/freeze", web::post().to(escrow_handlers::freeze_escrow))
+            .route("/payments/{payment_id}/escrow/capture", web::post().to(escrow_handlers::admin_capture_escrow))
+            .route("/payments/{payment_id}/escrow/refund", web::post().to(escrow_handlers::admin_refund_escrow))
+            .route("/disputes", web::get().to(dispute_handlers::get_dispute_queue))
+            .route("/disputes/liabilities", web::get().to(dispute_handlers::get_vendor_liabilities))
+            .route("/disputes/{id}/resolve", web::post().to(dispute_handlers::resolve_dispute))
+            .route("/disputes/{id}/retry-refund", web::post().to(dispute_handlers::retry_dispute_refund))
+            .route("/disputes/{id}/refund/resolve", web::post().to(dispute_handlers::resolve_dispute_refund))
+            .route("/disputes/{id}/liability/settle", web::post().to(dispute_handlers::settle_vendor_liability))
+            .route("/matching-pools", web::post().to(admin_handlers::create_matching_pool))
+            .route("/matching-pools/{id}/close", web::post().to(admin_handlers::close_matching_pool))
+            .route("/funding-rounds", web::post().to(admin_handlers::create_funding_round))
+            .route("/funding-rounds/{id}/close", web::post().to(admin_handlers::close_funding_round))
+            .route("/funding-rounds/{id}/distribute", web::post().to(admin_handlers::distribute_funding_round))
+            .route("/webhooks/failures", web::get().to(admin_handlers::get_webhook_failures))
+            .route("/webhooks/queue", web::get().to(admin_handlers::get_webhook_queue))
+            .route("/webhooks/secrets", web::get().to(admin_handlers::get_webhook_secret_status))
+            .route("/webhooks/{id}/replay", web::post().to(admin_handlers::replay_webhook_failure))
+            .route("/stripe-reconciliation", web::get().to(admin_handlers::get_stripe_reconciliation))
+            .route("/stripe-reconciliation/{session_id}/replay", web::post().to(admin_handlers::replay_stripe_session))
+            .route("/feature-flags", web::get().to(admin_handlers::get_feature_flags))
+            .route("/feature-flags/{name}", web::put().to(admin_handlers::set_feature_flag))
+            .route("/jobs", web::get().to(admin_handlers::get_scheduled_jobs))
+    );
+}
@@ -1,26 +1,84 @@
-use actix_web::web;
-use crate::handlers::cause_handlers;
+use actix_web::{web, Route, Scope};
+use crate::handlers::{campaign_handlers, cause_handlers, embed_handlers, fundraiser_handlers};
+use crate::response_caching::ConditionalGet;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/causes")
-            .route("", web::post().to(cause_handlers::create_cause))
-            .route("", web::get().to(cause_handlers::get_all_causes))
-            .route("/featured", web::get().to(cause_handlers::get_featured_causes))
-            .route("/admin/all", web::get().to(cause_handlers::get_all_causes_admin))
-            .route("/by-token/{token_name}", web::get().to(cause_handlers::get_cause_by_token_name))
-            .route("/by-name/{name}", web::get().to(cause_handlers::get_cause_by_name))
-            .route("/by-symbol/{token_symbol}", web::get().to(cause_handlers::get_cause_by_token_symbol))
-            .route("/drafts/find", web::post().to(cause_handlers::find_drafts_by_email))
-            .route("/drafts/{draft_id}/status", web::get().to(cause_handlers::get_draft_status))
-            .route("/donate", web::post().to(cause_handlers::create_donation_session))
-            .route("/validate/name", web::post().to(cause_handlers::validate_cause_name))
-            .route("/validate/token-symbol", web::post().to(cause_handlers::validate_token_symbol))
-            .route("/validate/token-name", web::post().to(cause_handlers::validate_token_name))
-            .route("/{id}", web::get().to(cause_handlers::get_cause))
-            .route("/{id}", web::put().to(cause_handlers::update_cause))
-            .route("/{id}", web::delete().to(cause_handlers::delete_cause))
-            .route("/{id}/onboarding", web::get().to(cause_handlers::get_onboarding_link))
-            .route("/{id}/status", web::get().to(cause_handlers::check_account_status))
-    );
+    cfg.service(causes_scope(web::get().to(cause_handlers::get_all_causes)));
+}
+
+/// Version 2 lists causes as pages of summaries; every other cause route is unchanged
+pub fn configure_v2(cfg: &mut web::ServiceConfig) {
+    cfg.service(causes_scope(web::get().to(cause_handlers::list_causes)));
+}
+
+fn causes_scope(list: Route) -> Scope {
+    web::scope("/causes")
+        .service(
+            web::resource("")
+                .wrap(ConditionalGet)
+                .route(web::post().to(cause_handlers::create_cause))
+                .route(list)
+        )
+        .route("/featured", web::get().to(cause_handlers::get_featured_causes))
+        .route("/admin/all", web::get().to(cause_handlers::get_all_causes_admin))
+        .route("/by-token/{token_name}", web::get().to(cause_handlers::get_cause_by_token_name))
+        .route("/by-name/{name}", web::get().to(cause_handlers::get_cause_by_name))
+        .route("/by-symbol/{token_symbol}", web::get().to(cause_handlers::get_cause_by_token_symbol))
+        .route("/drafts/find", web::post().to(cause_handlers::find_drafts_by_email))
+        .route("/drafts/{draft_id}/status", web::get().to(cause_handlers::get_draft_status))
+        .route("/drafts/{draft_id}/extend", web::post().to(cause_handlers::extend_draft))
+        .route("/drafts/{draft_id}/verify-email", web::post().to(cause_handlers::verify_draft_email))
+        .route("/drafts/{draft_id}/resend-verification", web::post().to(cause_handlers::resend_draft_verification))
+        .route("/donate", web::post().to(cause_handlers::create_donation_session))
+        .route("/validate/name", web::post().to(cause_handlers::validate_cause_name))
+        .route("/validate/token-symbol", web::post().to(cause_handlers::validate_token_symbol))
+        .route("/validate/token-name", web::post().to(cause_handlers::validate_token_name))
+        .route("/team/mine", web::get().to(cause_handlers::get_my_team_causes))
+        .service(
+            web::resource("/{id}")
+                .wrap(ConditionalGet)
+                .route(web::get().to(cause_handlers::get_cause))
+                .route(web::put().to(cause_handlers::update_cause))
+                .route(web::delete().to(cause_handlers::delete_cause))
+        )
+        .route("/{id}/onboarding", web::get().to(cause_handlers::get_onboarding_link))
+        .route("/{id}/status", web::get().to(cause_handlers::check_account_status))
+        .route("/{id}/donations", web::get().to(cause_handlers::get_cause_donations))
+        .route("/{id}/analytics", web::get().to(cause_handlers::get_cause_analytics))
+        .service(
+            web::resource("/{id}/payouts")
+                .route(web::get().to(cause_handlers::get_cause_payouts))
+                .route(web::post().to(cause_handlers::create_cause_payout))
+        )
+        .service(
+            web::resource("/{id}/team")
+                .route(web::get().to(cause_handlers::get_cause_team))
+                .route(web::post().to(cause_handlers::invite_team_member))
+        )
+        .route("/{id}/team/accept", web::post().to(cause_handlers::accept_team_invite))
+        .service(
+            web::resource("/{id}/team/{email}")
+                .route(web::patch().to(cause_handlers::update_team_member))
+                .route(web::delete().to(cause_handlers::remove_team_member))
+        )
+        .service(
+            web::resource("/{id}/embed-tokens")
+                .route(web::get().to(embed_handlers::get_embed_tokens))
+                .route(web::post().to(embed_handlers::create_embed_token))
+        )
+        .route("/{id}/embed-tokens/{token_id}", web::delete().to(embed_handlers::revoke_embed_token))
+        .service(
+            web::resource("/{id}/campaigns")
+                .route(web::get().to(campaign_handlers::get_campaigns))
+                .route(web::post().to(campaign_handlers::create_campaign))
+        )
+        .service(
+            web::resource("/{id}/campaigns/{campaign_id}")
+                .route(web::get().to(campaign_handlers::get_campaign))
+                .route(web::patch().to(campaign_handlers::update_campaign))
+                .route(web::delete().to(campaign_handlers::delete_campaign))
+        )
+        .route("/{id}/campaigns/{campaign_id}/progress", web::get().to(campaign_handlers::get_campaign_progress))
+        .route("/{id}/fundraisers/leaderboard", web::get().to(fundraiser_handlers::get_fundraiser_leaderboard))
+        .route("/{id}/retry", web::post().to(cause_handlers::retry_cause_creation))
 }
\ No newline at end of file
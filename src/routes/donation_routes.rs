@@ -0,0 +1,18 @@
+use actix_web::web;
+use crate::handlers::donation_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/donations")
+            .route("/payment-methods", web::get().to(donation_handlers::get_payment_methods))
+            .route("/sessions/{session_id}", web::get().to(donation_handlers::get_donation_session))
+            .route("/payment-intents", web::post().to(donation_handlers::create_payment_intent))
+            .route("/payment-intents/{id}/confirm", web::post().to(donation_handlers::confirm_payment_intent))
+    );
+    // Donations paid without a wallet, held for the donor's email
+    cfg.service(
+        web::scope("/deposits")
+            .route("/claim", web::post().to(donation_handlers::claim_deposits))
+            .route("/claim/link", web::post().to(donation_handlers::request_claim_link))
+    );
+}
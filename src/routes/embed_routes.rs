@@ -0,0 +1,10 @@
+use actix_web::web;
+use crate::handlers::embed_handlers;
+
+/// Called from causes' own sites, which CORS allows for these paths only
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/embed")
+            .route("/donate", web::post().to(embed_handlers::create_embed_donation))
+    );
+}
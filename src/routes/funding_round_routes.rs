@@ -0,0 +1,10 @@
+use actix_web::web;
+use crate::handlers::funding_round_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/funding-rounds")
+            .route("", web::get().to(funding_round_handlers::get_funding_rounds))
+            .route("/{id}", web::get().to(funding_round_handlers::get_funding_round))
+    );
+}
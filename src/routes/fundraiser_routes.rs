@@ -0,0 +1,16 @@
+use actix_web::web;
+use crate::handlers::fundraiser_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/fundraisers")
+            .route("", web::post().to(fundraiser_handlers::create_fundraiser))
+            .service(
+                web::resource("/{slug}")
+                    .route(web::get().to(fundraiser_handlers::get_fundraiser))
+                    .route(web::patch().to(fundraiser_handlers::update_fundraiser))
+            )
+            .route("/{slug}/progress", web::get().to(fundraiser_handlers::get_fundraiser_progress))
+            .route("/{slug}/donate", web::post().to(fundraiser_handlers::donate_to_fundraiser))
+    );
+}
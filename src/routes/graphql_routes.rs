@@ -0,0 +1,10 @@
+use actix_web::web;
+use crate::handlers::graphql_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/graphql")
+            .route(web::post().to(graphql_handlers::graphql))
+            .route(web::get().to(graphql_handlers::graphiql))
+    );
+}
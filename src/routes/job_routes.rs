@@ -0,0 +1,6 @@
+use actix_web::web;
+use crate::handlers::job_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/jobs/{id}", web::get().to(job_handlers::get_job));
+}
@@ -0,0 +1,6 @@
+use actix_web::web;
+use crate::handlers::key_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/signing-key", web::get().to(key_handlers::get_signing_key));
+}
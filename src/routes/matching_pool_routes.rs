@@ -0,0 +1,11 @@
+use actix_web::web;
+use crate::handlers::matching_pool_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/matching-pools")
+            .route("", web::get().to(matching_pool_handlers::get_matching_pools))
+            .route("/{id}", web::get().to(matching_pool_handlers::get_matching_pool))
+            .route("/{id}/matches", web::get().to(matching_pool_handlers::get_matching_pool_matches))
+    );
+}
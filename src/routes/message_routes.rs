@@ -12,19 +12,75 @@ pub mod message_routes {
                 .route("/health", web::get().to(handlers::health_check))
                 .route("/echo", web::post().to(handlers::echo))
                 .route("/users", web::post().to(handlers::create_user))
+                // Before the /users/{address}/... routes so the name isn't taken for an address
+                .route("/users/check-username/{username}", web::get().to(handlers::check_username))
                 .route("/users/{wallet_address}", web::get().to(handlers::get_user))
+                .route("/users/{wallet_address}", web::patch().to(handlers::update_user_profile))
+                .route("/users/{wallet_address}", web::delete().to(handlers::delete_user))
+                .route("/users/{wallet_address}/export", web::get().to(handlers::export_user_data))
+                .route("/users/{wallet_address}/contacts", web::get().to(handlers::contact_handlers::list_contacts))
+                .route("/users/{wallet_address}/contacts/{contact_address}", web::put().to(handlers::contact_handlers::save_contact))
+                .route("/users/{wallet_address}/contacts/{contact_address}", web::delete().to(handlers::contact_handlers::delete_contact))
+                .route("/users/{wallet_address}/notification-preferences", web::get().to(handlers::notification_handlers::get_notification_preferences))
+                .route("/users/{wallet_address}/notification-preferences", web::put().to(handlers::notification_handlers::update_notification_preferences))
+                .route("/users/{wallet_address}/preferences", web::put().to(handlers::preference_handlers::update_preferences))
+                .route("/users/{wallet_address}/preferences/history", web::get().to(handlers::preference_handlers::get_preference_history))
+                .route("/users/{wallet_address}/spending-weights", web::get().to(handlers::preference_handlers::get_spending_weights))
+                .route("/users/{wallet_address}/spending-weights", web::put().to(handlers::preference_handlers::update_spending_weights))
+                .route("/users/{wallet_address}/preference-ledger", web::get().to(handlers::preference_handlers::get_preference_ledger))
+                .route("/users/{wallet_address}/preference-templates", web::get().to(handlers::preference_handlers::list_preference_templates))
+                .route("/users/{wallet_address}/preference-templates/{name}", web::put().to(handlers::preference_handlers::save_preference_template))
+                .route("/users/{wallet_address}/preference-templates/{name}", web::delete().to(handlers::preference_handlers::delete_preference_template))
+                .route("/users/{wallet_address}/preference-templates/{name}/apply", web::post().to(handlers::preference_handlers::apply_preference_template))
+                .route("/users/{wallet_address}/loyalty", web::get().to(handlers::loyalty_handlers::get_user_loyalty))
+                .route("/users/{wallet_address}/activity", web::get().to(handlers::activity_handlers::get_wallet_activity))
+                .route("/users/{wallet_address}/devices", web::post().to(handlers::notification_handlers::register_device))
+                .route("/users/{wallet_address}/devices/{token}", web::delete().to(handlers::notification_handlers::unregister_device))
 
                 // Payment routes for creation, supplementation/calculation, and status, abstract this later into 
                 // own routes: 
 
                 .route("/payments", web::post().to(handlers::create_payment))
+                .route("/payments/batch", web::post().to(handlers::create_payment_batch))
                 .route("/payments/{payment_id}/supplement", web::post().to(handlers::supplement_transaction))
                 .route("/payments/{payment_id}/status", web::get().to(handlers::get_payment_status))
                 .route("/payments/{payment_id}/sign", web::post().to(handlers::process_signed_transaction))
+                .route("/payments/{payment_id}/capture", web::post().to(handlers::capture_payment))
+                .route("/payments/{payment_id}/void", web::post().to(handlers::void_payment))
+                .route("/payments/{payment_id}/review", web::post().to(handlers::review_handlers::create_review))
+                .route("/payments/{payment_id}/dispute", web::post().to(handlers::dispute_handlers::open_dispute))
                 .route("/payments/{payment_id}", web::delete().to(handlers::delete_payment))
                 
+                // Payment requests between users, paid through the payment routes above
+                .route("/payment-requests", web::post().to(handlers::payment_request_handlers::create_payment_request))
+                .route("/payment-requests/{request_id}/accept", web::post().to(handlers::payment_request_handlers::accept_payment_request))
+                .route("/payment-requests/{request_id}/decline", web::post().to(handlers::payment_request_handlers::decline_payment_request))
+                .route("/payment-requests/{request_id}", web::delete().to(handlers::payment_request_handlers::cancel_payment_request))
+                .route("/users/{wallet_address}/payment-requests", web::get().to(handlers::payment_request_handlers::get_user_payment_requests))
+                
+                // One-off and recurring payments to vendors; each run is paid through the payment routes
+                .route("/schedules", web::post().to(handlers::payment_schedule_handlers::create_payment_schedule))
+                .route("/schedules/{schedule_id}", web::get().to(handlers::payment_schedule_handlers::get_payment_schedule))
+                .route("/schedules/{schedule_id}", web::patch().to(handlers::payment_schedule_handlers::update_payment_schedule))
+                .route("/schedules/{schedule_id}", web::delete().to(handlers::payment_schedule_handlers::cancel_payment_schedule))
+                .route("/schedules/{schedule_id}/pay", web::post().to(handlers::payment_schedule_handlers::pay_payment_schedule))
+                .route("/users/{wallet_address}/schedules", web::get().to(handlers::payment_schedule_handlers::get_user_payment_schedules))
+                
+                // Invoices from vendors to customers, paid through the payment routes
+                .route("/invoices", web::post().to(handlers::invoice_handlers::create_invoice))
+                .route("/invoices/{invoice_id}", web::get().to(handlers::invoice_handlers::get_invoice))
+                .route("/invoices/{invoice_id}", web::delete().to(handlers::invoice_handlers::cancel_invoice))
+                .route("/invoices/{invoice_id}/pay", web::post().to(handlers::invoice_handlers::pay_invoice))
+                .route("/users/{wallet_address}/invoices", web::get().to(handlers::invoice_handlers::get_user_invoices))
+                
+                // Disputes of completed payments; admins resolve them under /admin/disputes
+                .route("/disputes/{dispute_id}", web::get().to(handlers::dispute_handlers::get_dispute))
+                .route("/disputes/{dispute_id}/respond", web::post().to(handlers::dispute_handlers::respond_to_dispute))
+                .route("/users/{wallet_address}/disputes", web::get().to(handlers::dispute_handlers::get_user_disputes))
+                
                 // Transaction history route
                 .route("/users/{user_address}/transactions", web::get().to(handlers::get_user_transaction_history))
+                .route("/users/{user_address}/deposits/pending", web::get().to(handlers::get_pending_deposits))
         );
     }
 } 
\ No newline at end of file
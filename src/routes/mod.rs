@@ -1,22 +1,164 @@
+use actix_web::{guard, web, HttpRequest, HttpResponse};
+use actix_web::dev::{RequestHead, Service};
+use actix_web::http::header::{HeaderName, HeaderValue, LINK};
+use actix_web::middleware::DefaultHeaders;
+use actix_web::web::ServiceConfig;
+use crate::handlers::health_handler::health;
+use crate::handlers::key_handlers::get_published_keys;
+use crate::models::ApiError;
+
 mod message_routes;
 mod vault_routes;
 mod cause_routes;
 mod webhook_routes;
 mod wallet_routes;
 mod vendor_routes;
+mod admin_routes;
+mod matching_pool_routes;
+mod funding_round_routes;
+mod donation_routes;
+mod account_routes;
+mod voucher_routes;
+mod graphql_routes;
+mod key_routes;
+mod token_routes;
+mod job_routes;
+mod embed_routes;
+mod fundraiser_routes;
+mod organization_routes;
 
 pub use message_routes::message_routes::configure as configure_message_routes;
 pub use vault_routes::configure as configure_vault_routes;
 pub use cause_routes::configure as configure_cause_routes;
+pub use cause_routes::configure_v2 as configure_cause_routes_v2;
 pub use webhook_routes::configure as configure_webhook_routes;
 pub use wallet_routes::configure as configure_wallet_routes;
 pub use vendor_routes::configure as configure_vendor_routes;
+pub use admin_routes::configure as configure_admin_routes;
+pub use matching_pool_routes::configure as configure_matching_pool_routes;
+pub use funding_round_routes::configure as configure_funding_round_routes;
+pub use donation_routes::configure as configure_donation_routes;
+pub use account_routes::configure as configure_account_routes;
+pub use voucher_routes::configure as configure_voucher_routes;
+pub use graphql_routes::configure as configure_graphql_routes;
+pub use key_routes::configure as configure_key_routes;
+pub use token_routes::configure as configure_token_routes;
+pub use job_routes::configure as configure_job_routes;
+pub use embed_routes::configure as configure_embed_routes;
+pub use fundraiser_routes::configure as configure_fundraiser_routes;
+pub use organization_routes::configure as configure_organization_routes;
+
+/// Request header a client can send on an unversioned path to pick a version, and the
+/// response header saying which version served the request
+pub const API_VERSION_HEADER: &str = "Api-Version";
+
+/// Version unversioned paths without an `Api-Version` header are served by. These are the
+/// paths from before versioning, so it stays at 1 however many versions are added.
+pub const LEGACY_VERSION: u32 = 1;
+
+/// Every version served, oldest first. A breaking change goes in a new version with its own
+/// configure function, so `/v1` keeps working until it is retired.
+const API_VERSIONS: &[(u32, fn(&mut ServiceConfig))] = &[
+    (1, configure_v1),
+    (2, configure_v2),
+];
+
+/// Every route of API version 1
+fn configure_v1(cfg: &mut ServiceConfig) {
+    configure_cause_routes(cfg);
+    configure_shared(cfg);
+}
+
+/// Version 2: `GET /causes` returns a page of cause summaries instead of every cause
+fn configure_v2(cfg: &mut ServiceConfig) {
+    configure_cause_routes_v2(cfg);
+    configure_shared(cfg);
+}
 
-pub fn configure(cfg: &mut actix_web::web::ServiceConfig) {
+/// Routes every version serves the same way
+fn configure_shared(cfg: &mut ServiceConfig) {
     configure_message_routes(cfg);
     configure_vault_routes(cfg);
-    configure_cause_routes(cfg);
     configure_webhook_routes(cfg);
     configure_wallet_routes(cfg);
     configure_vendor_routes(cfg);
-}
\ No newline at end of file
+    configure_admin_routes(cfg);
+    configure_matching_pool_routes(cfg);
+    configure_funding_round_routes(cfg);
+    configure_donation_routes(cfg);
+    configure_account_routes(cfg);
+    configure_voucher_routes(cfg);
+    configure_graphql_routes(cfg);
+    configure_key_routes(cfg);
+    configure_token_routes(cfg);
+    configure_job_routes(cfg);
+    configure_embed_routes(cfg);
+    configure_fundraiser_routes(cfg);
+    configure_organization_routes(cfg);
+}
+
+/// Mount each version under `/v{n}`. Unversioned paths still work: with an `Api-Version`
+/// header they are served by that version, and without one by `LEGACY_VERSION` with
+/// `Deprecation` and a `Link` to the same path under `/v{LEGACY_VERSION}`.
+pub fn configure(cfg: &mut ServiceConfig) {
+    // Unversioned, and ahead of the catch-all scopes below, which would otherwise shadow them
+    cfg.route("/health", web::get().to(health));
+    cfg.route("/.well-known/index-wallets-keys", web::get().to(get_published_keys));
+
+    for (version, configure_version) in API_VERSIONS {
+        cfg.service(
+            web::scope(&format!("/v{}", version))
+                .wrap(DefaultHeaders::new().add((API_VERSION_HEADER, version.to_string())))
+                .configure(*configure_version)
+        );
+    }
+
+    for &(version, configure_version) in API_VERSIONS {
+        cfg.service(
+            web::scope("")
+                .guard(guard::fn_guard(move |ctx| requested_version(ctx.head()) == Some(version)))
+                .wrap(DefaultHeaders::new().add((API_VERSION_HEADER, version.to_string())))
+                .configure(configure_version)
+        );
+    }
+    cfg.service(
+        web::scope("")
+            .guard(guard::fn_guard(|ctx| ctx.head().headers().contains_key(API_VERSION_HEADER)))
+            .default_service(web::to(unsupported_version))
+    );
+
+    let (_, configure_legacy) = API_VERSIONS.iter()
+        .find(|(version, _)| *version == LEGACY_VERSION)
+        .expect("LEGACY_VERSION must be served");
+    cfg.service(
+        web::scope("")
+            .wrap_fn(|req, srv| {
+                let successor = format!("</v{}{}>; rel=\"successor-version\"", LEGACY_VERSION, req.path());
+                let response = srv.call(req);
+                async move {
+                    let mut response = response.await?;
+                    let headers = response.headers_mut();
+                    headers.insert(HeaderName::from_static("api-version"), HeaderValue::from(LEGACY_VERSION));
+                    headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+                    if let Ok(link) = HeaderValue::from_str(&successor) {
+                        headers.insert(LINK, link);
+                    }
+                    Ok(response)
+                }
+            })
+            .configure(*configure_legacy)
+    );
+}
+
+/// The version asked for in the `Api-Version` header, if it's a number
+fn requested_version(head: &RequestHead) -> Option<u32> {
+    head.headers().get(API_VERSION_HEADER)?.to_str().ok()?.trim().parse().ok()
+}
+
+async fn unsupported_version(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let requested = req.headers().get(API_VERSION_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let supported: Vec<String> = API_VERSIONS.iter().map(|(version, _)| version.to_string()).collect();
+    Err(ApiError::ValidationError(format!(
+        "Unsupported API version '{}', supported versions are {}", requested, supported.join(", ")
+    )))
+}
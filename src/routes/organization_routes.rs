@@ -0,0 +1,16 @@
+use actix_web::web;
+use crate::handlers::organization_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/organizations")
+            .route("", web::post().to(organization_handlers::create_organization))
+            .route("/mine", web::get().to(organization_handlers::get_my_organizations))
+            .route("/{id}", web::get().to(organization_handlers::get_organization))
+            .route("/{id}/onboarding", web::post().to(organization_handlers::create_organization_onboarding_link))
+            .route("/{id}/admins", web::post().to(organization_handlers::add_organization_admin))
+            .route("/{id}/admins/{wallet_address}", web::delete().to(organization_handlers::remove_organization_admin))
+            .route("/{id}/causes", web::post().to(organization_handlers::create_organization_cause))
+            .route("/{id}/dashboard", web::get().to(organization_handlers::get_organization_dashboard))
+    );
+}
@@ -1,10 +1,11 @@
 use actix_web::web;
-use crate::handlers::token_handler;
+use crate::handlers::token_handlers;
+use crate::response_caching::ConditionalGet;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/tokens")
-            .route("", web::get().to(token_handler::get_all_tokens))
-            .route("/{name}", web::get().to(token_handler::get_token_by_name))
+            .service(web::resource("").wrap(ConditionalGet).route(web::get().to(token_handlers::list_tokens)))
+            .route("/{symbol}/holders", web::get().to(token_handlers::get_token_holders))
     );
-}
\ No newline at end of file
+}
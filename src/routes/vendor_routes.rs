@@ -1,9 +1,30 @@
 use actix_web::web;
-use crate::handlers::vendor_handlers;
+use crate::handlers::{vendor_handlers, review_handlers, loyalty_handlers, promo_code_handlers, escrow_handlers};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/vendors")
             .route("/partnered", web::get().to(vendor_handlers::get_partnered_vendors))
+            .route("/nearby", web::get().to(vendor_handlers::get_nearby_vendors))
+            .route("/{vendor_address}/reviews", web::get().to(review_handlers::get_vendor_reviews))
+            .route("/{vendor_address}/loyalty", web::get().to(loyalty_handlers::get_loyalty_program))
+    );
+    cfg.service(
+        web::scope("/vendor")
+            .route("/{vendor_address}/profile", web::put().to(vendor_handlers::update_vendor_profile))
+            .route("/{vendor_address}/stripe/onboarding", web::post().to(vendor_handlers::start_vendor_onboarding))
+            .route("/{vendor_address}/stripe/status", web::get().to(vendor_handlers::get_vendor_stripe_status))
+            .route("/{vendor_address}/payment-codes", web::put().to(vendor_handlers::set_payment_code_namespace))
+            .route("/{vendor_address}/loyalty", web::put().to(loyalty_handlers::update_loyalty_program))
+            .route("/{vendor_address}/promo-codes", web::get().to(promo_code_handlers::list_promo_codes))
+            .route("/{vendor_address}/promo-codes", web::post().to(promo_code_handlers::create_promo_code))
+            .route("/{vendor_address}/promo-codes/{code}", web::patch().to(promo_code_handlers::update_promo_code))
+            .route("/{vendor_address}/promo-codes/{code}", web::delete().to(promo_code_handlers::delete_promo_code))
+            .route("/{vendor_address}/payments", web::get().to(vendor_handlers::get_vendor_payments))
+            .route("/{vendor_address}/payments/{payment_id}/loyalty", web::post().to(loyalty_handlers::redeem_loyalty_reward))
+            .route("/{vendor_address}/payments/{payment_id}/loyalty", web::delete().to(loyalty_handlers::remove_loyalty_reward))
+            .route("/{vendor_address}/payments/{payment_id}/capture", web::post().to(escrow_handlers::capture_escrow))
+            .route("/{vendor_address}/payments/{payment_id}/refund", web::post().to(escrow_handlers::refund_escrow))
+            .route("/{vendor_address}/reports/daily", web::get().to(vendor_handlers::get_vendor_daily_report))
     );
 }
\ No newline at end of file
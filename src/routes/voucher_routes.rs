@@ -0,0 +1,14 @@
+use actix_web::web;
+use crate::handlers::voucher_handlers;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/vouchers")
+            .route("", web::post().to(voucher_handlers::create_voucher))
+            .route("", web::get().to(voucher_handlers::get_issued_vouchers))
+            .route("/{code}", web::get().to(voucher_handlers::get_voucher))
+            .route("/{code}/redeem", web::post().to(voucher_handlers::redeem_voucher))
+            .route("/{id}/fund", web::post().to(voucher_handlers::fund_voucher))
+            .route("/{id}", web::delete().to(voucher_handlers::cancel_voucher))
+    );
+}
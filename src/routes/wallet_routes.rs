@@ -10,5 +10,9 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .route("/{wallet_address}/valuations", web::get().to(wallet_handlers::get_user_valuations))
             .route("/{wallet_address}/valuations", web::post().to(wallet_handlers::update_user_valuation))
             .route("/{wallet_address}/user", web::get().to(wallet_handlers::get_user_info))
+            .route("/{wallet_address}/privacy", web::put().to(wallet_handlers::update_privacy))
+            .route("/{wallet_address}/topup-session", web::post().to(wallet_handlers::create_topup_session))
+            .route("/{wallet_address}/payment-methods", web::get().to(wallet_handlers::list_payment_methods))
+            .route("/{wallet_address}/payment-methods/{payment_method_id}", web::delete().to(wallet_handlers::detach_payment_method))
     );
 }
\ No newline at end of file
@@ -1,6 +1,5 @@
 use actix_web::web;
-use crate::handlers::webhook_handlers::handle_stripe_webhook;
-use crate::handlers::purchase_webhook_handlers::handle_stripe_purchases_webhook;
+use crate::handlers::stripe_event_router::{handle_stripe_webhook, handle_stripe_purchases_webhook};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::{env, fs};
+use actix_web::web;
+use delta_executor_sdk::base::crypto::Ed25519PrivKey;
+use log::{info, warn};
+use mongodb::bson::Document;
+use crate::config::SandboxConfig;
+use crate::models::{ApiError, CreateUserRequest, Payment, PaymentStatus, PreferenceChange, PreferenceChangeSource};
+use crate::models::cause::{Cause, CauseStatus};
+use crate::services::{executor_base_url, MongoDBService, TokenService, WalletService};
+use crate::utils::audit::SYSTEM_ACTOR;
+use crate::utils::to_on_chain_units;
+
+/// Where the demo wallets' private keys are kept between runs, so seeding again reuses them
+const DEFAULT_WALLETS_FILE: &str = "seed_wallets.json";
+
+/// Cause tokens minted per demo cause, as for real causes
+const CAUSE_SUPPLY: u64 = 100_000_000;
+
+/// Tokens a new demo user gets of USD and of each cause token
+const USD_GRANT: f64 = 500.0;
+const CAUSE_TOKEN_GRANT: f64 = 200.0;
+
+struct DemoUser {
+    username: &'static str,
+    user_type: &'static str,
+    description: Option<&'static str>,
+}
+
+const DEMO_USERS: &[DemoUser] = &[
+    DemoUser { username: "alice", user_type: "customer", description: None },
+    DemoUser { username: "bob", user_type: "customer", description: None },
+    DemoUser { username: "corner-cafe", user_type: "vendor", description: Some("Coffee and pastries on the corner") },
+    DemoUser { username: "book-nook", user_type: "vendor", description: Some("Second-hand books and zines") },
+];
+
+struct DemoCause {
+    name: &'static str,
+    organization: &'static str,
+    description: &'static str,
+    token_name: &'static str,
+    token_symbol: &'static str,
+}
+
+const DEMO_CAUSES: &[DemoCause] = &[
+    DemoCause { name: "Community Garden", organization: "Green Streets", description: "Raised beds and a tool library for the neighbourhood", token_name: "Garden Token", token_symbol: "GRDN" },
+    DemoCause { name: "Open Library", organization: "Readers Collective", description: "Free books and reading space for everyone", token_name: "Library Token", token_symbol: "BOOK" },
+    DemoCause { name: "Clean River", organization: "River Friends", description: "Monthly clean-ups and water quality testing", token_name: "River Token", token_symbol: "RIVR" },
+];
+
+/// Discount budgets, in USD, the demo vendors give for each cause token
+const VENDOR_BUDGETS: &[(&str, f64)] = &[("GRDN", 25.0), ("BOOK", 10.0), ("RIVR", 5.0)];
+
+/// Unpaid payments each demo vendor starts with
+const SAMPLE_PRICES: &[f64] = &[4.5, 12.0, 27.25];
+
+/// Fill a local database and executor with demo users, causes with minted tokens, vendor
+/// preferences and payment codes ready to pay, so the frontend can run without Stripe.
+/// Safe to run again: users, causes and tokens that exist are left alone, and only users
+/// created by this run are funded and given preferences and payments.
+pub async fn run(
+    mongodb: &web::Data<MongoDBService>,
+    token_service: &web::Data<TokenService>,
+    central_vault_keypair: &Ed25519PrivKey,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_local()?;
+
+    let wallets_file = env::var("SEED_WALLETS_FILE").unwrap_or_else(|_| DEFAULT_WALLETS_FILE.to_string());
+    let mut keys: BTreeMap<String, String> = match fs::read_to_string(&wallets_file) {
+        Ok(contents) => serde_json::from_str(&contents)?,
+        Err(_) => BTreeMap::new(),
+    };
+
+    for cause in DEMO_CAUSES {
+        seed_cause(mongodb, token_service, cause).await?;
+    }
+
+    let mut created = Vec::new();
+    for user in DEMO_USERS {
+        let keypair = match keys.get(user.username) {
+            Some(key) => Ed25519PrivKey::from_str(key)
+                .map_err(|e| format!("Invalid key for {} in {}: {:?}", user.username, wallets_file, e))?,
+            None => {
+                let keypair = Ed25519PrivKey::generate();
+                keys.insert(user.username.to_string(), keypair.to_string());
+                keypair
+            },
+        };
+        let wallet_address = keypair.pub_key().to_string();
+        if mongodb.get_user_by_wallet(&wallet_address).await?.is_some() {
+            info!("Demo user {} already exists", user.username);
+            continue;
+        }
+        mongodb.create_user_with_vendor_if_needed(CreateUserRequest {
+            wallet_address: wallet_address.clone(),
+            username: user.username.to_string(),
+            preferences: None,
+            is_verified: user.user_type == "vendor",
+            user_type: user.user_type.to_string(),
+            vendor_description: user.description.map(str::to_string),
+            vendor_google_maps_link: None,
+            vendor_website_link: None,
+        }).await?;
+        info!("Created demo {} {} with wallet {}", user.user_type, user.username, wallet_address);
+        created.push((user, wallet_address));
+    }
+    // Saved before touching the executor so a failed transfer doesn't lose the keys
+    fs::write(&wallets_file, serde_json::to_string_pretty(&keys)?)?;
+    info!("Demo wallet keys are in {}", wallets_file);
+
+    for (user, wallet_address) in &created {
+        fund(token_service, central_vault_keypair, wallet_address).await?;
+        if user.user_type == "vendor" {
+            set_vendor_budgets(mongodb, wallet_address).await?;
+            for price in SAMPLE_PRICES {
+                let payment_id = create_sample_payment(mongodb, user.username, wallet_address, *price).await?;
+                info!("Payment code {} for ${} at {}", payment_id, price, user.username);
+            }
+        }
+    }
+
+    info!("Seeding done: {} demo users created", created.len());
+    Ok(())
+}
+
+/// Mint the cause's token unless it exists and record the cause as active
+/// Refuse to seed a production deployment or against a remote executor: seeding moves USD
+/// out of the central vault and writes the demo wallets' private keys to disk. A sandbox's
+/// test executor counts as local.
+fn check_local() -> Result<(), String> {
+    let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string());
+    if environment == "production" {
+        return Err("--seed can't run when ENVIRONMENT=production".to_string());
+    }
+    let sandbox = SandboxConfig::from_env();
+    if sandbox.executor_url().is_some() {
+        return Ok(());
+    }
+    let executor_url = executor_base_url(&environment, &sandbox);
+    let host = reqwest::Url::parse(&executor_url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.trim_matches(|c| c == '[' || c == ']').to_string()));
+    let local = host.as_deref().map_or(false, |host| {
+        host == "localhost" || host.parse::<std::net::IpAddr>().map_or(false, |ip| ip.is_loopback())
+    });
+    if !local {
+        return Err(format!("--seed only runs against a local executor or SANDBOX_EXECUTOR_URL, not {}", executor_url));
+    }
+    Ok(())
+}
+
+async fn seed_cause(mongodb: &MongoDBService, token_service: &TokenService, demo: &DemoCause) -> Result<(), Box<dyn std::error::Error>> {
+    if mongodb.get_cause_by_token_symbol(demo.token_symbol).await?.is_some() {
+        info!("Demo cause {} already exists", demo.name);
+        return Ok(());
+    }
+    let token = match mongodb.get_token_by_symbol(demo.token_symbol).await? {
+        Some(token) => token,
+        None => token_service.create_token_for_cause(demo.token_name, demo.token_symbol, CAUSE_SUPPLY, None).await?,
+    };
+
+    let mut cause = Cause::new(
+        demo.name.to_string(),
+        demo.organization.to_string(),
+        demo.description.to_string(),
+        demo.description.to_string(),
+        "demo@example.com".to_string(),
+        demo.token_name.to_string(),
+        demo.token_symbol.to_string(),
+        None,
+        None,
+    );
+    cause.status = CauseStatus::Active;
+    cause.token_id = Some(token.token_id);
+    mongodb.create_cause(cause).await?;
+    info!("Created demo cause {} with token {}", demo.name, demo.token_symbol);
+    Ok(())
+}
+
+/// Send a new demo wallet USD and some of every demo cause token from the central vault
+async fn fund(token_service: &TokenService, central_vault_keypair: &Ed25519PrivKey, wallet_address: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let pubkey = WalletService::parse_public_key(wallet_address).map_err(|e| e.to_string())?;
+    let grants = std::iter::once(("USD", USD_GRANT))
+        .chain(DEMO_CAUSES.iter().map(|cause| (cause.token_symbol, CAUSE_TOKEN_GRANT)));
+    for (symbol, amount) in grants {
+        if let Err(e) = token_service.transfer_tokens(central_vault_keypair, &pubkey, symbol, to_on_chain_units(amount)).await {
+            warn!("Failed to send {} {} to {}: {}", amount, symbol, wallet_address, e);
+        }
+    }
+    Ok(())
+}
+
+async fn set_vendor_budgets(mongodb: &MongoDBService, wallet_address: &str) -> Result<(), ApiError> {
+    let mut after = Document::new();
+    for (symbol, budget) in VENDOR_BUDGETS {
+        after.insert(*symbol, *budget);
+    }
+    let before = mongodb.replace_user_preferences(wallet_address, after.clone()).await?;
+    mongodb.record_preference_change(&PreferenceChange {
+        id: None,
+        wallet_address: wallet_address.to_string(),
+        source: PreferenceChangeSource::Bulk,
+        template_name: None,
+        changed_by: SYSTEM_ACTOR.to_string(),
+        before,
+        after,
+        created_at: chrono::Utc::now().timestamp(),
+    }).await
+}
+
+async fn create_sample_payment(mongodb: &MongoDBService, vendor_name: &str, vendor_address: &str, price_usd: f64) -> Result<String, ApiError> {
+    let payment_id = mongodb.generate_payment_id();
+    mongodb.create_payment(Payment {
+        id: None,
+        payment_id: payment_id.clone(),
+        vendor_address: vendor_address.to_string(),
+        vendor_name: vendor_name.to_string(),
+        price_usd,
+        customer_address: None,
+        customer_username: None,
+        status: PaymentStatus::Created,
+        created_at: chrono::Utc::now().timestamp(),
+        vendor_valuations: None,
+        discount_consumption: None,
+        computed_payment: None,
+        initial_payment_bundle: None,
+        recepient_verified: true,
+        executor_tx_id: None,
+        submitted_at: None,
+        finality_checked_at: None,
+        failure_reason: None,
+        payment_request_id: None,
+        loyalty_redemption: None,
+        loyalty_points_earned: None,
+        promo: None,
+        escrow: None,
+        schedule_id: None,
+        batch_id: None,
+        invoice_id: None,
+        valuation_overrides: None,
+        budget_consumed: false,
+        splits: Vec::new(),
+        split_legs: Vec::new(),
+        authorization: None,
+        vendor_slug: None,
+        short_code: None,
+    }).await?;
+    Ok(payment_id)
+}
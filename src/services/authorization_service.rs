@@ -0,0 +1,71 @@
+use actix_web::web;
+use log::{info, warn, error};
+use crate::models::{ApiError, AuditAction, AuditLog, Payment, PaymentStatus};
+use crate::services::{MongoDBService, SharedState, SharedEvent};
+use crate::utils::audit::{snapshot, SYSTEM_ACTOR};
+
+/// Expired authorizations voided per run
+const BATCH_SIZE: i64 = 100;
+
+/// Voids two-phase payments. The payer's signed allowances are held unsubmitted while the
+/// payment is authorized; voiding drops them, so nothing is ever paid. Authorizations the
+/// vendor doesn't capture within their window are voided by the scheduler.
+#[derive(Clone)]
+pub struct AuthorizationService {
+    mongodb: web::Data<MongoDBService>,
+    shared_state: web::Data<SharedState>,
+}
+
+impl AuthorizationService {
+    pub fn new(mongodb: web::Data<MongoDBService>, shared_state: web::Data<SharedState>) -> Self {
+        Self { mongodb, shared_state }
+    }
+
+    /// Void an authorized payment. Taking the held allowances first means a capture that's
+    /// already under way wins and this fails with a conflict.
+    pub async fn void(&self, payment_id: &str, actor: &str) -> Result<Payment, ApiError> {
+        let before = self.mongodb.get_payment(payment_id).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", payment_id)))?;
+        if before.status != PaymentStatus::Authorized {
+            return Err(ApiError::Conflict(format!("Payment is {}", before.status)));
+        }
+        let held = self.mongodb.take_held_authorization(payment_id).await?
+            .ok_or_else(|| ApiError::Conflict("The payment is already being captured or voided".to_string()))?;
+
+        let voided = match self.mongodb.void_payment(payment_id, actor).await {
+            Ok(Some(voided)) => voided,
+            result => {
+                if let Err(e) = self.mongodb.hold_authorization(&held).await {
+                    error!("Failed to hold allowances of payment {} again after a failed void: {}", payment_id, e);
+                }
+                return Err(result.err().unwrap_or_else(|| ApiError::Conflict("The payment is no longer authorized".to_string())));
+            },
+        };
+        info!("{} voided payment {}", actor, payment_id);
+        self.shared_state.publish(SharedEvent::PaymentStatus { payment_id: payment_id.to_string(), status: PaymentStatus::Voided });
+
+        let audit = AuditLog::new(actor, AuditAction::PaymentVoided, "payment", payment_id, snapshot(&before), snapshot(&voided));
+        if let Err(e) = self.mongodb.record_audit_log(audit).await {
+            error!("Failed to record audit log for void of payment {}: {}", payment_id, e);
+        }
+        Ok(voided)
+    }
+
+    /// Void authorizations whose capture window has closed
+    pub async fn void_expired(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let payments = match self.mongodb.get_expired_authorizations(now, BATCH_SIZE).await {
+            Ok(payments) => payments,
+            Err(e) => {
+                error!("Failed to load expired authorizations: {}", e);
+                return;
+            }
+        };
+
+        for payment in payments {
+            if let Err(e) = self.void(&payment.payment_id, SYSTEM_ACTOR).await {
+                warn!("Failed to void expired authorization of payment {}: {}", payment.payment_id, e);
+            }
+        }
+    }
+}
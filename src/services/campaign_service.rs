@@ -0,0 +1,111 @@
+use actix_web::web;
+use log::info;
+use mongodb::bson::oid::ObjectId;
+use crate::models::{ApiError, Campaign, CampaignProgress, CampaignStatus, CreateCampaignRequest, UpdateCampaignRequest, MAX_ACTIVE_CAMPAIGNS};
+use crate::models::cause::Cause;
+use crate::services::MongoDBService;
+
+/// Named fundraisers under a cause. They share the cause's token and connected account;
+/// donations name the campaign in their checkout metadata and are totalled per campaign.
+#[derive(Clone)]
+pub struct CampaignService {
+    mongodb: web::Data<MongoDBService>,
+}
+
+impl CampaignService {
+    pub fn new(mongodb: web::Data<MongoDBService>) -> Self {
+        Self { mongodb }
+    }
+
+    pub async fn create(&self, cause: &Cause, request: &CreateCampaignRequest, created_by: &str) -> Result<Campaign, ApiError> {
+        let cause_id = cause.id.map(|id| id.to_hex()).unwrap_or_default();
+        if self.mongodb.count_active_campaigns(&cause_id).await? as usize >= MAX_ACTIVE_CAMPAIGNS {
+            return Err(ApiError::Conflict(format!("A cause can run at most {} campaigns at once; end one first", MAX_ACTIVE_CAMPAIGNS)));
+        }
+        let now = chrono::Utc::now().timestamp();
+        let mut campaign = Campaign {
+            id: None,
+            cause_id,
+            name: request.name.trim().to_string(),
+            description: request.description.clone(),
+            goal_cents: request.goal_cents,
+            starts_at: request.starts_at,
+            ends_at: request.ends_at,
+            status: CampaignStatus::Active,
+            created_by: created_by.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        campaign.id = Some(self.mongodb.create_campaign(&campaign).await?);
+        info!("{} created campaign {} for cause {}", created_by, campaign.name, campaign.cause_id);
+        Ok(campaign)
+    }
+
+    /// A campaign of the given cause
+    pub async fn get(&self, cause_id: &ObjectId, campaign_id: &str) -> Result<Campaign, ApiError> {
+        let not_found = || ApiError::NotFound(format!("Campaign {} not found", campaign_id));
+        let id = ObjectId::parse_str(campaign_id).map_err(|_| not_found())?;
+        self.mongodb.get_campaign(&id).await?
+            .filter(|campaign| campaign.cause_id == cause_id.to_hex())
+            .ok_or_else(not_found)
+    }
+
+    pub async fn list(&self, cause_id: &ObjectId) -> Result<Vec<Campaign>, ApiError> {
+        self.mongodb.get_campaigns(&cause_id.to_hex()).await
+    }
+
+    pub async fn update(&self, mut campaign: Campaign, request: &UpdateCampaignRequest) -> Result<Campaign, ApiError> {
+        if campaign.status == CampaignStatus::Ended && request.status != Some(CampaignStatus::Active) {
+            return Err(ApiError::Conflict("This campaign has ended; reopen it with status \"active\" to change it".to_string()));
+        }
+        if request.status == Some(CampaignStatus::Active) && campaign.status == CampaignStatus::Ended {
+            let active = self.mongodb.count_active_campaigns(&campaign.cause_id).await? as usize;
+            if active >= MAX_ACTIVE_CAMPAIGNS {
+                return Err(ApiError::Conflict(format!("A cause can run at most {} campaigns at once; end one first", MAX_ACTIVE_CAMPAIGNS)));
+            }
+        }
+        if let Some(name) = &request.name {
+            campaign.name = name.trim().to_string();
+        }
+        if request.description.is_some() {
+            campaign.description = request.description.clone();
+        }
+        campaign.goal_cents = request.goal_cents.unwrap_or(campaign.goal_cents);
+        campaign.starts_at = request.starts_at.or(campaign.starts_at);
+        campaign.ends_at = request.ends_at.or(campaign.ends_at);
+        campaign.status = request.status.unwrap_or(campaign.status);
+        if let (Some(starts_at), Some(ends_at)) = (campaign.starts_at, campaign.ends_at) {
+            if ends_at <= starts_at {
+                return Err(ApiError::ValidationError("ends_at must be after starts_at".to_string()));
+            }
+        }
+        campaign.updated_at = chrono::Utc::now().timestamp();
+        self.mongodb.replace_campaign(&campaign).await?;
+        Ok(campaign)
+    }
+
+    /// Only campaigns nobody has donated to can be deleted; others are ended instead so
+    /// their deposits keep pointing at something
+    pub async fn delete(&self, campaign: &Campaign) -> Result<(), ApiError> {
+        let id = campaign.id.ok_or_else(|| ApiError::InternalError("Campaign has no ID".to_string()))?;
+        let (donations, ..) = self.mongodb.get_attributed_totals("campaign_id", &id.to_hex()).await?;
+        if donations > 0 {
+            return Err(ApiError::Conflict("This campaign has donations; end it instead".to_string()));
+        }
+        self.mongodb.delete_campaign(&id).await
+    }
+
+    pub async fn progress(&self, campaign: Campaign) -> Result<CampaignProgress, ApiError> {
+        let campaign_id = campaign.id.map(|id| id.to_hex()).unwrap_or_default();
+        let (donations, donors, raised_cents, tokens) = self.mongodb.get_attributed_totals("campaign_id", &campaign_id).await?;
+        Ok(CampaignProgress {
+            raised_cents,
+            donations,
+            donors,
+            tokens,
+            percent_of_goal: (raised_cents as f64 * 10000.0 / campaign.goal_cents as f64).round() / 100.0,
+            accepting_donations: campaign.accepts_donations(chrono::Utc::now().timestamp()),
+            campaign,
+        })
+    }
+}
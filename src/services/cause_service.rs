@@ -3,10 +3,19 @@ use std::str::FromStr;
 use log::{info, error};
 use mongodb::bson::oid::ObjectId;
 use futures::stream::TryStreamExt;
-use crate::models::cause::{Cause, CauseStatus};
-use crate::models::{ApiError, CauseDraft, DraftStatus};
-use crate::services::{MongoDBService, TokenService};
-use stripe::{Client, PriceId, AccountId, CreateCheckoutSession, CheckoutSessionMode};
+use crate::models::cause::{Cause, BusinessType, default_country, TeamMember, TeamRole, InviteTeamMemberRequest, CausePayout, CausePayoutRequest, CauseListQuery, CauseListPage, CauseStatus, CauseReview, CreationSaga, CreationStep, ReviewDecision, CauseDashboard, StuckDraft, FailedCause, BulkCauseAction, BulkCauseResult, StripeRequirements, RequirementsState, CauseRequirements};
+use crate::models::{ApiError, CauseDraft, DraftStatus, AuditLog, AuditAction, Role, EmbedToken, CreateEmbedTokenRequest, EmbedDonationRequest, MAX_EMBED_TOKENS, FundraiserStatus, PayoutEvent, PayoutEventKind, Organization};
+use crate::models::payment::{CauseDonationsQuery, CauseDonationsPage, PendingDeposit, PendingDepositStatus, DonationAttribution, CauseAnalytics};
+use crate::utils::audit::{snapshot, SYSTEM_ACTOR};
+use crate::utils::retry::backoff_secs;
+use crate::utils::name_filter::NameFilter;
+use crate::utils::profile::validate_email;
+use crate::utils::redaction::redact;
+use crate::utils::validation::{self, FieldErrors, Validate};
+use crate::utils::email_verification::{sign_verification_token, verify_verification_token, verification_secret, VERIFICATION_TTL_SECS};
+use crate::services::{EmailService, JobProgress, MongoDBService, StripeApi, StripeCustomerService, TokenService};
+use crate::config::{ConnectConfig, ConnectServiceAgreement, PaymentMethodConfig};
+use stripe::{PriceId, AccountId, CreateCheckoutSession, CheckoutSessionMode};
 
 // Request and response structs
 #[derive(serde::Deserialize)]
@@ -15,19 +24,47 @@ pub struct CreateCauseRequest {
     pub organization: String,
     pub description: String,
     pub long_description: String,
+    #[serde(default)]  // organization causes use the organization's contact email
     pub creator_email: String,
     pub token_name: String,
     pub token_symbol: String,
     pub token_image_url: Option<String>,
     pub cause_image_url: Option<String>,
+    #[serde(default = "default_country")]
+    pub country: String,  // where the organization is; must be one of STRIPE_CONNECT_COUNTRIES
+    #[serde(default)]
+    pub business_type: BusinessType,
+    #[serde(skip_deserializing)]  // set from the authenticated wallet, never from the body
+    pub owner_address: Option<String>,
+    #[serde(skip_deserializing)]  // set when an organization admin creates the cause
+    pub organization_id: Option<String>,
 }
 
+impl Validate for CreateCauseRequest {
+    fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        errors.check("name", validation::required(&self.name));
+        errors.check("organization", validation::required(&self.organization));
+        errors.check("token_name", validation::required(&self.token_name));
+        errors.check("token_symbol", validation::token_symbol(&self.token_symbol));
+        errors.check("creator_email", validation::required(&self.creator_email).and_then(|_| validate_email(&self.creator_email)));
+        if self.country.len() != 2 || !self.country.chars().all(|c| c.is_ascii_alphabetic()) {
+            errors.add("country", "Must be a two-letter country code, e.g. US");
+        }
+        errors.into_result()
+    }
+}
+
+/// Smallest and largest donation a checkout session takes
+pub const MIN_DONATION_CENTS: i64 = 100;
+pub const MAX_DONATION_CENTS: i64 = 999999;
+
 #[derive(serde::Serialize)]
 pub struct CreateCauseResponse {
     pub id: String,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Default)]
 pub struct UpdateCauseRequest {
     pub name: Option<String>,
     pub organization: Option<String>,
@@ -44,25 +81,81 @@ pub struct UpdateCauseRequest {
     pub stripe_account_status: Option<String>,
     pub displayed: Option<bool>,
     pub featured: Option<bool>,
+    pub featured_rank: Option<i32>,
+    pub featured_until: Option<i64>,
+}
+
+impl UpdateCauseRequest {
+    /// Fields only admins may change; owners can edit content but not status, listing or payment wiring
+    pub fn touches_admin_fields(&self) -> bool {
+        self.is_active.is_some()
+            || self.stripe_product_id.is_some()
+            || self.payment_link.is_some()
+            || self.status.is_some()
+            || self.token_id.is_some()
+            || self.stripe_account_id.is_some()
+            || self.stripe_account_status.is_some()
+            || self.displayed.is_some()
+            || self.featured.is_some()
+            || self.featured_rank.is_some()
+            || self.featured_until.is_some()
+    }
 }
 
+// How long one runner may hold a cause's creation before another can take over
+const CREATION_LOCK_SECS: i64 = 300;
+// Automatic retries stop after this many failures; POST /causes/{id}/retry still works
+const MAX_AUTO_RETRIES: i32 = 5;
+// Top-up limits, same as donations
+const TOPUP_MIN_CENTS: i64 = 100;
+const TOPUP_MAX_CENTS: i64 = 999999;
+
 pub struct CauseService {
     mongodb_service: Arc<MongoDBService>,
     token_service: Arc<TokenService>,
-    stripe_client: Arc<stripe::Client>,
+    stripe: Arc<dyn StripeApi>,
+    email_service: Arc<EmailService>,
+    email_verification_secret: Vec<u8>,
+    name_filter: NameFilter,
+    payment_methods: PaymentMethodConfig,
+    customer_service: Arc<StripeCustomerService>,
+    connect: ConnectConfig,
 }
 
 impl CauseService {
     pub fn new(
         mongodb_service: Arc<MongoDBService>,
         token_service: Arc<TokenService>,
-        stripe_client: Arc<stripe::Client>,
-    ) -> Self {
-        Self {
+        stripe: Arc<dyn StripeApi>,
+        email_service: Arc<EmailService>,
+        name_filter: NameFilter,
+        payment_methods: PaymentMethodConfig,
+        customer_service: Arc<StripeCustomerService>,
+        connect: ConnectConfig,
+    ) -> Result<Self, String> {
+        let email_verification_secret = verification_secret()?.to_vec();
+        
+        Ok(Self {
             mongodb_service,
             token_service,
-            stripe_client,
-        }
+            stripe,
+            email_service,
+            email_verification_secret,
+            name_filter,
+            payment_methods,
+            customer_service,
+            connect,
+        })
+    }
+
+    /// Payment methods offered at checkout, for the frontend's wallet buttons
+    pub fn payment_methods(&self) -> &PaymentMethodConfig {
+        &self.payment_methods
+    }
+
+    /// Blocked words for cause names, also applied to usernames
+    pub fn name_filter(&self) -> &NameFilter {
+        &self.name_filter
     }
 
     // New draft-based cause creation
@@ -74,7 +167,7 @@ impl CauseService {
                 e
             })?;
         
-        let draft = CauseDraft::new(
+        let mut draft = CauseDraft::new(
             cause_data.name.clone(),
             cause_data.organization.clone(),
             cause_data.description.clone(),
@@ -85,6 +178,9 @@ impl CauseService {
             cause_data.token_image_url.clone(),
             cause_data.cause_image_url.clone(),
         );
+        draft.owner_address = cause_data.owner_address.clone();
+        draft.country = cause_data.country.to_uppercase();
+        draft.business_type = cause_data.business_type;
         
         let draft_id = self.mongodb_service.create_draft(draft.clone())
             .await
@@ -102,32 +198,25 @@ impl CauseService {
                 }
             })?;
         
+        if let Err(e) = self.send_verification_email(&draft_id, &cause_data.creator_email, &cause_data.name).await {
+            error!("Failed to send verification email for draft {}: {}", draft_id, e);
+        }
+        
         info!("Creating Stripe Connected Account for cause: {} (draft_id: {})", cause_data.name, draft_id);
         
         // Create Stripe Connected Account with draft metadata
-        let account_params = stripe::CreateAccount {
-            type_: Some(stripe::AccountType::Express),
-            country: Some("US"),
-            email: Some(&cause_data.creator_email),
-            capabilities: Some(stripe::CreateAccountCapabilities {
-                card_payments: Some(stripe::CreateAccountCapabilitiesCardPayments {
-                    requested: Some(true),
-                }),
-                transfers: Some(stripe::CreateAccountCapabilitiesTransfers {
-                    requested: Some(true),
-                }),
-                ..Default::default()
-            }),
-            business_type: Some(stripe::AccountBusinessType::Individual),
-            metadata: Some([
+        let account_params = self.connected_account_params(
+            &cause_data.creator_email,
+            &draft.country,
+            draft.business_type,
+            [
                 ("draft_id".to_string(), draft_id.clone()),
                 ("cause_name".to_string(), cause_data.name.clone()),
-            ].into()),
-            ..Default::default()
-        };
+            ].into(),
+        )?;
         
         info!("Calling Stripe API to create account...");
-        let account = match stripe::Account::create(&self.stripe_client, account_params).await {
+        let account = match self.stripe.create_account(account_params).await {
             Ok(acc) => {
                 info!("Successfully created Stripe account with ID: {}", acc.id);
                 acc
@@ -174,7 +263,7 @@ impl CauseService {
             expand: &[],
         };
         
-        let link = stripe::AccountLink::create(&self.stripe_client, link_params)
+        let link = self.stripe.create_account_link(link_params)
             .await
             .map_err(|e| ApiError::StripeError(e.to_string()))?;
         
@@ -211,25 +300,28 @@ impl CauseService {
             return Err(ApiError::ValidationError("Draft marked as completed but no cause ID found".to_string()));
         }
             
+        if !draft.email_verified {
+            return Err(ApiError::ValidationError("Creator email has not been verified".to_string()));
+        }
+            
         // Verify Stripe account is active
         let account_id = draft.stripe_account_id
             .ok_or_else(|| ApiError::ValidationError("No Stripe account associated with draft".to_string()))?;
             
-        let account = stripe::Account::retrieve(
-            &self.stripe_client, 
-            &stripe::AccountId::from_str(&account_id).map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?,
-            &[]
+        let account = self.stripe.retrieve_account(
+            &stripe::AccountId::from_str(&account_id).map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?
         ).await
         .map_err(|e| ApiError::StripeError(e.to_string()))?;
         
-        if !account.charges_enabled.unwrap_or(false) || !account.details_submitted.unwrap_or(false) {
+        if !self.onboarded(&account) {
             return Err(ApiError::ValidationError("Stripe account onboarding not complete".to_string()));
         }
         
-        // Log payouts status for monitoring
-        let payouts_enabled = account.payouts_enabled.unwrap_or(false);
-        if !payouts_enabled {
-            log::warn!("Account {} has charges_enabled but payouts_enabled is false", account_id);
+        // Onboarding webhooks can repeat; resume the cause already started for this draft
+        if let Some(cause_id_str) = &draft.cause_id {
+            let cause_id = ObjectId::parse_str(cause_id_str)
+                .map_err(|_| ApiError::ValidationError("Invalid cause ID in draft".to_string()))?;
+            return self.resume_cause_creation(&cause_id).await;
         }
         
         // Create the actual cause using the full flow
@@ -243,121 +335,518 @@ impl CauseService {
             token_symbol: draft.token_symbol.clone(),
             token_image_url: draft.token_image_url.clone(),
             cause_image_url: draft.cause_image_url.clone(),
+            country: draft.country.clone(),
+            business_type: draft.business_type,
+            owner_address: draft.owner_address.clone(),
+            organization_id: None,
         };
         
-        let mut cause = self.create_cause_full(cause_request, Some(account_id)).await?;
-        
-        // Update cause with payouts_enabled status and onboarding completion
-        cause.payouts_enabled = payouts_enabled;
-        cause.onboarding_completed = true;
-        
-        // Update the cause in the database with these fields
-        if let Some(cause_id) = &cause.id {
-            let filter = mongodb::bson::doc! { "_id": cause_id };
-            let update = mongodb::bson::doc! { 
-                "$set": { 
-                    "payouts_enabled": payouts_enabled,
-                    "onboarding_completed": true,
-                    "updated_at": mongodb::bson::DateTime::from_chrono(chrono::Utc::now())
-                } 
-            };
-            self.mongodb_service.get_causes_collection()
-                .update_one(filter, update, None)
-                .await
-                .map_err(|e| ApiError::DatabaseError(e))?;
-        }
-        
-        // Update draft to mark it as completed and link to the created cause
-        self.mongodb_service.update_draft(
-            &object_id,
-            mongodb::bson::doc! {
-                "status": mongodb::bson::to_bson(&DraftStatus::Completed).unwrap(),
-                "cause_id": cause.id.as_ref().unwrap().to_string(),
-                "completed_at": mongodb::bson::DateTime::from_chrono(chrono::Utc::now())
-            }
-        ).await.map_err(ApiError::DatabaseError)?;
-            
-        Ok(cause)
+        self.create_cause_full(cause_request, Some(account_id), Some(draft_id.to_string())).await
     }
     
     // Original method renamed - used internally after onboarding
-    async fn create_cause_full(&self, cause_data: CreateCauseRequest, existing_account_id: Option<String>) -> Result<Cause, ApiError> {
+    async fn create_cause_full(&self, cause_data: CreateCauseRequest, existing_account_id: Option<String>, draft_id: Option<String>) -> Result<Cause, ApiError> {
         // Validate and check for duplications
         self.validate_cause_data(&cause_data).await?;
         
-        let cause = self.create_pending_cause(&cause_data).await?;
+        let cause = self.create_pending_cause(&cause_data, existing_account_id, draft_id.clone()).await?;
         let cause_id = cause.id.unwrap();
         
-        // Use existing account or create new one
-        let account_id = if let Some(existing_id) = existing_account_id {
-            // Update cause with existing account ID
-            self.update_cause_account_id(&cause_id, &existing_id).await?;
-            existing_id
-        } else {
-            // Create Connected Account for the cause creator
-            let new_account_id = self.create_connected_account(&cause).await?;
-            
-            // Update cause with account ID
-            self.update_cause_account_id(&cause_id, &new_account_id).await?;
-            new_account_id
+        // Link the draft right away so a repeated onboarding webhook resumes this cause
+        if let Some(draft_id) = &draft_id {
+            if let Ok(draft_object_id) = ObjectId::parse_str(draft_id) {
+                self.mongodb_service.update_draft(
+                    &draft_object_id,
+                    mongodb::bson::doc! { "cause_id": cause_id.to_string() }
+                ).await.map_err(ApiError::DatabaseError)?;
+            }
+        }
+        
+        if !self.mongodb_service.claim_cause_creation(&cause_id, chrono::Utc::now().timestamp(), CREATION_LOCK_SECS)
+            .await
+            .map_err(ApiError::DatabaseError)? {
+            return Err(ApiError::InternalError("Could not lock new cause for creation".to_string()));
+        }
+        self.run_creation_saga(&cause_id).await
+    }
+    
+    /// Resume a failed or stuck cause creation from the step that failed
+    pub async fn resume_cause_creation(&self, cause_id: &ObjectId) -> Result<Cause, ApiError> {
+        match self.claim_cause_resume(cause_id).await? {
+            Some(cause) => Ok(cause),
+            None => self.run_creation_saga(cause_id).await,
+        }
+    }
+    
+    /// Take the creation lock to resume a cause, so the remaining steps can run later through
+    /// `continue_cause_creation`. Returns the cause instead if it has no steps left.
+    pub async fn claim_cause_resume(&self, cause_id: &ObjectId) -> Result<Option<Cause>, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        if cause.status == CauseStatus::Rejected {
+            return Err(ApiError::ValidationError("Cause was rejected in review".to_string()));
+        }
+        let saga = match &cause.creation {
+            Some(saga) => saga.clone(),
+            None if cause.status == CauseStatus::Active => {
+                return Err(ApiError::ValidationError("Cause is already active".to_string()));
+            },
+            None => {
+                let saga = CreationSaga::infer(&cause);
+                self.mongodb_service.set_cause_creation(cause_id, &saga).await.map_err(ApiError::DatabaseError)?;
+                saga
+            },
         };
+        if saga.step == CreationStep::Done {
+            return Ok(Some(cause));
+        }
         
-        // Create Stripe product on the platform account (not the connected account)
-        let stripe_id = self.create_stripe_product(&cause).await?;
+        if !self.mongodb_service.claim_cause_creation(cause_id, chrono::Utc::now().timestamp(), CREATION_LOCK_SECS)
+            .await
+            .map_err(ApiError::DatabaseError)? {
+            return Err(ApiError::DuplicateError(format!("Creation of cause {} is already in progress", cause_id)));
+        }
+        Ok(None)
+    }
+    
+    /// Run the remaining creation steps of a cause claimed with `claim_cause_resume`
+    pub async fn continue_cause_creation(&self, cause_id: &ObjectId) -> Result<Cause, ApiError> {
+        self.run_creation_saga(cause_id).await
+    }
+    
+    /// Retry every failed cause whose backoff has elapsed
+    pub async fn retry_due_causes(&self) {
+        let due = match self.mongodb_service
+            .get_causes_due_for_retry(chrono::Utc::now().timestamp(), MAX_AUTO_RETRIES)
+            .await {
+            Ok(causes) => causes,
+            Err(e) => {
+                error!("Failed to load causes due for retry: {}", e);
+                return;
+            }
+        };
+        
+        for cause in due {
+            let Some(cause_id) = cause.id else { continue };
+            match self.resume_cause_creation(&cause_id).await {
+                Ok(cause) => info!("Retried creation of cause {} ({}), now {}", cause_id, cause.name, cause.status),
+                Err(e) => error!("Retry of cause {} failed: {}", cause_id, e),
+            }
+        }
+    }
+    
+    /// Drop features whose `featured_until` has passed
+    pub async fn expire_featured_causes(&self) {
+        let expired = match self.mongodb_service.get_expired_featured_causes(chrono::Utc::now().timestamp()).await {
+            Ok(causes) => causes,
+            Err(e) => {
+                error!("Failed to load expired featured causes: {}", e);
+                return;
+            }
+        };
         
-        // Create price for the product
-        let price_id = self.create_product_price(&stripe_id).await?;
+        for cause in expired {
+            let Some(cause_id) = cause.id else { continue };
+            let update = UpdateCauseRequest { featured: Some(false), ..Default::default() };
+            match self.update_cause(&cause_id, update, SYSTEM_ACTOR).await {
+                Ok(_) => info!("Feature of cause {} ({}) expired", cause_id, cause.name),
+                Err(e) => error!("Failed to expire feature of cause {}: {}", cause_id, e),
+            }
+        }
+    }
+    
+    // Run steps until done, persisting progress after each one. The caller must hold the creation lock.
+    async fn run_creation_saga(&self, cause_id: &ObjectId) -> Result<Cause, ApiError> {
+        loop {
+            let cause = self.get_cause_by_id(cause_id).await?;
+            let mut saga = cause.creation.clone().unwrap_or_else(|| CreationSaga::infer(&cause));
+            if saga.step == CreationStep::Done {
+                return Ok(cause);
+            }
+            
+            info!("Cause {} creation step: {}", cause_id, saga.step);
+            match self.run_creation_step(&cause, &mut saga).await {
+                Ok(false) => {
+                    // Paused (awaiting review); release the lock until something resumes it
+                    saga.locked_until = None;
+                    self.mongodb_service.set_cause_creation(cause_id, &saga).await.map_err(ApiError::DatabaseError)?;
+                    return self.get_cause_by_id(cause_id).await;
+                },
+                Ok(true) => {
+                    saga.step = saga.step.next();
+                    saga.last_error = None;
+                    saga.next_retry_at = None;
+                    if saga.step == CreationStep::Done {
+                        saga.locked_until = None;
+                    }
+                    self.mongodb_service.set_cause_creation(cause_id, &saga).await.map_err(ApiError::DatabaseError)?;
+                },
+                Err(e) => {
+                    error!("Cause {} failed at step {}: {}", cause_id, saga.step, e);
+                    saga.attempts += 1;
+                    saga.last_error = Some(e.to_string());
+                    saga.next_retry_at = Some(chrono::Utc::now().timestamp() + backoff_secs(saga.attempts));
+                    saga.locked_until = None;
+                    if let Err(db_err) = self.mongodb_service.fail_cause_creation(cause_id, &saga, &e.to_string()).await {
+                        error!("Failed to record creation failure for cause {}: {}", cause_id, db_err);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+    
+    // Each step checks what is already on the cause so a resumed step never repeats external work.
+    // Returns false when the saga has to wait instead of moving to the next step.
+    async fn run_creation_step(&self, cause: &Cause, saga: &mut CreationSaga) -> Result<bool, ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        
+        match saga.step {
+            CreationStep::ConnectAccount => {
+                if cause.stripe_account_id.is_none() {
+                    let account_id = self.create_connected_account(cause).await?;
+                    self.update_cause_account_id(&cause_id, &account_id).await?;
+                }
+            },
+            CreationStep::StripeProduct => {
+                // Create Stripe product on the platform account (not the connected account)
+                if cause.stripe_product_id.is_none() {
+                    let stripe_id = self.create_stripe_product(cause).await?;
+                    // Skip payment link creation - we use checkout sessions now
+                    self.update_cause_stripe_id(&cause_id, &stripe_id, "").await?;
+                }
+            },
+            CreationStep::StripePrice => {
+                if saga.stripe_price_id.is_none() {
+                    let stripe_id = cause.stripe_product_id.as_deref()
+                        .ok_or_else(|| ApiError::InternalError("Cause has no Stripe product".to_string()))?;
+                    saga.stripe_price_id = Some(self.create_product_price(stripe_id).await?);
+                }
+            },
+            CreationStep::Review => {
+                // Nothing is minted or displayed until a moderator approves the cause
+                let approved = cause.review.as_ref().map_or(false, |r| r.decision == ReviewDecision::Approved);
+                if !approved {
+                    if cause.status != CauseStatus::PendingReview {
+                        self.update_cause_status(&cause_id, CauseStatus::PendingReview, None).await?;
+                        info!("Cause {} ({}) is awaiting review", cause_id, cause.name);
+                    }
+                    return Ok(false);
+                }
+            },
+            CreationStep::MintToken => {
+                if cause.token_id.is_none() {
+                    self.mint_token_for_cause(cause).await?;
+                }
+            },
+            CreationStep::Finalize => {
+                self.finalize_cause(cause, saga.draft_id.as_deref()).await?;
+            },
+            CreationStep::Done => {},
+        }
+        Ok(true)
+    }
+    
+    /// Causes waiting for a moderator, oldest first
+    pub async fn get_review_queue(&self) -> Result<Vec<Cause>, ApiError> {
+        self.mongodb_service.get_causes_by_status(CauseStatus::PendingReview).await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Cause counts by status, drafts waiting on Stripe for more than `stuck_hours`, and
+    /// failed causes with their errors
+    pub async fn lifecycle_dashboard(&self, stuck_hours: i64) -> Result<CauseDashboard, ApiError> {
+        let now = chrono::Utc::now();
+        let status_counts = self.mongodb_service.count_causes_by_status().await
+            .map_err(ApiError::DatabaseError)?;
+        let stuck_drafts = self.mongodb_service.get_stripe_pending_drafts(now - chrono::Duration::hours(stuck_hours)).await
+            .map_err(ApiError::DatabaseError)?
+            .into_iter()
+            .map(|draft| StuckDraft {
+                id: draft.id.map(|id| id.to_hex()).unwrap_or_default(),
+                hours_waiting: (now - draft.created_at).num_hours(),
+                name: draft.name,
+                token_symbol: draft.token_symbol,
+                creator_email: draft.creator_email,
+                stripe_account_id: draft.stripe_account_id,
+                created_at: draft.created_at,
+            })
+            .collect();
+        let failed_causes = self.mongodb_service.get_causes_by_status(CauseStatus::Failed).await
+            .map_err(ApiError::DatabaseError)?
+            .into_iter()
+            .map(FailedCause::from)
+            .collect();
+        Ok(CauseDashboard { status_counts, stuck_drafts, failed_causes })
+    }
+    
+    /// Apply one admin action to each cause, reporting each outcome on its own so one bad
+    /// ID doesn't stop the rest
+    pub async fn bulk_action(&self, action: BulkCauseAction, cause_ids: &[String], actor: &str, progress: Option<&JobProgress>) -> Vec<BulkCauseResult> {
+        let mut results = Vec::with_capacity(cause_ids.len());
+        for cause_id in cause_ids {
+            if let Some(progress) = progress {
+                progress.report(results.len(), cause_ids.len()).await;
+            }
+            let outcome = match ObjectId::parse_str(cause_id) {
+                Ok(object_id) => self.apply_bulk_action(action, &object_id, actor).await,
+                Err(e) => Err(ApiError::ValidationError(format!("Invalid cause ID: {}", e))),
+            };
+            if let Err(e) = &outcome {
+                error!("Bulk {:?} of cause {} failed: {}", action, cause_id, e);
+            }
+            results.push(BulkCauseResult {
+                cause_id: cause_id.clone(),
+                ok: outcome.is_ok(),
+                error: outcome.err().map(|e| e.to_string()),
+            });
+        }
+        if let Some(progress) = progress {
+            progress.report(results.len(), cause_ids.len()).await;
+        }
+        results
+    }
+    
+    async fn apply_bulk_action(&self, action: BulkCauseAction, cause_id: &ObjectId, actor: &str) -> Result<(), ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        let update = match action {
+            BulkCauseAction::Retry => return self.resume_cause_creation(cause_id).await.map(|_| ()),
+            BulkCauseAction::Hide => UpdateCauseRequest { displayed: Some(false), ..Default::default() },
+            BulkCauseAction::Show if cause.status == CauseStatus::Archived => {
+                return Err(ApiError::ValidationError("Archived causes can't be shown".to_string()));
+            },
+            BulkCauseAction::Show => UpdateCauseRequest { displayed: Some(true), ..Default::default() },
+            BulkCauseAction::Feature if cause.status != CauseStatus::Active => {
+                return Err(ApiError::ValidationError(format!("Cause is {}, only active causes can be featured", cause.status)));
+            },
+            BulkCauseAction::Feature => UpdateCauseRequest { featured: Some(true), ..Default::default() },
+            BulkCauseAction::Unfeature => UpdateCauseRequest { featured: Some(false), ..Default::default() },
+        };
+        // A cause that already had the value (already hidden, already featured) counts as done
+        self.update_cause(cause_id, update, actor).await.map(|_| ())
+    }
+    
+    /// Approve a cause in review and continue its creation (token mint, then go live)
+    pub async fn approve_cause(&self, cause_id: &ObjectId, actor: &str) -> Result<Cause, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        if cause.status != CauseStatus::PendingReview {
+            return Err(ApiError::ValidationError(format!("Cause is {}, not pending review", cause.status)));
+        }
         
-        // Skip payment link creation - we use checkout sessions now
-        let payment_link = ""; // Empty since we use checkout sessions
+        let review = CauseReview {
+            decision: ReviewDecision::Approved,
+            reason: None,
+            reviewed_by: actor.to_string(),
+            reviewed_at: chrono::Utc::now().timestamp(),
+        };
+        self.mongodb_service.set_cause_review(cause_id, &review, None).await
+            .map_err(ApiError::DatabaseError)?;
+        self.record_review(&cause, &review).await;
+        self.notify_review_decision(&cause, &review).await;
         
-        // Update cause with Stripe ID and status
-        self.update_cause_stripe_id(&cause_id, &stripe_id, &payment_link).await?;
+        self.resume_cause_creation(cause_id).await
+    }
+    
+    /// Reject a cause in review; it is never minted or displayed
+    pub async fn reject_cause(&self, cause_id: &ObjectId, actor: &str, reason: &str) -> Result<Cause, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        if cause.status != CauseStatus::PendingReview {
+            return Err(ApiError::ValidationError(format!("Cause is {}, not pending review", cause.status)));
+        }
         
- 
-        let updated_cause = self.get_cause_by_id(&cause_id).await?;
+        let review = CauseReview {
+            decision: ReviewDecision::Rejected,
+            reason: Some(reason.to_string()),
+            reviewed_by: actor.to_string(),
+            reviewed_at: chrono::Utc::now().timestamp(),
+        };
+        self.mongodb_service.set_cause_review(cause_id, &review, Some(CauseStatus::Rejected)).await
+            .map_err(ApiError::DatabaseError)?;
+        self.record_review(&cause, &review).await;
+        self.notify_review_decision(&cause, &review).await;
         
-        // Mint token
-        match self.mint_token_for_cause(&updated_cause).await {
-            Ok(token_id) => {
-                // Update cause with token ID and set to ACTIVE
-                self.finalize_cause(&cause_id, &token_id).await?;
-            },
-            Err(e) => {
-                // Cannot roll back token, but update status to FAILED
-                let _ = self.update_cause_status(&cause_id, CauseStatus::Failed, Some(e.to_string())).await;
-                return Err(e);
+        self.get_cause_by_id(cause_id).await
+    }
+    
+    /// Feature an active cause, optionally at `rank` and until `until` (unix seconds)
+    pub async fn feature_cause(&self, cause_id: &ObjectId, rank: Option<i32>, until: Option<i64>, actor: &str) -> Result<Cause, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        if cause.status != CauseStatus::Active {
+            return Err(ApiError::ValidationError(format!("Cause is {}, only active causes can be featured", cause.status)));
+        }
+        if until.is_some_and(|until| until <= chrono::Utc::now().timestamp()) {
+            return Err(ApiError::ValidationError("featured_until must be in the future".to_string()));
+        }
+        let update = UpdateCauseRequest {
+            featured: Some(true),
+            featured_rank: rank,
+            featured_until: until,
+            ..Default::default()
+        };
+        self.update_cause(cause_id, update, actor).await?;
+        self.get_cause_by_id(cause_id).await
+    }
+    
+    /// Rank featured causes in the given order, first shown first. Every cause must already
+    /// be featured; ones left out keep their rank.
+    pub async fn reorder_featured_causes(&self, cause_ids: &[ObjectId], actor: &str) -> Result<Vec<Cause>, ApiError> {
+        for cause_id in cause_ids {
+            let cause = self.get_cause_by_id(cause_id).await?;
+            if !cause.featured {
+                return Err(ApiError::ValidationError(format!("Cause {} is not featured", cause_id)));
             }
         }
-        
-        self.get_cause_by_id(&cause_id).await
+        for (rank, cause_id) in cause_ids.iter().enumerate() {
+            let update = UpdateCauseRequest { featured_rank: Some(rank as i32 + 1), ..Default::default() };
+            self.update_cause(cause_id, update, actor).await?;
+        }
+        self.get_featured_causes().await
+    }
+    
+    /// Stop an active cause taking donations; its token can still be spent
+    pub async fn suspend_cause(&self, cause_id: &ObjectId, actor: &str) -> Result<Cause, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        if cause.status != CauseStatus::Active {
+            return Err(ApiError::ValidationError(format!("Cause is {}, only active causes can be suspended", cause.status)));
+        }
+        let update = UpdateCauseRequest { status: Some(CauseStatus::Suspended), ..Default::default() };
+        self.update_cause(cause_id, update, actor).await?;
+        self.get_cause_by_id(cause_id).await
+    }
+    
+    /// Take donations for a suspended cause again
+    pub async fn reinstate_cause(&self, cause_id: &ObjectId, actor: &str) -> Result<Cause, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        if cause.status != CauseStatus::Suspended {
+            return Err(ApiError::ValidationError(format!("Cause is {}, not suspended", cause.status)));
+        }
+        let update = UpdateCauseRequest { status: Some(CauseStatus::Active), ..Default::default() };
+        self.update_cause(cause_id, update, actor).await?;
+        self.get_cause_by_id(cause_id).await
+    }
+    
+    /// Retire a cause: it is unlisted and unfeatured, but its donations and payments stay
+    /// in history. Causes still being created must finish or fail first.
+    pub async fn archive_cause(&self, cause_id: &ObjectId, actor: &str) -> Result<Cause, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        match cause.status {
+            CauseStatus::Active | CauseStatus::Suspended | CauseStatus::Failed | CauseStatus::Rejected => {},
+            _ => return Err(ApiError::ValidationError(format!("Cause is {} and can't be archived", cause.status))),
+        }
+        let update = UpdateCauseRequest {
+            status: Some(CauseStatus::Archived),
+            displayed: Some(false),
+            featured: Some(false),
+            ..Default::default()
+        };
+        self.update_cause(cause_id, update, actor).await?;
+        self.get_cause_by_id(cause_id).await
+    }
+    
+    async fn record_review(&self, cause: &Cause, review: &CauseReview) {
+        let cause_id = cause.id.map(|id| id.to_hex()).unwrap_or_default();
+        self.record_audit(AuditLog::new(
+            &review.reviewed_by,
+            AuditAction::CauseReviewed,
+            "cause",
+            &cause_id,
+            None,
+            snapshot(review),
+        )).await;
+    }
+    
+    // Email failures are logged; the decision itself is already stored
+    async fn notify_review_decision(&self, cause: &Cause, review: &CauseReview) {
+        let (subject, text) = match review.decision {
+            ReviewDecision::Approved => (
+                format!("Your cause \"{}\" was approved", cause.name),
+                format!("Good news! \"{}\" passed review. Its {} token is being created and the cause will go live shortly.\n", cause.name, cause.token_symbol),
+            ),
+            ReviewDecision::Rejected => (
+                format!("Your cause \"{}\" was not approved", cause.name),
+                format!(
+                    "\"{}\" did not pass review.\n\nReason: {}\n\nReply to this email if you have questions.\n",
+                    cause.name,
+                    review.reason.as_deref().unwrap_or("not given"),
+                ),
+            ),
+        };
+        if let Err(e) = self.email_service.send(&cause.creator_email, &subject, &text).await {
+            error!("Failed to notify creator of cause {:?} about review: {}", cause.id, e);
+        }
     }
 
     // Helper methods
-    async fn finalize_cause(&self, cause_id: &ObjectId, token_id: &str) -> Result<(), ApiError> {
-        // Update cause with token ID and set to ACTIVE
-        self.update_cause_status(cause_id, CauseStatus::Active, None).await?;
+    async fn finalize_cause(&self, cause: &Cause, draft_id: Option<&str>) -> Result<(), ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        
+        let mut fields = mongodb::bson::doc! {
+            "status": CauseStatus::Active.to_string(),
+            "displayed": true,
+            "error_message": mongodb::bson::Bson::Null,
+            "updated_at": mongodb::bson::DateTime::from_chrono(chrono::Utc::now()),
+        };
+        
+        // Causes from drafts finished Stripe onboarding before creation started
+        if let Some(draft_id) = draft_id {
+            let payouts_enabled = match &cause.stripe_account_id {
+                Some(account_id) => self.fetch_payouts_enabled(account_id).await?,
+                None => false,
+            };
+            if !payouts_enabled {
+                log::warn!("Account {:?} has charges_enabled but payouts_enabled is false", cause.stripe_account_id);
+            }
+            fields.insert("payouts_enabled", payouts_enabled);
+            fields.insert("onboarding_completed", true);
+            
+            // Update draft to mark it as completed and link to the created cause
+            let draft_object_id = ObjectId::parse_str(draft_id)
+                .map_err(|_| ApiError::ValidationError("Invalid draft ID".to_string()))?;
+            self.mongodb_service.update_draft(
+                &draft_object_id,
+                mongodb::bson::doc! {
+                    "status": mongodb::bson::to_bson(&DraftStatus::Completed).unwrap(),
+                    "cause_id": cause_id.to_string(),
+                    "completed_at": mongodb::bson::DateTime::from_chrono(chrono::Utc::now())
+                }
+            ).await.map_err(ApiError::DatabaseError)?;
+        }
+        
+        self.mongodb_service.get_causes_collection()
+            .update_one(mongodb::bson::doc! { "_id": cause_id }, mongodb::bson::doc! { "$set": fields }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        if let Some(owner_address) = &cause.owner_address {
+            if let Err(e) = self.mongodb_service.add_user_role(owner_address, Role::CauseOwner).await {
+                error!("Failed to grant cause_owner role to {}: {:?}", owner_address, e);
+            }
+        }
         Ok(())
     }
     
+    async fn fetch_payouts_enabled(&self, account_id: &str) -> Result<bool, ApiError> {
+        let account = self.stripe.retrieve_account(
+            &stripe::AccountId::from_str(account_id).map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?
+        ).await
+        .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        Ok(account.payouts_enabled.unwrap_or(false))
+    }
+    
     async fn validate_cause_data(&self, cause_data: &CreateCauseRequest) -> Result<(), ApiError> {
-        // Basic field validation only - uniqueness is handled by database constraints
-        
-        // Validate token symbol format (typically 3-5 uppercase letters)
-        let symbol = cause_data.token_symbol.trim().to_uppercase();
-        if symbol.len() < 2 || symbol.len() > 5 || !symbol.chars().all(|c| c.is_ascii_uppercase()) {
-            return Err(ApiError::ValidationError("Token symbol must be 2-5 uppercase letters".to_string()));
+        // Field validation only - uniqueness is handled by database constraints
+        let mut errors = cause_data.validate().err().unwrap_or_default();
+        for (field, value) in [("name", &cause_data.name), ("token_name", &cause_data.token_name), ("token_symbol", &cause_data.token_symbol)] {
+            errors.check(field, self.name_filter.check(value));
         }
-        
-        // Validate email format (basic check)
-        if !cause_data.creator_email.contains('@') {
-            return Err(ApiError::ValidationError("Invalid email format".to_string()));
+        if self.connect.service_agreement(&cause_data.country).is_none() {
+            errors.add("country", format!("Not supported yet; causes can be onboarded in {}", self.connect.countries().join(", ")));
         }
-        
-        Ok(())
+        errors.into_result().map_err(ApiError::from)
     }
     
-    async fn create_pending_cause(&self, cause_data: &CreateCauseRequest) -> Result<Cause, ApiError> {
+    async fn create_pending_cause(&self, cause_data: &CreateCauseRequest, existing_account_id: Option<String>, draft_id: Option<String>) -> Result<Cause, ApiError> {
         // Create a new cause with PENDING status
         let mut cause = Cause::new(
             cause_data.name.clone(),
@@ -371,6 +860,16 @@ impl CauseService {
             cause_data.cause_image_url.clone(),
         );
         cause.status = CauseStatus::Pending;
+        cause.owner_address = cause_data.owner_address.clone();
+        cause.organization_id = cause_data.organization_id.clone();
+        cause.country = cause_data.country.to_uppercase();
+        cause.business_type = cause_data.business_type;
+        if existing_account_id.is_some() {
+            cause.stripe_account_id = existing_account_id;
+            cause.stripe_account_status = Some("pending".to_string());
+        }
+        cause.creation = Some(CreationSaga::new(draft_id));
+        cause.displayed = false;  // shown once approved and live
 
         // Insert into MongoDB
         let id = self.mongodb_service.create_cause(cause.clone()).await
@@ -388,28 +887,17 @@ impl CauseService {
     async fn create_connected_account(&self, cause: &Cause) -> Result<String, ApiError> {
         // Creating Stripe Connected Account
         
-        let account_params = stripe::CreateAccount {
-            type_: Some(stripe::AccountType::Express),
-            country: Some("US"),
-            email: Some(&cause.creator_email),
-            capabilities: Some(stripe::CreateAccountCapabilities {
-                card_payments: Some(stripe::CreateAccountCapabilitiesCardPayments {
-                    requested: Some(true),
-                }),
-                transfers: Some(stripe::CreateAccountCapabilitiesTransfers {
-                    requested: Some(true),
-                }),
-                ..Default::default()
-            }),
-            business_type: Some(stripe::AccountBusinessType::Individual),
-            metadata: Some([
+        let account_params = self.connected_account_params(
+            &cause.creator_email,
+            &cause.country,
+            cause.business_type,
+            [
                 ("cause_id".to_string(), cause.id.unwrap().to_string()),
                 ("cause_name".to_string(), cause.name.clone()),
-            ].into()),
-            ..Default::default()
-        };
+            ].into(),
+        )?;
         
-        match stripe::Account::create(&self.stripe_client, account_params).await {
+        match self.stripe.create_account(account_params).await {
             Ok(account) => {
                 // Successfully created Connected Account
                 Ok(account.id.to_string())
@@ -421,6 +909,32 @@ impl CauseService {
         }
     }
 
+    /// The service agreement accounts in `country` are onboarded under, so callers can refuse
+    /// an unsupported country before they persist anything
+    pub fn connect_agreement(&self, country: &str) -> Result<ConnectServiceAgreement, ApiError> {
+        connect_agreement(&self.connect, country)
+    }
+
+    /// Express account parameters in `country` under this deployment's Connect countries
+    pub(crate) fn connected_account_params<'a>(
+        &self,
+        email: &'a str,
+        country: &'a str,
+        business_type: BusinessType,
+        metadata: stripe::Metadata,
+    ) -> Result<stripe::CreateAccount<'a>, ApiError> {
+        connected_account_params(&self.connect, email, country, business_type, metadata)
+    }
+
+    /// Whether a connected account has finished onboarding under the agreement for its
+    /// country. Accounts in a country that's no longer configured must be fully onboarded.
+    pub fn onboarded(&self, account: &stripe::Account) -> bool {
+        let agreement = account.country.as_deref()
+            .and_then(|country| self.connect.service_agreement(country))
+            .unwrap_or(ConnectServiceAgreement::Full);
+        account_onboarded(account, agreement)
+    }
+
     async fn create_stripe_product(&self, cause: &Cause) -> Result<String, ApiError> {
         // Creating Stripe product
 
@@ -447,7 +961,7 @@ impl CauseService {
             type_: None,
         };
 
-        match stripe::Product::create(&self.stripe_client, product_create_params).await {
+        match self.stripe.create_product(product_create_params).await {
             Ok(product) => {
                 // Successfully created Stripe product
                 Ok(product.id.to_string())
@@ -489,7 +1003,7 @@ impl CauseService {
             unit_amount_decimal: None,
         };
 
-        match stripe::Price::create(&self.stripe_client, price_create_params).await {
+        match self.stripe.create_price(price_create_params).await {
             Ok(price) => {
                 // Successfully created Stripe price
                 Ok(price.id.to_string())
@@ -518,6 +1032,8 @@ impl CauseService {
             cause_image_url: None,
             displayed: None,
             featured: None,
+            featured_rank: None,
+            featured_until: None,
         };
         
         self.mongodb_service.update_cause(cause_id, update)
@@ -544,13 +1060,332 @@ impl CauseService {
             stripe_account_status: None,
             displayed: None,
             featured: None,
+            featured_rank: None,
+            featured_until: None,
+        };
+        
+        self.mongodb_service.update_cause(cause_id, update)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        Ok(())
+    }
+    
+    /// Connected account for an organization. Its causes are all paid out to this account,
+    /// so the organization only onboards once.
+    pub async fn create_organization_account(&self, organization: &Organization) -> Result<String, ApiError> {
+        let organization_id = organization.id
+            .ok_or_else(|| ApiError::InternalError("Organization has no ID".to_string()))?;
+        let account_params = self.connected_account_params(
+            &organization.contact_email,
+            &organization.country,
+            organization.business_type,
+            [
+                ("organization_id".to_string(), organization_id.to_hex()),
+                ("organization_name".to_string(), organization.name.clone()),
+            ].into(),
+        )?;
+        
+        let account = self.stripe.create_account(account_params).await
+            .map_err(|e| {
+                error!("Failed to create Connected Account for organization {}: {}", organization_id, e);
+                ApiError::StripeError(format!("Stripe account creation failed: {}", e))
+            })?;
+        info!("Created Stripe account {} for organization {}", account.id, organization.name);
+        Ok(account.id.to_string())
+    }
+    
+    pub async fn create_organization_account_link(&self, organization: &Organization) -> Result<String, ApiError> {
+        let organization_id = organization.id
+            .ok_or_else(|| ApiError::InternalError("Organization has no ID".to_string()))?;
+        let account_id = organization.stripe_account_id.as_deref()
+            .ok_or_else(|| ApiError::ValidationError("No Stripe account associated with this organization".to_string()))?;
+        let account_id_obj = stripe::AccountId::from_str(account_id)
+            .map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?;
+        
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let refresh_url = format!("{}/organizations/{}/onboarding/refresh", frontend_url, organization_id);
+        let return_url = format!("{}/organizations/{}/onboarding/complete", frontend_url, organization_id);
+        
+        let link_params = stripe::CreateAccountLink {
+            account: account_id_obj,
+            refresh_url: Some(&refresh_url),
+            return_url: Some(&return_url),
+            type_: stripe::AccountLinkType::AccountOnboarding,
+            collect: None,
+            collection_options: None,
+            expand: &[],
         };
         
-        self.mongodb_service.update_cause(cause_id, update)
-            .await
-            .map_err(ApiError::DatabaseError)?;
+        let link = self.stripe.create_account_link(link_params).await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        Ok(link.url)
+    }
+    
+    /// Create a cause paid out to an onboarded organization's account. There's no Stripe
+    /// onboarding to wait for, so creation runs right away and pauses for review as usual.
+    pub async fn create_organization_cause(&self, organization: &Organization, mut cause_data: CreateCauseRequest) -> Result<Cause, ApiError> {
+        let organization_id = organization.id
+            .ok_or_else(|| ApiError::InternalError("Organization has no ID".to_string()))?;
+        let account_id = match (&organization.stripe_account_id, organization.onboarding_completed) {
+            (Some(account_id), true) => account_id.clone(),
+            _ => return Err(ApiError::Conflict("The organization hasn't finished Stripe onboarding yet".to_string())),
+        };
+        cause_data.creator_email = organization.contact_email.clone();
+        cause_data.country = organization.country.clone();
+        cause_data.business_type = organization.business_type;
+        cause_data.organization_id = Some(organization_id.to_hex());
+        self.validate_cause_data(&cause_data).await?;
+        
+        // A completed draft reserves the cause's names, like any other cause
+        let mut draft = CauseDraft::new(
+            cause_data.name.clone(),
+            cause_data.organization.clone(),
+            cause_data.description.clone(),
+            cause_data.long_description.clone(),
+            cause_data.creator_email.clone(),
+            cause_data.token_name.clone(),
+            cause_data.token_symbol.clone(),
+            cause_data.token_image_url.clone(),
+            cause_data.cause_image_url.clone(),
+        );
+        draft.owner_address = cause_data.owner_address.clone();
+        draft.country = cause_data.country.clone();
+        draft.business_type = cause_data.business_type;
+        draft.stripe_account_id = Some(account_id.clone());
+        draft.email_verified = true;  // the organization admin is signed in
+        let draft_id = self.mongodb_service.create_draft(draft)
+            .await
+            .map_err(|e| {
+                let error_msg = e.to_string();
+                if error_msg.contains("DUPLICATE_NAME:") {
+                    ApiError::DuplicateError("A cause with this name already exists".to_string())
+                } else if error_msg.contains("DUPLICATE_TOKEN_NAME:") {
+                    ApiError::DuplicateError("A cause with this token name already exists".to_string())
+                } else if error_msg.contains("DUPLICATE_TOKEN_SYMBOL:") {
+                    ApiError::DuplicateError("A cause with this token symbol already exists".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        
+        info!("Creating cause {} for organization {} on account {}", cause_data.name, organization.name, account_id);
+        self.create_cause_full(cause_data, Some(account_id), Some(draft_id)).await
+    }
+    
+    /// Whether the wallet administers the organization that owns the cause
+    pub async fn is_organization_admin(&self, cause: &Cause, wallet_address: &str) -> Result<bool, ApiError> {
+        let Some(organization_id) = cause.organization_id.as_deref() else {
+            return Ok(false);
+        };
+        let Ok(organization_id) = ObjectId::parse_str(organization_id) else {
+            return Ok(false);
+        };
+        Ok(self.mongodb_service.get_organization(&organization_id).await?
+            .map_or(false, |organization| organization.is_admin(wallet_address)))
+    }
+    
+    /// The caller's role on the cause: owners and their organization's admins are owners,
+    /// and team members have the role they were invited with once they've accepted
+    pub async fn team_role(&self, cause: &Cause, wallet_address: &str) -> Result<Option<TeamRole>, ApiError> {
+        if cause.owner_address.as_deref() == Some(wallet_address) || self.is_organization_admin(cause, wallet_address).await? {
+            return Ok(Some(TeamRole::Owner));
+        }
+        Ok(cause.team.iter()
+            .find(|member| member.wallet_address.as_deref() == Some(wallet_address))
+            .map(|member| member.role))
+    }
+    
+    /// Invite someone to the cause's team by email. Inviting someone who hasn't accepted yet
+    /// sends the invite again.
+    pub async fn invite_team_member(&self, cause: &Cause, request: &InviteTeamMemberRequest, actor: &str) -> Result<TeamMember, ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        let email = request.email.trim().to_lowercase();
+        let member = TeamMember {
+            email: email.clone(),
+            role: request.role,
+            wallet_address: None,
+            invited_by: actor.to_string(),
+            invited_at: chrono::Utc::now().timestamp(),
+            accepted_at: None,
+        };
+        let member = if self.mongodb_service.add_team_member(&cause_id, &member).await? {
+            self.record_audit(AuditLog::new(actor, AuditAction::CauseTeamChanged, "cause", &cause_id.to_hex(), None, snapshot(&member))).await;
+            member
+        } else {
+            match cause.team.iter().find(|existing| existing.email == email) {
+                Some(existing) if existing.accepted_at.is_none() => existing.clone(),
+                _ => return Err(ApiError::Conflict(format!("{} is already on this cause's team; change their role instead", email))),
+            }
+        };
+        
+        if let Err(e) = self.send_team_invite(cause, &member).await {
+            error!("Failed to send team invite for cause {} to {}: {}", cause_id, redact(&email), e);
+            return Err(ApiError::InternalError("The invite was saved but its email could not be sent; invite them again to retry".to_string()));
+        }
+        info!("{} invited {} to cause {} as {}", redact(actor), redact(&email), cause_id, member.role);
+        Ok(member)
+    }
+    
+    async fn send_team_invite(&self, cause: &Cause, member: &TeamMember) -> Result<(), String> {
+        let cause_id = cause.id.map(|id| id.to_hex()).unwrap_or_default();
+        let expires_at = chrono::Utc::now().timestamp() + VERIFICATION_TTL_SECS;
+        let token = sign_verification_token(&self.email_verification_secret, &team_invite_scope(&cause_id), &member.email, expires_at);
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let link = reqwest::Url::parse_with_params(
+            &format!("{}/causes/{}/team/accept", frontend_url, cause_id),
+            &[("email", member.email.as_str()), ("token", token.as_str())],
+        ).map_err(|e| format!("Invalid FRONTEND_URL: {}", e))?;
+        let text = format!(
+            "You've been invited to help run \"{}\" as {}.\n\nOpen this link in the app and sign in with your wallet to join:\n\n{}\n\nThe link is valid for 48 hours.\n",
+            cause.name, member.role, link
+        );
+        self.email_service.send(&member.email, &format!("You're invited to {}", cause.name), &text).await
+    }
+    
+    /// Join the team with the signed-in wallet, using the link from the invite email
+    pub async fn accept_team_invite(&self, cause: &Cause, email: &str, token: &str, wallet_address: &str) -> Result<TeamMember, ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        let email = email.trim().to_lowercase();
+        verify_verification_token(&self.email_verification_secret, &team_invite_scope(&cause_id.to_hex()), &email, token, chrono::Utc::now().timestamp())
+            .map_err(ApiError::ValidationError)?;
+        if !self.mongodb_service.accept_team_invite(&cause_id, &email, wallet_address).await? {
+            return Err(ApiError::Conflict("This invite was withdrawn or already accepted by another wallet".to_string()));
+        }
+        info!("{} joined the team of cause {} as {}", redact(wallet_address), cause_id, redact(&email));
+        let cause = self.get_cause_by_id(&cause_id).await?;
+        cause.team.into_iter()
+            .find(|member| member.email == email)
+            .ok_or_else(|| ApiError::NotFound("Team member not found".to_string()))
+    }
+    
+    pub async fn set_team_member_role(&self, cause: &Cause, email: &str, role: TeamRole, actor: &str) -> Result<(), ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        let email = email.trim().to_lowercase();
+        if !self.mongodb_service.set_team_member_role(&cause_id, &email, role).await? {
+            return Err(ApiError::NotFound(format!("{} is not on this cause's team", email)));
+        }
+        let before = cause.team.iter().find(|member| member.email == email);
+        let after = before.map(|member| TeamMember { role, ..member.clone() });
+        self.record_audit(AuditLog::new(actor, AuditAction::CauseTeamChanged, "cause", &cause_id.to_hex(), before.and_then(snapshot), after.as_ref().and_then(snapshot))).await;
+        Ok(())
+    }
+    
+    pub async fn remove_team_member(&self, cause: &Cause, email: &str, actor: &str) -> Result<(), ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        let email = email.trim().to_lowercase();
+        if !self.mongodb_service.remove_team_member(&cause_id, &email).await? {
+            return Err(ApiError::NotFound(format!("{} is not on this cause's team", email)));
+        }
+        let before = cause.team.iter().find(|member| member.email == email);
+        self.record_audit(AuditLog::new(actor, AuditAction::CauseTeamChanged, "cause", &cause_id.to_hex(), before.and_then(snapshot), None)).await;
+        Ok(())
+    }
+    
+    /// Causes the wallet has joined the team of
+    pub async fn get_team_causes(&self, wallet_address: &str) -> Result<Vec<Cause>, ApiError> {
+        self.mongodb_service.get_team_causes(wallet_address).await
+    }
+    
+    /// Pay out part of what the cause raised to its bank account now, instead of waiting for
+    /// the account's payout schedule. Causes sharing an organization's account can only pay
+    /// out their own donations, and a retry with the same idempotency key pays out once.
+    pub async fn create_payout(&self, cause: &Cause, request: &CausePayoutRequest, actor: &str) -> Result<stripe::Payout, ApiError> {
+        let cause_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        let stripe_account_id = cause.stripe_account_id.as_deref()
+            .ok_or_else(|| ApiError::ValidationError("This cause does not have a connected Stripe account".to_string()))?;
+        if !cause.payouts_enabled {
+            return Err(ApiError::ValidationError("Payouts are not enabled on this cause's Stripe account yet".to_string()));
+        }
+        let account_id = stripe::AccountId::from_str(stripe_account_id)
+            .map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?;
+        let currency_code = request.currency.to_lowercase();
+        let currency = stripe::Currency::from_str(&currency_code)
+            .map_err(|_| ApiError::ValidationError(format!("Unsupported currency {}", request.currency)))?;
+        // Donations are counted in USD, so that's all a shared account can pay out by cause
+        let reserved = currency_code == "usd";
+        if !reserved && cause.organization_id.is_some() {
+            return Err(ApiError::ValidationError("Causes on an organization's account can only pay out usd".to_string()));
+        }
+        
+        let idempotency_key = request.idempotency_key.trim();
+        let record = CausePayout {
+            id: None,
+            cause_id: cause_id.to_hex(),
+            idempotency_key: idempotency_key.to_string(),
+            stripe_account_id: stripe_account_id.to_string(),
+            amount_cents: request.amount_cents,
+            currency: currency_code.clone(),
+            requested_by: actor.to_string(),
+            payout_id: None,
+            failure_reason: None,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        let record = if self.mongodb_service.create_cause_payout(&record).await? {
+            if reserved && !self.mongodb_service.reserve_cause_payout(&cause_id, request.amount_cents).await? {
+                self.mongodb_service.delete_cause_payout(&record.cause_id, idempotency_key).await?;
+                let available = ((cause.amount_donated * 100.0).round() as i64 - cause.paid_out_cents).max(0);
+                return Err(ApiError::ValidationError(format!(
+                    "Only {} cents this cause raised are left to pay out", available
+                )));
+            }
+            record
+        } else {
+            let existing = self.mongodb_service.get_cause_payout(&record.cause_id, idempotency_key).await?
+                .ok_or_else(|| ApiError::InternalError(format!("Payout {} disappeared", idempotency_key)))?;
+            if existing.amount_cents != request.amount_cents || existing.currency != currency_code {
+                return Err(ApiError::Conflict("This idempotency_key was already used for a different payout".to_string()));
+            }
+            if let Some(reason) = existing.failure_reason {
+                return Err(ApiError::Conflict(format!("This payout failed ({}); request it with a new idempotency_key", reason)));
+            }
+            existing
+        };
         
-        Ok(())
+        let mut params = stripe::CreatePayout::new(request.amount_cents, currency);
+        params.metadata = Some([
+            ("cause_id".to_string(), record.cause_id.clone()),
+            ("requested_by".to_string(), actor.to_string()),
+        ].into());
+        let stripe_key = format!("cause-payout:{}:{}", record.cause_id, idempotency_key);
+        let payout = match self.stripe.create_payout(&account_id, params, &stripe_key).await {
+            Ok(payout) => payout,
+            // Stripe refused it, so nothing was paid out and the amount can be requested again
+            Err(stripe::StripeError::Stripe(e)) => {
+                let reason = e.message.clone().unwrap_or_else(|| e.to_string());
+                if reserved {
+                    self.mongodb_service.release_cause_payout(&cause_id, request.amount_cents).await?;
+                }
+                self.mongodb_service.set_cause_payout_result(&record.cause_id, idempotency_key, None, Some(&reason)).await?;
+                return Err(ApiError::StripeError(format!("Payout failed: {}", reason)));
+            }
+            // It may have been created; retrying with the same key finds out without paying twice
+            Err(e) => return Err(ApiError::StripeError(format!("Payout failed, retry with the same idempotency_key: {}", e))),
+        };
+        if record.payout_id.is_some() {
+            return Ok(payout);
+        }
+        self.mongodb_service.set_cause_payout_result(&record.cause_id, idempotency_key, Some(payout.id.as_str()), None).await?;
+        
+        info!("{} requested payout {} of {} {} for cause {}", actor, payout.id, request.amount_cents, currency_code, cause_id);
+        self.record_audit(AuditLog::new(
+            actor,
+            AuditAction::PayoutRequested,
+            "cause",
+            &cause_id.to_hex(),
+            None,
+            Some(mongodb::bson::doc! {
+                "payout_id": payout.id.to_string(),
+                "amount_cents": request.amount_cents,
+                "currency": currency_code,
+            }),
+        )).await;
+        Ok(payout)
     }
     
     // Create an account link for Stripe Connect onboarding
@@ -560,7 +1395,7 @@ impl CauseService {
             
         let cause = self.get_cause_by_id(&object_id).await?;
         
-        let account_id = cause.stripe_account_id
+        let account_id = cause.stripe_account_id.clone()
             .ok_or_else(|| ApiError::ValidationError("No Stripe account associated with this cause".to_string()))?;
         
         let account_id_obj = stripe::AccountId::from_str(&account_id)
@@ -580,35 +1415,39 @@ impl CauseService {
             expand: &[],
         };
         
-        match stripe::AccountLink::create(&self.stripe_client, account_link_params).await {
+        match self.stripe.create_account_link(account_link_params).await {
             Ok(link) => Ok(link.url),
             Err(e) => Err(ApiError::StripeError(e.to_string())),
         }
     }
     
     // Check the status of a connected account
-    pub async fn get_account_status(&self, cause_id: &str) -> Result<serde_json::Value, ApiError> {
-        let object_id = ObjectId::parse_str(cause_id)
-            .map_err(|_| ApiError::ValidationError("Invalid cause ID".to_string()))?;
-            
-        let cause = self.get_cause_by_id(&object_id).await?;
+    pub async fn get_account_status(&self, cause: &Cause) -> Result<serde_json::Value, ApiError> {
+        let object_id = cause.id
+            .ok_or_else(|| ApiError::InternalError("Cause has no ID".to_string()))?;
+        let cause_id = object_id.to_hex();
         
-        let account_id = cause.stripe_account_id
+        let account_id = cause.stripe_account_id.clone()
             .ok_or_else(|| ApiError::ValidationError("No Stripe account associated with this cause".to_string()))?;
         
         let account_id_obj = stripe::AccountId::from_str(&account_id)
             .map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?;
-        match stripe::Account::retrieve(&self.stripe_client, &account_id_obj, &[]).await {
+        match self.stripe.retrieve_account(&account_id_obj).await {
             Ok(account) => {
+                let requirements = account_requirements(&account);
                 let status = serde_json::json!({
                     "charges_enabled": account.charges_enabled.unwrap_or(false),
                     "payouts_enabled": account.payouts_enabled.unwrap_or(false),
                     "details_submitted": account.details_submitted.unwrap_or(false),
                     "account_id": account_id,
+                    "requirements": requirements,
                 });
+                if let Err(e) = self.mongodb_service.set_cause_requirements(&account_id, &requirements).await {
+                    error!("Failed to record Stripe requirements for cause {}: {}", cause_id, e);
+                }
                 
                 // Update cause status in DB
-                if account.charges_enabled.unwrap_or(false) && !cause.onboarding_completed {
+                if self.onboarded(&account) && !cause.onboarding_completed {
                     let update = UpdateCauseRequest {
                         stripe_account_status: Some("enabled".to_string()),
                         name: None,
@@ -625,6 +1464,8 @@ impl CauseService {
                         stripe_account_id: None,
                         displayed: None,
                         featured: None,
+                        featured_rank: None,
+                        featured_until: None,
                     };
                     let _ = self.mongodb_service.update_cause(&object_id, update).await;
                 }
@@ -672,14 +1513,9 @@ impl CauseService {
                     })
                 };
                 
-                match stripe::Account::retrieve(
-                    &self.stripe_client,
-                    &account_id_obj,
-                    &[]
-                ).await {
+                match self.stripe.retrieve_account(&account_id_obj).await {
                     Ok(account) => {
-                        let status = if account.charges_enabled.unwrap_or(false) && 
-                                       account.details_submitted.unwrap_or(false) {
+                        let status = if self.onboarded(&account) {
                             "pending" // Ready but not yet processed
                         } else {
                             "incomplete" // Still needs onboarding
@@ -742,13 +1578,8 @@ impl CauseService {
             // Add onboarding URL if account exists but incomplete
             if let Some(account_id) = &draft.stripe_account_id {
                 if let Ok(account_id_obj) = stripe::AccountId::from_str(account_id) {
-                    if let Ok(account) = stripe::Account::retrieve(
-                        &self.stripe_client,
-                        &account_id_obj,
-                        &[]
-                    ).await {
-                        let needs_onboarding = !account.charges_enabled.unwrap_or(false) || 
-                                             !account.details_submitted.unwrap_or(false);
+                    if let Ok(account) = self.stripe.retrieve_account(&account_id_obj).await {
+                        let needs_onboarding = !self.onboarded(&account);
                         
                         if needs_onboarding {
                             if let Ok(url) = self.create_account_link_for_draft(&draft).await {
@@ -795,16 +1626,104 @@ impl CauseService {
             expand: &[],
         };
         
-        match stripe::AccountLink::create(&self.stripe_client, link_params).await {
-            Ok(link) => Ok(link.url),
-            Err(e) => Err(ApiError::StripeError(e.to_string())),
+        let link = self.stripe.create_account_link(link_params).await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        
+        // The creator is still onboarding, so keep the draft alive while they do
+        if let Err(e) = self.extend_draft(draft).await {
+            error!("Failed to extend draft {:?} after creating onboarding link: {}", draft.id, e);
+        }
+        Ok(link.url)
+    }
+    
+    async fn send_verification_email(&self, draft_id: &str, email: &str, cause_name: &str) -> Result<(), String> {
+        let expires_at = chrono::Utc::now().timestamp() + VERIFICATION_TTL_SECS;
+        let token = sign_verification_token(&self.email_verification_secret, draft_id, email, expires_at);
+        let link = format!("{}/setup/verify-email?draft={}&token={}",
+            std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string()),
+            draft_id,
+            token
+        );
+        let text = format!(
+            "Please confirm this address to continue setting up \"{}\":\n\n{}\n\nThe link is valid for 48 hours. If you did not create this cause, ignore this email.\n",
+            cause_name, link
+        );
+        self.email_service.send(email, "Confirm your email for your cause", &text).await
+    }
+    
+    /// Send a fresh verification link for an unverified draft
+    pub async fn resend_verification_email(&self, draft: &CauseDraft) -> Result<(), ApiError> {
+        let draft_id = draft.id
+            .ok_or_else(|| ApiError::ValidationError("Draft has no ID".to_string()))?;
+        if draft.email_verified {
+            return Err(ApiError::ValidationError("Email is already verified".to_string()));
+        }
+        self.send_verification_email(&draft_id.to_hex(), &draft.creator_email, &draft.name).await
+            .map_err(ApiError::InternalError)
+    }
+    
+    /// Mark the creator's email verified. If Stripe onboarding already finished,
+    /// cause creation continues right away instead of waiting for the next webhook.
+    pub async fn verify_draft_email(&self, draft_id: &str, token: &str) -> Result<(), ApiError> {
+        let draft = self.get_draft(draft_id).await?;
+        if draft.email_verified {
+            return Ok(());
+        }
+        
+        verify_verification_token(&self.email_verification_secret, draft_id, &draft.creator_email, token, chrono::Utc::now().timestamp())
+            .map_err(ApiError::ValidationError)?;
+        
+        let object_id = draft.id
+            .ok_or_else(|| ApiError::ValidationError("Draft has no ID".to_string()))?;
+        self.mongodb_service.update_draft(
+            &object_id,
+            mongodb::bson::doc! {
+                "email_verified": true,
+                "email_verified_at": mongodb::bson::DateTime::from_chrono(chrono::Utc::now())
+            }
+        ).await.map_err(ApiError::DatabaseError)?;
+        info!("Verified creator email for draft {}", draft_id);
+        
+        if draft.stripe_account_id.is_some() {
+            match self.complete_cause_from_draft(draft_id).await {
+                Ok(cause) => info!("Continued creation of cause {} after email verification", cause.name),
+                Err(ApiError::ValidationError(msg)) => info!("Draft {} not ready to complete yet: {}", draft_id, msg),
+                Err(e) => error!("Failed to complete draft {} after email verification: {}", draft_id, e),
+            }
+        }
+        Ok(())
+    }
+    
+    pub async fn get_draft(&self, draft_id: &str) -> Result<CauseDraft, ApiError> {
+        let object_id = ObjectId::parse_str(draft_id)
+            .map_err(|_| ApiError::ValidationError("Invalid draft ID".to_string()))?;
+        self.mongodb_service.get_draft_by_id(&object_id)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .ok_or_else(|| ApiError::NotFound("Draft not found".to_string()))
+    }
+    
+    /// Push a draft's expiry out by one extension period, up to its maximum lifetime
+    pub async fn extend_draft(&self, draft: &CauseDraft) -> Result<chrono::DateTime<chrono::Utc>, ApiError> {
+        let draft_id = draft.id
+            .ok_or_else(|| ApiError::ValidationError("Draft has no ID".to_string()))?;
+        if draft.status == DraftStatus::Completed {
+            return Err(ApiError::ValidationError("Draft is already completed".to_string()));
+        }
+        
+        let expires_at = draft.extended_expiry(chrono::Utc::now());
+        if !self.mongodb_service.extend_draft_expiry(&draft_id, expires_at).await.map_err(ApiError::DatabaseError)? {
+            return Err(ApiError::NotFound("Draft not found".to_string()));
         }
+        info!("Draft {} now expires at {}", draft_id, expires_at);
+        Ok(expires_at)
     }
 
     pub async fn get_cause_by_token_name(&self, token_name: &str) -> Result<Cause, ApiError> {
         self.mongodb_service.get_cause_by_token_name(token_name)
             .await
             .map_err(ApiError::DatabaseError)?
+            .filter(|cause| cause.status != CauseStatus::Archived)
             .ok_or_else(|| ApiError::NotFound(format!("Cause not found with token name: {}", token_name)))
     }
     
@@ -812,6 +1731,7 @@ impl CauseService {
         self.mongodb_service.get_cause_by_name(name)
             .await
             .map_err(ApiError::DatabaseError)?
+            .filter(|cause| cause.status != CauseStatus::Archived)
             .ok_or_else(|| ApiError::NotFound(format!("Cause not found with name: {}", name)))
     }
     
@@ -819,6 +1739,7 @@ impl CauseService {
         self.mongodb_service.get_cause_by_token_symbol(token_symbol)
             .await
             .map_err(ApiError::DatabaseError)?
+            .filter(|cause| cause.status != CauseStatus::Archived)
             .ok_or_else(|| ApiError::NotFound(format!("Cause not found with token symbol: {}", token_symbol)))
     }
     
@@ -828,18 +1749,25 @@ impl CauseService {
         // Initial supply for the cause token
         let initial_supply = 100_000_000; // 100 million tokens => 1M USD(ish) 
         
-        // Create the token using TokenService - it handles all the configuration internally
-        let token = self.token_service.create_token_for_cause(
-            &cause.token_name,
-            &cause.token_symbol,
-            initial_supply,
-            cause.token_image_url.clone()
-        ).await
-        .map_err(|e| ApiError::InternalError(format!("Failed to create token: {}", e)))?;
+        // A previous attempt may have minted the token but failed to record it on the cause
+        let token = match self.mongodb_service.get_token_by_symbol(&cause.token_symbol).await? {
+            Some(token) => {
+                info!("Token {} already minted, linking it to cause", cause.token_symbol);
+                token
+            },
+            // Create the token using TokenService - it handles all the configuration internally
+            None => self.token_service.create_token_for_cause(
+                &cause.token_name,
+                &cause.token_symbol,
+                initial_supply,
+                cause.token_image_url.clone()
+            ).await
+            .map_err(|e| ApiError::InternalError(format!("Failed to create token: {}", e)))?,
+        };
         
-        // Update cause status in MongoDB to ACTIVE since we've completed all steps
+        // The cause goes ACTIVE once the finalize step has run
         let mut updated_cause = cause.clone();
-        updated_cause.status = CauseStatus::Active;
+        updated_cause.status = CauseStatus::TokenMinted;
         updated_cause.token_id = Some(token.token_id.clone());
         
         // Update the cause with new status and token ID
@@ -859,6 +1787,8 @@ impl CauseService {
             stripe_account_status: None,
             displayed: None,
             featured: None,
+            featured_rank: None,
+            featured_until: None,
         };
         
         self.mongodb_service.update_cause(&updated_cause.id.unwrap(), update)
@@ -875,6 +1805,14 @@ impl CauseService {
             .map_err(|e| ApiError::DatabaseError(e))
     }
     
+    pub async fn listed_causes_etag(&self) -> Result<String, ApiError> {
+        self.mongodb_service.listed_causes_etag().await
+    }
+    
+    pub async fn list_causes(&self, query: &CauseListQuery) -> Result<CauseListPage, ApiError> {
+        self.mongodb_service.get_cause_summaries(query).await
+    }
+    
     pub async fn get_featured_causes(&self) -> Result<Vec<Cause>, ApiError> {
         self.mongodb_service.get_featured_causes().await
             .map_err(|e| ApiError::DatabaseError(e))
@@ -902,6 +1840,8 @@ impl CauseService {
             stripe_account_status: None,
             displayed: None,
             featured: None,
+            featured_rank: None,
+            featured_until: None,
         };
         
         self.mongodb_service.update_cause(cause_id, update)
@@ -916,21 +1856,181 @@ impl CauseService {
             .ok_or_else(|| ApiError::NotFound(format!("Cause not found with ID: {}", cause_id)))
     }
 
-    pub async fn update_cause(&self, cause_id: &ObjectId, update_data: UpdateCauseRequest) -> Result<bool, ApiError> {
-        self.mongodb_service.update_cause(cause_id, update_data).await
-            .map_err(|e| ApiError::DatabaseError(e))
+    /// Recent donations for a cause page
+    pub async fn get_cause_donations(&self, cause_id: &ObjectId, query: &CauseDonationsQuery) -> Result<CauseDonationsPage, ApiError> {
+        let cause = self.get_cause_by_id(cause_id).await?;
+        self.mongodb_service.get_cause_donations(&cause.token_symbol, query).await
+    }
+
+    /// Refuse a donation attributed to a campaign or fundraiser page of another cause, or one
+    /// that isn't open
+    pub async fn check_attribution(&self, cause: &Cause, attribution: &DonationAttribution) -> Result<(), ApiError> {
+        if let Some(fundraiser_id) = &attribution.fundraiser_id {
+            let page = match ObjectId::parse_str(fundraiser_id) {
+                Ok(id) => self.mongodb_service.get_fundraiser(&id).await?,
+                Err(_) => None,
+            };
+            match page {
+                Some(page) if Some(page.cause_id.as_str()) == cause.id.map(|id| id.to_hex()).as_deref() => {
+                    if page.status != FundraiserStatus::Active {
+                        return Err(ApiError::ValidationError(format!("Fundraiser {} is closed", page.slug)));
+                    }
+                }
+                _ => return Err(ApiError::ValidationError(format!("Fundraiser {} not found for this cause", fundraiser_id))),
+            }
+        }
+        let Some(campaign_id) = &attribution.campaign_id else {
+            return Ok(());
+        };
+        let campaign = match ObjectId::parse_str(campaign_id) {
+            Ok(id) => self.mongodb_service.get_campaign(&id).await?,
+            Err(_) => None,
+        };
+        match campaign {
+            Some(campaign) if Some(campaign.cause_id.as_str()) == cause.id.map(|id| id.to_hex()).as_deref() => {
+                if campaign.accepts_donations(chrono::Utc::now().timestamp()) {
+                    Ok(())
+                } else {
+                    Err(ApiError::ValidationError(format!("Campaign {} is not accepting donations", campaign.name)))
+                }
+            }
+            _ => Err(ApiError::ValidationError(format!("Campaign {} not found for this cause", campaign_id))),
+        }
+    }
+
+    /// A cause's donation totals and where they came from
+    pub async fn get_cause_analytics(&self, cause: &Cause) -> Result<CauseAnalytics, ApiError> {
+        let by_referrer = self.mongodb_service.get_referrer_totals(&cause.token_symbol).await?;
+        Ok(CauseAnalytics {
+            cause_id: cause.id.map(|id| id.to_hex()).unwrap_or_default(),
+            token_symbol: cause.token_symbol.clone(),
+            donations: by_referrer.iter().map(|totals| totals.donations).sum(),
+            amount_usd: by_referrer.iter().map(|totals| totals.amount_usd).sum(),
+            tokens: by_referrer.iter().map(|totals| totals.tokens).sum(),
+            by_referrer,
+        })
+    }
+
+    /// Issue a token for a donate button on the cause's own site
+    pub async fn create_embed_token(&self, cause: &Cause, request: &CreateEmbedTokenRequest, created_by: &str) -> Result<EmbedToken, ApiError> {
+        let cause_id = cause.id.map(|id| id.to_hex()).unwrap_or_default();
+        if self.mongodb_service.get_embed_tokens(&cause_id).await?.len() >= MAX_EMBED_TOKENS {
+            return Err(ApiError::Conflict(format!("A cause can have at most {} embed tokens; revoke one first", MAX_EMBED_TOKENS)));
+        }
+        let origin = validation::origin(&request.origin).map_err(ApiError::ValidationError)?;
+        let referrer = match &request.referrer {
+            Some(referrer) => validation::referrer(referrer).map_err(ApiError::ValidationError)?,
+            None => reqwest::Url::parse(&origin).ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_else(|| origin.clone()),
+        };
+        let token = EmbedToken {
+            id: None,
+            token: format!("embed_{}", hex::encode(rand::random::<[u8; 24]>())),
+            cause_id,
+            origin,
+            referrer,
+            created_by: created_by.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            revoked_at: None,
+        };
+        self.mongodb_service.create_embed_token(&token).await?;
+        info!("{} issued an embed token for cause {} on {}", created_by, token.cause_id, token.origin);
+        Ok(token)
+    }
+
+    /// Create a donation checkout from an embedded donate button. The token is public, so it's
+    /// only honoured from the origin it was issued for. The request isn't signed, so no saved
+    /// Stripe customer is attached even when it names a wallet.
+    pub async fn create_embed_donation(&self, request: &EmbedDonationRequest, origin: Option<&str>) -> Result<(String, String), ApiError> {
+        let token = self.mongodb_service.get_embed_token(&request.token).await?
+            .ok_or_else(|| ApiError::Unauthorized("Unknown or revoked embed token".to_string()))?;
+        if origin.map(|origin| origin.trim_end_matches('/')) != Some(token.origin.as_str()) {
+            return Err(ApiError::Forbidden(format!("This embed token can only be used from {}", token.origin)));
+        }
+        let cause_id = ObjectId::parse_str(&token.cause_id)
+            .map_err(|e| ApiError::InternalError(format!("Embed token has an invalid cause ID: {}", e)))?;
+        let cause = self.get_cause_by_id(&cause_id).await?;
+        if !cause.accepts_donations() {
+            return Err(ApiError::ValidationError(format!("This cause is {} and is not accepting donations", cause.status)));
+        }
+        let connected_account_id = cause.stripe_account_id.clone()
+            .ok_or_else(|| ApiError::ValidationError("This cause does not have a connected Stripe account".to_string()))?;
+        let attribution = DonationAttribution {
+            referrer: Some(request.referrer.as_deref().map(str::trim).map(str::to_string).unwrap_or(token.referrer)),
+            campaign_id: request.campaign_id.clone(),
+            fundraiser_id: None,
+        };
+        self.create_donation_checkout_session(
+            &cause,
+            &connected_account_id,
+            request.amount_cents,
+            request.user_wallet_address.as_deref(),
+            false,
+            &attribution,
+        ).await
+    }
+
+    pub async fn update_cause(&self, cause_id: &ObjectId, update_data: UpdateCauseRequest, actor: &str) -> Result<bool, ApiError> {
+        let before = self.mongodb_service.get_cause_by_id(cause_id).await
+            .map_err(|e| ApiError::DatabaseError(e))?;
+        
+        let updated = self.mongodb_service.update_cause(cause_id, update_data).await
+            .map_err(|e| ApiError::DatabaseError(e))?;
+        
+        if updated {
+            let after = self.mongodb_service.get_cause_by_id(cause_id).await
+                .map_err(|e| ApiError::DatabaseError(e))?;
+            self.record_audit(AuditLog::new(
+                actor,
+                AuditAction::CauseUpdated,
+                "cause",
+                &cause_id.to_hex(),
+                before.as_ref().and_then(snapshot),
+                after.as_ref().and_then(snapshot),
+            )).await;
+        }
+        
+        Ok(updated)
     }
     
-    pub async fn delete_cause(&self, cause_id: &ObjectId) -> Result<bool, ApiError> {
-        self.mongodb_service.delete_cause(cause_id).await
-            .map_err(|e| ApiError::DatabaseError(e))
+    pub async fn delete_cause(&self, cause_id: &ObjectId, actor: &str) -> Result<bool, ApiError> {
+        let before = self.mongodb_service.get_cause_by_id(cause_id).await
+            .map_err(|e| ApiError::DatabaseError(e))?;
+        
+        let deleted = self.mongodb_service.delete_cause(cause_id).await
+            .map_err(|e| ApiError::DatabaseError(e))?;
+        
+        if deleted {
+            self.record_audit(AuditLog::new(
+                actor,
+                AuditAction::CauseDeleted,
+                "cause",
+                &cause_id.to_hex(),
+                before.as_ref().and_then(snapshot),
+                None,
+            )).await;
+        }
+        
+        Ok(deleted)
+    }
+    
+    // Audit failures are logged but never fail the action itself
+    async fn record_audit(&self, entry: AuditLog) {
+        if let Err(e) = self.mongodb_service.record_audit_log(entry).await {
+            error!("Failed to record audit log: {:?}", e);
+        }
     }
     
     // Validation methods for individual fields
-    pub async fn validate_cause_name(&self, name: &str) -> Result<bool, ApiError> {
+    // The validate_* methods return why a value is unusable, or None if it is fine
+    pub async fn validate_cause_name(&self, name: &str) -> Result<Option<String>, ApiError> {
         // Check if name is empty
         if name.trim().is_empty() {
-            return Ok(false);
+            return Ok(Some("Cause name is required".to_string()));
+        }
+        if let Err(msg) = self.name_filter.check(name) {
+            return Ok(Some(msg));
         }
         
         // Check if already taken
@@ -938,14 +2038,16 @@ impl CauseService {
             .await
             .map_err(ApiError::DatabaseError)?;
         
-        Ok(!is_taken)
+        Ok(is_taken.then(|| "This cause name is already taken".to_string()))
     }
     
-    pub async fn validate_token_symbol(&self, symbol: &str) -> Result<bool, ApiError> {
-        // Check format
-        let symbol = symbol.trim().to_uppercase();
-        if symbol.len() < 2 || symbol.len() > 5 || !symbol.chars().all(|c| c.is_ascii_uppercase()) {
-            return Ok(false);
+    pub async fn validate_token_symbol(&self, symbol: &str) -> Result<Option<String>, ApiError> {
+        let symbol = match validation::token_symbol(symbol) {
+            Ok(symbol) => symbol,
+            Err(msg) => return Ok(Some(msg)),
+        };
+        if let Err(msg) = self.name_filter.check(&symbol) {
+            return Ok(Some(msg));
         }
         
         // Check if already taken
@@ -953,7 +2055,7 @@ impl CauseService {
             .await
             .map_err(ApiError::DatabaseError)?;
         
-        Ok(!is_taken)
+        Ok(is_taken.then(|| "This token symbol is already taken".to_string()))
     }
     
     pub async fn update_causes_payouts_status(&self, stripe_account_id: &str, payouts_enabled: bool) -> Result<u64, ApiError> {
@@ -975,10 +2077,75 @@ impl CauseService {
         Ok(result.modified_count)
     }
 
-    pub async fn validate_token_name(&self, name: &str) -> Result<bool, ApiError> {
+    /// Store a payout or balance event for the causes on its connected account, and email
+    /// their creators when a payout failed. Redelivered events are stored and emailed once.
+    pub async fn record_payout_event(&self, mut event: PayoutEvent) -> Result<(), ApiError> {
+        let causes = self.mongodb_service.get_causes_by_stripe_account(&event.stripe_account_id).await?;
+        event.cause_ids = causes.iter().filter_map(|cause| cause.id.map(|id| id.to_hex())).collect();
+        if causes.is_empty() {
+            log::warn!("{} event {} is for account {}, which no cause uses", event.kind, event.event_id, event.stripe_account_id);
+        }
+        if !self.mongodb_service.record_payout_event(&event).await? {
+            info!("Payout event {} ({}) already recorded", event.event_id, event.currency);
+            return Ok(());
+        }
+        if event.kind == PayoutEventKind::PayoutFailed {
+            for cause in &causes {
+                self.notify_payout_failed(cause, &event).await;
+            }
+        }
+        Ok(())
+    }
+
+    // Email failures are logged; the event itself is already stored
+    async fn notify_payout_failed(&self, cause: &Cause, event: &PayoutEvent) {
+        let subject = format!("A payout for \"{}\" failed", cause.name);
+        let text = format!(
+            "Stripe could not pay out {:.2} {} to the bank account for \"{}\".\n\nReason: {}\n\nThe money has been returned to your Stripe balance. Update your bank details from your cause's Stripe dashboard and Stripe will try again.\n",
+            event.amount_cents as f64 / 100.0,
+            event.currency.to_uppercase(),
+            cause.name,
+            event.failure_message.as_deref().or(event.failure_code.as_deref()).unwrap_or("not given"),
+        );
+        if let Err(e) = self.email_service.send(&cause.creator_email, &subject, &text).await {
+            error!("Failed to notify creator of cause {:?} about failed payout: {}", cause.id, e);
+        }
+    }
+
+    /// A cause's payouts and balance changes, newest first
+    pub async fn get_payout_events(&self, cause: &Cause, limit: Option<i64>) -> Result<Vec<PayoutEvent>, ApiError> {
+        let cause_id = cause.id.map(|id| id.to_hex()).unwrap_or_default();
+        self.mongodb_service.get_payout_events(&cause_id, limit.unwrap_or(20).clamp(1, 100)).await
+    }
+
+    /// Record what Stripe still needs from a connected account on its causes
+    pub async fn update_causes_requirements(&self, account: &stripe::Account) -> Result<u64, ApiError> {
+        self.mongodb_service.set_cause_requirements(account.id.as_str(), &account_requirements(account)).await
+    }
+
+    /// Causes' Stripe requirements for admins: every cause checked so far, including those
+    /// with nothing outstanding, unless `state` narrows it. At most `limit` (default 100, up to 500).
+    pub async fn get_causes_by_requirements(&self, state: Option<RequirementsState>, limit: Option<i64>) -> Result<Vec<CauseRequirements>, ApiError> {
+        let causes = self.mongodb_service.get_causes_by_requirements(state, limit.unwrap_or(100).clamp(1, 500)).await?;
+        Ok(causes.into_iter()
+            .filter_map(|cause| Some(CauseRequirements {
+                id: cause.id?.to_hex(),
+                name: cause.name,
+                token_symbol: cause.token_symbol,
+                creator_email: cause.creator_email,
+                stripe_account_id: cause.stripe_account_id,
+                requirements: cause.requirements?,
+            }))
+            .collect())
+    }
+
+    pub async fn validate_token_name(&self, name: &str) -> Result<Option<String>, ApiError> {
         // Check if name is empty
         if name.trim().is_empty() {
-            return Ok(false);
+            return Ok(Some("Token name is required".to_string()));
+        }
+        if let Err(msg) = self.name_filter.check(name) {
+            return Ok(Some(msg));
         }
         
         // Check if already taken
@@ -986,35 +2153,47 @@ impl CauseService {
             .await
             .map_err(ApiError::DatabaseError)?;
         
-        Ok(!is_taken)
+        Ok(is_taken.then(|| "This token name is already taken".to_string()))
     }
     
-    // Create a checkout session for donations with destination charges
+    // Create a checkout session for donations with destination charges. The wallet's saved
+    // Stripe customer is only attached when `wallet_signed` says the wallet signed the request.
     pub async fn create_donation_checkout_session(
         &self,
         cause: &Cause,
         connected_account_id: &str,
         amount_cents: i64,
-        user_wallet_address: &str,
+        user_wallet_address: Option<&str>,
+        wallet_signed: bool,
+        attribution: &DonationAttribution,
     ) -> Result<(String, String), ApiError> {
         // Creating donation checkout session
         
         // Validate amount
-        if amount_cents < 100 {
+        if amount_cents < MIN_DONATION_CENTS {
             return Err(ApiError::ValidationError("Minimum donation is $1.00".to_string()));
         }
         
-        if amount_cents > 999999 {
+        if amount_cents > MAX_DONATION_CENTS {
             return Err(ApiError::ValidationError("Maximum donation is $9,999.99".to_string()));
         }
+        self.check_attribution(cause, attribution).await?;
         
         // Calculate platform fee (5%)
         let platform_fee = (amount_cents as f64 * 0.05).round() as i64;
         
+        // Save the card on the donor's customer so repeat donations skip card entry
+        let customer = match user_wallet_address {
+            Some(wallet) if wallet_signed => self.customer_service.customer_for_payment(wallet).await,
+            _ => None,
+        };
+        
         // Create checkout session params
         let mut params = CreateCheckoutSession::new();
         params.mode = Some(CheckoutSessionMode::Payment);
         
+        self.apply_payment_methods(&mut params)?;
+        
         // Set success and cancel URLs
         let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
         let success_url = format!("{}/donation-success?session_id={{CHECKOUT_SESSION_ID}}", frontend_url);
@@ -1059,7 +2238,7 @@ impl CauseService {
             metadata: None,
             on_behalf_of: None,
             receipt_email: None,
-            setup_future_usage: None,
+            setup_future_usage: customer.as_ref().map(|_| stripe::CreateCheckoutSessionPaymentIntentDataSetupFutureUsage::OnSession),
             shipping: None,
             statement_descriptor: None,
             statement_descriptor_suffix: None,
@@ -1067,24 +2246,31 @@ impl CauseService {
             description: None,
         });
         
-        // Add metadata for webhook processing
-        params.metadata = Some([
+        // Add metadata for webhook processing. Without a wallet, Checkout collects the donor's
+        // email and the tokens are held for them to claim.
+        let mut metadata = stripe::Metadata::from([
             ("cause_id".to_string(), cause.id.as_ref().unwrap().to_string()),
             ("cause_name".to_string(), cause.name.clone()),
             ("token_name".to_string(), cause.token_name.clone()),
             ("token_symbol".to_string(), cause.token_symbol.clone()),
-            ("user_wallet_address".to_string(), user_wallet_address.to_string()),
             ("connected_account_id".to_string(), connected_account_id.to_string()),
             ("platform_fee".to_string(), platform_fee.to_string()),
-        ].into());
+        ]);
+        if let Some(wallet) = user_wallet_address {
+            metadata.insert("user_wallet_address".to_string(), wallet.to_string());
+        }
+        metadata.extend(attribution.to_metadata());
+        params.metadata = Some(metadata);
         
-        // Set customer email collection
-        params.customer_email = None; // We already have wallet address
+        // Without a saved customer, Checkout asks for the donor's email
+        params.customer = customer;
         
         // Create the session
-        match stripe::CheckoutSession::create(&self.stripe_client, params).await {
+        match self.stripe.create_checkout_session(params).await {
             Ok(session) => {
-                // Successfully created checkout session
+                if let Some(wallet) = user_wallet_address {
+                    self.record_pending_deposit(&session, wallet, &cause.token_symbol, cause.id, amount_cents).await;
+                }
                 Ok((session.id.to_string(), session.url.unwrap_or_default()))
             },
             Err(e) => {
@@ -1093,4 +2279,281 @@ impl CauseService {
             }
         }
     }
+
+    /// Checkout session for topping up a wallet with USD. No connected account, so the
+    /// purchases webhook takes the topup branch and credits the full amount 1:1.
+    pub async fn create_topup_checkout_session(
+        &self,
+        user_wallet_address: &str,
+        amount_cents: i64,
+    ) -> Result<(String, String), ApiError> {
+        if amount_cents < TOPUP_MIN_CENTS {
+            return Err(ApiError::ValidationError("Minimum top-up is $1.00".to_string()));
+        }
+        if amount_cents > TOPUP_MAX_CENTS {
+            return Err(ApiError::ValidationError("Maximum top-up is $9,999.99".to_string()));
+        }
+
+        let mut params = CreateCheckoutSession::new();
+        params.mode = Some(CheckoutSessionMode::Payment);
+        self.apply_payment_methods(&mut params)?;
+
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let success_url = format!("{}/topup-success?session_id={{CHECKOUT_SESSION_ID}}", frontend_url);
+        let cancel_url = format!("{}/wallet", frontend_url);
+        params.success_url = Some(&success_url);
+        params.cancel_url = Some(&cancel_url);
+        params.client_reference_id = Some(user_wallet_address);
+
+        params.line_items = Some(vec![
+            stripe::CreateCheckoutSessionLineItems {
+                price_data: Some(stripe::CreateCheckoutSessionLineItemsPriceData {
+                    currency: stripe::Currency::USD,
+                    product_data: Some(stripe::CreateCheckoutSessionLineItemsPriceDataProductData {
+                        name: "USD top-up".to_string(),
+                        description: Some("Index Wallets USD balance".to_string()),
+                        images: None,
+                        metadata: None,
+                        tax_code: None,
+                    }),
+                    unit_amount: Some(amount_cents),
+                    recurring: None,
+                    tax_behavior: None,
+                    unit_amount_decimal: None,
+                    product: None,
+                }),
+                price: None,
+                quantity: Some(1),
+                adjustable_quantity: None,
+                dynamic_tax_rates: None,
+                tax_rates: None,
+            }
+        ]);
+
+        params.metadata = Some([
+            ("token_name".to_string(), "US Dollar".to_string()),
+            ("token_symbol".to_string(), "USD".to_string()),
+            ("user_wallet_address".to_string(), user_wallet_address.to_string()),
+        ].into());
+
+        if let Some(customer) = self.customer_service.customer_for_payment(user_wallet_address).await {
+            params.customer = Some(customer);
+            params.payment_intent_data = Some(stripe::CreateCheckoutSessionPaymentIntentData {
+                setup_future_usage: Some(stripe::CreateCheckoutSessionPaymentIntentDataSetupFutureUsage::OnSession),
+                ..Default::default()
+            });
+        }
+
+        match self.stripe.create_checkout_session(params).await {
+            Ok(session) => {
+                info!("Created top-up checkout session {} for {} ({} cents)", session.id, user_wallet_address, amount_cents);
+                self.record_pending_deposit(&session, user_wallet_address, "USD", None, amount_cents).await;
+                Ok((session.id.to_string(), session.url.unwrap_or_default()))
+            },
+            Err(e) => {
+                error!("Failed to create top-up checkout session: {}", e);
+                Err(ApiError::StripeError(e.to_string()))
+            }
+        }
+    }
+
+    // A dashboard payment method configuration decides everything (including Apple Pay / Google Pay);
+    // otherwise list the types explicitly - wallets ride along with card once the domain is registered
+    fn apply_payment_methods(&self, params: &mut CreateCheckoutSession<'_>) -> Result<(), ApiError> {
+        if let Some(configuration) = &self.payment_methods.payment_method_configuration {
+            params.payment_method_configuration = Some(
+                stripe::PaymentMethodConfigurationId::from_str(configuration)
+                    .map_err(|e| ApiError::InternalError(format!("Invalid STRIPE_PAYMENT_METHOD_CONFIGURATION: {}", e)))?
+            );
+            return Ok(());
+        }
+
+        let types: Vec<stripe::CreateCheckoutSessionPaymentMethodTypes> = self.payment_methods.payment_method_types
+            .iter()
+            .filter_map(|t| match serde_json::from_value(serde_json::Value::String(t.clone())) {
+                Ok(kind) => Some(kind),
+                Err(_) => {
+                    log::warn!("Ignoring unsupported checkout payment method type: {}", t);
+                    None
+                }
+            })
+            .collect();
+        if !types.is_empty() {
+            params.payment_method_types = Some(types);
+        }
+        Ok(())
+    }
+
+    // Track the checkout until the webhook credits it; a failure here only hides the "processing" entry
+    async fn record_pending_deposit(
+        &self,
+        session: &stripe::CheckoutSession,
+        wallet_address: &str,
+        token_symbol: &str,
+        cause_id: Option<ObjectId>,
+        amount_cents: i64,
+    ) {
+        let now = chrono::Utc::now().timestamp();
+        let pending = PendingDeposit {
+            id: None,
+            stripe_session_id: session.id.to_string(),
+            wallet_address: wallet_address.to_string(),
+            token_symbol: token_symbol.to_string(),
+            cause_id: cause_id.map(|id| id.to_hex()),
+            amount_cents,
+            status: PendingDepositStatus::Pending,
+            created_at: now,
+            updated_at: now,
+            expires_at: session.expires_at,
+        };
+        if let Err(e) = self.mongodb_service.create_pending_deposit(&pending).await {
+            error!("Failed to record pending deposit for session {}: {:?}", session.id, e);
+        }
+    }
+}
+
+/// What Stripe still needs from a connected account, summarized
+pub fn account_requirements(account: &stripe::Account) -> StripeRequirements {
+    let requirements = account.requirements.as_ref();
+    StripeRequirements::new(
+        requirements.and_then(|r| r.currently_due.clone()).unwrap_or_default(),
+        requirements.and_then(|r| r.eventually_due.clone()).unwrap_or_default(),
+        requirements.and_then(|r| r.past_due.clone()).unwrap_or_default(),
+        requirements.and_then(|r| r.disabled_reason.clone()),
+        requirements.and_then(|r| r.current_deadline),
+        chrono::Utc::now().timestamp(),
+    )
+}
+
+/// The service agreement causes in `country` onboard under, or why they can't
+pub fn connect_agreement(connect: &ConnectConfig, country: &str) -> Result<ConnectServiceAgreement, ApiError> {
+    connect.service_agreement(country)
+        .ok_or_else(|| ApiError::ValidationError(format!(
+            "Causes can't be onboarded in {} yet; supported countries are {}",
+            country,
+            connect.countries().join(", "),
+        )))
+}
+
+/// Express account parameters for a cause in `country`. Recipient-only countries get just
+/// the transfers capability under Stripe's recipient service agreement.
+pub fn connected_account_params<'a>(
+    connect: &ConnectConfig,
+    email: &'a str,
+    country: &'a str,
+    business_type: BusinessType,
+    metadata: stripe::Metadata,
+) -> Result<stripe::CreateAccount<'a>, ApiError> {
+    let agreement = connect_agreement(connect, country)?;
+    let card_payments = match agreement {
+        ConnectServiceAgreement::Full => Some(stripe::CreateAccountCapabilitiesCardPayments { requested: Some(true) }),
+        ConnectServiceAgreement::Recipient => None,
+    };
+    let tos_acceptance = match agreement {
+        ConnectServiceAgreement::Full => None,
+        ConnectServiceAgreement::Recipient => Some(stripe::AcceptTos {
+            service_agreement: Some("recipient".to_string()),
+            ..Default::default()
+        }),
+    };
+    Ok(stripe::CreateAccount {
+        type_: Some(stripe::AccountType::Express),
+        country: Some(country),
+        email: Some(email),
+        capabilities: Some(stripe::CreateAccountCapabilities {
+            card_payments,
+            transfers: Some(stripe::CreateAccountCapabilitiesTransfers {
+                requested: Some(true),
+            }),
+            ..Default::default()
+        }),
+        business_type: Some(match business_type {
+            BusinessType::Individual => stripe::AccountBusinessType::Individual,
+            BusinessType::Company => stripe::AccountBusinessType::Company,
+            BusinessType::NonProfit => stripe::AccountBusinessType::NonProfit,
+            BusinessType::GovernmentEntity => stripe::AccountBusinessType::GovernmentEntity,
+        }),
+        tos_acceptance,
+        metadata: Some(metadata),
+        ..Default::default()
+    })
+}
+
+/// Whether a connected account has finished onboarding: full accounts once they can take
+/// charges, recipient-only accounts once they can receive transfers
+pub fn account_onboarded(account: &stripe::Account, agreement: ConnectServiceAgreement) -> bool {
+    if !account.details_submitted.unwrap_or(false) {
+        return false;
+    }
+    match agreement {
+        ConnectServiceAgreement::Full => account.charges_enabled.unwrap_or(false),
+        ConnectServiceAgreement::Recipient => account.capabilities.as_ref()
+            .and_then(|capabilities| capabilities.transfers.as_ref())
+            .map_or(false, |status| *status == stripe::CapabilityStatus::Active),
+    }
+}
+
+/// What team invite tokens are signed for, in place of a draft ID
+fn team_invite_scope(cause_id: &str) -> String {
+    format!("team_invite:{}", cause_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect() -> ConnectConfig {
+        ConnectConfig::parse(Some("US"), Some("GB"))
+    }
+
+    fn account(details_submitted: bool, charges_enabled: bool, transfers: Option<stripe::CapabilityStatus>) -> stripe::Account {
+        stripe::Account {
+            details_submitted: Some(details_submitted),
+            charges_enabled: Some(charges_enabled),
+            capabilities: Some(stripe::AccountCapabilities { transfers, ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_full_accounts_are_onboarded_once_they_take_charges() {
+        let transfers_only = account(true, false, Some(stripe::CapabilityStatus::Active));
+        assert!(!account_onboarded(&transfers_only, ConnectServiceAgreement::Full));
+        assert!(account_onboarded(&account(true, true, None), ConnectServiceAgreement::Full));
+        assert!(!account_onboarded(&account(false, true, None), ConnectServiceAgreement::Full));
+    }
+
+    #[test]
+    fn test_recipient_accounts_are_onboarded_once_transfers_are_active() {
+        let transfers_only = account(true, false, Some(stripe::CapabilityStatus::Active));
+        assert!(account_onboarded(&transfers_only, ConnectServiceAgreement::Recipient));
+        let pending = account(true, false, Some(stripe::CapabilityStatus::Pending));
+        assert!(!account_onboarded(&pending, ConnectServiceAgreement::Recipient));
+        let unsubmitted = account(false, false, Some(stripe::CapabilityStatus::Active));
+        assert!(!account_onboarded(&unsubmitted, ConnectServiceAgreement::Recipient));
+    }
+
+    #[test]
+    fn test_full_countries_request_card_payments() {
+        let params = connected_account_params(&connect(), "a@example.org", "US", BusinessType::NonProfit, Default::default()).unwrap();
+        let capabilities = params.capabilities.unwrap();
+        assert!(capabilities.card_payments.is_some());
+        assert!(capabilities.transfers.is_some());
+        assert!(params.tos_acceptance.is_none());
+    }
+
+    #[test]
+    fn test_recipient_countries_take_transfers_under_the_recipient_agreement() {
+        let params = connected_account_params(&connect(), "a@example.org", "gb", BusinessType::Individual, Default::default()).unwrap();
+        let capabilities = params.capabilities.unwrap();
+        assert!(capabilities.card_payments.is_none());
+        assert!(capabilities.transfers.is_some());
+        assert_eq!(params.tos_acceptance.unwrap().service_agreement.as_deref(), Some("recipient"));
+    }
+
+    #[test]
+    fn test_unsupported_countries_are_refused() {
+        let refused = connected_account_params(&connect(), "a@example.org", "FR", BusinessType::Company, Default::default());
+        assert!(matches!(refused, Err(ApiError::ValidationError(message)) if message.contains("GB, US")));
+    }
 }
@@ -0,0 +1,266 @@
+use actix_web::web;
+use log::{info, warn, error};
+use mongodb::bson::{doc, Bson, oid::ObjectId};
+use delta_executor_sdk::base::crypto::Ed25519PrivKey;
+use crate::models::{
+    ApiError, ActivityEvent, ActivityKind, ActivityAmount, AuditAction, AuditLog, Payment, PaymentStatus, EscrowStatus, EscrowOutcome, Dispute, DisputeStatus,
+    DisputeRefundStatus, DisputeRefundSource, ResolveDisputeRefundRequest, DISPUTE_WINDOW_DAYS,
+};
+use crate::services::{MongoDBService, TokenService, TransferError, WalletService, EscrowService, PushService};
+use crate::utils::audit::snapshot;
+
+/// Customer disputes of completed payments. Opening one freezes an escrowed payment's
+/// held funds; resolving it for the customer sends the money back, from escrow when it's
+/// still held and otherwise from the central vault, since the vendor's vault can only be
+/// debited with the vendor's signature. Central vault refunds are recorded as the vendor's
+/// liability until an admin settles them.
+#[derive(Clone)]
+pub struct DisputeService {
+    mongodb: web::Data<MongoDBService>,
+    token_service: web::Data<TokenService>,
+    escrow_service: web::Data<EscrowService>,
+    push_service: web::Data<PushService>,
+    central_vault_keypair: Ed25519PrivKey,
+}
+
+impl DisputeService {
+    pub fn new(
+        mongodb: web::Data<MongoDBService>,
+        token_service: web::Data<TokenService>,
+        escrow_service: web::Data<EscrowService>,
+        push_service: web::Data<PushService>,
+        central_vault_keypair: Ed25519PrivKey,
+    ) -> Self {
+        Self { mongodb, token_service, escrow_service, push_service, central_vault_keypair }
+    }
+
+    pub async fn open(&self, payment: &Payment, customer_address: &str, reason: String, details: Option<String>) -> Result<Dispute, ApiError> {
+        if payment.status != PaymentStatus::Completed {
+            return Err(ApiError::Conflict("Only completed payments can be disputed".to_string()));
+        }
+        let now = chrono::Utc::now().timestamp();
+        if now - payment.created_at > DISPUTE_WINDOW_DAYS * 24 * 60 * 60 {
+            return Err(ApiError::Conflict(format!("Payments can only be disputed within {} days", DISPUTE_WINDOW_DAYS)));
+        }
+        if payment.escrow.as_ref().map_or(false, |escrow| matches!(escrow.status, EscrowStatus::Refunding | EscrowStatus::Refunded)) {
+            return Err(ApiError::Conflict("Payment has already been refunded".to_string()));
+        }
+
+        let dispute = self.mongodb.create_dispute(Dispute {
+            id: None,
+            payment_id: payment.payment_id.clone(),
+            customer_address: customer_address.to_string(),
+            vendor_address: payment.vendor_address.clone(),
+            amount_usd: payment.price_usd,
+            reason,
+            details,
+            status: DisputeStatus::Open,
+            created_at: now,
+            updated_at: now,
+            vendor_response: None,
+            vendor_responded_at: None,
+            resolution_note: None,
+            resolved_by: None,
+            resolved_at: None,
+            refund_status: None,
+            refund_source: None,
+            refund_tx_id: None,
+            refund_failure: None,
+        }).await?;
+
+        // Hold on to escrowed funds until the dispute is resolved
+        if payment.escrow.as_ref().map_or(false, |escrow| escrow.status == EscrowStatus::Held) {
+            if let Err(e) = self.escrow_service.freeze(&payment.payment_id).await {
+                warn!("Failed to freeze escrow of disputed payment {}: {}", payment.payment_id, e);
+            }
+        }
+
+        info!("Payment {} disputed by {}", payment.payment_id, customer_address);
+        self.push_service.dispute_updated(&dispute).await;
+        Ok(dispute)
+    }
+
+    /// The vendor gives their side. They can revise it until the dispute is resolved.
+    pub async fn respond(&self, dispute: &Dispute, response: String) -> Result<Dispute, ApiError> {
+        let id = dispute_id(dispute)?;
+        let set = doc! { "vendor_response": response, "vendor_responded_at": chrono::Utc::now().timestamp() };
+        let dispute = self.mongodb.transition_dispute(&id, &[DisputeStatus::Open, DisputeStatus::VendorResponded], DisputeStatus::VendorResponded, set).await?
+            .ok_or_else(|| ApiError::Conflict(format!("Dispute is already {}", dispute.status)))?;
+        self.push_service.dispute_updated(&dispute).await;
+        Ok(dispute)
+    }
+
+    /// Decide a dispute. A refund is sent straight away; if it fails the dispute stays
+    /// resolved with `refund_status: failed` for `retry_refund`. Upholding a payment lets
+    /// frozen escrow release to the vendor as it would have.
+    pub async fn resolve(&self, dispute: &Dispute, refund: bool, note: Option<String>, admin: &str) -> Result<Dispute, ApiError> {
+        let id = dispute_id(dispute)?;
+        let payment = self.mongodb.get_payment(&dispute.payment_id).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", dispute.payment_id)))?;
+        let escrow_status = payment.escrow.as_ref().map(|escrow| escrow.status.clone());
+
+        let mut set = doc! {
+            "resolution_note": note,
+            "resolved_by": admin,
+            "resolved_at": chrono::Utc::now().timestamp(),
+        };
+        let open = [DisputeStatus::Open, DisputeStatus::VendorResponded];
+        let resolved = if refund {
+            let source = match escrow_status {
+                Some(EscrowStatus::Held | EscrowStatus::Disputed | EscrowStatus::Refunding | EscrowStatus::Refunded) => DisputeRefundSource::Escrow,
+                Some(EscrowStatus::Capturing) => return Err(ApiError::Conflict("Escrowed funds are being released to the vendor, try again shortly".to_string())),
+                Some(EscrowStatus::Failed) => return Err(ApiError::Conflict("The escrow's last transfer may not have landed; resolve it before refunding".to_string())),
+                _ => DisputeRefundSource::CentralVault,
+            };
+            set.insert("refund_status", DisputeRefundStatus::Pending.to_string());
+            set.insert("refund_source", mongodb::bson::to_bson(&source)
+                .map_err(|e| ApiError::InternalError(format!("Failed to serialize refund source: {}", e)))?);
+            let resolved = self.mongodb.transition_dispute(&id, &open, DisputeStatus::ResolvedRefund, set).await?
+                .ok_or_else(|| ApiError::Conflict(format!("Dispute is already {}", dispute.status)))?;
+            self.refund(resolved, &payment, admin).await?
+        } else {
+            let resolved = self.mongodb.transition_dispute(&id, &open, DisputeStatus::ResolvedUpheld, set).await?
+                .ok_or_else(|| ApiError::Conflict(format!("Dispute is already {}", dispute.status)))?;
+            if escrow_status == Some(EscrowStatus::Disputed) {
+                self.mongodb.transition_payment_escrow(&payment.payment_id, &[EscrowStatus::Disputed], EscrowStatus::Held, doc! {}).await?;
+            }
+            resolved
+        };
+
+        info!("Dispute {} of payment {} {} by {}", id, resolved.payment_id, resolved.status, admin);
+        self.push_service.dispute_updated(&resolved).await;
+        Ok(resolved)
+    }
+
+    /// Send a refund that failed again
+    pub async fn retry_refund(&self, dispute: &Dispute, admin: &str) -> Result<Dispute, ApiError> {
+        let id = dispute_id(dispute)?;
+        let claimed = self.mongodb.transition_dispute_refund(&id, &[DisputeRefundStatus::Failed], DisputeRefundStatus::Pending, doc! {}).await?
+            .ok_or_else(|| ApiError::Conflict("Dispute has no failed refund to retry".to_string()))?;
+        let payment = self.mongodb.get_payment(&claimed.payment_id).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", claimed.payment_id)))?;
+        self.refund(claimed, &payment, admin).await
+    }
+
+    /// Settle a refund left `refunding` because its transfer may have landed: completed if
+    /// it did, otherwise failed so it can be sent again with `retry_refund`
+    pub async fn resolve_refund(&self, dispute: &Dispute, resolution: &ResolveDisputeRefundRequest, admin: &str) -> Result<Dispute, ApiError> {
+        let id = dispute_id(dispute)?;
+        if dispute.refund_status != Some(DisputeRefundStatus::Refunding) || dispute.refund_failure.is_none() {
+            return Err(ApiError::Conflict("Dispute has no refund awaiting resolution".to_string()));
+        }
+        let payment = self.mongodb.get_payment(&dispute.payment_id).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Payment {} not found", dispute.payment_id)))?;
+        let resolved = if resolution.refunded {
+            self.complete_refund(dispute, &payment, resolution.refund_tx_id.clone(), admin).await?
+        } else {
+            self.mongodb.transition_dispute_refund(&id, &[DisputeRefundStatus::Refunding], DisputeRefundStatus::Failed, doc! {}).await?
+        };
+        info!("Refund of dispute {} resolved as {} by {}", id, if resolution.refunded { "sent" } else { "not sent" }, admin);
+        resolved.ok_or_else(|| ApiError::Conflict("The refund changed while it was being resolved".to_string()))
+    }
+
+    /// Record that the vendor paid back a refund the central vault made on their behalf
+    pub async fn settle_liability(&self, dispute: &Dispute, admin: &str) -> Result<Dispute, ApiError> {
+        let id = dispute_id(dispute)?;
+        let settled = self.mongodb.settle_vendor_liability(&id, admin).await?
+            .ok_or_else(|| ApiError::Conflict("Dispute has no outstanding vendor liability".to_string()))?;
+        let audit = AuditLog::new(admin, AuditAction::LiabilitySettled, "dispute", &id.to_hex(), snapshot(dispute), snapshot(&settled));
+        if let Err(e) = self.mongodb.record_audit_log(audit).await {
+            error!("Failed to record audit log for liability of dispute {}: {}", id, e);
+        }
+        info!("Vendor liability of dispute {} settled by {}", id, admin);
+        Ok(settled)
+    }
+
+    /// Reverse transfer for a dispute resolved in the customer's favour, claimed as
+    /// `refunding` first so only one caller sends it. A refused transfer is recorded as
+    /// `failed` for `retry_refund`; one that may have landed stays `refunding` until an
+    /// admin resolves it. Failures are recorded on the dispute rather than returned, as the
+    /// resolution itself stands.
+    async fn refund(&self, dispute: Dispute, payment: &Payment, admin: &str) -> Result<Dispute, ApiError> {
+        let id = dispute_id(&dispute)?;
+        let claimed = self.mongodb.transition_dispute_refund(&id, &[DisputeRefundStatus::Pending], DisputeRefundStatus::Refunding, doc! { "refund_failure": Bson::Null }).await?
+            .ok_or_else(|| ApiError::Conflict("The refund is already being sent".to_string()))?;
+        let result = match claimed.refund_source {
+            // Escrow guards its own transfer, so a failed settlement is safe to retry
+            Some(DisputeRefundSource::Escrow) => self.refund_from_escrow(payment, admin).await
+                .map_err(|e| TransferError::NotTransferred(e.to_string())),
+            _ => self.refund_from_central_vault(&claimed, payment).await,
+        };
+
+        let updated = match result {
+            Ok(tx_id) => self.complete_refund(&claimed, payment, tx_id, admin).await?,
+            Err(e @ TransferError::NotTransferred(_)) => {
+                error!("Failed to refund disputed payment {}: {}", dispute.payment_id, e);
+                let set = doc! { "refund_failure": e.to_string() };
+                self.mongodb.transition_dispute_refund(&id, &[DisputeRefundStatus::Refunding], DisputeRefundStatus::Failed, set).await?
+            },
+            Err(e @ TransferError::Unknown(_)) => {
+                error!("Refund of disputed payment {} may have been sent, holding it for an admin: {}", dispute.payment_id, e);
+                let set = doc! { "refund_failure": e.to_string() };
+                self.mongodb.transition_dispute_refund(&id, &[DisputeRefundStatus::Refunding], DisputeRefundStatus::Refunding, set).await?
+            },
+        };
+        updated.ok_or_else(|| ApiError::InternalError(format!("Dispute {} changed during refund", id)))
+    }
+
+    /// Mark a refund sent. One from the central vault is owed back by the vendor, who kept
+    /// the payment, so it's recorded as their liability.
+    async fn complete_refund(&self, dispute: &Dispute, payment: &Payment, tx_id: Option<String>, admin: &str) -> Result<Option<Dispute>, ApiError> {
+        let id = dispute_id(dispute)?;
+        let from_central_vault = dispute.refund_source != Some(DisputeRefundSource::Escrow);
+        let mut set = doc! { "refund_tx_id": tx_id.clone(), "refund_failure": Bson::Null };
+        if from_central_vault {
+            set.insert("vendor_liability", mongodb::bson::to_bson(&payment.computed_payment.clone().unwrap_or_default())
+                .map_err(|e| ApiError::InternalError(format!("Failed to serialize vendor liability: {}", e)))?);
+        }
+        let updated = self.mongodb.transition_dispute_refund(&id, &[DisputeRefundStatus::Refunding], DisputeRefundStatus::Completed, set).await?;
+        if updated.is_none() {
+            return Ok(None);
+        }
+
+        let audit = AuditLog::new(admin, AuditAction::Refund, "dispute", &id.to_hex(), snapshot(dispute), updated.as_ref().and_then(snapshot));
+        if let Err(e) = self.mongodb.record_audit_log(audit).await {
+            error!("Failed to record audit log for dispute refund {}: {}", id, e);
+        }
+        // Escrow records its own refund activity
+        if from_central_vault {
+            let event = ActivityEvent::new(&dispute.customer_address, ActivityKind::Refund, ActivityAmount::of_payment(payment), "dispute", &id.to_hex())
+                .counterparty(&payment.vendor_address)
+                .executor_tx_id(tx_id);
+            if let Err(e) = self.mongodb.record_activity(event).await {
+                error!("Failed to record refund activity for dispute {}: {}", id, e);
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn refund_from_escrow(&self, payment: &Payment, admin: &str) -> Result<Option<String>, ApiError> {
+        let refundable = [EscrowStatus::Held, EscrowStatus::Disputed];
+        match payment.escrow.as_ref().map(|escrow| &escrow.status) {
+            Some(EscrowStatus::Refunded) => Ok(payment.escrow.as_ref().and_then(|escrow| escrow.settlement_tx_id.clone())),
+            // Already on its way back; the escrow scheduler retries it if needed
+            Some(EscrowStatus::Refunding) => Ok(None),
+            _ => {
+                let refunded = self.escrow_service.settle(&payment.payment_id, EscrowOutcome::Refund, &refundable, admin).await?;
+                Ok(refunded.escrow.and_then(|escrow| escrow.settlement_tx_id))
+            },
+        }
+    }
+
+    /// The vendor's vault can only be debited with their signature, so the platform fronts
+    /// the refund and the vendor owes it back
+    async fn refund_from_central_vault(&self, dispute: &Dispute, payment: &Payment) -> Result<Option<String>, TransferError> {
+        let customer = WalletService::parse_public_key(&dispute.customer_address)
+            .map_err(|e| TransferError::NotTransferred(e.to_string()))?;
+        let amounts: Vec<(String, u64)> = payment.computed_payment.iter().flatten()
+            .map(|token_payment| (token_payment.token_key.clone(), (token_payment.amount_to_pay * 100.0).round() as u64))
+            .collect();
+        self.token_service.transfer_token_bundle(&self.central_vault_keypair, &customer, &amounts).await
+    }
+}
+
+fn dispute_id(dispute: &Dispute) -> Result<ObjectId, ApiError> {
+    dispute.id.ok_or_else(|| ApiError::InternalError("Dispute has no ID".to_string()))
+}
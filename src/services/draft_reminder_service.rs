@@ -0,0 +1,68 @@
+use actix_web::web;
+use log::{info, error};
+use crate::models::CauseDraft;
+use crate::services::{EmailService, MongoDBService};
+
+/// Emails cause creators whose draft is about to expire, so they can finish
+/// Stripe onboarding or extend the draft before the TTL index deletes it.
+#[derive(Clone)]
+pub struct DraftReminderService {
+    mongodb: web::Data<MongoDBService>,
+    email_service: web::Data<EmailService>,
+    remind_before: chrono::Duration,  // how long before expiry the reminder goes out
+}
+
+impl DraftReminderService {
+    pub fn new(mongodb: web::Data<MongoDBService>, email_service: web::Data<EmailService>, remind_before: chrono::Duration) -> Self {
+        Self { mongodb, email_service, remind_before }
+    }
+
+    pub async fn send_due_reminders(&self) {
+        let drafts = match self.mongodb.get_drafts_needing_reminder(chrono::Utc::now() + self.remind_before).await {
+            Ok(drafts) => drafts,
+            Err(e) => {
+                error!("Failed to load expiring drafts: {}", e);
+                return;
+            }
+        };
+
+        for draft in drafts {
+            let Some(draft_id) = draft.id else { continue };
+            if !self.owner_wants_email(&draft).await {
+                info!("Skipping expiry reminder for draft {}: owner turned off email", draft_id);
+            } else if let Err(e) = self.email_service.send(&draft.creator_email, "Your cause draft is about to expire", &Self::reminder_text(&draft)).await {
+                error!("Failed to send expiry reminder for draft {}: {}", draft_id, e);
+                continue;
+            }
+            if let Err(e) = self.mongodb.mark_draft_reminder_sent(&draft_id).await {
+                error!("Failed to mark reminder sent for draft {}: {}", draft_id, e);
+            }
+        }
+    }
+
+    /// Drafts without a signed-in owner always get the reminder
+    async fn owner_wants_email(&self, draft: &CauseDraft) -> bool {
+        let Some(owner) = &draft.owner_address else { return true };
+        match self.mongodb.get_user_by_wallet(owner).await {
+            Ok(Some(user)) => user.notification_preferences.email,
+            Ok(None) => true,
+            Err(e) => {
+                error!("Failed to load notification preferences of {}: {}", owner, e);
+                true
+            }
+        }
+    }
+
+    fn reminder_text(draft: &CauseDraft) -> String {
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        format!(
+            "Hi,\n\n\
+             Your draft for \"{}\" expires on {} UTC and will be deleted if Stripe onboarding is not finished by then.\n\n\
+             Continue setup or extend the draft here: {}/setup/status?draft={}\n",
+            draft.name,
+            draft.expires_at.format("%Y-%m-%d %H:%M"),
+            frontend_url,
+            draft.id.map(|id| id.to_hex()).unwrap_or_default(),
+        )
+    }
+}
@@ -0,0 +1,61 @@
+use reqwest::Client;
+use log::{info, warn};
+use serde_json::json;
+use std::env;
+
+/// Sends transactional email through an HTTP email API (Resend-compatible:
+/// POST JSON with `from`, `to`, `subject` and `text`, bearer-token auth)
+#[derive(Clone)]
+pub struct EmailService {
+    api_url: Option<String>,
+    api_key: String,
+    from: String,
+    client: Client,
+}
+
+impl EmailService {
+    /// Reads EMAIL_API_URL, EMAIL_API_KEY and EMAIL_FROM. Without EMAIL_API_URL
+    /// emails are logged and dropped, which keeps local development quiet.
+    pub fn new(client: Client) -> Self {
+        let api_url = env::var("EMAIL_API_URL").ok().filter(|u| !u.is_empty());
+        if api_url.is_none() {
+            warn!("EMAIL_API_URL not set - emails will be logged instead of sent");
+        }
+
+        Self {
+            api_url,
+            api_key: env::var("EMAIL_API_KEY").unwrap_or_default(),
+            from: env::var("EMAIL_FROM").unwrap_or_else(|_| "Index Wallets <no-reply@indexwallets.org>".to_string()),
+            client,
+        }
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, text: &str) -> Result<(), String> {
+        let Some(api_url) = &self.api_url else {
+            info!("Email to {} not sent (no EMAIL_API_URL): {}", to, subject);
+            return Ok(());
+        };
+
+        let response = self.client
+            .post(api_url)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "from": self.from,
+                "to": [to],
+                "subject": subject,
+                "text": text,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send email: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Email API returned {}: {}", status, body));
+        }
+
+        info!("Sent email to {}: {}", to, subject);
+        Ok(())
+    }
+}
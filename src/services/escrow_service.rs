@@ -0,0 +1,209 @@
+use actix_web::web;
+use log::{info, warn, error};
+use mongodb::bson::{doc, Bson};
+use delta_executor_sdk::base::core::Shard;
+use delta_executor_sdk::base::crypto::Ed25519PrivKey;
+use delta_executor_sdk::base::vaults::VaultId;
+use crate::models::{ApiError, ActivityEvent, ActivityKind, ActivityAmount, AuditAction, AuditLog, Payment, PaymentEscrow, EscrowStatus, EscrowOutcome, DEFAULT_ESCROW_HOLD_HOURS, MAX_ESCROW_HOLD_HOURS};
+use crate::services::{MongoDBService, TokenService, TransferError, WalletService};
+use crate::utils::audit::{snapshot, SYSTEM_ACTOR};
+use crate::utils::signed_payload::contains_value;
+
+/// Escrows settled per run
+const BATCH_SIZE: i64 = 100;
+
+/// Refused captures and refunds are retried once they've been left this long
+const RETRY_AFTER_SECS: i64 = 5 * 60;
+
+/// Holds payments the vendor asked to escrow. The customer's allowance credits the escrow
+/// vault instead of the vendor; once the payment completes the funds are held until the
+/// vendor captures or refunds them, and captured automatically after the hold period
+/// unless a dispute has frozen them. A settlement whose transfer may have landed is never
+/// retried automatically: it fails and waits for an admin.
+#[derive(Clone)]
+pub struct EscrowService {
+    mongodb: web::Data<MongoDBService>,
+    token_service: web::Data<TokenService>,
+    escrow_vault_keypair: Ed25519PrivKey,
+}
+
+impl EscrowService {
+    pub fn new(mongodb: web::Data<MongoDBService>, token_service: web::Data<TokenService>, escrow_vault_keypair: Ed25519PrivKey) -> Self {
+        Self { mongodb, token_service, escrow_vault_keypair }
+    }
+
+    /// Escrow terms for a new payment, waiting for the customer to pay in
+    pub fn terms(&self, hold_hours: Option<i64>) -> Result<PaymentEscrow, ApiError> {
+        let hold_hours = hold_hours.unwrap_or(DEFAULT_ESCROW_HOLD_HOURS);
+        if !(1..=MAX_ESCROW_HOLD_HOURS).contains(&hold_hours) {
+            return Err(ApiError::ValidationError(format!("escrow_hold_hours must be between 1 and {}", MAX_ESCROW_HOLD_HOURS)));
+        }
+        Ok(PaymentEscrow {
+            status: EscrowStatus::AwaitingPayment,
+            vault_address: self.escrow_vault_keypair.pub_key().to_string(),
+            hold_hours,
+            held_at: None,
+            release_at: None,
+            settled_at: None,
+            settled_by: None,
+            settlement_tx_id: None,
+            submitted_at: None,
+            failure_reason: None,
+            updated_at: chrono::Utc::now().timestamp(),
+        })
+    }
+
+    /// Reject signed allowances for an escrowed payment that don't pay into the escrow vault
+    pub fn check_signed_transaction(&self, signed_transaction: &str) -> Result<(), ApiError> {
+        let escrow_vault = serde_json::to_value(VaultId::new(self.escrow_vault_keypair.pub_key(), Shard::from(1u64)))
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize escrow vault: {}", e)))?;
+        let allowances: Vec<serde_json::Value> = serde_json::from_str(signed_transaction)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid signed transaction format: {}", e)))?;
+        if allowances.is_empty() || !allowances.iter().all(|allowance| contains_value(allowance, &escrow_vault)) {
+            return Err(ApiError::ValidationError("Escrowed payments must be paid into the escrow vault".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Freeze held funds so they aren't released automatically; capture and refund still work
+    pub async fn freeze(&self, payment_id: &str) -> Result<Payment, ApiError> {
+        let payment = self.mongodb.transition_payment_escrow(payment_id, &[EscrowStatus::Held], EscrowStatus::Disputed, doc! {}).await?;
+        match payment {
+            Some(payment) => {
+                info!("Escrow of payment {} frozen", payment_id);
+                Ok(payment)
+            },
+            None => Err(self.not_settleable(payment_id).await),
+        }
+    }
+
+    /// Send held funds to the vendor (capture) or back to the customer (refund). Only one
+    /// caller can claim the escrow; a refused transfer is left for the scheduler to retry.
+    pub async fn settle(&self, payment_id: &str, outcome: EscrowOutcome, from: &[EscrowStatus], actor: &str) -> Result<Payment, ApiError> {
+        let claimed = self.mongodb.transition_payment_escrow(
+            payment_id,
+            from,
+            outcome.in_progress(),
+            doc! { "settled_by": actor, "submitted_at": Bson::Null },
+        ).await?;
+        match claimed {
+            Some(payment) => self.transfer(&payment, &outcome).await,
+            None => Err(self.not_settleable(payment_id).await),
+        }
+    }
+
+    pub async fn release_due(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let payments = match self.mongodb.get_escrows_to_settle(now, now - RETRY_AFTER_SECS, BATCH_SIZE).await {
+            Ok(payments) => payments,
+            Err(e) => {
+                error!("Failed to load escrowed payments to release: {}", e);
+                return;
+            }
+        };
+
+        for payment in payments {
+            let status = payment.escrow.as_ref().map(|escrow| escrow.status.clone());
+            let result = match status {
+                Some(EscrowStatus::Held) => self.settle(&payment.payment_id, EscrowOutcome::Capture, &[EscrowStatus::Held], SYSTEM_ACTOR).await,
+                Some(EscrowStatus::Capturing) => self.retry(&payment, EscrowOutcome::Capture).await,
+                Some(EscrowStatus::Refunding) => self.retry(&payment, EscrowOutcome::Refund).await,
+                _ => continue,
+            };
+            if let Err(e) = result {
+                warn!("Failed to settle escrow of payment {}: {}", payment.payment_id, e);
+            }
+        }
+    }
+
+    /// Take over a settlement that was refused, touching it first so it isn't picked up
+    /// twice. One whose transfer was submitted without its outcome being recorded may have
+    /// landed, so it fails instead.
+    async fn retry(&self, payment: &Payment, outcome: EscrowOutcome) -> Result<Payment, ApiError> {
+        let in_progress = outcome.in_progress();
+        let claimed = self.mongodb.transition_payment_escrow(&payment.payment_id, &[in_progress.clone()], in_progress.clone(), doc! {}).await?;
+        match claimed {
+            Some(claimed) if claimed.escrow.as_ref().map_or(false, |escrow| escrow.submitted_at.is_some()) => {
+                let reason = "Interrupted before the transfer outcome was recorded";
+                self.mongodb.transition_payment_escrow(&payment.payment_id, &[in_progress], EscrowStatus::Failed, doc! { "failure_reason": reason }).await?;
+                Err(ApiError::InternalError(format!("Escrow of payment {} may have been settled, holding it for an admin", payment.payment_id)))
+            },
+            Some(claimed) => self.transfer(&claimed, &outcome).await,
+            None => Err(self.not_settleable(&payment.payment_id).await),
+        }
+    }
+
+    async fn transfer(&self, payment: &Payment, outcome: &EscrowOutcome) -> Result<Payment, ApiError> {
+        let payment_id = payment.payment_id.as_str();
+        let to_address = match outcome {
+            EscrowOutcome::Capture => Some(payment.vendor_address.as_str()),
+            EscrowOutcome::Refund => payment.customer_address.as_deref(),
+        }.ok_or_else(|| ApiError::InternalError(format!("Escrowed payment {} has no customer", payment_id)))?;
+        let to_pubkey = WalletService::parse_public_key(to_address)?;
+        let amounts: Vec<(String, u64)> = payment.computed_payment.iter().flatten()
+            .map(|token_payment| (token_payment.token_key.clone(), (token_payment.amount_to_pay * 100.0).round() as u64))
+            .collect();
+
+        // Marked before submitting, so a retry knows this transfer may already have landed
+        let in_progress = outcome.in_progress();
+        let submitted_at = doc! { "submitted_at": chrono::Utc::now().timestamp() };
+        self.mongodb.transition_payment_escrow(payment_id, &[in_progress.clone()], in_progress.clone(), submitted_at).await?
+            .ok_or_else(|| ApiError::Conflict(format!("Escrow of payment {} changed before transfer", payment_id)))?;
+
+        match self.token_service.transfer_token_bundle(&self.escrow_vault_keypair, &to_pubkey, &amounts).await {
+            Ok(tx_id) => {
+                let set = doc! {
+                    "settled_at": chrono::Utc::now().timestamp(),
+                    "settlement_tx_id": tx_id,
+                    "submitted_at": Bson::Null,
+                    "failure_reason": Bson::Null,
+                };
+                let settled = self.mongodb.transition_payment_escrow(payment_id, &[in_progress], outcome.settled(), set).await?
+                    .ok_or_else(|| ApiError::InternalError(format!("Escrow of payment {} changed during transfer", payment_id)))?;
+                info!("Escrow of payment {} {} to {}", payment_id, outcome.settled(), to_address);
+                self.audit(payment, &settled).await;
+                // A capture completes the payment, which is already in the vendor's history
+                if let EscrowOutcome::Refund = outcome {
+                    let event = ActivityEvent::new(to_address, ActivityKind::Refund, ActivityAmount::of_payment(payment), "payment", payment_id)
+                        .counterparty(&payment.vendor_address)
+                        .executor_tx_id(settled.escrow.as_ref().and_then(|escrow| escrow.settlement_tx_id.clone()));
+                    if let Err(e) = self.mongodb.record_activity(event).await {
+                        error!("Failed to record refund activity for payment {}: {}", payment_id, e);
+                    }
+                }
+                Ok(settled)
+            },
+            Err(e @ TransferError::NotTransferred(_)) => {
+                error!("Failed to transfer escrow of payment {} to {}: {}", payment_id, to_address, e);
+                let set = doc! { "submitted_at": Bson::Null, "failure_reason": e.to_string() };
+                self.mongodb.transition_payment_escrow(payment_id, &[in_progress.clone()], in_progress, set).await?;
+                Err(ApiError::InternalError("Failed to transfer escrowed funds, it will be retried".to_string()))
+            },
+            Err(e @ TransferError::Unknown(_)) => {
+                error!("Escrow of payment {} may have been sent to {}, holding it for an admin: {}", payment_id, to_address, e);
+                self.mongodb.transition_payment_escrow(payment_id, &[in_progress], EscrowStatus::Failed, doc! { "failure_reason": e.to_string() }).await?;
+                Err(ApiError::InternalError("The escrow transfer may not have completed; it is on hold until it's checked".to_string()))
+            },
+        }
+    }
+
+    async fn audit(&self, before: &Payment, after: &Payment) {
+        let actor = after.escrow.as_ref().and_then(|escrow| escrow.settled_by.clone()).unwrap_or_else(|| SYSTEM_ACTOR.to_string());
+        let audit = AuditLog::new(&actor, AuditAction::EscrowSettled, "payment", &after.payment_id, snapshot(before), snapshot(after));
+        if let Err(e) = self.mongodb.record_audit_log(audit).await {
+            error!("Failed to record audit log for escrow of payment {}: {}", after.payment_id, e);
+        }
+    }
+
+    /// Why a payment's escrow couldn't be claimed
+    async fn not_settleable(&self, payment_id: &str) -> ApiError {
+        match self.mongodb.get_payment(payment_id).await {
+            Ok(Some(payment)) => match payment.escrow {
+                Some(escrow) => ApiError::Conflict(format!("Escrow is {}", escrow.status)),
+                None => ApiError::ValidationError(format!("Payment {} is not escrowed", payment_id)),
+            },
+            Ok(None) => ApiError::NotFound(format!("Payment {} not found", payment_id)),
+            Err(e) => e,
+        }
+    }
+}
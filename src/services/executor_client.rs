@@ -1,112 +1,435 @@
-use reqwest::{Client, StatusCode};
-use log::{info, error};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use log::{info, warn, error};
 use delta_executor_sdk::base::{
     crypto::{HashDigest, Ed25519PubKey},
     vaults::Vault,
     verifiable::VerifiableType,
 };
 use std::env;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use tokio::sync::broadcast::error::RecvError;
 use serde_json;
+use crate::config::{ExecutorPolicy, SandboxConfig};
+use crate::models::ApiError;
+use crate::services::{SharedEvent, SharedState};
+use crate::utils::circuit_breaker::{BreakerStatus, CircuitBreaker};
+use crate::utils::retry::jittered_backoff;
+use crate::utils::ttl_cache::TtlCache;
 
-/// Client for communicating with the Delta Executor service
+/// Vaults kept in the balance cache at once
+const VAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// Where a submitted transaction stands on the executor
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionStatus {
+    /// Accepted but not yet final, or not known to the executor yet
+    Pending,
+    Finalized,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutorError {
+    NotFound(String),
+    InsufficientBalance(String),
+    /// The allowance's nonce was already used; re-sign against the vault's current nonce
+    NonceConflict(String),
+    /// The executor couldn't be reached in time, returned something unusable, or the
+    /// circuit breaker is open
+    Unavailable(String),
+    Rejected { reason: String },
+}
+
+impl ExecutorError {
+    /// Classify an error response. The executor reports failures as `{"error": ...}` (or
+    /// `message`/`reason`) or plain text; the status code alone doesn't say why.
+    fn from_response(status: StatusCode, body: &str) -> Self {
+        let reason = match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(value) => ["error", "message", "reason"].iter()
+                .find_map(|name| value.get(*name).and_then(|r| r.as_str()).map(|r| r.to_string()))
+                .unwrap_or_else(|| body.trim().to_string()),
+            Err(_) => body.trim().to_string(),
+        };
+        let reason = if reason.is_empty() { format!("HTTP {}", status) } else { reason };
+        let lowered = reason.to_lowercase();
+
+        if status == StatusCode::NOT_FOUND {
+            ExecutorError::NotFound(reason)
+        } else if lowered.contains("insufficient") {
+            ExecutorError::InsufficientBalance(reason)
+        } else if status == StatusCode::CONFLICT || lowered.contains("nonce") {
+            ExecutorError::NonceConflict(reason)
+        } else if status.is_server_error() {
+            ExecutorError::Unavailable(reason)
+        } else {
+            ExecutorError::Rejected { reason }
+        }
+    }
+
+    /// Whether the executor definitely refused the submission, so none of it was applied.
+    /// An unavailable executor may have accepted a submission it didn't answer for.
+    pub fn is_rejection(&self) -> bool {
+        !matches!(self, ExecutorError::Unavailable(_))
+    }
+}
+
+impl fmt::Display for ExecutorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutorError::NotFound(msg) => write!(f, "Not found on executor: {}", msg),
+            ExecutorError::InsufficientBalance(msg) => write!(f, "Insufficient balance: {}", msg),
+            ExecutorError::NonceConflict(msg) => write!(f, "Nonce conflict: {}", msg),
+            ExecutorError::Unavailable(msg) => write!(f, "Executor unavailable: {}", msg),
+            ExecutorError::Rejected { reason } => write!(f, "Rejected by executor: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ExecutorError {}
+
+impl From<ExecutorError> for ApiError {
+    fn from(err: ExecutorError) -> Self {
+        match err {
+            ExecutorError::NotFound(_) => ApiError::NotFound(err.to_string()),
+            ExecutorError::InsufficientBalance(_) => ApiError::InsufficientBalance(err.to_string()),
+            ExecutorError::NonceConflict(_) => ApiError::Conflict(err.to_string()),
+            ExecutorError::Unavailable(_) => ApiError::ServiceUnavailable(err.to_string()),
+            ExecutorError::Rejected { .. } => ApiError::ValidationError(err.to_string()),
+        }
+    }
+}
+
+/// Where ExecutorClient sends its calls: the executor over HTTP, or a stand-in in tests
+#[async_trait]
+pub trait ExecutorBackend: Send + Sync {
+    /// Get a vault straight from the executor, uncached
+    async fn fetch_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, ExecutorError>;
+
+    /// Submit verifiable messages, returning the executor's transaction (or batch)
+    /// identifier when it reports one
+    async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<Option<String>, ExecutorError>;
+
+    /// Execution status of a transaction returned by `submit_verifiables`
+    async fn get_status(&self, tx_id: &str) -> Result<ExecutionStatus, ExecutorError>;
+
+    /// Circuit breaker state, for the health endpoint
+    fn breaker_status(&self) -> BreakerStatus;
+}
+
+/// Client for communicating with the Delta Executor service. Clones share one backend,
+/// so every service backs off together when the executor is down, and one short-lived
+/// vault cache, which other instances are told to invalidate too.
 #[derive(Clone)]
 pub struct ExecutorClient {
+    backend: Arc<dyn ExecutorBackend>,
+    vault_cache: Arc<Mutex<TtlCache<String, Option<Vault>>>>,
+    shared_state: SharedState,
+}
+
+impl ExecutorClient {
+    /// Create a new ExecutorClient talking to the executor on the shared HTTP client
+    pub fn new(client: Client, policy: ExecutorPolicy, shared_state: SharedState) -> Self {
+        let balance_cache_ttl = policy.balance_cache_ttl;
+        Self::with_backend(Arc::new(HttpExecutor::new(client, policy)), balance_cache_ttl, shared_state)
+    }
+    
+    /// Create an ExecutorClient on any backend, such as a MockExecutor in tests
+    pub fn with_backend(backend: Arc<dyn ExecutorBackend>, balance_cache_ttl: Duration, shared_state: SharedState) -> Self {
+        Self {
+            backend,
+            vault_cache: Arc::new(Mutex::new(TtlCache::new(balance_cache_ttl, VAULT_CACHE_CAPACITY))),
+            shared_state,
+        }
+    }
+    
+    /// Drop cached vaults that other instances changed, in the background
+    pub fn start_cache_sync(&self) {
+        let mut events = self.shared_state.subscribe();
+        let client = self.clone();
+        actix_web::rt::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(SharedEvent::VaultsChanged { pubkeys }) => {
+                        let mut cache = client.vault_cache();
+                        for pubkey in &pubkeys {
+                            cache.invalidate(pubkey);
+                        }
+                    },
+                    Ok(_) => {},
+                    // Missed some, so any vault may be stale
+                    Err(RecvError::Lagged(_)) => client.vault_cache().clear(),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+    
+    /// Circuit breaker state, for the health endpoint
+    pub fn breaker_status(&self) -> BreakerStatus {
+        self.backend.breaker_status()
+    }
+    
+    fn vault_cache(&self) -> MutexGuard<'_, TtlCache<String, Option<Vault>>> {
+        self.vault_cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Drop cached vaults after submitting a transfer that involves them, so the next
+    /// balance read reflects it
+    pub fn invalidate_vaults(&self, pubkeys: &[Ed25519PubKey]) {
+        let pubkeys: Vec<String> = pubkeys.iter().map(|pubkey| pubkey.to_string()).collect();
+        {
+            let mut cache = self.vault_cache();
+            for pubkey in &pubkeys {
+                cache.invalidate(pubkey);
+            }
+        }
+        self.shared_state.publish(SharedEvent::VaultsChanged { pubkeys });
+    }
+    
+    /// Get a vault by public key, served from the balance cache when fetched recently
+    pub async fn get_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, ExecutorError> {
+        let cache_key = pubkey.to_string();
+        let generation = {
+            let cache = self.vault_cache();
+            if let Some(vault) = cache.get(&cache_key, Instant::now()) {
+                return Ok(vault);
+            }
+            cache.generation()
+        };
+        
+        let vault = self.fetch_vault(pubkey).await?;
+        self.vault_cache().insert(cache_key, vault.clone(), generation, Instant::now());
+        Ok(vault)
+    }
+    
+    /// Get a vault straight from the executor, for callers that sign against its nonce
+    pub async fn fetch_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, ExecutorError> {
+        self.backend.fetch_vault(pubkey).await
+    }
+    
+    /// Submit verifiable messages to the executor. Returns the executor's transaction
+    /// (or batch) identifier when its response includes one.
+    pub async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<Option<String>, ExecutorError> {
+        self.backend.submit_verifiables(verifiables).await
+    }
+
+    /// Look up the execution status of a transaction returned by `submit_verifiables`
+    pub async fn get_status(&self, tx_id: &str) -> Result<ExecutionStatus, ExecutorError> {
+        self.backend.get_status(tx_id).await
+    }
+}
+
+/// Where the executor's HTTP API is for `environment`
+pub fn executor_base_url(environment: &str, sandbox: &SandboxConfig) -> String {
+    if let Some(url) = sandbox.executor_url() {
+        // A sandbox talks to the test executor wherever it runs
+        url.to_string()
+    } else if environment == "production" {
+        // In production, use EXECUTOR_URL which should be the full Railway URL
+        env::var("EXECUTOR_URL")
+            .unwrap_or_else(|_| {
+                error!("EXECUTOR_URL not set in production environment!");
+                panic!("EXECUTOR_URL must be set when ENVIRONMENT=production");
+            })
+    } else {
+        // In development, construct from host:port
+        let host = env::var("SERVER_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let port = env::var("EXECUTOR_PORT")
+            .ok()
+            .and_then(|p| p.parse::<u16>().ok())
+            .unwrap_or(8081);
+        
+        format!("http://{}:{}", host, port)
+    }
+}
+
+/// The executor's HTTP API, under the timeout, retry and circuit breaker policy
+pub struct HttpExecutor {
     base_url: String,
     client: Client,
+    policy: ExecutorPolicy,
+    breaker: Mutex<CircuitBreaker>,
 }
 
-impl ExecutorClient {
-    /// Create a new ExecutorClient
-    pub fn new() -> Self {
+impl HttpExecutor {
+    pub fn new(client: Client, policy: ExecutorPolicy) -> Self {
         let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string());
+        let base_url = executor_base_url(&environment, &SandboxConfig::from_env());
         
-        let base_url = if environment == "production" {
-            // In production, use EXECUTOR_URL which should be the full Railway URL
-            env::var("EXECUTOR_URL")
-                .unwrap_or_else(|_| {
-                    error!("EXECUTOR_URL not set in production environment!");
-                    panic!("EXECUTOR_URL must be set when ENVIRONMENT=production");
-                })
-        } else {
-            // In development, construct from host:port
-            let host = env::var("SERVER_HOST").unwrap_or_else(|_| "localhost".to_string());
-            let port = env::var("EXECUTOR_PORT")
-                .ok()
-                .and_then(|p| p.parse::<u16>().ok())
-                .unwrap_or(8081);
-            
-            format!("http://{}:{}", host, port)
-        };
-        
-        info!("Executor client connecting to: {} (environment: {})", base_url, environment);
+        info!("Executor client connecting to: {} (environment: {}, policy: {:?})", base_url, environment, policy);
         
         Self {
             base_url,
-            client: reqwest::Client::new(),
+            client,
+            breaker: Mutex::new(CircuitBreaker::new(policy.breaker_threshold, policy.breaker_cooldown)),
+            policy,
         }
     }
     
-    /// Get a vault by public key
-    pub async fn get_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, String> {
+    fn breaker(&self) -> MutexGuard<'_, CircuitBreaker> {
+        self.breaker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Send a request under the timeout, retry and circuit breaker policy. Connection
+    /// failures, timeouts and gateway errors count against the breaker; any other
+    /// response is returned for the caller to interpret. Non-idempotent requests are
+    /// only retried when no connection was made, so a submission that may have reached
+    /// the executor is never sent twice.
+    async fn send(&self, idempotent: bool, build: impl Fn() -> RequestBuilder) -> Result<Response, ExecutorError> {
+        let mut attempt = 0;
+        loop {
+            if !self.breaker().try_acquire(Instant::now()) {
+                return Err(ExecutorError::Unavailable("circuit breaker open".to_string()));
+            }
+            
+            let (retryable, error) = match build().timeout(self.policy.request_timeout).send().await {
+                Ok(response) if !matches!(response.status(), StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT) => {
+                    self.breaker().record_success();
+                    return Ok(response);
+                },
+                Ok(response) => {
+                    self.breaker().record_failure(Instant::now());
+                    (idempotent, format!("HTTP {}", response.status()))
+                },
+                Err(e) => {
+                    self.breaker().record_failure(Instant::now());
+                    let reason = if e.is_timeout() { "request timed out".to_string() } else { format!("request failed: {:?}", e) };
+                    (idempotent || e.is_connect(), reason)
+                },
+            };
+            
+            if !retryable || attempt >= self.policy.max_retries {
+                error!("Executor unavailable after {} attempt(s): {}", attempt + 1, error);
+                return Err(ExecutorError::Unavailable(error));
+            }
+            let delay = jittered_backoff(attempt, self.policy.retry_base_delay, self.policy.retry_max_delay, rand::random::<f64>());
+            warn!("Executor call failed ({}), retrying in {:?}", error, delay);
+            actix_web::rt::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutorBackend for HttpExecutor {
+    async fn fetch_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, ExecutorError> {
         info!("Requesting vault for public key: {}", pubkey);
         
         let url = format!("{}/vaults/{}", self.base_url, pubkey);
+        let response = self.send(true, || self.client.get(&url)).await?;
         
-        match self.client.get(&url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.json::<Vault>().await {
-                        Ok(vault) => {
-                            info!("Successfully retrieved vault");
-                            Ok(Some(vault))
-                        },
-                        Err(e) => {
-                            error!("Failed to deserialize vault: {:?}", e);
-                            Err(format!("Failed to deserialize vault: {:?}", e))
-                        }
-                    }
-                } else if response.status() == StatusCode::NOT_FOUND {
-                    info!("Vault not found for public key: {}", pubkey);
-                    Ok(None)
-                } else {
-                    let error = format!("Failed to get vault: HTTP {}", response.status());
-                    error!("{}", error);
-                    Err(error)
+        if response.status().is_success() {
+            match response.json::<Vault>().await {
+                Ok(vault) => {
+                    info!("Successfully retrieved vault");
+                    Ok(Some(vault))
+                },
+                Err(e) => {
+                    error!("Failed to deserialize vault: {:?}", e);
+                    Err(ExecutorError::Unavailable(format!("Failed to deserialize vault: {:?}", e)))
                 }
-            },
-            Err(e) => {
-                error!("Request to executor service failed: {:?}", e);
-                Err(format!("Request to executor service failed: {:?}", e))
             }
+        } else if response.status() == StatusCode::NOT_FOUND {
+            info!("Vault not found for public key: {}", pubkey);
+            Ok(None)
+        } else {
+            let status = response.status();
+            let error = ExecutorError::from_response(status, &response.text().await.unwrap_or_default());
+            error!("Failed to get vault: HTTP {} - {}", status, error);
+            Err(error)
         }
     }
     
-    /// Submit verifiable messages to the executor
-    pub async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<(), String> {
+    async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<Option<String>, ExecutorError> {
         let url = format!("{}/execute", self.base_url);
         info!("Attempting to submit {} verifiables to URL: {}", verifiables.len(), url);
 
-        match self.client.post(&url)
-            .json(&verifiables)
-            .send()
-            .await 
-        {
-            Ok(response) => {
-                if response.status().is_success() {
-                    info!("Successfully submitted {} verifiables", verifiables.len());
-                    Ok(())
-                } else {
-                    let status = response.status();
-                    let error_body = response.text().await.unwrap_or_else(|_| "unable to read error response".to_string());
-                    let error = format!("Failed to submit verifiables: HTTP {} - {}", status, error_body);
-                    error!("{}", error);
-                    Err(error)
-                }
-            },
-            Err(e) => {
-                error!("Request to executor service failed: {:?}", e);
-                Err(format!("Request to executor service failed: {:?}", e))
-            }
+        let response = self.send(false, || self.client.post(&url).json(&verifiables)).await?;
+        
+        if response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let tx_id = parse_execution_id(&body);
+            info!("Successfully submitted {} verifiables (executor tx: {})", verifiables.len(), tx_id.as_deref().unwrap_or("none reported"));
+            Ok(tx_id)
+        } else {
+            let status = response.status();
+            let error = ExecutorError::from_response(status, &response.text().await.unwrap_or_default());
+            error!("Failed to submit verifiables: HTTP {} - {}", status, error);
+            Err(error)
+        }
+    }
+
+    async fn get_status(&self, tx_id: &str) -> Result<ExecutionStatus, ExecutorError> {
+        let url = format!("{}/transactions/{}", self.base_url, tx_id);
+        let response = self.send(true, || self.client.get(&url)).await?;
+
+        if response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            Ok(parse_execution_status(&body))
+        } else if response.status() == StatusCode::NOT_FOUND {
+            // Submissions can take a moment to show up
+            Ok(ExecutionStatus::Pending)
+        } else {
+            let status = response.status();
+            let error = ExecutorError::from_response(status, &response.text().await.unwrap_or_default());
+            error!("Failed to get status of {}: HTTP {} - {}", tx_id, status, error);
+            Err(error)
+        }
+    }
+
+    fn breaker_status(&self) -> BreakerStatus {
+        self.breaker().status(Instant::now())
+    }
+}
+
+// Field names the executor has used for the submission identifier, most specific first
+const EXECUTION_ID_FIELDS: [&str; 6] = ["tx_hash", "transaction_hash", "tx_id", "batch_id", "hash", "id"];
+
+/// Pull the transaction/batch identifier out of an `/execute` response body: a JSON
+/// object with one of the known fields, a JSON string, or a bare hash-like token
+/// (short bodies such as "OK" are acknowledgements, not identifiers).
+fn parse_execution_id(body: &str) -> Option<String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(fields)) => EXECUTION_ID_FIELDS.iter()
+            .find_map(|name| match fields.get(*name) {
+                Some(serde_json::Value::String(id)) if !id.is_empty() => Some(id.clone()),
+                Some(serde_json::Value::Number(id)) => Some(id.to_string()),
+                _ => None,
+            }),
+        Ok(serde_json::Value::String(id)) if !id.is_empty() => Some(id),
+        Ok(_) => None,
+        Err(_) if !body.contains(char::is_whitespace) && (16..=128).contains(&body.len()) => Some(body.to_string()),
+        Err(_) => None,
+    }
+}
+
+/// Read a `/transactions/{id}` response: `{"status": ..., "error"|"reason": ...}` or a
+/// bare status string. Unrecognised statuses are treated as still pending.
+fn parse_execution_status(body: &str) -> ExecutionStatus {
+    let value = serde_json::from_str::<serde_json::Value>(body.trim())
+        .unwrap_or_else(|_| serde_json::Value::String(body.trim().to_string()));
+    let status = match &value {
+        serde_json::Value::Object(fields) => fields.get("status").and_then(|s| s.as_str()).unwrap_or_default(),
+        serde_json::Value::String(status) => status.as_str(),
+        _ => "",
+    }.to_lowercase();
+
+    match status.as_str() {
+        "finalized" | "final" | "executed" | "confirmed" | "success" | "succeeded" | "completed" => ExecutionStatus::Finalized,
+        "failed" | "rejected" | "reverted" | "error" => {
+            let reason = ["error", "reason", "message"].iter()
+                .find_map(|name| value.get(*name).and_then(|r| r.as_str()))
+                .map(|r| r.to_string())
+                .unwrap_or(status);
+            ExecutionStatus::Failed(reason)
         }
+        _ => ExecutionStatus::Pending,
     }
 }
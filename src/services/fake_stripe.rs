@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use async_trait::async_trait;
+use stripe::{
+    Account, AccountId, AccountLink, CheckoutSession, CheckoutSessionId, CheckoutSessionPaymentStatus,
+    CheckoutSessionStatus, CreateAccount, CreateAccountLink, CreateCheckoutSession, CreateCustomer,
+    CreatePaymentIntent, CreatePrice, CreateProduct, Customer, CustomerId, Expandable, List, ListCheckoutSessions,
+    ListPaymentMethods, PaymentIntent, PaymentIntentConfirmParams, PaymentIntentId, PaymentIntentStatus,
+    PaymentMethod, PaymentMethodId, Payout, CreatePayout, Price, Product, StripeError, UpdatePaymentIntent,
+};
+use crate::services::StripeApi;
+
+/// In-memory stand-in for Stripe, for tests. Objects it creates can be retrieved again,
+/// Connect accounts start un-onboarded and checkout sessions unpaid until the test says
+/// otherwise, and confirmed PaymentIntents succeed.
+#[derive(Default)]
+pub struct FakeStripe {
+    state: Mutex<FakeState>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    next_id: u64,
+    accounts: HashMap<String, Account>,
+    sessions: HashMap<String, CheckoutSession>,
+    intents: HashMap<String, PaymentIntent>,
+    payment_methods: HashMap<String, PaymentMethod>,
+    payouts: HashMap<String, Payout>,  // by idempotency key
+}
+
+impl FakeState {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}_fake{}", prefix, self.next_id)
+    }
+}
+
+impl FakeStripe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Finish Connect onboarding for an account: charges and payouts enabled
+    pub fn complete_onboarding(&self, account_id: &str) {
+        if let Some(account) = self.state().accounts.get_mut(account_id) {
+            account.charges_enabled = Some(true);
+            account.payouts_enabled = Some(true);
+            account.details_submitted = Some(true);
+        }
+    }
+
+    /// Payouts created so far, across all accounts
+    pub fn payout_count(&self) -> usize {
+        self.state().payouts.len()
+    }
+
+    /// Mark a checkout session paid and complete, as when the donor finishes checkout
+    pub fn pay_checkout_session(&self, session_id: &str) -> Option<CheckoutSession> {
+        let mut state = self.state();
+        let session = state.sessions.get_mut(session_id)?;
+        session.payment_status = CheckoutSessionPaymentStatus::Paid;
+        session.status = Some(CheckoutSessionStatus::Complete);
+        Some(session.clone())
+    }
+
+    /// Save a card on a customer, returning its ID
+    pub fn add_payment_method(&self, customer_id: &CustomerId) -> String {
+        let mut state = self.state();
+        let id = state.id("pm");
+        let method = PaymentMethod {
+            id: id.parse().expect("fake payment method ID"),
+            customer: Some(Expandable::Id(customer_id.clone())),
+            ..Default::default()
+        };
+        state.payment_methods.insert(id.clone(), method);
+        id
+    }
+
+    pub fn checkout_sessions(&self) -> Vec<CheckoutSession> {
+        self.state().sessions.values().cloned().collect()
+    }
+
+    pub fn payment_intents(&self) -> Vec<PaymentIntent> {
+        self.state().intents.values().cloned().collect()
+    }
+}
+
+fn not_found(kind: &str, id: &str) -> StripeError {
+    StripeError::ClientError(format!("No such {}: '{}'", kind, id))
+}
+
+fn list<T: Clone>(data: Vec<T>, url: &str) -> List<T> {
+    List { data, has_more: false, total_count: None, url: url.to_string() }
+}
+
+#[async_trait]
+impl StripeApi for FakeStripe {
+    async fn create_account(&self, params: CreateAccount<'_>) -> Result<Account, StripeError> {
+        let mut state = self.state();
+        let id = state.id("acct");
+        let account = Account {
+            id: id.parse().expect("fake account ID"),
+            country: params.country.map(str::to_string),
+            charges_enabled: Some(false),
+            payouts_enabled: Some(false),
+            details_submitted: Some(false),
+            ..Default::default()
+        };
+        state.accounts.insert(id, account.clone());
+        Ok(account)
+    }
+
+    async fn retrieve_account(&self, id: &AccountId) -> Result<Account, StripeError> {
+        self.state().accounts.get(id.as_str()).cloned().ok_or_else(|| not_found("account", id.as_str()))
+    }
+
+    async fn create_account_link(&self, params: CreateAccountLink<'_>) -> Result<AccountLink, StripeError> {
+        Ok(AccountLink {
+            url: format!("https://connect.stripe.test/setup/{}", params.account),
+            ..Default::default()
+        })
+    }
+
+    async fn create_product(&self, _params: CreateProduct<'_>) -> Result<Product, StripeError> {
+        let id = self.state().id("prod");
+        Ok(Product { id: id.parse().expect("fake product ID"), ..Default::default() })
+    }
+
+    async fn create_price(&self, _params: CreatePrice<'_>) -> Result<Price, StripeError> {
+        let id = self.state().id("price");
+        Ok(Price { id: id.parse().expect("fake price ID"), ..Default::default() })
+    }
+
+    async fn create_payout(&self, account: &AccountId, params: CreatePayout<'_>, idempotency_key: &str) -> Result<Payout, StripeError> {
+        let mut state = self.state();
+        if !state.accounts.contains_key(account.as_str()) {
+            return Err(not_found("account", account.as_str()));
+        }
+        if let Some(payout) = state.payouts.get(idempotency_key) {
+            return Ok(payout.clone());
+        }
+        let id = state.id("po");
+        let payout = Payout {
+            id: id.parse().expect("fake payout ID"),
+            amount: params.amount,
+            currency: params.currency,
+            ..Default::default()
+        };
+        state.payouts.insert(idempotency_key.to_string(), payout.clone());
+        Ok(payout)
+    }
+
+    async fn create_checkout_session(&self, params: CreateCheckoutSession<'_>) -> Result<CheckoutSession, StripeError> {
+        let mut state = self.state();
+        let id = state.id("cs_test");
+        let session = CheckoutSession {
+            id: id.parse().expect("fake checkout session ID"),
+            url: Some(format!("https://checkout.stripe.test/{}", id)),
+            metadata: params.metadata.clone(),
+            client_reference_id: params.client_reference_id.map(str::to_string),
+            customer: params.customer.clone().map(Expandable::Id),
+            status: Some(CheckoutSessionStatus::Open),
+            payment_status: CheckoutSessionPaymentStatus::Unpaid,
+            created: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        };
+        state.sessions.insert(id, session.clone());
+        Ok(session)
+    }
+
+    async fn retrieve_checkout_session(&self, id: &CheckoutSessionId) -> Result<CheckoutSession, StripeError> {
+        self.state().sessions.get(id.as_str()).cloned().ok_or_else(|| not_found("checkout session", id.as_str()))
+    }
+
+    async fn list_checkout_sessions(&self, _params: &ListCheckoutSessions<'_>) -> Result<List<CheckoutSession>, StripeError> {
+        Ok(list(self.checkout_sessions(), "/v1/checkout/sessions"))
+    }
+
+    async fn create_payment_intent(&self, params: CreatePaymentIntent<'_>) -> Result<PaymentIntent, StripeError> {
+        let mut state = self.state();
+        let id = state.id("pi");
+        let intent = PaymentIntent {
+            id: id.parse().expect("fake PaymentIntent ID"),
+            amount: params.amount,
+            currency: params.currency,
+            metadata: params.metadata.clone().unwrap_or_default(),
+            customer: params.customer.clone().map(Expandable::Id),
+            client_secret: Some(format!("{}_secret_fake", id)),
+            status: PaymentIntentStatus::RequiresPaymentMethod,
+            ..Default::default()
+        };
+        state.intents.insert(id, intent.clone());
+        Ok(intent)
+    }
+
+    async fn retrieve_payment_intent(&self, id: &PaymentIntentId) -> Result<PaymentIntent, StripeError> {
+        self.state().intents.get(id.as_str()).cloned().ok_or_else(|| not_found("payment_intent", id.as_str()))
+    }
+
+    async fn update_payment_intent(&self, id: &PaymentIntentId, params: UpdatePaymentIntent<'_>) -> Result<PaymentIntent, StripeError> {
+        let mut state = self.state();
+        let intent = state.intents.get_mut(id.as_str()).ok_or_else(|| not_found("payment_intent", id.as_str()))?;
+        if let Some(payment_method) = params.payment_method {
+            intent.payment_method = Some(Expandable::Id(payment_method));
+            intent.status = PaymentIntentStatus::RequiresConfirmation;
+        }
+        Ok(intent.clone())
+    }
+
+    async fn confirm_payment_intent(&self, id: &PaymentIntentId, _params: PaymentIntentConfirmParams<'_>) -> Result<PaymentIntent, StripeError> {
+        let mut state = self.state();
+        let intent = state.intents.get_mut(id.as_str()).ok_or_else(|| not_found("payment_intent", id.as_str()))?;
+        intent.status = PaymentIntentStatus::Succeeded;
+        Ok(intent.clone())
+    }
+
+    async fn create_customer(&self, _params: CreateCustomer<'_>) -> Result<Customer, StripeError> {
+        let id = self.state().id("cus");
+        Ok(Customer { id: id.parse().expect("fake customer ID"), ..Default::default() })
+    }
+
+    async fn list_payment_methods(&self, params: &ListPaymentMethods<'_>) -> Result<List<PaymentMethod>, StripeError> {
+        let methods = self.state().payment_methods.values()
+            .filter(|method| match (&params.customer, &method.customer) {
+                (Some(customer), Some(owner)) => owner.id() == *customer,
+                (None, _) => true,
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        Ok(list(methods, "/v1/payment_methods"))
+    }
+
+    async fn retrieve_payment_method(&self, id: &PaymentMethodId) -> Result<PaymentMethod, StripeError> {
+        self.state().payment_methods.get(id.as_str()).cloned().ok_or_else(|| not_found("payment_method", id.as_str()))
+    }
+
+    async fn detach_payment_method(&self, id: &PaymentMethodId) -> Result<PaymentMethod, StripeError> {
+        let mut state = self.state();
+        let method = state.payment_methods.get_mut(id.as_str()).ok_or_else(|| not_found("payment_method", id.as_str()))?;
+        method.customer = None;
+        Ok(method.clone())
+    }
+}
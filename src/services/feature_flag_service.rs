@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use actix_web::web;
+use log::{error, info};
+use serde_json::json;
+use crate::config::SandboxConfig;
+use crate::models::{ApiError, AuditAction, AuditLog, FeatureFlag, FeatureFlagStatus, FEATURES, feature_definition};
+use crate::services::MongoDBService;
+use crate::utils::audit::snapshot;
+
+/// Feature switches for this deployment's environment, kept in memory and refreshed from the
+/// `feature_flags` collection so handlers can check them without a query
+#[derive(Clone)]
+pub struct FeatureFlagService {
+    mongodb: web::Data<MongoDBService>,
+    environment: String,
+    flags: Arc<RwLock<HashMap<String, bool>>>,
+}
+
+impl FeatureFlagService {
+    pub fn new(mongodb: web::Data<MongoDBService>, environment: &str) -> Self {
+        Self {
+            mongodb,
+            environment: environment.to_string(),
+            flags: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// The environment flags are read for: "sandbox" in SANDBOX_MODE, otherwise ENVIRONMENT
+    /// (default "development")
+    pub fn environment_from_env() -> String {
+        if SandboxConfig::from_env().enabled {
+            return "sandbox".to_string();
+        }
+        env::var("ENVIRONMENT").ok().filter(|v| !v.trim().is_empty()).unwrap_or_else(|| "development".to_string())
+    }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// Reload this environment's flags
+    pub async fn refresh(&self) -> Result<(), ApiError> {
+        let stored = self.mongodb.get_feature_flags(&self.environment).await?;
+        let flags = stored.into_iter().map(|flag| (flag.name, flag.enabled)).collect();
+        *self.flags.write().unwrap_or_else(|e| e.into_inner()) = flags;
+        Ok(())
+    }
+
+    /// Pick up flags changed by other replicas every `interval`
+    pub fn start_refresh(self, interval: Duration) {
+        info!("Refreshing feature flags for {} every {:?}", self.environment, interval);
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.refresh().await {
+                    error!("Failed to refresh feature flags: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Whether a feature is on: its stored flag, else its default. Unknown features are off.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        let Some(feature) = feature_definition(name) else {
+            return false;
+        };
+        self.flags.read().unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .copied()
+            .unwrap_or(feature.default_enabled)
+    }
+
+    /// Guard for a handler of a feature: 503 while a released feature is switched off, 404
+    /// while one still rolling out is
+    pub fn require(&self, name: &str) -> Result<(), ApiError> {
+        if self.is_enabled(name) {
+            return Ok(());
+        }
+        match feature_definition(name) {
+            Some(feature) if feature.default_enabled => {
+                Err(ApiError::ServiceUnavailable(format!("{} is temporarily disabled", feature.description)))
+            }
+            _ => Err(ApiError::NotFound("This feature is not available".to_string())),
+        }
+    }
+
+    /// Every feature as `environment` sees it
+    pub async fn list(&self, environment: &str) -> Result<Vec<FeatureFlagStatus>, ApiError> {
+        let stored: HashMap<String, FeatureFlag> = self.mongodb.get_feature_flags(environment).await?
+            .into_iter()
+            .map(|flag| (flag.name.clone(), flag))
+            .collect();
+        Ok(FEATURES.iter().map(|feature| {
+            let flag = stored.get(feature.name);
+            FeatureFlagStatus {
+                name: feature.name.to_string(),
+                description: feature.description.to_string(),
+                environment: environment.to_string(),
+                enabled: flag.map_or(feature.default_enabled, |flag| flag.enabled),
+                default_enabled: feature.default_enabled,
+                updated_by: flag.map(|flag| flag.updated_by.clone()),
+                updated_at: flag.map(|flag| flag.updated_at),
+            }
+        }).collect())
+    }
+
+    /// Switch a feature for an environment. This deployment's own environment takes effect
+    /// at once; other replicas pick it up on their next refresh.
+    pub async fn set(&self, name: &str, environment: &str, enabled: bool, actor: &str) -> Result<FeatureFlag, ApiError> {
+        if feature_definition(name).is_none() {
+            return Err(ApiError::NotFound(format!("Unknown feature {}", name)));
+        }
+        let before = self.mongodb.get_feature_flags(environment).await?
+            .into_iter()
+            .find(|flag| flag.name == name);
+        let flag = self.mongodb.set_feature_flag(name, environment, enabled, actor).await?;
+        if environment == self.environment {
+            self.flags.write().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), enabled);
+        }
+
+        let entry = AuditLog::new(
+            actor,
+            AuditAction::ConfigChanged,
+            "feature_flag",
+            &format!("{}:{}", environment, name),
+            before.and_then(|flag| snapshot(&json!({ "enabled": flag.enabled }))),
+            snapshot(&json!({ "enabled": enabled })),
+        );
+        if let Err(e) = self.mongodb.record_audit_log(entry).await {
+            error!("Failed to record audit log for feature flag {}: {:?}", name, e);
+        }
+        info!("{} switched {} {} in {}", actor, name, if enabled { "on" } else { "off" }, environment);
+        Ok(flag)
+    }
+}
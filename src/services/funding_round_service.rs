@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use actix_web::web;
+use chrono::Utc;
+use log::{info, warn, error};
+use mongodb::bson::{doc, oid::ObjectId};
+use crate::models::{ApiError, ActivityEvent, ActivityKind, ActivityAmount, AuditLog, AuditAction, CreateFundingRoundRequest, CreditReservation, CreditReservationStatus, FundingRound, FundingRoundReport, FundingRoundStatus, RoundAllocation, RoundPayout, RoundPayoutStatus, WebhookError};
+use crate::services::{MongoDBService, WebhookService};
+use crate::utils::audit::snapshot;
+use crate::utils::payment_calculator::ON_CHAIN_UNITS_PER_TOKEN;
+use crate::utils::quadratic_funding::{qf_score, split_proportionally};
+use crate::utils::redaction::redact;
+
+/// How long a payout can stay processing before the recovery sweep takes its run as crashed
+const PAYOUT_STALE_SECS: i64 = 600;
+const PAYOUT_RECOVERY_BATCH_SIZE: i64 = 100;
+
+/// Runs quadratic funding rounds: at close, each eligible cause gets a share of the
+/// matching budget by QF score, and that share is paid out as cause tokens to the
+/// cause's contributors in proportion to what they gave.
+#[derive(Clone)]
+pub struct FundingRoundService {
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+}
+
+impl FundingRoundService {
+    pub fn new(mongodb: web::Data<MongoDBService>, webhook_service: web::Data<WebhookService>) -> Self {
+        Self { mongodb, webhook_service }
+    }
+
+    pub async fn create_round(&self, request: &CreateFundingRoundRequest, actor: &str) -> Result<FundingRound, ApiError> {
+        if request.name.trim().is_empty() {
+            return Err(ApiError::ValidationError("name is required".to_string()));
+        }
+        if request.matching_budget_cents <= 0 {
+            return Err(ApiError::ValidationError("matching_budget_cents must be positive".to_string()));
+        }
+        if request.ends_at <= request.starts_at {
+            return Err(ApiError::ValidationError("ends_at must be after starts_at".to_string()));
+        }
+
+        let mut cause_symbols: Vec<String> = Vec::new();
+        for symbol in &request.cause_symbols {
+            let symbol = symbol.trim().to_uppercase();
+            let cause = self.mongodb.get_cause_by_token_symbol(&symbol).await
+                .map_err(ApiError::DatabaseError)?
+                .filter(|cause| cause.token_symbol.eq_ignore_ascii_case(&symbol));
+            if cause.is_none() {
+                return Err(ApiError::ValidationError(format!("No cause with token symbol {}", symbol)));
+            }
+            if !cause_symbols.contains(&symbol) {
+                cause_symbols.push(symbol);
+            }
+        }
+        // Quadratic funding needs causes to compare against each other
+        if cause_symbols.len() < 2 {
+            return Err(ApiError::ValidationError("a round needs at least two causes".to_string()));
+        }
+
+        let mut round = FundingRound {
+            id: None,
+            name: request.name.trim().to_string(),
+            cause_symbols,
+            matching_budget_cents: request.matching_budget_cents,
+            starts_at: request.starts_at,
+            ends_at: request.ends_at,
+            status: FundingRoundStatus::Open,
+            allocations: None,
+            closed_at: None,
+            created_by: actor.to_string(),
+            created_at: Utc::now(),
+        };
+        let round_id = self.mongodb.create_funding_round(&round).await?;
+        round.id = Some(round_id);
+
+        self.audit(actor, &round_id, None, &round).await;
+        info!("{} created funding round {} for {:?}", redact(actor), round_id, round.cause_symbols);
+        Ok(round)
+    }
+
+    /// Close a round whose window has ended: compute allocations and create payouts.
+    /// Safe to call again; payouts missing from an interrupted close are filled in.
+    pub async fn close_round(&self, round_id: &ObjectId, actor: &str) -> Result<FundingRoundReport, ApiError> {
+        let round = self.get_round(round_id).await?;
+        let now = Utc::now().timestamp();
+
+        if round.status == FundingRoundStatus::Open {
+            if now < round.ends_at {
+                return Err(ApiError::ValidationError("Round is still open for contributions".to_string()));
+            }
+            let totals = self.mongodb.get_round_contribution_totals(round_id).await?;
+            let allocations = compute_allocations(&round, &totals);
+            if self.mongodb.close_funding_round(round_id, &allocations, now).await? {
+                let closed = self.get_round(round_id).await?;
+                self.audit(actor, round_id, Some(&round), &closed).await;
+                info!("Funding round {} closed with {} allocations", round_id, allocations.len());
+            }
+        }
+
+        let round = self.get_round(round_id).await?;
+        if round.status == FundingRoundStatus::Closed {
+            let totals = self.mongodb.get_round_contribution_totals(round_id).await?;
+            let payouts = compute_payouts(round_id, round.allocations.as_deref().unwrap_or_default(), &totals, now);
+            self.mongodb.create_round_payouts(&payouts).await?;
+        }
+
+        self.report(round_id).await
+    }
+
+    /// Credit every pending or failed payout of a closed round. Failed payouts stay
+    /// failed with their error and are picked up by the next call, unless their credit
+    /// is held for an admin to resolve.
+    pub async fn distribute_round(&self, round_id: &ObjectId, actor: &str) -> Result<FundingRoundReport, ApiError> {
+        let round = self.get_round(round_id).await?;
+        match round.status {
+            FundingRoundStatus::Open => return Err(ApiError::ValidationError("Close the round before distributing".to_string())),
+            FundingRoundStatus::Distributed => return self.report(round_id).await,
+            FundingRoundStatus::Closed => {}
+        }
+
+        let payouts = self.mongodb.get_round_payouts(round_id).await?;
+        let mut all_credited = true;
+        for payout in payouts {
+            let Some(payout_id) = payout.id else { continue };
+            match payout.status {
+                RoundPayoutStatus::Credited => continue,
+                RoundPayoutStatus::Processing => {
+                    all_credited = false;
+                    continue;
+                }
+                RoundPayoutStatus::Pending | RoundPayoutStatus::Failed => {}
+            }
+            if !self.mongodb.claim_round_payout(&payout_id).await? {
+                all_credited = false;
+                continue;
+            }
+
+            if !self.credit_payout(round_id, &payout_id, &payout).await? {
+                all_credited = false;
+            }
+        }
+
+        if all_credited {
+            self.mongodb.set_funding_round_distributed(round_id).await?;
+            let distributed = self.get_round(round_id).await?;
+            self.audit(actor, round_id, Some(&round), &distributed).await;
+            info!("Funding round {} fully distributed", round_id);
+        }
+
+        self.report(round_id).await
+    }
+
+    /// Fail payouts a crashed distribution left processing, settling the ones whose credit is
+    /// known to have landed. Returns how many were recovered.
+    pub async fn recover_stuck_payouts(&self) -> Result<usize, ApiError> {
+        let stale_before = Utc::now().timestamp() - PAYOUT_STALE_SECS;
+        let payouts = self.mongodb.get_stale_round_payouts(stale_before, PAYOUT_RECOVERY_BATCH_SIZE).await?;
+
+        let mut recovered = 0;
+        for payout in payouts {
+            let Some(payout_id) = payout.id else { continue };
+            if !self.mongodb.reclaim_round_payout(&payout_id, stale_before).await? {
+                continue;
+            }
+            warn!("Recovering round {} payout to {} left processing", payout.round_id, redact(&payout.wallet_address));
+            self.reconcile_payout(&payout_id, &payout).await?;
+            recovered += 1;
+        }
+        Ok(recovered)
+    }
+
+    /// Credit a claimed payout under its own credit reservation, so a run that crashes
+    /// mid-credit can't lead to it being paid twice. A refused transfer releases the
+    /// reservation and fails the payout for the next run; one that may have landed keeps the
+    /// reservation failed until an admin resolves it. Returns whether the payout was credited.
+    async fn credit_payout(&self, round_id: &ObjectId, payout_id: &ObjectId, payout: &RoundPayout) -> Result<bool, ApiError> {
+        let reservation = CreditReservation::new(payout_credit_key(payout_id), &payout.wallet_address, &payout.token_symbol, payout.matched_cents);
+        if !self.mongodb.reserve_credit(&reservation).await? {
+            return self.reconcile_payout(payout_id, payout).await;
+        }
+
+        match self.webhook_service.credit_account_with_fee_split(&payout.token_symbol, payout.matched_cents, &payout.wallet_address).await {
+            Ok(receipt) => {
+                let set = doc! { "executor_tx_id": receipt.executor_tx_id.clone() };
+                self.mongodb.transition_credit_reservation(&reservation.key, CreditReservationStatus::Pending, CreditReservationStatus::Credited, set).await?;
+                self.settle_payout(payout_id, payout, receipt.tokens, receipt.executor_tx_id).await
+            }
+            Err(WebhookError::TransferOutcomeUnknown(reason)) => {
+                error!("Round {} payout to {} may have been credited, holding it for an admin: {}", round_id, redact(&payout.wallet_address), reason);
+                let set = doc! { "failure_reason": reason.as_str() };
+                self.mongodb.transition_credit_reservation(&reservation.key, CreditReservationStatus::Pending, CreditReservationStatus::Failed, set).await?;
+                self.mongodb.finish_round_payout(payout_id, RoundPayoutStatus::Failed, 0.0, Some(held_for_admin(&reservation.key))).await?;
+                Ok(false)
+            }
+            Err(e) => {
+                error!("Round {} payout to {} failed: {:?}", round_id, redact(&payout.wallet_address), e);
+                self.mongodb.release_credit_reservation(&reservation.key, CreditReservationStatus::Pending).await?;
+                self.mongodb.finish_round_payout(payout_id, RoundPayoutStatus::Failed, 0.0, Some(e.to_string())).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Settle a claimed payout from the credit reservation an earlier attempt left behind.
+    /// Claims are exclusive, so a reservation still pending belongs to an attempt that never
+    /// recorded its outcome. Returns whether the payout was credited.
+    async fn reconcile_payout(&self, payout_id: &ObjectId, payout: &RoundPayout) -> Result<bool, ApiError> {
+        let key = payout_credit_key(payout_id);
+        let Some(reservation) = self.mongodb.get_credit_reservation(&key).await? else {
+            // Never reserved, so nothing was transferred; the next run credits it
+            let reason = "Interrupted before crediting".to_string();
+            self.mongodb.finish_round_payout(payout_id, RoundPayoutStatus::Failed, 0.0, Some(reason)).await?;
+            return Ok(false);
+        };
+        match reservation.status {
+            // An admin found the transfer landed. The tokens it bought aren't recorded
+            // anywhere, so the payout shows none.
+            CreditReservationStatus::Credited => self.settle_payout(payout_id, payout, 0.0, reservation.executor_tx_id).await,
+            CreditReservationStatus::Pending => {
+                let set = doc! { "failure_reason": "Interrupted before the transfer outcome was recorded" };
+                self.mongodb.transition_credit_reservation(&key, CreditReservationStatus::Pending, CreditReservationStatus::Failed, set).await?;
+                self.mongodb.finish_round_payout(payout_id, RoundPayoutStatus::Failed, 0.0, Some(held_for_admin(&key))).await?;
+                Ok(false)
+            }
+            CreditReservationStatus::Failed => {
+                self.mongodb.finish_round_payout(payout_id, RoundPayoutStatus::Failed, 0.0, Some(held_for_admin(&key))).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Mark a claimed payout credited and record the contributor's reward
+    async fn settle_payout(&self, payout_id: &ObjectId, payout: &RoundPayout, tokens: f64, executor_tx_id: Option<String>) -> Result<bool, ApiError> {
+        if !self.mongodb.finish_round_payout(payout_id, RoundPayoutStatus::Credited, tokens, None).await? {
+            return Ok(false);
+        }
+        let amounts = vec![ActivityAmount { token_symbol: payout.token_symbol.clone(), amount: tokens / ON_CHAIN_UNITS_PER_TOKEN }];
+        let activity = ActivityEvent::new(&payout.wallet_address, ActivityKind::Reward, amounts, "funding_round", &payout.round_id.to_hex())
+            .executor_tx_id(executor_tx_id);
+        if let Err(e) = self.mongodb.record_activity(activity).await {
+            error!("Failed to record reward activity for round {}: {}", payout.round_id, e);
+        }
+        Ok(true)
+    }
+
+    pub async fn report(&self, round_id: &ObjectId) -> Result<FundingRoundReport, ApiError> {
+        let round = self.get_round(round_id).await?;
+        let payouts = self.mongodb.get_round_payouts(round_id).await?;
+        let allocations = round.allocations.as_deref().unwrap_or_default();
+        Ok(FundingRoundReport {
+            total_contributed_cents: allocations.iter().map(|a| a.contributed_cents).sum(),
+            total_matched_cents: allocations.iter().map(|a| a.matched_cents).sum(),
+            round,
+            payouts,
+        })
+    }
+
+    async fn get_round(&self, round_id: &ObjectId) -> Result<FundingRound, ApiError> {
+        self.mongodb.get_funding_round(round_id).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Funding round {} not found", round_id)))
+    }
+
+    // Audit failures are logged but never fail the round operation
+    async fn audit(&self, actor: &str, round_id: &ObjectId, before: Option<&FundingRound>, after: &FundingRound) {
+        let entry = AuditLog::new(
+            actor,
+            AuditAction::FundingRoundChanged,
+            "funding_round",
+            &round_id.to_hex(),
+            before.and_then(snapshot),
+            snapshot(after),
+        );
+        if let Err(e) = self.mongodb.record_audit_log(entry).await {
+            error!("Failed to record audit log for funding round {}: {:?}", round_id, e);
+        }
+    }
+}
+
+/// Per-cause QF allocations from (symbol, wallet, cents) contribution totals
+/// The credit reservation a payout is credited under
+fn payout_credit_key(payout_id: &ObjectId) -> String {
+    format!("round:{}", payout_id.to_hex())
+}
+
+fn held_for_admin(key: &str) -> String {
+    format!("Credit {} may have landed and is waiting for an admin to resolve it", key)
+}
+
+fn compute_allocations(round: &FundingRound, totals: &[(String, String, i64)]) -> Vec<RoundAllocation> {
+    let mut by_cause: BTreeMap<&str, Vec<i64>> = round.cause_symbols.iter().map(|s| (s.as_str(), Vec::new())).collect();
+    for (symbol, _, amount) in totals {
+        if let Some(amounts) = by_cause.get_mut(symbol.as_str()) {
+            amounts.push(*amount);
+        }
+    }
+
+    let scores: Vec<f64> = by_cause.values().map(|amounts| qf_score(amounts)).collect();
+    let matched = split_proportionally(round.matching_budget_cents, &scores);
+
+    by_cause
+        .into_iter()
+        .zip(scores.into_iter().zip(matched))
+        .map(|((symbol, amounts), (qf_score, matched_cents))| RoundAllocation {
+            token_symbol: symbol.to_string(),
+            contributor_count: amounts.len() as i64,
+            contributed_cents: amounts.iter().sum(),
+            qf_score,
+            matched_cents,
+        })
+        .collect()
+}
+
+/// Split each cause's matched amount among its contributors by contribution
+fn compute_payouts(round_id: &ObjectId, allocations: &[RoundAllocation], totals: &[(String, String, i64)], now: i64) -> Vec<RoundPayout> {
+    let mut payouts = Vec::new();
+    for allocation in allocations.iter().filter(|a| a.matched_cents > 0) {
+        let contributors: Vec<&(String, String, i64)> = totals.iter().filter(|(symbol, _, _)| *symbol == allocation.token_symbol).collect();
+        let weights: Vec<f64> = contributors.iter().map(|(_, _, amount)| *amount as f64).collect();
+        let shares = split_proportionally(allocation.matched_cents, &weights);
+        for ((_, wallet, _), share) in contributors.into_iter().zip(shares) {
+            if share <= 0 {
+                continue;
+            }
+            payouts.push(RoundPayout {
+                id: None,
+                round_id: *round_id,
+                token_symbol: allocation.token_symbol.clone(),
+                wallet_address: wallet.clone(),
+                matched_cents: share,
+                tokens_credited: 0.0,
+                status: RoundPayoutStatus::Pending,
+                error: None,
+                updated_at: now,
+            });
+        }
+    }
+    payouts
+}
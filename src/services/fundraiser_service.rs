@@ -0,0 +1,100 @@
+use actix_web::web;
+use log::info;
+use mongodb::bson::oid::ObjectId;
+use crate::models::{ApiError, FundraiserPage, FundraiserProgress, FundraiserStatus, CreateFundraiserRequest, UpdateFundraiserRequest, LeaderboardEntry, MAX_FUNDRAISERS_PER_WALLET};
+use crate::models::cause::Cause;
+use crate::services::MongoDBService;
+use crate::utils::validation;
+
+/// Supporters' personal fundraising pages. A page has no money of its own: donations through
+/// it are ordinary donations to its cause that name the page in their checkout metadata, and
+/// are totalled per page for its progress and the cause's leaderboard.
+#[derive(Clone)]
+pub struct FundraiserService {
+    mongodb: web::Data<MongoDBService>,
+}
+
+impl FundraiserService {
+    pub fn new(mongodb: web::Data<MongoDBService>) -> Self {
+        Self { mongodb }
+    }
+
+    pub async fn create(&self, cause: &Cause, request: &CreateFundraiserRequest, owner_address: &str) -> Result<FundraiserPage, ApiError> {
+        if !cause.accepts_donations() {
+            return Err(ApiError::ValidationError(format!("This cause is {} and is not accepting donations", cause.status)));
+        }
+        if cause.stripe_account_id.is_none() {
+            return Err(ApiError::ValidationError("This cause does not have a connected Stripe account".to_string()));
+        }
+        if self.mongodb.count_active_fundraisers(owner_address).await? as usize >= MAX_FUNDRAISERS_PER_WALLET {
+            return Err(ApiError::Conflict(format!("You can run at most {} fundraisers at once; close one first", MAX_FUNDRAISERS_PER_WALLET)));
+        }
+        let now = chrono::Utc::now().timestamp();
+        let mut page = FundraiserPage {
+            id: None,
+            slug: validation::slug(&request.slug).map_err(ApiError::ValidationError)?,
+            cause_id: request.cause_id.clone(),
+            owner_address: owner_address.to_string(),
+            title: request.title.trim().to_string(),
+            story: request.story.clone(),
+            goal_cents: request.goal_cents,
+            status: FundraiserStatus::Active,
+            created_at: now,
+            updated_at: now,
+        };
+        page.id = Some(self.mongodb.create_fundraiser(&page).await?);
+        info!("{} created fundraiser {} for cause {}", owner_address, page.slug, page.cause_id);
+        Ok(page)
+    }
+
+    pub async fn get(&self, slug: &str) -> Result<FundraiserPage, ApiError> {
+        self.mongodb.get_fundraiser_by_slug(&slug.to_ascii_lowercase()).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Fundraiser {} not found", slug)))
+    }
+
+    pub async fn update(&self, mut page: FundraiserPage, request: &UpdateFundraiserRequest) -> Result<FundraiserPage, ApiError> {
+        if page.status == FundraiserStatus::Closed && request.status != Some(FundraiserStatus::Active) {
+            return Err(ApiError::Conflict("This fundraiser is closed; reopen it with status \"active\" to change it".to_string()));
+        }
+        if request.status == Some(FundraiserStatus::Active) && page.status == FundraiserStatus::Closed {
+            let active = self.mongodb.count_active_fundraisers(&page.owner_address).await? as usize;
+            if active >= MAX_FUNDRAISERS_PER_WALLET {
+                return Err(ApiError::Conflict(format!("You can run at most {} fundraisers at once; close one first", MAX_FUNDRAISERS_PER_WALLET)));
+            }
+        }
+        if let Some(title) = &request.title {
+            page.title = title.trim().to_string();
+        }
+        if request.story.is_some() {
+            page.story = request.story.clone();
+        }
+        page.goal_cents = request.goal_cents.unwrap_or(page.goal_cents);
+        page.status = request.status.unwrap_or(page.status);
+        page.updated_at = chrono::Utc::now().timestamp();
+        self.mongodb.replace_fundraiser(&page).await?;
+        Ok(page)
+    }
+
+    pub async fn progress(&self, page: FundraiserPage) -> Result<FundraiserProgress, ApiError> {
+        let page_id = page.id.map(|id| id.to_hex()).unwrap_or_default();
+        let (donations, donors, raised_cents, _) = self.mongodb.get_attributed_totals("fundraiser_id", &page_id).await?;
+        Ok(FundraiserProgress {
+            raised_cents,
+            donations,
+            donors,
+            percent_of_goal: (raised_cents as f64 * 10000.0 / page.goal_cents as f64).round() / 100.0,
+            page,
+        })
+    }
+
+    /// The cause's pages that have raised the most, at most `limit` (default 10, up to 100)
+    pub async fn leaderboard(&self, cause: &Cause, limit: Option<i64>) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        self.mongodb.get_fundraiser_leaderboard(&cause.token_symbol, limit.unwrap_or(10).clamp(1, 100)).await
+    }
+
+    /// The cause a page raises for
+    pub fn cause_id(page: &FundraiserPage) -> Result<ObjectId, ApiError> {
+        ObjectId::parse_str(&page.cause_id)
+            .map_err(|e| ApiError::InternalError(format!("Fundraiser has an invalid cause ID: {}", e)))
+    }
+}
@@ -0,0 +1,226 @@
+use actix_web::web;
+use log::{info, warn, error};
+use mongodb::bson::oid::ObjectId;
+use crate::models::{
+    ApiError, User, Payment, PaymentStatus, Invoice, InvoiceStatus, CreateInvoiceRequest, MAX_INVOICE_LINE_ITEMS,
+};
+use crate::models::payment::PaymentState;
+use crate::services::{MongoDBService, PushService};
+
+/// Overdue invoices reminded per tick
+const BATCH_SIZE: i64 = 100;
+
+/// Time between reminders for the same overdue invoice
+const REMINDER_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// Longest description, reference or note
+const MAX_TEXT_CHARS: usize = 140;
+
+/// Invoices from vendors to customers. Paying one makes a payment code with the customer
+/// assigned, paid through supplement and sign as usual; the invoice is marked paid when
+/// that payment completes. Overdue invoices get a reminder a day, a few times over.
+#[derive(Clone)]
+pub struct InvoiceService {
+    mongodb: web::Data<MongoDBService>,
+    push_service: web::Data<PushService>,
+}
+
+impl InvoiceService {
+    pub fn new(mongodb: web::Data<MongoDBService>, push_service: web::Data<PushService>) -> Self {
+        Self { mongodb, push_service }
+    }
+
+    pub async fn issue(&self, vendor_address: &str, request: &CreateInvoiceRequest) -> Result<Invoice, ApiError> {
+        if request.customer_address == vendor_address {
+            return Err(ApiError::ValidationError("Cannot invoice yourself".to_string()));
+        }
+        if request.line_items.is_empty() || request.line_items.len() > MAX_INVOICE_LINE_ITEMS {
+            return Err(ApiError::ValidationError(format!("An invoice must have between 1 and {} line items", MAX_INVOICE_LINE_ITEMS)));
+        }
+        let mut line_items = request.line_items.clone();
+        for item in line_items.iter_mut() {
+            item.description = check_text("Description", Some(&item.description))?
+                .ok_or_else(|| ApiError::ValidationError("Every line item needs a description".to_string()))?;
+            if !item.quantity.is_finite() || item.quantity <= 0.0 {
+                return Err(ApiError::ValidationError("Quantity must be greater than zero".to_string()));
+            }
+            if !item.unit_price_usd.is_finite() || item.unit_price_usd < 0.0 {
+                return Err(ApiError::ValidationError("Unit price can't be negative".to_string()));
+            }
+        }
+        let total_usd = (line_items.iter().map(|item| item.quantity * item.unit_price_usd).sum::<f64>() * 100.0).round() / 100.0;
+        if total_usd <= 0.0 {
+            return Err(ApiError::ValidationError("Invoice total must be greater than zero".to_string()));
+        }
+        let now = chrono::Utc::now().timestamp();
+        if request.due_at <= now {
+            return Err(ApiError::ValidationError("due_at must be in the future".to_string()));
+        }
+        let reference = check_text("Reference", request.reference.as_deref())?;
+        let note = check_text("Note", request.note.as_deref())?;
+
+        let vendor = active_user(&self.mongodb, vendor_address).await?;
+        active_user(&self.mongodb, &request.customer_address).await?;
+
+        let invoice = self.mongodb.create_invoice(Invoice {
+            id: None,
+            vendor_address: vendor.wallet_address,
+            vendor_name: vendor.username,
+            customer_address: request.customer_address.clone(),
+            reference,
+            line_items,
+            total_usd,
+            note,
+            due_at: request.due_at,
+            status: InvoiceStatus::Open,
+            created_at: now,
+            updated_at: now,
+            payment_id: None,
+            paid_at: None,
+            reminders_sent: 0,
+            last_reminder_at: None,
+        }).await?;
+
+        info!("Invoice {:?} for ${} from {} to {}", invoice.id, invoice.total_usd, invoice.vendor_address, invoice.customer_address);
+        self.push_service.invoice_received(&invoice).await;
+        Ok(invoice)
+    }
+
+    /// The payment code for paying an open invoice from `payer_address`. Asking again
+    /// returns the same code while it's still payable.
+    pub async fn pay(&self, invoice: &Invoice, payer_address: &str) -> Result<String, ApiError> {
+        let id = invoice_id(invoice)?;
+        if invoice.status != InvoiceStatus::Open {
+            return Err(ApiError::Conflict(format!("Invoice is already {}", invoice.status)));
+        }
+        if let Some(payment_id) = &invoice.payment_id {
+            if let Some(payment) = self.mongodb.get_payment(payment_id).await? {
+                match payment.state(chrono::Utc::now().timestamp()) {
+                    PaymentState::Processing | PaymentState::Completed => return Ok(payment.payment_id),
+                    PaymentState::Active => match payment.customer_address.as_deref() {
+                        Some(customer) if customer == payer_address => return Ok(payment.payment_id),
+                        // Started from another linked wallet; pay from this one instead
+                        Some(customer) => {
+                            let payer = self.mongodb.get_user_by_wallet(payer_address).await?;
+                            if !self.mongodb.reassign_payment_customer(&payment.payment_id, customer, payer_address, payer.map(|payer| payer.username)).await? {
+                                return Err(ApiError::Conflict("Invoice is being paid from another wallet".to_string()));
+                            }
+                            return Ok(payment.payment_id);
+                        },
+                        None => {},
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        let vendor = self.mongodb.get_user_by_wallet(&invoice.vendor_address).await?;
+        let payer = self.mongodb.get_user_by_wallet(payer_address).await?;
+        let payment_id = self.mongodb.generate_payment_id();
+        self.mongodb.create_payment(Payment {
+            id: None,
+            payment_id: payment_id.clone(),
+            vendor_address: invoice.vendor_address.clone(),
+            vendor_name: invoice.vendor_name.clone(),
+            price_usd: invoice.total_usd,
+            customer_address: Some(payer_address.to_string()),
+            customer_username: payer.map(|payer| payer.username),
+            status: PaymentStatus::Created,
+            created_at: chrono::Utc::now().timestamp(),
+            vendor_valuations: None,
+            discount_consumption: None,
+            computed_payment: None,
+            initial_payment_bundle: None,
+            recepient_verified: vendor.map_or(false, |vendor| vendor.is_verified),
+            executor_tx_id: None,
+            submitted_at: None,
+            finality_checked_at: None,
+            failure_reason: None,
+            payment_request_id: None,
+            loyalty_redemption: None,
+            loyalty_points_earned: None,
+            promo: None,
+            escrow: None,
+            schedule_id: None,
+            batch_id: None,
+            invoice_id: Some(id.to_hex()),
+            valuation_overrides: None,
+            budget_consumed: false,
+            splits: Vec::new(),
+            split_legs: Vec::new(),
+            authorization: None,
+            vendor_slug: None,
+            short_code: None,
+        }).await?;
+        if !self.mongodb.set_invoice_payment(&id, &payment_id).await? {
+            // Cancelled or paid while the code was being made
+            self.mongodb.delete_unsigned_payment(&payment_id).await?;
+            return Err(ApiError::Conflict("Invoice is no longer open".to_string()));
+        }
+        info!("Invoice {} being paid with payment {}", id, payment_id);
+        Ok(payment_id)
+    }
+
+    /// Withdraw an open invoice, dropping its unpaid payment code. Fails once the customer
+    /// has signed. The code is deleted only while unsigned, and the invoice cancelled only
+    /// while it still points at that code, so a payment racing the cancel either wins or
+    /// can't be signed.
+    pub async fn cancel(&self, invoice: &Invoice) -> Result<Invoice, ApiError> {
+        let id = invoice_id(invoice)?;
+        if invoice.status != InvoiceStatus::Open {
+            return Err(ApiError::Conflict(format!("Invoice is already {}", invoice.status)));
+        }
+        if let Some(payment_id) = &invoice.payment_id {
+            if !self.mongodb.delete_unsigned_payment(payment_id).await? {
+                let signed = self.mongodb.get_payment(payment_id).await?
+                    .map_or(false, |payment| matches!(payment.status, PaymentStatus::Submitted | PaymentStatus::Completed | PaymentStatus::Authorized));
+                if signed {
+                    return Err(ApiError::Conflict("Invoice is already being paid".to_string()));
+                }
+            }
+        }
+        let cancelled = self.mongodb.cancel_open_invoice(&id, invoice.payment_id.as_deref()).await?
+            .ok_or_else(|| ApiError::Conflict("Invoice was updated, please reload it".to_string()))?;
+        info!("Invoice {} cancelled", id);
+        Ok(cancelled)
+    }
+
+    pub async fn send_reminders(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let invoices = match self.mongodb.get_invoices_needing_reminder(now, now - REMINDER_INTERVAL_SECS, BATCH_SIZE).await {
+            Ok(invoices) => invoices,
+            Err(e) => {
+                error!("Failed to load overdue invoices: {}", e);
+                return;
+            }
+        };
+
+        for invoice in invoices {
+            let Some(id) = invoice.id else { continue };
+            match self.mongodb.mark_invoice_reminded(&id, now).await {
+                Ok(true) => self.push_service.invoice_overdue(&invoice).await,
+                Ok(false) => {},
+                Err(e) => warn!("Failed to record reminder for invoice {}: {}", id, e),
+            }
+        }
+    }
+}
+
+/// Trimmed text, None when blank
+fn check_text(field: &str, text: Option<&str>) -> Result<Option<String>, ApiError> {
+    let text = text.map(str::trim).filter(|t| !t.is_empty());
+    if text.map_or(false, |t| t.chars().count() > MAX_TEXT_CHARS) {
+        return Err(ApiError::ValidationError(format!("{} must be at most {} characters", field, MAX_TEXT_CHARS)));
+    }
+    Ok(text.map(str::to_string))
+}
+
+fn invoice_id(invoice: &Invoice) -> Result<ObjectId, ApiError> {
+    invoice.id.ok_or_else(|| ApiError::InternalError("Invoice has no ID".to_string()))
+}
+
+async fn active_user(db: &MongoDBService, wallet_address: &str) -> Result<User, ApiError> {
+    db.get_user_by_wallet(wallet_address).await?
+        .filter(|user| user.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)))
+}
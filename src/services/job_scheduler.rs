@@ -0,0 +1,194 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use futures_util::future::LocalBoxFuture;
+use log::{debug, error, info, warn};
+use crate::config::SchedulerConfig;
+use crate::models::{ApiError, SchedulerStatus};
+use crate::services::MongoDBService;
+use crate::utils::cron::CronSchedule;
+use crate::utils::replica::replica_instance_id;
+
+/// When a job runs: on a fixed interval, or on a cron expression from JOB_SCHEDULES
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobSchedule {
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl JobSchedule {
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            JobSchedule::Every(interval) => chrono::Duration::from_std(*interval).ok().map(|interval| after + interval),
+            JobSchedule::Cron(schedule) => schedule.next_after(after),
+        }
+    }
+}
+
+impl fmt::Display for JobSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobSchedule::Every(interval) => write!(f, "every {}s", interval.as_secs()),
+            JobSchedule::Cron(schedule) => write!(f, "{}", schedule),
+        }
+    }
+}
+
+type JobFn = Box<dyn Fn() -> LocalBoxFuture<'static, Result<(), ApiError>>>;
+
+struct Job {
+    name: String,
+    schedule: JobSchedule,
+    run: JobFn,
+}
+
+/// Runs the periodic sweeps (expiry, reconciliation, settlements, reminders) on the one
+/// replica holding the scheduler lease, recording each job's runs in `scheduled_jobs`
+pub struct JobScheduler {
+    mongodb: web::Data<MongoDBService>,
+    config: SchedulerConfig,
+    instance_id: String,
+    jobs: Vec<Job>,
+}
+
+impl JobScheduler {
+    pub fn new(mongodb: web::Data<MongoDBService>, config: SchedulerConfig) -> Self {
+        let instance_id = replica_instance_id();
+        Self { mongodb, config, instance_id, jobs: Vec::new() }
+    }
+
+    /// Run `job` every `interval`, or on the job's cron expression in JOB_SCHEDULES. A zero
+    /// interval without a cron expression leaves the job disabled.
+    pub fn register<F, Fut>(&mut self, name: &str, interval: Duration, job: F)
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), ApiError>> + 'static,
+    {
+        let schedule = match self.config.schedules.get(name) {
+            Some(schedule) => JobSchedule::Cron(schedule.clone()),
+            None if interval.is_zero() => {
+                info!("Job {} is disabled", name);
+                return;
+            }
+            None => JobSchedule::Every(interval),
+        };
+        self.jobs.push(Job {
+            name: name.to_string(),
+            schedule,
+            run: Box::new(move || Box::pin(job())),
+        });
+    }
+
+    /// Contend for the lease and run every registered job in the background. Runs are
+    /// fenced with the lease's epoch, so one that starts after the lease was lost is refused.
+    pub fn start(self) {
+        let is_leader = Arc::new(AtomicBool::new(!self.config.leader_election));
+        let epoch = Arc::new(AtomicI64::new(0));
+        if self.config.leader_election {
+            self.start_lease(is_leader.clone(), epoch.clone());
+        }
+        let leader_election = self.config.leader_election;
+        for job in self.jobs {
+            info!("Scheduling job {} ({})", job.name, job.schedule);
+            let mongodb = self.mongodb.clone();
+            let instance_id = self.instance_id.clone();
+            let is_leader = is_leader.clone();
+            let epoch = epoch.clone();
+            actix_web::rt::spawn(async move {
+                let mut registered = false;
+                loop {
+                    let Some(next_run_at) = job.schedule.next_after(Utc::now()) else {
+                        warn!("Job {} ({}) has no further runs", job.name, job.schedule);
+                        return;
+                    };
+                    // Listed with its next run before the first one, which may be days away
+                    if !registered && is_leader.load(Ordering::Relaxed) {
+                        match mongodb.register_scheduled_job(&job.name, &job.schedule.to_string(), Some(next_run_at.timestamp())).await {
+                            Ok(()) => registered = true,
+                            Err(e) => error!("Failed to record schedule of job {}: {}", job.name, e),
+                        }
+                    }
+                    actix_web::rt::time::sleep((next_run_at - Utc::now()).to_std().unwrap_or_default()).await;
+                    if is_leader.load(Ordering::Relaxed) {
+                        let fence = leader_election.then(|| epoch.load(Ordering::Relaxed));
+                        run_job(&mongodb, &instance_id, &job, fence).await;
+                    }
+                }
+            });
+        }
+    }
+
+    /// Renew the lease at a third of its length, so a leader that stops renewing is replaced
+    /// within one lease
+    fn start_lease(&self, is_leader: Arc<AtomicBool>, epoch: Arc<AtomicI64>) {
+        let mongodb = self.mongodb.clone();
+        let instance_id = self.instance_id.clone();
+        let lease = self.config.lease;
+        info!("Contending for the scheduler lease as {} ({:?} lease)", instance_id, lease);
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(lease / 3);
+            loop {
+                ticker.tick().await;
+                let expires_at = Utc::now().timestamp() + lease.as_secs() as i64;
+                let leading = match mongodb.acquire_scheduler_lease(&instance_id, expires_at).await {
+                    Ok(Some(lease_epoch)) => {
+                        epoch.store(lease_epoch, Ordering::Relaxed);
+                        true
+                    },
+                    Ok(None) => false,
+                    Err(e) => {
+                        // Stand down rather than risk two leaders while the lease can't be seen
+                        error!("Failed to renew the scheduler lease: {}", e);
+                        false
+                    }
+                };
+                if leading != is_leader.swap(leading, Ordering::Relaxed) {
+                    info!("{} {} the scheduler lease", instance_id, if leading { "took" } else { "lost" });
+                }
+            }
+        });
+    }
+}
+
+async fn run_job(mongodb: &MongoDBService, instance_id: &str, job: &Job, fence: Option<i64>) {
+    let started = Instant::now();
+    match mongodb.start_scheduled_job_run(&job.name, &job.schedule.to_string(), Utc::now().timestamp(), instance_id, fence).await {
+        Ok(true) => {},
+        Ok(false) => {
+            warn!("Skipping job {}: {} no longer holds the scheduler lease at epoch {:?}", job.name, instance_id, fence);
+            return;
+        },
+        // Fenced runs need the lease confirmed; unfenced ones go ahead unrecorded
+        Err(e) if fence.is_some() => {
+            error!("Skipping job {}: failed to record its start: {}", job.name, e);
+            return;
+        },
+        Err(e) => error!("Failed to record start of job {}: {}", job.name, e),
+    }
+    let result = (job.run)().await;
+    let duration_ms = started.elapsed().as_millis() as i64;
+    let error = result.err().map(|e| e.to_string());
+    match &error {
+        Some(e) => error!("Job {} failed after {}ms: {}", job.name, duration_ms, e),
+        None => debug!("Job {} finished in {}ms", job.name, duration_ms),
+    }
+    let next_run_at = job.schedule.next_after(Utc::now()).map(|at| at.timestamp());
+    if let Err(e) = mongodb.finish_scheduled_job_run(&job.name, duration_ms, error.as_deref(), next_run_at, fence).await {
+        error!("Failed to record run of job {}: {}", job.name, e);
+    }
+}
+
+/// Every job's schedule and last run, and which replica is running them
+pub async fn scheduler_status(mongodb: &MongoDBService) -> Result<SchedulerStatus, ApiError> {
+    let now = Utc::now().timestamp();
+    let lease = mongodb.get_scheduler_lease().await?.filter(|lease| lease.expires_at > now);
+    Ok(SchedulerStatus {
+        leader: lease.as_ref().map(|lease| lease.holder.clone()),
+        lease_expires_at: lease.map(|lease| lease.expires_at),
+        jobs: mongodb.get_scheduled_jobs().await?,
+    })
+}
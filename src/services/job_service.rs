@@ -0,0 +1,129 @@
+use std::future::Future;
+use std::time::Duration;
+use actix_web::web;
+use log::{error, info, warn};
+use mongodb::bson;
+use uuid::Uuid;
+use crate::models::{ApiError, Job, JobKind, JobStatus};
+use crate::services::MongoDBService;
+
+/// How often a running job records that it's still alive
+const HEARTBEAT_SECS: u64 = 30;
+
+/// A running job that hasn't updated for this long was interrupted, e.g. by a restart
+pub const STALE_JOB_SECS: i64 = 120;
+
+/// How long a finished job, and its result, can still be fetched
+const JOB_RETENTION_SECS: i64 = 7 * 24 * 3600;
+
+/// Runs long operations in the background as jobs in the `jobs` collection, which clients
+/// poll at `GET /jobs/{id}`
+#[derive(Clone)]
+pub struct JobService {
+    mongodb: web::Data<MongoDBService>,
+}
+
+/// How a running job reports how far it has got
+#[derive(Clone)]
+pub struct JobProgress {
+    mongodb: web::Data<MongoDBService>,
+    job_id: String,
+}
+
+impl JobProgress {
+    /// Record `completed` of `total` items done. Failing to record progress doesn't stop the job.
+    pub async fn report(&self, completed: usize, total: usize) {
+        if let Err(e) = self.mongodb.set_job_progress(&self.job_id, completed as i64, total as i64).await {
+            error!("Failed to record progress of job {}: {}", self.job_id, e);
+        }
+    }
+}
+
+impl JobService {
+    pub fn new(mongodb: web::Data<MongoDBService>) -> Self {
+        Self { mongodb }
+    }
+
+    /// Record a job for `created_by` and run `task` in the background, storing what it
+    /// returns as the job's result. Returns the job as started.
+    pub async fn start<F, Fut>(&self, kind: JobKind, created_by: &str, task: F) -> Result<Job, ApiError>
+    where
+        F: FnOnce(JobProgress) -> Fut + 'static,
+        Fut: Future<Output = Result<serde_json::Value, ApiError>> + 'static,
+    {
+        let now = chrono::Utc::now().timestamp();
+        let job = Job {
+            id: None,
+            job_id: Uuid::new_v4().to_string(),
+            kind,
+            status: JobStatus::Running,
+            created_by: created_by.to_string(),
+            completed: 0,
+            total: None,
+            result: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            finished_at: None,
+        };
+        self.mongodb.create_job(&job).await?;
+        info!("{} started {:?} job {}", created_by, kind, job.job_id);
+
+        let mongodb = self.mongodb.clone();
+        let job_id = job.job_id.clone();
+        actix_web::rt::spawn(async move {
+            loop {
+                actix_web::rt::time::sleep(Duration::from_secs(HEARTBEAT_SECS)).await;
+                match mongodb.touch_job(&job_id).await {
+                    Ok(true) => {},
+                    Ok(false) => break,
+                    Err(e) => error!("Failed to record heartbeat of job {}: {}", job_id, e),
+                }
+            }
+        });
+
+        let mongodb = self.mongodb.clone();
+        let job_id = job.job_id.clone();
+        actix_web::rt::spawn(async move {
+            let progress = JobProgress { mongodb: mongodb.clone(), job_id: job_id.clone() };
+            let outcome = task(progress).await;
+            match &outcome {
+                Ok(_) => info!("Job {} succeeded", job_id),
+                Err(e) => error!("Job {} failed: {}", job_id, e),
+            }
+            if let Err(e) = mongodb.finish_job(&job_id, outcome.map_err(|e| e.to_string()), expires_at()).await {
+                error!("Failed to record outcome of job {}: {}", job_id, e);
+            }
+        });
+        Ok(job)
+    }
+
+    /// A job by ID. A running job whose process stopped is reported, and recorded, as failed.
+    pub async fn get(&self, job_id: &str) -> Result<Option<Job>, ApiError> {
+        match self.mongodb.get_job(job_id).await? {
+            Some(job) if job.status == JobStatus::Running && job.updated_at < stale_before() => {
+                self.fail_stale_jobs().await?;
+                self.mongodb.get_job(job_id).await
+            },
+            job => Ok(job),
+        }
+    }
+
+    /// Fail every running job that stopped updating, so clients polling it stop waiting.
+    /// Run at startup for jobs the previous process left running.
+    pub async fn fail_stale_jobs(&self) -> Result<u64, ApiError> {
+        let failed = self.mongodb.fail_stale_jobs(stale_before(), expires_at()).await?;
+        if failed > 0 {
+            warn!("Failed {} jobs interrupted before they finished", failed);
+        }
+        Ok(failed)
+    }
+}
+
+fn stale_before() -> i64 {
+    chrono::Utc::now().timestamp() - STALE_JOB_SECS
+}
+
+fn expires_at() -> bson::DateTime {
+    bson::DateTime::from_millis((chrono::Utc::now().timestamp() + JOB_RETENTION_SECS) * 1000)
+}
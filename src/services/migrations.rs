@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+use log::info;
+use crate::models::{ApiError, SchemaMigration};
+use crate::services::MongoDBService;
+
+/// A versioned change to stored data, applied once per database in version order
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+}
+
+/// Every migration, oldest first. Append new ones with the next version and a matching
+/// arm in `apply`; never renumber or remove one that has shipped. Indexes are still
+/// created when MongoDBService starts, since creating them is idempotent; backfills and
+/// other changes to existing documents go here.
+///
+/// Two instances starting together may both apply the same migration before either
+/// records it, so each must be safe to run twice.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "backfill_user_defaults" },
+    Migration { version: 2, name: "backfill_username_keys" },
+    Migration { version: 3, name: "backfill_payment_flags" },
+    Migration { version: 4, name: "payout_events_per_currency" },
+    Migration { version: 5, name: "preference_template_slots" },
+];
+
+/// Apply the migrations this database hasn't had yet, recording each in
+/// `schema_migrations`. Stops at the first that fails, leaving it to run again on the
+/// next start.
+pub async fn run_migrations(db: &MongoDBService) -> Result<(), ApiError> {
+    let applied: HashSet<i32> = db.get_applied_migrations().await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+
+    for migration in MIGRATIONS.iter().filter(|migration| !applied.contains(&migration.version)) {
+        info!("Applying migration {} {}", migration.version, migration.name);
+        let summary = apply(db, migration.version).await
+            .map_err(|e| ApiError::InternalError(format!("Migration {} {} failed: {}", migration.version, migration.name, e)))?;
+        db.record_migration(&SchemaMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            summary: summary.clone(),
+            applied_at: chrono::Utc::now().timestamp(),
+        }).await?;
+        info!("Applied migration {} {}: {}", migration.version, migration.name, summary);
+    }
+    Ok(())
+}
+
+async fn apply(db: &MongoDBService, version: i32) -> Result<String, ApiError> {
+    match version {
+        1 => {
+            let modified = db.backfill_user_defaults().await?;
+            Ok(format!("{} user fields backfilled", modified))
+        },
+        2 => {
+            let (backfilled, skipped) = db.backfill_username_keys().await?;
+            Ok(format!("{} usernames keyed, {} clashing left without a key", backfilled, skipped))
+        },
+        3 => {
+            let modified = db.backfill_payment_flags().await?;
+            Ok(format!("{} payment flags backfilled", modified))
+        },
+        4 => {
+            let dropped = db.drop_payout_event_id_index().await?;
+            Ok(if dropped { "event_id index dropped".to_string() } else { "event_id index already gone".to_string() })
+        },
+        5 => {
+            let modified = db.backfill_preference_template_slots().await?;
+            Ok(format!("{} preference templates given a slot", modified))
+        },
+        _ => Err(ApiError::InternalError(format!("No migration with version {}", version))),
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use async_trait::async_trait;
+use delta_executor_sdk::base::{crypto::Ed25519PubKey, vaults::{ReadableVault, Vault}, verifiable::VerifiableType};
+use delta_executor_sdk::base::verifiable::debit_allowance::DebitAllowance;
+use crate::services::{ExecutionStatus, ExecutorBackend, ExecutorError};
+use crate::utils::circuit_breaker::{BreakerState, BreakerStatus};
+use crate::utils::signed_payload::find_messages;
+
+/// In-memory stand-in for the executor, for tests. Serves the vaults a test puts in it,
+/// records every submission, and finalizes submitted transactions unless told otherwise.
+/// Vault contents are whatever the test set: submissions don't move balances or nonces.
+/// Debit allowances are applied in order, so several from one vault in a submission must
+/// take consecutive nonces after the vault's.
+#[derive(Default)]
+pub struct MockExecutor {
+    state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    vaults: HashMap<String, Vault>,
+    submissions: Vec<serde_json::Value>,
+    statuses: HashMap<String, ExecutionStatus>,
+    failures: VecDeque<ExecutorError>,
+    unavailable: bool,
+}
+
+impl MockExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_vault(&self, pubkey: &Ed25519PubKey, vault: Vault) {
+        self.state().vaults.insert(pubkey.to_string(), vault);
+    }
+
+    pub fn remove_vault(&self, pubkey: &Ed25519PubKey) {
+        self.state().vaults.remove(&pubkey.to_string());
+    }
+
+    /// Reject the next submission with `error`; queued errors are used in order
+    pub fn fail_next_submission(&self, error: ExecutorError) {
+        self.state().failures.push_back(error);
+    }
+
+    /// Answer every call as if the executor couldn't be reached
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.state().unavailable = unavailable;
+    }
+
+    /// Report `status` for a submitted transaction from now on
+    pub fn set_status(&self, tx_id: &str, status: ExecutionStatus) {
+        self.state().statuses.insert(tx_id.to_string(), status);
+    }
+
+    /// Every accepted submission so far, as the JSON the executor would have received
+    pub fn submissions(&self) -> Vec<serde_json::Value> {
+        self.state().submissions.clone()
+    }
+}
+
+#[async_trait]
+impl ExecutorBackend for MockExecutor {
+    async fn fetch_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, ExecutorError> {
+        let state = self.state();
+        if state.unavailable {
+            return Err(ExecutorError::Unavailable("mock executor unavailable".to_string()));
+        }
+        Ok(state.vaults.get(&pubkey.to_string()).cloned())
+    }
+
+    async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<Option<String>, ExecutorError> {
+        let mut state = self.state();
+        if state.unavailable {
+            return Err(ExecutorError::Unavailable("mock executor unavailable".to_string()));
+        }
+        if let Some(error) = state.failures.pop_front() {
+            return Err(error);
+        }
+        let submission = serde_json::to_value(&verifiables)
+            .map_err(|e| ExecutorError::Rejected { reason: format!("unserializable submission: {}", e) })?;
+        check_nonces(&state, &submission)?;
+        state.submissions.push(submission);
+        let tx_id = format!("mock-tx-{}", state.submissions.len());
+        state.statuses.insert(tx_id.clone(), ExecutionStatus::Finalized);
+        Ok(Some(tx_id))
+    }
+
+    async fn get_status(&self, tx_id: &str) -> Result<ExecutionStatus, ExecutorError> {
+        let state = self.state();
+        if state.unavailable {
+            return Err(ExecutorError::Unavailable("mock executor unavailable".to_string()));
+        }
+        Ok(state.statuses.get(tx_id).cloned().unwrap_or(ExecutionStatus::Pending))
+    }
+
+    fn breaker_status(&self) -> BreakerStatus {
+        BreakerStatus { state: BreakerState::Closed, consecutive_failures: 0, retry_in_secs: None }
+    }
+}
+
+/// Each debit allowance must take the nonce after its vault's last one: the vault's own
+/// for the first (when the test set the vault), then the previous allowance's from the
+/// same vault in this submission
+fn check_nonces(state: &MockState, submission: &serde_json::Value) -> Result<(), ExecutorError> {
+    let mut nonces: HashMap<String, u64> = HashMap::new();
+    for message in find_messages(submission, &["debited", "credited", "allowances"]) {
+        let Ok(allowance) = serde_json::from_value::<DebitAllowance>(message.clone()) else { continue };
+        let debited = allowance.debited.pubkey().to_string();
+        let last = nonces.get(&debited).copied().or_else(|| state.vaults.get(&debited).map(|vault| vault.nonce()));
+        if last.map_or(false, |last| allowance.new_nonce != last + 1) {
+            return Err(ExecutorError::NonceConflict(format!("nonce {} of {} is out of order", allowance.new_nonce, debited)));
+        }
+        nonces.insert(debited, allowance.new_nonce);
+    }
+    Ok(())
+}
@@ -4,10 +4,71 @@ mod wallet_service;
 mod executor_client;
 pub mod cause_service;
 mod webhook_service;
+mod reconciliation_service;
+mod email_service;
+mod draft_reminder_service;
+mod funding_round_service;
+mod payment_intent_service;
+mod stripe_customer_service;
+mod payment_finality_service;
+mod vault_provisioning_service;
+mod push_service;
+mod push_credentials;
+mod voucher_service;
+mod escrow_service;
+mod authorization_service;
+mod dispute_service;
+mod payment_schedule_service;
+mod invoice_service;
+mod webhook_queue_service;
+mod feature_flag_service;
+mod job_scheduler;
+mod job_service;
+mod campaign_service;
+mod fundraiser_service;
+mod organization_service;
+mod vendor_service;
+mod shared_state;
+mod migrations;
+mod stripe_api;
+#[cfg(any(test, feature = "test-harness"))]
+mod mock_executor;
+#[cfg(any(test, feature = "test-harness"))]
+mod fake_stripe;
 
 pub use mongodb::MongoDBService;
-pub use token_service::TokenService;
-pub use wallet_service::WalletService;
-pub use executor_client::ExecutorClient;
+pub use token_service::{TokenService, TransferError};
+pub use wallet_service::{WalletService, WalletError, TokenInfo};
+pub use executor_client::{ExecutorClient, ExecutorBackend, ExecutionStatus, ExecutorError, executor_base_url};
 pub use cause_service::CauseService;
-pub use webhook_service::WebhookService;
\ No newline at end of file
+pub use webhook_service::WebhookService;
+pub use reconciliation_service::ReconciliationService;
+pub use email_service::EmailService;
+pub use draft_reminder_service::DraftReminderService;
+pub use funding_round_service::FundingRoundService;
+pub use payment_intent_service::PaymentIntentService;
+pub use stripe_customer_service::StripeCustomerService;
+pub use payment_finality_service::PaymentFinalityService;
+pub use vault_provisioning_service::VaultProvisioningService;
+pub use push_service::PushService;
+pub use voucher_service::VoucherService;
+pub use escrow_service::EscrowService;
+pub use authorization_service::AuthorizationService;
+pub use dispute_service::DisputeService;
+pub use payment_schedule_service::PaymentScheduleService;
+pub use invoice_service::InvoiceService;
+pub use webhook_queue_service::WebhookQueueService;
+pub use feature_flag_service::FeatureFlagService;
+pub use job_scheduler::{JobScheduler, JobSchedule, scheduler_status};
+pub use job_service::{JobService, JobProgress};
+pub use campaign_service::CampaignService;
+pub use fundraiser_service::FundraiserService;
+pub use organization_service::OrganizationService;
+pub use vendor_service::VendorService;
+pub use shared_state::{SharedState, SharedEvent};
+pub use migrations::run_migrations;
+pub use stripe_api::{StripeApi, LiveStripe};
+#[cfg(any(test, feature = "test-harness"))]
+pub use mock_executor::MockExecutor;
+#[cfg(any(test, feature = "test-harness"))]
+pub use fake_stripe::FakeStripe;
@@ -2,11 +2,20 @@ use mongodb::{Client, Collection};
 use mongodb::bson::{self, doc, Document, oid::ObjectId};
 use mongodb::options::{ClientOptions, ServerApi, ServerApiVersion, IndexOptions};
 use mongodb::IndexModel;
-use crate::models::{ApiError, User, Preferences, CreateUserRequest, Payment, Token, TokenValuation, DiscountConsumption, TokenPayment, PaymentStatus, TransactionRecord, CauseDraft, DraftStatus, DepositRecord, PartneredVendor};
-use crate::models::cause::Cause;
+use crate::models::{ApiError, User, Preferences, CreateUserRequest, Payment, Token, TokenValuation, DiscountConsumption, TokenPayment, PaymentStatus, SubmittedAllowance, HeldAuthorization, TransactionRecord, CauseDraft, DraftStatus, DepositRecord, PendingDeposit, PendingDepositStatus, UnclaimedDeposit, UnclaimedDepositStatus, PartneredVendor, Role, UserDataExport, AnonymizationSummary, TokenIssuerKey, AuditLog, AuditLogQuery, AuditLogPage, DailySettlementReport, TokenSettlement, ReconciliationIssue, ReconciliationIssueQuery, WebhookFailure, WebhookFailureStatus, WebhookFailureQuery, WebhookEndpoint, ProcessedStripeEvent, WebhookJob, WebhookJobStatus, WebhookQueueQuery, WebhookQueueStatus, BlockedWord, MatchingPool, MatchingPoolStatus, MatchingPoolQuery, MatchEvent, MatchEventStatus, FundingRound, FundingRoundStatus, RoundAllocation, RoundContribution, RoundPayout, RoundPayoutStatus, Contact, MAX_CONTACTS, PaymentRequest, PaymentRequestStatus, Account, LinkedWallet, MAX_LINKED_WALLETS, DeviceToken, DevicePlatform, NotificationPreferences, PushNotification, Review, ReviewQuery, ReviewPage, VendorRating, LoyaltyProgram, LoyaltyAccount, LoyaltyRedemption, PromoCode, AppliedPromo, SplitLeg, Voucher, VoucherStatus, EscrowStatus, Dispute, DisputeStatus, DisputeRefundStatus, PaymentSchedule, ScheduleStatus, Invoice, InvoiceStatus, MAX_INVOICE_REMINDERS, PreferenceTemplate, PreferenceChange, PreferenceLedgerEntry, PreferenceLedgerKind, PreferenceLedgerQuery, PreferenceLedgerPage, MAX_PREFERENCE_TEMPLATES, PREFERENCE_HISTORY_LIMIT, SchemaMigration, Holding, ActivityEvent, ActivityQuery, ActivityPage, FeatureFlag, ScheduledJob, SchedulerLease, JobRunStatus, Job, JobStatus, EmbedToken, Campaign, CampaignStatus, FundraiserPage, FundraiserStatus, LeaderboardEntry, PayoutEvent, Organization, MAX_ORGANIZATION_ADMINS, VendorStripeAccount, PaymentCodeNamespace, UsedSignature, CreditReservation, CreditReservationStatus};
+use crate::models::payment::{ActivityItem, TransactionHistoryItem, TransactionHistoryQuery, TransactionDirection, PaymentState, VendorPaymentsQuery, VendorPaymentsPage, VendorPaymentItem, CauseDonationsQuery, CauseDonation, CauseDonationsPage, ReferrerTotals, PAYMENT_CODE_TTL_SECS};
+use crate::utils::pagination::{encode_cursor, decode_cursor};
+use crate::utils::profile::username_key;
+use crate::utils::payment_code::{parse_payment_code, PaymentCodeRef};
+use crate::utils::holdings::HoldingDelta;
+use crate::utils::etag::listing_etag;
+use crate::utils::preferences::{budget_changes, budget_value};
+use crate::models::cause::{Cause, CausePayout, CauseSummary, CauseListQuery, CauseListPage, CauseStatus, CauseReview, CreationSaga, CreationStep, StripeRequirements, RequirementsState, TeamMember, TeamMembership, TeamRole, MAX_TEAM_MEMBERS};
 use futures_util::{TryStreamExt, StreamExt};
 use crate::services::cause_service::UpdateCauseRequest;
+use crate::config::SandboxConfig;
 use std::env;
+use std::collections::HashMap;
 use rand::Rng;
 
 #[derive(Clone)]
@@ -19,15 +28,70 @@ pub struct MongoDBService {
     transaction_records: Collection<TransactionRecord>,
     deposit_records: Collection<DepositRecord>,
     partnered_vendors: Collection<PartneredVendor>,
+    token_keys: Collection<TokenIssuerKey>,
+    audit_logs: Collection<AuditLog>,
+    daily_reports: Collection<DailySettlementReport>,
+    reconciliation_issues: Collection<ReconciliationIssue>,
+    webhook_failures: Collection<WebhookFailure>,
+    processed_stripe_events: Collection<ProcessedStripeEvent>,
+    webhook_jobs: Collection<WebhookJob>,
+    blocked_words: Collection<BlockedWord>,
+    matching_pools: Collection<MatchingPool>,
+    match_events: Collection<MatchEvent>,
+    funding_rounds: Collection<FundingRound>,
+    round_contributions: Collection<RoundContribution>,
+    round_payouts: Collection<RoundPayout>,
+    pending_deposits: Collection<PendingDeposit>,
+    unclaimed_deposits: Collection<UnclaimedDeposit>,
+    contacts: Collection<Contact>,
+    payment_requests: Collection<PaymentRequest>,
+    accounts: Collection<Account>,
+    device_tokens: Collection<DeviceToken>,
+    push_notifications: Collection<PushNotification>,
+    reviews: Collection<Review>,
+    loyalty_programs: Collection<LoyaltyProgram>,
+    loyalty_accounts: Collection<LoyaltyAccount>,
+    promo_codes: Collection<PromoCode>,
+    vouchers: Collection<Voucher>,
+    disputes: Collection<Dispute>,
+    payment_schedules: Collection<PaymentSchedule>,
+    invoices: Collection<Invoice>,
+    preference_templates: Collection<PreferenceTemplate>,
+    preference_changes: Collection<PreferenceChange>,
+    preference_ledger: Collection<PreferenceLedgerEntry>,
+    submitted_allowances: Collection<SubmittedAllowance>,
+    held_authorizations: Collection<HeldAuthorization>,
+    schema_migrations: Collection<SchemaMigration>,
+    holdings: Collection<Holding>,
+    activities: Collection<ActivityEvent>,
+    feature_flags: Collection<FeatureFlag>,
+    scheduled_jobs: Collection<ScheduledJob>,
+    scheduler_leases: Collection<SchedulerLease>,
+    jobs: Collection<Job>,
+    embed_tokens: Collection<EmbedToken>,
+    campaigns: Collection<Campaign>,
+    fundraisers: Collection<FundraiserPage>,
+    payout_events: Collection<PayoutEvent>,
+    cause_payouts: Collection<CausePayout>,
+    organizations: Collection<Organization>,
+    used_signatures: Collection<UsedSignature>,
+    credit_reservations: Collection<CreditReservation>,
 }
 
 impl MongoDBService {
     pub async fn init() -> Result<Self, mongodb::error::Error> {
         // Get MongoDB URI from environment variable
         let uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
-        
+        // A sandbox keeps its data in a database of its own
+        let sandbox = SandboxConfig::from_env();
+        Self::connect(&uri, sandbox.database()).await
+    }
+
+    /// Connect to `database` on the server at `uri` and create its indexes, e.g. a
+    /// throwaway database in tests
+    pub async fn connect(uri: &str, database: &str) -> Result<Self, mongodb::error::Error> {
         // Parse options and configure client
-        let mut client_options = ClientOptions::parse(&uri).await?;
+        let mut client_options = ClientOptions::parse(uri).await?;
         
         // Set the server API version to V1
         let server_api = ServerApi::builder()
@@ -53,7 +117,7 @@ impl MongoDBService {
         log::info!("Successfully connected to MongoDB Atlas!");
         
         // Get database and collection
-        let db = client.database("index_wallets");
+        let db = client.database(database);
         let users = db.collection("users");
         let transactions = db.collection("transactions");
         let tokens = db.collection("tokens");
@@ -62,6 +126,54 @@ impl MongoDBService {
         let transaction_records = db.collection("transaction_records");
         let deposit_records = db.collection::<DepositRecord>("deposit_records");
         let partnered_vendors = db.collection::<PartneredVendor>("partnered_vendors");
+        let token_keys = db.collection::<TokenIssuerKey>("token_keys");
+        let audit_logs = db.collection::<AuditLog>("audit_logs");
+        let daily_reports = db.collection::<DailySettlementReport>("vendor_daily_reports");
+        let reconciliation_issues = db.collection::<ReconciliationIssue>("reconciliation_issues");
+        let webhook_failures = db.collection::<WebhookFailure>("webhook_failures");
+        let processed_stripe_events = db.collection::<ProcessedStripeEvent>("processed_stripe_events");
+        let webhook_jobs = db.collection::<WebhookJob>("webhook_jobs");
+        let blocked_words = db.collection::<BlockedWord>("blocked_words");
+        let matching_pools = db.collection::<MatchingPool>("matching_pools");
+        let match_events = db.collection::<MatchEvent>("match_events");
+        let funding_rounds = db.collection::<FundingRound>("funding_rounds");
+        let round_contributions = db.collection::<RoundContribution>("round_contributions");
+        let round_payouts = db.collection::<RoundPayout>("round_payouts");
+        let pending_deposits = db.collection::<PendingDeposit>("pending_deposits");
+        let unclaimed_deposits = db.collection::<UnclaimedDeposit>("unclaimed_deposits");
+        let contacts = db.collection::<Contact>("contacts");
+        let payment_requests = db.collection::<PaymentRequest>("payment_requests");
+        let accounts = db.collection::<Account>("accounts");
+        let device_tokens = db.collection::<DeviceToken>("device_tokens");
+        let push_notifications = db.collection::<PushNotification>("push_notifications");
+        let reviews = db.collection::<Review>("reviews");
+        let loyalty_programs = db.collection::<LoyaltyProgram>("loyalty_programs");
+        let loyalty_accounts = db.collection::<LoyaltyAccount>("loyalty_accounts");
+        let promo_codes = db.collection::<PromoCode>("promo_codes");
+        let vouchers = db.collection::<Voucher>("vouchers");
+        let disputes = db.collection::<Dispute>("disputes");
+        let payment_schedules = db.collection::<PaymentSchedule>("payment_schedules");
+        let invoices = db.collection::<Invoice>("invoices");
+        let preference_templates = db.collection::<PreferenceTemplate>("preference_templates");
+        let preference_changes = db.collection::<PreferenceChange>("preference_changes");
+        let preference_ledger = db.collection::<PreferenceLedgerEntry>("preference_ledger");
+        let submitted_allowances = db.collection::<SubmittedAllowance>("submitted_allowances");
+        let held_authorizations = db.collection::<HeldAuthorization>("held_authorizations");
+        let schema_migrations = db.collection::<SchemaMigration>("schema_migrations");
+        let holdings = db.collection::<Holding>("holdings");
+        let activities = db.collection::<ActivityEvent>("activities");
+        let feature_flags = db.collection::<FeatureFlag>("feature_flags");
+        let scheduled_jobs = db.collection::<ScheduledJob>("scheduled_jobs");
+        let scheduler_leases = db.collection::<SchedulerLease>("scheduler_leases");
+        let jobs = db.collection::<Job>("jobs");
+        let embed_tokens = db.collection::<EmbedToken>("embed_tokens");
+        let campaigns = db.collection::<Campaign>("campaigns");
+        let fundraisers = db.collection::<FundraiserPage>("fundraisers");
+        let payout_events = db.collection::<PayoutEvent>("payout_events");
+        let cause_payouts = db.collection::<CausePayout>("cause_payouts");
+        let organizations = db.collection::<Organization>("organizations");
+        let used_signatures = db.collection::<UsedSignature>("used_signatures");
+        let credit_reservations = db.collection::<CreditReservation>("credit_reservations");
         
         // Create unique index for wallet_address only
         let options = IndexOptions::builder().unique(true).build();
@@ -70,6 +182,14 @@ impl MongoDBService {
             .options(options)
             .build();
         users.create_index(wallet_model, None).await?;
+        
+        // Usernames are unique ignoring case; sparse since older users have no key
+        let username_options = IndexOptions::builder().unique(true).sparse(true).build();
+        let username_model = IndexModel::builder()
+            .keys(doc! { "username_key": 1 })
+            .options(username_options)
+            .build();
+        users.create_index(username_model, None).await?;
 
         // Create unique index for payment_id
         let payment_options = IndexOptions::builder().unique(true).build();
@@ -79,6 +199,99 @@ impl MongoDBService {
             .build();
         transactions.create_index(payment_model, None).await?;
         
+        // Rolling back a failed batch; sparse since most payments aren't batched
+        let payment_batch_options = IndexOptions::builder().sparse(true).build();
+        let payment_batch_model = IndexModel::builder()
+            .keys(doc! { "batch_id": 1 })
+            .options(payment_batch_options)
+            .build();
+        transactions.create_index(payment_batch_model, None).await?;
+        
+        // One deposit per Stripe checkout session; sparse so older deposits without one are allowed
+        let deposit_session_options = IndexOptions::builder().unique(true).sparse(true).build();
+        let deposit_session_model = IndexModel::builder()
+            .keys(doc! { "stripe_session_id": 1 })
+            .options(deposit_session_options)
+            .build();
+        deposit_records.create_index(deposit_session_model, None).await?;
+        
+        // Same for embedded-form PaymentIntents
+        let deposit_intent_options = IndexOptions::builder().unique(true).sparse(true).build();
+        let deposit_intent_model = IndexModel::builder()
+            .keys(doc! { "stripe_payment_intent_id": 1 })
+            .options(deposit_intent_options)
+            .build();
+        deposit_records.create_index(deposit_intent_model, None).await?;
+        
+        let deposit_created_model = IndexModel::builder()
+            .keys(doc! { "created_at": 1 })
+            .build();
+        deposit_records.create_index(deposit_created_model, None).await?;
+        
+        // A wallet's deposits in date order, for its history
+        let deposit_wallet_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "created_at": -1 })
+            .build();
+        deposit_records.create_index(deposit_wallet_model, None).await?;
+        
+        // Per-cause donation history, newest first
+        let deposit_symbol_model = IndexModel::builder()
+            .keys(doc! { "token_symbol": 1, "created_at": -1, "_id": -1 })
+            .build();
+        deposit_records.create_index(deposit_symbol_model, None).await?;
+        
+        // Retried manual credits with the same idempotency key must not credit twice
+        let manual_credit_options = IndexOptions::builder().unique(true).sparse(true).build();
+        let manual_credit_model = IndexModel::builder()
+            .keys(doc! { "manual_credit.idempotency_key": 1 })
+            .options(manual_credit_options)
+            .build();
+        deposit_records.create_index(manual_credit_model, None).await?;
+        
+        // Vendor payment lists: newest first, optionally narrowed by status
+        let vendor_payments_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "created_at": -1, "payment_id": -1 })
+            .build();
+        transactions.create_index(vendor_payments_model, None).await?;
+        
+        let vendor_status_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "status": 1, "created_at": -1 })
+            .build();
+        transactions.create_index(vendor_status_model, None).await?;
+        
+        // Customer side of wallet histories, and token filters on them
+        let customer_history_model = IndexModel::builder()
+            .keys(doc! { "customer_address": 1, "created_at": -1 })
+            .build();
+        transactions.create_index(customer_history_model, None).await?;
+        let payment_symbol_model = IndexModel::builder()
+            .keys(doc! { "computed_payment.symbol": 1, "created_at": -1 })
+            .build();
+        transactions.create_index(payment_symbol_model, None).await?;
+        
+        // Payments awaiting executor finality, polled least recently checked first
+        let submitted_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "finality_checked_at": 1, "submitted_at": 1 })
+            .build();
+        transactions.create_index(submitted_model, None).await?;
+        
+        // Escrowed payments due for auto-release
+        let escrow_model = IndexModel::builder()
+            .keys(doc! { "escrow.status": 1, "escrow.release_at": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        transactions.create_index(escrow_model, None).await?;
+        
+        // Authorized two-phase payments due to be voided
+        let authorization_options = IndexOptions::builder()
+            .partial_filter_expression(doc! { "authorization": { "$exists": true } })
+            .build();
+        let authorization_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "authorization.expires_at": 1 })
+            .options(authorization_options)
+            .build();
+        transactions.create_index(authorization_model, None).await?;
+        
         // Create TTL index for cause_drafts to auto-expire after 1 day
         let ttl_options = IndexOptions::builder()
             .expire_after(Some(std::time::Duration::from_secs(0))) // 0 means use the expires_at field
@@ -147,7 +360,577 @@ impl MongoDBService {
             .build();
         causes.create_index(compound_model, None).await?;
         
-        Ok(Self { users, transactions, tokens, causes, cause_drafts, transaction_records, deposit_records, partnered_vendors })
+        // Paged cause listings, newest first
+        let listing_model = IndexModel::builder()
+            .keys(doc! { "displayed": 1, "created_at": -1, "_id": -1 })
+            .build();
+        causes.create_index(listing_model, None).await?;
+        
+        // Admins filter causes by what Stripe still needs from them
+        let requirements_model = IndexModel::builder()
+            .keys(doc! { "requirements.state": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        causes.create_index(requirements_model, None).await?;
+        
+        // One issuer key per token
+        let token_key_options = IndexOptions::builder().unique(true).build();
+        let token_key_model = IndexModel::builder()
+            .keys(doc! { "token_id": 1 })
+            .options(token_key_options)
+            .build();
+        token_keys.create_index(token_key_model, None).await?;
+        
+        // Audit logs are listed newest first, optionally per resource
+        let audit_created_model = IndexModel::builder()
+            .keys(doc! { "created_at": -1 })
+            .build();
+        audit_logs.create_index(audit_created_model, None).await?;
+        
+        let audit_resource_model = IndexModel::builder()
+            .keys(doc! { "resource_id": 1, "created_at": -1 })
+            .build();
+        audit_logs.create_index(audit_resource_model, None).await?;
+        
+        // One cached settlement report per vendor per day
+        let daily_report_options = IndexOptions::builder().unique(true).build();
+        let daily_report_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "date": 1 })
+            .options(daily_report_options)
+            .build();
+        daily_reports.create_index(daily_report_model, None).await?;
+        
+        // Reconciliation issues are reviewed newest first, optionally per wallet
+        let issue_wallet_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "detected_at": -1 })
+            .build();
+        reconciliation_issues.create_index(issue_wallet_model, None).await?;
+        
+        let issue_resolved_model = IndexModel::builder()
+            .keys(doc! { "resolved": 1, "detected_at": -1 })
+            .build();
+        reconciliation_issues.create_index(issue_resolved_model, None).await?;
+        
+        // Repeated failures of the same Stripe event update one entry
+        let webhook_event_options = IndexOptions::builder().unique(true).build();
+        let webhook_event_model = IndexModel::builder()
+            .keys(doc! { "event_id": 1 })
+            .options(webhook_event_options)
+            .build();
+        webhook_failures.create_index(webhook_event_model, None).await?;
+        
+        let webhook_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "last_failed_at": -1 })
+            .build();
+        webhook_failures.create_index(webhook_status_model, None).await?;
+        
+        // Deduplicates redelivered Stripe events; entries expire after 30 days
+        let processed_event_options = IndexOptions::builder().unique(true).build();
+        let processed_event_model = IndexModel::builder()
+            .keys(doc! { "event_id": 1 })
+            .options(processed_event_options)
+            .build();
+        processed_stripe_events.create_index(processed_event_model, None).await?;
+        
+        let processed_ttl_options = IndexOptions::builder()
+            .expire_after(Some(std::time::Duration::from_secs(30 * 24 * 3600)))
+            .build();
+        let processed_ttl_model = IndexModel::builder()
+            .keys(doc! { "processed_at": 1 })
+            .options(processed_ttl_options)
+            .build();
+        processed_stripe_events.create_index(processed_ttl_model, None).await?;
+        
+        // An event is queued once; workers take queued jobs oldest first
+        let webhook_job_options = IndexOptions::builder().unique(true).build();
+        let webhook_job_model = IndexModel::builder()
+            .keys(doc! { "event_id": 1 })
+            .options(webhook_job_options)
+            .build();
+        webhook_jobs.create_index(webhook_job_model, None).await?;
+        let webhook_job_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "created_at": 1 })
+            .build();
+        webhook_jobs.create_index(webhook_job_status_model, None).await?;
+        // One job per ordering key is processing at a time, across every replica
+        let webhook_job_key_options = IndexOptions::builder()
+            .unique(true)
+            .partial_filter_expression(doc! { "status": WebhookJobStatus::Processing.to_string() })
+            .build();
+        let webhook_job_key_model = IndexModel::builder()
+            .keys(doc! { "ordering_key": 1 })
+            .options(webhook_job_key_options)
+            .build();
+        webhook_jobs.create_index(webhook_job_key_model, None).await?;
+        let webhook_job_ttl_options = IndexOptions::builder()
+            .expire_after(Some(std::time::Duration::from_secs(30 * 24 * 3600)))
+            .build();
+        let webhook_job_ttl_model = IndexModel::builder()
+            .keys(doc! { "processed_at": 1 })
+            .options(webhook_job_ttl_options)
+            .build();
+        webhook_jobs.create_index(webhook_job_ttl_model, None).await?;
+        
+        let pool_symbol_model = IndexModel::builder()
+            .keys(doc! { "cause_symbols": 1, "status": 1 })
+            .build();
+        matching_pools.create_index(pool_symbol_model, None).await?;
+        
+        // A checkout session is matched at most once per pool, even if the webhook is replayed
+        let match_session_options = IndexOptions::builder().unique(true).build();
+        let match_session_model = IndexModel::builder()
+            .keys(doc! { "pool_id": 1, "stripe_session_id": 1 })
+            .options(match_session_options)
+            .build();
+        match_events.create_index(match_session_model, None).await?;
+        
+        let match_created_model = IndexModel::builder()
+            .keys(doc! { "pool_id": 1, "created_at": -1 })
+            .build();
+        match_events.create_index(match_created_model, None).await?;
+        
+        let match_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "created_at": 1 })
+            .build();
+        match_events.create_index(match_status_model, None).await?;
+        
+        let round_symbol_model = IndexModel::builder()
+            .keys(doc! { "cause_symbols": 1, "status": 1 })
+            .build();
+        funding_rounds.create_index(round_symbol_model, None).await?;
+        
+        // A donation counts once per round, even if the webhook is replayed
+        let contribution_session_options = IndexOptions::builder().unique(true).build();
+        let contribution_session_model = IndexModel::builder()
+            .keys(doc! { "round_id": 1, "stripe_session_id": 1 })
+            .options(contribution_session_options)
+            .build();
+        round_contributions.create_index(contribution_session_model, None).await?;
+        
+        // One payout per contributor and cause, so closing and distributing can be retried
+        let payout_options = IndexOptions::builder().unique(true).build();
+        let payout_model = IndexModel::builder()
+            .keys(doc! { "round_id": 1, "token_symbol": 1, "wallet_address": 1 })
+            .options(payout_options)
+            .build();
+        round_payouts.create_index(payout_model, None).await?;
+        
+        let payout_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "updated_at": 1 })
+            .build();
+        round_payouts.create_index(payout_status_model, None).await?;
+        
+        let pending_session_options = IndexOptions::builder().unique(true).build();
+        let pending_session_model = IndexModel::builder()
+            .keys(doc! { "stripe_session_id": 1 })
+            .options(pending_session_options)
+            .build();
+        pending_deposits.create_index(pending_session_model, None).await?;
+        
+        let pending_wallet_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "status": 1, "created_at": -1 })
+            .build();
+        pending_deposits.create_index(pending_wallet_model, None).await?;
+        
+        // Held once per payment, however often its webhook is retried
+        let unclaimed_payment_model = IndexModel::builder()
+            .keys(doc! { "payment_id": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        unclaimed_deposits.create_index(unclaimed_payment_model, None).await?;
+        
+        let unclaimed_email_model = IndexModel::builder()
+            .keys(doc! { "email": 1, "status": 1 })
+            .build();
+        unclaimed_deposits.create_index(unclaimed_email_model, None).await?;
+        
+        // A wallet saves each counterparty once; the nickname is updated in place
+        let contact_options = IndexOptions::builder().unique(true).build();
+        let contact_model = IndexModel::builder()
+            .keys(doc! { "owner_address": 1, "contact_address": 1 })
+            .options(contact_options)
+            .build();
+        contacts.create_index(contact_model, None).await?;
+        
+        // Incoming and outgoing request feeds, and the expiry sweep
+        let request_payer_model = IndexModel::builder()
+            .keys(doc! { "payer_address": 1, "created_at": -1 })
+            .build();
+        payment_requests.create_index(request_payer_model, None).await?;
+        let request_requester_model = IndexModel::builder()
+            .keys(doc! { "requester_address": 1, "created_at": -1 })
+            .build();
+        payment_requests.create_index(request_requester_model, None).await?;
+        let request_expiry_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "expires_at": 1 })
+            .build();
+        payment_requests.create_index(request_expiry_model, None).await?;
+        
+        // A wallet can be linked to only one account
+        let account_wallet_options = IndexOptions::builder().unique(true).build();
+        let account_wallet_model = IndexModel::builder()
+            .keys(doc! { "wallets.wallet_address": 1 })
+            .options(account_wallet_options)
+            .build();
+        accounts.create_index(account_wallet_model, None).await?;
+        
+        // A push token identifies one app install; registering it again moves it to the new wallet
+        let device_token_options = IndexOptions::builder().unique(true).build();
+        let device_token_model = IndexModel::builder()
+            .keys(doc! { "token": 1 })
+            .options(device_token_options)
+            .build();
+        device_tokens.create_index(device_token_model, None).await?;
+        let device_wallet_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1 })
+            .build();
+        device_tokens.create_index(device_wallet_model, None).await?;
+        // Queued notifications are delivered oldest first, and dropped a day after queueing
+        let push_queue_model = IndexModel::builder()
+            .keys(doc! { "lease_expires_at": 1, "queued_at": 1 })
+            .build();
+        push_notifications.create_index(push_queue_model, None).await?;
+        let push_lease_model = IndexModel::builder()
+            .keys(doc! { "lease": 1 })
+            .build();
+        push_notifications.create_index(push_lease_model, None).await?;
+        let push_ttl_options = IndexOptions::builder()
+            .expire_after(Some(std::time::Duration::from_secs(24 * 3600)))
+            .build();
+        let push_ttl_model = IndexModel::builder()
+            .keys(doc! { "queued_at": 1 })
+            .options(push_ttl_options)
+            .build();
+        push_notifications.create_index(push_ttl_model, None).await?;
+        
+        // Vendors with a location appear in the nearby directory
+        let vendor_location_model = IndexModel::builder()
+            .keys(doc! { "location": "2dsphere" })
+            .build();
+        partnered_vendors.create_index(vendor_location_model, None).await?;
+        
+        // One review per payment; listed per vendor, newest first
+        let review_payment_options = IndexOptions::builder().unique(true).build();
+        let review_payment_model = IndexModel::builder()
+            .keys(doc! { "payment_id": 1 })
+            .options(review_payment_options)
+            .build();
+        reviews.create_index(review_payment_model, None).await?;
+        let review_vendor_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "created_at": -1, "_id": -1 })
+            .build();
+        reviews.create_index(review_vendor_model, None).await?;
+        
+        // One program per vendor, and one points balance per vendor and customer
+        let loyalty_program_options = IndexOptions::builder().unique(true).build();
+        let loyalty_program_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1 })
+            .options(loyalty_program_options)
+            .build();
+        loyalty_programs.create_index(loyalty_program_model, None).await?;
+        let loyalty_account_options = IndexOptions::builder().unique(true).build();
+        let loyalty_account_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "customer_address": 1 })
+            .options(loyalty_account_options)
+            .build();
+        loyalty_accounts.create_index(loyalty_account_model, None).await?;
+        let loyalty_customer_model = IndexModel::builder()
+            .keys(doc! { "customer_address": 1, "updated_at": -1 })
+            .build();
+        loyalty_accounts.create_index(loyalty_customer_model, None).await?;
+        
+        // Codes are unique per vendor; two vendors can hand out the same word
+        let promo_code_options = IndexOptions::builder().unique(true).build();
+        let promo_code_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "code": 1 })
+            .options(promo_code_options)
+            .build();
+        promo_codes.create_index(promo_code_model, None).await?;
+        
+        // Codes are looked up on redemption; issuers list theirs; the sweep finds expired ones
+        let voucher_code_options = IndexOptions::builder().unique(true).build();
+        let voucher_code_model = IndexModel::builder()
+            .keys(doc! { "code": 1 })
+            .options(voucher_code_options)
+            .build();
+        vouchers.create_index(voucher_code_model, None).await?;
+        let voucher_issuer_model = IndexModel::builder()
+            .keys(doc! { "issuer_address": 1, "created_at": -1 })
+            .build();
+        vouchers.create_index(voucher_issuer_model, None).await?;
+        let voucher_expiry_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "expires_at": 1 })
+            .build();
+        vouchers.create_index(voucher_expiry_model, None).await?;
+        
+        // One dispute per payment; admins work the queue by status, each side lists theirs
+        let dispute_payment_options = IndexOptions::builder().unique(true).build();
+        let dispute_payment_model = IndexModel::builder()
+            .keys(doc! { "payment_id": 1 })
+            .options(dispute_payment_options)
+            .build();
+        disputes.create_index(dispute_payment_model, None).await?;
+        let dispute_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "created_at": 1 })
+            .build();
+        disputes.create_index(dispute_status_model, None).await?;
+        let dispute_customer_model = IndexModel::builder()
+            .keys(doc! { "customer_address": 1, "created_at": -1 })
+            .build();
+        disputes.create_index(dispute_customer_model, None).await?;
+        let dispute_vendor_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "created_at": -1 })
+            .build();
+        disputes.create_index(dispute_vendor_model, None).await?;
+        
+        // Runs coming due, and each side's list of schedules
+        let schedule_due_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "next_run_at": 1 })
+            .build();
+        payment_schedules.create_index(schedule_due_model, None).await?;
+        let schedule_customer_model = IndexModel::builder()
+            .keys(doc! { "customer_address": 1, "created_at": -1 })
+            .build();
+        payment_schedules.create_index(schedule_customer_model, None).await?;
+        let schedule_vendor_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "created_at": -1 })
+            .build();
+        payment_schedules.create_index(schedule_vendor_model, None).await?;
+        
+        // Each side's invoices, and overdue ones for reminders
+        let invoice_customer_model = IndexModel::builder()
+            .keys(doc! { "customer_address": 1, "created_at": -1 })
+            .build();
+        invoices.create_index(invoice_customer_model, None).await?;
+        let invoice_vendor_model = IndexModel::builder()
+            .keys(doc! { "vendor_address": 1, "created_at": -1 })
+            .build();
+        invoices.create_index(invoice_vendor_model, None).await?;
+        let invoice_due_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "due_at": 1 })
+            .build();
+        invoices.create_index(invoice_due_model, None).await?;
+        
+        // A vendor's templates are saved by name, and their history read newest first
+        let template_options = IndexOptions::builder().unique(true).build();
+        let template_model = IndexModel::builder()
+            .keys(doc! { "owner_address": 1, "name": 1 })
+            .options(template_options)
+            .build();
+        preference_templates.create_index(template_model, None).await?;
+        // Each template holds one of the owner's MAX_PREFERENCE_TEMPLATES slots, so
+        // concurrent saves can't go over the cap
+        let template_slot_options = IndexOptions::builder()
+            .unique(true)
+            .partial_filter_expression(doc! { "slot": { "$exists": true } })
+            .build();
+        let template_slot_model = IndexModel::builder()
+            .keys(doc! { "owner_address": 1, "slot": 1 })
+            .options(template_slot_options)
+            .build();
+        preference_templates.create_index(template_slot_model, None).await?;
+        let preference_change_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "created_at": -1 })
+            .build();
+        preference_changes.create_index(preference_change_model, None).await?;
+        let preference_ledger_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "created_at": -1, "_id": -1 })
+            .build();
+        preference_ledger.create_index(preference_ledger_model, None).await?;
+        
+        // A signed allowance is only ever submitted once
+        let submitted_allowance_options = IndexOptions::builder().unique(true).build();
+        let submitted_allowance_model = IndexModel::builder()
+            .keys(doc! { "hash": 1 })
+            .options(submitted_allowance_options)
+            .build();
+        submitted_allowances.create_index(submitted_allowance_model, None).await?;
+        
+        // One set of held allowances per authorized payment
+        let held_authorization_model = IndexModel::builder()
+            .keys(doc! { "payment_id": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        held_authorizations.create_index(held_authorization_model, None).await?;
+        
+        // One projected balance per wallet and token; holders of a token are looked up by token
+        let holding_options = IndexOptions::builder().unique(true).build();
+        let holding_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "token_id": 1 })
+            .options(holding_options)
+            .build();
+        holdings.create_index(holding_model, None).await?;
+        let holding_token_model = IndexModel::builder()
+            .keys(doc! { "token_id": 1, "balance": -1 })
+            .build();
+        holdings.create_index(holding_token_model, None).await?;
+        
+        // A wallet's activity feed, newest first
+        let activity_model = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "created_at": -1, "_id": -1 })
+            .build();
+        activities.create_index(activity_model, None).await?;
+        
+        // One switch per feature per environment
+        let feature_flag_model = IndexModel::builder()
+            .keys(doc! { "name": 1, "environment": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        feature_flags.create_index(feature_flag_model, None).await?;
+        
+        let job_model = IndexModel::builder()
+            .keys(doc! { "job_id": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        jobs.create_index(job_model, None).await?;
+        // Interrupted jobs are found by their last heartbeat
+        let job_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "updated_at": 1 })
+            .build();
+        jobs.create_index(job_status_model, None).await?;
+        // Finished jobs, exports included, are removed once `expires_at` passes
+        let job_ttl_model = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder().expire_after(Some(std::time::Duration::ZERO)).build())
+            .build();
+        jobs.create_index(job_ttl_model, None).await?;
+        
+        let embed_token_model = IndexModel::builder()
+            .keys(doc! { "token": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        embed_tokens.create_index(embed_token_model, None).await?;
+        
+        let embed_cause_model = IndexModel::builder()
+            .keys(doc! { "cause_id": 1, "created_at": -1 })
+            .build();
+        embed_tokens.create_index(embed_cause_model, None).await?;
+        
+        let campaign_name_model = IndexModel::builder()
+            .keys(doc! { "cause_id": 1, "name": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        campaigns.create_index(campaign_name_model, None).await?;
+        
+        // Progress totals add up a campaign's deposits
+        let deposit_campaign_model = IndexModel::builder()
+            .keys(doc! { "campaign_id": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        deposit_records.create_index(deposit_campaign_model, None).await?;
+        
+        let fundraiser_slug_model = IndexModel::builder()
+            .keys(doc! { "slug": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        fundraisers.create_index(fundraiser_slug_model, None).await?;
+        
+        let fundraiser_owner_model = IndexModel::builder()
+            .keys(doc! { "owner_address": 1, "status": 1 })
+            .build();
+        fundraisers.create_index(fundraiser_owner_model, None).await?;
+        
+        // Page progress and the leaderboard add up a page's deposits
+        let deposit_fundraiser_model = IndexModel::builder()
+            .keys(doc! { "fundraiser_id": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        deposit_records.create_index(deposit_fundraiser_model, None).await?;
+        
+        // A balance event is recorded once per currency
+        let payout_event_model = IndexModel::builder()
+            .keys(doc! { "event_id": 1, "currency": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        payout_events.create_index(payout_event_model, None).await?;
+        
+        let payout_cause_model = IndexModel::builder()
+            .keys(doc! { "cause_ids": 1, "created_at": -1 })
+            .build();
+        payout_events.create_index(payout_cause_model, None).await?;
+        
+        // A retried payout request finds the first one instead of paying out again
+        let cause_payout_model = IndexModel::builder()
+            .keys(doc! { "cause_id": 1, "idempotency_key": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        cause_payouts.create_index(cause_payout_model, None).await?;
+        
+        // Organization names are unique like cause names, ignoring case
+        let organization_name_model = IndexModel::builder()
+            .keys(doc! { "name": 1 })
+            .options(IndexOptions::builder().unique(true).collation(collation.clone()).build())
+            .build();
+        organizations.create_index(organization_name_model, None).await?;
+        
+        let organization_admins_model = IndexModel::builder()
+            .keys(doc! { "admins": 1 })
+            .build();
+        organizations.create_index(organization_admins_model, None).await?;
+        
+        let organization_account_model = IndexModel::builder()
+            .keys(doc! { "stripe_account_id": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        organizations.create_index(organization_account_model, None).await?;
+        
+        // Each request signature is accepted once; kept until its timestamp would be rejected anyway
+        let used_signature_model = IndexModel::builder()
+            .keys(doc! { "signature": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        used_signatures.create_index(used_signature_model, None).await?;
+        let used_signature_ttl_model = IndexModel::builder()
+            .keys(doc! { "used_at": 1 })
+            .options(IndexOptions::builder()
+                .expire_after(Some(std::time::Duration::from_secs(2 * crate::auth::MAX_CLOCK_SKEW_SECS as u64)))
+                .build())
+            .build();
+        used_signatures.create_index(used_signature_ttl_model, None).await?;
+        
+        let cause_organization_model = IndexModel::builder()
+            .keys(doc! { "organization_id": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        causes.create_index(cause_organization_model, None).await?;
+        
+        let cause_team_model = IndexModel::builder()
+            .keys(doc! { "team.wallet_address": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        causes.create_index(cause_team_model, None).await?;
+        
+        // account.updated finds the vendor by their connected account
+        let vendor_stripe_account_model = IndexModel::builder()
+            .keys(doc! { "stripe_account.account_id": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build();
+        users.create_index(vendor_stripe_account_model, None).await?;
+        
+        // Vendors' payment code namespaces, and the short codes of payments in them
+        let payment_code_namespace_model = IndexModel::builder()
+            .keys(doc! { "payment_code_namespace.slug": 1 })
+            .options(IndexOptions::builder().unique(true).sparse(true).build())
+            .build();
+        users.create_index(payment_code_namespace_model, None).await?;
+        let short_code_model = IndexModel::builder()
+            .keys(doc! { "vendor_slug": 1, "short_code": 1 })
+            .options(IndexOptions::builder().unique(true).sparse(true).build())
+            .build();
+        transactions.create_index(short_code_model, None).await?;
+        
+        // A credit's key is reserved once, before its tokens move
+        let credit_key_model = IndexModel::builder()
+            .keys(doc! { "key": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        credit_reservations.create_index(credit_key_model, None).await?;
+        let credit_status_model = IndexModel::builder()
+            .keys(doc! { "status": 1, "updated_at": -1 })
+            .build();
+        credit_reservations.create_index(credit_status_model, None).await?;
+        
+        Ok(Self { users, transactions, tokens, causes, cause_drafts, transaction_records, deposit_records, partnered_vendors, token_keys, audit_logs, daily_reports, reconciliation_issues, webhook_failures, processed_stripe_events, webhook_jobs, blocked_words, matching_pools, match_events, funding_rounds, round_contributions, round_payouts, pending_deposits, unclaimed_deposits, contacts, payment_requests, accounts, device_tokens, push_notifications, reviews, loyalty_programs, loyalty_accounts, promo_codes, vouchers, disputes, payment_schedules, invoices, preference_templates, preference_changes, preference_ledger, submitted_allowances, held_authorizations, schema_migrations, holdings, activities, feature_flags, scheduled_jobs, scheduler_leases, jobs, embed_tokens, campaigns, fundraisers, payout_events, cause_payouts, organizations, used_signatures, credit_reservations })
     }
 
     pub async fn create_user(&self, user: User) -> Result<User, ApiError> {
@@ -166,12 +949,21 @@ impl MongoDBService {
             .map_err(ApiError::DatabaseError)? {
             return Err(ApiError::DuplicateUser(format!("User with wallet address {} already exists", user.wallet_address)));
         }
+        if !self.is_username_available(&user.username, None).await? {
+            return Err(ApiError::DuplicateError(format!("Username {} is already taken", user.username)));
+        }
 
         // Insert the user
         self.users
             .insert_one(user.clone(), None)
             .await
-            .map_err(ApiError::DatabaseError)?;
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::DuplicateError(format!("Username {} is already taken", user.username))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
 
         Ok(user)
     }
@@ -207,6 +999,19 @@ impl MongoDBService {
             preferences: request.preferences.unwrap_or(Preferences(Document::new())),
             is_verified: request.is_verified,
             user_type: request.user_type.clone(),
+            roles: if request.user_type == "vendor" { vec![Role::User, Role::Vendor] } else { vec![Role::User] },
+            deleted_at: None,
+            donate_anonymously: false,
+            stripe_customer_id: None,
+            username_key: Some(username_key(&request.username)),
+            username_changed_at: None,
+            display_name: None,
+            avatar_url: None,
+            email: None,
+            notification_preferences: NotificationPreferences::default(),
+            spending_weights: HashMap::new(),
+            stripe_account: None,
+            payment_code_namespace: None,
         };
         
         let created_user = self.create_user(user).await?;
@@ -239,6 +1044,91 @@ impl MongoDBService {
         Ok(created_user)
     }
 
+    /// Whether no other user has this username, ignoring case. Older users have no
+    /// `username_key`, so they are matched with a case-insensitive collation.
+    pub async fn is_username_available(&self, username: &str, except_wallet: Option<&str>) -> Result<bool, ApiError> {
+        let mut filter = doc! { "username": username.trim() };
+        if let Some(wallet_address) = except_wallet {
+            filter.insert("wallet_address", doc! { "$ne": wallet_address });
+        }
+        let options = mongodb::options::FindOneOptions::builder()
+            .collation(mongodb::options::Collation::builder()
+                .locale("en")
+                .strength(mongodb::options::CollationStrength::Secondary)
+                .build())
+            .build();
+        let existing = self.users
+            .find_one(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(existing.is_none())
+    }
+
+    /// Apply a profile update (`$set`/`$unset` fields) and return the updated user.
+    /// A username taken concurrently is caught by the unique `username_key` index.
+    pub async fn update_user_profile(&self, wallet_address: &str, set: Document, unset: Document) -> Result<User, ApiError> {
+        let mut update = Document::new();
+        if !set.is_empty() {
+            update.insert("$set", set);
+        }
+        if !unset.is_empty() {
+            update.insert("$unset", unset);
+        }
+        if update.is_empty() {
+            return self.get_user_by_wallet(wallet_address).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)));
+        }
+
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.users
+            .find_one_and_update(doc! { "wallet_address": wallet_address }, update, options)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::DuplicateError("Username is already taken".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?
+            .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)))
+    }
+
+    pub async fn update_notification_preferences(&self, wallet_address: &str, preferences: &NotificationPreferences) -> Result<NotificationPreferences, ApiError> {
+        let preferences_bson = bson::to_bson(preferences)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize notification preferences: {}", e)))?;
+        let result = self.users
+            .update_one(
+                doc! { "wallet_address": wallet_address, "deleted_at": { "$exists": false } },
+                doc! { "$set": { "notification_preferences": preferences_bson } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if result.matched_count == 0 {
+            return Err(ApiError::NotFound(format!("User not found: {}", wallet_address)));
+        }
+        Ok(preferences.clone())
+    }
+
+    pub async fn update_spending_weights(&self, wallet_address: &str, weights: &HashMap<String, f64>) -> Result<(), ApiError> {
+        let weights_bson = bson::to_bson(weights)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize spending weights: {}", e)))?;
+        let result = self.users
+            .update_one(
+                doc! { "wallet_address": wallet_address, "deleted_at": { "$exists": false } },
+                doc! { "$set": { "spending_weights": weights_bson } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if result.matched_count == 0 {
+            return Err(ApiError::NotFound(format!("User not found: {}", wallet_address)));
+        }
+        Ok(())
+    }
+
     pub async fn get_user_by_wallet(&self, wallet_address: &str) -> Result<Option<User>, ApiError> {
         self.users
             .find_one(doc! { "wallet_address": wallet_address }, None)
@@ -246,21 +1136,161 @@ impl MongoDBService {
             .map_err(ApiError::DatabaseError)
     }
 
-    pub async fn create_payment(&self, payment_data: Payment) -> Result<Payment, ApiError> {
-        // Insert the payment into transactions collection
-        self.transactions
-            .insert_one(payment_data.clone(), None)
+    /// Store a user's Stripe customer unless one was already stored by a concurrent request.
+    /// Returns the customer ID to use either way.
+    pub async fn set_stripe_customer_id(&self, wallet_address: &str, customer_id: &str) -> Result<String, ApiError> {
+        let result = self.users
+            .update_one(
+                doc! { "wallet_address": wallet_address, "stripe_customer_id": { "$exists": false } },
+                doc! { "$set": { "stripe_customer_id": customer_id } },
+                None,
+            )
             .await
             .map_err(ApiError::DatabaseError)?;
 
-        Ok(payment_data)
+        if result.modified_count == 1 {
+            return Ok(customer_id.to_string());
+        }
+        self.get_user_by_wallet(wallet_address).await?
+            .and_then(|user| user.stripe_customer_id)
+            .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)))
     }
 
-    pub async fn get_payment(&self, payment_id: &str) -> Result<Option<Payment>, ApiError> {
-        log::info!("Querying database for payment_id: {}", payment_id);
-        let result = self.transactions
-            .find_one(doc! { "payment_id": payment_id }, None)
-            .await;
+    /// Claim or change a vendor's payment code namespace. Fails if another vendor has the slug.
+    pub async fn set_payment_code_namespace(&self, wallet_address: &str, namespace: &PaymentCodeNamespace) -> Result<(), ApiError> {
+        let value = bson::to_bson(namespace)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize payment code namespace: {}", e)))?;
+        let result = self.users
+            .update_one(doc! { "wallet_address": wallet_address }, doc! { "$set": { "payment_code_namespace": value } }, None)
+            .await
+            .map_err(|e| if e.to_string().contains("E11000 duplicate key error") {
+                ApiError::DuplicateError(format!("The slug {} is already taken", namespace.slug))
+            } else {
+                ApiError::DatabaseError(e)
+            })?;
+        if result.matched_count == 0 {
+            return Err(ApiError::NotFound(format!("User {} not found", wallet_address)));
+        }
+        Ok(())
+    }
+    
+    /// Store a vendor's first connected account. Returns false if they already have one.
+    pub async fn set_vendor_stripe_account(&self, wallet_address: &str, account: &VendorStripeAccount) -> Result<bool, ApiError> {
+        let account = bson::to_bson(account)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize Stripe account: {}", e)))?;
+        let result = self.users
+            .update_one(
+                doc! { "wallet_address": wallet_address, "stripe_account": { "$exists": false } },
+                doc! { "$set": { "stripe_account": account } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if result.matched_count == 1 {
+            return Ok(true);
+        }
+        match self.get_user_by_wallet(wallet_address).await? {
+            Some(_) => Ok(false),
+            None => Err(ApiError::NotFound(format!("User {} not found", wallet_address))),
+        }
+    }
+    
+    /// Record a vendor's connected account status. Returns whether a vendor has the account.
+    pub async fn update_vendor_stripe_status(
+        &self,
+        account_id: &str,
+        onboarding_completed: bool,
+        charges_enabled: bool,
+        payouts_enabled: bool,
+        requirements: &StripeRequirements,
+    ) -> Result<bool, ApiError> {
+        let requirements = bson::to_bson(requirements)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize requirements: {}", e)))?;
+        let result = self.users
+            .update_one(
+                doc! { "stripe_account.account_id": account_id },
+                doc! { "$set": {
+                    "stripe_account.onboarding_completed": onboarding_completed,
+                    "stripe_account.charges_enabled": charges_enabled,
+                    "stripe_account.payouts_enabled": payouts_enabled,
+                    "stripe_account.requirements": requirements,
+                    "stripe_account.updated_at": chrono::Utc::now().timestamp(),
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count == 1)
+    }
+    
+    /// Replace the stored roles of a user
+    pub async fn set_user_roles(&self, wallet_address: &str, roles: &[Role]) -> Result<(), ApiError> {
+        let roles = bson::to_bson(roles)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize roles: {}", e)))?;
+        let result = self.users
+            .update_one(doc! { "wallet_address": wallet_address }, doc! { "$set": { "roles": roles } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        if result.matched_count == 0 {
+            return Err(ApiError::NotFound(format!("User not found: {}", wallet_address)));
+        }
+        Ok(())
+    }
+
+    /// Add a role to a user if they don't already have it
+    pub async fn add_user_role(&self, wallet_address: &str, role: Role) -> Result<(), ApiError> {
+        let role = bson::to_bson(&role)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize role: {}", e)))?;
+        self.users
+            .update_one(doc! { "wallet_address": wallet_address }, doc! { "$addToSet": { "roles": role } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Insert a new payment. A clash with an existing payment code is a Conflict so the
+    /// caller can retry with new codes.
+    pub async fn create_payment(&self, payment_data: Payment) -> Result<Payment, ApiError> {
+        // Insert the payment into transactions collection
+        self.transactions
+            .insert_one(payment_data.clone(), None)
+            .await
+            .map_err(|e| if e.to_string().contains("E11000 duplicate key error") {
+                ApiError::Conflict("Payment code already in use".to_string())
+            } else {
+                ApiError::DatabaseError(e)
+            })?;
+
+        Ok(payment_data)
+    }
+
+    /// Insert a batch of payments sharing a `batch_id`, all or none. If any insert fails the
+    /// ones already made are removed again; a clash with an existing payment code is a
+    /// Conflict so the caller can retry with new codes.
+    pub async fn create_payment_batch(&self, batch_id: &str, payments: &[Payment]) -> Result<(), ApiError> {
+        let result = self.transactions
+            .insert_many(payments, None)
+            .await;
+        if let Err(e) = result {
+            self.transactions
+                .delete_many(doc! { "batch_id": batch_id }, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            return Err(if e.to_string().contains("E11000 duplicate key error") {
+                ApiError::Conflict("Payment code already in use".to_string())
+            } else {
+                ApiError::DatabaseError(e)
+            });
+        }
+        Ok(())
+    }
+
+    pub async fn get_payment(&self, payment_id: &str) -> Result<Option<Payment>, ApiError> {
+        log::info!("Querying database for payment_id: {}", payment_id);
+        let result = self.transactions
+            .find_one(doc! { "payment_id": payment_id }, None)
+            .await;
         
         match result {
             Ok(payment) => {
@@ -274,15 +1304,37 @@ impl MongoDBService {
         }
     }
 
+    /// A payment in a vendor's code namespace by its short code
+    pub async fn get_payment_by_short_code(&self, vendor_slug: &str, short_code: &str) -> Result<Option<Payment>, ApiError> {
+        self.transactions
+            .find_one(doc! { "vendor_slug": vendor_slug, "short_code": short_code }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// The payment ID a customer's code refers to. Global codes are the ID; vendor-scoped
+    /// codes are looked up.
+    pub async fn resolve_payment_code(&self, code: &str) -> Result<String, ApiError> {
+        match parse_payment_code(code) {
+            PaymentCodeRef::Global(payment_id) => Ok(payment_id),
+            PaymentCodeRef::Scoped { vendor_slug, short_code } => self.get_payment_by_short_code(&vendor_slug, &short_code).await?
+                .map(|payment| payment.payment_id)
+                .ok_or_else(|| ApiError::NotFound(format!("Payment with code {} not found", code))),
+        }
+    }
+    
     pub async fn update_payment_with_payer(&self, payment_id: &str, payer_address: String, payer_username: Option<String>) -> Result<Payment, ApiError> {
         // First check if payment exists
         let payment = self.get_payment(payment_id).await?
             .ok_or_else(|| ApiError::ValidationError("Payment code not found".to_string()))?;
 
         // Check if payment is already completed
-        if matches!(payment.status, PaymentStatus::Completed) {
+        if matches!(payment.status, PaymentStatus::Completed | PaymentStatus::Submitted) {
             return Err(ApiError::ValidationError("Transaction already fulfilled".to_string()));
         }
+        if matches!(payment.status, PaymentStatus::Authorized | PaymentStatus::Voided) {
+            return Err(ApiError::ValidationError(format!("Payment is already {}", payment.status.to_string().to_lowercase())));
+        }
 
         // Check if payment already has a customer assigned
         if let Some(existing_customer) = &payment.customer_address {
@@ -391,6 +1443,22 @@ impl MongoDBService {
             .map_err(ApiError::DatabaseError)
     }
 
+    // Token issuer keys - only TokenService holds the master key to decrypt these
+    pub async fn save_token_issuer_key(&self, key: TokenIssuerKey) -> Result<(), ApiError> {
+        self.token_keys
+            .insert_one(key, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_token_issuer_key(&self, token_id: &str) -> Result<Option<TokenIssuerKey>, ApiError> {
+        self.token_keys
+            .find_one(doc! { "token_id": token_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
     /// Get all tokens from the database
     pub async fn get_all_tokens(&self) -> Result<Vec<Token>, ApiError> {
         self.tokens
@@ -402,18 +1470,36 @@ impl MongoDBService {
             .map_err(ApiError::DatabaseError)
     }
 
-    /// Update a token valuation for a user
+    /// How many tokens there are and when the latest was created or changed, as an ETag
+    pub async fn tokens_etag(&self) -> Result<String, ApiError> {
+        let pipeline = vec![doc! {
+            "$group": {
+                "_id": null,
+                "count": { "$sum": 1 },
+                "latest": { "$max": { "$ifNull": ["$updated_at", "$created_at"] } },
+            }
+        }];
+        let summary = self.tokens.aggregate(pipeline, None).await
+            .map_err(ApiError::DatabaseError)?
+            .try_next().await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(match summary {
+            Some(summary) => listing_etag(number(&summary, "count") as u64, number(&summary, "latest") as i64),
+            None => listing_etag(0, 0),
+        })
+    }
+
+    /// Update a token valuation for a user. Returns the preferences before and after.
     pub async fn update_user_valuation(
         &self,
         wallet_address: &str,
         symbol: &str,
         valuation: f64
-    ) -> Result<(), ApiError> {
+    ) -> Result<(Document, Document), ApiError> {
         // First ensure user exists
-        let user = match self.get_user_by_wallet(wallet_address).await? {
-            Some(user) => user,
-            None => return Err(ApiError::NotFound(format!("User not found: {}", wallet_address))),
-        };
+        if self.get_user_by_wallet(wallet_address).await?.is_none() {
+            return Err(ApiError::NotFound(format!("User not found: {}", wallet_address)));
+        }
 
         // Then ensure token exists by symbol
         if let None = self.get_token_by_symbol(symbol).await? {
@@ -421,18 +1507,218 @@ impl MongoDBService {
         }
 
         // Update the user document using dot notation for efficiency
-        self.users
-            .update_one(
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::Before)
+            .build();
+        let before = self.users
+            .find_one_and_update(
                 doc! { "wallet_address": wallet_address },
                 doc! { "$set": { format!("preferences.{}", symbol): valuation } }, // Changed from valuations to preferences
-                None
+                options
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)))?
+            .preferences.0;
+
+        let mut after = before.clone();
+        after.insert(symbol, valuation);
+        Ok((before, after))
+    }
+
+    /// Replace a user's preferences wholesale. Returns the preferences they replaced.
+    pub async fn replace_user_preferences(&self, wallet_address: &str, preferences: Document) -> Result<Document, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::Before)
+            .build();
+        self.users
+            .find_one_and_update(
+                doc! { "wallet_address": wallet_address, "deleted_at": null },
+                doc! { "$set": { "preferences": preferences } },
+                options,
             )
             .await
+            .map_err(ApiError::DatabaseError)?
+            .map(|user| user.preferences.0)
+            .ok_or_else(|| ApiError::NotFound(format!("User not found: {}", wallet_address)))
+    }
+
+    /// Record a change to a user's preferences, with a ledger grant for each budget it moved
+    pub async fn record_preference_change(&self, change: &PreferenceChange) -> Result<(), ApiError> {
+        self.preference_changes
+            .insert_one(change, None)
+            .await
             .map_err(ApiError::DatabaseError)?;
 
+        let grants: Vec<PreferenceLedgerEntry> = budget_changes(&change.before, &change.after)
+            .into_iter()
+            .map(|(symbol, before, after)| PreferenceLedgerEntry {
+                id: None,
+                wallet_address: change.wallet_address.clone(),
+                kind: PreferenceLedgerKind::Grant,
+                symbol,
+                amount: after - before,
+                before,
+                after,
+                payment_id: None,
+                source: Some(change.source.clone()),
+                created_at: change.created_at,
+            })
+            .collect();
+        if !grants.is_empty() {
+            self.preference_ledger
+                .insert_many(&grants, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+        }
         Ok(())
     }
 
+    /// A user's budget grants and consumption, newest first
+    pub async fn get_preference_ledger(&self, wallet_address: &str, query: &PreferenceLedgerQuery) -> Result<PreferenceLedgerPage, ApiError> {
+        let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+        let mut conditions = vec![doc! { "wallet_address": wallet_address }];
+        if let Some(symbol) = &query.symbol {
+            conditions.push(doc! { "symbol": symbol });
+        }
+        if let Some(kind) = &query.kind {
+            let kind = kind.parse::<PreferenceLedgerKind>().map_err(ApiError::ValidationError)?;
+            conditions.push(doc! { "kind": bson::to_bson(&kind).map_err(|e| ApiError::InternalError(e.to_string()))? });
+        }
+        if let Some(cursor) = &query.cursor {
+            let (created_at, id) = decode_cursor(cursor).map_err(ApiError::ValidationError)?;
+            let id = ObjectId::parse_str(&id).map_err(|_| ApiError::ValidationError("Invalid cursor".to_string()))?;
+            conditions.push(doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, "_id": { "$lt": id } }
+                ]
+            });
+        }
+
+        // Fetch one extra to know whether there is another page
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+        let mut entries: Vec<PreferenceLedgerEntry> = self.preference_ledger
+            .find(doc! { "$and": conditions }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let next_cursor = if entries.len() as i64 > limit {
+            entries.truncate(limit as usize);
+            entries.last().and_then(|e| e.id.map(|id| encode_cursor(e.created_at, &id.to_hex())))
+        } else {
+            None
+        };
+        Ok(PreferenceLedgerPage { entries, next_cursor })
+    }
+
+    /// A wallet's latest preference changes, newest first
+    pub async fn get_preference_changes(&self, wallet_address: &str) -> Result<Vec<PreferenceChange>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(PREFERENCE_HISTORY_LIMIT)
+            .build();
+        self.preference_changes
+            .find(doc! { "wallet_address": wallet_address }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// A vendor's saved preference templates, by name
+    pub async fn get_preference_templates(&self, owner_address: &str) -> Result<Vec<PreferenceTemplate>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "name": 1 })
+            .build();
+        self.preference_templates
+            .find(doc! { "owner_address": owner_address }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_preference_template(&self, owner_address: &str, name: &str) -> Result<Option<PreferenceTemplate>, ApiError> {
+        self.preference_templates
+            .find_one(doc! { "owner_address": owner_address, "name": name }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Save a template, or overwrite the one with the same name. A new template takes the
+    /// owner's first free slot, and the unique slot index turns away a concurrent save that
+    /// took it first.
+    pub async fn save_preference_template(&self, owner_address: &str, name: &str, preferences: &std::collections::BTreeMap<String, f64>) -> Result<PreferenceTemplate, ApiError> {
+        let filter = doc! { "owner_address": owner_address, "name": name };
+        let preferences = bson::to_bson(preferences)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize preferences: {}", e)))?;
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let update = doc! { "$set": { "preferences": preferences.clone(), "updated_at": now } };
+        if let Some(template) = self.preference_templates
+            .find_one_and_update(filter.clone(), update, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+        {
+            return Ok(template);
+        }
+
+        let taken: Vec<i64> = self.preference_templates
+            .distinct("slot", doc! { "owner_address": owner_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .iter()
+            .filter_map(|slot| slot.as_i64().or_else(|| slot.as_i32().map(i64::from)))
+            .collect();
+        let slot = (0..MAX_PREFERENCE_TEMPLATES as i64)
+            .find(|slot| !taken.contains(slot))
+            .ok_or_else(|| ApiError::ValidationError(format!("At most {} preference templates can be saved", MAX_PREFERENCE_TEMPLATES)))?;
+
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.preference_templates
+            .find_one_and_update(
+                filter,
+                doc! {
+                    "$set": { "preferences": preferences, "updated_at": now },
+                    "$setOnInsert": { "created_at": now, "slot": slot },
+                },
+                options,
+            )
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("Template was saved concurrently, please retry".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?
+            .ok_or_else(|| ApiError::InternalError("Template upsert returned no document".to_string()))
+    }
+
+    /// Returns whether the template existed, freeing its slot
+    pub async fn delete_preference_template(&self, owner_address: &str, name: &str) -> Result<bool, ApiError> {
+        let result = self.preference_templates
+            .delete_one(doc! { "owner_address": owner_address, "name": name }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.deleted_count > 0)
+    }
+
     // Cause-related methods
     pub async fn create_cause(&self, cause: Cause) -> Result<String, mongodb::error::Error> {
         let result = self.causes.insert_one(cause, None).await?;
@@ -460,22 +1746,98 @@ impl MongoDBService {
     }
 
     pub async fn get_all_causes(&self) -> Result<Vec<Cause>, mongodb::error::Error> {
-        // Only return causes that are displayed
-        let filter = doc! { "displayed": true };
+        // Only return causes that are displayed and not archived
+        let filter = doc! { "displayed": true, "status": { "$ne": CauseStatus::Archived.to_string() } };
         let cursor = self.causes.find(filter, None).await?;
         cursor.try_collect().await
     }
     
+    /// How many causes `get_all_causes` returns and when the latest of them changed, as an ETag
+    pub async fn listed_causes_etag(&self) -> Result<String, ApiError> {
+        let pipeline = vec![
+            doc! { "$match": { "displayed": true, "status": { "$ne": CauseStatus::Archived.to_string() } } },
+            doc! { "$group": { "_id": null, "count": { "$sum": 1 }, "latest": { "$max": "$updated_at" } } },
+        ];
+        let summary = self.causes.aggregate(pipeline, None).await
+            .map_err(ApiError::DatabaseError)?
+            .try_next().await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(match summary {
+            Some(summary) => {
+                let latest = summary.get_datetime("latest").map(|latest| latest.timestamp_millis()).unwrap_or(0);
+                listing_etag(number(&summary, "count") as u64, latest)
+            },
+            None => listing_etag(0, 0),
+        })
+    }
+    
+    /// A page of the causes `get_all_causes` returns, newest first, with only the fields
+    /// a listing shows
+    pub async fn get_cause_summaries(&self, query: &CauseListQuery) -> Result<CauseListPage, ApiError> {
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        let mut conditions = vec![doc! { "displayed": true, "status": { "$ne": CauseStatus::Archived.to_string() } }];
+        if let Some(cursor) = &query.cursor {
+            let (created_at, id) = decode_cursor(cursor).map_err(ApiError::ValidationError)?;
+            let created_at = bson::DateTime::from_millis(created_at);
+            let id = ObjectId::parse_str(&id).map_err(|_| ApiError::ValidationError("Invalid cursor".to_string()))?;
+            conditions.push(doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, "_id": { "$lt": id } }
+                ]
+            });
+        }
+        
+        // Fetch one extra to know whether there is another page
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .projection(CauseSummary::projection())
+            .limit(limit + 1)
+            .build();
+        let mut causes: Vec<CauseSummary> = self.causes
+            .clone_with_type::<CauseSummary>()
+            .find(doc! { "$and": conditions }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let next_cursor = if causes.len() as i64 > limit {
+            causes.truncate(limit as usize);
+            causes.last().map(|c| encode_cursor(c.created_at.timestamp_millis(), &c.id.to_hex()))
+        } else {
+            None
+        };
+        
+        Ok(CauseListPage { causes, next_cursor })
+    }
+    
     pub async fn get_featured_causes(&self) -> Result<Vec<Cause>, mongodb::error::Error> {
-        // Get causes that are both featured and displayed, sorted by creation date
+        // Get causes that are both featured and displayed and whose feature hasn't run out
         let filter = doc! { 
             "featured": true,
-            "displayed": true 
+            "displayed": true,
+            "status": { "$ne": CauseStatus::Archived.to_string() },
+            "$or": [
+                { "featured_until": null },
+                { "featured_until": { "$gt": chrono::Utc::now().timestamp() } },
+            ],
         };
         let options = mongodb::options::FindOptions::builder()
             .sort(doc! { "created_at": -1 })
             .build();
-        let cursor = self.causes.find(filter, options).await?;
+        let mut causes: Vec<Cause> = self.causes.find(filter, options).await?.try_collect().await?;
+        // Ranked causes first by rank, then unranked ones newest first (a stable sort keeps that order)
+        causes.sort_by_key(|cause| cause.featured_rank.unwrap_or(i32::MAX));
+        Ok(causes)
+    }
+    
+    /// Featured causes whose `featured_until` is at or before `now`
+    pub async fn get_expired_featured_causes(&self, now: i64) -> Result<Vec<Cause>, mongodb::error::Error> {
+        let filter = doc! { "featured": true, "featured_until": { "$lte": now } };
+        let cursor = self.causes.find(filter, None).await?;
         cursor.try_collect().await
     }
     
@@ -484,36 +1846,174 @@ impl MongoDBService {
         let cursor = self.causes.find(None, None).await?;
         cursor.try_collect().await
     }
-
-    pub async fn update_cause(&self, id: &ObjectId, update: UpdateCauseRequest) -> Result<bool, mongodb::error::Error> {
-        // Build the update document based on provided fields
-        let mut update_doc = doc! {};
-        
-        if let Some(name) = update.name {
-            update_doc.insert("name", name);
-        }
-        if let Some(organization) = update.organization {
-            update_doc.insert("organization", organization);
-        }
-        if let Some(description) = update.description {
-            update_doc.insert("description", description);
-        }
-        if let Some(long_description) = update.long_description {
-            update_doc.insert("long_description", long_description);
-        }
-        if let Some(is_active) = update.is_active {
-            update_doc.insert("is_active", is_active);
-        }
-        if let Some(stripe_id) = update.stripe_product_id {
-            update_doc.insert("stripe_product_id", stripe_id);
-        }
-        if let Some(payment_link) = update.payment_link {
-            update_doc.insert("payment_link", payment_link);
-        }
-        if let Some(status) = update.status {
-            update_doc.insert("status", status.to_string());
+    
+    pub async fn get_causes_by_status(&self, status: CauseStatus) -> Result<Vec<Cause>, mongodb::error::Error> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+        let cursor = self.causes.find(doc! { "status": status.to_string() }, options).await?;
+        cursor.try_collect().await
+    }
+    
+    /// Record what Stripe still needs on every cause using the account. Returns how many changed.
+    pub async fn set_cause_requirements(&self, stripe_account_id: &str, requirements: &StripeRequirements) -> Result<u64, ApiError> {
+        let requirements = bson::to_bson(requirements)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize requirements: {}", e)))?;
+        let result = self.causes
+            .update_many(
+                doc! { "stripe_account_id": stripe_account_id },
+                doc! { "$set": { "requirements": requirements, "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count)
+    }
+    
+    /// Causes with Stripe requirements on record, complete ones included unless `state`
+    /// narrows it, most recently checked first, at most `limit`
+    pub async fn get_causes_by_requirements(&self, state: Option<RequirementsState>, limit: i64) -> Result<Vec<Cause>, ApiError> {
+        let filter = match state {
+            Some(state) => doc! { "requirements.state": state.to_string() },
+            None => doc! { "requirements": { "$exists": true } },
+        };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "requirements.checked_at": -1 })
+            .limit(limit)
+            .build();
+        self.causes
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Number of causes in each status
+    pub async fn count_causes_by_status(&self) -> Result<std::collections::BTreeMap<String, u64>, mongodb::error::Error> {
+        let pipeline = vec![
+            doc! { "$group": { "_id": "$status", "count": { "$sum": 1 } } },
+        ];
+        let groups: Vec<Document> = self.causes.aggregate(pipeline, None).await?.try_collect().await?;
+        Ok(groups.iter()
+            .filter_map(|group| Some((group.get_str("_id").ok()?.to_string(), number(group, "count") as u64)))
+            .collect())
+    }
+    
+    /// Drafts still waiting on Stripe onboarding that were created before `created_before`, oldest first
+    pub async fn get_stripe_pending_drafts(&self, created_before: chrono::DateTime<chrono::Utc>) -> Result<Vec<CauseDraft>, mongodb::error::Error> {
+        let filter = doc! {
+            "status": bson::to_bson(&DraftStatus::StripePending)?,
+            "created_at": { "$lt": bson::DateTime::from_chrono(created_before) },
+        };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+        let cursor = self.cause_drafts.find(filter, options).await?;
+        cursor.try_collect().await
+    }
+    
+    /// Store a moderation decision; rejected causes are also hidden and deactivated
+    pub async fn set_cause_review(&self, id: &ObjectId, review: &CauseReview, status: Option<CauseStatus>) -> Result<(), mongodb::error::Error> {
+        let mut fields = doc! {
+            "review": bson::to_bson(review)?,
+            "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()),
+        };
+        if let Some(status) = status {
+            if status == CauseStatus::Rejected {
+                fields.insert("displayed", false);
+                fields.insert("is_active", false);
+            }
+            fields.insert("status", status.to_string());
         }
-        
+        self.causes.update_one(doc! { "_id": id }, doc! { "$set": fields }, None).await?;
+        Ok(())
+    }
+    
+    // Cause creation saga state
+    pub async fn set_cause_creation(&self, id: &ObjectId, saga: &CreationSaga) -> Result<(), mongodb::error::Error> {
+        let update = doc! {
+            "$set": {
+                "creation": bson::to_bson(saga)?,
+                "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()),
+            }
+        };
+        self.causes.update_one(doc! { "_id": id }, update, None).await?;
+        Ok(())
+    }
+    
+    pub async fn fail_cause_creation(&self, id: &ObjectId, saga: &CreationSaga, error: &str) -> Result<(), mongodb::error::Error> {
+        let update = doc! {
+            "$set": {
+                "status": CauseStatus::Failed.to_string(),
+                "error_message": error,
+                "creation": bson::to_bson(saga)?,
+                "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()),
+            }
+        };
+        self.causes.update_one(doc! { "_id": id }, update, None).await?;
+        Ok(())
+    }
+    
+    /// Take the creation lock on a cause unless another runner holds it.
+    /// Returns false if the saga is locked or already done.
+    pub async fn claim_cause_creation(&self, id: &ObjectId, now: i64, lock_secs: i64) -> Result<bool, mongodb::error::Error> {
+        let filter = doc! {
+            "_id": id,
+            "creation.step": { "$ne": CreationStep::Done.to_string() },
+            "$or": [
+                { "creation.locked_until": null },
+                { "creation.locked_until": { "$lt": now } },
+            ],
+        };
+        let update = doc! { "$set": { "creation.locked_until": now + lock_secs } };
+        let result = self.causes.update_one(filter, update, None).await?;
+        Ok(result.modified_count > 0)
+    }
+    
+    /// Failed causes whose backoff has elapsed and that have retries left
+    pub async fn get_causes_due_for_retry(&self, now: i64, max_attempts: i32) -> Result<Vec<Cause>, mongodb::error::Error> {
+        let filter = doc! {
+            "status": CauseStatus::Failed.to_string(),
+            "creation.step": { "$ne": CreationStep::Done.to_string() },
+            "creation.next_retry_at": { "$lte": now },
+            "creation.attempts": { "$lt": max_attempts },
+        };
+        let cursor = self.causes.find(filter, None).await?;
+        cursor.try_collect().await
+    }
+
+    pub async fn update_cause(&self, id: &ObjectId, update: UpdateCauseRequest) -> Result<bool, mongodb::error::Error> {
+        // Build the update document based on provided fields
+        let mut update_doc = doc! {};
+        let update_featured = update.featured;
+        
+        if let Some(name) = update.name {
+            update_doc.insert("name", name);
+        }
+        if let Some(organization) = update.organization {
+            update_doc.insert("organization", organization);
+        }
+        if let Some(description) = update.description {
+            update_doc.insert("description", description);
+        }
+        if let Some(long_description) = update.long_description {
+            update_doc.insert("long_description", long_description);
+        }
+        if let Some(is_active) = update.is_active {
+            update_doc.insert("is_active", is_active);
+        }
+        if let Some(stripe_id) = update.stripe_product_id {
+            update_doc.insert("stripe_product_id", stripe_id);
+        }
+        if let Some(payment_link) = update.payment_link {
+            update_doc.insert("payment_link", payment_link);
+        }
+        if let Some(status) = update.status {
+            update_doc.insert("status", status.to_string());
+        }
+        
         if let Some(token_id) = update.token_id {
             update_doc.insert("token_id", token_id);
         }
@@ -535,11 +2035,23 @@ impl MongoDBService {
         if let Some(featured) = update.featured {
             update_doc.insert("featured", featured);
         }
+        if update_featured != Some(false) {
+            if let Some(featured_rank) = update.featured_rank {
+                update_doc.insert("featured_rank", featured_rank);
+            }
+            if let Some(featured_until) = update.featured_until {
+                update_doc.insert("featured_until", featured_until);
+            }
+        }
 
         // Add updated_at timestamp
         update_doc.insert("updated_at", chrono::Utc::now());
         
-        let update = doc! { "$set": update_doc };
+        let mut update = doc! { "$set": update_doc };
+        // An unfeatured cause keeps no rank or schedule to come back with
+        if update_featured == Some(false) {
+            update.insert("$unset", doc! { "featured_rank": "", "featured_until": "" });
+        }
         let filter = doc! { "_id": id };
         
         let result = self.causes.update_one(filter, update, None).await?;
@@ -612,6 +2124,37 @@ impl MongoDBService {
         Ok(result.modified_count > 0)
     }
     
+    /// Move a draft's expiry; the TTL index reads `expires_at`, so this keeps the draft alive
+    pub async fn extend_draft_expiry(&self, id: &ObjectId, expires_at: chrono::DateTime<chrono::Utc>) -> Result<bool, mongodb::error::Error> {
+        let update = doc! {
+            "$set": { "expires_at": bson::DateTime::from_chrono(expires_at) },
+            // A fresh expiry deserves a fresh reminder
+            "$unset": { "reminder_sent_at": "" },
+        };
+        let result = self.cause_drafts.update_one(doc! { "_id": id }, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+    
+    /// Unfinished drafts expiring before `before` that have not been reminded yet
+    pub async fn get_drafts_needing_reminder(&self, before: chrono::DateTime<chrono::Utc>) -> Result<Vec<CauseDraft>, mongodb::error::Error> {
+        let filter = doc! {
+            "status": { "$ne": bson::to_bson(&DraftStatus::Completed)? },
+            "expires_at": {
+                "$gt": bson::DateTime::from_chrono(chrono::Utc::now()),
+                "$lte": bson::DateTime::from_chrono(before),
+            },
+            "reminder_sent_at": { "$exists": false },
+        };
+        let cursor = self.cause_drafts.find(filter, None).await?;
+        cursor.try_collect().await
+    }
+    
+    pub async fn mark_draft_reminder_sent(&self, id: &ObjectId) -> Result<(), mongodb::error::Error> {
+        let update = doc! { "$set": { "reminder_sent_at": bson::DateTime::from_chrono(chrono::Utc::now()) } };
+        self.cause_drafts.update_one(doc! { "_id": id }, update, None).await?;
+        Ok(())
+    }
+    
     pub async fn find_drafts_by_email(&self, email: &str) -> Result<Vec<CauseDraft>, mongodb::error::Error> {
         let filter = doc! { 
             "creator_email": email,
@@ -631,6 +2174,12 @@ impl MongoDBService {
         &self.causes
     }
 
+    /// Extra reserved/profane words maintained in the database
+    pub async fn get_blocked_words(&self) -> Result<Vec<BlockedWord>, mongodb::error::Error> {
+        let cursor = self.blocked_words.find(None, None).await?;
+        cursor.try_collect().await
+    }
+    
     pub async fn is_cause_name_taken(&self, name: &str) -> Result<bool, mongodb::error::Error> {
         let filter = doc! { 
             "name": { "$regex": &format!("^{}$", name), "$options": "i" }
@@ -665,70 +2214,209 @@ impl MongoDBService {
         Ok(user.preferences.0) // Return the Document containing preferences
     }
 
-    // Update user preferences after consuming discounts
-    pub async fn update_user_preferences_after_payment(
-        &self,
-        user_address: &str,
-        discount_consumptions: &[DiscountConsumption],
-        _effective_valuations: Option<&[(String, f64)]>, // Deprecated parameter, kept for compatibility
-    ) -> Result<(), ApiError> {
-        // Get current preferences
-        let current_prefs = self.get_user_preferences(user_address).await?;
-        let mut updated_prefs = current_prefs.clone();
-        
-        // Apply discount consumptions
-        for consumption in discount_consumptions {
-            if consumption.amount_used > 0.0 {
-                // Use token symbol as key (matching how preferences are stored)
-                let token_symbol = &consumption.symbol;
-                
-                // Update the preference value for this token
-                if let Some(current_value) = updated_prefs.get(token_symbol) {
-                    if let Some(current_float) = current_value.as_f64() {
-                        // Calculate new value after consuming discount/premium
-                        // Positive values (discounts) decrease towards 0
-                        // Negative values (premiums) increase towards 0
-                        let new_value = if current_float > 0.0 {
-                            // Positive value = discount available
-                            // Reduce the discount by the amount consumed
-                            let new_val = current_float - consumption.amount_used;
-                            new_val.max(0.0) // Don't go below 0.0
-                        } else if current_float < 0.0 {
-                            // Negative value = premium charged
-                            // Move towards 0 by the amount consumed (premium paid)
-                            let new_val = current_float + consumption.amount_used;
-                            new_val.min(0.0) // Don't go above 0.0
-                        } else {
-                            // Already at zero
-                            0.0
-                        };
-                        
-                        updated_prefs.insert(token_symbol.clone(), new_value);
-                        log::info!("Updated {} preference from {} to {} after consuming {}", 
-                                  token_symbol, current_float, new_value, consumption.amount_used);
-                    }
+    /// Draw a payment's discount consumption from the vendor's budgets. Each token is drawn
+    /// with a conditional `$inc`, so concurrent payments can't take more than is left: a
+    /// discount only while the budget covers it, a premium only while the (negative) budget
+    /// does. If one ran out since the payment was supplemented, nothing is drawn and
+    /// `DiscountBudgetExhausted` tells the payer to supplement again. Draws once per payment:
+    /// the same update that draws a token records the draw under the vendor's
+    /// `budget_draws.{payment_id}`, so a crash part way leaves nothing drawn unrecorded, and
+    /// drawing again skips what was already drawn.
+    pub async fn consume_discount_budgets(&self, payment: &Payment) -> Result<(), ApiError> {
+        let consumptions: Vec<&DiscountConsumption> = payment.discount_consumption.iter()
+            .flatten()
+            .filter(|c| c.amount_used != 0.0)
+            .collect();
+        if consumptions.is_empty() || payment.budget_consumed {
+            return Ok(());
+        }
+
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let now = chrono::Utc::now().timestamp();
+        let mut entries: Vec<PreferenceLedgerEntry> = Vec::new();
+        for consumption in consumptions {
+            let key = format!("preferences.{}", consumption.symbol);
+            let draw_key = budget_draw_key(&payment.payment_id, &consumption.symbol);
+            let covered = if consumption.amount_used > 0.0 {
+                doc! { "$gte": consumption.amount_used }
+            } else {
+                doc! { "$lte": consumption.amount_used }
+            };
+            let drawn = self.users
+                .find_one_and_update(
+                    doc! { "wallet_address": &payment.vendor_address, key.clone(): covered, draw_key.clone(): { "$exists": false } },
+                    doc! {
+                        "$inc": { key: -consumption.amount_used },
+                        "$set": { draw_key.clone(): consumption.amount_used },
+                    },
+                    options.clone(),
+                )
+                .await
+                .map_err(ApiError::DatabaseError)?;
+
+            let Some(vendor) = drawn else {
+                let already_drawn = self.users
+                    .count_documents(doc! { "wallet_address": &payment.vendor_address, draw_key: { "$exists": true } }, None)
+                    .await
+                    .map_err(ApiError::DatabaseError)? > 0;
+                if already_drawn {
+                    continue;
                 }
-            }
+                // Give back what this payment already drew
+                self.return_budget_draws(payment).await?;
+                return Err(ApiError::DiscountBudgetExhausted(format!(
+                    "the vendor's {} budget no longer covers this payment", consumption.symbol
+                )));
+            };
+            let after = vendor.preferences.0.get(&consumption.symbol).and_then(budget_value).unwrap_or(0.0);
+            entries.push(PreferenceLedgerEntry {
+                id: None,
+                wallet_address: payment.vendor_address.clone(),
+                kind: PreferenceLedgerKind::Consumption,
+                symbol: consumption.symbol.clone(),
+                amount: -consumption.amount_used,
+                before: after + consumption.amount_used,
+                after,
+                payment_id: Some(payment.payment_id.clone()),
+                source: None,
+                created_at: now,
+            });
+            log::info!("Drew {} from {}'s {} budget for payment {}, {} left",
+                consumption.amount_used, payment.vendor_address, consumption.symbol, payment.payment_id, after);
         }
-        
-        // Note: We no longer store effective valuations in preferences
-        // They are now stored in transaction records for market price calculation
-        
-        // Update user preferences in database
-        let filter = doc! { "wallet_address": user_address };
-        let update = doc! {
-            "$set": {
-                "preferences": bson::to_bson(&updated_prefs)
-                    .map_err(|e| ApiError::InternalError(format!("Failed to serialize preferences: {}", e)))?
+
+        self.set_budget_consumed(&payment.payment_id, true).await?;
+        if !entries.is_empty() {
+            self.preference_ledger
+                .insert_many(&entries, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+        }
+        Ok(())
+    }
+
+    /// Give back the budgets a payment drew, once it failed. Draws recorded on the vendor
+    /// are each given back in the same update that forgets them.
+    pub async fn release_discount_budgets(&self, payment: &Payment) -> Result<(), ApiError> {
+        let unflagged = self.set_budget_consumed(&payment.payment_id, false).await?;
+        let mut released = self.return_budget_draws(payment).await?;
+        if unflagged && released.is_empty() {
+            // Drawn before draws were recorded on the vendor
+            released = payment.discount_consumption.iter()
+                .flatten()
+                .filter(|c| c.amount_used != 0.0)
+                .map(|c| (c.symbol.clone(), c.amount_used))
+                .collect();
+            self.adjust_discount_budgets(&payment.vendor_address, released.iter().map(|(symbol, amount)| (symbol.as_str(), *amount))).await?;
+        }
+        if released.is_empty() {
+            return Ok(());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let preferences = self.get_user_preferences(&payment.vendor_address).await?;
+        let entries: Vec<PreferenceLedgerEntry> = released.iter()
+            .map(|(symbol, amount)| {
+                let after = preferences.get(symbol).and_then(budget_value).unwrap_or(0.0);
+                PreferenceLedgerEntry {
+                    id: None,
+                    wallet_address: payment.vendor_address.clone(),
+                    kind: PreferenceLedgerKind::Release,
+                    symbol: symbol.clone(),
+                    amount: *amount,
+                    before: after - amount,
+                    after,
+                    payment_id: Some(payment.payment_id.clone()),
+                    source: None,
+                    created_at: now,
+                }
+            })
+            .collect();
+        self.preference_ledger
+            .insert_many(&entries, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        log::info!("Released {}'s discount budgets drawn by failed payment {}", payment.vendor_address, payment.payment_id);
+        Ok(())
+    }
+
+    /// Forget the draws recorded for a completed payment; what it drew stays drawn
+    pub async fn settle_discount_budgets(&self, payment: &Payment) -> Result<(), ApiError> {
+        self.users
+            .update_one(
+                doc! { "wallet_address": &payment.vendor_address },
+                doc! { "$unset": { format!("budget_draws.{}", payment.payment_id): "" } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Add each token a payment drew back to the vendor's budget, forgetting the draw in
+    /// the same update. Returns the (symbol, amount) given back.
+    async fn return_budget_draws(&self, payment: &Payment) -> Result<Vec<(String, f64)>, ApiError> {
+        let consumptions = payment.discount_consumption.iter()
+            .flatten()
+            .filter(|c| c.amount_used != 0.0);
+        let mut returned = Vec::new();
+        for consumption in consumptions {
+            let draw_key = budget_draw_key(&payment.payment_id, &consumption.symbol);
+            let result = self.users
+                .update_one(
+                    doc! { "wallet_address": &payment.vendor_address, draw_key.clone(): { "$exists": true } },
+                    doc! {
+                        "$inc": { format!("preferences.{}", consumption.symbol): consumption.amount_used },
+                        "$unset": { draw_key: "" },
+                    },
+                    None,
+                )
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            if result.modified_count > 0 {
+                returned.push((consumption.symbol.clone(), consumption.amount_used));
             }
-        };
-        
-        self.users.update_one(filter, update, None).await
-            .map_err(|e| ApiError::InternalError(format!("Failed to update user preferences: {}", e)))?;
-        
+        }
+        Ok(returned)
+    }
+
+    /// Add `amount` to each token's budget
+    async fn adjust_discount_budgets<'a>(&self, wallet_address: &str, adjustments: impl Iterator<Item = (&'a str, f64)>) -> Result<(), ApiError> {
+        let mut inc = Document::new();
+        for (symbol, amount) in adjustments {
+            inc.insert(format!("preferences.{}", symbol), amount);
+        }
+        if inc.is_empty() {
+            return Ok(());
+        }
+        self.users
+            .update_one(doc! { "wallet_address": wallet_address }, doc! { "$inc": inc }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
         Ok(())
     }
 
+    /// Flip a payment's `budget_consumed` flag. Returns false if it was already set that way.
+    async fn set_budget_consumed(&self, payment_id: &str, consumed: bool) -> Result<bool, ApiError> {
+        // Payments from before the flag don't have it, and never drew at signing
+        let filter = if consumed {
+            doc! { "payment_id": payment_id, "budget_consumed": { "$ne": true } }
+        } else {
+            doc! { "payment_id": payment_id, "budget_consumed": true }
+        };
+        let result = self.transactions
+            .update_one(
+                filter,
+                doc! { "$set": { "budget_consumed": consumed } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
     // Update payment with all calculated data
     pub async fn update_payment_with_calculations(
         &self,
@@ -754,8 +2442,11 @@ impl MongoDBService {
             }
         };
         
-        self.transactions.update_one(filter, update, None).await
+        let result = self.transactions.update_one(filter, update, None).await
             .map_err(|e| ApiError::InternalError(format!("Failed to update payment: {}", e)))?;
+        if result.matched_count == 0 {
+            return Err(ApiError::NotFound(format!("Payment {} not found", payment_id)));
+        }
         
         Ok(())
     }
@@ -775,13 +2466,19 @@ impl MongoDBService {
         
         // Check if the requester is the vendor
         if payment.vendor_address != vendor_address {
-            return Err(ApiError::ValidationError("Only the vendor can cancel this payment".to_string()));
+            return Err(ApiError::Forbidden("Only the vendor can cancel this payment".to_string()));
         }
         
         // Check if payment is already completed
         if matches!(payment.status, PaymentStatus::Completed) {
             return Err(ApiError::ValidationError("Cannot cancel completed payment".to_string()));
         }
+        if matches!(payment.status, PaymentStatus::Submitted) {
+            return Err(ApiError::ValidationError("Cannot cancel a payment that is being processed".to_string()));
+        }
+        if matches!(payment.status, PaymentStatus::Authorized) {
+            return Err(ApiError::ValidationError("Void an authorized payment instead of cancelling it".to_string()));
+        }
         
         // Delete the payment
         let filter = doc! { "payment_id": payment_id };
@@ -796,142 +2493,4851 @@ impl MongoDBService {
         Ok(())
     }
 
-    /// Update the status of a payment
-    pub async fn update_payment_status(
-        &self,
-        payment_id: &str,
-        status: PaymentStatus,
-    ) -> Result<(), ApiError> {
-        log::info!("Updating payment {} status to {:?}", payment_id, status);
-        
-        let filter = doc! { "payment_id": payment_id };
-        let update = doc! {
-            "$set": {
-                "status": bson::to_bson(&status)
-                    .map_err(|e| ApiError::InternalError(format!("Failed to serialize status: {}", e)))?
+    /// Record the executor transaction that carried a payment's debit allowances
+    pub async fn set_payment_executor_tx_id(&self, payment_id: &str, executor_tx_id: &str) -> Result<(), ApiError> {
+        self.transactions
+            .update_one(doc! { "payment_id": payment_id }, doc! { "$set": { "executor_tx_id": executor_tx_id } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Claim signed allowances by hash before they're submitted. Fails with a conflict if
+    /// any was submitted before, claiming none of them.
+    pub async fn record_submitted_allowances(&self, hashes: &[String], payment_id: &str) -> Result<(), ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        for (i, hash) in hashes.iter().enumerate() {
+            let allowance = SubmittedAllowance {
+                id: None,
+                hash: hash.clone(),
+                payment_id: payment_id.to_string(),
+                created_at: now,
+            };
+            if let Err(e) = self.submitted_allowances.insert_one(allowance, None).await {
+                self.forget_submitted_allowances(&hashes[..i]).await?;
+                return Err(if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("This signed transaction was already submitted".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                });
             }
-        };
-        
-        self.transactions.update_one(filter, update, None).await
-            .map_err(|e| {
-                log::error!("Failed to update payment status: {}", e);
-                ApiError::DatabaseError(e)
-            })?;
-        
-        log::info!("Successfully updated payment {} status to {:?}", payment_id, status);
+        }
         Ok(())
     }
 
-    // Deposit Records methods
-    pub async fn save_deposit_record(&self, deposit: DepositRecord) -> Result<(), ApiError> {
-        self.deposit_records
-            .insert_one(deposit, None)
+    /// Give back claimed allowances whose submission failed, so they can be retried
+    pub async fn forget_submitted_allowances(&self, hashes: &[String]) -> Result<(), ApiError> {
+        if hashes.is_empty() {
+            return Ok(());
+        }
+        self.submitted_allowances
+            .delete_many(doc! { "hash": { "$in": hashes } }, None)
             .await
-            .map_err(|e| ApiError::DatabaseError(e))?;
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Hold a two-phase payment's signed allowances. Fails with a conflict if some are
+    /// already held for it.
+    pub async fn hold_authorization(&self, held: &HeldAuthorization) -> Result<(), ApiError> {
+        self.held_authorizations.insert_one(held, None).await
+            .map_err(|e| if e.to_string().contains("E11000 duplicate key error") {
+                ApiError::Conflict("This payment is already authorized".to_string())
+            } else {
+                ApiError::DatabaseError(e)
+            })?;
         Ok(())
     }
     
-    pub async fn get_user_deposits(&self, wallet_address: &str) -> Result<Vec<DepositRecord>, ApiError> {
-        let filter = doc! { "wallet_address": wallet_address };
-        let mut cursor = self.deposit_records
-            .find(filter, None)
+    /// Take a payment's held allowances, so only one of capture and void gets them
+    pub async fn take_held_authorization(&self, payment_id: &str) -> Result<Option<HeldAuthorization>, ApiError> {
+        self.held_authorizations
+            .find_one_and_delete(doc! { "payment_id": payment_id }, None)
             .await
-            .map_err(|e| ApiError::DatabaseError(e))?;
-        
-        let mut deposits = Vec::new();
-        while let Some(deposit) = cursor.try_next().await.map_err(|e| ApiError::DatabaseError(e))? {
-            deposits.push(deposit);
-        }
-        
-        Ok(deposits)
+            .map_err(ApiError::DatabaseError)
     }
-
-    // Transaction Records methods for market price calculations
-    pub async fn create_transaction_record(&self, record: TransactionRecord) -> Result<TransactionRecord, ApiError> {
-        let result = self.transaction_records
-            .insert_one(record.clone(), None)
+    
+    /// Move a supplemented two-phase payment to Authorized once its allowances are held.
+    /// Returns whether it was waiting to be signed.
+    pub async fn authorize_payment(&self, payment_id: &str, authorized_at: i64, expires_at: i64, capture_nonce: i64) -> Result<bool, ApiError> {
+        let result = self.transactions
+            .update_one(
+                doc! { "payment_id": payment_id, "status": PaymentStatus::Calculated.to_string(), "authorization": { "$exists": true } },
+                doc! { "$set": {
+                    "status": PaymentStatus::Authorized.to_string(),
+                    "authorization.authorized_at": authorized_at,
+                    "authorization.expires_at": expires_at,
+                    "authorization.capture_nonce": capture_nonce,
+                } },
+                None,
+            )
             .await
             .map_err(ApiError::DatabaseError)?;
-        
-        log::info!("Created transaction record with ID: {:?}", result.inserted_id);
-        Ok(record)
+        Ok(result.modified_count == 1)
     }
-
-    pub async fn get_recent_transactions_for_token(&self, token_key: &str, limit: i64) -> Result<Vec<TransactionRecord>, ApiError> {
-        let cursor = self.transaction_records
-            .find(doc! { "token_key": token_key }, None)
+    
+    /// Whether the payer has an authorization waiting on their next nonce: held for capture,
+    /// or captured without the executor confirming it
+    pub async fn has_pending_capture(&self, payer_address: &str) -> Result<bool, ApiError> {
+        let filter = doc! {
+            "customer_address": payer_address,
+            "authorization": { "$exists": true },
+            "$or": [
+                { "status": PaymentStatus::Authorized.to_string() },
+                { "status": PaymentStatus::Submitted.to_string(), "executor_tx_id": { "$exists": false } },
+            ],
+        };
+        let options = mongodb::options::CountOptions::builder().limit(1).build();
+        let count = self.transactions
+            .count_documents(filter, options)
             .await
             .map_err(ApiError::DatabaseError)?;
-        
-        let mut records: Vec<TransactionRecord> = cursor
-            .try_collect()
+        Ok(count > 0)
+    }
+    
+    /// Move a capture the executor didn't answer for to Submitted without a transaction ID,
+    /// for the finality poller to settle from the payer's nonce. Returns whether it was
+    /// still authorized.
+    pub async fn mark_capture_unconfirmed(&self, payment_id: &str, payment_bundle: &[TokenPayment]) -> Result<bool, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let result = self.transactions
+            .update_one(
+                doc! { "payment_id": payment_id, "status": PaymentStatus::Authorized.to_string() },
+                doc! { "$set": {
+                    "status": PaymentStatus::Submitted.to_string(),
+                    "submitted_at": now,
+                    "authorization.captured_at": now,
+                    "computed_payment": bson::to_bson(payment_bundle)
+                        .map_err(|e| ApiError::InternalError(format!("Failed to serialize payment bundle: {}", e)))?,
+                } },
+                None,
+            )
             .await
             .map_err(ApiError::DatabaseError)?;
-        
-        // Sort by timestamp descending (newest first) and limit
-        records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        records.truncate(limit as usize);
-        
-        Ok(records)
+        Ok(result.modified_count == 1)
     }
-
-    pub async fn update_token_market_price(&self, token_key: &str, new_price: f64) -> Result<(), ApiError> {
-        let result = self.tokens
+    
+    pub async fn mark_payment_captured(&self, payment_id: &str) -> Result<(), ApiError> {
+        self.transactions
             .update_one(
-                doc! { "token_id": token_key },
-                doc! { "$set": { "market_valuation": new_price } },
-                None
+                doc! { "payment_id": payment_id },
+                doc! { "$set": { "authorization.captured_at": chrono::Utc::now().timestamp() } },
+                None,
             )
             .await
             .map_err(ApiError::DatabaseError)?;
-        
-        if result.matched_count == 0 {
-            log::warn!("No token found with token_key: {}", token_key);
-        } else {
-            log::info!("Updated market price for token {}: {}", token_key, new_price);
-        }
-        
         Ok(())
     }
-
-
-    /// Get transaction history for a user address (as vendor or customer)
-    pub async fn get_user_transaction_history(&self, user_address: &str) -> Result<Vec<Payment>, ApiError> {
-        let filter = doc! {
-            "$or": [
-                { "vendor_address": user_address },
-                { "customer_address": user_address }
-            ]
+    
+    /// Delete a payment nobody has signed yet. Returns false if it was signed in the meantime,
+    /// or is gone.
+    pub async fn delete_unsigned_payment(&self, payment_id: &str) -> Result<bool, ApiError> {
+        let result = self.transactions
+            .delete_one(
+                doc! { "payment_id": payment_id, "status": { "$in": unsigned_statuses() }, "budget_consumed": { "$ne": true } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.deleted_count > 0)
+    }
+    
+    /// Move an authorized payment to Voided, returning it if it was still authorized
+    pub async fn void_payment(&self, payment_id: &str, voided_by: &str) -> Result<Option<Payment>, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.transactions
+            .find_one_and_update(
+                doc! { "payment_id": payment_id, "status": PaymentStatus::Authorized.to_string() },
+                doc! { "$set": {
+                    "status": PaymentStatus::Voided.to_string(),
+                    "authorization.voided_at": chrono::Utc::now().timestamp(),
+                    "authorization.voided_by": voided_by,
+                } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Authorized payments whose capture window has closed, longest expired first
+    pub async fn get_expired_authorizations(&self, now: i64, limit: i64) -> Result<Vec<Payment>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "authorization.expires_at": 1 })
+            .limit(limit)
+            .build();
+        self.transactions
+            .find(doc! { "status": PaymentStatus::Authorized.to_string(), "authorization.expires_at": { "$lte": now } }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Move a payment to Submitted once its signed allowances are with the executor. The
+    /// bundle that was signed is kept so completion can be applied after finality.
+    pub async fn mark_payment_submitted(&self, payment_id: &str, executor_tx_id: &str, payment_bundle: &[TokenPayment]) -> Result<(), ApiError> {
+        let update = doc! {
+            "$set": {
+                "status": bson::to_bson(&PaymentStatus::Submitted)
+                    .map_err(|e| ApiError::InternalError(format!("Failed to serialize status: {}", e)))?,
+                "executor_tx_id": executor_tx_id,
+                "submitted_at": chrono::Utc::now().timestamp(),
+                "computed_payment": bson::to_bson(payment_bundle)
+                    .map_err(|e| ApiError::InternalError(format!("Failed to serialize payment bundle: {}", e)))?,
+            }
         };
-        
-        let mut cursor = self.transactions
-            .find(filter, None)
+        self.transactions
+            .update_one(doc! { "payment_id": payment_id }, update, None)
             .await
             .map_err(ApiError::DatabaseError)?;
-        
-        let mut payments = Vec::new();
-        while let Some(payment) = cursor.try_next().await.map_err(ApiError::DatabaseError)? {
-            payments.push(payment);
+        Ok(())
+    }
+
+    /// Payments waiting on executor finality, least recently checked first, marked checked now.
+    /// Payments that stay pending go to the back, so they can't keep newer ones from being checked.
+    pub async fn get_submitted_payments(&self, limit: i64) -> Result<Vec<Payment>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "finality_checked_at": 1, "submitted_at": 1 })
+            .limit(limit)
+            .build();
+        let payments: Vec<Payment> = self.transactions
+            .find(doc! { "status": "Submitted" }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let payment_ids: Vec<&str> = payments.iter().map(|payment| payment.payment_id.as_str()).collect();
+        if !payment_ids.is_empty() {
+            self.transactions
+                .update_many(
+                    doc! { "payment_id": { "$in": payment_ids } },
+                    doc! { "$set": { "finality_checked_at": chrono::Utc::now().timestamp() } },
+                    None,
+                )
+                .await
+                .map_err(ApiError::DatabaseError)?;
+        }
+        Ok(payments)
+    }
+
+    /// Settle a Submitted payment as Completed or Failed. Conditional on it still being
+    /// Submitted, so only one poller applies the outcome; returns whether this call did.
+    pub async fn settle_submitted_payment(&self, payment_id: &str, status: PaymentStatus, failure_reason: Option<&str>) -> Result<bool, ApiError> {
+        let mut set = doc! {
+            "status": bson::to_bson(&status)
+                .map_err(|e| ApiError::InternalError(format!("Failed to serialize status: {}", e)))?
+        };
+        if let Some(reason) = failure_reason {
+            set.insert("failure_reason", reason);
         }
+        let result = self.transactions
+            .update_one(doc! { "payment_id": payment_id, "status": "Submitted" }, doc! { "$set": set }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count == 1)
+    }
+
+    /// Update the status of a payment
+    pub async fn update_payment_status(
+        &self,
+        payment_id: &str,
+        status: PaymentStatus,
+    ) -> Result<(), ApiError> {
+        log::info!("Updating payment {} status to {:?}", payment_id, status);
         
-        // Sort by created_at descending (newest first)
-        payments.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        let filter = doc! { "payment_id": payment_id };
+        let update = doc! {
+            "$set": {
+                "status": bson::to_bson(&status)
+                    .map_err(|e| ApiError::InternalError(format!("Failed to serialize status: {}", e)))?
+            }
+        };
         
-        Ok(payments)
+        self.transactions.update_one(filter, update, None).await
+            .map_err(|e| {
+                log::error!("Failed to update payment status: {}", e);
+                ApiError::DatabaseError(e)
+            })?;
+        
+        log::info!("Successfully updated payment {} status to {:?}", payment_id, status);
+        Ok(())
+    }
+
+    // Deposit Records methods
+    pub async fn save_deposit_record(&self, deposit: DepositRecord) -> Result<(), ApiError> {
+        self.deposit_records
+            .insert_one(deposit, None)
+            .await
+            .map_err(|e| ApiError::DatabaseError(e))?;
+        Ok(())
     }
     
-    // Get all partnered vendors
-    pub async fn get_all_partnered_vendors(&self) -> Result<Vec<PartneredVendor>, ApiError> {
-        let mut cursor = self.partnered_vendors
-            .find(None, None)
+    pub async fn get_deposit_by_session_id(&self, session_id: &str) -> Result<Option<DepositRecord>, ApiError> {
+        self.deposit_records
+            .find_one(doc! { "stripe_session_id": session_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_deposit_by_payment_intent_id(&self, payment_intent_id: &str) -> Result<Option<DepositRecord>, ApiError> {
+        self.deposit_records
+            .find_one(doc! { "stripe_payment_intent_id": payment_intent_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_deposit_by_idempotency_key(&self, idempotency_key: &str) -> Result<Option<DepositRecord>, ApiError> {
+        self.deposit_records
+            .find_one(doc! { "manual_credit.idempotency_key": idempotency_key }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Reserve a credit's key before its tokens move, so a retry or a concurrent request
+    /// can't credit it again. Returns false if the key is already reserved.
+    pub async fn reserve_credit(&self, reservation: &CreditReservation) -> Result<bool, ApiError> {
+        match self.credit_reservations.insert_one(reservation, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn get_credit_reservation(&self, key: &str) -> Result<Option<CreditReservation>, ApiError> {
+        self.credit_reservations
+            .find_one(doc! { "key": key }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_credit_reservation_by_id(&self, id: &ObjectId) -> Result<Option<CreditReservation>, ApiError> {
+        self.credit_reservations
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Move a reservation from `from` to `to`, setting `set` alongside. Returns the updated
+    /// reservation, or None if it wasn't in `from`.
+    pub async fn transition_credit_reservation(
+        &self,
+        key: &str,
+        from: CreditReservationStatus,
+        to: CreditReservationStatus,
+        mut set: Document,
+    ) -> Result<Option<CreditReservation>, ApiError> {
+        set.insert("status", to.to_string());
+        set.insert("updated_at", chrono::Utc::now().timestamp());
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.credit_reservations
+            .find_one_and_update(doc! { "key": key, "status": from.to_string() }, doc! { "$set": set }, options)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Drop a reservation in `from` whose tokens certainly didn't move, so its key can be
+    /// credited again. Returns false if it wasn't in `from`.
+    pub async fn release_credit_reservation(&self, key: &str, from: CreditReservationStatus) -> Result<bool, ApiError> {
+        let result = self.credit_reservations
+            .delete_one(doc! { "key": key, "status": from.to_string() }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.deleted_count == 1)
+    }
+
+    /// Failed credits waiting for an admin, most recent first
+    pub async fn get_failed_credit_reservations(&self, limit: i64) -> Result<Vec<CreditReservation>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "updated_at": -1 })
+            .limit(limit)
+            .build();
+        self.credit_reservations
+            .find(doc! { "status": CreditReservationStatus::Failed.to_string() }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn create_pending_deposit(&self, pending: &PendingDeposit) -> Result<(), ApiError> {
+        self.pending_deposits
+            .insert_one(pending, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Move a still-pending entry to completed or expired. Unknown sessions (e.g. payment
+    /// links, which we never created an entry for) are ignored.
+    pub async fn resolve_pending_deposit(&self, session_id: &str, status: PendingDepositStatus) -> Result<(), ApiError> {
+        self.pending_deposits
+            .update_one(
+                doc! { "stripe_session_id": session_id, "status": PendingDepositStatus::Pending.to_string() },
+                doc! { "$set": {
+                    "status": status.to_string(),
+                    "updated_at": chrono::Utc::now().timestamp(),
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// A wallet's checkouts still waiting for their webhook, newest first.
+    /// Sessions past Stripe's expiry are left out even if the expiry event was missed.
+    pub async fn get_pending_deposits(&self, wallet_address: &str) -> Result<Vec<PendingDeposit>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+
+        self.pending_deposits
+            .find(doc! {
+                "wallet_address": wallet_address,
+                "status": PendingDepositStatus::Pending.to_string(),
+                "expires_at": { "$gt": chrono::Utc::now().timestamp() },
+            }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Hold a payment for its donor's email. Returns false if the payment is already held.
+    pub async fn create_unclaimed_deposit(&self, unclaimed: &UnclaimedDeposit) -> Result<bool, ApiError> {
+        match self.unclaimed_deposits.insert_one(unclaimed, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+    
+    /// Deposits still waiting to be claimed for an email, oldest first
+    pub async fn get_unclaimed_deposits(&self, email: &str) -> Result<Vec<UnclaimedDeposit>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+
+        self.unclaimed_deposits
+            .find(doc! {
+                "email": email.trim().to_lowercase(),
+                "status": UnclaimedDepositStatus::Unclaimed.to_string(),
+            }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Reserve an unclaimed deposit for a wallet before crediting it, so concurrent claims
+    /// can't both credit it. Returns false if another claim got there first.
+    pub async fn reserve_unclaimed_deposit(&self, id: &ObjectId, wallet_address: &str) -> Result<bool, ApiError> {
+        let result = self.unclaimed_deposits
+            .update_one(
+                doc! { "_id": id, "status": UnclaimedDepositStatus::Unclaimed.to_string() },
+                doc! { "$set": {
+                    "status": UnclaimedDepositStatus::Claiming.to_string(),
+                    "claimed_by": wallet_address,
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count == 1)
+    }
+    
+    /// Settle a reserved deposit: claimed once credited, or back to unclaimed if crediting
+    /// certainly moved no tokens
+    pub async fn finish_unclaimed_deposit_claim(&self, id: &ObjectId, credited: bool) -> Result<(), ApiError> {
+        let update = if credited {
+            doc! { "$set": {
+                "status": UnclaimedDepositStatus::Claimed.to_string(),
+                "claimed_at": chrono::Utc::now().timestamp(),
+            } }
+        } else {
+            doc! { "$set": {
+                "status": UnclaimedDepositStatus::Unclaimed.to_string(),
+                "claimed_by": bson::Bson::Null,
+            } }
+        };
+        self.unclaimed_deposits
+            .update_one(doc! { "_id": id, "status": UnclaimedDepositStatus::Claiming.to_string() }, update, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Park a reserved deposit whose credit may have landed, so it can't be claimed again
+    /// until an admin has checked the executor
+    pub async fn fail_unclaimed_deposit_claim(&self, id: &ObjectId, reason: &str) -> Result<(), ApiError> {
+        self.unclaimed_deposits
+            .update_one(
+                doc! { "_id": id, "status": UnclaimedDepositStatus::Claiming.to_string() },
+                doc! { "$set": {
+                    "status": UnclaimedDepositStatus::Failed.to_string(),
+                    "failure_reason": reason,
+                } },
+                None,
+            )
             .await
             .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Deposits created in [start, end), oldest first
+    pub async fn get_deposits_between(&self, start: i64, end: i64) -> Result<Vec<DepositRecord>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+        self.deposit_records
+            .find(doc! { "created_at": { "$gte": start, "$lt": end } }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn get_user_deposits(&self, wallet_address: &str) -> Result<Vec<DepositRecord>, ApiError> {
+        let filter = doc! { "wallet_address": wallet_address };
+        let mut cursor = self.deposit_records
+            .find(filter, None)
+            .await
+            .map_err(|e| ApiError::DatabaseError(e))?;
         
-        let mut vendors = Vec::new();
-        while let Some(vendor) = cursor.try_next().await.map_err(ApiError::DatabaseError)? {
-            vendors.push(vendor);
+        let mut deposits = Vec::new();
+        while let Some(deposit) = cursor.try_next().await.map_err(|e| ApiError::DatabaseError(e))? {
+            deposits.push(deposit);
         }
         
-        Ok(vendors)
+        Ok(deposits)
+    }
+
+    // Transaction Records methods for market price calculations
+    pub async fn create_transaction_record(&self, record: TransactionRecord) -> Result<TransactionRecord, ApiError> {
+        let result = self.transaction_records
+            .insert_one(record.clone(), None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        log::info!("Created transaction record with ID: {:?}", result.inserted_id);
+        Ok(record)
+    }
+
+    pub async fn get_recent_transactions_for_token(&self, token_key: &str, limit: i64) -> Result<Vec<TransactionRecord>, ApiError> {
+        let cursor = self.transaction_records
+            .find(doc! { "token_key": token_key }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let mut records: Vec<TransactionRecord> = cursor
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Sort by timestamp descending (newest first) and limit
+        records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        records.truncate(limit as usize);
+        
+        Ok(records)
     }
-}
\ No newline at end of file
+
+    pub async fn update_token_market_price(&self, token_key: &str, new_price: f64) -> Result<(), ApiError> {
+        let result = self.tokens
+            .update_one(
+                doc! { "token_id": token_key },
+                doc! { "$set": { "market_valuation": new_price, "updated_at": chrono::Utc::now().timestamp() } },
+                None
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        if result.matched_count == 0 {
+            log::warn!("No token found with token_key: {}", token_key);
+        } else {
+            log::info!("Updated market price for token {}: {}", token_key, new_price);
+        }
+        
+        Ok(())
+    }
+
+
+    /// Get transaction history for a user address (as vendor or customer)
+    pub async fn get_user_transaction_history(&self, user_address: &str) -> Result<Vec<Payment>, ApiError> {
+        let filter = doc! {
+            "$or": [
+                { "vendor_address": user_address },
+                { "customer_address": user_address }
+            ]
+        };
+        
+        let mut cursor = self.transactions
+            .find(filter, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let mut payments = Vec::new();
+        while let Some(payment) = cursor.try_next().await.map_err(ApiError::DatabaseError)? {
+            payments.push(payment);
+        }
+        
+        // Sort by created_at descending (newest first)
+        payments.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        
+        Ok(payments)
+    }
+    
+    /// A wallet's payments and deposits narrowed by `query`, each newest first. Every filter
+    /// is a condition on indexed fields rather than a pass over the whole history.
+    pub async fn get_filtered_history(&self, wallet_address: &str, query: &TransactionHistoryQuery) -> Result<(Vec<Payment>, Vec<DepositRecord>), ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let direction = query.direction.as_deref()
+            .map(str::parse::<TransactionDirection>)
+            .transpose()
+            .map_err(ApiError::ValidationError)?;
+        let state = query.status.as_deref()
+            .map(str::parse::<PaymentState>)
+            .transpose()
+            .map_err(ApiError::ValidationError)?;
+
+        let mut created_at = Document::new();
+        if let Some(from) = query.from {
+            created_at.insert("$gte", from);
+        }
+        if let Some(to) = query.to {
+            created_at.insert("$lt", to);
+        }
+
+        let mut payment_conditions = vec![match direction {
+            Some(TransactionDirection::Sent) => doc! { "customer_address": wallet_address },
+            Some(TransactionDirection::Received) => doc! { "vendor_address": wallet_address },
+            None => doc! { "$or": [{ "vendor_address": wallet_address }, { "customer_address": wallet_address }] },
+        }];
+        if !created_at.is_empty() {
+            payment_conditions.push(doc! { "created_at": created_at.clone() });
+        }
+        if let Some(token) = query.token.as_deref() {
+            payment_conditions.push(doc! { "computed_payment.symbol": token });
+        }
+        if let Some(state) = &state {
+            payment_conditions.push(payment_state_condition(state, now));
+        }
+        let options = mongodb::options::FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let payments: Vec<Payment> = self.transactions
+            .find(doc! { "$and": payment_conditions }, options.clone())
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        // Deposits are always received, and only exist once credited
+        let deposits_match = !matches!(direction, Some(TransactionDirection::Sent))
+            && state.as_ref().map_or(true, |state| *state == PaymentState::Completed);
+        let deposits: Vec<DepositRecord> = if deposits_match {
+            let mut filter = doc! { "wallet_address": wallet_address };
+            if !created_at.is_empty() {
+                filter.insert("created_at", created_at);
+            }
+            if let Some(token) = query.token.as_deref() {
+                filter.insert("token_symbol", token);
+            }
+            self.deposit_records
+                .find(filter, options)
+                .await
+                .map_err(ApiError::DatabaseError)?
+                .try_collect()
+                .await
+                .map_err(ApiError::DatabaseError)?
+        } else {
+            Vec::new()
+        };
+
+        Ok((payments, deposits))
+    }
+    
+    /// A wallet's saved contacts, by nickname
+    pub async fn get_contacts(&self, owner_address: &str) -> Result<Vec<Contact>, ApiError> {
+        let collation = mongodb::options::Collation::builder()
+            .locale("en")
+            .strength(mongodb::options::CollationStrength::Secondary)
+            .build();
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "nickname": 1 })
+            .collation(collation)
+            .build();
+
+        self.contacts
+            .find(doc! { "owner_address": owner_address }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Save a contact, or rename it if it's already saved
+    pub async fn save_contact(&self, owner_address: &str, contact_address: &str, nickname: &str) -> Result<Contact, ApiError> {
+        let filter = doc! { "owner_address": owner_address, "contact_address": contact_address };
+        let exists = self.contacts
+            .count_documents(filter.clone(), None)
+            .await
+            .map_err(ApiError::DatabaseError)? > 0;
+        if !exists {
+            let saved = self.contacts
+                .count_documents(doc! { "owner_address": owner_address }, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            if saved >= MAX_CONTACTS {
+                return Err(ApiError::ValidationError(format!("At most {} contacts can be saved", MAX_CONTACTS)));
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.contacts
+            .find_one_and_update(
+                filter,
+                doc! {
+                    "$set": { "nickname": nickname, "updated_at": now },
+                    "$setOnInsert": { "created_at": now },
+                },
+                options,
+            )
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("Contact was saved concurrently, please retry".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?
+            .ok_or_else(|| ApiError::InternalError("Contact upsert returned no document".to_string()))
+    }
+
+    /// Returns whether the contact was saved
+    pub async fn delete_contact(&self, owner_address: &str, contact_address: &str) -> Result<bool, ApiError> {
+        let result = self.contacts
+            .delete_one(doc! { "owner_address": owner_address, "contact_address": contact_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Look up several users at once; unknown addresses are simply missing from the result
+    pub async fn get_users_by_wallets(&self, wallet_addresses: &[String]) -> Result<Vec<User>, ApiError> {
+        if wallet_addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.users
+            .find(doc! { "wallet_address": { "$in": wallet_addresses } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn create_payment_request(&self, mut request: PaymentRequest) -> Result<PaymentRequest, ApiError> {
+        let result = self.payment_requests
+            .insert_one(&request, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        request.id = result.inserted_id.as_object_id();
+        Ok(request)
+    }
+
+    pub async fn get_payment_request(&self, id: &ObjectId) -> Result<Option<PaymentRequest>, ApiError> {
+        self.payment_requests
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Mark pending requests past their expiry as expired. Returns how many were.
+    pub async fn expire_payment_requests(&self, now: i64) -> Result<u64, ApiError> {
+        let result = self.payment_requests
+            .update_many(
+                doc! {
+                    "status": PaymentRequestStatus::Pending.to_string(),
+                    "expires_at": { "$lte": now },
+                },
+                doc! { "$set": { "status": PaymentRequestStatus::Expired.to_string() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count)
+    }
+
+    /// Requests a wallet received (or sent, if `incoming` is false), newest first
+    pub async fn get_payment_requests(&self, wallet_address: &str, incoming: bool, status: Option<PaymentRequestStatus>) -> Result<Vec<PaymentRequest>, ApiError> {
+        let mut filter = if incoming {
+            doc! { "payer_address": wallet_address }
+        } else {
+            doc! { "requester_address": wallet_address }
+        };
+        if let Some(status) = status {
+            filter.insert("status", status.to_string());
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(100)
+            .build();
+
+        self.payment_requests
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Move a request to `to` if it's still in one of the `from` states.
+    /// Returns the updated request, or None if it had already moved on.
+    pub async fn transition_payment_request(
+        &self,
+        id: &ObjectId,
+        from: &[PaymentRequestStatus],
+        to: PaymentRequestStatus,
+        payment_id: Option<&str>,
+    ) -> Result<Option<PaymentRequest>, ApiError> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        let mut set = doc! {
+            "status": to.to_string(),
+            "responded_at": chrono::Utc::now().timestamp(),
+        };
+        if let Some(payment_id) = payment_id {
+            set.insert("payment_id", payment_id);
+        }
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+
+        self.payment_requests
+            .find_one_and_update(
+                doc! { "_id": id, "status": { "$in": from } },
+                doc! { "$set": set },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Close the request a completed payment was made for
+    pub async fn mark_payment_request_paid(&self, request_id: &str, payment_id: &str) -> Result<(), ApiError> {
+        let id = ObjectId::parse_str(request_id)
+            .map_err(|_| ApiError::ValidationError(format!("Invalid payment request ID: {}", request_id)))?;
+        self.payment_requests
+            .update_one(
+                doc! { "_id": id, "payment_id": payment_id },
+                doc! { "$set": { "status": PaymentRequestStatus::Paid.to_string() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Create an account with its primary wallet linked
+    pub async fn create_account(&self, primary_wallet: &str) -> Result<Account, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let mut account = Account {
+            id: None,
+            primary_wallet: primary_wallet.to_string(),
+            wallets: vec![LinkedWallet { wallet_address: primary_wallet.to_string(), linked_at: now }],
+            created_at: now,
+        };
+        let result = self.accounts
+            .insert_one(&account, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("Wallet is already linked to an account".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        account.id = result.inserted_id.as_object_id();
+        Ok(account)
+    }
+
+    pub async fn get_account(&self, id: &ObjectId) -> Result<Option<Account>, ApiError> {
+        self.accounts
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// The account a wallet is linked to, if any
+    pub async fn get_account_by_wallet(&self, wallet_address: &str) -> Result<Option<Account>, ApiError> {
+        self.accounts
+            .find_one(doc! { "wallets.wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Add a wallet to an account, up to MAX_LINKED_WALLETS
+    pub async fn link_wallet(&self, id: &ObjectId, wallet_address: &str) -> Result<Account, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let full_key = format!("wallets.{}", MAX_LINKED_WALLETS - 1);
+        let mut filter = doc! { "_id": id, "wallets.wallet_address": { "$ne": wallet_address } };
+        filter.insert(full_key, doc! { "$exists": false });
+
+        self.accounts
+            .find_one_and_update(
+                filter,
+                doc! { "$push": { "wallets": {
+                    "wallet_address": wallet_address,
+                    "linked_at": chrono::Utc::now().timestamp(),
+                } } },
+                options,
+            )
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("Wallet is already linked to an account".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?
+            .ok_or_else(|| ApiError::Conflict(format!("Wallet is already linked, or the account has {} wallets", MAX_LINKED_WALLETS)))
+    }
+
+    /// Remove a wallet from an account. The primary wallet stays linked.
+    pub async fn unlink_wallet(&self, id: &ObjectId, wallet_address: &str) -> Result<Option<Account>, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.accounts
+            .find_one_and_update(
+                doc! {
+                    "_id": id,
+                    "primary_wallet": { "$ne": wallet_address },
+                    "wallets.wallet_address": wallet_address,
+                },
+                doc! { "$pull": { "wallets": { "wallet_address": wallet_address } } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Whether two wallets are linked to the same account
+    pub async fn wallets_share_account(&self, wallet_a: &str, wallet_b: &str) -> Result<bool, ApiError> {
+        let count = self.accounts
+            .count_documents(doc! { "wallets.wallet_address": { "$all": [wallet_a, wallet_b] } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(count > 0)
+    }
+    
+    /// Register a device's push token for a wallet, or refresh it
+    pub async fn register_device_token(&self, wallet_address: &str, token: &str, platform: DevicePlatform) -> Result<DeviceToken, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let platform = bson::to_bson(&platform)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize platform: {}", e)))?;
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.device_tokens
+            .find_one_and_update(
+                doc! { "token": token },
+                doc! {
+                    "$set": { "wallet_address": wallet_address, "platform": platform, "updated_at": now },
+                    "$setOnInsert": { "created_at": now },
+                },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .ok_or_else(|| ApiError::InternalError("Device token upsert returned no document".to_string()))
+    }
+
+    /// Returns whether the token was registered to this wallet
+    pub async fn remove_device_token(&self, wallet_address: &str, token: &str) -> Result<bool, ApiError> {
+        let result = self.device_tokens
+            .delete_one(doc! { "wallet_address": wallet_address, "token": token }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Drop a token the push provider reported as no longer valid
+    pub async fn delete_device_token(&self, token: &str) -> Result<(), ApiError> {
+        self.device_tokens
+            .delete_one(doc! { "token": token }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_device_tokens(&self, wallet_address: &str) -> Result<Vec<DeviceToken>, ApiError> {
+        self.device_tokens
+            .find(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn queue_push_notification(&self, notification: &PushNotification) -> Result<(), ApiError> {
+        self.push_notifications
+            .insert_one(notification, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Lease up to `limit` of the oldest queued notifications no other replica holds, until
+    /// `lease_expires_at`, and return them. A lease that runs out puts its notifications back
+    /// in the queue, so a replica that stops mid-batch doesn't lose them.
+    pub async fn lease_push_notifications(&self, lease: &str, lease_expires_at: i64, limit: i64) -> Result<Vec<PushNotification>, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "queued_at": 1 })
+            .limit(limit)
+            .projection(doc! { "_id": 1 })
+            .build();
+        let ids: Vec<ObjectId> = self.push_notifications.clone_with_type::<Document>()
+            .find(doc! { "lease_expires_at": { "$lt": now } }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .iter()
+            .filter_map(|notification| notification.get_object_id("_id").ok())
+            .collect();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Another replica may lease some of them first; each goes to one lease
+        self.push_notifications
+            .update_many(
+                doc! { "_id": { "$in": ids }, "lease_expires_at": { "$lt": now } },
+                doc! { "$set": { "lease": lease, "lease_expires_at": lease_expires_at } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        self.push_notifications
+            .find(doc! { "lease": lease }, mongodb::options::FindOptions::builder().sort(doc! { "queued_at": 1 }).build())
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Drop the notifications of a lease once they've been delivered
+    pub async fn delete_push_notifications(&self, lease: &str) -> Result<(), ApiError> {
+        self.push_notifications
+            .delete_many(doc! { "lease": lease }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Collect every record stored for a wallet (GDPR data export)
+    pub async fn export_user_data(&self, wallet_address: &str) -> Result<UserDataExport, ApiError> {
+        let user = self.get_user_by_wallet(wallet_address).await?;
+        let payments = self.get_user_transaction_history(wallet_address).await?;
+        let deposits = self.get_user_deposits(wallet_address).await?;
+        let vendor = self.partnered_vendors
+            .find_one(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let causes: Vec<Cause> = self.causes
+            .find(doc! { "owner_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let cause_drafts: Vec<CauseDraft> = self.cause_drafts
+            .find(doc! { "owner_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let cause_teams: Vec<TeamMembership> = self.get_team_causes(wallet_address).await?
+            .into_iter()
+            .flat_map(|cause| {
+                let cause_id = cause.id.map(|id| id.to_hex()).unwrap_or_default();
+                let cause_name = cause.name;
+                cause.team.into_iter()
+                    .filter(|member| member.wallet_address.as_deref() == Some(wallet_address))
+                    .map(move |member| TeamMembership { cause_id: cause_id.clone(), cause_name: cause_name.clone(), member })
+            })
+            .collect();
+        
+        let contacts = self.get_contacts(wallet_address).await?;
+        let account = self.get_account_by_wallet(wallet_address).await?;
+        let reviews: Vec<Review> = self.reviews
+            .find(doc! { "customer_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let loyalty_accounts = self.get_customer_loyalty_accounts(wallet_address).await?;
+        let disputes: Vec<Dispute> = self.disputes
+            .find(doc! { "customer_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let payment_schedules: Vec<PaymentSchedule> = self.payment_schedules
+            .find(doc! { "customer_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let invoices: Vec<Invoice> = self.invoices
+            .find(doc! { "$or": [{ "customer_address": wallet_address }, { "vendor_address": wallet_address }] }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let preference_templates = self.get_preference_templates(wallet_address).await?;
+        let preference_changes = self.get_preference_changes(wallet_address).await?;
+        let payment_requests: Vec<PaymentRequest> = self.payment_requests
+            .find(doc! { "$or": [{ "requester_address": wallet_address }, { "payer_address": wallet_address }] }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let activities: Vec<ActivityEvent> = self.activities
+            .find(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        Ok(UserDataExport {
+            exported_at: chrono::Utc::now().timestamp(),
+            wallet_address: wallet_address.to_string(),
+            preferences: user.as_ref().map(|u| u.preferences.0.clone()),
+            user,
+            payments,
+            deposits,
+            vendor,
+            causes,
+            cause_drafts,
+            cause_teams,
+            contacts,
+            account,
+            reviews,
+            loyalty_accounts,
+            disputes,
+            payment_schedules,
+            invoices,
+            preference_templates,
+            preference_changes,
+            payment_requests,
+            activities,
+        })
+    }
+
+    /// Remove personal data for a wallet (GDPR erasure).
+    /// Usernames, vendor profile and contact emails are scrubbed and saved contacts deleted, but payments and
+    /// deposits keep their addresses and amounts so balances and history still reconcile.
+    pub async fn anonymize_user(&self, wallet_address: &str) -> Result<AnonymizationSummary, ApiError> {
+        const DELETED_NAME: &str = "Deleted user";
+        const DELETED_EMAIL: &str = "deleted@anonymized.invalid";
+        let now = chrono::Utc::now().timestamp();
+        
+        let user_result = self.users
+            .update_one(
+                doc! { "wallet_address": wallet_address },
+                doc! {
+                    "$set": {
+                        "username": DELETED_NAME,
+                        "preferences": {},
+                        "deleted_at": now,
+                    },
+                    "$unset": {
+                        "username_key": "",
+                        "display_name": "",
+                        "avatar_url": "",
+                        "email": "",
+                        "spending_weights": "",
+                        "payment_code_namespace": "",
+                    },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let customer_payments = self.transactions
+            .update_many(
+                doc! { "customer_address": wallet_address },
+                doc! { "$set": { "customer_username": DELETED_NAME } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let vendor_payments = self.transactions
+            .update_many(
+                doc! { "vendor_address": wallet_address },
+                doc! { "$set": { "vendor_name": DELETED_NAME } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let vendor_result = self.partnered_vendors
+            .update_one(
+                doc! { "wallet_address": wallet_address },
+                doc! { "$set": {
+                    "name": DELETED_NAME,
+                    "description": null,
+                    "google_maps_link": null,
+                    "website_link": null,
+                }, "$unset": {
+                    "business_name": "",
+                    "category": "",
+                    "location": "",
+                    "hours": "",
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let causes_result = self.causes
+            .update_many(
+                doc! { "owner_address": wallet_address },
+                doc! { "$set": { "creator_email": DELETED_EMAIL } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let drafts_result = self.cause_drafts
+            .update_many(
+                doc! { "owner_address": wallet_address },
+                doc! { "$set": { "creator_email": DELETED_EMAIL } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // A deleted wallet leaves the teams it joined, taking its invite email with it
+        let teams_result = self.causes
+            .update_many(
+                doc! { "team.wallet_address": wallet_address },
+                doc! { "$pull": { "team": { "wallet_address": wallet_address } } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let contacts_result = self.contacts
+            .delete_many(doc! { "owner_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        self.device_tokens
+            .delete_many(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Ratings still count towards the vendor; the reviewer and their words don't stay
+        let reviews_result = self.reviews
+            .update_many(
+                doc! { "customer_address": wallet_address },
+                doc! { "$set": { "customer_username": null, "comment": null } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let loyalty_result = self.loyalty_accounts
+            .delete_many(doc! { "customer_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Disputes stay on record with the payment; the customer's own account of it doesn't
+        let disputes_result = self.disputes
+            .update_many(
+                doc! { "customer_address": wallet_address },
+                doc! { "$set": { "details": null } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // No more payment codes for or to a deleted user; notes were the customer's own words
+        let schedules_result = self.payment_schedules
+            .update_many(
+                doc! {
+                    "$or": [{ "customer_address": wallet_address }, { "vendor_address": wallet_address }],
+                    "status": { "$in": [ScheduleStatus::Active.to_string(), ScheduleStatus::Paused.to_string()] },
+                },
+                doc! { "$set": { "status": ScheduleStatus::Cancelled.to_string(), "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        self.payment_schedules
+            .update_many(doc! { "customer_address": wallet_address }, doc! { "$set": { "note": null } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Open invoices from or to a deleted user can't be paid any more
+        let invoices_result = self.invoices
+            .update_many(
+                doc! {
+                    "$or": [{ "customer_address": wallet_address }, { "vendor_address": wallet_address }],
+                    "status": InvoiceStatus::Open.to_string(),
+                },
+                doc! { "$set": { "status": InvoiceStatus::Cancelled.to_string(), "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Requests keep their amounts; the requester's name and the notes between the two don't stay
+        let requests_result = self.payment_requests
+            .update_many(
+                doc! { "requester_address": wallet_address },
+                doc! { "$set": { "requester_username": DELETED_NAME, "note": null } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let payer_requests_result = self.payment_requests
+            .update_many(doc! { "payer_address": wallet_address }, doc! { "$set": { "note": null } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Preferences were cleared above, so their templates and history go too
+        let templates_result = self.preference_templates
+            .delete_many(doc! { "owner_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        self.preference_changes
+            .delete_many(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        self.preference_ledger
+            .delete_many(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        // Unlink the wallet; an account it created goes away with it
+        let accounts_deleted = self.accounts
+            .delete_many(doc! { "primary_wallet": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let accounts_unlinked = self.accounts
+            .update_many(
+                doc! { "wallets.wallet_address": wallet_address },
+                doc! { "$pull": { "wallets": { "wallet_address": wallet_address } } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        Ok(AnonymizationSummary {
+            wallet_address: wallet_address.to_string(),
+            user_anonymized: user_result.matched_count > 0,
+            payments_anonymized: customer_payments.modified_count + vendor_payments.modified_count,
+            vendor_anonymized: vendor_result.matched_count > 0,
+            causes_anonymized: causes_result.modified_count,
+            drafts_anonymized: drafts_result.modified_count,
+            team_memberships_removed: teams_result.modified_count,
+            contacts_deleted: contacts_result.deleted_count,
+            account_unlinked: accounts_deleted.deleted_count + accounts_unlinked.modified_count > 0,
+            reviews_anonymized: reviews_result.modified_count,
+            loyalty_accounts_deleted: loyalty_result.deleted_count,
+            disputes_anonymized: disputes_result.modified_count,
+            payment_schedules_cancelled: schedules_result.modified_count,
+            invoices_cancelled: invoices_result.modified_count,
+            preference_templates_deleted: templates_result.deleted_count,
+            payment_requests_anonymized: requests_result.modified_count + payer_requests_result.modified_count,
+        })
+    }
+    
+    /// List a vendor's payments newest first, with keyset pagination so the POS
+    /// can page through its order queue while new payments keep arriving
+    pub async fn get_vendor_payments(&self, vendor_address: &str, query: &VendorPaymentsQuery) -> Result<VendorPaymentsPage, ApiError> {
+        let limit = query.limit.unwrap_or(50).clamp(1, 200);
+        let now = chrono::Utc::now().timestamp();
+
+        let mut conditions = vec![doc! { "vendor_address": vendor_address }];
+
+        if let Some(status) = &query.status {
+            let state = status.parse::<PaymentState>().map_err(ApiError::ValidationError)?;
+            conditions.push(payment_state_condition(&state, now));
+        }
+        if let Some(from) = query.from {
+            conditions.push(doc! { "created_at": { "$gte": from } });
+        }
+        if let Some(to) = query.to {
+            conditions.push(doc! { "created_at": { "$lt": to } });
+        }
+        if let Some(cursor) = &query.cursor {
+            let (created_at, payment_id) = decode_cursor(cursor).map_err(ApiError::ValidationError)?;
+            conditions.push(doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, "payment_id": { "$lt": payment_id } }
+                ]
+            });
+        }
+
+        // Fetch one extra to know whether there is another page
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, "payment_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let mut payments: Vec<Payment> = self.transactions
+            .find(doc! { "$and": conditions }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let next_cursor = if payments.len() as i64 > limit {
+            payments.truncate(limit as usize);
+            payments.last().map(|p| encode_cursor(p.created_at, &p.payment_id))
+        } else {
+            None
+        };
+
+        let payments = payments
+            .into_iter()
+            .map(|payment| VendorPaymentItem { state: payment.state(now), payment })
+            .collect();
+
+        Ok(VendorPaymentsPage { payments, next_cursor })
+    }
+    
+    /// Donations to a cause token, newest first. Manual credits are not donations and
+    /// are left out; donors who opted out of attribution or were erased show as anonymous.
+    pub async fn get_cause_donations(&self, token_symbol: &str, query: &CauseDonationsQuery) -> Result<CauseDonationsPage, ApiError> {
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+        let mut conditions = vec![
+            doc! { "token_symbol": token_symbol },
+            doc! { "manual_credit": { "$exists": false } },
+        ];
+        if let Some(cursor) = &query.cursor {
+            let (created_at, id) = decode_cursor(cursor).map_err(ApiError::ValidationError)?;
+            let id = ObjectId::parse_str(&id).map_err(|_| ApiError::ValidationError("Invalid cursor".to_string()))?;
+            conditions.push(doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, "_id": { "$lt": id } }
+                ]
+            });
+        }
+
+        // Fetch one extra to know whether there is another page
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let mut deposits: Vec<DepositRecord> = self.deposit_records
+            .find(doc! { "$and": conditions }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let next_cursor = if deposits.len() as i64 > limit {
+            deposits.truncate(limit as usize);
+            deposits.last().and_then(|d| d.id.map(|id| encode_cursor(d.created_at, &id.to_hex())))
+        } else {
+            None
+        };
+
+        let mut wallets: Vec<&str> = deposits.iter().map(|d| d.wallet_address.as_str()).collect();
+        wallets.sort_unstable();
+        wallets.dedup();
+        let donors: HashMap<String, String> = self.users
+            .find(doc! {
+                "wallet_address": { "$in": wallets },
+                "donate_anonymously": { "$ne": true },
+                "deleted_at": { "$exists": false },
+            }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect::<Vec<User>>()
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .into_iter()
+            .map(|user| (user.wallet_address, user.username))
+            .collect();
+
+        let donations = deposits
+            .into_iter()
+            .map(|deposit| CauseDonation {
+                amount_usd: deposit.amount_deposited_usd,
+                tokens_received: deposit.amount_tokens_received,
+                created_at: deposit.created_at,
+                donor_username: donors.get(&deposit.wallet_address).cloned(),
+            })
+            .collect();
+
+        Ok(CauseDonationsPage { donations, next_cursor })
+    }
+    
+    /// A cause token's donations grouped by referrer, largest first. Manual credits aren't donations.
+    pub async fn get_referrer_totals(&self, token_symbol: &str) -> Result<Vec<ReferrerTotals>, ApiError> {
+        let pipeline = vec![
+            doc! { "$match": { "token_symbol": token_symbol, "manual_credit": { "$exists": false } } },
+            doc! { "$group": {
+                "_id": "$referrer",  // missing for direct donations
+                "donations": { "$sum": 1 },
+                "amount_usd": { "$sum": "$amount_deposited_usd" },
+                "tokens": { "$sum": "$amount_tokens_received" },
+            } },
+            doc! { "$sort": { "amount_usd": -1 } },
+        ];
+        let groups: Vec<Document> = self.deposit_records
+            .aggregate(pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(groups.iter().map(|group| ReferrerTotals {
+            referrer: group.get_str("_id").ok().map(str::to_string),
+            donations: number(group, "donations") as i64,
+            amount_usd: number(group, "amount_usd"),
+            tokens: number(group, "tokens"),
+        }).collect())
+    }
+
+    pub async fn set_donate_anonymously(&self, wallet_address: &str, donate_anonymously: bool) -> Result<(), ApiError> {
+        let result = self.users
+            .update_one(
+                doc! { "wallet_address": wallet_address },
+                doc! { "$set": { "donate_anonymously": donate_anonymously } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if result.matched_count == 0 {
+            return Err(ApiError::NotFound(format!("User not found: {}", wallet_address)));
+        }
+        Ok(())
+    }
+    
+    /// Add submitted balance changes to the holdings projection
+    pub async fn apply_holding_deltas(&self, deltas: &[HoldingDelta]) -> Result<(), ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        for delta in deltas {
+            self.holdings
+                .update_one(
+                    doc! { "wallet_address": &delta.wallet_address, "token_id": &delta.token_id },
+                    doc! { "$inc": { "balance": delta.amount }, "$set": { "updated_at": now } },
+                    options.clone(),
+                )
+                .await
+                .map_err(ApiError::DatabaseError)?;
+        }
+        Ok(())
+    }
+    
+    /// Projected balances of a wallet, keyed by token ID
+    pub async fn get_wallet_holdings(&self, wallet_address: &str) -> Result<HashMap<String, i64>, ApiError> {
+        let holdings: Vec<Holding> = self.holdings
+            .find(doc! { "wallet_address": wallet_address, "balance": { "$gt": 0 } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(holdings.into_iter().map(|holding| (holding.token_id, holding.balance)).collect())
+    }
+    
+    /// Every wallet with a positive projected balance of a token, largest first
+    pub async fn get_token_holdings(&self, token_id: &str) -> Result<Vec<Holding>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "balance": -1 })
+            .build();
+        self.holdings
+            .find(doc! { "token_id": token_id, "balance": { "$gt": 0 } }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Set a wallet's projection to the balances its vault actually holds, returning how
+    /// many of its tokens were off
+    pub async fn replace_wallet_holdings(&self, wallet_address: &str, actual: &HashMap<String, u64>) -> Result<usize, ApiError> {
+        let projected: Vec<Holding> = self.holdings
+            .find(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let projected: HashMap<String, i64> = projected.into_iter()
+            .map(|holding| (holding.token_id, holding.balance))
+            .collect();
+        
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        let mut corrected = 0;
+        for (token_id, balance) in actual {
+            if projected.get(token_id) != Some(&(*balance as i64)) {
+                corrected += 1;
+            }
+            self.holdings
+                .update_one(
+                    doc! { "wallet_address": wallet_address, "token_id": token_id },
+                    doc! { "$set": { "balance": *balance as i64, "updated_at": now, "reconciled_at": now } },
+                    options.clone(),
+                )
+                .await
+                .map_err(ApiError::DatabaseError)?;
+        }
+        let gone: Vec<String> = projected.keys().filter(|token_id| !actual.contains_key(*token_id)).cloned().collect();
+        corrected += gone.iter().filter(|token_id| projected[*token_id] != 0).count();
+        if !gone.is_empty() {
+            self.holdings
+                .delete_many(doc! { "wallet_address": wallet_address, "token_id": { "$in": gone } }, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+        }
+        Ok(corrected)
+    }
+    
+    pub async fn get_feature_flags(&self, environment: &str) -> Result<Vec<FeatureFlag>, ApiError> {
+        self.feature_flags
+            .find(doc! { "environment": environment }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn set_feature_flag(&self, name: &str, environment: &str, enabled: bool, updated_by: &str) -> Result<FeatureFlag, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.feature_flags
+            .find_one_and_update(
+                doc! { "name": name, "environment": environment },
+                doc! { "$set": { "enabled": enabled, "updated_by": updated_by, "updated_at": chrono::Utc::now().timestamp() } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .ok_or_else(|| ApiError::InternalError(format!("Feature flag {} was not saved", name)))
+    }
+    
+    /// Take or renew the scheduler lease for `holder` until `expires_at`, returning its
+    /// epoch to fence job runs with. Renewing keeps the epoch and taking the lease bumps it,
+    /// so a paused former leader's runs are refused once another replica has taken over.
+    /// None while another replica holds an unexpired lease.
+    pub async fn acquire_scheduler_lease(&self, holder: &str, expires_at: i64) -> Result<Option<i64>, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let renewed = self.scheduler_leases
+            .find_one_and_update(
+                doc! { "_id": "scheduler", "holder": holder, "expires_at": { "$gt": now } },
+                doc! { "$set": { "expires_at": expires_at } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if let Some(lease) = renewed {
+            return Ok(Some(lease.epoch));
+        }
+
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let taken = self.scheduler_leases
+            .find_one_and_update(
+                doc! { "_id": "scheduler", "expires_at": { "$lte": now } },
+                doc! { "$set": { "holder": holder, "expires_at": expires_at }, "$inc": { "epoch": 1_i64 } },
+                options,
+            )
+            .await;
+        match taken {
+            Ok(lease) => Ok(lease.map(|lease| lease.epoch)),
+            // Held by someone else, so the filter missed and the upsert hit the existing _id
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(None),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+    
+    pub async fn get_scheduler_lease(&self) -> Result<Option<SchedulerLease>, ApiError> {
+        self.scheduler_leases
+            .find_one(doc! { "_id": "scheduler" }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Record a job's schedule, keeping its run history
+    pub async fn register_scheduled_job(&self, name: &str, schedule: &str, next_run_at: Option<i64>) -> Result<(), ApiError> {
+        self.scheduled_jobs
+            .update_one(
+                doc! { "_id": name },
+                doc! {
+                    "$set": { "schedule": schedule, "next_run_at": next_run_at },
+                    "$setOnInsert": { "running": false, "runs": 0_i64, "failures": 0_i64 },
+                },
+                mongodb::options::UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Record a job run starting. With a `fence` (the lease epoch the run started under),
+    /// the run is refused unless `instance_id` still holds the lease at that epoch and no
+    /// run has started under a later one; returns false when refused.
+    pub async fn start_scheduled_job_run(&self, name: &str, schedule: &str, started_at: i64, instance_id: &str, fence: Option<i64>) -> Result<bool, ApiError> {
+        let mut filter = doc! { "_id": name };
+        if let Some(fence) = fence {
+            let held = self.scheduler_leases
+                .count_documents(doc! { "_id": "scheduler", "holder": instance_id, "epoch": fence, "expires_at": { "$gt": started_at } }, None)
+                .await
+                .map_err(ApiError::DatabaseError)? > 0;
+            if !held {
+                return Ok(false);
+            }
+            filter.insert("$or", vec![doc! { "fence": null }, doc! { "fence": { "$lte": fence } }]);
+        }
+        let result = self.scheduled_jobs
+            .update_one(
+                filter,
+                doc! {
+                    "$set": { "schedule": schedule, "running": true, "last_started_at": started_at, "last_run_by": instance_id, "fence": fence },
+                    "$setOnInsert": { "runs": 0_i64, "failures": 0_i64 },
+                },
+                mongodb::options::UpdateOptions::builder().upsert(true).build(),
+            )
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            // Started under a later lease, so the filter missed and the upsert hit the existing _id
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+    
+    /// Record a job run finishing, unless a run under a later lease has started since
+    pub async fn finish_scheduled_job_run(&self, name: &str, duration_ms: i64, error: Option<&str>, next_run_at: Option<i64>, fence: Option<i64>) -> Result<(), ApiError> {
+        let status = if error.is_some() { JobRunStatus::Failed } else { JobRunStatus::Succeeded };
+        let mut filter = doc! { "_id": name };
+        if let Some(fence) = fence {
+            filter.insert("fence", fence);
+        }
+        self.scheduled_jobs
+            .update_one(
+                filter,
+                doc! {
+                    "$set": {
+                        "running": false,
+                        "last_finished_at": chrono::Utc::now().timestamp(),
+                        "last_duration_ms": duration_ms,
+                        "last_status": bson::to_bson(&status).map_err(|e| ApiError::InternalError(e.to_string()))?,
+                        "last_error": error,
+                        "next_run_at": next_run_at,
+                    },
+                    "$inc": { "runs": 1_i64, "failures": if error.is_some() { 1_i64 } else { 0_i64 } },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    pub async fn get_scheduled_jobs(&self) -> Result<Vec<ScheduledJob>, ApiError> {
+        self.scheduled_jobs
+            .find(doc! {}, mongodb::options::FindOptions::builder().sort(doc! { "_id": 1 }).build())
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn create_job(&self, job: &Job) -> Result<(), ApiError> {
+        self.jobs
+            .insert_one(job, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    pub async fn get_job(&self, job_id: &str) -> Result<Option<Job>, ApiError> {
+        self.jobs
+            .find_one(doc! { "job_id": job_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn set_job_progress(&self, job_id: &str, completed: i64, total: i64) -> Result<(), ApiError> {
+        self.jobs
+            .update_one(
+                doc! { "job_id": job_id },
+                doc! { "$set": { "completed": completed, "total": total, "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Mark a running job as still alive. Returns false once it's no longer running.
+    pub async fn touch_job(&self, job_id: &str) -> Result<bool, ApiError> {
+        let result = self.jobs
+            .update_one(
+                doc! { "job_id": job_id, "status": bson::to_bson(&JobStatus::Running).map_err(|e| ApiError::InternalError(e.to_string()))? },
+                doc! { "$set": { "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count > 0)
+    }
+    
+    /// Fail running jobs that haven't updated since `before`, whose process must have stopped
+    pub async fn fail_stale_jobs(&self, before: i64, expires_at: bson::DateTime) -> Result<u64, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let result = self.jobs
+            .update_many(
+                doc! {
+                    "status": bson::to_bson(&JobStatus::Running).map_err(|e| ApiError::InternalError(e.to_string()))?,
+                    "updated_at": { "$lt": before },
+                },
+                doc! { "$set": {
+                    "status": bson::to_bson(&JobStatus::Failed).map_err(|e| ApiError::InternalError(e.to_string()))?,
+                    "error": "Interrupted before it finished; start it again",
+                    "updated_at": now,
+                    "finished_at": now,
+                    "expires_at": expires_at,
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count)
+    }
+    
+    /// Record a job's result, or why it failed. The job is removed at `expires_at`.
+    pub async fn finish_job(&self, job_id: &str, outcome: Result<serde_json::Value, String>, expires_at: bson::DateTime) -> Result<(), ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let (status, result, error) = match outcome {
+            Ok(result) => (JobStatus::Succeeded, Some(result), None),
+            Err(error) => (JobStatus::Failed, None, Some(error)),
+        };
+        self.jobs
+            .update_one(
+                doc! { "job_id": job_id },
+                doc! { "$set": {
+                    "status": bson::to_bson(&status).map_err(|e| ApiError::InternalError(e.to_string()))?,
+                    "result": bson::to_bson(&result).map_err(|e| ApiError::InternalError(e.to_string()))?,
+                    "error": error,
+                    "updated_at": now,
+                    "finished_at": now,
+                    "expires_at": expires_at,
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    pub async fn create_embed_token(&self, token: &EmbedToken) -> Result<(), ApiError> {
+        self.embed_tokens
+            .insert_one(token, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// An embed token that hasn't been revoked
+    pub async fn get_embed_token(&self, token: &str) -> Result<Option<EmbedToken>, ApiError> {
+        self.embed_tokens
+            .find_one(doc! { "token": token, "revoked_at": null }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// A cause's live embed tokens, newest first
+    pub async fn get_embed_tokens(&self, cause_id: &str) -> Result<Vec<EmbedToken>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        self.embed_tokens
+            .find(doc! { "cause_id": cause_id, "revoked_at": null }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Returns false if the cause has no such live token
+    pub async fn revoke_embed_token(&self, cause_id: &str, id: &ObjectId) -> Result<bool, ApiError> {
+        let result = self.embed_tokens
+            .update_one(
+                doc! { "_id": id, "cause_id": cause_id, "revoked_at": null },
+                doc! { "$set": { "revoked_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count == 1)
+    }
+    
+    pub async fn create_campaign(&self, campaign: &Campaign) -> Result<ObjectId, ApiError> {
+        let result = self.campaigns
+            .insert_one(campaign, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::DuplicateError(format!("This cause already has a campaign named {}", campaign.name))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| ApiError::InternalError("Campaign was inserted without an ID".to_string()))
+    }
+    
+    pub async fn get_campaign(&self, id: &ObjectId) -> Result<Option<Campaign>, ApiError> {
+        self.campaigns
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// A cause's campaigns, newest first
+    pub async fn get_campaigns(&self, cause_id: &str) -> Result<Vec<Campaign>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        self.campaigns
+            .find(doc! { "cause_id": cause_id }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn count_active_campaigns(&self, cause_id: &str) -> Result<u64, ApiError> {
+        self.campaigns
+            .count_documents(doc! { "cause_id": cause_id, "status": CampaignStatus::Active.to_string() }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn replace_campaign(&self, campaign: &Campaign) -> Result<(), ApiError> {
+        let id = campaign.id.ok_or_else(|| ApiError::InternalError("Campaign has no ID".to_string()))?;
+        self.campaigns
+            .replace_one(doc! { "_id": id }, campaign, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::DuplicateError(format!("This cause already has a campaign named {}", campaign.name))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        Ok(())
+    }
+    
+    pub async fn delete_campaign(&self, id: &ObjectId) -> Result<(), ApiError> {
+        self.campaigns
+            .delete_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Donations attributed to a campaign or fundraiser page, by `field` ("campaign_id" or
+    /// "fundraiser_id"): (count, distinct donors, cents, tokens)
+    pub async fn get_attributed_totals(&self, field: &str, id: &str) -> Result<(i64, i64, i64, f64), ApiError> {
+        let pipeline = vec![
+            doc! { "$match": { field: id, "manual_credit": { "$exists": false } } },
+            doc! { "$group": {
+                "_id": null,
+                "donations": { "$sum": 1 },
+                "donors": { "$addToSet": "$wallet_address" },
+                "amount_usd": { "$sum": "$amount_deposited_usd" },
+                "tokens": { "$sum": "$amount_tokens_received" },
+            } },
+        ];
+        let totals: Vec<Document> = self.deposit_records
+            .aggregate(pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(totals.first().map_or((0, 0, 0, 0.0), |totals| (
+            number(totals, "donations") as i64,
+            totals.get_array("donors").map_or(0, |donors| donors.len() as i64),
+            (number(totals, "amount_usd") * 100.0).round() as i64,
+            number(totals, "tokens"),
+        )))
+    }
+    
+    pub async fn create_fundraiser(&self, page: &FundraiserPage) -> Result<ObjectId, ApiError> {
+        let result = self.fundraisers
+            .insert_one(page, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::DuplicateError(format!("The slug {} is already taken", page.slug))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| ApiError::InternalError("Fundraiser was inserted without an ID".to_string()))
+    }
+    
+    pub async fn get_fundraiser(&self, id: &ObjectId) -> Result<Option<FundraiserPage>, ApiError> {
+        self.fundraisers
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn get_fundraiser_by_slug(&self, slug: &str) -> Result<Option<FundraiserPage>, ApiError> {
+        self.fundraisers
+            .find_one(doc! { "slug": slug }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn count_active_fundraisers(&self, owner_address: &str) -> Result<u64, ApiError> {
+        self.fundraisers
+            .count_documents(doc! { "owner_address": owner_address, "status": FundraiserStatus::Active.to_string() }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn replace_fundraiser(&self, page: &FundraiserPage) -> Result<(), ApiError> {
+        let id = page.id.ok_or_else(|| ApiError::InternalError("Fundraiser has no ID".to_string()))?;
+        self.fundraisers
+            .replace_one(doc! { "_id": id }, page, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// A cause's fundraiser pages ranked by what they've raised, most first. Pages nobody has
+    /// donated through yet aren't ranked.
+    pub async fn get_fundraiser_leaderboard(&self, token_symbol: &str, limit: i64) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        let pipeline = vec![
+            doc! { "$match": {
+                "token_symbol": token_symbol,
+                "fundraiser_id": { "$exists": true },
+                "manual_credit": { "$exists": false },
+            } },
+            doc! { "$group": {
+                "_id": "$fundraiser_id",
+                "donations": { "$sum": 1 },
+                "amount_usd": { "$sum": "$amount_deposited_usd" },
+            } },
+            doc! { "$sort": { "amount_usd": -1, "_id": 1 } },
+            doc! { "$limit": limit },
+        ];
+        let groups: Vec<Document> = self.deposit_records
+            .aggregate(pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let ids: Vec<ObjectId> = groups.iter()
+            .filter_map(|group| group.get_str("_id").ok())
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let pages: HashMap<String, FundraiserPage> = self.fundraisers
+            .find(doc! { "_id": { "$in": ids } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .into_iter()
+            .filter_map(|page| page.id.map(|id| (id.to_hex(), page)))
+            .collect();
+
+        Ok(groups.iter()
+            .filter_map(|group| pages.get(group.get_str("_id").ok()?).map(|page| (group, page)))
+            .enumerate()
+            .map(|(index, (group, page))| LeaderboardEntry {
+                rank: index + 1,
+                slug: page.slug.clone(),
+                title: page.title.clone(),
+                owner_address: page.owner_address.clone(),
+                raised_cents: (number(group, "amount_usd") * 100.0).round() as i64,
+                donations: number(group, "donations") as i64,
+                goal_cents: page.goal_cents,
+            })
+            .collect())
+    }
+    
+    /// Returns false if the event was already recorded
+    pub async fn record_payout_event(&self, event: &PayoutEvent) -> Result<bool, ApiError> {
+        match self.payout_events.insert_one(event, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+    
+    /// Returns false if the cause already has a payout with this idempotency key
+    pub async fn create_cause_payout(&self, payout: &CausePayout) -> Result<bool, ApiError> {
+        match self.cause_payouts.insert_one(payout, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+    
+    pub async fn get_cause_payout(&self, cause_id: &str, idempotency_key: &str) -> Result<Option<CausePayout>, ApiError> {
+        self.cause_payouts
+            .find_one(doc! { "cause_id": cause_id, "idempotency_key": idempotency_key }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Forget a payout request that was refused before Stripe was asked, so its key can be reused
+    pub async fn delete_cause_payout(&self, cause_id: &str, idempotency_key: &str) -> Result<(), ApiError> {
+        self.cause_payouts
+            .delete_one(doc! { "cause_id": cause_id, "idempotency_key": idempotency_key }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Record Stripe's answer to a payout request: the payout it created, or why it refused
+    pub async fn set_cause_payout_result(&self, cause_id: &str, idempotency_key: &str, payout_id: Option<&str>, failure_reason: Option<&str>) -> Result<(), ApiError> {
+        self.cause_payouts
+            .update_one(
+                doc! { "cause_id": cause_id, "idempotency_key": idempotency_key },
+                doc! { "$set": { "payout_id": payout_id, "failure_reason": failure_reason } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Count `amount_cents` against what the cause raised, if that much of it hasn't been paid
+    /// out yet. Returns false otherwise.
+    pub async fn reserve_cause_payout(&self, cause_id: &ObjectId, amount_cents: i64) -> Result<bool, ApiError> {
+        let result = self.causes
+            .update_one(
+                doc! {
+                    "_id": cause_id,
+                    "$expr": { "$lte": [
+                        { "$add": [{ "$ifNull": ["$paid_out_cents", 0] }, amount_cents] },
+                        { "$round": [{ "$multiply": ["$amount_donated", 100] }, 0] },
+                    ] },
+                },
+                doc! {
+                    "$inc": { "paid_out_cents": amount_cents },
+                    "$set": { "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+    
+    /// Give back an amount reserved by `reserve_cause_payout` that Stripe didn't pay out
+    pub async fn release_cause_payout(&self, cause_id: &ObjectId, amount_cents: i64) -> Result<(), ApiError> {
+        self.causes
+            .update_one(
+                doc! { "_id": cause_id },
+                doc! {
+                    "$inc": { "paid_out_cents": -amount_cents },
+                    "$set": { "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// A cause's payout events, newest first
+    pub async fn get_payout_events(&self, cause_id: &str, limit: i64) -> Result<Vec<PayoutEvent>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+        self.payout_events
+            .find(doc! { "cause_ids": cause_id }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn get_causes_by_stripe_account(&self, stripe_account_id: &str) -> Result<Vec<Cause>, ApiError> {
+        self.causes
+            .find(doc! { "stripe_account_id": stripe_account_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Add an invited member unless the email is already on the team or the team is full.
+    /// Returns false if the email is already on the team.
+    pub async fn add_team_member(&self, cause_id: &ObjectId, member: &TeamMember) -> Result<bool, ApiError> {
+        let member_doc = bson::to_bson(member)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize team member: {}", e)))?;
+        let last_member_slot = format!("team.{}", MAX_TEAM_MEMBERS - 1);
+        let result = self.causes
+            .update_one(
+                doc! { "_id": cause_id, "team.email": { "$ne": &member.email }, last_member_slot: { "$exists": false } },
+                doc! { "$push": { "team": member_doc }, "$set": { "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if result.modified_count == 1 {
+            return Ok(true);
+        }
+        let cause = self.causes.find_one(doc! { "_id": cause_id }, None).await
+            .map_err(ApiError::DatabaseError)?
+            .ok_or_else(|| ApiError::NotFound("Cause not found".to_string()))?;
+        if cause.team.iter().any(|existing| existing.email == member.email) {
+            Ok(false)
+        } else {
+            Err(ApiError::ValidationError(format!("A cause can have at most {} team members", MAX_TEAM_MEMBERS)))
+        }
+    }
+    
+    /// Returns false if no member has the email
+    pub async fn set_team_member_role(&self, cause_id: &ObjectId, email: &str, role: TeamRole) -> Result<bool, ApiError> {
+        let result = self.causes
+            .update_one(
+                doc! { "_id": cause_id, "team.email": email },
+                doc! { "$set": { "team.$.role": role.to_string(), "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count == 1)
+    }
+    
+    /// Returns false if no member has the email
+    pub async fn remove_team_member(&self, cause_id: &ObjectId, email: &str) -> Result<bool, ApiError> {
+        let result = self.causes
+            .update_one(
+                doc! { "_id": cause_id, "team.email": email },
+                doc! { "$pull": { "team": { "email": email } }, "$set": { "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count == 1)
+    }
+    
+    /// Link the invited email to the wallet that accepted. Returns false if the email isn't
+    /// invited or another wallet already accepted for it.
+    pub async fn accept_team_invite(&self, cause_id: &ObjectId, email: &str, wallet_address: &str) -> Result<bool, ApiError> {
+        let result = self.causes
+            .update_one(
+                doc! {
+                    "_id": cause_id,
+                    "team": { "$elemMatch": {
+                        "email": email,
+                        "$or": [{ "wallet_address": { "$exists": false } }, { "wallet_address": wallet_address }],
+                    } },
+                },
+                doc! { "$set": {
+                    "team.$.wallet_address": wallet_address,
+                    "team.$.accepted_at": chrono::Utc::now().timestamp(),
+                    "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()),
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count == 1)
+    }
+    
+    /// Causes the wallet is on the team of
+    pub async fn get_team_causes(&self, wallet_address: &str) -> Result<Vec<Cause>, ApiError> {
+        self.causes
+            .find(doc! { "team.wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn create_organization(&self, organization: &Organization) -> Result<ObjectId, ApiError> {
+        let result = self.organizations
+            .insert_one(organization, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::DuplicateError(format!("An organization named {} already exists", organization.name))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| ApiError::InternalError("Organization was inserted without an ID".to_string()))
+    }
+    
+    pub async fn get_organization(&self, id: &ObjectId) -> Result<Option<Organization>, ApiError> {
+        self.organizations
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Organizations a wallet administers, by name
+    pub async fn get_organizations_for_admin(&self, wallet_address: &str) -> Result<Vec<Organization>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "name": 1 })
+            .build();
+        self.organizations
+            .find(doc! { "admins": wallet_address }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn set_organization_account(&self, id: &ObjectId, stripe_account_id: &str) -> Result<(), ApiError> {
+        self.organizations
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "stripe_account_id": stripe_account_id, "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Returns whether an organization uses the account
+    pub async fn set_organization_onboarded(&self, stripe_account_id: &str, onboarded: bool) -> Result<bool, ApiError> {
+        let result = self.organizations
+            .update_one(
+                doc! { "stripe_account_id": stripe_account_id },
+                doc! { "$set": { "onboarding_completed": onboarded, "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count == 1)
+    }
+    
+    /// Returns the organization as updated. Adding an existing admin is a no-op.
+    pub async fn add_organization_admin(&self, id: &ObjectId, wallet_address: &str) -> Result<Organization, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let last_admin_slot = format!("admins.{}", MAX_ORGANIZATION_ADMINS - 1);
+        let updated = self.organizations
+            .find_one_and_update(
+                doc! {
+                    "_id": id,
+                    "$or": [
+                        { "admins": wallet_address },
+                        { last_admin_slot: { "$exists": false } },
+                    ],
+                },
+                doc! {
+                    "$addToSet": { "admins": wallet_address },
+                    "$set": { "updated_at": chrono::Utc::now().timestamp() },
+                },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        match updated {
+            Some(organization) => Ok(organization),
+            None if self.get_organization(id).await?.is_some() => Err(ApiError::ValidationError(
+                format!("An organization can have at most {} admins", MAX_ORGANIZATION_ADMINS)
+            )),
+            None => Err(ApiError::NotFound("Organization not found".to_string())),
+        }
+    }
+    
+    /// Returns the organization as updated. The last admin can't be removed.
+    pub async fn remove_organization_admin(&self, id: &ObjectId, wallet_address: &str) -> Result<Organization, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let updated = self.organizations
+            .find_one_and_update(
+                doc! { "_id": id, "admins": wallet_address, "admins.1": { "$exists": true } },
+                doc! {
+                    "$pull": { "admins": wallet_address },
+                    "$set": { "updated_at": chrono::Utc::now().timestamp() },
+                },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if let Some(organization) = updated {
+            return Ok(organization);
+        }
+        match self.get_organization(id).await? {
+            Some(organization) if organization.is_admin(wallet_address) => Err(ApiError::ValidationError(
+                "An organization must keep at least one admin".to_string()
+            )),
+            Some(_) => Err(ApiError::NotFound(format!("{} is not an admin of this organization", wallet_address))),
+            None => Err(ApiError::NotFound("Organization not found".to_string())),
+        }
+    }
+    
+    /// Clear `wallet_address` as owner of the organization's causes, leaving them to its admins
+    pub async fn release_organization_causes(&self, organization_id: &str, wallet_address: &str) -> Result<u64, ApiError> {
+        let result = self.causes
+            .update_many(
+                doc! { "organization_id": organization_id, "owner_address": wallet_address },
+                doc! {
+                    "$unset": { "owner_address": "" },
+                    "$set": { "updated_at": bson::DateTime::from_chrono(chrono::Utc::now()) },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count)
+    }
+
+    /// An organization's causes, newest first
+    pub async fn get_organization_causes(&self, organization_id: &str) -> Result<Vec<Cause>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        self.causes
+            .find(doc! { "organization_id": organization_id }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// A connected account's payout events, newest first
+    pub async fn get_payout_events_for_account(&self, stripe_account_id: &str, limit: i64) -> Result<Vec<PayoutEvent>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+        self.payout_events
+            .find(doc! { "stripe_account_id": stripe_account_id }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn record_activity(&self, event: ActivityEvent) -> Result<(), ApiError> {
+        self.activities
+            .insert_one(event, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// A page of a wallet's payments, deposits and activity events, newest first. Items from
+    /// the same second are ordered by source and then ID so the cursor never skips one.
+    pub async fn get_wallet_activity(&self, wallet_address: &str, query: &ActivityQuery) -> Result<ActivityPage, ApiError> {
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        let types = query.types().map_err(ApiError::ValidationError)?;
+        let cursor = query.cursor.as_deref().map(decode_activity_cursor).transpose()?;
+        
+        // Fetch one extra from each source to know whether there is another page
+        let options = |id_field: &str| mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, id_field: -1 })
+            .limit(limit + 1)
+            .build();
+        let mut items: Vec<(i64, usize, String, ActivityItem)> = Vec::new();
+        
+        if types.contains(&"transaction") {
+            let mut conditions = vec![doc! { "$or": [{ "vendor_address": wallet_address }, { "customer_address": wallet_address }] }];
+            conditions.extend(activity_keyset(cursor.as_ref(), 0, "payment_id")?);
+            let payments: Vec<Payment> = self.transactions
+                .find(doc! { "$and": conditions }, options("payment_id"))
+                .await
+                .map_err(ApiError::DatabaseError)?
+                .try_collect()
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            items.extend(payments.into_iter().map(|payment| (
+                payment.created_at,
+                0,
+                payment.payment_id.clone(),
+                ActivityItem::Transaction(TransactionHistoryItem::for_wallet(payment, wallet_address)),
+            )));
+        }
+        
+        if types.contains(&"deposit") {
+            let mut conditions = vec![doc! { "wallet_address": wallet_address }];
+            conditions.extend(activity_keyset(cursor.as_ref(), 1, "_id")?);
+            let deposits: Vec<DepositRecord> = self.deposit_records
+                .find(doc! { "$and": conditions }, options("_id"))
+                .await
+                .map_err(ApiError::DatabaseError)?
+                .try_collect()
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            items.extend(deposits.into_iter().filter_map(|deposit| {
+                let id = deposit.id?.to_hex();
+                Some((deposit.created_at, 1, id, ActivityItem::Deposit(deposit)))
+            }));
+        }
+        
+        let kinds: Vec<&str> = types.iter().copied().filter(|t| *t != "transaction" && *t != "deposit").collect();
+        if !kinds.is_empty() {
+            let mut conditions = vec![doc! { "wallet_address": wallet_address, "kind": { "$in": kinds } }];
+            conditions.extend(activity_keyset(cursor.as_ref(), 2, "_id")?);
+            let events: Vec<ActivityEvent> = self.activities
+                .find(doc! { "$and": conditions }, options("_id"))
+                .await
+                .map_err(ApiError::DatabaseError)?
+                .try_collect()
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            items.extend(events.into_iter().filter_map(|event| {
+                let id = event.id?.to_hex();
+                Some((event.created_at, 2, id, ActivityItem::from(event)))
+            }));
+        }
+        
+        items.sort_by(|a, b| (b.0, b.1, &b.2).cmp(&(a.0, a.1, &a.2)));
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(|(created_at, source, id, _)| encode_cursor(*created_at, &format!("{}-{}", ACTIVITY_SOURCES[*source], id)))
+        } else {
+            None
+        };
+        
+        Ok(ActivityPage {
+            activities: items.into_iter().map(|(_, _, _, item)| item).collect(),
+            next_cursor,
+        })
+    }
+    
+    pub async fn get_all_wallet_addresses(&self) -> Result<Vec<String>, ApiError> {
+        let values = self.users
+            .distinct("wallet_address", None, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(values.into_iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+    }
+
+    pub async fn save_reconciliation_issues(&self, issues: &[ReconciliationIssue]) -> Result<(), ApiError> {
+        if issues.is_empty() {
+            return Ok(());
+        }
+        self.reconciliation_issues
+            .insert_many(issues, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_reconciliation_issues(&self, query: &ReconciliationIssueQuery) -> Result<Vec<ReconciliationIssue>, ApiError> {
+        let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+        let mut filter = doc! {};
+        if let Some(wallet_address) = &query.wallet_address {
+            filter.insert("wallet_address", wallet_address);
+        }
+        if let Some(run_id) = &query.run_id {
+            filter.insert("run_id", run_id);
+        }
+        if let Some(resolved) = query.resolved {
+            filter.insert("resolved", resolved);
+        }
+
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "detected_at": -1 })
+            .limit(limit)
+            .build();
+
+        self.reconciliation_issues
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn resolve_reconciliation_issue(&self, issue_id: &ObjectId) -> Result<bool, ApiError> {
+        let result = self.reconciliation_issues
+            .update_one(doc! { "_id": issue_id }, doc! { "$set": { "resolved": true } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count > 0)
+    }
+    
+    /// Store (or bump the attempt count of) a Stripe event that failed processing
+    pub async fn record_webhook_failure(&self, endpoint: WebhookEndpoint, event_id: &str, event_type: &str, payload: &str, error: &str) -> Result<(), ApiError> {
+        let now = bson::DateTime::from_chrono(chrono::Utc::now());
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        self.webhook_failures
+            .update_one(
+                doc! { "event_id": event_id },
+                doc! {
+                    "$set": {
+                        "error": error,
+                        "status": WebhookFailureStatus::Pending.to_string(),
+                        "last_failed_at": now,
+                    },
+                    "$inc": { "attempts": 1 },
+                    "$setOnInsert": {
+                        "event_type": event_type,
+                        "endpoint": endpoint.to_string(),
+                        "payload": payload,
+                        "first_failed_at": now,
+                    },
+                },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Record an accepted request signature. Returns false if it was already used.
+    pub async fn claim_request_signature(&self, used: &UsedSignature) -> Result<bool, ApiError> {
+        match self.used_signatures.insert_one(used, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    /// Returns false if the event was already marked processed
+    pub async fn mark_stripe_event_processed(&self, event: &ProcessedStripeEvent) -> Result<bool, ApiError> {
+        match self.processed_stripe_events.insert_one(event, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    /// Take back the mark of an event whose replay failed
+    pub async fn unmark_stripe_event_processed(&self, event_id: &str) -> Result<(), ApiError> {
+        self.processed_stripe_events
+            .delete_one(doc! { "event_id": event_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_webhook_failure(&self, failure_id: &ObjectId) -> Result<Option<WebhookFailure>, ApiError> {
+        self.webhook_failures
+            .find_one(doc! { "_id": failure_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_webhook_failures(&self, query: &WebhookFailureQuery) -> Result<Vec<WebhookFailure>, ApiError> {
+        let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+        let mut filter = doc! {};
+        if let Some(status) = &query.status {
+            filter.insert("status", status);
+        }
+
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "last_failed_at": -1 })
+            .limit(limit)
+            .build();
+
+        self.webhook_failures
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Queue a verified Stripe event for the webhook workers. Returns false if it's
+    /// already queued.
+    pub async fn enqueue_webhook_job(&self, job: &WebhookJob) -> Result<bool, ApiError> {
+        match self.webhook_jobs.insert_one(job, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    /// Jobs a worker could take, oldest first, whether or not they're due: queued ones and
+    /// ones whose worker's lease ran out
+    pub async fn get_queued_webhook_jobs(&self, limit: i64) -> Result<Vec<WebhookJob>, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1, "_id": 1 })
+            .limit(limit)
+            .build();
+        self.webhook_jobs
+            .find(doc! { "$or": [
+                { "status": WebhookJobStatus::Queued.to_string() },
+                { "status": WebhookJobStatus::Processing.to_string(), "lease_expires_at": { "$not": { "$gte": now } } },
+            ] }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Take a due job for `owner` until `lease_expires_at`, counting the attempt: a queued
+    /// one, or one whose previous worker's lease ran out. None if another worker has it, or
+    /// is processing an earlier job with the same ordering key.
+    pub async fn claim_webhook_job(&self, job_id: &ObjectId, owner: &str, lease_expires_at: i64) -> Result<Option<WebhookJob>, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let claimed = self.webhook_jobs
+            .find_one_and_update(
+                doc! { "_id": job_id, "$or": [
+                    { "status": WebhookJobStatus::Queued.to_string(), "next_attempt_at": { "$lte": now } },
+                    { "status": WebhookJobStatus::Processing.to_string(), "lease_expires_at": { "$not": { "$gte": now } } },
+                ] },
+                doc! {
+                    "$set": {
+                        "status": WebhookJobStatus::Processing.to_string(),
+                        "lease_owner": owner,
+                        "lease_expires_at": lease_expires_at,
+                        "updated_at": now,
+                    },
+                    "$inc": { "attempts": 1 },
+                },
+                options,
+            )
+            .await;
+        match claimed {
+            Ok(job) => Ok(job),
+            // The ordering key's unique processing index: an earlier job is still being applied
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(None),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    /// Mark a job `owner` applied as processed, along with its event. False if its lease ran
+    /// out and another worker took it over, which then records the outcome instead.
+    pub async fn finish_webhook_job(&self, job: &WebhookJob, owner: &str) -> Result<bool, ApiError> {
+        let finished = self.webhook_jobs
+            .update_one(
+                doc! { "_id": job.id, "status": WebhookJobStatus::Processing.to_string(), "lease_owner": owner },
+                doc! {
+                    "$set": {
+                        "status": WebhookJobStatus::Processed.to_string(),
+                        "processed_at": mongodb::bson::DateTime::now(),
+                        "updated_at": chrono::Utc::now().timestamp(),
+                    },
+                    "$unset": { "lease_owner": "", "lease_expires_at": "" },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(finished.modified_count > 0)
+    }
+
+    /// Mark the failed job of an event that was replayed processed. It's kept, like any
+    /// processed job, so redeliveries of the event are still dropped.
+    pub async fn mark_failed_webhook_job_processed(&self, event_id: &str) -> Result<(), ApiError> {
+        self.webhook_jobs
+            .update_one(
+                doc! { "event_id": event_id, "status": WebhookJobStatus::Failed.to_string() },
+                doc! { "$set": {
+                    "status": WebhookJobStatus::Processed.to_string(),
+                    "processed_at": mongodb::bson::DateTime::now(),
+                    "updated_at": chrono::Utc::now().timestamp(),
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Put a job whose attempt by `owner` failed back in the queue from `next_attempt_at`,
+    /// or leave it failed when `next_attempt_at` is None. False if `owner` no longer holds it.
+    pub async fn fail_webhook_job(&self, job_id: &ObjectId, owner: &str, error: &str, next_attempt_at: Option<i64>) -> Result<bool, ApiError> {
+        let status = if next_attempt_at.is_some() { WebhookJobStatus::Queued } else { WebhookJobStatus::Failed };
+        let mut set = doc! {
+            "status": status.to_string(),
+            "last_error": error,
+            "updated_at": chrono::Utc::now().timestamp(),
+        };
+        if let Some(next_attempt_at) = next_attempt_at {
+            set.insert("next_attempt_at", next_attempt_at);
+        }
+        let failed = self.webhook_jobs
+            .update_one(
+                doc! { "_id": job_id, "status": WebhookJobStatus::Processing.to_string(), "lease_owner": owner },
+                doc! { "$set": set, "$unset": { "lease_owner": "", "lease_expires_at": "" } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(failed.modified_count > 0)
+    }
+
+    pub async fn get_webhook_queue_status(&self, query: &WebhookQueueQuery, concurrency: usize) -> Result<WebhookQueueStatus, ApiError> {
+        let limit = query.limit.unwrap_or(100).clamp(1, 500);
+        let count = |status: WebhookJobStatus| self.webhook_jobs.count_documents(doc! { "status": status.to_string() }, None);
+        let queued = count(WebhookJobStatus::Queued).await.map_err(ApiError::DatabaseError)?;
+        let processing = count(WebhookJobStatus::Processing).await.map_err(ApiError::DatabaseError)?;
+        let failed = count(WebhookJobStatus::Failed).await.map_err(ApiError::DatabaseError)?;
+        let oldest_queued_at = self.get_queued_webhook_jobs(1).await?.first().map(|job| job.created_at);
+
+        let mut filter = doc! {};
+        if let Some(status) = &query.status {
+            filter.insert("status", status);
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1, "_id": 1 })
+            .limit(limit)
+            .build();
+        let jobs = self.webhook_jobs
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        Ok(WebhookQueueStatus { concurrency, queued, processing, failed, oldest_queued_at, jobs })
+    }
+
+    pub async fn mark_webhook_failure_replayed(&self, failure_id: &ObjectId) -> Result<(), ApiError> {
+        self.webhook_failures
+            .update_one(
+                doc! { "_id": failure_id },
+                doc! { "$set": { "status": WebhookFailureStatus::Replayed.to_string() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    // Get all partnered vendors
+    pub async fn get_all_partnered_vendors(&self) -> Result<Vec<PartneredVendor>, ApiError> {
+        let mut cursor = self.partnered_vendors
+            .find(None, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let mut vendors = Vec::new();
+        while let Some(vendor) = cursor.try_next().await.map_err(ApiError::DatabaseError)? {
+            vendors.push(vendor);
+        }
+        
+        Ok(vendors)
+    }
+
+    pub async fn get_partnered_vendor(&self, wallet_address: &str) -> Result<Option<PartneredVendor>, ApiError> {
+        self.partnered_vendors
+            .find_one(doc! { "wallet_address": wallet_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Apply directory field changes to a vendor, returning the updated vendor
+    pub async fn update_vendor_profile(&self, wallet_address: &str, set: Document, unset: Document) -> Result<Option<PartneredVendor>, ApiError> {
+        let mut update = Document::new();
+        if !set.is_empty() {
+            update.insert("$set", set);
+        }
+        if !unset.is_empty() {
+            update.insert("$unset", unset);
+        }
+        if update.is_empty() {
+            return self.get_partnered_vendor(wallet_address).await;
+        }
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.partnered_vendors
+            .find_one_and_update(doc! { "wallet_address": wallet_address }, update, options)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Vendors with a location within `radius_m` meters, closest first
+    pub async fn get_nearby_vendors(&self, lat: f64, lng: f64, radius_m: f64, category: Option<&str>, limit: i64) -> Result<Vec<PartneredVendor>, ApiError> {
+        let mut filter = doc! {
+            "location": {
+                "$nearSphere": {
+                    "$geometry": { "type": "Point", "coordinates": [lng, lat] },
+                    "$maxDistance": radius_m,
+                }
+            }
+        };
+        if let Some(category) = category {
+            filter.insert("category", category);
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .limit(limit)
+            .build();
+        self.partnered_vendors
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Store a review and refresh the vendor's rating
+    pub async fn create_review(&self, mut review: Review) -> Result<Review, ApiError> {
+        let result = self.reviews
+            .insert_one(&review, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("This payment has already been reviewed".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        review.id = result.inserted_id.as_object_id();
+        
+        let rating = self.vendor_rating(&review.vendor_address).await?;
+        let rating_bson = bson::to_bson(&rating)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize rating: {}", e)))?;
+        self.partnered_vendors
+            .update_one(
+                doc! { "wallet_address": &review.vendor_address },
+                doc! { "$set": { "rating": rating_bson } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(review)
+    }
+    
+    pub async fn vendor_rating(&self, vendor_address: &str) -> Result<Option<VendorRating>, ApiError> {
+        let pipeline = vec![
+            doc! { "$match": { "vendor_address": vendor_address } },
+            doc! { "$group": { "_id": null, "average": { "$avg": "$rating" }, "count": { "$sum": 1 } } },
+        ];
+        let totals: Vec<Document> = self.reviews
+            .aggregate(pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(totals.first().map(|d| VendorRating {
+            average: (number(d, "average") * 100.0).round() / 100.0,
+            count: number(d, "count") as i64,
+        }))
+    }
+    
+    /// A vendor's reviews, newest first, with the overall rating
+    pub async fn get_vendor_reviews(&self, vendor_address: &str, query: &ReviewQuery) -> Result<ReviewPage, ApiError> {
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        let mut conditions = vec![doc! { "vendor_address": vendor_address }];
+        if let Some(cursor) = &query.cursor {
+            let (created_at, id) = decode_cursor(cursor).map_err(ApiError::ValidationError)?;
+            let id = ObjectId::parse_str(&id).map_err(|_| ApiError::ValidationError("Invalid cursor".to_string()))?;
+            conditions.push(doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, "_id": { "$lt": id } }
+                ]
+            });
+        }
+        
+        // Fetch one extra to know whether there is another page
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+        let mut reviews: Vec<Review> = self.reviews
+            .find(doc! { "$and": conditions }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        
+        let next_cursor = if reviews.len() as i64 > limit {
+            reviews.truncate(limit as usize);
+            reviews.last().and_then(|r| r.id.map(|id| encode_cursor(r.created_at, &id.to_hex())))
+        } else {
+            None
+        };
+        
+        Ok(ReviewPage {
+            rating: self.vendor_rating(vendor_address).await?,
+            reviews,
+            next_cursor,
+        })
+    }
+
+    pub async fn get_loyalty_program(&self, vendor_address: &str) -> Result<Option<LoyaltyProgram>, ApiError> {
+        self.loyalty_programs
+            .find_one(doc! { "vendor_address": vendor_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn save_loyalty_program(&self, program: &LoyaltyProgram) -> Result<(), ApiError> {
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        self.loyalty_programs
+            .replace_one(doc! { "vendor_address": &program.vendor_address }, program, options)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    pub async fn get_loyalty_account(&self, vendor_address: &str, customer_address: &str) -> Result<Option<LoyaltyAccount>, ApiError> {
+        self.loyalty_accounts
+            .find_one(doc! { "vendor_address": vendor_address, "customer_address": customer_address }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// A customer's points with every vendor, most recently active first
+    pub async fn get_customer_loyalty_accounts(&self, customer_address: &str) -> Result<Vec<LoyaltyAccount>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "updated_at": -1 })
+            .build();
+        self.loyalty_accounts
+            .find(doc! { "customer_address": customer_address }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Attach a reward to one of the vendor's payments that hasn't been signed yet.
+    /// Returns None if the payment can't take a reward (signed, or one is already applied).
+    pub async fn set_loyalty_redemption(&self, payment_id: &str, vendor_address: &str, redemption: &LoyaltyRedemption) -> Result<Option<Payment>, ApiError> {
+        let redemption_bson = bson::to_bson(redemption)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize loyalty redemption: {}", e)))?;
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.transactions
+            .find_one_and_update(
+                doc! {
+                    "payment_id": payment_id,
+                    "vendor_address": vendor_address,
+                    "status": { "$in": ["Created", "CustomerAssigned", "Calculated"] },
+                    "loyalty_redemption": { "$exists": false },
+                },
+                doc! { "$set": { "loyalty_redemption": redemption_bson } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Take a reward back off a payment that hasn't been signed yet
+    pub async fn clear_loyalty_redemption(&self, payment_id: &str, vendor_address: &str) -> Result<bool, ApiError> {
+        let result = self.transactions
+            .update_one(
+                doc! {
+                    "payment_id": payment_id,
+                    "vendor_address": vendor_address,
+                    "status": { "$in": ["Created", "CustomerAssigned", "Calculated"] },
+                    "loyalty_redemption": { "$exists": true },
+                },
+                doc! { "$unset": { "loyalty_redemption": "" } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+    
+    /// Deduct the points of the reward applied to a payment at signing, before the transfer
+    /// goes out. The balance is checked in the same update, so points spent on another
+    /// payment meanwhile refuse this one and it can be supplemented again.
+    pub async fn deduct_loyalty_points(&self, payment: &Payment) -> Result<(), ApiError> {
+        let redemption = match &payment.loyalty_redemption {
+            Some(redemption) if !redemption.points_deducted => redemption,
+            _ => return Ok(()),
+        };
+        let deducted = self.loyalty_accounts
+            .update_one(
+                doc! {
+                    "vendor_address": &payment.vendor_address,
+                    "customer_address": &redemption.customer_address,
+                    "points": { "$gte": redemption.points_cost },
+                },
+                doc! { "$inc": { "points": -redemption.points_cost }, "$set": { "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if deducted.modified_count == 0 {
+            return Err(ApiError::Conflict("Not enough loyalty points left for the applied reward".to_string()));
+        }
+        self.transactions
+            .update_one(
+                doc! { "payment_id": &payment.payment_id, "loyalty_redemption.reward_id": &redemption.reward_id },
+                doc! { "$set": { "loyalty_redemption.points_deducted": true } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        log::info!("Deducted {} loyalty points from {} for reward {} on payment {}",
+            redemption.points_cost, redemption.customer_address, redemption.reward_id, payment.payment_id);
+        Ok(())
+    }
+
+    /// Give back the points a payment's reward deducted, once it failed
+    pub async fn refund_loyalty_points(&self, payment: &Payment) -> Result<(), ApiError> {
+        let Some(redemption) = &payment.loyalty_redemption else { return Ok(()) };
+        let released = self.transactions
+            .update_one(
+                doc! { "payment_id": &payment.payment_id, "loyalty_redemption.points_deducted": true },
+                doc! { "$set": { "loyalty_redemption.points_deducted": false } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if released.modified_count == 0 {
+            return Ok(());
+        }
+        self.loyalty_accounts
+            .update_one(
+                doc! { "vendor_address": &payment.vendor_address, "customer_address": &redemption.customer_address },
+                doc! { "$inc": { "points": redemption.points_cost }, "$set": { "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        log::info!("Gave {} loyalty points back to {} for failed payment {}", redemption.points_cost, redemption.customer_address, payment.payment_id);
+        Ok(())
+    }
+
+    /// Credit points earned for a completed payment; a reward's points were deducted at
+    /// signing. Runs once per payment; returns the points earned, or None if already settled.
+    pub async fn settle_loyalty(&self, payment: &Payment) -> Result<Option<i64>, ApiError> {
+        let customer_address = match &payment.customer_address {
+            Some(customer_address) => customer_address,
+            None => return Ok(None),
+        };
+        let earned = self.get_loyalty_program(&payment.vendor_address).await?
+            .map_or(0, |program| program.points_for(payment.price_usd));
+        
+        let claimed = self.transactions
+            .update_one(
+                doc! { "payment_id": &payment.payment_id, "loyalty_points_earned": { "$exists": false } },
+                doc! { "$set": { "loyalty_points_earned": earned } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if claimed.modified_count == 0 {
+            return Ok(None);
+        }
+        
+        if earned > 0 {
+            let now = chrono::Utc::now().timestamp();
+            let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+            self.loyalty_accounts
+                .update_one(
+                    doc! { "vendor_address": &payment.vendor_address, "customer_address": customer_address },
+                    doc! {
+                        "$inc": { "points": earned, "lifetime_points": earned },
+                        "$set": { "updated_at": now },
+                    },
+                    options,
+                )
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            log::info!("{} earned {} loyalty points at {} for payment {}", customer_address, earned, payment.vendor_address, payment.payment_id);
+        }
+        Ok(Some(earned))
+    }
+
+    pub async fn get_promo_code(&self, vendor_address: &str, code: &str) -> Result<Option<PromoCode>, ApiError> {
+        self.promo_codes
+            .find_one(doc! { "vendor_address": vendor_address, "code": code.trim().to_uppercase() }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// A vendor's promo codes, newest first
+    pub async fn get_promo_codes(&self, vendor_address: &str) -> Result<Vec<PromoCode>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        self.promo_codes
+            .find(doc! { "vendor_address": vendor_address }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn create_promo_code(&self, mut promo_code: PromoCode) -> Result<PromoCode, ApiError> {
+        let result = self.promo_codes
+            .insert_one(&promo_code, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict(format!("Promo code {} already exists", promo_code.code))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        promo_code.id = result.inserted_id.as_object_id();
+        Ok(promo_code)
+    }
+    
+    pub async fn update_promo_code(&self, vendor_address: &str, code: &str, set: Document) -> Result<Option<PromoCode>, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.promo_codes
+            .find_one_and_update(
+                doc! { "vendor_address": vendor_address, "code": code.trim().to_uppercase() },
+                doc! { "$set": set },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Returns whether the code existed
+    pub async fn delete_promo_code(&self, vendor_address: &str, code: &str) -> Result<bool, ApiError> {
+        let result = self.promo_codes
+            .delete_one(doc! { "vendor_address": vendor_address, "code": code.trim().to_uppercase() }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.deleted_count > 0)
+    }
+    
+    /// Record the promo applied at supplement, or clear one left from an earlier supplement
+    pub async fn set_payment_promo(&self, payment_id: &str, promo: Option<&AppliedPromo>) -> Result<(), ApiError> {
+        let update = match promo {
+            Some(promo) => {
+                let promo_bson = bson::to_bson(promo)
+                    .map_err(|e| ApiError::InternalError(format!("Failed to serialize promo: {}", e)))?;
+                doc! { "$set": { "promo": promo_bson } }
+            },
+            None => doc! { "$unset": { "promo": "" } },
+        };
+        self.transactions
+            .update_one(doc! { "payment_id": payment_id }, update, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Record how a split payment's bundle is divided between its recipients
+    pub async fn set_payment_split_legs(&self, payment_id: &str, split_legs: &[SplitLeg]) -> Result<(), ApiError> {
+        let split_legs = bson::to_bson(split_legs)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize split legs: {}", e)))?;
+        self.transactions
+            .update_one(doc! { "payment_id": payment_id }, doc! { "$set": { "split_legs": split_legs } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Count a payment's promo code use at signing, before the transfer goes out. The usage
+    /// limit is enforced in the same update as the count, so concurrent payments can't
+    /// overshoot it; the one that would is refused and can be supplemented again.
+    pub async fn consume_payment_promo(&self, payment: &Payment) -> Result<(), ApiError> {
+        let promo = match &payment.promo {
+            Some(promo) if !promo.consumed => promo,
+            _ => return Ok(()),
+        };
+        let consumed = self.promo_codes
+            .update_one(
+                doc! {
+                    "vendor_address": &payment.vendor_address,
+                    "code": &promo.code,
+                    "$or": [
+                        { "max_uses": null },
+                        { "$expr": { "$lt": ["$uses", "$max_uses"] } },
+                    ],
+                },
+                doc! { "$inc": { "uses": 1 }, "$set": { "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if consumed.modified_count == 0 {
+            return Err(ApiError::Conflict(format!("Promo code {} is used up, supplement the payment again", promo.code)));
+        }
+        self.transactions
+            .update_one(
+                doc! { "payment_id": &payment.payment_id, "promo.code": &promo.code },
+                doc! { "$set": { "promo.consumed": true } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Give back the promo code use a payment counted, once it failed
+    pub async fn release_payment_promo(&self, payment: &Payment) -> Result<(), ApiError> {
+        let Some(promo) = &payment.promo else { return Ok(()) };
+        let released = self.transactions
+            .update_one(
+                doc! { "payment_id": &payment.payment_id, "promo.consumed": true },
+                doc! { "$set": { "promo.consumed": false } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if released.modified_count == 0 {
+            return Ok(());
+        }
+        self.promo_codes
+            .update_one(
+                doc! { "vendor_address": &payment.vendor_address, "code": &promo.code, "uses": { "$gt": 0 } },
+                doc! { "$inc": { "uses": -1 }, "$set": { "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        log::info!("Released promo code {} use of failed payment {}", promo.code, payment.payment_id);
+        Ok(())
+    }
+
+    /// Move a payment's escrow between states, setting `set` (field names within the
+    /// escrow) alongside. Returns None if the escrow wasn't in one of the `from` states.
+    pub async fn transition_payment_escrow(
+        &self,
+        payment_id: &str,
+        from: &[EscrowStatus],
+        to: EscrowStatus,
+        set: Document,
+    ) -> Result<Option<Payment>, ApiError> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        let mut update = doc! {
+            "escrow.status": to.to_string(),
+            "escrow.updated_at": chrono::Utc::now().timestamp(),
+        };
+        for (key, value) in set {
+            update.insert(format!("escrow.{}", key), value);
+        }
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.transactions
+            .find_one_and_update(
+                doc! { "payment_id": payment_id, "escrow.status": { "$in": from } },
+                doc! { "$set": update },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Held escrows past their release time, plus settlements that failed and haven't
+    /// been touched since `retry_before`, so one still in flight isn't repeated
+    pub async fn get_escrows_to_settle(&self, now: i64, retry_before: i64, limit: i64) -> Result<Vec<Payment>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "escrow.release_at": 1 })
+            .limit(limit)
+            .build();
+        self.transactions
+            .find(doc! { "$or": [
+                { "escrow.status": EscrowStatus::Held.to_string(), "escrow.release_at": { "$lte": now } },
+                {
+                    "escrow.status": { "$in": [EscrowStatus::Capturing.to_string(), EscrowStatus::Refunding.to_string()] },
+                    "escrow.updated_at": { "$lte": retry_before },
+                },
+            ] }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn create_voucher(&self, mut voucher: Voucher) -> Result<Voucher, ApiError> {
+        let result = self.vouchers
+            .insert_one(&voucher, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict("Voucher code collision, please retry".to_string())
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        voucher.id = result.inserted_id.as_object_id();
+        Ok(voucher)
+    }
+    
+    pub async fn get_voucher(&self, id: &ObjectId) -> Result<Option<Voucher>, ApiError> {
+        self.vouchers
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn get_voucher_by_code(&self, code: &str) -> Result<Option<Voucher>, ApiError> {
+        self.vouchers
+            .find_one(doc! { "code": code }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Vouchers a wallet issued, newest first
+    pub async fn get_issued_vouchers(&self, issuer_address: &str, status: Option<VoucherStatus>) -> Result<Vec<Voucher>, ApiError> {
+        let mut filter = doc! { "issuer_address": issuer_address };
+        if let Some(status) = status {
+            filter.insert("status", status.to_string());
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(200)
+            .build();
+        self.vouchers
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Move a voucher between states, setting `set` alongside. Returns None if it wasn't in
+    /// one of the `from` states, so only one caller wins each transition.
+    pub async fn transition_voucher(
+        &self,
+        id: &ObjectId,
+        from: &[VoucherStatus],
+        to: VoucherStatus,
+        mut set: Document,
+    ) -> Result<Option<Voucher>, ApiError> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        set.insert("status", to.to_string());
+        set.insert("updated_at", chrono::Utc::now().timestamp());
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.vouchers
+            .find_one_and_update(
+                doc! { "_id": id, "status": { "$in": from } },
+                doc! { "$set": set },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Claim an unexpired active voucher for a redeemer
+    pub async fn claim_voucher(&self, code: &str, redeemer: &str, now: i64) -> Result<Option<Voucher>, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.vouchers
+            .find_one_and_update(
+                doc! { "code": code, "status": VoucherStatus::Active.to_string(), "expires_at": { "$gt": now } },
+                doc! { "$set": {
+                    "status": VoucherStatus::Redeeming.to_string(),
+                    "redeemed_by": redeemer,
+                    "redeemed_at": now,
+                    "updated_at": now,
+                } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Tokens of one kind the central vault currently holds for vouchers
+    pub async fn escrowed_voucher_amount(&self, token_symbol: &str) -> Result<f64, ApiError> {
+        let pipeline = vec![
+            doc! { "$match": {
+                "token_symbol": token_symbol,
+                "status": { "$in": [
+                    VoucherStatus::Funding.to_string(),
+                    VoucherStatus::Active.to_string(),
+                    VoucherStatus::Redeeming.to_string(),
+                    VoucherStatus::Refunding.to_string(),
+                    VoucherStatus::Failed.to_string(),
+                ] },
+            } },
+            doc! { "$group": { "_id": null, "amount": { "$sum": "$amount" } } },
+        ];
+        let totals: Vec<Document> = self.vouchers
+            .aggregate(pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(totals.first().map_or(0.0, |d| number(d, "amount")))
+    }
+    
+    /// Vouchers past their expiry that haven't been closed, plus refunds that failed and
+    /// haven't been touched for `retry_after` seconds, so one still in flight isn't repeated,
+    /// and redemptions or fundings left in flight that long, whose outcome was never recorded
+    pub async fn get_vouchers_to_expire(&self, now: i64, retry_after: i64, limit: i64) -> Result<Vec<Voucher>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "expires_at": 1 })
+            .limit(limit)
+            .build();
+        self.vouchers
+            .find(doc! { "$or": [
+                {
+                    "status": { "$in": [VoucherStatus::Active.to_string(), VoucherStatus::PendingFunding.to_string()] },
+                    "expires_at": { "$lte": now },
+                },
+                {
+                    "status": { "$in": [VoucherStatus::Refunding.to_string(), VoucherStatus::Redeeming.to_string(), VoucherStatus::Funding.to_string()] },
+                    "updated_at": { "$lte": now - retry_after },
+                },
+            ] }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn create_dispute(&self, mut dispute: Dispute) -> Result<Dispute, ApiError> {
+        let result = self.disputes
+            .insert_one(&dispute, None)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("E11000 duplicate key error") {
+                    ApiError::Conflict(format!("Payment {} has already been disputed", dispute.payment_id))
+                } else {
+                    ApiError::DatabaseError(e)
+                }
+            })?;
+        dispute.id = result.inserted_id.as_object_id();
+        Ok(dispute)
+    }
+    
+    pub async fn get_dispute(&self, id: &ObjectId) -> Result<Option<Dispute>, ApiError> {
+        self.disputes
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Disputes a wallet raised or received, newest first, or every dispute oldest first
+    /// (the admin queue) without a wallet
+    pub async fn get_disputes(&self, wallet_address: Option<&str>, status: Option<DisputeStatus>) -> Result<Vec<Dispute>, ApiError> {
+        let mut filter = match wallet_address {
+            Some(wallet_address) => doc! { "$or": [
+                { "customer_address": wallet_address },
+                { "vendor_address": wallet_address },
+            ] },
+            None => doc! {},
+        };
+        if let Some(status) = status {
+            filter.insert("status", status.to_string());
+        }
+        let direction = if wallet_address.is_some() { -1 } else { 1 };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": direction })
+            .limit(200)
+            .build();
+        self.disputes
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Move a dispute between states, setting `set` alongside. Returns None if it wasn't
+    /// in one of the `from` states, so only one caller wins each transition.
+    pub async fn transition_dispute(
+        &self,
+        id: &ObjectId,
+        from: &[DisputeStatus],
+        to: DisputeStatus,
+        mut set: Document,
+    ) -> Result<Option<Dispute>, ApiError> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        set.insert("status", to.to_string());
+        set.insert("updated_at", chrono::Utc::now().timestamp());
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.disputes
+            .find_one_and_update(
+                doc! { "_id": id, "status": { "$in": from } },
+                doc! { "$set": set },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Move a refund-resolved dispute's reverse transfer between states, as `transition_dispute`
+    pub async fn transition_dispute_refund(
+        &self,
+        id: &ObjectId,
+        from: &[DisputeRefundStatus],
+        to: DisputeRefundStatus,
+        mut set: Document,
+    ) -> Result<Option<Dispute>, ApiError> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        set.insert("refund_status", to.to_string());
+        set.insert("updated_at", chrono::Utc::now().timestamp());
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.disputes
+            .find_one_and_update(
+                doc! { "_id": id, "status": DisputeStatus::ResolvedRefund.to_string(), "refund_status": { "$in": from } },
+                doc! { "$set": set },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Central vault refunds the vendor hasn't paid back yet, oldest first
+    pub async fn get_outstanding_liabilities(&self, vendor_address: Option<&str>) -> Result<Vec<Dispute>, ApiError> {
+        let mut filter = doc! {
+            "refund_status": DisputeRefundStatus::Completed.to_string(),
+            "vendor_liability": { "$exists": true },
+            "liability_settled_at": { "$exists": false },
+        };
+        if let Some(vendor_address) = vendor_address {
+            filter.insert("vendor_address", vendor_address);
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .limit(200)
+            .build();
+        self.disputes
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Record that the vendor paid back a refund. Returns None if there was no outstanding
+    /// liability to settle.
+    pub async fn settle_vendor_liability(&self, id: &ObjectId, admin: &str) -> Result<Option<Dispute>, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.disputes
+            .find_one_and_update(
+                doc! {
+                    "_id": id,
+                    "refund_status": DisputeRefundStatus::Completed.to_string(),
+                    "vendor_liability": { "$exists": true },
+                    "liability_settled_at": { "$exists": false },
+                },
+                doc! { "$set": { "liability_settled_at": now, "liability_settled_by": admin, "updated_at": now } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    pub async fn create_payment_schedule(&self, mut schedule: PaymentSchedule) -> Result<PaymentSchedule, ApiError> {
+        let result = self.payment_schedules
+            .insert_one(&schedule, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        schedule.id = result.inserted_id.as_object_id();
+        Ok(schedule)
+    }
+    
+    pub async fn get_payment_schedule(&self, id: &ObjectId) -> Result<Option<PaymentSchedule>, ApiError> {
+        self.payment_schedules
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Active and paused schedules a customer pays
+    pub async fn count_open_payment_schedules(&self, customer_address: &str) -> Result<u64, ApiError> {
+        self.payment_schedules
+            .count_documents(doc! {
+                "customer_address": customer_address,
+                "status": { "$in": [ScheduleStatus::Active.to_string(), ScheduleStatus::Paused.to_string()] },
+            }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Schedules a wallet pays (or is paid by, if `outgoing` is false), newest first
+    pub async fn get_payment_schedules(&self, wallet_address: &str, outgoing: bool, status: Option<ScheduleStatus>) -> Result<Vec<PaymentSchedule>, ApiError> {
+        let mut filter = if outgoing {
+            doc! { "customer_address": wallet_address }
+        } else {
+            doc! { "vendor_address": wallet_address }
+        };
+        if let Some(status) = status {
+            filter.insert("status", status.to_string());
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(100)
+            .build();
+        self.payment_schedules
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Active schedules with a run due by `now`, longest overdue first
+    pub async fn get_due_payment_schedules(&self, now: i64, limit: i64) -> Result<Vec<PaymentSchedule>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "next_run_at": 1 })
+            .limit(limit)
+            .build();
+        self.payment_schedules
+            .find(doc! { "status": ScheduleStatus::Active.to_string(), "next_run_at": { "$lte": now } }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Move a schedule to `to` if it's still in one of the `from` states, setting `set`
+    /// alongside. Returns None if it had already moved on.
+    pub async fn transition_payment_schedule(
+        &self,
+        id: &ObjectId,
+        from: &[ScheduleStatus],
+        to: ScheduleStatus,
+        mut set: Document,
+    ) -> Result<Option<PaymentSchedule>, ApiError> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        set.insert("status", to.to_string());
+        set.insert("updated_at", chrono::Utc::now().timestamp());
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.payment_schedules
+            .find_one_and_update(
+                doc! { "_id": id, "status": { "$in": from } },
+                doc! { "$set": set },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Claim the run of an active schedule due at `run_at`, setting `set` to move it on.
+    /// Returns None if the run was already taken or the schedule changed.
+    pub async fn claim_payment_schedule_run(&self, id: &ObjectId, run_at: i64, mut set: Document) -> Result<Option<PaymentSchedule>, ApiError> {
+        set.insert("updated_at", chrono::Utc::now().timestamp());
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.payment_schedules
+            .find_one_and_update(
+                doc! { "_id": id, "status": ScheduleStatus::Active.to_string(), "next_run_at": run_at },
+                doc! { "$set": set, "$inc": { "payments_created": 1 } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Hand an unsigned payment assigned to `from` over to `to`, another wallet of the same
+    /// customer, so the one code is paid from whichever wallet they choose. What `from`'s
+    /// supplement computed is dropped for `to` to supplement again. Returns false if the
+    /// payment was signed or reassigned in the meantime.
+    pub async fn reassign_payment_customer(&self, payment_id: &str, from: &str, to: &str, to_username: Option<String>) -> Result<bool, ApiError> {
+        let result = self.transactions
+            .update_one(
+                doc! { "payment_id": payment_id, "customer_address": from, "status": { "$in": unsigned_statuses() }, "budget_consumed": { "$ne": true } },
+                doc! {
+                    "$set": {
+                        "customer_address": to,
+                        "customer_username": to_username,
+                        "status": PaymentStatus::CustomerAssigned.to_string(),
+                    },
+                    "$unset": {
+                        "discount_consumption": "",
+                        "computed_payment": "",
+                        "initial_payment_bundle": "",
+                        "promo": "",
+                        "loyalty_redemption": "",
+                        "split_legs": "",
+                    },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Point a schedule at the payment code made for its latest run
+    pub async fn set_schedule_payment(&self, id: &ObjectId, payment_id: &str) -> Result<(), ApiError> {
+        self.payment_schedules
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "last_payment_id": payment_id, "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn create_invoice(&self, mut invoice: Invoice) -> Result<Invoice, ApiError> {
+        let result = self.invoices
+            .insert_one(&invoice, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        invoice.id = result.inserted_id.as_object_id();
+        Ok(invoice)
+    }
+    
+    pub async fn get_invoice(&self, id: &ObjectId) -> Result<Option<Invoice>, ApiError> {
+        self.invoices
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Invoices a wallet received (or issued, if `incoming` is false), newest first
+    pub async fn get_invoices(&self, wallet_address: &str, incoming: bool, status: Option<InvoiceStatus>) -> Result<Vec<Invoice>, ApiError> {
+        let mut filter = if incoming {
+            doc! { "customer_address": wallet_address }
+        } else {
+            doc! { "vendor_address": wallet_address }
+        };
+        if let Some(status) = status {
+            filter.insert("status", status.to_string());
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(100)
+            .build();
+        self.invoices
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Close the invoice a completed payment was made for
+    pub async fn mark_invoice_paid(&self, invoice_id: &str, payment_id: &str) -> Result<(), ApiError> {
+        let id = ObjectId::parse_str(invoice_id)
+            .map_err(|_| ApiError::ValidationError(format!("Invalid invoice ID: {}", invoice_id)))?;
+        let now = chrono::Utc::now().timestamp();
+        self.invoices
+            .update_one(
+                doc! { "_id": id, "status": InvoiceStatus::Open.to_string() },
+                doc! { "$set": {
+                    "status": InvoiceStatus::Paid.to_string(),
+                    "payment_id": payment_id,
+                    "paid_at": now,
+                    "updated_at": now,
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+    
+    /// Open invoices past due whose last reminder (if any) went out before `remind_before`
+    pub async fn get_invoices_needing_reminder(&self, now: i64, remind_before: i64, limit: i64) -> Result<Vec<Invoice>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "due_at": 1 })
+            .limit(limit)
+            .build();
+        self.invoices
+            .find(doc! {
+                "status": InvoiceStatus::Open.to_string(),
+                "due_at": { "$lt": now },
+                "reminders_sent": { "$lt": MAX_INVOICE_REMINDERS },
+                "$or": [
+                    { "last_reminder_at": { "$exists": false } },
+                    { "last_reminder_at": { "$lte": remind_before } },
+                ],
+            }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+    
+    /// Count a reminder for an invoice that's still open. Returns false if it was paid or
+    /// cancelled in the meantime.
+    pub async fn mark_invoice_reminded(&self, id: &ObjectId, now: i64) -> Result<bool, ApiError> {
+        let result = self.invoices
+            .update_one(
+                doc! { "_id": id, "status": InvoiceStatus::Open.to_string() },
+                doc! { "$set": { "last_reminder_at": now }, "$inc": { "reminders_sent": 1 } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+    
+    /// Point an open invoice at the payment code made for paying it. Returns false if the
+    /// invoice was cancelled or paid in the meantime.
+    pub async fn set_invoice_payment(&self, id: &ObjectId, payment_id: &str) -> Result<bool, ApiError> {
+        let result = self.invoices
+            .update_one(
+                doc! { "_id": id, "status": InvoiceStatus::Open.to_string() },
+                doc! { "$set": { "payment_id": payment_id, "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Cancel an open invoice if it still points at `payment_id`, so a code issued for it
+    /// since isn't left payable. Returns None if it moved on.
+    pub async fn cancel_open_invoice(&self, id: &ObjectId, payment_id: Option<&str>) -> Result<Option<Invoice>, ApiError> {
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.invoices
+            .find_one_and_update(
+                doc! { "_id": id, "status": InvoiceStatus::Open.to_string(), "payment_id": payment_id },
+                doc! { "$set": { "status": InvoiceStatus::Cancelled.to_string(), "updated_at": chrono::Utc::now().timestamp() } },
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    // Audit log methods
+    pub async fn record_audit_log(&self, entry: AuditLog) -> Result<(), ApiError> {
+        log::info!("Audit: {} {} {} {}", entry.actor, entry.action, entry.resource_type, entry.resource_id);
+        self.audit_logs
+            .insert_one(entry, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Paginated audit log query, newest first. Pages start at 1.
+    pub async fn get_audit_logs(&self, query: &AuditLogQuery) -> Result<AuditLogPage, ApiError> {
+        let page = query.page.unwrap_or(1).max(1);
+        let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+        let mut filter = doc! {};
+        if let Some(action) = &query.action {
+            filter.insert("action", action);
+        }
+        if let Some(actor) = &query.actor {
+            filter.insert("actor", actor);
+        }
+        if let Some(resource_id) = &query.resource_id {
+            filter.insert("resource_id", resource_id);
+        }
+
+        let total = self.audit_logs
+            .count_documents(filter.clone(), None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .skip((page - 1) * limit as u64)
+            .limit(limit)
+            .build();
+
+        let logs = self.audit_logs
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        Ok(AuditLogPage { logs, page, limit, total })
+    }
+
+    pub async fn get_cached_daily_report(&self, vendor_address: &str, date: &str) -> Result<Option<DailySettlementReport>, ApiError> {
+        self.daily_reports
+            .find_one(doc! { "vendor_address": vendor_address, "date": date }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn cache_daily_report(&self, report: &DailySettlementReport) -> Result<(), ApiError> {
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        self.daily_reports
+            .replace_one(
+                doc! { "vendor_address": &report.vendor_address, "date": &report.date },
+                report,
+                options,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Aggregate a vendor's completed payments in [period_start, period_end) into a settlement report
+    pub async fn build_daily_report(
+        &self,
+        vendor_address: &str,
+        date: &str,
+        period_start: i64,
+        period_end: i64,
+    ) -> Result<DailySettlementReport, ApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let day_match = doc! {
+            "$match": {
+                "vendor_address": vendor_address,
+                "status": "Completed",
+                "created_at": { "$gte": period_start, "$lt": period_end },
+            }
+        };
+
+        // Payment count and USD totals for the day
+        let totals_pipeline = vec![
+            day_match.clone(),
+            doc! { "$group": { "_id": null, "payment_count": { "$sum": 1 }, "total_price_usd": { "$sum": "$price_usd" } } },
+        ];
+        let totals: Vec<Document> = self.transactions
+            .aggregate(totals_pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        let (payment_count, total_price_usd) = totals
+            .first()
+            .map(|d| (number(d, "payment_count") as u64, number(d, "total_price_usd")))
+            .unwrap_or((0, 0.0));
+
+        // Per-token receipts, valued at the effective valuation recorded at sale time
+        // and at the token's current market valuation
+        let receipts_pipeline = vec![
+            day_match.clone(),
+            doc! { "$unwind": "$computed_payment" },
+            doc! { "$lookup": {
+                "from": "transaction_records",
+                "let": { "pid": "$payment_id", "tk": "$computed_payment.token_key" },
+                "pipeline": [
+                    { "$match": { "$expr": { "$and": [
+                        { "$eq": ["$payment_id", "$$pid"] },
+                        { "$eq": ["$token_key", "$$tk"] },
+                    ] } } },
+                    { "$limit": 1 },
+                ],
+                "as": "record",
+            } },
+            doc! { "$group": {
+                "_id": "$computed_payment.token_key",
+                "symbol": { "$first": "$computed_payment.symbol" },
+                "gross_receipts": { "$sum": "$computed_payment.amount_to_pay" },
+                "effective_usd_value": { "$sum": { "$multiply": [
+                    "$computed_payment.amount_to_pay",
+                    { "$ifNull": [{ "$arrayElemAt": ["$record.effective_valuation", 0] }, 1.0] },
+                ] } },
+                "payments": { "$addToSet": "$payment_id" },
+            } },
+            doc! { "$lookup": { "from": "tokens", "localField": "_id", "foreignField": "token_id", "as": "token" } },
+            doc! { "$project": {
+                "symbol": 1,
+                "gross_receipts": 1,
+                "effective_usd_value": 1,
+                "payment_count": { "$size": "$payments" },
+                "market_valuation": { "$ifNull": [{ "$arrayElemAt": ["$token.market_valuation", 0] }, 1.0] },
+            } },
+            doc! { "$sort": { "symbol": 1 } },
+        ];
+        let receipts: Vec<Document> = self.transactions
+            .aggregate(receipts_pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        // Discounts the vendor granted, per token
+        let discounts_pipeline = vec![
+            day_match,
+            doc! { "$unwind": "$discount_consumption" },
+            doc! { "$group": { "_id": "$discount_consumption.token_key", "amount": { "$sum": "$discount_consumption.amount_used" } } },
+        ];
+        let discounts: Vec<Document> = self.transactions
+            .aggregate(discounts_pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let tokens = receipts
+            .iter()
+            .map(|r| {
+                let token_key = r.get_str("_id").unwrap_or_default().to_string();
+                let discounts_granted = discounts
+                    .iter()
+                    .find(|d| d.get_str("_id").ok() == Some(token_key.as_str()))
+                    .map(|d| number(d, "amount"))
+                    .unwrap_or(0.0);
+                let gross_receipts = number(r, "gross_receipts");
+                let current_market_valuation = number(r, "market_valuation");
+                TokenSettlement {
+                    symbol: r.get_str("symbol").unwrap_or_default().to_string(),
+                    token_key,
+                    gross_receipts,
+                    discounts_granted,
+                    effective_usd_value: number(r, "effective_usd_value"),
+                    current_market_valuation,
+                    current_market_value: gross_receipts * current_market_valuation,
+                    payment_count: number(r, "payment_count") as u64,
+                }
+            })
+            .collect();
+
+        Ok(DailySettlementReport {
+            id: None,
+            vendor_address: vendor_address.to_string(),
+            date: date.to_string(),
+            period_start,
+            period_end,
+            payment_count,
+            total_price_usd,
+            tokens,
+            generated_at: now,
+            market_valued_at: now,
+        })
+    }
+
+    pub async fn create_matching_pool(&self, pool: &MatchingPool) -> Result<ObjectId, ApiError> {
+        let result = self.matching_pools
+            .insert_one(pool, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| ApiError::InternalError("Failed to get inserted matching pool ID".to_string()))
+    }
+
+    pub async fn get_matching_pool(&self, pool_id: &ObjectId) -> Result<Option<MatchingPool>, ApiError> {
+        self.matching_pools
+            .find_one(doc! { "_id": pool_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_matching_pools(&self, query: &MatchingPoolQuery) -> Result<Vec<MatchingPool>, ApiError> {
+        let mut filter = doc! {};
+        if let Some(symbol) = &query.cause_symbol {
+            filter.insert("cause_symbols", symbol.to_uppercase());
+        }
+        if let Some(status) = &query.status {
+            filter.insert("status", status);
+        }
+
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+
+        self.matching_pools
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Active pools matching a cause token right now, oldest first so earlier sponsors are drawn first
+    pub async fn get_open_pools_for_symbol(&self, token_symbol: &str, now: i64) -> Result<Vec<MatchingPool>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+
+        self.matching_pools
+            .find(doc! {
+                "cause_symbols": token_symbol,
+                "status": MatchingPoolStatus::Active.to_string(),
+                "starts_at": { "$lte": now },
+                "$or": [
+                    { "ends_at": null },
+                    { "ends_at": { "$gt": now } }
+                ]
+            }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Take `cents` from an active pool's budget. Returns false if the pool is no longer
+    /// active or does not have that much left (e.g. a concurrent match got there first).
+    pub async fn reserve_pool_budget(&self, pool_id: &ObjectId, cents: i64) -> Result<bool, ApiError> {
+        let result = self.matching_pools
+            .update_one(
+                doc! {
+                    "_id": pool_id,
+                    "status": MatchingPoolStatus::Active.to_string(),
+                    "$expr": { "$lte": [ { "$add": ["$matched_cents", cents] }, "$cap_cents" ] }
+                },
+                doc! { "$inc": { "matched_cents": cents } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        if result.modified_count == 0 {
+            return Ok(false);
+        }
+
+        // Flip to exhausted once the whole budget is used
+        self.matching_pools
+            .update_one(
+                doc! {
+                    "_id": pool_id,
+                    "status": MatchingPoolStatus::Active.to_string(),
+                    "$expr": { "$gte": ["$matched_cents", "$cap_cents"] }
+                },
+                doc! { "$set": { "status": MatchingPoolStatus::Exhausted.to_string() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(true)
+    }
+
+    pub async fn close_matching_pool(&self, pool_id: &ObjectId) -> Result<bool, ApiError> {
+        let result = self.matching_pools
+            .update_one(
+                doc! { "_id": pool_id },
+                doc! { "$set": { "status": MatchingPoolStatus::Closed.to_string() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.matched_count > 0)
+    }
+
+    /// Record a match before tokens move. Returns false if this session was already matched from the pool.
+    pub async fn insert_match_event(&self, event: &MatchEvent) -> Result<bool, ApiError> {
+        match self.match_events.insert_one(event, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    /// Settle a match once its tokens reached the donor. Returns false if it was already credited.
+    pub async fn mark_match_event_credited(&self, event_id: &ObjectId, tokens_credited: f64, executor_tx_id: Option<&str>) -> Result<bool, ApiError> {
+        let result = self.match_events
+            .update_one(
+                doc! { "_id": event_id, "status": { "$ne": MatchEventStatus::Credited.to_string() } },
+                doc! {
+                    "$set": {
+                        "status": MatchEventStatus::Credited.to_string(),
+                        "tokens_credited": tokens_credited,
+                        "executor_tx_id": executor_tx_id,
+                    },
+                    "$unset": { "failure_reason": "" },
+                },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count == 1)
+    }
+
+    /// Leave a match for the retry sweep. Its pool budget stays reserved.
+    pub async fn fail_match_event(&self, event_id: &ObjectId, reason: &str) -> Result<(), ApiError> {
+        self.match_events
+            .update_one(
+                doc! { "_id": event_id, "status": { "$ne": MatchEventStatus::Credited.to_string() } },
+                doc! { "$set": { "status": MatchEventStatus::Failed.to_string(), "failure_reason": reason } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Drop a pending match that never got its pool budget
+    pub async fn delete_match_event(&self, event_id: &ObjectId) -> Result<(), ApiError> {
+        self.match_events
+            .delete_one(doc! { "_id": event_id, "status": MatchEventStatus::Pending.to_string() }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Matches the sweep should settle: failed ones, and pending ones created before
+    /// `stale_before` whose webhook never finished them. Oldest first.
+    pub async fn get_unsettled_match_events(&self, stale_before: i64, limit: i64) -> Result<Vec<MatchEvent>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .build();
+
+        self.match_events
+            .find(doc! {
+                "$or": [
+                    { "status": MatchEventStatus::Failed.to_string() },
+                    { "status": MatchEventStatus::Pending.to_string(), "created_at": { "$lt": stale_before } },
+                ]
+            }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_match_events(&self, pool_id: &ObjectId, limit: i64) -> Result<Vec<MatchEvent>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+
+        self.match_events
+            .find(doc! { "pool_id": pool_id }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn count_match_events(&self, pool_id: &ObjectId) -> Result<u64, ApiError> {
+        self.match_events
+            .count_documents(doc! { "pool_id": pool_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn create_funding_round(&self, round: &FundingRound) -> Result<ObjectId, ApiError> {
+        let result = self.funding_rounds
+            .insert_one(round, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| ApiError::InternalError("Failed to get inserted funding round ID".to_string()))
+    }
+
+    pub async fn get_funding_round(&self, round_id: &ObjectId) -> Result<Option<FundingRound>, ApiError> {
+        self.funding_rounds
+            .find_one(doc! { "_id": round_id }, None)
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn get_funding_rounds(&self, status: Option<&str>) -> Result<Vec<FundingRound>, ApiError> {
+        let mut filter = doc! {};
+        if let Some(status) = status {
+            filter.insert("status", status);
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "starts_at": -1 })
+            .build();
+
+        self.funding_rounds
+            .find(filter, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Rounds whose window is open right now and that include this cause token
+    pub async fn get_open_rounds_for_symbol(&self, token_symbol: &str, now: i64) -> Result<Vec<FundingRound>, ApiError> {
+        self.funding_rounds
+            .find(doc! {
+                "cause_symbols": token_symbol,
+                "status": FundingRoundStatus::Open.to_string(),
+                "starts_at": { "$lte": now },
+                "ends_at": { "$gt": now },
+            }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Returns false if this session was already counted for the round
+    pub async fn record_round_contribution(&self, contribution: &RoundContribution) -> Result<bool, ApiError> {
+        match self.round_contributions.insert_one(contribution, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000 duplicate key error") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+
+    /// Total contributed per (cause token, contributor) in a round
+    pub async fn get_round_contribution_totals(&self, round_id: &ObjectId) -> Result<Vec<(String, String, i64)>, ApiError> {
+        let pipeline = vec![
+            doc! { "$match": { "round_id": round_id } },
+            doc! { "$group": {
+                "_id": { "symbol": "$token_symbol", "wallet": "$contributor_wallet" },
+                "amount": { "$sum": "$amount_cents" },
+            } },
+            doc! { "$sort": { "_id.symbol": 1, "_id.wallet": 1 } },
+        ];
+        let totals: Vec<Document> = self.round_contributions
+            .aggregate(pipeline, None)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        Ok(totals
+            .iter()
+            .filter_map(|t| {
+                let key = t.get_document("_id").ok()?;
+                Some((
+                    key.get_str("symbol").ok()?.to_string(),
+                    key.get_str("wallet").ok()?.to_string(),
+                    number(t, "amount") as i64,
+                ))
+            })
+            .collect())
+    }
+
+    /// Store allocations and move an open round to closed. Returns false if it was not open.
+    pub async fn close_funding_round(&self, round_id: &ObjectId, allocations: &[RoundAllocation], closed_at: i64) -> Result<bool, ApiError> {
+        let allocations = bson::to_bson(allocations)
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize allocations: {}", e)))?;
+        let result = self.funding_rounds
+            .update_one(
+                doc! { "_id": round_id, "status": FundingRoundStatus::Open.to_string() },
+                doc! { "$set": {
+                    "status": FundingRoundStatus::Closed.to_string(),
+                    "allocations": allocations,
+                    "closed_at": closed_at,
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
+    pub async fn set_funding_round_distributed(&self, round_id: &ObjectId) -> Result<(), ApiError> {
+        self.funding_rounds
+            .update_one(
+                doc! { "_id": round_id, "status": FundingRoundStatus::Closed.to_string() },
+                doc! { "$set": { "status": FundingRoundStatus::Distributed.to_string() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Insert payouts, skipping any that already exist from an earlier attempt
+    pub async fn create_round_payouts(&self, payouts: &[RoundPayout]) -> Result<(), ApiError> {
+        for payout in payouts {
+            match self.round_payouts.insert_one(payout, None).await {
+                Ok(_) => {}
+                Err(e) if e.to_string().contains("E11000 duplicate key error") => {}
+                Err(e) => return Err(ApiError::DatabaseError(e)),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get_round_payouts(&self, round_id: &ObjectId) -> Result<Vec<RoundPayout>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "token_symbol": 1, "matched_cents": -1 })
+            .build();
+
+        self.round_payouts
+            .find(doc! { "round_id": round_id }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Claim a pending or failed payout for crediting. Returns false if another run has it.
+    pub async fn claim_round_payout(&self, payout_id: &ObjectId) -> Result<bool, ApiError> {
+        let result = self.round_payouts
+            .update_one(
+                doc! {
+                    "_id": payout_id,
+                    "status": { "$in": [RoundPayoutStatus::Pending.to_string(), RoundPayoutStatus::Failed.to_string()] }
+                },
+                doc! { "$set": {
+                    "status": RoundPayoutStatus::Processing.to_string(),
+                    "updated_at": chrono::Utc::now().timestamp(),
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Payouts left processing since before `stale_before`, oldest first
+    pub async fn get_stale_round_payouts(&self, stale_before: i64, limit: i64) -> Result<Vec<RoundPayout>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "updated_at": 1 })
+            .limit(limit)
+            .build();
+
+        self.round_payouts
+            .find(doc! { "status": RoundPayoutStatus::Processing.to_string(), "updated_at": { "$lt": stale_before } }, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    /// Take over a payout still processing since before `stale_before`. Returns false if its
+    /// run finished it, or another sweep took it, in the meantime.
+    pub async fn reclaim_round_payout(&self, payout_id: &ObjectId, stale_before: i64) -> Result<bool, ApiError> {
+        let result = self.round_payouts
+            .update_one(
+                doc! {
+                    "_id": payout_id,
+                    "status": RoundPayoutStatus::Processing.to_string(),
+                    "updated_at": { "$lt": stale_before },
+                },
+                doc! { "$set": { "updated_at": chrono::Utc::now().timestamp() } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Record the outcome of crediting a claimed payout
+    pub async fn finish_round_payout(&self, payout_id: &ObjectId, status: RoundPayoutStatus, tokens_credited: f64, error: Option<String>) -> Result<bool, ApiError> {
+        let result = self.round_payouts
+            .update_one(
+                doc! { "_id": payout_id, "status": RoundPayoutStatus::Processing.to_string() },
+                doc! { "$set": {
+                    "status": status.to_string(),
+                    "tokens_credited": tokens_credited,
+                    "error": error,
+                    "updated_at": chrono::Utc::now().timestamp(),
+                } },
+                None,
+            )
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Migrations already applied, oldest first
+    pub async fn get_applied_migrations(&self) -> Result<Vec<SchemaMigration>, ApiError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "_id": 1 })
+            .build();
+
+        self.schema_migrations
+            .find(doc! {}, options)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .try_collect()
+            .await
+            .map_err(ApiError::DatabaseError)
+    }
+
+    pub async fn record_migration(&self, migration: &SchemaMigration) -> Result<(), ApiError> {
+        let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+        self.schema_migrations
+            .replace_one(doc! { "_id": migration.version }, migration, options)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Store the defaults older users were read with, so queries on these fields find
+    /// them. Returns how many users changed.
+    pub async fn backfill_user_defaults(&self) -> Result<u64, ApiError> {
+        let defaults = doc! {
+            "is_verified": false,
+            "user_type": "customer",
+            "roles": [],
+            "donate_anonymously": false,
+        };
+        let mut modified = 0;
+        for (field, value) in defaults {
+            let mut filter = Document::new();
+            filter.insert(field.as_str(), doc! { "$exists": false });
+            let mut set = Document::new();
+            set.insert(field, value);
+            let result = self.users
+                .update_many(filter, doc! { "$set": set }, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            modified += result.modified_count;
+        }
+        Ok(modified)
+    }
+
+    /// Give older users a `username_key` so the unique index covers them. Users whose
+    /// username clashes with another's are left without one and counted as skipped.
+    /// Returns (backfilled, skipped).
+    pub async fn backfill_username_keys(&self) -> Result<(u64, u64), ApiError> {
+        let mut cursor = self.users
+            .find(doc! { "username_key": { "$exists": false }, "deleted_at": { "$exists": false } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let (mut backfilled, mut skipped) = (0, 0);
+        while let Some(user) = cursor.try_next().await.map_err(ApiError::DatabaseError)? {
+            let result = self.users
+                .update_one(
+                    doc! { "wallet_address": &user.wallet_address, "username_key": { "$exists": false } },
+                    doc! { "$set": { "username_key": username_key(&user.username) } },
+                    None,
+                )
+                .await;
+            match result {
+                Ok(result) => backfilled += result.modified_count,
+                Err(e) if e.to_string().contains("E11000 duplicate key error") => {
+                    log::warn!("Username {} of {} clashes with another user's, left without a username_key", user.username, user.wallet_address);
+                    skipped += 1;
+                },
+                Err(e) => return Err(ApiError::DatabaseError(e)),
+            }
+        }
+        Ok((backfilled, skipped))
+    }
+
+    /// Store the false older payments were read with for their flags. Returns how many
+    /// payments changed.
+    pub async fn backfill_payment_flags(&self) -> Result<u64, ApiError> {
+        let mut modified = 0;
+        for field in ["recepient_verified", "budget_consumed"] {
+            let mut filter = Document::new();
+            filter.insert(field, doc! { "$exists": false });
+            let mut set = Document::new();
+            set.insert(field, false);
+            let result = self.transactions
+                .update_many(filter, doc! { "$set": set }, None)
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            modified += result.modified_count;
+        }
+        Ok(modified)
+    }
+    
+    /// Give templates saved before slots the owner's free slots, oldest first, so the slot
+    /// index counts them toward the cap. Returns how many templates changed.
+    pub async fn backfill_preference_template_slots(&self) -> Result<u64, ApiError> {
+        let owners = self.preference_templates
+            .distinct("owner_address", doc! { "slot": { "$exists": false } }, None)
+            .await
+            .map_err(ApiError::DatabaseError)?;
+
+        let mut modified = 0;
+        for owner in owners.iter().filter_map(|owner| owner.as_str()) {
+            let taken: Vec<i64> = self.preference_templates
+                .distinct("slot", doc! { "owner_address": owner }, None)
+                .await
+                .map_err(ApiError::DatabaseError)?
+                .iter()
+                .filter_map(|slot| slot.as_i64().or_else(|| slot.as_i32().map(i64::from)))
+                .collect();
+            let options = mongodb::options::FindOptions::builder()
+                .sort(doc! { "created_at": 1 })
+                .build();
+            let unslotted: Vec<PreferenceTemplate> = self.preference_templates
+                .find(doc! { "owner_address": owner, "slot": { "$exists": false } }, options)
+                .await
+                .map_err(ApiError::DatabaseError)?
+                .try_collect()
+                .await
+                .map_err(ApiError::DatabaseError)?;
+            // Owners already over the cap keep their templates, in slots past it
+            let mut free = (0..).filter(|slot| !taken.contains(slot));
+            for template in unslotted {
+                let (Some(id), Some(slot)) = (template.id, free.next()) else { continue };
+                let result = self.preference_templates
+                    .update_one(doc! { "_id": id, "slot": { "$exists": false } }, doc! { "$set": { "slot": slot } }, None)
+                    .await;
+                match result {
+                    Ok(result) => modified += result.modified_count,
+                    // Another instance running this migration slotted the owner's templates
+                    Err(e) if e.to_string().contains("E11000 duplicate key error") => break,
+                    Err(e) => return Err(ApiError::DatabaseError(e)),
+                }
+            }
+        }
+        Ok(modified)
+    }
+
+    /// Drop the unique index on `event_id` alone, which stopped a balance event being
+    /// recorded for each of its currencies. Returns false if it was already gone.
+    pub async fn drop_payout_event_id_index(&self) -> Result<bool, ApiError> {
+        match self.payout_events.drop_index("event_id_1", None).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.to_string().contains("index not found") || e.to_string().contains("ns not found") => Ok(false),
+            Err(e) => Err(ApiError::DatabaseError(e)),
+        }
+    }
+}
+
+// Aggregation sums come back as Int32, Int64 or Double depending on the inputs
+fn number(doc: &Document, key: &str) -> f64 {
+    match doc.get(key) {
+        Some(bson::Bson::Double(v)) => *v,
+        Some(bson::Bson::Int32(v)) => *v as f64,
+        Some(bson::Bson::Int64(v)) => *v as f64,
+        _ => 0.0,
+    }
+}
+
+/// Where the vendor's user document records what a payment drew from a token's budget
+fn budget_draw_key(payment_id: &str, symbol: &str) -> String {
+    format!("budget_draws.{}.{}", payment_id, symbol)
+}
+
+/// Statuses of a payment nobody has signed yet
+fn unsigned_statuses() -> Vec<String> {
+    [PaymentStatus::Created, PaymentStatus::CustomerAssigned, PaymentStatus::Calculated]
+        .iter()
+        .map(|status| status.to_string())
+        .collect()
+}
+
+/// Condition matching the payments in a vendor-facing lifecycle state at `now`
+
+fn payment_state_condition(state: &PaymentState, now: i64) -> Document {
+    let expiry_cutoff = now - PAYMENT_CODE_TTL_SECS;
+    let open_statuses = vec!["Created", "CustomerAssigned", "Calculated"];
+    match state {
+        PaymentState::Completed => doc! { "status": "Completed" },
+        PaymentState::Failed => doc! { "status": "Failed" },
+        PaymentState::Processing => doc! { "status": "Submitted" },
+        PaymentState::Authorized => doc! { "status": "Authorized" },
+        PaymentState::Voided => doc! { "status": "Voided" },
+        PaymentState::Active => doc! { "status": { "$in": &open_statuses }, "created_at": { "$gte": expiry_cutoff } },
+        PaymentState::Expired => doc! { "status": { "$in": &open_statuses }, "created_at": { "$lt": expiry_cutoff } },
+    }
+}
+
+/// Sources merged into a wallet's activity feed, in the order items from the same second
+/// are listed (last first)
+const ACTIVITY_SOURCES: [&str; 3] = ["transaction", "deposit", "event"];
+
+/// An activity feed cursor: created_at, index into `ACTIVITY_SOURCES`, and the item's ID
+fn decode_activity_cursor(cursor: &str) -> Result<(i64, usize, String), ApiError> {
+    let invalid = || ApiError::ValidationError("Invalid cursor".to_string());
+    let (created_at, key) = decode_cursor(cursor).map_err(ApiError::ValidationError)?;
+    let (source, id) = key.split_once('-').ok_or_else(invalid)?;
+    let source = ACTIVITY_SOURCES.iter().position(|s| *s == source).ok_or_else(invalid)?;
+    if id.is_empty() {
+        return Err(invalid());
+    }
+    Ok((created_at, source, id.to_string()))
+}
+
+/// Condition selecting the items of one activity source that come after the cursor
+fn activity_keyset(cursor: Option<&(i64, usize, String)>, source: usize, id_field: &str) -> Result<Option<Document>, ApiError> {
+    let Some((created_at, cursor_source, id)) = cursor else {
+        return Ok(None);
+    };
+    let condition = match source.cmp(cursor_source) {
+        std::cmp::Ordering::Less => doc! { "created_at": { "$lte": created_at } },
+        std::cmp::Ordering::Greater => doc! { "created_at": { "$lt": created_at } },
+        std::cmp::Ordering::Equal => {
+            let id = if id_field == "_id" {
+                bson::Bson::ObjectId(ObjectId::parse_str(id).map_err(|_| ApiError::ValidationError("Invalid cursor".to_string()))?)
+            } else {
+                bson::Bson::String(id.clone())
+            };
+            doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, id_field: { "$lt": id } }
+                ]
+            }
+        },
+    };
+    Ok(Some(condition))
+}
@@ -0,0 +1,141 @@
+use actix_web::web;
+use log::{info, error};
+use mongodb::bson::oid::ObjectId;
+use crate::models::{ApiError, Organization, CreateOrganizationRequest, OrganizationCreated, OrganizationCause, OrganizationDashboard};
+use crate::models::cause::Cause;
+use crate::services::{CauseService, MongoDBService};
+use crate::services::cause_service::CreateCauseRequest;
+
+// Payout events shown on an organization's dashboard
+const DASHBOARD_PAYOUTS: i64 = 20;
+
+/// Organizations that run several causes. The organization onboards with Stripe once and
+/// every cause it creates is paid out to that connected account; its admins manage all of
+/// its causes.
+#[derive(Clone)]
+pub struct OrganizationService {
+    mongodb: web::Data<MongoDBService>,
+    cause_service: web::Data<CauseService>,
+}
+
+impl OrganizationService {
+    pub fn new(mongodb: web::Data<MongoDBService>, cause_service: web::Data<CauseService>) -> Self {
+        Self { mongodb, cause_service }
+    }
+
+    /// Create the organization with `created_by` as its first admin, and its connected account
+    pub async fn create(&self, request: &CreateOrganizationRequest, created_by: &str) -> Result<OrganizationCreated, ApiError> {
+        // Refuse an unsupported country before the organization takes its name
+        self.cause_service.connect_agreement(&request.country)?;
+        let now = chrono::Utc::now().timestamp();
+        let mut organization = Organization {
+            id: None,
+            name: request.name.trim().to_string(),
+            contact_email: request.contact_email.trim().to_string(),
+            country: request.country.to_uppercase(),
+            business_type: request.business_type,
+            stripe_account_id: None,
+            onboarding_completed: false,
+            admins: vec![created_by.to_string()],
+            created_by: created_by.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        let id = self.mongodb.create_organization(&organization).await?;
+        organization.id = Some(id);
+
+        // If Stripe fails the organization is kept, and requesting onboarding creates the account
+        let account_id = self.cause_service.create_organization_account(&organization).await?;
+        self.mongodb.set_organization_account(&id, &account_id).await?;
+        organization.stripe_account_id = Some(account_id);
+        info!("{} created organization {} ({})", created_by, organization.name, id);
+
+        let onboarding_url = self.cause_service.create_organization_account_link(&organization).await?;
+        Ok(OrganizationCreated { organization, onboarding_url })
+    }
+
+    pub async fn get(&self, organization_id: &str) -> Result<Organization, ApiError> {
+        let id = ObjectId::parse_str(organization_id)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid organization ID format: {}", e)))?;
+        self.mongodb.get_organization(&id).await?
+            .ok_or_else(|| ApiError::NotFound("Organization not found".to_string()))
+    }
+
+    pub async fn list_for_wallet(&self, wallet_address: &str) -> Result<Vec<Organization>, ApiError> {
+        self.mongodb.get_organizations_for_admin(wallet_address).await
+    }
+
+    /// A fresh Stripe onboarding link, creating the connected account if that failed before
+    pub async fn onboarding_link(&self, mut organization: Organization) -> Result<String, ApiError> {
+        if organization.onboarding_completed {
+            return Err(ApiError::Conflict("The organization has already finished Stripe onboarding".to_string()));
+        }
+        if organization.stripe_account_id.is_none() {
+            let id = organization.id
+                .ok_or_else(|| ApiError::InternalError("Organization has no ID".to_string()))?;
+            let account_id = self.cause_service.create_organization_account(&organization).await?;
+            self.mongodb.set_organization_account(&id, &account_id).await?;
+            organization.stripe_account_id = Some(account_id);
+        }
+        self.cause_service.create_organization_account_link(&organization).await
+    }
+
+    pub async fn add_admin(&self, organization: &Organization, wallet_address: &str) -> Result<Organization, ApiError> {
+        let id = organization.id
+            .ok_or_else(|| ApiError::InternalError("Organization has no ID".to_string()))?;
+        self.mongodb.add_organization_admin(&id, wallet_address).await
+    }
+
+    /// Remove an admin, who also stops owning the organization's causes they created so they
+    /// no longer manage them
+    pub async fn remove_admin(&self, organization: &Organization, wallet_address: &str) -> Result<Organization, ApiError> {
+        let id = organization.id
+            .ok_or_else(|| ApiError::InternalError("Organization has no ID".to_string()))?;
+        let updated = self.mongodb.remove_organization_admin(&id, wallet_address).await?;
+        let released = self.mongodb.release_organization_causes(&id.to_hex(), wallet_address).await?;
+        if released > 0 {
+            info!("{} no longer owns {} causes of organization {}", wallet_address, released, organization.name);
+        }
+        Ok(updated)
+    }
+
+    /// Create a cause owned by `owner_address` under the organization, on its connected account
+    pub async fn create_cause(&self, organization: &Organization, mut request: CreateCauseRequest, owner_address: &str) -> Result<Cause, ApiError> {
+        request.owner_address = Some(owner_address.to_string());
+        let cause = self.cause_service.create_organization_cause(organization, request).await?;
+        info!("{} created cause {} under organization {}", owner_address, cause.name, organization.name);
+        Ok(cause)
+    }
+
+    /// Every cause of the organization with what they've raised together, and the recent
+    /// payouts to its account
+    pub async fn dashboard(&self, organization: Organization) -> Result<OrganizationDashboard, ApiError> {
+        let organization_id = organization.id.map(|id| id.to_hex()).unwrap_or_default();
+        let causes: Vec<OrganizationCause> = self.mongodb.get_organization_causes(&organization_id).await?
+            .into_iter()
+            .map(|cause| OrganizationCause {
+                cause_id: cause.id.map(|id| id.to_hex()).unwrap_or_default(),
+                name: cause.name,
+                token_symbol: cause.token_symbol,
+                status: cause.status,
+                amount_donated: cause.amount_donated,
+                tokens_purchased: cause.tokens_purchased,
+            })
+            .collect();
+        let recent_payouts = match &organization.stripe_account_id {
+            Some(account_id) => self.mongodb.get_payout_events_for_account(account_id, DASHBOARD_PAYOUTS).await
+                .unwrap_or_else(|e| {
+                    error!("Failed to load payouts for organization {}: {}", organization_id, e);
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+        Ok(OrganizationDashboard {
+            total_donated: causes.iter().map(|cause| cause.amount_donated).sum(),
+            total_tokens_purchased: causes.iter().map(|cause| cause.tokens_purchased).sum(),
+            organization,
+            causes,
+            recent_payouts,
+        })
+    }
+}
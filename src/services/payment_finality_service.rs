@@ -0,0 +1,134 @@
+use actix_web::web;
+use log::{info, warn, error};
+use delta_executor_sdk::base::vaults::ReadableVault;
+use crate::handlers::{apply_completed_payment, release_signing_holds};
+use crate::models::{AuditAction, AuditLog, Payment, PaymentStatus};
+use crate::services::{ExecutionStatus, MongoDBService, PushService, SharedEvent, SharedState, WalletService};
+use crate::utils::audit::snapshot;
+
+/// Submitted payments checked per run
+const BATCH_SIZE: i64 = 100;
+
+/// Warn about payments the executor hasn't settled after this long
+const STUCK_AFTER_SECS: i64 = 30 * 60;
+
+/// Fail payments the executor still hasn't settled after this long
+const MAX_PENDING_SECS: i64 = 24 * 60 * 60;
+
+/// Follows payments the executor accepted until it reports them final, then completes
+/// them; executions that fail, or are still pending after MAX_PENDING_SECS, mark the
+/// payment Failed instead.
+#[derive(Clone)]
+pub struct PaymentFinalityService {
+    mongodb: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+    push_service: web::Data<PushService>,
+    shared_state: web::Data<SharedState>,
+}
+
+impl PaymentFinalityService {
+    pub fn new(
+        mongodb: web::Data<MongoDBService>,
+        wallet_service: web::Data<WalletService>,
+        push_service: web::Data<PushService>,
+        shared_state: web::Data<SharedState>,
+    ) -> Self {
+        Self { mongodb, wallet_service, push_service, shared_state }
+    }
+
+    pub async fn check_submitted_payments(&self) {
+        let payments = match self.mongodb.get_submitted_payments(BATCH_SIZE).await {
+            Ok(payments) => payments,
+            Err(e) => {
+                error!("Failed to load submitted payments: {}", e);
+                return;
+            }
+        };
+
+        for payment in payments {
+            let Some(tx_id) = payment.executor_tx_id.clone() else {
+                self.check_unconfirmed_capture(&payment).await;
+                continue;
+            };
+            match self.wallet_service.get_execution_status(&tx_id).await {
+                Ok(ExecutionStatus::Finalized) => self.complete(&payment).await,
+                Ok(ExecutionStatus::Failed(reason)) => self.fail(&payment, &reason).await,
+                Ok(ExecutionStatus::Pending) => {
+                    let waited = chrono::Utc::now().timestamp() - payment.submitted_at.unwrap_or(payment.created_at);
+                    if waited > MAX_PENDING_SECS {
+                        self.fail(&payment, "The executor didn't finalize the transaction within 24 hours").await;
+                    } else if waited > STUCK_AFTER_SECS {
+                        warn!("Payment {} still pending on executor tx {} after {}s", payment.payment_id, tx_id, waited);
+                    }
+                }
+                // Executor unreachable; try again next run
+                Err(e) => error!("Failed to get executor status of {} for payment {}: {}", tx_id, payment.payment_id, e),
+            }
+        }
+    }
+
+    /// A capture the executor didn't answer for has no transaction to follow. It landed once
+    /// the payer's nonce reaches the held allowances', which nothing else can take while the
+    /// capture is pending; if it hasn't after STUCK_AFTER_SECS it's failed.
+    async fn check_unconfirmed_capture(&self, payment: &Payment) {
+        let capture_nonce = payment.authorization.as_ref().and_then(|authorization| authorization.capture_nonce);
+        let payer = payment.customer_address.as_deref().and_then(|address| WalletService::parse_public_key(address).ok());
+        let (Some(capture_nonce), Some(payer)) = (capture_nonce, payer) else {
+            error!("Submitted payment {} has no executor transaction ID", payment.payment_id);
+            return;
+        };
+        match self.wallet_service.fetch_vault(&payer).await {
+            Ok(Some(vault)) if vault.nonce() as i64 >= capture_nonce => self.complete(payment).await,
+            Ok(_) => {
+                let waited = chrono::Utc::now().timestamp() - payment.submitted_at.unwrap_or(payment.created_at);
+                if waited > STUCK_AFTER_SECS {
+                    self.fail(payment, "The executor never applied the capture").await;
+                }
+            },
+            Err(e) => error!("Failed to get the payer's vault for unconfirmed capture of payment {}: {}", payment.payment_id, e),
+        }
+    }
+
+    async fn complete(&self, payment: &Payment) {
+        match self.mongodb.settle_submitted_payment(&payment.payment_id, PaymentStatus::Completed, None).await {
+            Ok(true) => {
+                info!("Payment {} final on executor, marked Completed", payment.payment_id);
+                let bundle = payment.computed_payment.clone().unwrap_or_default();
+                apply_completed_payment(&self.mongodb, payment, &bundle).await;
+                self.push_service.payment_received(payment).await;
+                self.shared_state.publish(SharedEvent::PaymentStatus { payment_id: payment.payment_id.clone(), status: PaymentStatus::Completed });
+            }
+            Ok(false) => info!("Payment {} already settled", payment.payment_id),
+            Err(e) => error!("Failed to complete payment {}: {}", payment.payment_id, e),
+        }
+    }
+
+    /// Completion side effects are only applied after finality, so a failed execution only
+    /// gives back what signing took (discount budgets, promo code use, reward points): the payment is marked Failed
+    /// so the vendor can request a new one, and the failure is audited for follow-up with the payer.
+    async fn fail(&self, payment: &Payment, reason: &str) {
+        match self.mongodb.settle_submitted_payment(&payment.payment_id, PaymentStatus::Failed, Some(reason)).await {
+            Ok(true) => {
+                warn!("Executor failed payment {}: {}", payment.payment_id, reason);
+                self.shared_state.publish(SharedEvent::PaymentStatus { payment_id: payment.payment_id.clone(), status: PaymentStatus::Failed });
+                release_signing_holds(&self.mongodb, payment).await;
+                let mut after = payment.clone();
+                after.status = PaymentStatus::Failed;
+                after.failure_reason = Some(reason.to_string());
+                let audit = AuditLog::new(
+                    "system",
+                    AuditAction::PaymentFailed,
+                    "payment",
+                    &payment.payment_id,
+                    snapshot(payment),
+                    snapshot(&after),
+                );
+                if let Err(e) = self.mongodb.record_audit_log(audit).await {
+                    error!("Failed to record audit log for failed payment {}: {}", payment.payment_id, e);
+                }
+            }
+            Ok(false) => info!("Payment {} already settled", payment.payment_id),
+            Err(e) => error!("Failed to mark payment {} failed: {}", payment.payment_id, e),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use log::{info, error};
+use stripe::{CreatePaymentIntent, CreatePaymentIntentAutomaticPaymentMethods, CreatePaymentIntentTransferData, Currency, Metadata, PaymentIntent, PaymentIntentConfirmParams, PaymentIntentId, PaymentIntentSetupFutureUsage, PaymentMethodConfigurationId, PaymentMethodId, UpdatePaymentIntent};
+use crate::config::PaymentMethodConfig;
+use crate::handlers::purchase_webhook_handlers::EMBEDDED_PAYMENT_FLOW;
+use crate::models::{ApiError, DonationAttribution};
+use crate::models::cause::Cause;
+use crate::services::{StripeApi, StripeCustomerService};
+
+/// Smallest and largest amounts accepted, matching hosted Checkout donations
+const MIN_AMOUNT_CENTS: i64 = 100;
+const MAX_AMOUNT_CENTS: i64 = 999999;
+
+/// PaymentIntents for partners embedding a card form instead of redirecting to
+/// hosted Checkout. Tokens are credited by the purchases webhook on
+/// `payment_intent.succeeded`, using the metadata set here.
+pub struct PaymentIntentService {
+    stripe: Arc<dyn StripeApi>,
+    payment_methods: PaymentMethodConfig,
+    customer_service: Arc<StripeCustomerService>,
+}
+
+impl PaymentIntentService {
+    pub fn new(stripe: Arc<dyn StripeApi>, payment_methods: PaymentMethodConfig, customer_service: Arc<StripeCustomerService>) -> Self {
+        Self { stripe, payment_methods, customer_service }
+    }
+
+    /// Donation to a cause as a destination charge, keeping the 5% platform fee
+    pub async fn create_donation_intent(&self, cause: &Cause, amount_cents: i64, user_wallet_address: &str, wallet_signed: bool, attribution: &DonationAttribution) -> Result<PaymentIntent, ApiError> {
+        validate_amount(amount_cents)?;
+        let connected_account_id = cause.stripe_account_id.as_ref()
+            .ok_or_else(|| ApiError::ValidationError("This cause does not have a connected Stripe account".to_string()))?;
+        let platform_fee = (amount_cents as f64 * 0.05).round() as i64;
+        let description = format!("Donation to {}", cause.name);
+
+        let mut params = CreatePaymentIntent::new(amount_cents, Currency::USD);
+        params.automatic_payment_methods = Some(CreatePaymentIntentAutomaticPaymentMethods { allow_redirects: None, enabled: true });
+        params.application_fee_amount = Some(platform_fee);
+        params.transfer_data = Some(CreatePaymentIntentTransferData {
+            amount: None, // Transfer full amount minus application fee
+            destination: connected_account_id.clone(),
+        });
+        params.description = Some(&description);
+        let mut metadata = Metadata::from([
+            ("flow".to_string(), EMBEDDED_PAYMENT_FLOW.to_string()),
+            ("cause_id".to_string(), cause.id.map(|id| id.to_hex()).unwrap_or_default()),
+            ("cause_name".to_string(), cause.name.clone()),
+            ("token_name".to_string(), cause.token_name.clone()),
+            ("token_symbol".to_string(), cause.token_symbol.clone()),
+            ("user_wallet_address".to_string(), user_wallet_address.to_string()),
+            ("connected_account_id".to_string(), connected_account_id.clone()),
+            ("platform_fee".to_string(), platform_fee.to_string()),
+        ]);
+        metadata.extend(attribution.to_metadata());
+        params.metadata = Some(metadata);
+
+        self.create(params, user_wallet_address, wallet_signed).await
+    }
+
+    /// USD top-up credited 1:1 to the wallet
+    pub async fn create_topup_intent(&self, amount_cents: i64, user_wallet_address: &str, wallet_signed: bool) -> Result<PaymentIntent, ApiError> {
+        validate_amount(amount_cents)?;
+
+        let mut params = CreatePaymentIntent::new(amount_cents, Currency::USD);
+        params.automatic_payment_methods = Some(CreatePaymentIntentAutomaticPaymentMethods { allow_redirects: None, enabled: true });
+        params.description = Some("USD top-up");
+        params.metadata = Some([
+            ("flow".to_string(), EMBEDDED_PAYMENT_FLOW.to_string()),
+            ("token_name".to_string(), "US Dollar".to_string()),
+            ("token_symbol".to_string(), "USD".to_string()),
+            ("user_wallet_address".to_string(), user_wallet_address.to_string()),
+        ].into());
+
+        self.create(params, user_wallet_address, wallet_signed).await
+    }
+
+    pub async fn retrieve(&self, payment_intent_id: &str) -> Result<PaymentIntent, ApiError> {
+        let id = PaymentIntentId::from_str(payment_intent_id)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid PaymentIntent ID: {}", e)))?;
+        self.stripe.retrieve_payment_intent(&id)
+            .await
+            .map_err(|e| ApiError::NotFound(format!("PaymentIntent {} not found: {}", payment_intent_id, e)))
+    }
+
+    /// Attach a payment method collected by the embedded form and confirm server-side.
+    /// The returned intent may need a client-side next action (e.g. 3-D Secure).
+    pub async fn confirm(&self, intent: &PaymentIntent, payment_method_id: &str, return_url: Option<&str>) -> Result<PaymentIntent, ApiError> {
+        if intent.metadata.get("flow").map(String::as_str) != Some(EMBEDDED_PAYMENT_FLOW) {
+            return Err(ApiError::ValidationError("PaymentIntent was not created for an embedded payment".to_string()));
+        }
+        let payment_method = PaymentMethodId::from_str(payment_method_id)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid payment method ID: {}", e)))?;
+
+        let mut update = UpdatePaymentIntent::new();
+        update.payment_method = Some(payment_method);
+        self.stripe.update_payment_intent(&intent.id, update)
+            .await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+
+        let params = PaymentIntentConfirmParams {
+            return_url,
+            ..Default::default()
+        };
+        let confirmed = self.stripe.confirm_payment_intent(&intent.id, params)
+            .await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        info!("Confirmed PaymentIntent {} (status {:?})", confirmed.id, confirmed.status);
+        Ok(confirmed)
+    }
+
+    /// The wallet's saved Stripe customer is only attached when `wallet_signed` says the
+    /// wallet signed the request
+    async fn create(&self, mut params: CreatePaymentIntent<'_>, user_wallet_address: &str, wallet_signed: bool) -> Result<PaymentIntent, ApiError> {
+        // On the wallet's customer, saved cards can be confirmed directly (one-click) and new ones are kept
+        let customer = match wallet_signed {
+            true => self.customer_service.customer_for_payment(user_wallet_address).await,
+            false => None,
+        };
+        if let Some(customer) = customer {
+            params.customer = Some(customer);
+            params.setup_future_usage = Some(PaymentIntentSetupFutureUsage::OnSession);
+        }
+        // Same dashboard configuration as hosted Checkout, so the Payment Element shows the same wallets
+        if let Some(configuration) = &self.payment_methods.payment_method_configuration {
+            params.payment_method_configuration = Some(
+                PaymentMethodConfigurationId::from_str(configuration)
+                    .map_err(|e| ApiError::InternalError(format!("Invalid STRIPE_PAYMENT_METHOD_CONFIGURATION: {}", e)))?
+            );
+        }
+        match self.stripe.create_payment_intent(params).await {
+            Ok(intent) => {
+                info!("Created PaymentIntent {} for {} cents", intent.id, intent.amount);
+                Ok(intent)
+            },
+            Err(e) => {
+                error!("Failed to create PaymentIntent: {}", e);
+                Err(ApiError::StripeError(e.to_string()))
+            }
+        }
+    }
+}
+
+fn validate_amount(amount_cents: i64) -> Result<(), ApiError> {
+    if amount_cents < MIN_AMOUNT_CENTS {
+        return Err(ApiError::ValidationError("Minimum amount is $1.00".to_string()));
+    }
+    if amount_cents > MAX_AMOUNT_CENTS {
+        return Err(ApiError::ValidationError("Maximum amount is $9,999.99".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use actix_web::web;
+use log::{info, warn, error};
+use mongodb::bson::{doc, oid::ObjectId};
+use crate::models::{
+    ApiError, User, Payment, PaymentStatus, PaymentSchedule, ScheduleStatus, CreatePaymentScheduleRequest,
+    UpdatePaymentScheduleRequest, MAX_PAYMENT_SCHEDULES,
+};
+use crate::models::payment::PaymentState;
+use crate::services::{MongoDBService, PushService};
+use crate::utils::recurrence::next_occurrence;
+
+/// Schedules run per tick
+const BATCH_SIZE: i64 = 100;
+
+/// Longest note a customer can attach
+const MAX_NOTE_CHARS: usize = 140;
+
+/// Runs customers' payment schedules. When a run comes due a payment code is created with
+/// the customer assigned and they're notified to supplement and sign it. Payments aren't
+/// executed without them: a debit allowance is signed against the payer vault's current
+/// state, so one can't be signed in advance for a later run.
+#[derive(Clone)]
+pub struct PaymentScheduleService {
+    mongodb: web::Data<MongoDBService>,
+    push_service: web::Data<PushService>,
+}
+
+impl PaymentScheduleService {
+    pub fn new(mongodb: web::Data<MongoDBService>, push_service: web::Data<PushService>) -> Self {
+        Self { mongodb, push_service }
+    }
+
+    /// Start paying `request.vendor_address` on a schedule. A start in the past runs straight away.
+    pub async fn create(&self, customer_address: &str, request: &CreatePaymentScheduleRequest) -> Result<PaymentSchedule, ApiError> {
+        check_amount(request.amount_usd)?;
+        let note = check_note(request.note.as_deref())?;
+        if request.vendor_address == customer_address {
+            return Err(ApiError::ValidationError("Cannot schedule a payment to yourself".to_string()));
+        }
+        let now = chrono::Utc::now().timestamp();
+        let start_at = request.start_at.unwrap_or(now).max(now);
+        if request.ends_at.map_or(false, |ends_at| ends_at < start_at) {
+            return Err(ApiError::ValidationError("ends_at must be after start_at".to_string()));
+        }
+
+        active_user(&self.mongodb, customer_address).await?;
+        let vendor = active_user(&self.mongodb, &request.vendor_address).await?;
+        if self.mongodb.count_open_payment_schedules(customer_address).await? >= MAX_PAYMENT_SCHEDULES {
+            return Err(ApiError::Conflict(format!("You can have at most {} payment schedules", MAX_PAYMENT_SCHEDULES)));
+        }
+
+        let schedule = self.mongodb.create_payment_schedule(PaymentSchedule {
+            id: None,
+            customer_address: customer_address.to_string(),
+            vendor_address: vendor.wallet_address,
+            vendor_name: vendor.username,
+            amount_usd: request.amount_usd,
+            note,
+            frequency: request.frequency,
+            status: ScheduleStatus::Active,
+            anchor_at: start_at,
+            occurrence: 0,
+            next_run_at: start_at,
+            ends_at: request.ends_at,
+            payments_created: 0,
+            last_run_at: None,
+            last_payment_id: None,
+            created_at: now,
+            updated_at: now,
+        }).await?;
+        info!("Payment schedule {:?}: ${} {} from {} to {}", schedule.id, schedule.amount_usd, schedule.frequency, customer_address, schedule.vendor_address);
+        Ok(schedule)
+    }
+
+    /// Change an active or paused schedule. Runs missed while it was paused are skipped.
+    pub async fn update(&self, schedule: &PaymentSchedule, request: &UpdatePaymentScheduleRequest) -> Result<PaymentSchedule, ApiError> {
+        let id = schedule_id(schedule)?;
+        if !matches!(schedule.status, ScheduleStatus::Active | ScheduleStatus::Paused) {
+            return Err(ApiError::Conflict(format!("Schedule is already {}", schedule.status)));
+        }
+        let now = chrono::Utc::now().timestamp();
+        let mut set = doc! {};
+        if let Some(amount_usd) = request.amount_usd {
+            check_amount(amount_usd)?;
+            set.insert("amount_usd", amount_usd);
+        }
+        if request.note.is_some() {
+            set.insert("note", check_note(request.note.as_deref())?);
+        }
+
+        let ends_at = request.ends_at.or(schedule.ends_at);
+        let (mut anchor_at, mut occurrence, mut next_run_at) = (schedule.anchor_at, schedule.occurrence, schedule.next_run_at);
+        if let Some(start_at) = request.start_at {
+            (anchor_at, occurrence, next_run_at) = (start_at.max(now), 0, start_at.max(now));
+        }
+        let paused = request.paused.unwrap_or(schedule.status == ScheduleStatus::Paused);
+        let mut to = if paused { ScheduleStatus::Paused } else { ScheduleStatus::Active };
+        if !paused && next_run_at < now && schedule.status == ScheduleStatus::Paused {
+            match next_occurrence(schedule.frequency, anchor_at, occurrence, now - 1, ends_at) {
+                Some((n, at)) => (occurrence, next_run_at) = (n, at),
+                None => to = ScheduleStatus::Completed,
+            }
+        }
+        if to != ScheduleStatus::Completed && ends_at.map_or(false, |ends_at| next_run_at > ends_at) {
+            return Err(ApiError::ValidationError("ends_at is before the next run, cancel the schedule instead".to_string()));
+        }
+        set.insert("anchor_at", anchor_at);
+        set.insert("occurrence", occurrence as i64);
+        set.insert("next_run_at", next_run_at);
+        set.insert("ends_at", ends_at);
+
+        self.mongodb.transition_payment_schedule(&id, &[schedule.status.clone()], to, set).await?
+            .ok_or_else(|| ApiError::Conflict("Schedule was updated, please reload it".to_string()))
+    }
+
+    /// Stop a schedule for good. A payment code already made for a run can still be paid.
+    pub async fn cancel(&self, schedule: &PaymentSchedule) -> Result<PaymentSchedule, ApiError> {
+        let id = schedule_id(schedule)?;
+        let cancelled = self.mongodb.transition_payment_schedule(&id, &[ScheduleStatus::Active, ScheduleStatus::Paused], ScheduleStatus::Cancelled, doc! {}).await?
+            .ok_or_else(|| ApiError::Conflict(format!("Schedule is already {}", schedule.status)))?;
+        info!("Payment schedule {} cancelled", id);
+        Ok(cancelled)
+    }
+
+    /// The payment code for the latest run, for `payer_address` to supplement and sign.
+    /// A code still open for another of the customer's wallets is handed over to this one,
+    /// so a run only ever has one payable code. Codes expire after an hour, so one that
+    /// expired unpaid is replaced.
+    pub async fn pay(&self, schedule: &PaymentSchedule, payer_address: &str) -> Result<String, ApiError> {
+        let payment_id = schedule.last_payment_id.as_deref()
+            .ok_or_else(|| ApiError::Conflict("No payment is due on this schedule yet".to_string()))?;
+        if let Some(payment) = self.mongodb.get_payment(payment_id).await? {
+            match payment.state(chrono::Utc::now().timestamp()) {
+                PaymentState::Completed => return Err(ApiError::Conflict("The latest scheduled payment is already paid".to_string())),
+                PaymentState::Processing => return Ok(payment.payment_id),
+                PaymentState::Active => match payment.customer_address.as_deref() {
+                    Some(customer) if customer == payer_address => return Ok(payment.payment_id),
+                    Some(customer) => {
+                        let payer = self.mongodb.get_user_by_wallet(payer_address).await?;
+                        if !self.mongodb.reassign_payment_customer(&payment.payment_id, customer, payer_address, payer.map(|payer| payer.username)).await? {
+                            return Err(ApiError::Conflict("The latest scheduled payment is being paid from another wallet".to_string()));
+                        }
+                        info!("Scheduled payment {} moved from {} to {}", payment.payment_id, customer, payer_address);
+                        return Ok(payment.payment_id);
+                    },
+                    None => {},
+                },
+                _ => {},
+            }
+        }
+        self.issue_payment(schedule, payer_address).await
+    }
+
+    pub async fn run_due(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let schedules = match self.mongodb.get_due_payment_schedules(now, BATCH_SIZE).await {
+            Ok(schedules) => schedules,
+            Err(e) => {
+                error!("Failed to load due payment schedules: {}", e);
+                return;
+            }
+        };
+        for schedule in schedules {
+            if let Err(e) = self.run(&schedule, now).await {
+                warn!("Failed to run payment schedule {:?}: {}", schedule.id, e);
+            }
+        }
+    }
+
+    /// Claim a due run, moving the schedule on to its next run after `now` (skipping any
+    /// missed while the scheduler was down), then make the run's payment code
+    async fn run(&self, schedule: &PaymentSchedule, now: i64) -> Result<(), ApiError> {
+        let id = schedule_id(schedule)?;
+        let mut set = doc! { "last_run_at": now };
+        match next_occurrence(schedule.frequency, schedule.anchor_at, schedule.occurrence + 1, now, schedule.ends_at) {
+            Some((occurrence, next_run_at)) => {
+                set.insert("occurrence", occurrence as i64);
+                set.insert("next_run_at", next_run_at);
+            },
+            None => {
+                set.insert("status", ScheduleStatus::Completed.to_string());
+            },
+        }
+        let Some(claimed) = self.mongodb.claim_payment_schedule_run(&id, schedule.next_run_at, set).await? else {
+            return Ok(());
+        };
+
+        let payment_id = self.issue_payment(&claimed, &claimed.customer_address).await?;
+        info!("Payment schedule {} ran: payment {} for ${}", id, payment_id, claimed.amount_usd);
+        self.push_service.scheduled_payment_due(&claimed, &payment_id).await;
+        Ok(())
+    }
+
+    async fn issue_payment(&self, schedule: &PaymentSchedule, payer_address: &str) -> Result<String, ApiError> {
+        let id = schedule_id(schedule)?;
+        let vendor = self.mongodb.get_user_by_wallet(&schedule.vendor_address).await?;
+        let payer = self.mongodb.get_user_by_wallet(payer_address).await?;
+        let payment_id = self.mongodb.generate_payment_id();
+        self.mongodb.create_payment(Payment {
+            id: None,
+            payment_id: payment_id.clone(),
+            vendor_address: schedule.vendor_address.clone(),
+            vendor_name: schedule.vendor_name.clone(),
+            price_usd: schedule.amount_usd,
+            customer_address: Some(payer_address.to_string()),
+            customer_username: payer.map(|payer| payer.username),
+            status: PaymentStatus::Created,
+            created_at: chrono::Utc::now().timestamp(),
+            vendor_valuations: None,
+            discount_consumption: None,
+            computed_payment: None,
+            initial_payment_bundle: None,
+            recepient_verified: vendor.map_or(false, |vendor| vendor.is_verified),
+            executor_tx_id: None,
+            submitted_at: None,
+            finality_checked_at: None,
+            failure_reason: None,
+            payment_request_id: None,
+            loyalty_redemption: None,
+            loyalty_points_earned: None,
+            promo: None,
+            escrow: None,
+            schedule_id: Some(id.to_hex()),
+            batch_id: None,
+            invoice_id: None,
+            valuation_overrides: None,
+            budget_consumed: false,
+            splits: Vec::new(),
+            split_legs: Vec::new(),
+            authorization: None,
+            vendor_slug: None,
+            short_code: None,
+        }).await?;
+        self.mongodb.set_schedule_payment(&id, &payment_id).await?;
+        Ok(payment_id)
+    }
+}
+
+fn check_amount(amount_usd: f64) -> Result<(), ApiError> {
+    if !amount_usd.is_finite() || amount_usd <= 0.0 {
+        return Err(ApiError::ValidationError("Amount must be greater than zero".to_string()));
+    }
+    Ok(())
+}
+
+/// Trimmed note, None when blank
+fn check_note(note: Option<&str>) -> Result<Option<String>, ApiError> {
+    let note = note.map(str::trim).filter(|n| !n.is_empty());
+    if note.map_or(false, |n| n.chars().count() > MAX_NOTE_CHARS) {
+        return Err(ApiError::ValidationError(format!("Note must be at most {} characters", MAX_NOTE_CHARS)));
+    }
+    Ok(note.map(str::to_string))
+}
+
+fn schedule_id(schedule: &PaymentSchedule) -> Result<ObjectId, ApiError> {
+    schedule.id.ok_or_else(|| ApiError::InternalError("Payment schedule has no ID".to_string()))
+}
+
+async fn active_user(db: &MongoDBService, wallet_address: &str) -> Result<User, ApiError> {
+    db.get_user_by_wallet(wallet_address).await?
+        .filter(|user| user.deleted_at.is_none())
+        .ok_or_else(|| ApiError::NotFound(format!("User with wallet address {} not found", wallet_address)))
+}
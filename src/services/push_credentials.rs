@@ -0,0 +1,224 @@
+use std::env;
+use std::time::{Duration, Instant};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+/// Google access tokens are used until this long before they expire
+const FCM_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+/// APNs rejects provider tokens older than an hour, and ones replaced more often than every
+/// 20 minutes
+const APNS_TOKEN_LIFETIME: Duration = Duration::from_secs(50 * 60);
+
+struct CachedToken {
+    token: String,
+    refresh_at: Instant,
+}
+
+/// The parts of a Google service account key file FCM needs
+#[derive(Deserialize)]
+struct ServiceAccount {
+    project_id: String,
+    client_email: String,
+    private_key: String,
+    #[serde(default = "default_token_uri")]
+    token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+#[derive(Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// FCM HTTP v1 credentials: OAuth access tokens minted from a service account key and
+/// exchanged again before they expire
+pub struct FcmCredentials {
+    pub api_url: String,
+    client_email: String,
+    token_uri: String,
+    key: PKey<Private>,
+    cached: tokio::sync::Mutex<Option<CachedToken>>,
+}
+
+impl FcmCredentials {
+    /// Reads the service account key file at FCM_SERVICE_ACCOUNT_FILE. The send URL is the
+    /// project's, unless FCM_API_URL overrides it. None when unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(path) = env::var("FCM_SERVICE_ACCOUNT_FILE").ok().filter(|path| !path.is_empty()) else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(&path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        let account: ServiceAccount = serde_json::from_str(&contents).map_err(|e| format!("Invalid service account key {}: {}", path, e))?;
+        let key = PKey::private_key_from_pem(account.private_key.as_bytes()).map_err(|e| format!("Invalid service account private key: {}", e))?;
+        let api_url = env::var("FCM_API_URL").ok().filter(|url| !url.is_empty())
+            .unwrap_or_else(|| format!("https://fcm.googleapis.com/v1/projects/{}/messages:send", account.project_id));
+        Ok(Some(Self {
+            api_url,
+            client_email: account.client_email,
+            token_uri: account.token_uri,
+            key,
+            cached: tokio::sync::Mutex::new(None),
+        }))
+    }
+
+    /// A current access token, exchanging a new one if the last is about to expire
+    pub async fn access_token(&self, client: &Client) -> Result<String, String> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref().filter(|token| Instant::now() < token.refresh_at) {
+            return Ok(token.token.clone());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = json!({ "iss": self.client_email, "scope": FCM_SCOPE, "aud": self.token_uri, "iat": now, "exp": now + 3600 });
+        let assertion = jwt(&json!({ "alg": "RS256", "typ": "JWT" }), &claims, |message| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+            signer.update(message)?;
+            signer.sign_to_vec()
+        })?;
+        let response = client
+            .post(&self.token_uri)
+            .form(&[("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())])
+            .send()
+            .await
+            .map_err(|e| format!("Access token request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Access token request failed: {}: {}", status, body));
+        }
+        let granted: AccessTokenResponse = response.json().await.map_err(|e| format!("Invalid access token response: {}", e))?;
+        let lifetime = Duration::from_secs(granted.expires_in).saturating_sub(FCM_REFRESH_MARGIN);
+        *cached = Some(CachedToken { token: granted.access_token.clone(), refresh_at: Instant::now() + lifetime });
+        Ok(granted.access_token)
+    }
+
+    /// Forget the access token after FCM refuses it, so the next send exchanges a new one
+    pub async fn invalidate(&self) {
+        *self.cached.lock().await = None;
+    }
+}
+
+/// APNs token-based credentials: provider JWTs signed with the team's .p8 key, made again
+/// before APNs would refuse them as expired
+pub struct ApnsCredentials {
+    pub api_url: String,
+    pub topic: String,
+    key_id: String,
+    team_id: String,
+    key: PKey<Private>,
+    cached: std::sync::Mutex<Option<CachedToken>>,
+}
+
+impl ApnsCredentials {
+    /// Reads APNS_KEY_FILE (the .p8 signing key), APNS_KEY_ID, APNS_TEAM_ID and APNS_TOPIC,
+    /// and APNS_API_URL (default https://api.push.apple.com). None when any is unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let (Some(path), Some(key_id), Some(team_id), Some(topic)) = (var("APNS_KEY_FILE"), var("APNS_KEY_ID"), var("APNS_TEAM_ID"), var("APNS_TOPIC")) else {
+            return Ok(None);
+        };
+        let pem = std::fs::read(&path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        let key = PKey::private_key_from_pem(&pem).map_err(|e| format!("Invalid APNs signing key {}: {}", path, e))?;
+        Ok(Some(Self {
+            api_url: var("APNS_API_URL").unwrap_or_else(|| "https://api.push.apple.com".to_string()),
+            topic,
+            key_id,
+            team_id,
+            key,
+            cached: std::sync::Mutex::new(None),
+        }))
+    }
+
+    /// A current provider token, signing a new one once the last is old enough to replace
+    pub fn provider_token(&self) -> Result<String, String> {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(token) = cached.as_ref().filter(|token| Instant::now() < token.refresh_at) {
+            return Ok(token.token.clone());
+        }
+
+        let claims = json!({ "iss": self.team_id, "iat": chrono::Utc::now().timestamp() });
+        let token = jwt(&json!({ "alg": "ES256", "kid": self.key_id }), &claims, |message| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+            signer.update(message)?;
+            // JWS wants the raw r and s, not OpenSSL's DER
+            let signature = EcdsaSig::from_der(&signer.sign_to_vec()?)?;
+            let mut raw = signature.r().to_vec_padded(32)?;
+            raw.extend(signature.s().to_vec_padded(32)?);
+            Ok(raw)
+        })?;
+        *cached = Some(CachedToken { token: token.clone(), refresh_at: Instant::now() + APNS_TOKEN_LIFETIME });
+        Ok(token)
+    }
+
+    /// Forget the provider token after APNs refuses it, so the next send signs a new one
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// A compact JWS of `claims`, signed by `sign` over the encoded header and claims
+fn jwt<F>(header: &serde_json::Value, claims: &serde_json::Value, sign: F) -> Result<String, String>
+where
+    F: FnOnce(&[u8]) -> Result<Vec<u8>, openssl::error::ErrorStack>,
+{
+    let signing_input = format!("{}.{}", BASE64URL.encode(header.to_string()), BASE64URL.encode(claims.to_string()));
+    let signature = sign(signing_input.as_bytes()).map_err(|e| format!("Failed to sign token: {}", e))?;
+    Ok(format!("{}.{}", signing_input, BASE64URL.encode(signature)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::sign::Verifier;
+
+    #[test]
+    fn test_apns_token_is_an_es256_jws() {
+        let ec_key = EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap();
+        let key = PKey::from_ec_key(ec_key.clone()).unwrap();
+        let credentials = ApnsCredentials {
+            api_url: "https://api.push.apple.com".to_string(),
+            topic: "org.example.app".to_string(),
+            key_id: "KEY123".to_string(),
+            team_id: "TEAM456".to_string(),
+            key,
+            cached: std::sync::Mutex::new(None),
+        };
+
+        let token = credentials.provider_token().unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        let header: serde_json::Value = serde_json::from_slice(&BASE64URL.decode(parts[0]).unwrap()).unwrap();
+        assert_eq!(header, json!({ "alg": "ES256", "kid": "KEY123" }));
+        let claims: serde_json::Value = serde_json::from_slice(&BASE64URL.decode(parts[1]).unwrap()).unwrap();
+        assert_eq!(claims["iss"], "TEAM456");
+
+        let raw = BASE64URL.decode(parts[2]).unwrap();
+        assert_eq!(raw.len(), 64);
+        let signature = EcdsaSig::from_private_components(
+            openssl::bn::BigNum::from_slice(&raw[..32]).unwrap(),
+            openssl::bn::BigNum::from_slice(&raw[32..]).unwrap(),
+        ).unwrap();
+        let public = PKey::from_ec_key(EcKey::from_public_key(ec_key.group(), ec_key.public_key()).unwrap()).unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public).unwrap();
+        verifier.update(format!("{}.{}", parts[0], parts[1]).as_bytes()).unwrap();
+        assert!(verifier.verify(&signature.to_der().unwrap()).unwrap());
+
+        // Reused until it's due for replacement, and made again once refused
+        assert_eq!(credentials.provider_token().unwrap(), token);
+        credentials.invalidate();
+        assert!(credentials.cached.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::web;
+use futures_util::future::join_all;
+use log::{info, warn, error};
+use reqwest::{Client, StatusCode};
+use serde_json::json;
+use uuid::Uuid;
+use crate::models::{DevicePlatform, DeviceToken, DepositRecord, NotificationEvent, Payment, PaymentRequest, PaymentRequestStatus, PushNotification, Dispute, DisputeStatus, PaymentSchedule, Invoice};
+use crate::services::push_credentials::{ApnsCredentials, FcmCredentials};
+use crate::services::{EmailService, MongoDBService};
+use crate::utils::redaction::redact;
+use crate::utils::retry::jittered_backoff;
+
+/// Most notifications taken off the queue per flush
+const BATCH_SIZE: i64 = 100;
+/// Attempts per device before a notification is dropped
+const MAX_ATTEMPTS: u32 = 3;
+/// How long a replica holds the batch it's delivering; after that it's taken to have
+/// stopped and the batch is delivered again
+const LEASE_SECS: i64 = 5 * 60;
+
+enum SendOutcome {
+    Sent,
+    Unregistered,  // the provider says the token is gone; stop using it
+    Unauthorized(String),  // the provider refused our credentials; get new ones and retry
+    Retry(String),
+    Failed(String),
+}
+
+/// Queues notifications in MongoDB and delivers them in batches to every registered device
+/// of a wallet, and to the user's email if they gave one, skipping channels and events the
+/// user turned off. Failed pushes are retried with backoff; tokens the provider rejects
+/// as unregistered are deleted. Provider credentials are renewed before they expire, and
+/// again whenever the provider refuses them.
+#[derive(Clone)]
+pub struct PushService {
+    mongodb: web::Data<MongoDBService>,
+    email_service: web::Data<EmailService>,
+    client: Client,
+    fcm: Option<Arc<FcmCredentials>>,
+    apns: Option<Arc<ApnsCredentials>>,
+}
+
+impl PushService {
+    /// Reads FCM_SERVICE_ACCOUNT_FILE for Android, and APNS_KEY_FILE, APNS_KEY_ID,
+    /// APNS_TEAM_ID and APNS_TOPIC for iOS (see `push_credentials`). A platform without
+    /// credentials has its notifications logged and dropped; unreadable credentials are
+    /// a startup error.
+    pub fn new(mongodb: web::Data<MongoDBService>, email_service: web::Data<EmailService>, client: Client) -> Result<Self, String> {
+        let fcm = FcmCredentials::from_env()?.map(Arc::new);
+        if fcm.is_none() {
+            warn!("FCM_SERVICE_ACCOUNT_FILE not set - Android push notifications will be logged instead of sent");
+        }
+        let apns = ApnsCredentials::from_env()?.map(Arc::new);
+        if apns.is_none() {
+            warn!("APNS_KEY_FILE/APNS_KEY_ID/APNS_TEAM_ID/APNS_TOPIC not set - iOS push notifications will be logged instead of sent");
+        }
+
+        Ok(Self {
+            mongodb,
+            email_service,
+            client,
+            fcm,
+            apns,
+        })
+    }
+
+    /// Queue a notification for a wallet's devices. Doesn't wait for delivery.
+    pub async fn notify(&self, wallet_address: &str, event: NotificationEvent, title: &str, body: &str, data: HashMap<String, String>) {
+        let notification = PushNotification {
+            id: None,
+            wallet_address: wallet_address.to_string(),
+            event,
+            title: title.to_string(),
+            body: body.to_string(),
+            data,
+            queued_at: chrono::Utc::now(),
+            lease: None,
+            lease_expires_at: 0,
+        };
+        if let Err(e) = self.mongodb.queue_push_notification(&notification).await {
+            error!("Failed to queue {} notification for {}: {}", event, redact(wallet_address), e);
+        }
+    }
+
+    /// Tell the recipient of a completed payment
+    pub async fn payment_received(&self, payment: &Payment) {
+        let from = payment.customer_username.as_deref().unwrap_or("Someone");
+        self.notify(
+            &payment.vendor_address,
+            NotificationEvent::PaymentReceived,
+            "Payment received",
+            &format!("{} paid you ${:.2}", from, payment.price_usd),
+            HashMap::from([("payment_id".to_string(), payment.payment_id.clone())]),
+        ).await;
+    }
+
+    pub async fn deposit_credited(&self, deposit: &DepositRecord) {
+        self.notify(
+            &deposit.wallet_address,
+            NotificationEvent::DepositCredited,
+            "Deposit credited",
+            &format!("{:.2} {} added to your wallet", deposit.amount_tokens_received, deposit.token_symbol),
+            HashMap::from([("token_symbol".to_string(), deposit.token_symbol.clone())]),
+        ).await;
+    }
+
+    /// Tell the payer about a new request
+    pub async fn payment_request_received(&self, request: &PaymentRequest) {
+        self.notify(
+            &request.payer_address,
+            NotificationEvent::PaymentRequestReceived,
+            "Payment request",
+            &format!("{} requested ${:.2}", request.requester_username, request.amount_usd),
+            Self::request_data(request),
+        ).await;
+    }
+
+    /// Tell the other side that a request was declined or cancelled
+    pub async fn payment_request_closed(&self, request: &PaymentRequest) {
+        let (recipient, body) = match request.status {
+            PaymentRequestStatus::Declined => (&request.requester_address, format!("Your request for ${:.2} was declined", request.amount_usd)),
+            PaymentRequestStatus::Cancelled => (&request.payer_address, format!("{} cancelled their request for ${:.2}", request.requester_username, request.amount_usd)),
+            _ => return,
+        };
+        self.notify(recipient, NotificationEvent::PaymentRequestUpdated, "Payment request", &body, Self::request_data(request)).await;
+    }
+
+    /// Tell the vendor about a new dispute, the customer about the vendor's response, and
+    /// both sides about the resolution
+    pub async fn dispute_updated(&self, dispute: &Dispute) {
+        let mut data = HashMap::from([
+            ("payment_id".to_string(), dispute.payment_id.clone()),
+            ("status".to_string(), dispute.status.to_string()),
+        ]);
+        if let Some(id) = &dispute.id {
+            data.insert("dispute_id".to_string(), id.to_hex());
+        }
+        let amount = format!("${:.2}", dispute.amount_usd);
+        let (recipients, body) = match dispute.status {
+            DisputeStatus::Open => (vec![&dispute.vendor_address], format!("A customer disputed a {} payment", amount)),
+            DisputeStatus::VendorResponded => (vec![&dispute.customer_address], format!("The vendor responded to your dispute of a {} payment", amount)),
+            DisputeStatus::ResolvedRefund => (vec![&dispute.customer_address, &dispute.vendor_address], format!("The dispute of a {} payment was resolved with a refund", amount)),
+            DisputeStatus::ResolvedUpheld => (vec![&dispute.customer_address, &dispute.vendor_address], format!("The dispute of a {} payment was resolved in the vendor's favour", amount)),
+        };
+        for recipient in recipients {
+            self.notify(recipient, NotificationEvent::DisputeUpdated, "Payment dispute", &body, data.clone()).await;
+        }
+    }
+
+    /// Ask the customer to sign the payment code made for a schedule's run
+    pub async fn scheduled_payment_due(&self, schedule: &PaymentSchedule, payment_id: &str) {
+        let mut data = HashMap::from([("payment_id".to_string(), payment_id.to_string())]);
+        if let Some(id) = &schedule.id {
+            data.insert("schedule_id".to_string(), id.to_hex());
+        }
+        self.notify(
+            &schedule.customer_address,
+            NotificationEvent::ScheduledPaymentDue,
+            "Scheduled payment",
+            &format!("Your ${:.2} payment to {} is ready to sign", schedule.amount_usd, schedule.vendor_name),
+            data,
+        ).await;
+    }
+
+    /// Tell the customer about a new invoice
+    pub async fn invoice_received(&self, invoice: &Invoice) {
+        self.notify(
+            &invoice.customer_address,
+            NotificationEvent::InvoiceReceived,
+            "New invoice",
+            &format!("{} sent you an invoice for ${:.2}", invoice.vendor_name, invoice.total_usd),
+            Self::invoice_data(invoice),
+        ).await;
+    }
+
+    /// Remind the customer of an invoice past its due date
+    pub async fn invoice_overdue(&self, invoice: &Invoice) {
+        self.notify(
+            &invoice.customer_address,
+            NotificationEvent::InvoiceOverdue,
+            "Invoice overdue",
+            &format!("Your ${:.2} invoice from {} is overdue", invoice.total_usd, invoice.vendor_name),
+            Self::invoice_data(invoice),
+        ).await;
+    }
+
+    fn invoice_data(invoice: &Invoice) -> HashMap<String, String> {
+        let mut data = HashMap::from([("status".to_string(), invoice.status.to_string())]);
+        if let Some(id) = &invoice.id {
+            data.insert("invoice_id".to_string(), id.to_hex());
+        }
+        data
+    }
+
+    fn request_data(request: &PaymentRequest) -> HashMap<String, String> {
+        let mut data = HashMap::from([("status".to_string(), request.status.to_string())]);
+        if let Some(id) = &request.id {
+            data.insert("payment_request_id".to_string(), id.to_hex());
+        }
+        data
+    }
+
+    /// Deliver queued notifications every `interval` in the background
+    pub fn start_scheduler(self, interval: Duration) {
+        info!("Scheduling push notification delivery every {:?}", interval);
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.flush().await;
+            }
+        });
+    }
+
+    /// Send up to one batch of queued notifications
+    pub async fn flush(&self) {
+        let lease = Uuid::new_v4().to_string();
+        let lease_expires_at = chrono::Utc::now().timestamp() + LEASE_SECS;
+        let batch = match self.mongodb.lease_push_notifications(&lease, lease_expires_at, BATCH_SIZE).await {
+            Ok(batch) => batch,
+            Err(e) => {
+                error!("Failed to load queued notifications: {}", e);
+                return;
+            },
+        };
+        if batch.is_empty() {
+            return;
+        }
+
+        let mut sends = Vec::new();
+        let mut emails = Vec::new();
+        for notification in &batch {
+            let user = match self.mongodb.get_user_by_wallet(&notification.wallet_address).await {
+                Ok(Some(user)) if user.deleted_at.is_none() => user,
+                Ok(_) => continue,
+                Err(e) => {
+                    error!("Failed to load user {} for notification: {}", redact(&notification.wallet_address), e);
+                    continue;
+                },
+            };
+            let preferences = &user.notification_preferences;
+            if preferences.allows_push(notification.event) {
+                for device in self.devices_for(notification).await {
+                    sends.push(self.deliver(notification, device));
+                }
+            }
+            if let Some(email) = user.email.clone().filter(|_| preferences.allows_email(notification.event)) {
+                emails.push(self.send_email(notification, email));
+            }
+        }
+        let sent = join_all(sends).await.into_iter().filter(|ok| *ok).count();
+        let emailed = join_all(emails).await.into_iter().filter(|ok| *ok).count();
+        info!("Notification batch: {} notifications, {} device deliveries, {} emails", batch.len(), sent, emailed);
+        if let Err(e) = self.mongodb.delete_push_notifications(&lease).await {
+            error!("Failed to remove delivered notifications: {}", e);
+        }
+    }
+
+    async fn devices_for(&self, notification: &PushNotification) -> Vec<DeviceToken> {
+        self.mongodb.get_device_tokens(&notification.wallet_address).await.unwrap_or_else(|e| {
+            error!("Failed to load device tokens for {}: {}", redact(&notification.wallet_address), e);
+            Vec::new()
+        })
+    }
+
+    async fn send_email(&self, notification: &PushNotification, to: String) -> bool {
+        match self.email_service.send(&to, &notification.title, &notification.body).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Email {} to {} failed: {}", notification.event, redact(&notification.wallet_address), e);
+                false
+            },
+        }
+    }
+
+    async fn deliver(&self, notification: &PushNotification, device: DeviceToken) -> bool {
+        for attempt in 0..MAX_ATTEMPTS {
+            let outcome = match device.platform {
+                DevicePlatform::Android => self.send_fcm(notification, &device.token).await,
+                DevicePlatform::Ios => self.send_apns(notification, &device.token).await,
+            };
+            match outcome {
+                SendOutcome::Sent => return true,
+                SendOutcome::Unregistered => {
+                    info!("Removing unregistered push token for {}", redact(&device.wallet_address));
+                    if let Err(e) = self.mongodb.delete_device_token(&device.token).await {
+                        error!("Failed to remove push token: {}", e);
+                    }
+                    return false;
+                },
+                SendOutcome::Failed(reason) => {
+                    warn!("Push {} to {} failed: {}", notification.event, redact(&device.wallet_address), reason);
+                    return false;
+                },
+                SendOutcome::Unauthorized(reason) if attempt + 1 < MAX_ATTEMPTS => {
+                    warn!("Push {} to {} refused our credentials ({}), renewing them", notification.event, redact(&device.wallet_address), reason);
+                    self.invalidate_credentials(device.platform).await;
+                },
+                SendOutcome::Unauthorized(reason) => {
+                    warn!("Push {} to {} refused our credentials after {} attempts: {}", notification.event, redact(&device.wallet_address), MAX_ATTEMPTS, reason);
+                },
+                SendOutcome::Retry(reason) if attempt + 1 < MAX_ATTEMPTS => {
+                    let delay = jittered_backoff(attempt, Duration::from_millis(500), Duration::from_secs(10), rand::random::<f64>());
+                    warn!("Push {} to {} failed ({}), retrying in {:?}", notification.event, redact(&device.wallet_address), reason, delay);
+                    actix_web::rt::time::sleep(delay).await;
+                },
+                SendOutcome::Retry(reason) => {
+                    warn!("Push {} to {} failed after {} attempts: {}", notification.event, redact(&device.wallet_address), MAX_ATTEMPTS, reason);
+                },
+            }
+        }
+        false
+    }
+
+    async fn invalidate_credentials(&self, platform: DevicePlatform) {
+        match platform {
+            DevicePlatform::Android => if let Some(fcm) = &self.fcm { fcm.invalidate().await },
+            DevicePlatform::Ios => if let Some(apns) = &self.apns { apns.invalidate() },
+        }
+    }
+
+    async fn send_fcm(&self, notification: &PushNotification, token: &str) -> SendOutcome {
+        let Some(fcm) = &self.fcm else {
+            info!("Push to {} not sent (no FCM credentials): {}", redact(&notification.wallet_address), notification.title);
+            return SendOutcome::Sent;
+        };
+        let access_token = match fcm.access_token(&self.client).await {
+            Ok(access_token) => access_token,
+            Err(e) => return SendOutcome::Retry(e),
+        };
+
+        let mut data = notification.data.clone();
+        data.insert("event".to_string(), notification.event.to_string());
+        let result = self.client
+            .post(&fcm.api_url)
+            .bearer_auth(&access_token)
+            .json(&json!({
+                "message": {
+                    "token": token,
+                    "notification": { "title": notification.title, "body": notification.body },
+                    "data": data,
+                }
+            }))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                Self::outcome(status, &body, status == StatusCode::NOT_FOUND || body.contains("UNREGISTERED"))
+            },
+            Err(e) => SendOutcome::Retry(e.to_string()),
+        }
+    }
+
+    async fn send_apns(&self, notification: &PushNotification, token: &str) -> SendOutcome {
+        let Some(apns) = &self.apns else {
+            info!("Push to {} not sent (no APNs credentials): {}", redact(&notification.wallet_address), notification.title);
+            return SendOutcome::Sent;
+        };
+        let provider_token = match apns.provider_token() {
+            Ok(provider_token) => provider_token,
+            Err(e) => return SendOutcome::Failed(e),
+        };
+
+        let mut payload = json!({
+            "aps": { "alert": { "title": notification.title, "body": notification.body }, "sound": "default" },
+            "event": notification.event.to_string(),
+        });
+        for (key, value) in &notification.data {
+            payload[key] = json!(value);
+        }
+        let result = self.client
+            .post(format!("{}/3/device/{}", apns.api_url, token))
+            .bearer_auth(&provider_token)
+            .header("apns-topic", &apns.topic)
+            .header("apns-push-type", "alert")
+            .json(&payload)
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                Self::outcome(status, &body, status == StatusCode::GONE || body.contains("BadDeviceToken"))
+            },
+            Err(e) => SendOutcome::Retry(e.to_string()),
+        }
+    }
+
+    fn outcome(status: StatusCode, body: &str, unregistered: bool) -> SendOutcome {
+        if status.is_success() {
+            SendOutcome::Sent
+        } else if unregistered {
+            SendOutcome::Unregistered
+        } else if status == StatusCode::UNAUTHORIZED || (status == StatusCode::FORBIDDEN && body.contains("ExpiredProviderToken")) {
+            SendOutcome::Unauthorized(format!("{}: {}", status, body))
+        } else if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            SendOutcome::Retry(format!("{}: {}", status, body))
+        } else {
+            SendOutcome::Failed(format!("{}: {}", status, body))
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet};
+use actix_web::web;
+use chrono::Utc;
+use log::warn;
+use rand::seq::SliceRandom;
+use uuid::Uuid;
+use stripe::{CheckoutSession, CheckoutSessionPaymentStatus, ListCheckoutSessions, RangeBounds, RangeQuery};
+use crate::models::{ApiError, ReconciliationIssue, ReconciliationRun, StripeChargeCheck, StripeChargeStatus};
+use crate::services::{JobProgress, MongoDBService, StripeApi, WalletService};
+use crate::utils::ledger::{expected_balances, find_deposit_for_session};
+
+/// Wallets checked between progress reports of a reconciliation job
+const PROGRESS_EVERY: usize = 10;
+
+/// Compares executor vault balances with what our deposit and payment records say
+/// each wallet should hold, so lost or double submissions surface quickly. Each vault
+/// read also resets that wallet's holdings projection to the real balances.
+#[derive(Clone)]
+pub struct ReconciliationService {
+    mongodb: web::Data<MongoDBService>,
+    wallet_service: web::Data<WalletService>,
+    tolerance: i64,  // base units of drift ignored to absorb rounding
+}
+
+impl ReconciliationService {
+    pub fn new(mongodb: web::Data<MongoDBService>, wallet_service: web::Data<WalletService>, tolerance: i64) -> Self {
+        Self { mongodb, wallet_service, tolerance }
+    }
+
+    /// Check a random sample of wallets (or all when `sample_size` is None/0), reporting
+    /// the wallets checked to `progress` when run as a job
+    pub async fn run(&self, sample_size: Option<usize>, progress: Option<&JobProgress>) -> Result<ReconciliationRun, ApiError> {
+        let started_at = Utc::now();
+        let run_id = Uuid::new_v4().to_string();
+
+        let mut wallets = self.mongodb.get_all_wallet_addresses().await?;
+        if let Some(n) = sample_size.filter(|n| *n > 0 && *n < wallets.len()) {
+            wallets.shuffle(&mut rand::thread_rng());
+            wallets.truncate(n);
+        }
+
+        let tokens = self.mongodb.get_all_tokens().await?;
+        let token_ids_by_symbol: HashMap<String, String> = tokens
+            .iter()
+            .filter_map(|t| t.token_symbol.clone().map(|s| (s, t.token_id.clone())))
+            .collect();
+        let symbols_by_token_id: HashMap<String, String> = token_ids_by_symbol
+            .iter()
+            .map(|(symbol, id)| (id.clone(), symbol.clone()))
+            .collect();
+
+        let mut issues = Vec::new();
+        let mut wallets_failed = 0;
+        let mut holdings_corrected = 0;
+        for (checked, wallet_address) in wallets.iter().enumerate() {
+            if let Some(progress) = progress.filter(|_| checked % PROGRESS_EVERY == 0) {
+                progress.report(checked, wallets.len()).await;
+            }
+            match self.check_wallet(wallet_address, &run_id, &token_ids_by_symbol, &symbols_by_token_id).await {
+                Ok((mut wallet_issues, corrected)) => {
+                    issues.append(&mut wallet_issues);
+                    holdings_corrected += corrected;
+                },
+                Err(e) => {
+                    warn!("Could not reconcile wallet {}: {}", wallet_address, e);
+                    wallets_failed += 1;
+                }
+            }
+        }
+
+        if let Some(progress) = progress {
+            progress.report(wallets.len(), wallets.len()).await;
+        }
+        self.mongodb.save_reconciliation_issues(&issues).await?;
+
+        Ok(ReconciliationRun {
+            run_id,
+            wallets_checked: wallets.len() - wallets_failed,
+            wallets_failed,
+            issues_found: issues.len(),
+            holdings_corrected,
+            started_at,
+            finished_at: Utc::now(),
+        })
+    }
+
+    async fn check_wallet(
+        &self,
+        wallet_address: &str,
+        run_id: &str,
+        token_ids_by_symbol: &HashMap<String, String>,
+        symbols_by_token_id: &HashMap<String, String>,
+    ) -> Result<(Vec<ReconciliationIssue>, usize), ApiError> {
+        let deposits = self.mongodb.get_user_deposits(wallet_address).await?;
+        let payments = self.mongodb.get_user_transaction_history(wallet_address).await?;
+        let expected = expected_balances(wallet_address, &deposits, &payments, token_ids_by_symbol);
+
+        let pubkey = WalletService::parse_public_key(wallet_address)
+            .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+        let actual = match self.wallet_service.get_vault(&pubkey).await {
+            Ok(Some(vault)) => WalletService::vault_balances(&vault),
+            Ok(None) => HashMap::new(),
+            Err(e) => return Err(ApiError::InternalError(format!("Failed to read vault: {}", e))),
+        };
+        let holdings_corrected = match self.mongodb.replace_wallet_holdings(wallet_address, &actual).await {
+            Ok(corrected) => corrected,
+            Err(e) => {
+                warn!("Could not refresh holdings projection of {}: {}", wallet_address, e);
+                0
+            }
+        };
+
+        let token_keys: HashSet<&String> = expected.keys().chain(actual.keys()).collect();
+        let detected_at = Utc::now();
+
+        let issues = token_keys
+            .into_iter()
+            .filter_map(|token_key| {
+                let expected_balance = expected.get(token_key).copied().unwrap_or(0);
+                let actual_balance = actual.get(token_key).copied().unwrap_or(0) as i64;
+                let difference = actual_balance - expected_balance;
+                if difference.abs() <= self.tolerance {
+                    return None;
+                }
+                Some(ReconciliationIssue {
+                    id: None,
+                    run_id: run_id.to_string(),
+                    wallet_address: wallet_address.to_string(),
+                    token_key: token_key.clone(),
+                    symbol: symbols_by_token_id.get(token_key).cloned(),
+                    expected_balance,
+                    actual_balance,
+                    difference,
+                    resolved: false,
+                    detected_at,
+                })
+            })
+            .collect();
+        Ok((issues, holdings_corrected))
+    }
+
+    /// Cross-reference paid Stripe checkout sessions created in `[start, end)` against
+    /// deposit records, flagging charges whose tokens were never credited
+    pub async fn check_stripe_charges(
+        &self,
+        stripe: &dyn StripeApi,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<StripeChargeCheck>, ApiError> {
+        // Deposits are written when the webhook lands, which can lag the charge
+        const DEPOSIT_LAG_SECS: i64 = 24 * 60 * 60;
+
+        let sessions = Self::list_paid_sessions(stripe, start, end).await?;
+        let deposits = self.mongodb.get_deposits_between(start - 60, end + DEPOSIT_LAG_SECS).await?;
+        let mut claimed = vec![false; deposits.len()];
+
+        Ok(sessions
+            .iter()
+            .map(|sess| {
+                let metadata = sess.metadata.as_ref();
+                let wallet_address = metadata
+                    .and_then(|m| m.get("user_wallet_address"))
+                    .cloned()
+                    .or_else(|| sess.client_reference_id.clone())
+                    .filter(|w| !w.is_empty() && w != "none");
+                let token_symbol = metadata.and_then(|m| m.get("token_symbol")).cloned();
+                let amount_total_cents = sess.amount_total.unwrap_or(0);
+
+                let deposit = match &wallet_address {
+                    Some(wallet) => find_deposit_for_session(
+                        sess.id.as_str(),
+                        wallet,
+                        token_symbol.as_deref().unwrap_or("unknown"),
+                        amount_total_cents,
+                        sess.created,
+                        &deposits,
+                        &claimed,
+                    ),
+                    None => None,
+                };
+                if let Some(i) = deposit {
+                    claimed[i] = true;
+                }
+
+                let status = match (&wallet_address, deposit) {
+                    (None, _) => StripeChargeStatus::NoWallet,
+                    (Some(_), Some(_)) => StripeChargeStatus::Credited,
+                    (Some(_), None) => StripeChargeStatus::MissingCredit,
+                };
+
+                StripeChargeCheck {
+                    session_id: sess.id.to_string(),
+                    created: sess.created,
+                    amount_total_cents,
+                    wallet_address,
+                    token_symbol,
+                    status,
+                    deposit_id: deposit.and_then(|i| deposits[i].id).map(|id| id.to_hex()),
+                }
+            })
+            .collect())
+    }
+
+    async fn list_paid_sessions(
+        stripe: &dyn StripeApi,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<CheckoutSession>, ApiError> {
+        let mut sessions = Vec::new();
+        let mut starting_after = None;
+        loop {
+            let params = ListCheckoutSessions {
+                created: Some(RangeQuery::Bounds(RangeBounds {
+                    gte: Some(start),
+                    lt: Some(end),
+                    ..Default::default()
+                })),
+                limit: Some(100),
+                starting_after,
+                ..ListCheckoutSessions::new()
+            };
+            let page = stripe.list_checkout_sessions(&params)
+                .await
+                .map_err(|e| ApiError::InternalError(format!("Failed to list Stripe checkout sessions: {}", e)))?;
+
+            starting_after = page.data.last().map(|s| s.id.clone());
+            sessions.extend(
+                page.data
+                    .into_iter()
+                    .filter(|s| s.payment_status == CheckoutSessionPaymentStatus::Paid),
+            );
+            if !page.has_more || starting_after.is_none() {
+                break;
+            }
+        }
+        Ok(sessions)
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use crate::models::{ApiError, PaymentStatus};
+use crate::utils::rate_limit::RateLimiter;
+#[cfg(feature = "redis")]
+use crate::utils::rate_limit::window_start;
+
+/// Redis channel every instance publishes and listens on
+#[cfg(feature = "redis")]
+const CHANNEL: &str = "index-wallets:events";
+
+/// Events buffered for slow local subscribers before they start missing some
+const LOCAL_CAPACITY: usize = 1024;
+
+/// Something every instance of the backend needs to hear about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SharedEvent {
+    PaymentStatus { payment_id: String, status: PaymentStatus },
+    VaultsChanged { pubkeys: Vec<String> },  // balances cached for these are stale
+}
+
+/// State shared between instances of the backend: rate limit counts, and events such as
+/// payment status changes and balance cache invalidations. Kept in this process unless
+/// the `redis` feature is built and REDIS_URL is set, when it goes through Redis so the
+/// backend can run with more than one replica.
+#[derive(Clone)]
+pub struct SharedState {
+    events: broadcast::Sender<SharedEvent>,
+    limiter: Arc<Mutex<RateLimiter>>,
+    #[cfg(feature = "redis")]
+    redis: Option<RedisState>,
+}
+
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+struct RedisState {
+    client: redis::Client,
+    connection: redis::aio::ConnectionManager,
+}
+
+impl SharedState {
+    /// Shared through Redis at `redis_url` if given, otherwise within this process
+    pub async fn connect(redis_url: Option<&str>) -> Result<Self, String> {
+        let (events, _) = broadcast::channel(LOCAL_CAPACITY);
+        let limiter = Arc::new(Mutex::new(RateLimiter::new()));
+
+        #[cfg(feature = "redis")]
+        {
+            let redis = match redis_url {
+                Some(url) => {
+                    let client = redis::Client::open(url).map_err(|e| format!("Invalid REDIS_URL: {}", e))?;
+                    let connection = redis::aio::ConnectionManager::new(client.clone()).await
+                        .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+                    log::info!("Sharing rate limits, events and cache invalidations through Redis");
+                    Some(RedisState { client, connection })
+                },
+                None => {
+                    log::info!("REDIS_URL not set, shared state stays in this process");
+                    None
+                },
+            };
+            let state = Self { events, limiter, redis };
+            state.start_listener();
+            Ok(state)
+        }
+
+        #[cfg(not(feature = "redis"))]
+        {
+            if redis_url.is_some() {
+                warn!("REDIS_URL is set but the backend was built without the redis feature; shared state stays in this process");
+            }
+            Ok(Self { events, limiter })
+        }
+    }
+
+    /// Events from every instance, this one included
+    pub fn subscribe(&self) -> broadcast::Receiver<SharedEvent> {
+        self.events.subscribe()
+    }
+
+    /// Tell every instance. Delivery is best effort: a failure is logged, not returned.
+    pub fn publish(&self, event: SharedEvent) {
+        #[cfg(feature = "redis")]
+        {
+            if let Some(redis) = &self.redis {
+                let mut connection = redis.connection.clone();
+                actix_web::rt::spawn(async move {
+                    let published: Result<i64, String> = match serde_json::to_string(&event) {
+                        Ok(message) => redis::cmd("PUBLISH").arg(CHANNEL).arg(message)
+                            .query_async(&mut connection).await
+                            .map_err(|e| e.to_string()),
+                        Err(e) => Err(e.to_string()),
+                    };
+                    if let Err(e) = published {
+                        log::error!("Failed to publish shared event {:?}: {}", event, e);
+                    }
+                });
+                return;
+            }
+        }
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    /// Count a request against `key`, rejecting it with 429 once there have been more than
+    /// `limit` in the current `window_secs` window. If Redis can't be reached the request
+    /// is refused with 503 rather than let through unlimited.
+    pub async fn check_rate_limit(&self, key: &str, limit: u32, window_secs: i64) -> Result<(), ApiError> {
+        let now = chrono::Utc::now().timestamp();
+
+        #[cfg(feature = "redis")]
+        {
+            if let Some(redis) = &self.redis {
+                let redis_key = format!("ratelimit:{}:{}", key, window_start(now, window_secs));
+                let mut connection = redis.connection.clone();
+                let counted: Result<(u32, i64), _> = redis::pipe()
+                    .atomic()
+                    .incr(&redis_key, 1)
+                    .expire(&redis_key, window_secs.max(1) as usize)
+                    .query_async(&mut connection)
+                    .await;
+                return match counted {
+                    Ok((count, _)) if count > limit => Err(too_many_requests(window_secs)),
+                    Ok(_) => Ok(()),
+                    Err(e) => {
+                        log::error!("Rate limit for {} not checked, Redis failed: {}", key, e);
+                        Err(ApiError::ServiceUnavailable("Rate limiting is unavailable, try again shortly".to_string()))
+                    },
+                };
+            }
+        }
+
+        let allowed = self.limiter.lock().unwrap_or_else(|e| e.into_inner()).hit(key, limit, window_secs, now);
+        if allowed { Ok(()) } else { Err(too_many_requests(window_secs)) }
+    }
+
+    /// Forward events other instances publish to local subscribers, reconnecting if the
+    /// subscription drops
+    #[cfg(feature = "redis")]
+    fn start_listener(&self) {
+        use futures::StreamExt;
+
+        let Some(redis) = self.redis.clone() else { return };
+        let events = self.events.clone();
+        actix_web::rt::spawn(async move {
+            loop {
+                match redis.client.get_async_connection().await {
+                    Ok(connection) => {
+                        let mut pubsub = connection.into_pubsub();
+                        if let Err(e) = pubsub.subscribe(CHANNEL).await {
+                            log::error!("Failed to subscribe to {}: {}", CHANNEL, e);
+                        } else {
+                            let mut messages = pubsub.on_message();
+                            while let Some(message) = messages.next().await {
+                                let event = message.get_payload::<String>().ok()
+                                    .and_then(|payload| serde_json::from_str::<SharedEvent>(&payload).ok());
+                                match event {
+                                    Some(event) => { let _ = events.send(event); },
+                                    None => warn!("Ignoring unreadable shared event on {}", CHANNEL),
+                                }
+                            }
+                            warn!("Redis subscription to {} dropped, reconnecting", CHANNEL);
+                        }
+                    },
+                    Err(e) => log::error!("Failed to connect to Redis for {}: {}", CHANNEL, e),
+                }
+                actix_web::rt::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+    }
+}
+
+fn too_many_requests(window_secs: i64) -> ApiError {
+    ApiError::TooManyRequests(format!("Too many requests, try again within {} seconds", window_secs))
+}
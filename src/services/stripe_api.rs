@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use stripe::{
+    Account, AccountId, AccountLink, CheckoutSession, CheckoutSessionId, Client, CreateAccount, CreateAccountLink,
+    CreateCheckoutSession, CreateCustomer, CreatePaymentIntent, CreatePrice, CreateProduct, Customer, List,
+    ListCheckoutSessions, ListPaymentMethods, PaymentIntent, PaymentIntentConfirmParams, PaymentIntentId,
+    PaymentMethod, PaymentMethodId, Payout, CreatePayout, Price, Product, StripeError, UpdatePaymentIntent,
+};
+
+/// The Stripe calls the backend makes, so services can run against a FakeStripe in tests
+#[async_trait]
+pub trait StripeApi: Send + Sync {
+    async fn create_account(&self, params: CreateAccount<'_>) -> Result<Account, StripeError>;
+    async fn retrieve_account(&self, id: &AccountId) -> Result<Account, StripeError>;
+    async fn create_account_link(&self, params: CreateAccountLink<'_>) -> Result<AccountLink, StripeError>;
+    async fn create_product(&self, params: CreateProduct<'_>) -> Result<Product, StripeError>;
+    async fn create_price(&self, params: CreatePrice<'_>) -> Result<Price, StripeError>;
+    /// Pay out from a connected account's balance to its bank account. Stripe answers a retry
+    /// with the same `idempotency_key` with the payout it already created.
+    async fn create_payout(&self, account: &AccountId, params: CreatePayout<'_>, idempotency_key: &str) -> Result<Payout, StripeError>;
+
+    async fn create_checkout_session(&self, params: CreateCheckoutSession<'_>) -> Result<CheckoutSession, StripeError>;
+    async fn retrieve_checkout_session(&self, id: &CheckoutSessionId) -> Result<CheckoutSession, StripeError>;
+    async fn list_checkout_sessions(&self, params: &ListCheckoutSessions<'_>) -> Result<List<CheckoutSession>, StripeError>;
+
+    async fn create_payment_intent(&self, params: CreatePaymentIntent<'_>) -> Result<PaymentIntent, StripeError>;
+    async fn retrieve_payment_intent(&self, id: &PaymentIntentId) -> Result<PaymentIntent, StripeError>;
+    async fn update_payment_intent(&self, id: &PaymentIntentId, params: UpdatePaymentIntent<'_>) -> Result<PaymentIntent, StripeError>;
+    async fn confirm_payment_intent(&self, id: &PaymentIntentId, params: PaymentIntentConfirmParams<'_>) -> Result<PaymentIntent, StripeError>;
+
+    async fn create_customer(&self, params: CreateCustomer<'_>) -> Result<Customer, StripeError>;
+    async fn list_payment_methods(&self, params: &ListPaymentMethods<'_>) -> Result<List<PaymentMethod>, StripeError>;
+    async fn retrieve_payment_method(&self, id: &PaymentMethodId) -> Result<PaymentMethod, StripeError>;
+    async fn detach_payment_method(&self, id: &PaymentMethodId) -> Result<PaymentMethod, StripeError>;
+}
+
+/// Stripe itself, through the API client
+pub struct LiveStripe {
+    client: Client,
+}
+
+impl LiveStripe {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl StripeApi for LiveStripe {
+    async fn create_account(&self, params: CreateAccount<'_>) -> Result<Account, StripeError> {
+        Account::create(&self.client, params).await
+    }
+
+    async fn retrieve_account(&self, id: &AccountId) -> Result<Account, StripeError> {
+        Account::retrieve(&self.client, id, &[]).await
+    }
+
+    async fn create_account_link(&self, params: CreateAccountLink<'_>) -> Result<AccountLink, StripeError> {
+        AccountLink::create(&self.client, params).await
+    }
+
+    async fn create_product(&self, params: CreateProduct<'_>) -> Result<Product, StripeError> {
+        Product::create(&self.client, params).await
+    }
+
+    async fn create_price(&self, params: CreatePrice<'_>) -> Result<Price, StripeError> {
+        Price::create(&self.client, params).await
+    }
+
+    async fn create_payout(&self, account: &AccountId, params: CreatePayout<'_>, idempotency_key: &str) -> Result<Payout, StripeError> {
+        let client = self.client.clone()
+            .with_stripe_account(account.clone())
+            .with_strategy(stripe::RequestStrategy::Idempotent(idempotency_key.to_string()));
+        Payout::create(&client, params).await
+    }
+
+    async fn create_checkout_session(&self, params: CreateCheckoutSession<'_>) -> Result<CheckoutSession, StripeError> {
+        CheckoutSession::create(&self.client, params).await
+    }
+
+    async fn retrieve_checkout_session(&self, id: &CheckoutSessionId) -> Result<CheckoutSession, StripeError> {
+        CheckoutSession::retrieve(&self.client, id, &[]).await
+    }
+
+    async fn list_checkout_sessions(&self, params: &ListCheckoutSessions<'_>) -> Result<List<CheckoutSession>, StripeError> {
+        CheckoutSession::list(&self.client, params).await
+    }
+
+    async fn create_payment_intent(&self, params: CreatePaymentIntent<'_>) -> Result<PaymentIntent, StripeError> {
+        PaymentIntent::create(&self.client, params).await
+    }
+
+    async fn retrieve_payment_intent(&self, id: &PaymentIntentId) -> Result<PaymentIntent, StripeError> {
+        PaymentIntent::retrieve(&self.client, id, &[]).await
+    }
+
+    async fn update_payment_intent(&self, id: &PaymentIntentId, params: UpdatePaymentIntent<'_>) -> Result<PaymentIntent, StripeError> {
+        PaymentIntent::update(&self.client, id, params).await
+    }
+
+    async fn confirm_payment_intent(&self, id: &PaymentIntentId, params: PaymentIntentConfirmParams<'_>) -> Result<PaymentIntent, StripeError> {
+        PaymentIntent::confirm(&self.client, id.as_str(), params).await
+    }
+
+    async fn create_customer(&self, params: CreateCustomer<'_>) -> Result<Customer, StripeError> {
+        Customer::create(&self.client, params).await
+    }
+
+    async fn list_payment_methods(&self, params: &ListPaymentMethods<'_>) -> Result<List<PaymentMethod>, StripeError> {
+        PaymentMethod::list(&self.client, params).await
+    }
+
+    async fn retrieve_payment_method(&self, id: &PaymentMethodId) -> Result<PaymentMethod, StripeError> {
+        PaymentMethod::retrieve(&self.client, id, &[]).await
+    }
+
+    async fn detach_payment_method(&self, id: &PaymentMethodId) -> Result<PaymentMethod, StripeError> {
+        PaymentMethod::detach(&self.client, id).await
+    }
+}
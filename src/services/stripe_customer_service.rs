@@ -0,0 +1,116 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use log::{info, error};
+use stripe::{CreateCustomer, CustomerId, Expandable, ListPaymentMethods, PaymentMethod, PaymentMethodId, PaymentMethodTypeFilter};
+use crate::models::ApiError;
+use crate::models::payment::SavedPaymentMethod;
+use crate::services::{MongoDBService, StripeApi};
+use crate::utils::redaction::redact;
+
+/// One Stripe Customer per wallet, so cards saved at checkout can be reused
+/// for repeat donations and one-click top-ups
+pub struct StripeCustomerService {
+    mongodb_service: Arc<MongoDBService>,
+    stripe: Arc<dyn StripeApi>,
+}
+
+impl StripeCustomerService {
+    pub fn new(mongodb_service: Arc<MongoDBService>, stripe: Arc<dyn StripeApi>) -> Self {
+        Self { mongodb_service, stripe }
+    }
+
+    /// The wallet's customer, created on first use. Wallets without a user record get none.
+    pub async fn get_or_create_customer(&self, wallet_address: &str) -> Result<Option<CustomerId>, ApiError> {
+        let user = match self.mongodb_service.get_user_by_wallet(wallet_address).await? {
+            Some(user) if user.deleted_at.is_none() => user,
+            _ => return Ok(None),
+        };
+        if let Some(existing) = &user.stripe_customer_id {
+            return parse_customer_id(existing).map(Some);
+        }
+
+        let mut params = CreateCustomer::new();
+        params.name = Some(&user.username);
+        params.metadata = Some([
+            ("wallet_address".to_string(), wallet_address.to_string()),
+        ].into());
+        let customer = self.stripe.create_customer(params)
+            .await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        info!("Created Stripe customer {} for wallet {}", customer.id, redact(wallet_address));
+
+        // A concurrent first donation may have stored its own customer; use whichever won
+        let stored = self.mongodb_service.set_stripe_customer_id(wallet_address, customer.id.as_str()).await?;
+        if stored != customer.id.as_str() {
+            info!("Wallet {} already had customer {}, leaving {} unused", redact(wallet_address), stored, customer.id);
+        }
+        parse_customer_id(&stored).map(Some)
+    }
+
+    /// Customer to attach to a new checkout or PaymentIntent. Failures are logged and the
+    /// payment goes ahead without one; the donor just won't have the card saved.
+    pub async fn customer_for_payment(&self, wallet_address: &str) -> Option<CustomerId> {
+        match self.get_or_create_customer(wallet_address).await {
+            Ok(customer) => customer,
+            Err(e) => {
+                error!("Failed to get Stripe customer for {}: {:?}", redact(wallet_address), e);
+                None
+            }
+        }
+    }
+
+    /// Cards saved for the wallet, without creating a customer if there isn't one
+    pub async fn list_payment_methods(&self, wallet_address: &str) -> Result<Vec<SavedPaymentMethod>, ApiError> {
+        let customer_id = match self.stored_customer_id(wallet_address).await? {
+            Some(id) => id,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut params = ListPaymentMethods::new();
+        params.customer = Some(customer_id);
+        params.type_ = Some(PaymentMethodTypeFilter::Card);
+        params.limit = Some(100);
+        let methods = self.stripe.list_payment_methods(&params)
+            .await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+
+        Ok(methods.data.into_iter().map(SavedPaymentMethod::from).collect())
+    }
+
+    /// Remove a saved card. It must belong to the wallet's customer.
+    pub async fn detach_payment_method(&self, wallet_address: &str, payment_method_id: &str) -> Result<(), ApiError> {
+        let id = PaymentMethodId::from_str(payment_method_id)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid payment method ID: {}", e)))?;
+        let customer_id = self.stored_customer_id(wallet_address).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Payment method {} not found", payment_method_id)))?;
+
+        let method = self.stripe.retrieve_payment_method(&id)
+            .await
+            .map_err(|e| ApiError::NotFound(format!("Payment method {} not found: {}", payment_method_id, e)))?;
+        let owner = method.customer.as_ref().map(|c| match c {
+            Expandable::Id(id) => id.clone(),
+            Expandable::Object(customer) => customer.id.clone(),
+        });
+        if owner.as_ref() != Some(&customer_id) {
+            return Err(ApiError::NotFound(format!("Payment method {} not found", payment_method_id)));
+        }
+
+        self.stripe.detach_payment_method(&id)
+            .await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        info!("Detached payment method {} from customer {} ({})", id, customer_id, redact(wallet_address));
+        Ok(())
+    }
+
+    async fn stored_customer_id(&self, wallet_address: &str) -> Result<Option<CustomerId>, ApiError> {
+        match self.mongodb_service.get_user_by_wallet(wallet_address).await? {
+            Some(user) => user.stripe_customer_id.as_deref().map(parse_customer_id).transpose(),
+            None => Err(ApiError::NotFound(format!("User not found: {}", wallet_address))),
+        }
+    }
+}
+
+fn parse_customer_id(id: &str) -> Result<CustomerId, ApiError> {
+    CustomerId::from_str(id)
+        .map_err(|e| ApiError::InternalError(format!("Invalid stored Stripe customer ID {}: {}", id, e)))
+}
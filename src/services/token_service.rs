@@ -15,25 +15,71 @@ use delta_executor_sdk::{
     },
 };
 
-use crate::{models::Token, services::{MongoDBService, executor_client::ExecutorClient}};
+use crate::{models::{Token, TokenIssuerKey, AuditLog, AuditAction, WebhookError}, services::{MongoDBService, executor_client::{ExecutorClient, ExecutorError}}};
+use crate::utils::audit::{snapshot, SYSTEM_ACTOR};
+use crate::utils::key_encryption::{seal_with_data_key, open_with_data_key};
+use crate::utils::holdings::{transfer_deltas, HoldingDelta};
 
+/// Why a token transfer failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferError {
+    /// No tokens moved: the transfer was never submitted, or the executor refused it
+    NotTransferred(String),
+    /// The executor didn't answer clearly, so the transfer may still have landed
+    Unknown(String),
+}
+
+impl TransferError {
+    fn submission(err: ExecutorError) -> Self {
+        let message = format!("Failed to submit transfer to executor: {}", err);
+        if err.is_rejection() { TransferError::NotTransferred(message) } else { TransferError::Unknown(message) }
+    }
+}
+
+impl From<String> for TransferError {
+    fn from(message: String) -> Self {
+        TransferError::NotTransferred(message)
+    }
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::NotTransferred(msg) => write!(f, "{}", msg),
+            TransferError::Unknown(msg) => write!(f, "{} (the transfer may still land)", msg),
+        }
+    }
+}
+
+impl From<TransferError> for WebhookError {
+    fn from(err: TransferError) -> Self {
+        match err {
+            TransferError::NotTransferred(msg) => WebhookError::TokenTransferError(msg),
+            unknown => WebhookError::TransferOutcomeUnknown(unknown.to_string()),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct TokenService {
     mongodb: web::Data<MongoDBService>,
     central_vault_id: VaultId,
+    central_vault_pubkey: Ed25519PubKey,
     executor_client: ExecutorClient,
+    token_key_master_key: [u8; 32],
 }
 
 impl TokenService {
-    pub fn new(mongodb: web::Data<MongoDBService>, central_vault_keypair: Ed25519PrivKey) -> Self {
+    pub fn new(mongodb: web::Data<MongoDBService>, central_vault_keypair: Ed25519PrivKey, token_key_master_key: [u8; 32], executor_client: ExecutorClient) -> Self {
         // Use shard 1 as default for the central vault
         let central_vault_id = VaultId::new(central_vault_keypair.pub_key(), Shard::from(1u64));
         
         Self { 
             mongodb,
             central_vault_id,
-            executor_client: ExecutorClient::new()
+            central_vault_pubkey: central_vault_keypair.pub_key(),
+            executor_client,
+            token_key_master_key,
         }
     }
     
@@ -110,10 +156,14 @@ impl TokenService {
             market_valuation: 1.0,
             total_allocated: initial_supply,
             created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+            updated_at: None,
             stripe_product_id: "".to_string(),
             token_image_url,
         };
         
+        // Persist the issuer key before minting so it is never lost for a live token
+        self.store_issuer_keypair(&token.token_id, issuer_keypair).await?;
+        
         // Sign the payload
         info!("Signing token mint payload");
         let signed = SignedMessage::sign(payload, issuer_keypair)
@@ -123,14 +173,32 @@ impl TokenService {
         let verifiable = VerifiableType::TokenMint(signed);
         
         // Submit to executor
-        match self.executor_client.submit_verifiables(vec![verifiable]).await {
+        let result = self.executor_client.submit_verifiables(vec![verifiable]).await;
+        self.executor_client.invalidate_vaults(&[issuer_keypair.pub_key(), self.central_vault_pubkey]);
+        match result {
             Ok(_) => {
                 info!("Successfully submitted token mint to executor");
+                self.project_holdings(&[HoldingDelta {
+                    wallet_address: self.central_vault_pubkey.to_string(),
+                    token_id: token.token_id.clone(),
+                    amount: initial_supply as i64,
+                }]).await;
                 
                 // Save token to database
                 match self.mongodb.save_token(token.clone()).await {
                     Ok(_) => {
                         info!("Successfully saved token to database");
+                        let audit = AuditLog::new(
+                            SYSTEM_ACTOR,
+                            AuditAction::TokenMinted,
+                            "token",
+                            &token.token_id,
+                            None,
+                            snapshot(&token),
+                        );
+                        if let Err(e) = self.mongodb.record_audit_log(audit).await {
+                            error!("Failed to record audit log for token mint: {:?}", e);
+                        }
                         Ok(token)
                     },
                     Err(e) => {
@@ -146,6 +214,55 @@ impl TokenService {
         }
     }
     
+    /// Encrypt and save the issuer keypair for a token
+    async fn store_issuer_keypair(&self, token_id: &str, issuer_keypair: &Ed25519PrivKey) -> Result<(), String> {
+        let (encrypted_private_key, wrapped_data_key) = seal_with_data_key(
+            &self.token_key_master_key,
+            issuer_keypair.to_string().as_bytes(),
+            token_id.as_bytes(),
+        ).map_err(|e| format!("Failed to encrypt issuer key: {}", e))?;
+        
+        let key = TokenIssuerKey {
+            id: None,
+            token_id: token_id.to_string(),
+            issuer_pubkey: issuer_keypair.pub_key().to_string(),
+            encrypted_private_key,
+            wrapped_data_key,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+        };
+        
+        self.mongodb.save_token_issuer_key(key).await
+            .map_err(|e| format!("Failed to save issuer key: {:?}", e))?;
+        
+        info!("Stored encrypted issuer key for token {}", token_id);
+        Ok(())
+    }
+    
+    /// Load and decrypt the issuer keypair for a token, for later supply operations
+    pub async fn load_issuer_keypair(&self, token_id: &str) -> Result<Ed25519PrivKey, String> {
+        let key = self.mongodb.get_token_issuer_key(token_id).await
+            .map_err(|e| format!("Failed to get issuer key from database: {:?}", e))?
+            .ok_or_else(|| format!("No issuer key stored for token: {}", token_id))?;
+        
+        let plaintext = open_with_data_key(
+            &self.token_key_master_key,
+            &key.encrypted_private_key,
+            &key.wrapped_data_key,
+            token_id.as_bytes(),
+        ).map_err(|e| format!("Failed to decrypt issuer key: {}", e))?;
+        
+        let key_str = String::from_utf8(plaintext)
+            .map_err(|e| format!("Invalid issuer key encoding: {}", e))?;
+        let issuer_keypair = Ed25519PrivKey::from_str(&key_str)
+            .map_err(|e| format!("Invalid issuer key: {}", e))?;
+        
+        if issuer_keypair.pub_key().to_string() != key.issuer_pubkey {
+            return Err(format!("Issuer key for token {} does not match stored pubkey", token_id));
+        }
+        
+        Ok(issuer_keypair)
+    }
+    
     /// Get a token by name
     pub async fn get_token_by_name(&self, token_name: &str) -> Result<Option<Token>, String> {
         self.mongodb.get_token_by_name(token_name).await
@@ -158,25 +275,25 @@ impl TokenService {
     }
 
     
-    /// Transfer tokens from one vault to another
+    /// Transfer tokens from one vault to another. Returns the executor transaction ID if reported.
     pub async fn transfer_tokens(
         &self,
         from_keypair: &Ed25519PrivKey,
         to_pubkey: &Ed25519PubKey,
         token_symbol: &str,
         amount: u64,
-    ) -> Result<(), String> {
+    ) -> Result<Option<String>, TransferError> {
         // Get token information by symbol
         let token = match self.mongodb.get_token_by_symbol(token_symbol).await
             .map_err(|e| format!("Failed to get token from database: {:?}", e))? {
             Some(token) => token,
-            None => return Err(format!("Token not found: {}", token_symbol)),
+            None => return Err(format!("Token not found: {}", token_symbol).into()),
         };
         
         // Parse token ID
         let token_id_parts: Vec<&str> = token.token_id.split(',').collect();
         if token_id_parts.len() != 2 {
-            return Err(format!("Invalid token ID format: {}", token.token_id));
+            return Err(format!("Invalid token ID format: {}", token.token_id).into());
         }
         
         let token_pubkey = Ed25519PubKey::from_str(token_id_parts[0])
@@ -191,11 +308,11 @@ impl TokenService {
         // Get from vault information
         let from_pubkey = from_keypair.pub_key();
 
-        // Get the vault from the executor
-        let from_vault = match self.executor_client.get_vault(&from_pubkey).await {
+        // Get the vault from the executor, uncached since the allowance signs against its nonce
+        let from_vault = match self.executor_client.fetch_vault(&from_pubkey).await {
             Ok(Some(vault)) => vault,
-            Ok(None) => return Err(format!("Vault not found for pubkey: {}", from_pubkey)),
-            Err(e) => return Err(format!("Error fetching vault: {}", e)),
+            Ok(None) => return Err(format!("Vault not found for pubkey: {}", from_pubkey).into()),
+            Err(e) => return Err(format!("Error fetching vault: {}", e).into()),
         };
         
         // Create vault IDs
@@ -231,16 +348,79 @@ impl TokenService {
         let verifiable = VerifiableType::DebitAllowance(signed);
         
         // Submit to executor
-        match self.executor_client.submit_verifiables(vec![verifiable]).await {
-            Ok(_) => {
-                info!("Successfully transferred {} tokens from {} to {}", 
-                      amount, from_pubkey, to_pubkey);
-                Ok(())
+        let result = self.executor_client.submit_verifiables(vec![verifiable]).await;
+        self.executor_client.invalidate_vaults(&[from_pubkey, *to_pubkey]);
+        match result {
+            Ok(tx_id) => {
+                info!("Successfully transferred {} tokens from {} to {} (executor tx: {:?})", 
+                      amount, from_pubkey, to_pubkey, tx_id);
+                self.project_holdings(&transfer_deltas(&from_pubkey.to_string(), &to_pubkey.to_string(), &[(token.token_id.clone(), amount)])).await;
+                Ok(tx_id)
+            },
+            Err(e) => {
+                error!("Failed to submit transfer to executor: {}", e);
+                Err(TransferError::submission(e))
+            }
+        }
+    }
+
+    /// Transfer several tokens from one vault to another in a single debit allowance.
+    /// `amounts` pairs token IDs ("pubkey,shard") with base units; zero amounts are skipped.
+    pub async fn transfer_token_bundle(
+        &self,
+        from_keypair: &Ed25519PrivKey,
+        to_pubkey: &Ed25519PubKey,
+        amounts: &[(String, u64)],
+    ) -> Result<Option<String>, TransferError> {
+        let mut allowances = std::collections::BTreeMap::new();
+        for (token_id, amount) in amounts.iter().filter(|(_, amount)| *amount > 0) {
+            let (token_pubkey, token_shard) = token_id.split_once(',')
+                .ok_or_else(|| format!("Invalid token ID format: {}", token_id))?;
+            let token_pubkey = Ed25519PubKey::from_str(token_pubkey)
+                .map_err(|_| format!("Invalid token pubkey: {}", token_pubkey))?;
+            let token_shard = token_shard.parse::<u64>()
+                .map_err(|_| format!("Invalid token shard: {}", token_shard))?;
+            *allowances.entry(TokenKind::NonNative(VaultId::new(token_pubkey, token_shard))).or_insert(0) += *amount;
+        }
+        if allowances.is_empty() {
+            return Ok(None);
+        }
+
+        let from_pubkey = from_keypair.pub_key();
+        let from_vault = match self.executor_client.fetch_vault(&from_pubkey).await {
+            Ok(Some(vault)) => vault,
+            Ok(None) => return Err(format!("Vault not found for pubkey: {}", from_pubkey).into()),
+            Err(e) => return Err(format!("Error fetching vault: {}", e).into()),
+        };
+
+        let debit = delta_executor_sdk::base::verifiable::debit_allowance::DebitAllowance {
+            debited: VaultId::new(from_pubkey, from_vault.shard()),
+            credited: VaultId::new(*to_pubkey, from_vault.shard()),
+            new_nonce: from_vault.nonce() + 1,
+            allowances,
+        };
+        let signed = SignedMessage::sign(debit, from_keypair)
+            .map_err(|e| format!("Failed to sign DebitAllowance: {:?}", e))?;
+
+        let result = self.executor_client.submit_verifiables(vec![VerifiableType::DebitAllowance(signed)]).await;
+        self.executor_client.invalidate_vaults(&[from_pubkey, *to_pubkey]);
+        match result {
+            Ok(tx_id) => {
+                info!("Transferred {} tokens from {} to {} (executor tx: {:?})", amounts.len(), from_pubkey, to_pubkey, tx_id);
+                self.project_holdings(&transfer_deltas(&from_pubkey.to_string(), &to_pubkey.to_string(), amounts)).await;
+                Ok(tx_id)
             },
             Err(e) => {
                 error!("Failed to submit transfer to executor: {}", e);
-                Err(format!("Failed to submit transfer to executor: {}", e))
+                Err(TransferError::submission(e))
             }
         }
     }
-}
\ No newline at end of file
+
+    // Best effort: reconciliation corrects the projection if this is lost
+    async fn project_holdings(&self, deltas: &[HoldingDelta]) {
+        if let Err(e) = self.mongodb.apply_holding_deltas(deltas).await {
+            error!("Failed to project holdings of submitted transfer: {}", e);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use actix_web::web;
+use delta_executor_sdk::base::crypto::Ed25519PrivKey;
+use log::{info, error};
+use crate::services::{TokenService, WalletService};
+use crate::utils::redaction::redact;
+use crate::utils::ttl_cache::TtlCache;
+
+/// Don't submit another provisioning transfer for a wallet within this window; the
+/// first one may still be executing
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+const RECENT_CAPACITY: usize = 10_000;
+
+/// Makes sure every wallet has a vault on the executor. Vaults only exist once something
+/// has been credited to them, so new wallets get a zero-value USD transfer from the
+/// central vault.
+#[derive(Clone)]
+pub struct VaultProvisioningService {
+    wallet_service: web::Data<WalletService>,
+    token_service: web::Data<TokenService>,
+    central_vault_keypair: Ed25519PrivKey,
+    recently_requested: Arc<Mutex<TtlCache<String, ()>>>,
+}
+
+impl VaultProvisioningService {
+    pub fn new(wallet_service: web::Data<WalletService>, token_service: web::Data<TokenService>, central_vault_keypair: Ed25519PrivKey) -> Self {
+        Self {
+            wallet_service,
+            token_service,
+            central_vault_keypair,
+            recently_requested: Arc::new(Mutex::new(TtlCache::new(RETRY_AFTER, RECENT_CAPACITY))),
+        }
+    }
+
+    /// Provision in the background so callers don't wait on the executor
+    pub fn provision_in_background(&self, wallet_address: String) {
+        {
+            let mut recent = self.recently_requested.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            if recent.get(&wallet_address, now).is_some() {
+                return;
+            }
+            let generation = recent.generation();
+            recent.insert(wallet_address.clone(), (), generation, now);
+        }
+
+        let service = self.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = service.provision(&wallet_address).await {
+                error!("Failed to provision vault for {}: {}", redact(&wallet_address), e);
+            }
+        });
+    }
+
+    /// Create the wallet's vault unless it already exists. Returns whether a transfer was submitted.
+    pub async fn provision(&self, wallet_address: &str) -> Result<bool, String> {
+        let pubkey = WalletService::parse_public_key(wallet_address).map_err(|e| e.to_string())?;
+        if self.wallet_service.get_vault(&pubkey).await.map_err(|e| e.to_string())?.is_some() {
+            return Ok(false);
+        }
+
+        let tx_id = self.token_service.transfer_tokens(&self.central_vault_keypair, &pubkey, "USD", 0).await
+            .map_err(|e| e.to_string())?;
+        info!("Provisioned vault for {} (executor tx: {:?})", redact(wallet_address), tx_id);
+        Ok(true)
+    }
+}
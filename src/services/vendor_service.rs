@@ -0,0 +1,128 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use actix_web::web;
+use log::{info, warn, error};
+use crate::models::{ApiError, User, VendorOnboardingRequest, VendorStripeAccount};
+use crate::services::cause_service::account_requirements;
+use crate::services::{CauseService, MongoDBService, StripeApi};
+use crate::utils::redaction::redact;
+
+/// Vendors' own Stripe Connect accounts, so they can take card payments. The account
+/// parameters come from `CauseService`, so vendors onboard in the same countries as causes.
+#[derive(Clone)]
+pub struct VendorService {
+    mongodb: web::Data<MongoDBService>,
+    cause_service: web::Data<CauseService>,
+    stripe: Arc<dyn StripeApi>,
+}
+
+impl VendorService {
+    pub fn new(mongodb: web::Data<MongoDBService>, cause_service: web::Data<CauseService>, stripe: Arc<dyn StripeApi>) -> Self {
+        Self { mongodb, cause_service, stripe }
+    }
+
+    /// Start or resume Stripe onboarding: the vendor's account, created the first time, and a
+    /// fresh onboarding link for it. The email defaults to the one on the vendor's profile.
+    pub async fn start_onboarding(&self, vendor: &User, request: &VendorOnboardingRequest) -> Result<(VendorStripeAccount, String), ApiError> {
+        let account = match &vendor.stripe_account {
+            Some(account) if account.onboarding_completed => {
+                return Err(ApiError::Conflict("The vendor has already finished Stripe onboarding".to_string()));
+            }
+            Some(account) => account.clone(),
+            None => self.connect_account(vendor, request).await?,
+        };
+        let onboarding_url = self.account_link(&account.account_id).await?;
+        info!("Started Stripe onboarding for vendor {} on account {}", redact(&vendor.wallet_address), account.account_id);
+        Ok((account, onboarding_url))
+    }
+
+    /// Create the vendor's Express account and store it, unless a concurrent request stored
+    /// one first; then that one is used and the new account is left unused
+    async fn connect_account(&self, vendor: &User, request: &VendorOnboardingRequest) -> Result<VendorStripeAccount, ApiError> {
+        let email = request.email.as_deref()
+            .map(str::trim)
+            .filter(|email| !email.is_empty())
+            .or(vendor.email.as_deref())
+            .ok_or_else(|| ApiError::ValidationError("An email is required to connect a Stripe account".to_string()))?;
+        let country = request.country.to_uppercase();
+        // The vendor's wallet is kept in the metadata so account.updated can find them
+        let account_params = self.cause_service.connected_account_params(
+            email,
+            &country,
+            request.business_type,
+            [("vendor_address".to_string(), vendor.wallet_address.clone())].into(),
+        )?;
+        let created = self.stripe.create_account(account_params).await
+            .map_err(|e| {
+                error!("Failed to create Connected Account for vendor {}: {}", redact(&vendor.wallet_address), e);
+                ApiError::StripeError(format!("Stripe account creation failed: {}", e))
+            })?;
+        info!("Created Stripe account {} for vendor {}", created.id, redact(&vendor.wallet_address));
+
+        let now = chrono::Utc::now().timestamp();
+        let account = VendorStripeAccount {
+            account_id: created.id.to_string(),
+            country,
+            business_type: request.business_type,
+            onboarding_completed: false,
+            charges_enabled: false,
+            payouts_enabled: false,
+            requirements: None,
+            created_at: now,
+            updated_at: now,
+        };
+        if self.mongodb.set_vendor_stripe_account(&vendor.wallet_address, &account).await? {
+            return Ok(account);
+        }
+        warn!("Vendor {} already has a Stripe account; {} is unused", redact(&vendor.wallet_address), account.account_id);
+        self.mongodb.get_user_by_wallet(&vendor.wallet_address).await?
+            .and_then(|user| user.stripe_account)
+            .ok_or_else(|| ApiError::InternalError(format!("Vendor {} lost their Stripe account", vendor.wallet_address)))
+    }
+
+    async fn account_link(&self, account_id: &str) -> Result<String, ApiError> {
+        let account_id_obj = stripe::AccountId::from_str(account_id)
+            .map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?;
+
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let refresh_url = format!("{}/vendor/stripe/refresh", frontend_url);
+        let return_url = format!("{}/vendor/stripe/complete", frontend_url);
+
+        let link_params = stripe::CreateAccountLink {
+            account: account_id_obj,
+            refresh_url: Some(&refresh_url),
+            return_url: Some(&return_url),
+            type_: stripe::AccountLinkType::AccountOnboarding,
+            collect: None,
+            collection_options: None,
+            expand: &[],
+        };
+
+        let link = self.stripe.create_account_link(link_params).await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+        Ok(link.url)
+    }
+
+    /// Re-read a vendor's account from Stripe and record its status, in case a webhook was missed
+    pub async fn refresh_account(&self, mut vendor_account: VendorStripeAccount) -> Result<VendorStripeAccount, ApiError> {
+        let account_id_obj = stripe::AccountId::from_str(&vendor_account.account_id)
+            .map_err(|_| ApiError::ValidationError("Invalid account ID".to_string()))?;
+        let account = self.stripe.retrieve_account(&account_id_obj).await
+            .map_err(|e| ApiError::StripeError(e.to_string()))?;
+
+        let requirements = account_requirements(&account);
+        vendor_account.onboarding_completed = self.cause_service.onboarded(&account);
+        vendor_account.charges_enabled = account.charges_enabled.unwrap_or(false);
+        vendor_account.payouts_enabled = account.payouts_enabled.unwrap_or(false);
+        vendor_account.updated_at = chrono::Utc::now().timestamp();
+        self.mongodb.update_vendor_stripe_status(
+            &vendor_account.account_id,
+            vendor_account.onboarding_completed,
+            vendor_account.charges_enabled,
+            vendor_account.payouts_enabled,
+            &requirements,
+        ).await?;
+        vendor_account.requirements = Some(requirements);
+        Ok(vendor_account)
+    }
+}
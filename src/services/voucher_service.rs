@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use actix_web::web;
+use log::{info, warn, error};
+use mongodb::bson::{doc, oid::ObjectId};
+use delta_executor_sdk::base::core::Shard;
+use delta_executor_sdk::base::crypto::{Ed25519PrivKey, Ed25519PubKey};
+use delta_executor_sdk::base::vaults::{VaultId, TokenKind, ReadableVault};
+use delta_executor_sdk::base::verifiable::debit_allowance::{DebitAllowance, SignedDebitAllowance};
+use delta_executor_sdk::base::verifiable::VerifiableType;
+use crate::models::{ApiError, ActivityEvent, ActivityKind, ActivityAmount, Voucher, VoucherStatus, VoucherFunding, CreateVoucherRequest, DEFAULT_VOUCHER_TTL_DAYS, MAX_VOUCHER_TTL_DAYS};
+use crate::services::{MongoDBService, TokenService, TransferError, WalletService};
+use crate::utils::payment_code::generate_voucher_code;
+use crate::utils::signed_payload::contains_value;
+
+/// Expired vouchers closed per sweep
+const BATCH_SIZE: i64 = 100;
+
+/// Failed refunds are retried once they've been left this long
+const RETRY_AFTER_SECS: i64 = 5 * 60;
+
+/// Longest note an issuer can attach
+const MAX_NOTE_CHARS: usize = 140;
+
+/// Issues gift vouchers and moves their escrowed tokens. Everything a voucher holds sits in
+/// the central vault: admins issue from the platform's holdings, vendors sign a transfer in.
+/// Redeeming sends the tokens to the redeemer; expiry sends vendor-funded tokens back. A
+/// transfer that may have landed leaves the voucher failed rather than retrying it.
+#[derive(Clone)]
+pub struct VoucherService {
+    mongodb: web::Data<MongoDBService>,
+    token_service: web::Data<TokenService>,
+    wallet_service: web::Data<WalletService>,
+    central_vault_keypair: Ed25519PrivKey,
+}
+
+impl VoucherService {
+    pub fn new(
+        mongodb: web::Data<MongoDBService>,
+        token_service: web::Data<TokenService>,
+        wallet_service: web::Data<WalletService>,
+        central_vault_keypair: Ed25519PrivKey,
+    ) -> Self {
+        Self { mongodb, token_service, wallet_service, central_vault_keypair }
+    }
+
+    /// Create a voucher. From the platform's holdings it is active straight away; from a
+    /// vendor it waits for the vendor to sign the returned `funding_transaction`.
+    pub async fn issue(&self, issuer_address: &str, funding: VoucherFunding, request: &CreateVoucherRequest) -> Result<Voucher, ApiError> {
+        if !request.amount.is_finite() || request.amount < 0.01 {
+            return Err(ApiError::ValidationError("Amount must be at least 0.01".to_string()));
+        }
+        let ttl_days = request.expires_in_days.unwrap_or(DEFAULT_VOUCHER_TTL_DAYS);
+        if !(1..=MAX_VOUCHER_TTL_DAYS).contains(&ttl_days) {
+            return Err(ApiError::ValidationError(format!("expires_in_days must be between 1 and {}", MAX_VOUCHER_TTL_DAYS)));
+        }
+        let note = request.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        if note.map_or(false, |n| n.chars().count() > MAX_NOTE_CHARS) {
+            return Err(ApiError::ValidationError(format!("Note must be at most {} characters", MAX_NOTE_CHARS)));
+        }
+        let token = self.mongodb.get_token_by_symbol(&request.token_symbol).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Token {} not found", request.token_symbol)))?;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut voucher = Voucher {
+            id: None,
+            code: generate_voucher_code(),
+            issuer_address: issuer_address.to_string(),
+            funding: funding.clone(),
+            token_symbol: request.token_symbol.clone(),
+            amount: (request.amount * 100.0).round() / 100.0,
+            note: note.map(str::to_string),
+            status: VoucherStatus::PendingFunding,
+            created_at: now,
+            updated_at: now,
+            expires_at: now + ttl_days * 24 * 60 * 60,
+            funding_transaction: None,
+            funding_tx_id: None,
+            redeemed_by: None,
+            redeemed_at: None,
+            redemption_tx_id: None,
+            refund_tx_id: None,
+            failure_reason: None,
+        };
+
+        match funding {
+            VoucherFunding::CentralVault => {
+                // Only lock what the central vault holds beyond other vouchers' escrow
+                let held = self.central_vault_balance(&token.token_id).await?;
+                let escrowed = (self.mongodb.escrowed_voucher_amount(&voucher.token_symbol).await? * 100.0).round() as u64;
+                if held < escrowed + voucher.units() {
+                    return Err(ApiError::ValidationError(format!("Central vault doesn't hold enough {} for this voucher", voucher.token_symbol)));
+                }
+                voucher.status = VoucherStatus::Active;
+            },
+            VoucherFunding::Issuer => {
+                voucher.funding_transaction = Some(self.funding_transaction(issuer_address, &token.token_id, voucher.units()).await?);
+            },
+        }
+
+        let voucher = self.mongodb.create_voucher(voucher).await?;
+        info!("{} issued voucher {:?} for {} {} ({})", issuer_address, voucher.id, voucher.amount, voucher.token_symbol, voucher.status);
+        Ok(voucher)
+    }
+
+    /// Submit the issuer's signed transfer into escrow and activate the voucher. The signed
+    /// allowance must be exactly the `funding_transaction` handed out at issuance. The voucher
+    /// moves to funding before the transfer is submitted, so it can't be cancelled meanwhile.
+    pub async fn fund(&self, voucher: &Voucher, signed_transaction: &str) -> Result<Voucher, ApiError> {
+        let id = voucher.id.ok_or_else(|| ApiError::InternalError("Voucher has no ID".to_string()))?;
+        if voucher.status != VoucherStatus::PendingFunding {
+            return Err(ApiError::Conflict(format!("Voucher is already {}", voucher.status)));
+        }
+        let expected = voucher.funding_transaction.as_deref()
+            .ok_or_else(|| ApiError::InternalError("Voucher has no funding transaction".to_string()))?;
+        let expected: Vec<serde_json::Value> = serde_json::from_str(expected)
+            .map_err(|e| ApiError::InternalError(format!("Stored funding transaction is invalid: {}", e)))?;
+        let signed: Vec<serde_json::Value> = serde_json::from_str(signed_transaction)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid signed transaction format: {}", e)))?;
+        if signed.len() != expected.len() || !signed.iter().zip(&expected).all(|(s, e)| contains_value(s, e)) {
+            return Err(ApiError::ValidationError("Signed transaction doesn't match the voucher's funding transaction".to_string()));
+        }
+        let allowances: Vec<SignedDebitAllowance> = serde_json::from_str(signed_transaction)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid signed transaction format: {}", e)))?;
+
+        self.mongodb.transition_voucher(&id, &[VoucherStatus::PendingFunding], VoucherStatus::Funding, doc! {}).await?
+            .ok_or_else(|| ApiError::Conflict("Voucher was updated, please reload it".to_string()))?;
+
+        let verifiables = allowances.into_iter().map(VerifiableType::DebitAllowance).collect();
+        let result = self.wallet_service.submit_verifiables(verifiables).await;
+        let mut touched = vec![self.central_vault_keypair.pub_key()];
+        touched.extend(WalletService::parse_public_key(&voucher.issuer_address).ok());
+        self.wallet_service.invalidate_balances(&touched);
+        let tx_id = match result {
+            Ok(tx_id) => tx_id,
+            Err(e) => {
+                // Refused, the issuer can sign again; unanswered, the tokens may be in escrow
+                let to = if e.is_rejection() { VoucherStatus::PendingFunding } else { VoucherStatus::Failed };
+                error!("Failed to fund voucher {}, now {}: {}", id, to, e);
+                if let Err(db_err) = self.mongodb.transition_voucher(&id, &[VoucherStatus::Funding], to, doc! { "failure_reason": e.to_string() }).await {
+                    error!("Failed to record funding failure of voucher {}: {}", id, db_err);
+                }
+                return Err(e.into());
+            },
+        };
+
+        let mut set = doc! { "funding_tx_id": tx_id.clone() };
+        set.insert("funding_transaction", mongodb::bson::Bson::Null);
+        set.insert("failure_reason", mongodb::bson::Bson::Null);
+        let voucher = self.mongodb.transition_voucher(&id, &[VoucherStatus::Funding], VoucherStatus::Active, set).await?
+            .ok_or_else(|| ApiError::InternalError("Voucher left funding state during transfer".to_string()))?;
+        info!("Voucher {} funded by {} (executor tx: {:?})", id, voucher.issuer_address, tx_id);
+        let event = ActivityEvent::new(&voucher.issuer_address, ActivityKind::Transfer, voucher_amounts(&voucher), "voucher", &id.to_hex())
+            .executor_tx_id(tx_id);
+        self.record_activity(event).await;
+        Ok(voucher)
+    }
+
+    /// Send a voucher's tokens to the redeemer. The voucher is claimed first so a code
+    /// can only be redeemed once; if the executor refuses the transfer it becomes
+    /// redeemable again, and if it may have landed the voucher is failed.
+    pub async fn redeem(&self, code: &str, redeemer: &str) -> Result<Voucher, ApiError> {
+        let redeemer_pubkey = WalletService::parse_public_key(redeemer)?;
+        let now = chrono::Utc::now().timestamp();
+        let voucher = match self.mongodb.claim_voucher(code, redeemer, now).await? {
+            Some(voucher) => voucher,
+            None => {
+                let voucher = self.mongodb.get_voucher_by_code(code).await?
+                    .ok_or_else(|| ApiError::NotFound("Voucher not found".to_string()))?;
+                return Err(match voucher.status {
+                    VoucherStatus::Active => ApiError::Conflict("Voucher has expired".to_string()),
+                    VoucherStatus::PendingFunding => ApiError::Conflict("Voucher isn't funded yet".to_string()),
+                    status => ApiError::Conflict(format!("Voucher is already {}", status)),
+                });
+            },
+        };
+        let id = voucher.id.ok_or_else(|| ApiError::InternalError("Voucher has no ID".to_string()))?;
+
+        match self.token_service.transfer_tokens(&self.central_vault_keypair, &redeemer_pubkey, &voucher.token_symbol, voucher.units()).await {
+            Ok(tx_id) => {
+                let voucher = self.mongodb.transition_voucher(&id, &[VoucherStatus::Redeeming], VoucherStatus::Redeemed, doc! { "redemption_tx_id": tx_id.clone() }).await?
+                    .ok_or_else(|| ApiError::InternalError("Voucher left redeeming state during transfer".to_string()))?;
+                info!("Voucher {} redeemed by {} for {} {}", id, redeemer, voucher.amount, voucher.token_symbol);
+                let event = ActivityEvent::new(redeemer, ActivityKind::Redemption, voucher_amounts(&voucher), "voucher", &id.to_hex())
+                    .counterparty(&voucher.issuer_address)
+                    .executor_tx_id(tx_id);
+                self.record_activity(event).await;
+                Ok(voucher)
+            },
+            Err(e @ TransferError::NotTransferred(_)) => {
+                error!("Failed to transfer voucher {} to {}: {}", id, redeemer, e);
+                let reset = doc! {
+                    "redeemed_by": mongodb::bson::Bson::Null,
+                    "redeemed_at": mongodb::bson::Bson::Null,
+                    "failure_reason": e.to_string(),
+                };
+                if let Err(e) = self.mongodb.transition_voucher(&id, &[VoucherStatus::Redeeming], VoucherStatus::Active, reset).await {
+                    error!("Failed to release voucher {} after failed redemption: {}", id, e);
+                }
+                Err(ApiError::InternalError("Failed to transfer voucher tokens, please try again".to_string()))
+            },
+            Err(e @ TransferError::Unknown(_)) => {
+                error!("Voucher {} may have been paid to {}, holding it as failed: {}", id, redeemer, e);
+                if let Err(e) = self.mongodb.transition_voucher(&id, &[VoucherStatus::Redeeming], VoucherStatus::Failed, doc! { "failure_reason": e.to_string() }).await {
+                    error!("Failed to mark voucher {} failed: {}", id, e);
+                }
+                Err(ApiError::InternalError("The voucher transfer may not have completed; the voucher is on hold until it's checked".to_string()))
+            },
+        }
+    }
+
+    /// Withdraw a voucher that hasn't been redeemed. Unfunded ones are just cancelled;
+    /// funded ones go back to the issuer as on expiry.
+    pub async fn cancel(&self, voucher: &Voucher) -> Result<Voucher, ApiError> {
+        let id = voucher.id.ok_or_else(|| ApiError::InternalError("Voucher has no ID".to_string()))?;
+        let closed = match voucher.status {
+            VoucherStatus::PendingFunding | VoucherStatus::Active => self.close(&id, &voucher.funding, VoucherStatus::Cancelled).await?,
+            ref status => return Err(ApiError::Conflict(format!("Voucher is already {}", status))),
+        };
+        closed.ok_or_else(|| ApiError::Conflict("Voucher was updated, please reload it".to_string()))
+    }
+
+    pub async fn expire_vouchers(&self) {
+        let vouchers = match self.mongodb.get_vouchers_to_expire(chrono::Utc::now().timestamp(), RETRY_AFTER_SECS, BATCH_SIZE).await {
+            Ok(vouchers) => vouchers,
+            Err(e) => {
+                error!("Failed to load expired vouchers: {}", e);
+                return;
+            }
+        };
+
+        for voucher in vouchers {
+            let Some(id) = voucher.id else { continue };
+            let result = match voucher.status {
+                VoucherStatus::Refunding => self.refund(&voucher).await.map(Some),
+                VoucherStatus::Redeeming | VoucherStatus::Funding => self.fail_interrupted(&id, voucher.status.clone()).await,
+                _ => self.close(&id, &voucher.funding, VoucherStatus::Expired).await,
+            };
+            match result {
+                Ok(Some(closed)) => info!("Voucher {} closed as {}", id, closed.status),
+                Ok(None) => {},
+                Err(e) => warn!("Failed to close expired voucher {}: {}", id, e),
+            }
+        }
+    }
+
+    /// Stop a voucher being redeemed. Platform-funded and unfunded vouchers end as
+    /// `unfunded_status`; vendor-funded ones are refunded to the vendor.
+    async fn close(&self, id: &ObjectId, funding: &VoucherFunding, unfunded_status: VoucherStatus) -> Result<Option<Voucher>, ApiError> {
+        let pending = self.mongodb.transition_voucher(id, &[VoucherStatus::PendingFunding], unfunded_status.clone(), doc! {}).await?;
+        if pending.is_some() {
+            return Ok(pending);
+        }
+        match funding {
+            VoucherFunding::CentralVault => self.mongodb.transition_voucher(id, &[VoucherStatus::Active], unfunded_status, doc! {}).await,
+            VoucherFunding::Issuer => match self.mongodb.transition_voucher(id, &[VoucherStatus::Active], VoucherStatus::Refunding, doc! {}).await? {
+                Some(voucher) => self.refund(&voucher).await.map(Some),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Fail a voucher whose redemption or funding was left in flight without its outcome
+    /// being recorded, since its transfer may have landed
+    async fn fail_interrupted(&self, id: &ObjectId, status: VoucherStatus) -> Result<Option<Voucher>, ApiError> {
+        let set = doc! { "failure_reason": "Interrupted before the transfer outcome was recorded" };
+        self.mongodb.transition_voucher(id, &[status], VoucherStatus::Failed, set).await
+    }
+
+    /// Send a refunding voucher's tokens back to the issuer. A refused transfer stays
+    /// refunding and is retried by the next sweep; one that may have landed is failed.
+    async fn refund(&self, voucher: &Voucher) -> Result<Voucher, ApiError> {
+        let id = voucher.id.ok_or_else(|| ApiError::InternalError("Voucher has no ID".to_string()))?;
+        let issuer_pubkey = WalletService::parse_public_key(&voucher.issuer_address)?;
+        match self.token_service.transfer_tokens(&self.central_vault_keypair, &issuer_pubkey, &voucher.token_symbol, voucher.units()).await {
+            Ok(tx_id) => {
+                let refunded = self.mongodb.transition_voucher(&id, &[VoucherStatus::Refunding], VoucherStatus::Refunded, doc! { "refund_tx_id": tx_id.clone() }).await?
+                    .ok_or_else(|| ApiError::InternalError("Voucher left refunding state during transfer".to_string()))?;
+                let event = ActivityEvent::new(&voucher.issuer_address, ActivityKind::Refund, voucher_amounts(voucher), "voucher", &id.to_hex())
+                    .executor_tx_id(tx_id);
+                self.record_activity(event).await;
+                Ok(refunded)
+            },
+            Err(e) => {
+                let to = match e {
+                    TransferError::NotTransferred(_) => VoucherStatus::Refunding,
+                    TransferError::Unknown(_) => VoucherStatus::Failed,
+                };
+                self.mongodb.transition_voucher(&id, &[VoucherStatus::Refunding], to, doc! { "failure_reason": e.to_string() }).await?;
+                Err(ApiError::InternalError(format!("Failed to refund voucher: {}", e)))
+            },
+        }
+    }
+
+    async fn record_activity(&self, event: ActivityEvent) {
+        if let Err(e) = self.mongodb.record_activity(event).await {
+            error!("Failed to record voucher activity: {}", e);
+        }
+    }
+
+    async fn central_vault_balance(&self, token_id: &str) -> Result<u64, ApiError> {
+        let vault = self.wallet_service.get_vault(&self.central_vault_keypair.pub_key()).await?;
+        Ok(vault.map_or(0, |vault| WalletService::vault_balances(&vault).get(token_id).copied().unwrap_or(0)))
+    }
+
+    /// Unsigned transfer of `units` of a token from the issuer's vault into the central vault
+    async fn funding_transaction(&self, issuer_address: &str, token_id: &str, units: u64) -> Result<String, ApiError> {
+        let issuer_pubkey = WalletService::parse_public_key(issuer_address)?;
+        // Uncached, since the allowance signs against the vault's current nonce
+        let issuer_vault = self.wallet_service.fetch_vault(&issuer_pubkey).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Vault not found for {}", issuer_address)))?;
+        let balance = WalletService::vault_balances(&issuer_vault).get(token_id).copied().unwrap_or(0);
+        if balance < units {
+            return Err(ApiError::ValidationError("Insufficient funds".to_string()));
+        }
+
+        let (token_pubkey, token_shard) = token_id.split_once(',')
+            .ok_or_else(|| ApiError::InternalError(format!("Invalid token ID format: {}", token_id)))?;
+        let token_pubkey = Ed25519PubKey::from_str(token_pubkey)
+            .map_err(|e| ApiError::InternalError(format!("Invalid token pubkey: {}", e)))?;
+        let token_shard = token_shard.parse::<u64>()
+            .map_err(|e| ApiError::InternalError(format!("Invalid token shard: {}", e)))?;
+
+        let shard = Shard::from(1u64);
+        let mut allowances = BTreeMap::new();
+        allowances.insert(TokenKind::NonNative(VaultId::new(token_pubkey, Shard::from(token_shard))), units);
+        let debit_allowance = DebitAllowance {
+            debited: VaultId::new(issuer_pubkey, shard),
+            credited: VaultId::new(self.central_vault_keypair.pub_key(), shard),
+            new_nonce: issuer_vault.nonce() + 1,
+            allowances,
+        };
+        serde_json::to_string(&vec![debit_allowance])
+            .map_err(|e| ApiError::InternalError(format!("Failed to serialize debit allowance: {}", e)))
+    }
+}
+
+fn voucher_amounts(voucher: &Voucher) -> Vec<ActivityAmount> {
+    vec![ActivityAmount { token_symbol: voucher.token_symbol.clone(), amount: voucher.amount }]
+}
@@ -3,7 +3,7 @@ use log::{info, error};
 use serde::Serialize;
 use serde_json::Value;
 use std::str::FromStr;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use delta_executor_sdk::{
     self,
@@ -15,9 +15,10 @@ use delta_executor_sdk::{
     },
     runtime::Error as RuntimeError,
 };
-use crate::services::executor_client::ExecutorClient;
+use crate::services::executor_client::{ExecutorClient, ExecutionStatus, ExecutorError};
 use crate::services::MongoDBService;
-use crate::models::Token;
+use crate::models::{ApiError, Holding, Token};
+use crate::utils::holdings::{allowance_deltas, net_deltas};
 
 
 #[derive(Debug, Serialize)]
@@ -33,6 +34,7 @@ pub enum WalletError {
     InvalidPublicKeyLength(usize),
     RuntimeError(String),
     HexDecodeError(hex::FromHexError),
+    Executor(ExecutorError),
 }
 
 impl fmt::Display for WalletError {
@@ -42,18 +44,42 @@ impl fmt::Display for WalletError {
             WalletError::InvalidPublicKeyLength(len) => write!(f, "Invalid public key length: {} bytes (expected 32)", len),
             WalletError::RuntimeError(msg) => write!(f, "Runtime error: {}", msg),
             WalletError::HexDecodeError(e) => write!(f, "Hex decode error: {}", e),
+            WalletError::Executor(e) => write!(f, "{}", e),
         }
     }
 }
 
 impl std::error::Error for WalletError {}
 
+impl WalletError {
+    /// Whether nothing was applied: every error but an executor that didn't answer
+    pub fn is_rejection(&self) -> bool {
+        !matches!(self, WalletError::Executor(e) if !e.is_rejection())
+    }
+}
+
 impl From<hex::FromHexError> for WalletError {
     fn from(err: hex::FromHexError) -> Self {
         WalletError::HexDecodeError(err)
     }
 }
 
+impl From<ExecutorError> for WalletError {
+    fn from(err: ExecutorError) -> Self {
+        WalletError::Executor(err)
+    }
+}
+
+impl From<WalletError> for ApiError {
+    fn from(err: WalletError) -> Self {
+        match err {
+            WalletError::Executor(e) => e.into(),
+            WalletError::InvalidPublicKeyFormat(_) | WalletError::InvalidPublicKeyLength(_) | WalletError::HexDecodeError(_) => ApiError::ValidationError(err.to_string()),
+            WalletError::RuntimeError(_) => ApiError::InternalError(err.to_string()),
+        }
+    }
+}
+
 impl From<RuntimeError> for WalletError {
     fn from(err: RuntimeError) -> Self {
         WalletError::RuntimeError(format!("{:?}", err))
@@ -62,11 +88,11 @@ impl From<RuntimeError> for WalletError {
 
 #[derive(Debug, Serialize)]
 pub struct TokenMetadataInfo {
-    name: String,
-    symbol: String,
-    market_valuation: f64,
-    total_allocated: u64,
-    token_image_url: String, 
+    pub name: String,
+    pub symbol: String,
+    pub market_valuation: f64,
+    pub total_allocated: u64,
+    pub token_image_url: String, 
 }
 
 impl Default for TokenMetadataInfo {
@@ -83,9 +109,16 @@ impl Default for TokenMetadataInfo {
 
 #[derive(Debug, Serialize)]
 pub struct TokenInfo {
-    balance: u64,
+    pub balance: u64,
     #[serde(flatten)]
-    metadata: TokenMetadataInfo,
+    pub metadata: TokenMetadataInfo,
+}
+
+/// Token balances across an account's linked wallets
+#[derive(Debug, Serialize)]
+pub struct AccountBalances {
+    pub total: HashMap<String, TokenInfo>,
+    pub wallets: HashMap<String, HashMap<String, TokenInfo>>,
 }
 
 pub struct WalletService {
@@ -94,9 +127,9 @@ pub struct WalletService {
 }
 
 impl WalletService {
-    pub fn new(mongodb: web::Data<MongoDBService>) -> Self {
+    pub fn new(mongodb: web::Data<MongoDBService>, executor_client: ExecutorClient) -> Self {
         Self { 
-            executor_client: ExecutorClient::new(),
+            executor_client,
             mongodb,
         }
     }
@@ -106,7 +139,16 @@ impl WalletService {
         self.executor_client
             .get_vault(pubkey)
             .await
-            .map_err(|e| WalletError::RuntimeError(e))
+            .map_err(WalletError::from)
+    }
+
+    /// Get a vault straight from the executor, skipping the balance cache, for building
+    /// transactions that sign against its nonce
+    pub async fn fetch_vault(&self, pubkey: &Ed25519PubKey) -> Result<Option<Vault>, WalletError> {
+        self.executor_client
+            .fetch_vault(pubkey)
+            .await
+            .map_err(WalletError::from)
     }
 
 
@@ -139,8 +181,99 @@ impl WalletService {
 
     pub async fn map_vault_tokens(&self, vault: &Vault) -> Result<HashMap<String, TokenInfo>, WalletError> {
         // 1. Prepare token IDs and balances for batch query
+        let token_balances = Self::vault_balances(vault);
+
+        // 2. Batch query MongoDB for token metadata
+        let metadata_list = self.token_metadata(token_balances.keys().cloned().collect()).await?;
+
+        // 3. Create final mapping with both balance and metadata
+        Ok(Self::with_metadata(token_balances, &metadata_list))
+    }
+
+    /// Balances of several wallets, per wallet and summed per token.
+    /// Wallets without a vault yet count as empty.
+    pub async fn account_balances(&self, wallet_addresses: &[String]) -> Result<AccountBalances, WalletError> {
+        let mut per_wallet = Vec::with_capacity(wallet_addresses.len());
+        for address in wallet_addresses {
+            let pubkey = Self::parse_public_key(address)?;
+            let balances = match self.get_vault(&pubkey).await? {
+                Some(vault) => Self::vault_balances(&vault),
+                None => HashMap::new(),
+            };
+            per_wallet.push((address.clone(), balances));
+        }
+
+        let mut total: HashMap<String, u64> = HashMap::new();
+        for (_, balances) in &per_wallet {
+            for (token_id, balance) in balances {
+                *total.entry(token_id.clone()).or_default() += balance;
+            }
+        }
+
+        let metadata_list = self.token_metadata(total.keys().cloned().collect()).await?;
+        Ok(AccountBalances {
+            total: Self::with_metadata(total, &metadata_list),
+            wallets: per_wallet.into_iter()
+                .map(|(address, balances)| (address, Self::with_metadata(balances, &metadata_list)))
+                .collect(),
+        })
+    }
+
+    async fn token_metadata(&self, token_ids: Vec<String>) -> Result<Vec<Token>, WalletError> {
+        self.mongodb
+            .get_tokens_by_ids(&token_ids)
+            .await
+            .map_err(|e| WalletError::RuntimeError(format!("Failed to fetch token metadata: {}", e)))
+    }
+
+    fn with_metadata(token_balances: HashMap<String, u64>, metadata_list: &[Token]) -> HashMap<String, TokenInfo> {
+        token_balances
+            .into_iter()
+            .map(|(token_id, balance)| {
+                let metadata = metadata_list
+                    .iter()
+                    .find(|m| m.token_id == token_id)
+                    .map(|m| TokenMetadataInfo {
+                        name: m.token_name.clone(),
+                        token_image_url: m.token_image_url.clone().unwrap_or_default(),
+                        symbol: m.token_symbol.clone().unwrap_or_default(),
+                        total_allocated: m.total_allocated,
+                        market_valuation: m.market_valuation,
+                    })
+                    .unwrap_or_default();
+
+                (token_id, TokenInfo { balance, metadata })
+            })
+            .collect()
+    }
+
+    /// Projected holding of `token_id` in each user wallet that holds any. Platform vaults
+    /// (central, escrow, issuers) aren't users and are left out.
+    pub async fn token_holdings(&self, token_id: &str) -> Result<Vec<Holding>, ApiError> {
+        let users: HashSet<String> = self.mongodb.get_all_wallet_addresses().await?.into_iter().collect();
+        Ok(self.mongodb.get_token_holdings(token_id).await?
+            .into_iter()
+            .filter(|holding| users.contains(&holding.wallet_address))
+            .collect())
+    }
+
+    /// A wallet's balances from the holdings projection rather than the executor, for when
+    /// the executor can't be reached. May miss the latest transfers.
+    pub async fn projected_balances(&self, wallet_address: &str) -> Result<HashMap<String, TokenInfo>, WalletError> {
+        let token_balances: HashMap<String, u64> = self.mongodb.get_wallet_holdings(wallet_address)
+            .await
+            .map_err(|e| WalletError::RuntimeError(format!("Failed to read projected holdings: {}", e)))?
+            .into_iter()
+            .map(|(token_id, balance)| (token_id, balance as u64))
+            .collect();
+        let metadata_list = self.token_metadata(token_balances.keys().cloned().collect()).await?;
+        Ok(Self::with_metadata(token_balances, &metadata_list))
+    }
+
+    /// Raw token holdings of a vault, keyed by token id, in base units
+    pub fn vault_balances(vault: &Vault) -> HashMap<String, u64> {
         // Get token balances from vault data
-        let token_balances = if let Some(data) = vault.data() {
+        if let Some(data) = vault.data() {
             // Convert VaultDataType to serde_json::Value
             let data_value = serde_json::to_value(data).unwrap_or(Value::Null);
             if let Some(holdings) = data_value.get("TokenHoldings") {
@@ -160,41 +293,37 @@ impl WalletService {
             }
         } else {
             HashMap::new()
-        };
+        }
+    }
 
-        // 2. Batch query MongoDB for token metadata
-        let metadata_list = self.mongodb
-            .get_tokens_by_ids(&token_balances.keys().cloned().collect::<Vec<_>>())
+    /// Submit verifiable messages to the executor, returning its transaction ID if reported.
+    /// Accepted debit allowances are added to the holdings projection.
+    pub async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<Option<String>, WalletError> {
+        let deltas = serde_json::to_value(&verifiables)
+            .map(|submitted| net_deltas(allowance_deltas(&submitted)))
+            .unwrap_or_default();
+        let tx_id = self.executor_client
+            .submit_verifiables(verifiables)
             .await
-            .map_err(|e| WalletError::RuntimeError(format!("Failed to fetch token metadata: {}", e)))?;
-
-        // 3. Create final mapping with both balance and metadata
-        Ok(token_balances
-            .into_iter()
-            .map(|(token_id, balance)| {
-                let metadata = metadata_list
-                    .iter()
-                    .find(|m| m.token_id == token_id)
-                    .map(|m| TokenMetadataInfo {
-                        name: m.token_name.clone(),
-                        token_image_url: m.token_image_url.clone().unwrap_or_default(),
-                        symbol: m.token_symbol.clone().unwrap_or_default(),
-                        total_allocated: m.total_allocated,
-                        market_valuation: m.market_valuation,
-                    })
-                    .unwrap_or_default();
+            .map_err(WalletError::from)?;
+        // Best effort: reconciliation corrects the projection if this is lost
+        if let Err(e) = self.mongodb.apply_holding_deltas(&deltas).await {
+            error!("Failed to project holdings of submitted transfer: {}", e);
+        }
+        Ok(tx_id)
+    }
 
-                (token_id, TokenInfo { balance, metadata })
-            })
-            .collect())
+    /// Drop cached balances of vaults a just-submitted transfer touched
+    pub fn invalidate_balances(&self, pubkeys: &[Ed25519PubKey]) {
+        self.executor_client.invalidate_vaults(pubkeys);
     }
 
-    /// Submit verifiable messages to the executor
-    pub async fn submit_verifiables(&self, verifiables: Vec<VerifiableType>) -> Result<(), WalletError> {
+    /// Execution status of a previously submitted transaction
+    pub async fn get_execution_status(&self, tx_id: &str) -> Result<ExecutionStatus, WalletError> {
         self.executor_client
-            .submit_verifiables(verifiables)
+            .get_status(tx_id)
             .await
-            .map_err(|e| WalletError::RuntimeError(e))
+            .map_err(WalletError::from)
     }
     
     /// Parse a public key from a string (supports both Base58 and hex formats)
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::time::Duration;
+use actix_web::web;
+use futures::stream::{self, StreamExt};
+use log::{info, warn, error};
+use crate::handlers::stripe_event_router::{mark_processed, StripeEventRouter, WebhookContext};
+use crate::models::{WebhookJob, WebhookJobStatus, MAX_WEBHOOK_JOB_ATTEMPTS};
+use crate::services::{CauseService, MongoDBService, WebhookService};
+use crate::utils::replica::replica_instance_id;
+
+/// Queued jobs looked at per tick
+const BATCH_SIZE: i64 = 500;
+
+/// How long a worker holds a job it claimed; after that it's taken to have died and
+/// another worker may take the job over
+const LEASE_SECS: i64 = 10 * 60;
+
+/// First retry delay, doubled for each further attempt
+const RETRY_BASE_SECS: i64 = 30;
+
+/// Applies the Stripe events the webhooks queued, at most `concurrency` at a time. Jobs
+/// with the same ordering key (the wallet credited) run one at a time in arrival order,
+/// across every replica, and a failed job holds back later ones for its key until it
+/// succeeds or runs out of attempts, when it's recorded as a webhook failure for replay.
+/// Workers lease the jobs they claim, and only the lease holder can mark a job processed.
+#[derive(Clone)]
+pub struct WebhookQueueService {
+    router: web::Data<StripeEventRouter>,
+    mongodb: web::Data<MongoDBService>,
+    webhook_service: web::Data<WebhookService>,
+    cause_service: web::Data<CauseService>,
+    concurrency: usize,
+    instance_id: String,
+}
+
+impl WebhookQueueService {
+    pub fn new(
+        router: web::Data<StripeEventRouter>,
+        mongodb: web::Data<MongoDBService>,
+        webhook_service: web::Data<WebhookService>,
+        cause_service: web::Data<CauseService>,
+        concurrency: usize,
+    ) -> Self {
+        let instance_id = replica_instance_id();
+        Self { router, mongodb, webhook_service, cause_service, concurrency: concurrency.max(1), instance_id }
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Work through the queue every `interval` in the background
+    pub fn start_workers(self, interval: Duration) {
+        info!("Processing queued webhook events every {:?}, {} at a time", interval, self.concurrency);
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.process_queue().await;
+            }
+        });
+    }
+
+    pub async fn process_queue(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let jobs = match self.mongodb.get_queued_webhook_jobs(BATCH_SIZE).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("Failed to load queued webhook jobs: {}", e);
+                return;
+            }
+        };
+
+        // The oldest job of each key, if it's due; the rest wait for a later tick. A key whose
+        // earlier job another worker is still processing is refused at the claim.
+        let mut seen = HashSet::new();
+        let due: Vec<WebhookJob> = jobs.into_iter()
+            .filter(|job| seen.insert(job.ordering_key.clone()))
+            .filter(|job| job.status == WebhookJobStatus::Processing || job.next_attempt_at <= now)
+            .collect();
+
+        stream::iter(due)
+            .for_each_concurrent(self.concurrency, |job| self.process(job))
+            .await;
+    }
+
+    async fn process(&self, job: WebhookJob) {
+        let Some(job_id) = job.id else { return };
+        let taking_over = job.status == WebhookJobStatus::Processing;
+        let lease_expires_at = chrono::Utc::now().timestamp() + LEASE_SECS;
+        let job = match self.mongodb.claim_webhook_job(&job_id, &self.instance_id, lease_expires_at).await {
+            Ok(Some(job)) => job,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to claim webhook job {}: {}", job_id, e);
+                return;
+            }
+        };
+        if taking_over {
+            warn!("Taking over {} event {} from a worker whose lease ran out", job.endpoint, job.event_id);
+        }
+
+        let ctx = WebhookContext {
+            webhook_service: self.webhook_service.clone(),
+            mongodb: self.mongodb.clone(),
+            cause_service: self.cause_service.clone(),
+        };
+        match self.router.apply(&job, &ctx).await {
+            Ok(handled) => match self.mongodb.finish_webhook_job(&job, &self.instance_id).await {
+                Ok(true) => {
+                    if handled {
+                        mark_processed(job.endpoint, &job.event_id, &job.event_type, &ctx).await;
+                    }
+                },
+                Ok(false) => warn!("Lost the lease on {} event {} while applying it", job.endpoint, job.event_id),
+                Err(e) => error!("Failed to mark webhook job {} processed: {}", job_id, e),
+            },
+            Err(e) if job.attempts < MAX_WEBHOOK_JOB_ATTEMPTS => {
+                let retry_at = chrono::Utc::now().timestamp() + (RETRY_BASE_SECS << (job.attempts - 1).clamp(0, 10));
+                warn!("{} event {} failed (attempt {}), retrying at {}: {}", job.endpoint, job.event_id, job.attempts, retry_at, e);
+                match self.mongodb.fail_webhook_job(&job_id, &self.instance_id, &e.to_string(), Some(retry_at)).await {
+                    Ok(true) => {},
+                    Ok(false) => warn!("Lost the lease on {} event {} while applying it", job.endpoint, job.event_id),
+                    Err(e) => error!("Failed to requeue webhook job {}: {}", job_id, e),
+                }
+            },
+            Err(e) => {
+                error!("{} event {} failed after {} attempts: {}", job.endpoint, job.event_id, job.attempts, e);
+                match self.mongodb.fail_webhook_job(&job_id, &self.instance_id, &e.to_string(), None).await {
+                    // Keep the verified payload so it can be replayed once the underlying issue is fixed
+                    Ok(true) => {
+                        if let Err(db_err) = self.mongodb.record_webhook_failure(job.endpoint, &job.event_id, &job.event_type, &job.payload, &e.to_string()).await {
+                            error!("Failed to record webhook failure for event {}: {:?}", job.event_id, db_err);
+                        }
+                    },
+                    Ok(false) => warn!("Lost the lease on {} event {} while applying it", job.endpoint, job.event_id),
+                    Err(e) => error!("Failed to mark webhook job {} failed: {}", job_id, e),
+                }
+            },
+        }
+    }
+}
@@ -1,48 +1,216 @@
-use std::sync::Arc;
-use log::{info, error};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use log::{info, warn, error};
 use delta_executor_sdk::base::crypto::{Ed25519PubKey, Ed25519PrivKey};
 use std::str::FromStr;
 
-use crate::models::WebhookError;
+use crate::models::{ActivityEvent, ApiError, ActivityKind, ActivityAmount, WebhookError, WebhookEndpoint, WebhookSecretStatus, AuditLog, AuditAction, CreditReservation, CreditReservationStatus, DepositRecord, ManualCreditRequest, ResolveCreditRequest, MatchEvent, MatchEventStatus, RoundContribution};
+use crate::utils::audit::STRIPE_WEBHOOK_ACTOR;
 use crate::utils::bonding_curve::BondingCurve;
-use super::{TokenService, MongoDBService};
-use mongodb::bson::oid::ObjectId;
+use crate::utils::email_verification::{sign_verification_token, verify_verification_token, configured_secret, DEPOSIT_CLAIM_SCOPE, VERIFICATION_TTL_SECS};
+use crate::utils::matching::compute_match;
+use crate::utils::payment_calculator::ON_CHAIN_UNITS_PER_TOKEN;
+use crate::utils::redaction::redact;
+use super::{EmailService, TokenService, TransferError, MongoDBService, PushService};
+use mongodb::bson::{doc, oid::ObjectId};
+
+/// Matches the retry sweep pays per run
+const MATCH_RETRY_BATCH_SIZE: i64 = 100;
+
+/// How long a match or its credit can stay pending before the sweep takes it as interrupted
+const MATCH_STALE_SECS: i64 = 600;
+
+/// Tokens credited to a wallet and the executor transaction that moved them
+#[derive(Debug, Clone)]
+pub struct CreditReceipt {
+    pub tokens: f64,
+    pub executor_tx_id: Option<String>,
+    pub price_usd: Option<f64>,  // average bonding-curve price per whole token, for cause tokens
+}
+
+/// Average USD price of a whole token when `amount_usd` bought `units` on-chain units, or
+/// None if it bought none
+fn price_per_token(amount_usd: f64, units: f64) -> Option<f64> {
+    (units > 0.0).then(|| amount_usd / (units / ON_CHAIN_UNITS_PER_TOKEN))
+}
+
+/// A donation receipt's body. Deposits record tokens in on-chain units; donors see whole tokens.
+fn donation_receipt_text(cause_name: &str, deposit: &DepositRecord, price_usd: Option<f64>, payment_id: &str) -> String {
+    let price = price_usd.map_or_else(String::new, |price| format!("Price per token: ${:.4}\n", price));
+    format!(
+        "Thank you for your donation to {cause}.\n\n\
+         Amount: ${amount:.2}\n\
+         Tokens received: {tokens:.2} {symbol}\n\
+         {price}\
+         Credited to wallet: {wallet}\n\
+         Date: {date}\n\
+         Reference: {reference}\n",
+        cause = cause_name,
+        amount = deposit.amount_deposited_usd,
+        tokens = deposit.amount_tokens_received / ON_CHAIN_UNITS_PER_TOKEN,
+        symbol = deposit.token_symbol,
+        price = price,
+        wallet = deposit.wallet_address,
+        date = chrono::DateTime::from_timestamp(deposit.created_at, 0).map_or_else(String::new, |at| at.format("%Y-%m-%d %H:%M UTC").to_string()),
+        reference = payment_id,
+    )
+}
 
 pub struct WebhookService {
-    stripe_secret: String,
-    stripe_purchases_secret: String,
+    // Current secret first; older ones keep verifying while Stripe rolls over
+    stripe_secrets: Vec<String>,
+    stripe_purchases_secrets: Vec<String>,
+    secret_usage: Mutex<HashMap<(WebhookEndpoint, usize), (u64, i64)>>,  // (matches, last matched at)
     token_service: Arc<TokenService>,
     mongodb_service: Arc<MongoDBService>,
     central_vault_keypair: Ed25519PrivKey,
     network_goods_vault_keypair: Ed25519PrivKey,
+    matching_vault_keypair: Option<Ed25519PrivKey>,
+    push_service: Arc<PushService>,
+    email_service: Arc<EmailService>,
 }
 
 impl WebhookService {
     pub fn new(
-        stripe_secret: String,
-        stripe_purchases_secret: String,
+        stripe_secrets: Vec<String>,
+        stripe_purchases_secrets: Vec<String>,
         token_service: Arc<TokenService>,
         mongodb_service: Arc<MongoDBService>,
         central_vault_keypair: Ed25519PrivKey,
         network_goods_vault_keypair: Ed25519PrivKey,
+        matching_vault_keypair: Option<Ed25519PrivKey>,
+        push_service: Arc<PushService>,
+        email_service: Arc<EmailService>,
     ) -> Self {
         info!("Network goods vault address: {}", network_goods_vault_keypair.pub_key());
+        if stripe_secrets.len() > 1 || stripe_purchases_secrets.len() > 1 {
+            info!("Webhook secret rotation in progress: {} connect, {} purchases secrets", stripe_secrets.len(), stripe_purchases_secrets.len());
+        }
         Self {
-            stripe_secret,
-            stripe_purchases_secret,
+            stripe_secrets,
+            stripe_purchases_secrets,
+            secret_usage: Mutex::new(HashMap::new()),
             token_service,
             mongodb_service,
             central_vault_keypair,
             network_goods_vault_keypair,
+            matching_vault_keypair,
+            push_service,
+            email_service,
+        }
+    }
+
+    /// Let the wallet's devices know tokens arrived
+    pub async fn deposit_credited(&self, deposit: &DepositRecord) {
+        self.push_service.deposit_credited(deposit).await;
+    }
+
+    /// Email a donor the tokens their donation bought, which are only known once the webhook
+    /// has run the bonding curve. The donation is already credited, so failures are only logged.
+    pub async fn send_donation_receipt(&self, email: &str, deposit: &DepositRecord, receipt: &CreditReceipt, payment_id: &str) {
+        let cause_name = match self.mongodb_service.get_cause_by_token_symbol(&deposit.token_symbol).await {
+            Ok(Some(cause)) => cause.name,
+            _ => deposit.token_symbol.clone(),
+        };
+        let text = donation_receipt_text(&cause_name, deposit, receipt.price_usd, payment_id);
+        match self.email_service.send(email, &format!("Your donation receipt for {}", cause_name), &text).await {
+            Ok(()) => info!("Sent donation receipt for payment {}", payment_id),
+            Err(e) => error!("Failed to send donation receipt for payment {}: {}", payment_id, e),
+        }
+    }
+
+    /// Email a link for claiming the deposits held for `email` into a wallet, proving the
+    /// claimant can read that inbox
+    pub async fn send_claim_link(&self, email: &str) -> Result<(), String> {
+        let secret = Self::claim_secret().map_err(|e| e.to_string())?;
+        let email = email.trim().to_lowercase();
+        let expires_at = chrono::Utc::now().timestamp() + VERIFICATION_TTL_SECS;
+        let token = sign_verification_token(secret, DEPOSIT_CLAIM_SCOPE, &email, expires_at);
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let link = reqwest::Url::parse_with_params(
+            &format!("{}/claim", frontend_url),
+            &[("email", email.as_str()), ("token", token.as_str())],
+        ).map_err(|e| format!("Invalid FRONTEND_URL: {}", e))?;
+        let text = format!(
+            "Thank you for your donation. It was paid without a wallet, so its tokens are being held for this address.\n\n\
+             Open this link in the app to claim them into your wallet:\n\n{}\n\n\
+             The link is valid for 48 hours; you can ask for a new one from the claim page.\n",
+            link
+        );
+        self.email_service.send(&email, "Claim your donation tokens", &text).await
+    }
+
+    pub fn verify_claim_token(&self, email: &str, token: &str) -> Result<(), ApiError> {
+        verify_verification_token(Self::claim_secret()?, DEPOSIT_CLAIM_SCOPE, email, token, chrono::Utc::now().timestamp())
+            .map_err(ApiError::Unauthorized)
+    }
+
+    /// Fails unless claim links can be sent and verified
+    pub fn require_claim_links(&self) -> Result<(), ApiError> {
+        Self::claim_secret().map(|_| ())
+    }
+
+    /// Claim links are checked by whichever instance the claim reaches, possibly after a
+    /// restart, so unlike draft verification they need EMAIL_VERIFICATION_SECRET set
+    fn claim_secret() -> Result<&'static [u8], ApiError> {
+        configured_secret().ok_or_else(|| ApiError::ServiceUnavailable("Deposit claims need EMAIL_VERIFICATION_SECRET to be set".to_string()))
+    }
+
+    fn secrets(&self, endpoint: WebhookEndpoint) -> &[String] {
+        match endpoint {
+            WebhookEndpoint::Connect => &self.stripe_secrets,
+            WebhookEndpoint::Purchases => &self.stripe_purchases_secrets,
         }
     }
 
-    pub fn get_stripe_secret(&self) -> &str {
-        &self.stripe_secret
+    /// Verify a webhook against each of the endpoint's secrets in turn. If none match,
+    /// the error from the current secret is returned.
+    pub fn construct_event(&self, endpoint: WebhookEndpoint, payload: &str, signature: &str) -> Result<stripe::Event, WebhookError> {
+        let mut first_error = None;
+        for (index, secret) in self.secrets(endpoint).iter().enumerate() {
+            match stripe::Webhook::construct_event(payload, signature, secret) {
+                Ok(event) => {
+                    if index > 0 {
+                        info!("{} webhook {} verified with previous secret #{}", endpoint, event.id, index);
+                    }
+                    let mut usage = self.secret_usage.lock().unwrap_or_else(|e| e.into_inner());
+                    let entry = usage.entry((endpoint, index)).or_insert((0, 0));
+                    entry.0 += 1;
+                    entry.1 = chrono::Utc::now().timestamp();
+                    return Ok(event);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(match first_error {
+            Some(e) => WebhookError::StripeError(e),
+            None => WebhookError::InvalidPayload(format!("No {} webhook secret configured", endpoint)),
+        })
     }
 
-    pub fn get_stripe_purchases_secret(&self) -> &str {
-        &self.stripe_purchases_secret
+    /// How often each configured secret has verified an event since startup
+    pub fn secret_statuses(&self) -> Vec<WebhookSecretStatus> {
+        let usage = self.secret_usage.lock().unwrap_or_else(|e| e.into_inner());
+        [WebhookEndpoint::Connect, WebhookEndpoint::Purchases]
+            .into_iter()
+            .flat_map(|endpoint| {
+                self.secrets(endpoint).iter().enumerate().map(move |(index, secret)| (endpoint, index, secret))
+            })
+            .map(|(endpoint, index, secret)| {
+                let (matches, last_matched_at) = usage.get(&(endpoint, index))
+                    .map(|(count, at)| (*count, Some(*at)))
+                    .unwrap_or((0, None));
+                WebhookSecretStatus {
+                    endpoint,
+                    index,
+                    hint: secret_hint(secret),
+                    matches,
+                    last_matched_at,
+                }
+            })
+            .collect()
     }
 
     pub async fn credit_account(
@@ -50,10 +218,27 @@ impl WebhookService {
         token_symbol: &str,
         amount: i64,
         user_address: &str,
-    ) -> Result<f64, WebhookError> {
+    ) -> Result<CreditReceipt, WebhookError> {
+        let receipt = self.transfer_credit(token_symbol, amount, user_address).await?;
+        self.record_credit_audit(user_address, doc! {
+            "token_symbol": token_symbol,
+            "amount": amount,
+            "executor_tx_id": receipt.executor_tx_id.clone(),
+        }).await;
+        Ok(receipt)
+    }
+
+    /// Send `amount` base units of a token from the central vault to a wallet. Callers
+    /// record the audit entry, under whoever asked for the credit.
+    async fn transfer_credit(
+        &self,
+        token_symbol: &str,
+        amount: i64,
+        user_address: &str,
+    ) -> Result<CreditReceipt, WebhookError> {
         info!(
             "Starting credit_account for user: {}, token: {}, amount: {}", 
-            user_address, token_symbol, amount
+            redact(user_address), token_symbol, amount
         );
         
         // Convert i64 to u64 safely
@@ -69,7 +254,7 @@ impl WebhookService {
             .map_err(|e| WebhookError::InvalidPublicKey(e.to_string()))?;
 
         // Transfer tokens
-        self.token_service
+        let executor_tx_id = self.token_service
             .transfer_tokens(
                 &self.central_vault_keypair,
                 &user_pubkey,
@@ -77,10 +262,10 @@ impl WebhookService {
                 amount_u64,
             )
             .await
-            .map_err(|e| WebhookError::TokenTransferError(e.to_string()))?;
+            .map_err(WebhookError::from)?;
 
-        info!("Successfully credited {} tokens to user {}", amount, user_address);
-        Ok(amount_u64 as f64)
+        info!("Successfully credited {} tokens to user {}", amount, redact(user_address));
+        Ok(CreditReceipt { tokens: amount_u64 as f64, executor_tx_id, price_usd: None })
     }
 
     pub async fn credit_account_with_fee_split(
@@ -88,10 +273,10 @@ impl WebhookService {
         token_symbol: &str,
         total_amount: i64,
         user_address: &str,
-    ) -> Result<f64, WebhookError> {
+    ) -> Result<CreditReceipt, WebhookError> {
         info!(
             "Starting credit_account_with_fee_split for user: {}, token: {}, total amount: {} units", 
-            user_address, token_symbol, total_amount
+            redact(user_address), token_symbol, total_amount
         );
         
         // Calculate amounts
@@ -104,7 +289,7 @@ impl WebhookService {
         let amount_in_dollars = amount_to_cause as f64 / 100.0;
         
         // Get current bonding curve state by looking up cause by token symbol
-        let (tokens_minted, new_price) = if token_symbol != "USD" && token_symbol != "unknown" {
+        let (tokens_minted, price_usd) = if token_symbol != "USD" && token_symbol != "unknown" {
             match self.mongodb_service.get_cause_by_token_symbol(token_symbol).await {
                 Ok(Some(cause)) => {
                     let curve = BondingCurve::new();
@@ -123,21 +308,21 @@ impl WebhookService {
                     ).await.map_err(|e| WebhookError::TokenTransferError(format!("Failed to update bonding curve: {}", e)))?;
                     
                     
-                    (tokens, new_price)
+                    (tokens, price_per_token(amount_in_dollars, tokens))
                 },
                 Ok(None) => {
                     // Cause not found
-                    (amount_to_cause as f64, 1.0)
+                    (amount_to_cause as f64, None)
                 },
                 Err(e) => {
                     // Database error
                     error!("Failed to look up cause for token {}: {}", token_symbol, e);
-                    (amount_to_cause as f64, 1.0)
+                    (amount_to_cause as f64, None)
                 }
             }
         } else {
             // USD or unknown token, use simple calculation
-            (amount_to_cause as f64, 1.0)
+            (amount_to_cause as f64, None)
         };
         
         // Convert back to integer tokens
@@ -153,7 +338,7 @@ impl WebhookService {
             .map_err(|e| WebhookError::InvalidPublicKey(e.to_string()))?;
 
         // Transfer tokens to user
-        self.token_service
+        let executor_tx_id = self.token_service
             .transfer_tokens(
                 &self.central_vault_keypair,
                 &user_pubkey,
@@ -161,7 +346,7 @@ impl WebhookService {
                 user_tokens,
             )
             .await
-            .map_err(|e| WebhookError::TokenTransferError(e.to_string()))?;
+            .map_err(WebhookError::from)?;
 
         // Transfer platform fee tokens to network goods vault
         let network_goods_pubkey = self.network_goods_vault_keypair.pub_key();
@@ -173,13 +358,433 @@ impl WebhookService {
                 platform_tokens,
             )
             .await
-            .map_err(|e| WebhookError::TokenTransferError(format!("Failed to transfer platform fee: {}", e)))?;
+            .map_err(|e| WebhookError::FeeTransferError(e.to_string()))?;
         
         info!(
             "Successfully distributed tokens: {} to user {}, {} to network goods vault",
-            user_tokens, user_address, platform_tokens
+            user_tokens, redact(user_address), platform_tokens
         );
+        self.record_credit_audit(user_address, doc! {
+            "token_symbol": token_symbol,
+            "total_amount_cents": total_amount,
+            "user_tokens": user_tokens as i64,
+            "platform_tokens": platform_tokens as i64,
+            "executor_tx_id": executor_tx_id.clone(),
+        }).await;
         
-        Ok(user_tokens as f64)
+        Ok(CreditReceipt { tokens: user_tokens as f64, executor_tx_id, price_usd })
+    }
+
+    /// Credit a wallet by hand, e.g. after a missed webhook. The idempotency key is reserved
+    /// before tokens move, so retries never credit twice; a credit whose transfer fails keeps
+    /// its key as failed until an admin resolves it. Returns the deposit and whether this call
+    /// performed the credit.
+    pub async fn manual_credit(
+        &self,
+        request: &ManualCreditRequest,
+        credited_by: &str,
+    ) -> Result<(DepositRecord, bool), ApiError> {
+        if let Some(existing) = self.mongodb_service.get_deposit_by_idempotency_key(&request.idempotency_key).await? {
+            return Ok((existing, false));
+        }
+        Ed25519PubKey::from_str(&request.wallet_address)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid wallet address: {}", e)))?;
+
+        let reservation = CreditReservation::manual(request, credited_by);
+        if !self.mongodb_service.reserve_credit(&reservation).await? {
+            let existing = self.mongodb_service.get_credit_reservation(&reservation.key).await?
+                .ok_or_else(|| ApiError::InternalError("Manual credit reservation vanished".to_string()))?;
+            return match existing.status {
+                // Lost a race with a concurrent request using the same key
+                CreditReservationStatus::Credited => {
+                    let deposit = self.mongodb_service.get_deposit_by_idempotency_key(&request.idempotency_key).await?
+                        .ok_or_else(|| ApiError::InternalError(format!("Manual credit {} has no deposit record", request.idempotency_key)))?;
+                    Ok((deposit, false))
+                },
+                CreditReservationStatus::Pending => Err(ApiError::Conflict(format!(
+                    "Manual credit {} is still being applied", request.idempotency_key
+                ))),
+                CreditReservationStatus::Failed => Err(ApiError::Conflict(format!(
+                    "Manual credit {} failed and is waiting for an admin to resolve it: {}",
+                    request.idempotency_key, existing.failure_reason.unwrap_or_default()
+                ))),
+            };
+        }
+
+        let receipt = match self.transfer_credit(&request.token_symbol, request.amount, &request.wallet_address).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                error!("Manual credit {} failed, holding it for an admin: {}", request.idempotency_key, e);
+                let failed = self.mongodb_service.transition_credit_reservation(
+                    &reservation.key,
+                    CreditReservationStatus::Pending,
+                    CreditReservationStatus::Failed,
+                    doc! { "failure_reason": e.to_string() },
+                ).await;
+                if let Err(fail_err) = failed {
+                    error!("Failed to mark manual credit {} failed: {}", request.idempotency_key, fail_err);
+                }
+                return Err(ApiError::InternalError(format!("Manual credit failed and is held for an admin to resolve: {}", e)));
+            }
+        };
+
+        let deposit = self.settle_manual_credit(&reservation, CreditReservationStatus::Pending, receipt.executor_tx_id).await?;
+        info!("Manual credit of {} {} to {} by {}", request.amount, request.token_symbol, redact(&request.wallet_address), redact(credited_by));
+        Ok((deposit, true))
+    }
+
+    /// Settle a failed credit once an admin has checked the executor: mark it credited if the
+    /// tokens did land (recording the deposit of a manual credit), or release its key so the
+    /// credit can be tried again. Returns the reservation as it was and the new deposit, if any.
+    pub async fn resolve_credit(
+        &self,
+        id: &ObjectId,
+        request: &ResolveCreditRequest,
+    ) -> Result<(CreditReservation, Option<DepositRecord>), ApiError> {
+        let reservation = self.mongodb_service.get_credit_reservation_by_id(id).await?
+            .ok_or_else(|| ApiError::NotFound(format!("Credit {} not found", id)))?;
+        if reservation.status != CreditReservationStatus::Failed {
+            return Err(ApiError::Conflict(format!("Credit {} is {}, only failed credits can be resolved", id, reservation.status)));
+        }
+
+        if !request.credited {
+            if !self.mongodb_service.release_credit_reservation(&reservation.key, CreditReservationStatus::Failed).await? {
+                return Err(ApiError::Conflict(format!("Credit {} was resolved concurrently", id)));
+            }
+            return Ok((reservation, None));
+        }
+        let deposit = match reservation.manual_credit {
+            Some(_) => Some(self.settle_manual_credit(&reservation, CreditReservationStatus::Failed, request.executor_tx_id.clone()).await?),
+            None => {
+                self.mongodb_service.transition_credit_reservation(
+                    &reservation.key,
+                    CreditReservationStatus::Failed,
+                    CreditReservationStatus::Credited,
+                    doc! { "executor_tx_id": request.executor_tx_id.clone() },
+                ).await?
+                    .ok_or_else(|| ApiError::Conflict(format!("Credit {} was resolved concurrently", id)))?;
+                None
+            },
+        };
+        Ok((reservation, deposit))
+    }
+
+    /// Mark a manual credit's reservation credited and record its deposit
+    async fn settle_manual_credit(
+        &self,
+        reservation: &CreditReservation,
+        from: CreditReservationStatus,
+        executor_tx_id: Option<String>,
+    ) -> Result<DepositRecord, ApiError> {
+        self.mongodb_service.transition_credit_reservation(
+            &reservation.key,
+            from,
+            CreditReservationStatus::Credited,
+            doc! { "executor_tx_id": executor_tx_id.clone() },
+        ).await?
+            .ok_or_else(|| ApiError::Conflict(format!("Credit {} was resolved concurrently", reservation.key)))?;
+
+        let token_image_url = match self.mongodb_service.get_cause_by_token_symbol(&reservation.token_symbol).await {
+            Ok(Some(cause)) => cause.token_image_url,
+            _ => None,
+        };
+        let deposit = DepositRecord {
+            id: Some(ObjectId::new()),
+            wallet_address: reservation.wallet_address.clone(),
+            token_symbol: reservation.token_symbol.clone(),
+            token_image_url,
+            amount_deposited_usd: 0.0,  // no money changed hands
+            amount_tokens_received: reservation.amount as f64,
+            created_at: chrono::Utc::now().timestamp(),
+            stripe_session_id: None,
+            stripe_payment_intent_id: None,
+            executor_tx_id,
+            manual_credit: reservation.manual_credit.clone(),
+            referrer: None,
+            campaign_id: None,
+            fundraiser_id: None,
+        };
+        if let Err(e) = self.mongodb_service.save_deposit_record(deposit.clone()).await {
+            error!("Failed to save deposit record of manual credit {}: {}", reservation.key, e);
+        }
+        self.push_service.deposit_credited(&deposit).await;
+        Ok(deposit)
+    }
+
+    /// Match a donation from every open pool for its cause token. Matches are paid from the
+    /// matching vault and recorded before tokens move; the pool budget is reserved first and
+    /// kept even if the transfer fails, leaving the match for `retry_failed_matches`. Without a
+    /// matching vault nothing is matched. Returns the credited matches.
+    pub async fn apply_matching_pools(
+        &self,
+        stripe_session_id: &str,
+        token_symbol: &str,
+        donation_cents: i64,
+        donor_wallet: &str,
+    ) -> Result<Vec<MatchEvent>, WebhookError> {
+        let db_err = |e: ApiError| WebhookError::DatabaseError(e.to_string());
+        let Some(vault) = &self.matching_vault_keypair else {
+            return Ok(Vec::new());
+        };
+        let now = chrono::Utc::now().timestamp();
+        let pools = self.mongodb_service.get_open_pools_for_symbol(token_symbol, now).await.map_err(db_err)?;
+
+        let mut matches = Vec::new();
+        for pool in pools {
+            let Some(pool_id) = pool.id else { continue };
+            let matched_cents = compute_match(donation_cents, pool.match_ratio, pool.remaining_cents(), pool.per_donation_cap_cents);
+            if matched_cents == 0 {
+                continue;
+            }
+            let units = self.quote_match_units(token_symbol, matched_cents).await?;
+            if units == 0 {
+                continue;
+            }
+
+            let event = MatchEvent {
+                id: Some(ObjectId::new()),
+                pool_id,
+                stripe_session_id: stripe_session_id.to_string(),
+                token_symbol: token_symbol.to_string(),
+                donor_wallet: donor_wallet.to_string(),
+                donation_cents,
+                matched_cents,
+                units,
+                tokens_credited: 0.0,
+                status: MatchEventStatus::Pending,
+                failure_reason: None,
+                executor_tx_id: None,
+                created_at: now,
+            };
+            let event_id = event.id.unwrap();
+
+            if !self.mongodb_service.insert_match_event(&event).await.map_err(db_err)? {
+                info!("Session {} already matched from pool {}, skipping", stripe_session_id, pool_id);
+                continue;
+            }
+            if !self.mongodb_service.reserve_pool_budget(&pool_id, matched_cents).await.map_err(db_err)? {
+                info!("Pool {} ran out of budget before matching session {}", pool_id, stripe_session_id);
+                self.mongodb_service.delete_match_event(&event_id).await.map_err(db_err)?;
+                continue;
+            }
+
+            if let Some(credited) = self.pay_match(vault, &event).await? {
+                matches.push(credited);
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Pay matches the webhook couldn't: failed ones, and pending ones it never finished.
+    /// Their pool budget is already reserved.
+    pub async fn retry_failed_matches(&self) {
+        let Some(vault) = &self.matching_vault_keypair else { return };
+        let stale_before = chrono::Utc::now().timestamp() - MATCH_STALE_SECS;
+        let events = match self.mongodb_service.get_unsettled_match_events(stale_before, MATCH_RETRY_BATCH_SIZE).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Failed to load unsettled matches: {}", e);
+                return;
+            }
+        };
+
+        for event in events {
+            match self.pay_match(vault, &event).await {
+                Ok(Some(_)) => info!("Retried match {:?} from pool {}", event.id, event.pool_id),
+                Ok(None) => {}
+                Err(e) => error!("Failed to retry match {:?} from pool {}: {:?}", event.id, event.pool_id, e),
+            }
+        }
+    }
+
+    /// Token units `cents` of matching buys at the cause's current price. Matches come out of
+    /// the sponsors' funded vault, so the bonding curve doesn't move.
+    async fn quote_match_units(&self, token_symbol: &str, cents: i64) -> Result<i64, WebhookError> {
+        if token_symbol == "USD" {
+            return Ok(cents);
+        }
+        let cause = self.mongodb_service.get_cause_by_token_symbol(token_symbol).await
+            .map_err(|e| WebhookError::DatabaseError(e.to_string()))?
+            .filter(|cause| cause.token_symbol.eq_ignore_ascii_case(token_symbol))
+            .ok_or_else(|| WebhookError::InvalidPayload(format!("No cause with token symbol {}", token_symbol)))?;
+        if cause.current_price <= 0.0 {
+            return Ok(0);
+        }
+        Ok((cents as f64 / 100.0 / cause.current_price).round() as i64)
+    }
+
+    /// Pay a match under its own credit reservation, so neither a webhook retry nor the sweep
+    /// can pay it twice. A refused transfer releases the reservation and leaves the match
+    /// failed for the next sweep; one that may have landed keeps the reservation failed until
+    /// an admin resolves it. Returns the match if this call credited it.
+    async fn pay_match(&self, vault: &Ed25519PrivKey, event: &MatchEvent) -> Result<Option<MatchEvent>, WebhookError> {
+        let db_err = |e: ApiError| WebhookError::DatabaseError(e.to_string());
+        let event_id = event.id.ok_or_else(|| WebhookError::DatabaseError("Match event has no ID".to_string()))?;
+        let key = format!("match:{}", event_id.to_hex());
+
+        let reservation = CreditReservation::new(key.clone(), &event.donor_wallet, &event.token_symbol, event.units);
+        if !self.mongodb_service.reserve_credit(&reservation).await.map_err(db_err)? {
+            let Some(existing) = self.mongodb_service.get_credit_reservation(&key).await.map_err(db_err)? else {
+                return Ok(None);  // released since; the next sweep retries it
+            };
+            return match existing.status {
+                // An admin found the transfer landed
+                CreditReservationStatus::Credited => self.settle_match(event, existing.executor_tx_id).await,
+                // An attempt that never finished may have reached the executor
+                CreditReservationStatus::Pending if existing.updated_at < chrono::Utc::now().timestamp() - MATCH_STALE_SECS => {
+                    let set = doc! { "failure_reason": "Interrupted before the transfer outcome was recorded" };
+                    self.mongodb_service.transition_credit_reservation(&key, CreditReservationStatus::Pending, CreditReservationStatus::Failed, set).await.map_err(db_err)?;
+                    self.mongodb_service.fail_match_event(&event_id, "Interrupted before the transfer outcome was recorded").await.map_err(db_err)?;
+                    Ok(None)
+                }
+                CreditReservationStatus::Pending | CreditReservationStatus::Failed => Ok(None),
+            };
+        }
+
+        let transfer = match Ed25519PubKey::from_str(&event.donor_wallet) {
+            Ok(donor) => self.token_service.transfer_tokens(vault, &donor, &event.token_symbol, event.units as u64).await,
+            Err(e) => Err(TransferError::NotTransferred(format!("Invalid donor wallet: {}", e))),
+        };
+        match transfer {
+            Ok(executor_tx_id) => {
+                let set = doc! { "executor_tx_id": executor_tx_id.clone() };
+                self.mongodb_service.transition_credit_reservation(&key, CreditReservationStatus::Pending, CreditReservationStatus::Credited, set).await.map_err(db_err)?;
+                self.settle_match(event, executor_tx_id).await
+            }
+            Err(TransferError::NotTransferred(reason)) => {
+                warn!("Matching vault refused match {} from pool {}: {}", event_id, event.pool_id, reason);
+                self.mongodb_service.release_credit_reservation(&key, CreditReservationStatus::Pending).await.map_err(db_err)?;
+                self.mongodb_service.fail_match_event(&event_id, &reason).await.map_err(db_err)?;
+                Ok(None)
+            }
+            Err(TransferError::Unknown(reason)) => {
+                error!("Match {} from pool {} may have been paid, holding it for an admin: {}", event_id, event.pool_id, reason);
+                let set = doc! { "failure_reason": reason.as_str() };
+                self.mongodb_service.transition_credit_reservation(&key, CreditReservationStatus::Pending, CreditReservationStatus::Failed, set).await.map_err(db_err)?;
+                self.mongodb_service.fail_match_event(&event_id, &reason).await.map_err(db_err)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Mark a paid match credited and record the donor's reward
+    async fn settle_match(&self, event: &MatchEvent, executor_tx_id: Option<String>) -> Result<Option<MatchEvent>, WebhookError> {
+        let event_id = event.id.ok_or_else(|| WebhookError::DatabaseError("Match event has no ID".to_string()))?;
+        let tokens = event.units as f64;
+        if !self.mongodb_service.mark_match_event_credited(&event_id, tokens, executor_tx_id.as_deref()).await
+            .map_err(|e| WebhookError::DatabaseError(e.to_string()))? {
+            return Ok(None);
+        }
+        info!("Pool {} matched {} cents for session {} ({} tokens)", event.pool_id, event.matched_cents, event.stripe_session_id, tokens);
+
+        let amounts = vec![ActivityAmount { token_symbol: event.token_symbol.clone(), amount: tokens / ON_CHAIN_UNITS_PER_TOKEN }];
+        let activity = ActivityEvent::new(&event.donor_wallet, ActivityKind::Reward, amounts, "matching_pool", &event.pool_id.to_hex())
+            .executor_tx_id(executor_tx_id.clone());
+        if let Err(e) = self.mongodb_service.record_activity(activity).await {
+            error!("Failed to record reward activity for pool {}: {:?}", event.pool_id, e);
+        }
+
+        let mut credited = event.clone();
+        credited.tokens_credited = tokens;
+        credited.status = MatchEventStatus::Credited;
+        credited.failure_reason = None;
+        credited.executor_tx_id = executor_tx_id;
+        Ok(Some(credited))
+    }
+
+    /// Count a donation towards every open funding round that includes its cause
+    pub async fn record_round_contributions(
+        &self,
+        stripe_session_id: &str,
+        token_symbol: &str,
+        donation_cents: i64,
+        donor_wallet: &str,
+    ) -> Result<(), WebhookError> {
+        let db_err = |e: ApiError| WebhookError::DatabaseError(e.to_string());
+        let now = chrono::Utc::now().timestamp();
+        let rounds = self.mongodb_service.get_open_rounds_for_symbol(token_symbol, now).await.map_err(db_err)?;
+
+        for round in rounds {
+            let Some(round_id) = round.id else { continue };
+            let contribution = RoundContribution {
+                id: None,
+                round_id,
+                token_symbol: token_symbol.to_string(),
+                contributor_wallet: donor_wallet.to_string(),
+                amount_cents: donation_cents,
+                stripe_session_id: stripe_session_id.to_string(),
+                created_at: now,
+            };
+            if self.mongodb_service.record_round_contribution(&contribution).await.map_err(db_err)? {
+                info!("Session {} counted towards funding round {}", stripe_session_id, round_id);
+            }
+        }
+        Ok(())
+    }
+
+    // Audit failures are logged but never fail the credit itself
+    async fn record_credit_audit(&self, user_address: &str, details: mongodb::bson::Document) {
+        let audit = AuditLog::new(
+            STRIPE_WEBHOOK_ACTOR,
+            AuditAction::AccountCredited,
+            "wallet",
+            user_address,
+            None,
+            Some(details),
+        );
+        if let Err(e) = self.mongodb_service.record_audit_log(audit).await {
+            error!("Failed to record audit log for credit: {:?}", e);
+        }
+    }
+}
+
+// Enough to tell secrets apart in the admin report
+fn secret_hint(secret: &str) -> String {
+    let tail: String = secret.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("...{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deposit(tokens: f64) -> DepositRecord {
+        DepositRecord {
+            id: None,
+            wallet_address: "wallet".to_string(),
+            token_symbol: "TREE".to_string(),
+            token_image_url: None,
+            amount_deposited_usd: 100.0,
+            amount_tokens_received: tokens,
+            created_at: 0,
+            stripe_session_id: None,
+            stripe_payment_intent_id: None,
+            executor_tx_id: None,
+            manual_credit: None,
+            referrer: None,
+            campaign_id: None,
+            fundraiser_id: None,
+        }
+    }
+
+    #[test]
+    fn test_price_per_token() {
+        // $95 buying 9,500 units is 95 whole tokens at $1
+        assert_eq!(price_per_token(95.0, 9500.0), Some(1.0));
+        assert_eq!(price_per_token(95.0, 4750.0), Some(2.0));
+        assert_eq!(price_per_token(95.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_donation_receipt_shows_whole_tokens() {
+        let text = donation_receipt_text("Trees", &deposit(9000.0), price_per_token(95.0, 9500.0), "cs_1");
+        assert!(text.contains("Tokens received: 90.00 TREE\n"), "{}", text);
+        assert!(text.contains("Price per token: $1.0000\n"), "{}", text);
+        assert!(text.contains("Reference: cs_1\n"), "{}", text);
+
+        let text = donation_receipt_text("Trees", &deposit(0.0), None, "cs_2");
+        assert!(text.contains("Tokens received: 0.00 TREE\n"), "{}", text);
+        assert!(!text.contains("Price per token"), "{}", text);
     }
 }
@@ -0,0 +1,16 @@
+use mongodb::bson::{self, Document};
+use serde::Serialize;
+
+pub const SYSTEM_ACTOR: &str = "system";
+pub const STRIPE_WEBHOOK_ACTOR: &str = "stripe_webhook";
+
+/// Convert a value to a BSON document for before/after snapshots
+pub fn snapshot<T: Serialize>(value: &T) -> Option<Document> {
+    match bson::to_document(value) {
+        Ok(doc) => Some(doc),
+        Err(e) => {
+            log::error!("Failed to snapshot value for audit log: {}", e);
+            None
+        }
+    }
+}
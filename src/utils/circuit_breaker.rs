@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum BreakerState {
+    #[serde(rename = "closed")]
+    Closed,
+    #[serde(rename = "open")]
+    Open,
+    #[serde(rename = "half_open")]
+    HalfOpen,
+}
+
+/// Snapshot of a breaker for health checks
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub retry_in_secs: Option<u64>,  // until a trial call is allowed, while open
+}
+
+/// Opens after `failure_threshold` consecutive failures so callers fail fast instead of
+/// waiting on a dead dependency. After `cooldown` a single trial call is let through:
+/// success closes the breaker, failure opens it for another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            consecutive_failures: 0,
+            opened_at: None,
+            trial_started_at: None,
+        }
+    }
+
+    pub fn state(&self, now: Instant) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a call may go ahead. While half open only one trial runs at a time; a trial
+    /// that never reports back is given up on after another cooldown.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen => {
+                let trial_running = self.trial_started_at
+                    .map_or(false, |started| now.duration_since(started) < self.cooldown);
+                if trial_running {
+                    return false;
+                }
+                self.trial_started_at = Some(now);
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.trial_started_at = None;
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.trial_started_at.take().is_some() || self.consecutive_failures >= self.failure_threshold {
+            self.opened_at = Some(now);
+        }
+    }
+
+    pub fn status(&self, now: Instant) -> BreakerStatus {
+        let state = self.state(now);
+        BreakerStatus {
+            state,
+            consecutive_failures: self.consecutive_failures,
+            retry_in_secs: match (state, self.opened_at) {
+                (BreakerState::Open, Some(opened_at)) => Some(self.cooldown.saturating_sub(now.duration_since(opened_at)).as_secs()),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, COOLDOWN);
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(breaker.try_acquire(now));
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), BreakerState::Open);
+        assert!(!breaker.try_acquire(now));
+        assert_eq!(breaker.status(now).retry_in_secs, Some(30));
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(2, COOLDOWN);
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_allows_one_trial() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure(now);
+
+        let later = now + COOLDOWN;
+        assert_eq!(breaker.state(later), BreakerState::HalfOpen);
+        assert!(breaker.try_acquire(later));
+        assert!(!breaker.try_acquire(later));
+
+        breaker.record_success();
+        assert_eq!(breaker.state(later), BreakerState::Closed);
+        assert!(breaker.try_acquire(later));
+    }
+
+    #[test]
+    fn test_failed_trial_reopens() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(5, COOLDOWN);
+        for _ in 0..5 {
+            breaker.record_failure(now);
+        }
+
+        let later = now + COOLDOWN;
+        assert!(breaker.try_acquire(later));
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(later), BreakerState::Open);
+        assert!(breaker.try_acquire(later + COOLDOWN));
+    }
+
+    #[test]
+    fn test_abandoned_trial_expires() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure(now);
+
+        let later = now + COOLDOWN;
+        assert!(breaker.try_acquire(later));
+        assert!(!breaker.try_acquire(later + COOLDOWN / 2));
+        assert!(breaker.try_acquire(later + COOLDOWN));
+    }
+}
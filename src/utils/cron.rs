@@ -0,0 +1,175 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+
+/// A five-field cron expression (minute, hour, day of month, month, day of week), in UTC.
+/// Fields take `*`, values, ranges `a-b`, steps `*/n`, `a/n` or `a-b/n`, and lists of these
+/// separated by commas. Days of the week run 0-6 from Sunday; 7 is Sunday too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,      // day of month is `*`, so only the day of week restricts days
+    any_weekday: bool,
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    value.parse::<u32>().ok()
+        .filter(|v| (min..=max).contains(v))
+        .ok_or_else(|| format!("{} is not between {} and {}", value, min, max))
+}
+
+/// The values a field allows, as bits
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step.parse::<u32>().ok().filter(|s| *s > 0)
+                    .ok_or_else(|| format!("Invalid step in {}", part))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // `a/n` runs from a to the end of the field
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            return Err(format!("Invalid range {}", part));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("Cron expression \"{}\" must have five fields", expression.trim()));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl CronSchedule {
+    /// As in cron, when both day fields are restricted a day matching either one runs
+    fn matches_day(&self, at: DateTime<Utc>) -> bool {
+        let day = has(self.days, at.day());
+        let weekday = has(self.weekdays, at.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first minute after `after` the schedule runs at, or None when it never does
+    /// (e.g. `0 0 30 2 *`)
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0).map(|at| at.and_utc());
+        let mut at = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // Every day of the month falls on every day of the week within a few years
+        let give_up_at = at + Duration::days(5 * 366);
+        while at < give_up_at {
+            if !has(self.months, at.month()) {
+                let (year, month) = if at.month() == 12 { (at.year() + 1, 1) } else { (at.year(), at.month() + 1) };
+                at = midnight(NaiveDate::from_ymd_opt(year, month, 1)?)?;
+            } else if !self.matches_day(at) {
+                at = midnight(at.date_naive() + Duration::days(1))?;
+            } else if !has(self.hours, at.hour()) {
+                at = at.with_minute(0)? + Duration::hours(1);
+            } else if !has(self.minutes, at.minute()) {
+                at += Duration::minutes(1);
+            } else {
+                return Some(at);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    // 2024-01-31T09:00:30Z, a Wednesday
+    const JAN_31: i64 = 1706691630;
+
+    #[test]
+    fn test_parse_cron_schedule() {
+        let schedule: CronSchedule = "*/15  9-17 * * 1-5".parse().unwrap();
+        assert_eq!(schedule.to_string(), "*/15 9-17 * * 1-5");
+        assert!(has(schedule.minutes, 45) && !has(schedule.minutes, 50));
+        assert!(has(schedule.hours, 17) && !has(schedule.hours, 18));
+
+        let sunday: CronSchedule = "0 0 * * 7".parse().unwrap();
+        assert_eq!(sunday.weekdays, 1);
+        assert!("0 0 * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("* * 0 * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("5-1 * * * *".parse::<CronSchedule>().is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let every_quarter: CronSchedule = "*/15 * * * *".parse().unwrap();
+        assert_eq!(every_quarter.next_after(at(JAN_31)), Some(at(JAN_31 - 30 + 15 * 60)));
+
+        // 03:00 on Mondays: Feb 5th
+        let mondays: CronSchedule = "0 3 * * 1".parse().unwrap();
+        assert_eq!(mondays.next_after(at(JAN_31)), Some(at(JAN_31 - 30 + 5 * 86400 - 6 * 3600)));
+
+        // Both day fields restricted: the 1st of the month or a Friday, whichever comes first
+        let first_or_friday: CronSchedule = "0 0 1 * 5".parse().unwrap();
+        assert_eq!(first_or_friday.next_after(at(JAN_31)), Some(at(JAN_31 - 30 + 15 * 3600)));
+
+        // Leap day only
+        let leap_day: CronSchedule = "30 12 29 2 *".parse().unwrap();
+        assert_eq!(leap_day.next_after(at(JAN_31)), Some(at(JAN_31 - 30 + 29 * 86400 + 3 * 3600 + 30 * 60)));
+        assert_eq!("0 0 30 2 *".parse::<CronSchedule>().unwrap().next_after(at(JAN_31)), None);
+    }
+}
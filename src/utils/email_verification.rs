@@ -0,0 +1,94 @@
+use std::sync::OnceLock;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long a verification link stays valid
+pub const VERIFICATION_TTL_SECS: i64 = 48 * 60 * 60;
+
+/// What deposit claim link tokens are signed for, in place of a draft ID
+pub const DEPOSIT_CLAIM_SCOPE: &str = "deposit_claim";
+
+/// EMAIL_VERIFICATION_SECRET, if it's set
+pub fn configured_secret() -> Option<&'static [u8]> {
+    static SECRET: OnceLock<Option<Vec<u8>>> = OnceLock::new();
+    SECRET.get_or_init(|| {
+        std::env::var("EMAIL_VERIFICATION_SECRET").ok()
+            .filter(|secret| !secret.is_empty())
+            .map(String::into_bytes)
+    }).as_deref()
+}
+
+/// EMAIL_VERIFICATION_SECRET, or outside production a random secret shared by the whole
+/// process when it's unset. Links signed with a random secret don't verify on other replicas
+/// or after a restart, so production refuses to run without the real one.
+pub fn verification_secret() -> Result<&'static [u8], String> {
+    static RANDOM: OnceLock<Vec<u8>> = OnceLock::new();
+    if let Some(secret) = configured_secret() {
+        return Ok(secret);
+    }
+    if std::env::var("ENVIRONMENT").as_deref() == Ok("production") {
+        return Err("EMAIL_VERIFICATION_SECRET must be set in production".to_string());
+    }
+    Ok(RANDOM.get_or_init(|| {
+        log::warn!("EMAIL_VERIFICATION_SECRET not set - using a random secret, verification links won't survive a restart");
+        rand::random::<[u8; 32]>().to_vec()
+    }))
+}
+
+fn mac(secret: &[u8], draft_id: &str, email: &str, expires_at: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}:{}", draft_id, email.trim().to_lowercase(), expires_at).as_bytes());
+    mac
+}
+
+/// Token for an email verification link: "<expires_at>.<hex hmac>" over the draft and email
+pub fn sign_verification_token(secret: &[u8], draft_id: &str, email: &str, expires_at: i64) -> String {
+    let signature = mac(secret, draft_id, email, expires_at).finalize().into_bytes();
+    format!("{}.{}", expires_at, hex::encode(signature))
+}
+
+pub fn verify_verification_token(secret: &[u8], draft_id: &str, email: &str, token: &str, now: i64) -> Result<(), String> {
+    let (expires_at, signature) = token.split_once('.')
+        .ok_or_else(|| "Malformed verification token".to_string())?;
+    let expires_at: i64 = expires_at.parse()
+        .map_err(|_| "Malformed verification token".to_string())?;
+    let signature = hex::decode(signature)
+        .map_err(|_| "Malformed verification token".to_string())?;
+
+    mac(secret, draft_id, email, expires_at)
+        .verify_slice(&signature)
+        .map_err(|_| "Invalid verification token".to_string())?;
+    if now > expires_at {
+        return Err("Verification link has expired".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    #[test]
+    fn test_token_round_trip() {
+        let token = sign_verification_token(SECRET, "draft1", "Creator@Example.org", 1_000);
+        assert!(verify_verification_token(SECRET, "draft1", "creator@example.org", &token, 999).is_ok());
+    }
+
+    #[test]
+    fn test_token_rejects_tampering_and_expiry() {
+        let token = sign_verification_token(SECRET, "draft1", "creator@example.org", 1_000);
+        assert!(verify_verification_token(SECRET, "draft2", "creator@example.org", &token, 999).is_err());
+        assert!(verify_verification_token(SECRET, "draft1", "other@example.org", &token, 999).is_err());
+        assert!(verify_verification_token(b"other-secret", "draft1", "creator@example.org", &token, 999).is_err());
+        assert!(verify_verification_token(SECRET, "draft1", "creator@example.org", &token.replacen("1000", "9000", 1), 999).is_err());
+        assert_eq!(
+            verify_verification_token(SECRET, "draft1", "creator@example.org", &token, 1_001).unwrap_err(),
+            "Verification link has expired"
+        );
+        assert!(verify_verification_token(SECRET, "draft1", "creator@example.org", "garbage", 0).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use sha2::{Digest, Sha256};
+
+/// Strong ETag for a listing from how many items it has and its latest change: editing an
+/// item bumps its timestamp, and adding or removing one changes the count
+pub fn listing_etag(count: u64, latest_change: i64) -> String {
+    let digest = Sha256::digest(format!("{}:{}", count, latest_change).as_bytes());
+    format!("\"{}\"", hex::encode(&digest[..8]))
+}
+
+/// Whether an `If-None-Match` header matches `etag`, comparing weakly as If-None-Match does
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_etag_changes_with_count_and_time() {
+        let etag = listing_etag(3, 1700000000000);
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_eq!(etag, listing_etag(3, 1700000000000));
+        assert_ne!(etag, listing_etag(4, 1700000000000));
+        assert_ne!(etag, listing_etag(3, 1700000000001));
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = listing_etag(1, 5);
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches(&format!("W/{}", etag), &etag));
+        assert!(etag_matches(&format!("\"other\", {}", etag), &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"other\"", &etag));
+        assert!(!etag_matches("", &etag));
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::NaiveTime;
+use crate::models::OpeningHours;
+
+/// Mean Earth radius used by MongoDB's spherical queries
+const EARTH_RADIUS_M: f64 = 6_378_100.0;
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+pub fn validate_coordinates(lat: f64, lng: f64) -> Result<(), String> {
+    if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err("Latitude must be between -90 and 90".to_string());
+    }
+    if !lng.is_finite() || !(-180.0..=180.0).contains(&lng) {
+        return Err("Longitude must be between -180 and 180".to_string());
+    }
+    Ok(())
+}
+
+/// Great-circle distance in meters
+pub fn haversine_distance_m(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lng2 - lng1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+}
+
+/// One entry per weekday at most, days as "mon".."sun", times as "HH:MM" with opening
+/// before closing. Days are normalized to lowercase and sorted Monday first.
+pub fn validate_opening_hours(hours: &[OpeningHours]) -> Result<Vec<OpeningHours>, String> {
+    let mut normalized = Vec::with_capacity(hours.len());
+    for entry in hours {
+        let day = entry.day.trim().to_lowercase();
+        if !WEEKDAYS.contains(&day.as_str()) {
+            return Err(format!("Invalid day '{}', expected one of {}", entry.day, WEEKDAYS.join(", ")));
+        }
+        if normalized.iter().any(|h: &OpeningHours| h.day == day) {
+            return Err(format!("Hours for '{}' given more than once", day));
+        }
+        let opens = parse_time(&entry.opens)?;
+        let closes = parse_time(&entry.closes)?;
+        if opens >= closes {
+            return Err(format!("Opening time must be before closing time on '{}'", day));
+        }
+        normalized.push(OpeningHours {
+            day,
+            opens: opens.format("%H:%M").to_string(),
+            closes: closes.format("%H:%M").to_string(),
+        });
+    }
+    normalized.sort_by_key(|h| WEEKDAYS.iter().position(|d| *d == h.day));
+    Ok(normalized)
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time '{}', expected HH:MM", time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(day: &str, opens: &str, closes: &str) -> OpeningHours {
+        OpeningHours { day: day.to_string(), opens: opens.to_string(), closes: closes.to_string() }
+    }
+
+    #[test]
+    fn test_validate_coordinates() {
+        assert!(validate_coordinates(40.7, -74.0).is_ok());
+        assert!(validate_coordinates(90.0, 180.0).is_ok());
+        assert!(validate_coordinates(90.1, 0.0).is_err());
+        assert!(validate_coordinates(0.0, -180.5).is_err());
+        assert!(validate_coordinates(f64::NAN, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_haversine_distance() {
+        assert_eq!(haversine_distance_m(51.5, -0.12, 51.5, -0.12), 0.0);
+        // One degree of latitude is roughly 111km
+        let d = haversine_distance_m(0.0, 0.0, 1.0, 0.0);
+        assert!((d - 111_318.0).abs() < 100.0);
+    }
+
+    #[test]
+    fn test_validate_opening_hours() {
+        let result = validate_opening_hours(&[hours("Tue", "9:00", "17:30"), hours("mon", "08:00", "12:00")]).unwrap();
+        assert_eq!(result, vec![hours("mon", "08:00", "12:00"), hours("tue", "09:00", "17:30")]);
+
+        assert!(validate_opening_hours(&[hours("monday", "08:00", "12:00")]).is_err());
+        assert!(validate_opening_hours(&[hours("mon", "12:00", "08:00")]).is_err());
+        assert!(validate_opening_hours(&[hours("mon", "8am", "12:00")]).is_err());
+        assert!(validate_opening_hours(&[hours("mon", "08:00", "12:00"), hours("MON", "13:00", "17:00")]).is_err());
+    }
+}
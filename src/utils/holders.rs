@@ -0,0 +1,42 @@
+use crate::models::TopHolder;
+
+/// Wallets holding a token, how much they hold between them, and the largest `top`
+/// holdings with their share of that total. Empty balances don't count as holders.
+pub fn holder_distribution(balances: &[u64], top: usize) -> (usize, u64, Vec<TopHolder>) {
+    let mut held: Vec<u64> = balances.iter().copied().filter(|balance| *balance > 0).collect();
+    held.sort_unstable_by(|a, b| b.cmp(a));
+    let total: u64 = held.iter().sum();
+    let top_holders = held.iter()
+        .take(top)
+        .enumerate()
+        .map(|(i, balance)| TopHolder {
+            rank: i + 1,
+            balance: *balance,
+            share: *balance as f64 / total as f64,
+        })
+        .collect();
+    (held.len(), total, top_holders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranks_largest_first_and_skips_empty() {
+        let (count, total, top) = holder_distribution(&[10, 0, 60, 30], 2);
+        assert_eq!(count, 3);
+        assert_eq!(total, 100);
+        assert_eq!(top, vec![
+            TopHolder { rank: 1, balance: 60, share: 0.6 },
+            TopHolder { rank: 2, balance: 30, share: 0.3 },
+        ]);
+    }
+
+    #[test]
+    fn test_no_holders() {
+        let (count, total, top) = holder_distribution(&[0, 0], 10);
+        assert_eq!((count, total), (0, 0));
+        assert!(top.is_empty());
+    }
+}
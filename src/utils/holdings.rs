@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use delta_executor_sdk::base::vaults::TokenKind;
+use delta_executor_sdk::base::verifiable::debit_allowance::DebitAllowance;
+use serde_json::Value;
+use crate::utils::signed_payload::find_messages;
+
+/// A change to one vault's balance of one token, in base units
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoldingDelta {
+    pub wallet_address: String,
+    pub token_id: String,
+    pub amount: i64,
+}
+
+/// Balance changes of moving `amounts` (token ID, base units) from one wallet to another
+pub fn transfer_deltas(from: &str, to: &str, amounts: &[(String, u64)]) -> Vec<HoldingDelta> {
+    amounts.iter()
+        .filter(|(_, amount)| *amount > 0)
+        .flat_map(|(token_id, amount)| [
+            HoldingDelta { wallet_address: from.to_string(), token_id: token_id.clone(), amount: -(*amount as i64) },
+            HoldingDelta { wallet_address: to.to_string(), token_id: token_id.clone(), amount: *amount as i64 },
+        ])
+        .collect()
+}
+
+/// Balance changes of the debit allowances in a batch of submitted verifiables, given as
+/// JSON. Native token allowances aren't tracked.
+pub fn allowance_deltas(submitted: &Value) -> Vec<HoldingDelta> {
+    find_messages(submitted, &["debited", "credited", "allowances"])
+        .into_iter()
+        .filter_map(|message| serde_json::from_value::<DebitAllowance>(message.clone()).ok())
+        .flat_map(|allowance| {
+            let amounts: Vec<(String, u64)> = allowance.allowances.iter()
+                .filter_map(|(kind, amount)| match kind {
+                    TokenKind::NonNative(token) => Some((format!("{},{}", token.pubkey(), token.shard()), *amount)),
+                    _ => None,
+                })
+                .collect();
+            transfer_deltas(&allowance.debited.pubkey().to_string(), &allowance.credited.pubkey().to_string(), &amounts)
+        })
+        .collect()
+}
+
+/// Sum deltas to the same wallet and token, dropping ones that cancel out
+pub fn net_deltas(deltas: Vec<HoldingDelta>) -> Vec<HoldingDelta> {
+    let mut net: BTreeMap<(String, String), i64> = BTreeMap::new();
+    for delta in deltas {
+        *net.entry((delta.wallet_address, delta.token_id)).or_default() += delta.amount;
+    }
+    net.into_iter()
+        .filter(|(_, amount)| *amount != 0)
+        .map(|((wallet_address, token_id), amount)| HoldingDelta { wallet_address, token_id, amount })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn delta(wallet_address: &str, token_id: &str, amount: i64) -> HoldingDelta {
+        HoldingDelta { wallet_address: wallet_address.to_string(), token_id: token_id.to_string(), amount }
+    }
+
+    #[test]
+    fn test_transfer_moves_each_token() {
+        let deltas = transfer_deltas("alice", "bob", &[("usd,1".to_string(), 500), ("meme,1".to_string(), 0)]);
+        assert_eq!(deltas, vec![delta("alice", "usd,1", -500), delta("bob", "usd,1", 500)]);
+    }
+
+    #[test]
+    fn test_net_deltas_combines_and_drops_zero() {
+        let deltas = net_deltas(vec![
+            delta("alice", "usd,1", -500),
+            delta("bob", "usd,1", 500),
+            delta("bob", "usd,1", -500),
+            delta("alice", "usd,1", 200),
+        ]);
+        assert_eq!(deltas, vec![delta("alice", "usd,1", -300)]);
+    }
+
+    #[test]
+    fn test_no_allowances_found() {
+        assert!(allowance_deltas(&json!([{ "message": { "operation": "Create" } }])).is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+use openssl::rand::rand_bytes;
+use openssl::symm::{Cipher, encrypt_aead, decrypt_aead};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use crate::models::EncryptedBlob;
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Encrypt `plaintext` with AES-256-GCM. `aad` is authenticated but not encrypted,
+/// so a blob can be bound to e.g. the token it belongs to.
+pub fn seal(key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<EncryptedBlob, String> {
+    if key.len() != KEY_LEN {
+        return Err(format!("Encryption key must be {} bytes", KEY_LEN));
+    }
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rand_bytes(&mut nonce).map_err(|e| format!("Failed to generate nonce: {}", e))?;
+
+    let mut tag = [0u8; TAG_LEN];
+    let ciphertext = encrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), aad, plaintext, &mut tag)
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    Ok(EncryptedBlob {
+        ciphertext: BASE64.encode(ciphertext),
+        nonce: BASE64.encode(nonce),
+        tag: BASE64.encode(tag),
+    })
+}
+
+/// Decrypt a blob produced by `seal` with the same key and aad
+pub fn open(key: &[u8], blob: &EncryptedBlob, aad: &[u8]) -> Result<Vec<u8>, String> {
+    if key.len() != KEY_LEN {
+        return Err(format!("Encryption key must be {} bytes", KEY_LEN));
+    }
+
+    let ciphertext = BASE64.decode(&blob.ciphertext).map_err(|e| format!("Invalid ciphertext encoding: {}", e))?;
+    let nonce = BASE64.decode(&blob.nonce).map_err(|e| format!("Invalid nonce encoding: {}", e))?;
+    let tag = BASE64.decode(&blob.tag).map_err(|e| format!("Invalid tag encoding: {}", e))?;
+
+    decrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), aad, &ciphertext, &tag)
+        .map_err(|e| format!("Decryption failed: {}", e))
+}
+
+/// Envelope encryption: seal `secret` under a fresh data key, then seal the data key under `master_key`.
+/// Returns (encrypted secret, wrapped data key).
+pub fn seal_with_data_key(master_key: &[u8], secret: &[u8], aad: &[u8]) -> Result<(EncryptedBlob, EncryptedBlob), String> {
+    let mut data_key = [0u8; KEY_LEN];
+    rand_bytes(&mut data_key).map_err(|e| format!("Failed to generate data key: {}", e))?;
+
+    let encrypted_secret = seal(&data_key, secret, aad)?;
+    let wrapped_data_key = seal(master_key, &data_key, aad)?;
+
+    Ok((encrypted_secret, wrapped_data_key))
+}
+
+/// Reverse of `seal_with_data_key`
+pub fn open_with_data_key(
+    master_key: &[u8],
+    encrypted_secret: &EncryptedBlob,
+    wrapped_data_key: &EncryptedBlob,
+    aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    let data_key = open(master_key, wrapped_data_key, aad)?;
+    open(&data_key, encrypted_secret, aad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_roundtrip() {
+        let key = [7u8; 32];
+        let blob = seal(&key, b"issuer secret", b"token-1").unwrap();
+        let plaintext = open(&key, &blob, b"token-1").unwrap();
+        assert_eq!(plaintext, b"issuer secret");
+    }
+
+    #[test]
+    fn test_open_fails_with_wrong_key_or_aad() {
+        let key = [7u8; 32];
+        let blob = seal(&key, b"issuer secret", b"token-1").unwrap();
+        assert!(open(&[8u8; 32], &blob, b"token-1").is_err());
+        assert!(open(&key, &blob, b"token-2").is_err());
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let master = [42u8; 32];
+        let (secret, wrapped) = seal_with_data_key(&master, b"issuer secret", b"token-1").unwrap();
+        let plaintext = open_with_data_key(&master, &secret, &wrapped, b"token-1").unwrap();
+        assert_eq!(plaintext, b"issuer secret");
+        assert!(open_with_data_key(&[0u8; 32], &secret, &wrapped, b"token-1").is_err());
+    }
+
+    #[test]
+    fn test_seal_rejects_short_key() {
+        let result = seal(&[1u8; 16], b"data", b"");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("32 bytes"));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use crate::models::{DepositRecord, Payment, PaymentStatus, EscrowStatus};
+use crate::utils::payment_calculator::ON_CHAIN_UNITS_PER_TOKEN;
+
+/// Vault balances are stored in base units
+pub fn to_base_units(amount: f64) -> i64 {
+    (amount * ON_CHAIN_UNITS_PER_TOKEN).round() as i64
+}
+
+/// Balance per token_key that a wallet should hold according to our own records:
+/// deposits credited to it, plus completed payments received as vendor,
+/// minus completed payments made as customer. Escrowed payments only reach the
+/// vendor once captured, and cost the customer nothing once refunded.
+/// Deposits only carry a symbol, so `token_ids_by_symbol` maps them to token keys;
+/// deposits for unknown symbols are skipped.
+pub fn expected_balances(
+    wallet_address: &str,
+    deposits: &[DepositRecord],
+    payments: &[Payment],
+    token_ids_by_symbol: &HashMap<String, String>,
+) -> HashMap<String, i64> {
+    let mut balances: HashMap<String, i64> = HashMap::new();
+
+    for deposit in deposits.iter().filter(|d| d.wallet_address == wallet_address) {
+        if let Some(token_id) = token_ids_by_symbol.get(&deposit.token_symbol) {
+            // amount_tokens_received is already recorded in base units
+            *balances.entry(token_id.clone()).or_default() += deposit.amount_tokens_received.round() as i64;
+        }
+    }
+
+    for payment in payments.iter().filter(|p| p.status == PaymentStatus::Completed) {
+        let escrow_status = payment.escrow.as_ref().map(|escrow| &escrow.status);
+        let sign = if payment.customer_address.as_deref() == Some(wallet_address) {
+            if escrow_status == Some(&EscrowStatus::Refunded) {
+                continue;
+            }
+            -1
+        } else if payment.vendor_address == wallet_address {
+            if !matches!(escrow_status, None | Some(EscrowStatus::Captured)) {
+                continue;
+            }
+            1
+        } else {
+            continue;
+        };
+        for token_payment in payment.computed_payment.iter().flatten() {
+            *balances.entry(token_payment.token_key.clone()).or_default() += sign * to_base_units(token_payment.amount_to_pay);
+        }
+    }
+
+    balances
+}
+
+/// Index of the deposit that credited a Stripe checkout session, skipping deposits
+/// already `claimed` by another session. Deposits recorded with the session id match
+/// directly; older ones without it are matched on wallet, symbol and amount, taking
+/// the earliest deposit at or after the charge.
+pub fn find_deposit_for_session(
+    session_id: &str,
+    wallet_address: &str,
+    token_symbol: &str,
+    amount_cents: i64,
+    session_created: i64,
+    deposits: &[DepositRecord],
+    claimed: &[bool],
+) -> Option<usize> {
+    // Webhooks normally land within seconds; allow for clock skew between Stripe and us
+    const CLOCK_SKEW_SECS: i64 = 60;
+
+    if let Some(i) = deposits.iter().position(|d| d.stripe_session_id.as_deref() == Some(session_id)) {
+        return Some(i);
+    }
+
+    deposits
+        .iter()
+        .enumerate()
+        .filter(|(i, d)| {
+            !claimed.get(*i).copied().unwrap_or(false)
+                && d.stripe_session_id.is_none()
+                && d.wallet_address == wallet_address
+                && d.token_symbol == token_symbol
+                && (d.amount_deposited_usd * 100.0).round() as i64 == amount_cents
+                && d.created_at >= session_created - CLOCK_SKEW_SECS
+        })
+        .min_by_key(|(_, d)| d.created_at)
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{TokenPayment, PaymentEscrow};
+
+    fn deposit(wallet: &str, symbol: &str, units: f64) -> DepositRecord {
+        DepositRecord {
+            id: None,
+            wallet_address: wallet.to_string(),
+            token_symbol: symbol.to_string(),
+            token_image_url: None,
+            amount_deposited_usd: units / ON_CHAIN_UNITS_PER_TOKEN,
+            amount_tokens_received: units,
+            created_at: 0,
+            stripe_session_id: None,
+            stripe_payment_intent_id: None,
+            executor_tx_id: None,
+            manual_credit: None,
+            referrer: None,
+            campaign_id: None,
+            fundraiser_id: None,
+        }
+    }
+
+    fn payment(vendor: &str, customer: &str, status: PaymentStatus, bundle: Vec<(&str, f64)>) -> Payment {
+        Payment {
+            id: None,
+            payment_id: "ABC12".to_string(),
+            vendor_address: vendor.to_string(),
+            vendor_name: "Vendor".to_string(),
+            price_usd: 0.0,
+            customer_address: Some(customer.to_string()),
+            customer_username: None,
+            status,
+            created_at: 0,
+            vendor_valuations: None,
+            discount_consumption: None,
+            computed_payment: Some(bundle.into_iter().map(|(key, amount)| TokenPayment {
+                token_key: key.to_string(),
+                symbol: "T".to_string(),
+                amount_to_pay: amount,
+                token_image_url: None,
+            }).collect()),
+            initial_payment_bundle: None,
+            recepient_verified: false,
+            executor_tx_id: None,
+            submitted_at: None,
+            finality_checked_at: None,
+            failure_reason: None,
+            payment_request_id: None,
+            loyalty_redemption: None,
+            loyalty_points_earned: None,
+            promo: None,
+            escrow: None,
+            schedule_id: None,
+            batch_id: None,
+            invoice_id: None,
+            valuation_overrides: None,
+            budget_consumed: false,
+            splits: Vec::new(),
+            split_legs: Vec::new(),
+            authorization: None,
+            vendor_slug: None,
+            short_code: None,
+        }
+    }
+
+    #[test]
+    fn test_expected_balances() {
+        let ids: HashMap<String, String> = [("USD".to_string(), "usd,1".to_string())].into();
+        let deposits = vec![deposit("alice", "USD", 1000.0), deposit("alice", "UNKNOWN", 50.0)];
+        let payments = vec![
+            payment("bob", "alice", PaymentStatus::Completed, vec![("usd,1", 2.5)]),
+            payment("bob", "alice", PaymentStatus::Calculated, vec![("usd,1", 5.0)]),
+            payment("alice", "carol", PaymentStatus::Completed, vec![("gift,1", 1.0)]),
+        ];
+
+        let alice = expected_balances("alice", &deposits, &payments, &ids);
+        assert_eq!(alice.get("usd,1"), Some(&750));
+        assert_eq!(alice.get("gift,1"), Some(&100));
+        assert_eq!(alice.len(), 2);
+
+        let bob = expected_balances("bob", &deposits, &payments, &ids);
+        assert_eq!(bob.get("usd,1"), Some(&250));
+    }
+
+    #[test]
+    fn test_expected_balances_with_escrow() {
+        let escrowed = |status: EscrowStatus| {
+            let mut payment = payment("bob", "alice", PaymentStatus::Completed, vec![("usd,1", 1.0)]);
+            payment.escrow = Some(PaymentEscrow {
+                status,
+                vault_address: "escrow".to_string(),
+                hold_hours: 24,
+                held_at: None,
+                release_at: None,
+                settled_at: None,
+                settled_by: None,
+                settlement_tx_id: None,
+                submitted_at: None,
+                failure_reason: None,
+                updated_at: 0,
+            });
+            payment
+        };
+        let payments = vec![
+            escrowed(EscrowStatus::Held),
+            escrowed(EscrowStatus::Captured),
+            escrowed(EscrowStatus::Refunded),
+        ];
+        let ids = HashMap::new();
+
+        let alice = expected_balances("alice", &[], &payments, &ids);
+        assert_eq!(alice.get("usd,1"), Some(&-200));
+
+        let bob = expected_balances("bob", &[], &payments, &ids);
+        assert_eq!(bob.get("usd,1"), Some(&100));
+    }
+
+    #[test]
+    fn test_find_deposit_for_session() {
+        let mut by_session = deposit("alice", "USD", 500.0);
+        by_session.stripe_session_id = Some("cs_1".to_string());
+        by_session.created_at = 2000;
+        let mut legacy_late = deposit("alice", "USD", 500.0);
+        legacy_late.created_at = 1500;
+        let mut legacy_early = deposit("alice", "USD", 500.0);
+        legacy_early.created_at = 1010;
+        let deposits = vec![by_session, legacy_late, legacy_early];
+        let claimed = vec![false; 3];
+
+        assert_eq!(find_deposit_for_session("cs_1", "alice", "USD", 500, 1000, &deposits, &claimed), Some(0));
+        assert_eq!(find_deposit_for_session("cs_2", "alice", "USD", 500, 1000, &deposits, &claimed), Some(2));
+        assert_eq!(find_deposit_for_session("cs_2", "alice", "USD", 500, 1000, &deposits, &[false, false, true]), Some(1));
+        assert_eq!(find_deposit_for_session("cs_2", "alice", "USD", 700, 1000, &deposits, &claimed), None);
+        assert_eq!(find_deposit_for_session("cs_2", "bob", "USD", 500, 1000, &deposits, &claimed), None);
+        assert_eq!(find_deposit_for_session("cs_2", "alice", "USD", 500, 1600, &deposits, &claimed), None);
+    }
+
+    #[test]
+    fn test_to_base_units_rounds() {
+        assert_eq!(to_base_units(2.5), 250);
+        assert_eq!(to_base_units(0.333), 33);
+    }
+}
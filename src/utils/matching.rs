@@ -0,0 +1,33 @@
+/// Cents a pool should add for a donation: the donation times the ratio, limited by
+/// the per-donation cap and whatever is left in the pool
+pub fn compute_match(donation_cents: i64, match_ratio: f64, remaining_cents: i64, per_donation_cap_cents: Option<i64>) -> i64 {
+    if donation_cents <= 0 || match_ratio <= 0.0 || remaining_cents <= 0 {
+        return 0;
+    }
+    let mut matched = (donation_cents as f64 * match_ratio).floor() as i64;
+    if let Some(cap) = per_donation_cap_cents {
+        matched = matched.min(cap);
+    }
+    matched.min(remaining_cents).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_match_ratio_and_caps() {
+        assert_eq!(compute_match(1000, 1.0, 50_000, None), 1000);
+        assert_eq!(compute_match(1000, 0.5, 50_000, None), 500);
+        assert_eq!(compute_match(1000, 2.0, 50_000, Some(1500)), 1500);
+        // Only what is left in the pool
+        assert_eq!(compute_match(1000, 1.0, 300, None), 300);
+    }
+
+    #[test]
+    fn test_compute_match_nothing_to_match() {
+        assert_eq!(compute_match(1000, 1.0, 0, None), 0);
+        assert_eq!(compute_match(0, 1.0, 500, None), 0);
+        assert_eq!(compute_match(1000, 0.0, 500, None), 0);
+    }
+}
@@ -1,4 +1,32 @@
 pub mod payment_calculator;
 pub mod bonding_curve;
 pub mod payment_code;
-pub use payment_calculator::{calculate_vendor_valuations, calculate_payment_bundle, apply_discounts_to_payment, calculate_post_payment_valuations, verify_sufficient_funds_after_discounts};
+pub mod key_encryption;
+pub mod audit;
+pub mod wallet_signature;
+pub mod pagination;
+pub mod report_period;
+pub mod ledger;
+pub mod retry;
+pub mod circuit_breaker;
+pub mod ttl_cache;
+pub mod profile;
+pub mod email_verification;
+pub mod name_filter;
+pub mod matching;
+pub mod quadratic_funding;
+pub mod geo;
+pub mod signed_payload;
+pub mod recurrence;
+pub mod preferences;
+pub mod rate_limit;
+pub mod response_signature;
+pub mod holders;
+pub mod holdings;
+pub mod etag;
+pub mod validation;
+pub mod redaction;
+pub mod cron;
+pub mod payment_split;
+pub mod replica;
+pub use payment_calculator::{calculate_vendor_valuations, calculate_payment_bundle, apply_discounts_to_payment, calculate_post_payment_valuations, verify_sufficient_funds_after_discounts, spread_discount, check_valuation_override, apply_valuation_overrides, reconcile_on_chain_amounts, to_on_chain_units};
@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use crate::models::BlockedWordKind;
+
+/// Names that could pass a cause off as the platform or an existing currency
+pub const DEFAULT_RESERVED_WORDS: &[&str] = &[
+    "usd", "usdc", "usdt", "dollar", "admin", "administrator", "official", "indexwallets",
+    "support", "moderator", "staff", "system", "root", "stripe", "verified",
+];
+
+pub const DEFAULT_PROFANITY: &[&str] = &[
+    "fuck", "fucker", "fucking", "shit", "bullshit", "bitch", "cunt", "asshole", "bastard",
+    "dick", "cock", "pussy", "whore", "slut", "wanker", "nigger", "nigga", "faggot", "retard",
+];
+
+/// Blocks reserved words and profanity in cause and token names
+#[derive(Clone, Default)]
+pub struct NameFilter {
+    reserved: HashSet<String>,
+    profanity: HashSet<String>,
+}
+
+impl NameFilter {
+    pub fn with_defaults() -> Self {
+        let mut filter = Self::default();
+        for word in DEFAULT_RESERVED_WORDS {
+            filter.add(BlockedWordKind::Reserved, word);
+        }
+        for word in DEFAULT_PROFANITY {
+            filter.add(BlockedWordKind::Profanity, word);
+        }
+        filter
+    }
+
+    pub fn add(&mut self, kind: BlockedWordKind, word: &str) {
+        let word = normalize(word).replace(' ', "");
+        if word.is_empty() {
+            return;
+        }
+        match kind {
+            BlockedWordKind::Reserved => self.reserved.insert(word),
+            BlockedWordKind::Profanity => self.profanity.insert(word),
+        };
+    }
+
+    /// Add comma-separated words from NAME_FILTER_RESERVED_WORDS and NAME_FILTER_PROFANITY
+    pub fn extend_from_env(&mut self) {
+        for (var, kind) in [
+            ("NAME_FILTER_RESERVED_WORDS", BlockedWordKind::Reserved),
+            ("NAME_FILTER_PROFANITY", BlockedWordKind::Profanity),
+        ] {
+            if let Ok(list) = std::env::var(var) {
+                for word in list.split(',') {
+                    self.add(kind, word);
+                }
+            }
+        }
+    }
+
+    /// Err with a user-facing message if `value` contains a blocked word
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let words = words(value);
+        let compact: String = words.concat();
+
+        // Profanity also matches simple plurals ("bitches" -> "bitch"). Reserved words only
+        // match exactly, since they're ordinary words whose plurals make ordinary names
+        // ("Stripes Animal Rescue", "Roots Garden").
+        let profane = self.profanity.contains(&compact)
+            || words.iter().any(|w| {
+                self.profanity.contains(w)
+                    || w.strip_suffix('s').map_or(false, |w| self.profanity.contains(w))
+                    || w.strip_suffix("es").map_or(false, |w| self.profanity.contains(w))
+            });
+        let reserved = self.reserved.contains(&compact) || words.iter().any(|w| self.reserved.contains(w));
+
+        if profane {
+            return Err("Name contains inappropriate language".to_string());
+        }
+        if reserved {
+            return Err("Name contains a reserved word".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Lowercase and undo common character substitutions ("4dm1n" -> "admin")
+fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect()
+}
+
+// Split into words, joining runs of single letters so "f u c k" is one word
+fn words(value: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut letters = String::new();
+    for word in normalize(value).split_whitespace() {
+        if word.chars().count() == 1 {
+            letters.push_str(word);
+            continue;
+        }
+        if !letters.is_empty() {
+            words.push(std::mem::take(&mut letters));
+        }
+        words.push(word.to_string());
+    }
+    if !letters.is_empty() {
+        words.push(letters);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_ordinary_names() {
+        let filter = NameFilter::with_defaults();
+        assert!(filter.check("Clean Water Fund").is_ok());
+        assert!(filter.check("Scunthorpe Food Bank").is_ok());
+        assert!(filter.check("GROW").is_ok());
+    }
+
+    #[test]
+    fn test_blocks_reserved_words() {
+        let filter = NameFilter::with_defaults();
+        assert!(filter.check("USD").is_err());
+        assert!(filter.check("Official Relief Fund").is_err());
+        assert!(filter.check("4dm1n tokens").is_err());
+        assert!(filter.check("Index-Wallets").is_err());
+    }
+
+    #[test]
+    fn test_allows_plurals_of_reserved_words() {
+        let filter = NameFilter::with_defaults();
+        assert!(filter.check("Stripes Animal Rescue").is_ok());
+        assert!(filter.check("Roots Community Garden").is_ok());
+        assert!(filter.check("Dollars for Scholars").is_ok());
+        assert!(filter.check("Systems Thinking Trust").is_ok());
+        assert!(filter.check("Supporters of the Library").is_ok());
+        // The words themselves are still reserved
+        assert!(filter.check("Stripe Rescue").is_err());
+        assert!(filter.check("Root Garden").is_err());
+    }
+
+    #[test]
+    fn test_blocks_profanity_and_evasions() {
+        let filter = NameFilter::with_defaults();
+        assert_eq!(filter.check("Sh1t Coin").unwrap_err(), "Name contains inappropriate language");
+        assert!(filter.check("f u c k").is_err());
+        assert!(filter.check("Bitches United").is_err());
+    }
+
+    #[test]
+    fn test_custom_words() {
+        let mut filter = NameFilter::default();
+        assert!(filter.check("Acme Fund").is_ok());
+        filter.add(BlockedWordKind::Reserved, "Acme");
+        assert!(filter.check("Acme Fund").is_err());
+    }
+}
@@ -0,0 +1,39 @@
+/// Opaque keyset cursor for lists sorted by (created_at desc, id desc).
+/// Encoded as "<created_at>_<id>" so it survives query strings unescaped.
+pub fn encode_cursor(created_at: i64, id: &str) -> String {
+    format!("{}_{}", created_at, id)
+}
+
+/// Reverse of `encode_cursor`
+pub fn decode_cursor(cursor: &str) -> Result<(i64, String), String> {
+    let (created_at, id) = cursor
+        .split_once('_')
+        .ok_or_else(|| "Invalid cursor".to_string())?;
+    let created_at = created_at
+        .parse::<i64>()
+        .map_err(|_| "Invalid cursor".to_string())?;
+    if id.is_empty() {
+        return Err("Invalid cursor".to_string());
+    }
+    Ok((created_at, id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_roundtrip() {
+        let cursor = encode_cursor(1700000000, "ABC12");
+        assert_eq!(cursor, "1700000000_ABC12");
+        assert_eq!(decode_cursor(&cursor).unwrap(), (1700000000, "ABC12".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_cursor() {
+        assert!(decode_cursor("").is_err());
+        assert!(decode_cursor("ABC12").is_err());
+        assert!(decode_cursor("notanumber_ABC12").is_err());
+        assert!(decode_cursor("1700000000_").is_err());
+    }
+}
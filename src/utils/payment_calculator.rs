@@ -1,11 +1,72 @@
+use std::collections::HashMap;
 use crate::models::{TokenBalance, TokenValuation, DiscountConsumption, TokenPayment};
 use mongodb::bson::Document;
 
 const LAMBDA: f64 = 0.2;
 
+/// Token amounts go on-chain as integers of this many units per token
+pub const ON_CHAIN_UNITS_PER_TOKEN: f64 = 100.0;
+
+/// Bounds on a per-payment valuation override, as a multiple of the token's market valuation
+pub const MIN_VALUATION_OVERRIDE_RATIO: f64 = 0.5;
+pub const MAX_VALUATION_OVERRIDE_RATIO: f64 = 2.0;
+
+/// How much of a payment, in USD, each of the payer's tokens covers, index-aligned with
+/// `payer_balances`. Without spending weights every token pays in proportion to its value,
+/// keeping the portfolio's allocation. With them, tokens pay in proportion to value times
+/// weight (1 when unlisted), never more than they're worth, and tokens weighted 0 only
+/// cover what the others can't. A price beyond the portfolio's value is split
+/// proportionally, for the insufficient funds checks to catch.
+pub fn spending_shares(
+    payer_balances: &[TokenBalance],
+    spending_weights: &HashMap<String, f64>,
+    total_price: f64,
+) -> Vec<f64> {
+    let values: Vec<f64> = payer_balances.iter()
+        .map(|b| b.balance * b.average_valuation)
+        .collect();
+    let total_value: f64 = values.iter().sum();
+    if total_value <= 0.0 {
+        return vec![0.0; values.len()];
+    }
+    if spending_weights.is_empty() || total_price >= total_value {
+        return values.iter().map(|value| total_price * value / total_value).collect();
+    }
+
+    let weight = |i: usize| spending_weights.get(&payer_balances[i].symbol).copied().unwrap_or(1.0).max(0.0);
+    let mut shares = vec![0.0; values.len()];
+    let mut remaining = total_price;
+    let (weighted, preserved): (Vec<usize>, Vec<usize>) = (0..values.len())
+        .filter(|&i| values[i] > 0.0)
+        .partition(|&i| weight(i) > 0.0);
+    for (mut open, weighted) in [(weighted, true), (preserved, false)] {
+        while remaining > 0.0 && !open.is_empty() {
+            let stake = |i: usize| if weighted { values[i] * weight(i) } else { values[i] };
+            let total_stake: f64 = open.iter().map(|&i| stake(i)).sum();
+            // Tokens this round would overdraw pay in full, and the rest is split again
+            let (full, partial): (Vec<usize>, Vec<usize>) = open.into_iter()
+                .partition(|&i| remaining * stake(i) / total_stake >= values[i]);
+            if full.is_empty() {
+                for &i in &partial {
+                    shares[i] = remaining * stake(i) / total_stake;
+                }
+                remaining = 0.0;
+            } else {
+                for &i in &full {
+                    shares[i] = values[i];
+                    remaining -= values[i];
+                }
+            }
+            open = partial;
+        }
+    }
+    shares
+}
+
 pub fn calculate_vendor_valuations(
     user_preferences: &Document,
     available_tokens: &[TokenBalance],
+    spending_weights: &HashMap<String, f64>,
     payment_amount: f64,
 ) -> (Vec<TokenValuation>, Vec<DiscountConsumption>) {
     let mut valuations = Vec::new();
@@ -20,11 +81,8 @@ pub fn calculate_vendor_valuations(
         return (valuations, consumptions);
     }
     
-    for token in available_tokens {
-        let token_value = token.balance * token.average_valuation;
-        let payment_proportion = token_value / total_balance;
-        let token_payment_value = payment_amount * payment_proportion;
-        
+    let shares = spending_shares(available_tokens, spending_weights, payment_amount);
+    for (token, &token_payment_value) in available_tokens.iter().zip(&shares) {
         // Look up vendor's discount budget for this token (stored in USD)
         let preference_amount = user_preferences
             .get(&token.symbol)
@@ -74,9 +132,103 @@ pub fn calculate_vendor_valuations(
     (valuations, consumptions)
 }
 
+/// Check a vendor's per-payment valuation of a token against its market valuation
+pub fn check_valuation_override(valuation: f64, market_valuation: f64) -> Result<(), String> {
+    if !valuation.is_finite() || valuation <= 0.0 {
+        return Err("Valuations must be greater than zero".to_string());
+    }
+    if market_valuation > 0.0 {
+        let ratio = valuation / market_valuation;
+        if !(MIN_VALUATION_OVERRIDE_RATIO..=MAX_VALUATION_OVERRIDE_RATIO).contains(&ratio) {
+            return Err(format!(
+                "Valuations must be between {}x and {}x the token's market valuation of {}",
+                MIN_VALUATION_OVERRIDE_RATIO, MAX_VALUATION_OVERRIDE_RATIO, market_valuation,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Replace preference-based valuations with the vendor's per-payment overrides, matched by
+/// token key or symbol. An overridden token draws nothing from the vendor's discount budget;
+/// instead the customer pays as if the token were worth the override, so valuing it above
+/// market is a discount and below a premium. Returns that adjustment as consumptions for
+/// `apply_discounts_to_payment`, kept apart from the budget consumptions.
+pub fn apply_valuation_overrides(
+    valuations: &mut [TokenValuation],
+    consumptions: &mut [DiscountConsumption],
+    overrides: &[TokenValuation],
+    available_tokens: &[TokenBalance],
+    spending_weights: &HashMap<String, f64>,
+    payment_amount: f64,
+) -> Vec<DiscountConsumption> {
+    let total_balance: f64 = available_tokens.iter()
+        .map(|t| t.balance * t.average_valuation)
+        .sum();
+    if total_balance == 0.0 {
+        return Vec::new();
+    }
+
+    let shares = spending_shares(available_tokens, spending_weights, payment_amount);
+    let mut adjustments = Vec::new();
+    for (token, &token_payment_value) in available_tokens.iter().zip(&shares) {
+        let Some(valuation_override) = overrides.iter()
+            .find(|o| o.token_key == token.token_key || o.symbol == token.symbol) else { continue };
+        if valuation_override.valuation <= 0.0 || token.average_valuation <= 0.0 {
+            continue;
+        }
+        if let Some(valuation) = valuations.iter_mut().find(|v| v.token_key == token.token_key) {
+            valuation.valuation = valuation_override.valuation;
+        }
+        if let Some(consumption) = consumptions.iter_mut().find(|c| c.token_key == token.token_key) {
+            consumption.amount_used = 0.0;
+        }
+        adjustments.push(DiscountConsumption {
+            token_key: token.token_key.clone(),
+            symbol: token.symbol.clone(),
+            amount_used: token_payment_value * (1.0 - token.average_valuation / valuation_override.valuation),
+        });
+    }
+    adjustments
+}
+
+/// Drop legs paying less than `dust_threshold` of their token, which would round to nothing
+/// on-chain, and move their value to the largest holdings still paying (then the largest
+/// of the rest) up to what each holds, so the bundle is worth the same
+fn consolidate_dust(shares: &mut [f64], payer_balances: &[TokenBalance], dust_threshold: f64) {
+    if dust_threshold <= 0.0 {
+        return;
+    }
+    let value = |i: usize| payer_balances[i].balance * payer_balances[i].average_valuation;
+    let mut freed = 0.0;
+    for (i, balance) in payer_balances.iter().enumerate() {
+        if shares[i] > 0.0 && balance.average_valuation > 0.0 && shares[i] / balance.average_valuation < dust_threshold {
+            freed += shares[i];
+            shares[i] = 0.0;
+        }
+    }
+    if freed == 0.0 {
+        return;
+    }
+
+    let mut order: Vec<usize> = (0..shares.len()).filter(|&i| value(i) > 0.0).collect();
+    order.sort_by(|&a, &b| (shares[b] > 0.0).cmp(&(shares[a] > 0.0))
+        .then(value(b).partial_cmp(&value(a)).unwrap_or(std::cmp::Ordering::Equal)));
+    for i in order {
+        let moved = freed.min(value(i) - shares[i]);
+        shares[i] += moved;
+        freed -= moved;
+        if freed <= 0.0 {
+            break;
+        }
+    }
+}
+
 pub fn calculate_payment_bundle(
     payer_balances: &[TokenBalance],
     vendor_valuations: &[TokenValuation],
+    spending_weights: &HashMap<String, f64>,
+    dust_threshold: f64,
     total_price: f64,
 ) -> Result<Vec<TokenPayment>, String> {
     let mut payments = Vec::new();
@@ -91,11 +243,13 @@ pub fn calculate_payment_bundle(
     
     // Skip the insufficient funds check here - we'll check after discounts/premiums
     
-    // Pay proportionally based on value to maintain portfolio allocation
-    for balance in payer_balances {
-        let token_value = balance.balance * balance.average_valuation;
-        let payment_proportion = token_value / total_wallet_value;
-        let payment_value = total_price * payment_proportion;
+    // Pay proportionally based on value to maintain portfolio allocation, unless the
+    // payer weighted which tokens to spend
+    let mut shares = spending_shares(payer_balances, spending_weights, total_price);
+    if total_price < total_wallet_value {
+        consolidate_dust(&mut shares, payer_balances, dust_threshold);
+    }
+    for (balance, &payment_value) in payer_balances.iter().zip(&shares) {
         
         let tokens_to_pay = if balance.average_valuation > 0.0 {
             payment_value / balance.average_valuation
@@ -103,7 +257,8 @@ pub fn calculate_payment_bundle(
             0.0
         };
         
-        if balance.balance == 0.0 {
+        // Nothing to pay from empty holdings, or ones the payer held back or folded away as dust
+        if balance.balance == 0.0 || payment_value <= 0.0 {
             continue;
         }
         
@@ -157,6 +312,35 @@ pub fn apply_discounts_to_payment(
     Ok(())
 }
 
+/// Spread a flat USD discount (e.g. a loyalty reward) across the tokens of a payment in
+/// proportion to their market value, as consumptions `apply_discounts_to_payment` can apply.
+/// The discount is capped at the payment's value.
+pub fn spread_discount(
+    payments: &[TokenPayment],
+    payer_balances: &[TokenBalance],
+    discount_usd: f64,
+) -> Vec<DiscountConsumption> {
+    let market_value = |payment: &TokenPayment| payer_balances.iter()
+        .find(|b| b.token_key == payment.token_key)
+        .map(|b| b.average_valuation)
+        .unwrap_or(0.0);
+    let total_value: f64 = payments.iter()
+        .map(|p| p.amount_to_pay * market_value(p))
+        .sum();
+    if total_value <= 0.0 || discount_usd <= 0.0 {
+        return Vec::new();
+    }
+    let discount_usd = discount_usd.min(total_value);
+    
+    payments.iter()
+        .map(|payment| DiscountConsumption {
+            token_key: payment.token_key.clone(),
+            symbol: payment.symbol.clone(),
+            amount_used: discount_usd * payment.amount_to_pay * market_value(payment) / total_value,
+        })
+        .collect()
+}
+
 pub fn verify_sufficient_funds_after_discounts(
     final_payments: &[TokenPayment],
     payer_balances: &[TokenBalance],
@@ -204,6 +388,58 @@ pub fn verify_sufficient_funds_after_discounts(
     Ok(actual_total_cost)
 }
 
+/// A token amount in on-chain units
+pub fn to_on_chain_units(amount: f64) -> u64 {
+    (amount * ON_CHAIN_UNITS_PER_TOKEN).round().max(0.0) as u64
+}
+
+/// Round a final bundle to on-chain units so its USD value at the vendor's valuations stays
+/// as close to the unrounded value as whole units allow, rather than drifting by a unit per
+/// token. A unit of each token is worth a different amount, so the error is made up in
+/// value: the leg with the cheapest unit takes the correction first, the largest leg among
+/// equals, within the payer's balance. Amounts are rewritten to match what will be signed;
+/// the units are returned index-aligned with `payments`.
+pub fn reconcile_on_chain_amounts(payments: &mut [TokenPayment], payer_balances: &[TokenBalance], vendor_valuations: &[TokenValuation]) -> Vec<u64> {
+    let unit_values: Vec<f64> = payments.iter()
+        .map(|p| {
+            let valuation = vendor_valuations.iter()
+                .find(|v| v.token_key == p.token_key)
+                .map(|v| v.valuation)
+                .or_else(|| payer_balances.iter().find(|b| b.token_key == p.token_key).map(|b| b.average_valuation))
+                .unwrap_or(0.0);
+            valuation / ON_CHAIN_UNITS_PER_TOKEN
+        })
+        .collect();
+    let mut units: Vec<u64> = payments.iter().map(|p| to_on_chain_units(p.amount_to_pay)).collect();
+    let target: f64 = payments.iter().zip(&unit_values)
+        .map(|(p, unit_value)| p.amount_to_pay * ON_CHAIN_UNITS_PER_TOKEN * unit_value)
+        .sum();
+    let mut error: f64 = units.iter().zip(&unit_values)
+        .map(|(&amount, unit_value)| amount as f64 * unit_value)
+        .sum::<f64>() - target;
+
+    let mut order: Vec<usize> = (0..payments.len()).filter(|&i| unit_values[i] > 0.0).collect();
+    order.sort_by(|&a, &b| unit_values[a].total_cmp(&unit_values[b]).then(units[b].cmp(&units[a])));
+    for i in order {
+        // Less than half this leg's unit off, and every later leg's unit is bigger still
+        let step = (-error / unit_values[i]).round() as i64;
+        if step == 0 {
+            break;
+        }
+        let available = payer_balances.iter()
+            .find(|b| b.token_key == payments[i].token_key)
+            .map_or(u64::MAX, |b| (b.balance * ON_CHAIN_UNITS_PER_TOKEN).round() as u64);
+        let adjusted = (units[i] as i64 + step).clamp(0, available.max(units[i]) as i64);
+        error += (adjusted - units[i] as i64) as f64 * unit_values[i];
+        units[i] = adjusted as u64;
+    }
+
+    for (payment, &amount) in payments.iter_mut().zip(&units) {
+        payment.amount_to_pay = amount as f64 / ON_CHAIN_UNITS_PER_TOKEN;
+    }
+    units
+}
+
 #[allow(dead_code)]
 pub fn calculate_post_payment_valuations(
     initial_payments: &[TokenPayment],
@@ -268,7 +504,7 @@ mod tests {
         let vendor_valuations = vec![];
         let total_price = 1000.0;
 
-        let result = calculate_payment_bundle(&balances, &vendor_valuations, total_price).unwrap();
+        let result = calculate_payment_bundle(&balances, &vendor_valuations, &HashMap::new(), 0.0, total_price).unwrap();
 
         assert_eq!(result.len(), 3);
         
@@ -283,6 +519,32 @@ mod tests {
         assert!((usd_payment.amount_to_pay - 200.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_spending_shares() {
+        let balances = vec![
+            create_test_balance("MEME", 100.0, 1.0),
+            create_test_balance("USD", 100.0, 1.0),
+            create_test_balance("ETH", 0.0, 3000.0),
+        ];
+        let close = |a: &[f64], b: &[f64]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9);
+
+        // No weights keeps the portfolio's allocation
+        assert!(close(&spending_shares(&balances, &HashMap::new(), 50.0), &[25.0, 25.0, 0.0]));
+
+        // Spend MEME first, preserve USD
+        let weights = HashMap::from([("MEME".to_string(), 3.0), ("USD".to_string(), 0.0)]);
+        assert!(close(&spending_shares(&balances, &weights, 50.0), &[50.0, 0.0, 0.0]));
+        assert!(close(&spending_shares(&balances, &weights, 150.0), &[100.0, 50.0, 0.0]));
+
+        // Heavier weights pay more, up to the token's value
+        let weights = HashMap::from([("MEME".to_string(), 3.0)]);
+        assert!(close(&spending_shares(&balances, &weights, 100.0), &[75.0, 25.0, 0.0]));
+        assert!(close(&spending_shares(&balances, &weights, 180.0), &[100.0, 80.0, 0.0]));
+
+        // More than the portfolio is worth is split proportionally
+        assert!(close(&spending_shares(&balances, &weights, 300.0), &[150.0, 150.0, 0.0]));
+    }
+
     #[test]
     fn test_discount_application_with_lambda() {
         let balances = vec![
@@ -296,7 +558,7 @@ mod tests {
 
         let payment_amount = 1000.0;
         
-        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, payment_amount);
+        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, &HashMap::new(), payment_amount);
 
         // λ=0.2 caps discount at 20% of payment value
         // BTC gets $625 of payment, max discount $125, budget $100 -> uses $100
@@ -365,13 +627,76 @@ mod tests {
         let vendor_valuations = vec![];
         let total_price = 100.0;
 
-        let result = calculate_payment_bundle(&balances, &vendor_valuations, total_price).unwrap();
+        let result = calculate_payment_bundle(&balances, &vendor_valuations, &HashMap::new(), 0.0, total_price).unwrap();
 
         // Should only have 2 payments (skip ETH with 0 balance)
         assert_eq!(result.len(), 2);
         assert!(result.iter().find(|p| p.symbol == "ETH").is_none());
     }
 
+    #[test]
+    fn test_dust_consolidation() {
+        let balances = vec![
+            create_test_balance("BTC", 1.0, 50000.0),   // $50k
+            create_test_balance("MEME", 5.0, 0.01),     // $0.05
+            create_test_balance("USD", 100.0, 1.0),     // $100
+            create_test_balance("ETH", 10.0, 3000.0),   // $30k
+        ];
+        let total_price = 100.0;
+
+        let result = calculate_payment_bundle(&balances, &vec![], &HashMap::new(), 0.0, total_price).unwrap();
+        assert_eq!(result.len(), 4);
+
+        // BTC, MEME and USD would each pay less than one on-chain unit, so ETH covers them
+        let result = calculate_payment_bundle(&balances, &vec![], &HashMap::new(), 0.01, total_price).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].symbol, "ETH");
+        assert!((result[0].amount_to_pay * 3000.0 - total_price).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reconcile_on_chain_amounts() {
+        let balances = vec![
+            create_test_balance("A", 10.0, 1.0),
+            create_test_balance("B", 10.0, 1.0),
+            create_test_balance("C", 10.0, 1.0),
+        ];
+        let payment = |symbol: &str, amount_to_pay: f64| TokenPayment {
+            token_key: format!("test_{}", symbol),
+            symbol: symbol.to_string(),
+            amount_to_pay,
+            token_image_url: None,
+        };
+
+        // Each rounds down on its own, losing a unit of the 10.00 total
+        let mut payments = vec![payment("A", 3.334), payment("B", 3.333), payment("C", 3.333)];
+        assert_eq!(reconcile_on_chain_amounts(&mut payments, &balances, &[]), vec![334, 333, 333]);
+        assert_eq!(payments[0].amount_to_pay, 3.34);
+
+        // Each rounds up, gaining one; the largest leg gives it back
+        let mut payments = vec![payment("A", 1.006), payment("B", 4.006), payment("C", 2.006)];
+        assert_eq!(reconcile_on_chain_amounts(&mut payments, &balances, &[]), vec![101, 400, 201]);
+
+        // The largest leg is the whole balance, so the next one takes the extra unit
+        let balances = vec![
+            create_test_balance("A", 5.0, 1.0),
+            create_test_balance("B", 10.0, 1.0),
+            create_test_balance("C", 10.0, 1.0),
+        ];
+        let mut payments = vec![payment("A", 5.0), payment("B", 1.004), payment("C", 1.004)];
+        assert_eq!(reconcile_on_chain_amounts(&mut payments, &balances, &[]), vec![500, 101, 100]);
+
+        // A unit of A is worth twenty of B at the vendor's valuations, so B makes up the
+        // $0.04 that rounding A lost instead of a unit count across both
+        let balances = vec![create_test_balance("A", 10.0, 8.0), create_test_balance("B", 10.0, 0.4)];
+        let valuations = vec![
+            TokenValuation { token_key: "test_A".to_string(), symbol: "A".to_string(), valuation: 10.0 },
+            TokenValuation { token_key: "test_B".to_string(), symbol: "B".to_string(), valuation: 0.5 },
+        ];
+        let mut payments = vec![payment("A", 1.234), payment("B", 2.0)];
+        assert_eq!(reconcile_on_chain_amounts(&mut payments, &balances, &valuations), vec![123, 208]);
+    }
+
     #[test]
     fn test_insufficient_individual_token() {
         let balances = vec![
@@ -383,7 +708,7 @@ mod tests {
         let total_price = 100.0;
 
         // Should fail on individual token check, not total value
-        let result = calculate_payment_bundle(&balances, &vendor_valuations, total_price);
+        let result = calculate_payment_bundle(&balances, &vendor_valuations, &HashMap::new(), 0.0, total_price);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Insufficient BTC"));
     }
@@ -401,8 +726,8 @@ mod tests {
 
         let payment_amount = 1000.0;
         
-        let initial_payments = calculate_payment_bundle(&balances, &vec![], payment_amount).unwrap();
-        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, payment_amount);
+        let initial_payments = calculate_payment_bundle(&balances, &vec![], &HashMap::new(), 0.0, payment_amount).unwrap();
+        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, &HashMap::new(), payment_amount);
         
         let mut final_payments = initial_payments.clone();
         apply_discounts_to_payment(&mut final_payments, &consumptions, &balances).unwrap();
@@ -434,8 +759,8 @@ mod tests {
 
         let payment_amount = 120.0; // Close to wallet value
 
-        let initial_payments = calculate_payment_bundle(&balances, &vec![], payment_amount).unwrap();
-        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, payment_amount);
+        let initial_payments = calculate_payment_bundle(&balances, &vec![], &HashMap::new(), 0.0, payment_amount).unwrap();
+        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, &HashMap::new(), payment_amount);
         
         let mut final_payments = initial_payments.clone();
         apply_discounts_to_payment(&mut final_payments, &consumptions, &balances).unwrap();
@@ -459,8 +784,8 @@ mod tests {
         let payment_amount = 100.0;
 
         // Calculate everything
-        let initial_payments = calculate_payment_bundle(&balances, &vec![], payment_amount).unwrap();
-        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, payment_amount);
+        let initial_payments = calculate_payment_bundle(&balances, &vec![], &HashMap::new(), 0.0, payment_amount).unwrap();
+        let (_valuations, consumptions) = calculate_vendor_valuations(&preferences, &balances, &HashMap::new(), payment_amount);
         
         let mut final_payments = initial_payments.clone();
         apply_discounts_to_payment(&mut final_payments, &consumptions, &balances).unwrap();
@@ -471,5 +796,75 @@ mod tests {
         let actual_cost = result.unwrap();
         assert!((actual_cost - 80.0).abs() < 1.0); // Should be around $80
     }
-}
 
+    #[test]
+    fn test_spread_discount() {
+        let balances = vec![
+            create_test_balance("BTC", 1.0, 50000.0),
+            create_test_balance("ETH", 10.0, 3000.0),
+        ];
+        let payments = vec![
+            TokenPayment {
+                token_key: "test_BTC".to_string(),
+                symbol: "BTC".to_string(),
+                amount_to_pay: 0.01, // $500
+                token_image_url: None,
+            },
+            TokenPayment {
+                token_key: "test_ETH".to_string(),
+                symbol: "ETH".to_string(),
+                amount_to_pay: 0.1, // $300
+                token_image_url: None,
+            },
+        ];
+
+        let consumptions = spread_discount(&payments, &balances, 8.0);
+        let btc = consumptions.iter().find(|c| c.symbol == "BTC").unwrap();
+        let eth = consumptions.iter().find(|c| c.symbol == "ETH").unwrap();
+        assert!((btc.amount_used - 5.0).abs() < 0.0001);
+        assert!((eth.amount_used - 3.0).abs() < 0.0001);
+
+        // Never more than the payment is worth
+        let total: f64 = spread_discount(&payments, &balances, 10_000.0).iter().map(|c| c.amount_used).sum();
+        assert!((total - 800.0).abs() < 0.0001);
+
+        assert!(spread_discount(&payments, &balances, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_check_valuation_override() {
+        assert!(check_valuation_override(1.5, 1.0).is_ok());
+        assert!(check_valuation_override(0.5, 1.0).is_ok());
+        assert!(check_valuation_override(2.5, 1.0).is_err());
+        assert!(check_valuation_override(0.0, 1.0).is_err());
+        assert!(check_valuation_override(f64::NAN, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_apply_valuation_overrides() {
+        let balances = vec![
+            create_test_balance("BTC", 1.0, 50000.0),
+            create_test_balance("ETH", 10.0, 3000.0),
+        ];
+        let mut preferences = Document::new();
+        preferences.insert("BTC", 100.0);
+        preferences.insert("ETH", 50.0);
+        let (mut valuations, mut consumptions) = calculate_vendor_valuations(&preferences, &balances, &HashMap::new(), 1000.0);
+
+        // ETH valued at 1.25x market: its $375 share costs 20% less
+        let overrides = vec![TokenValuation { token_key: "other".to_string(), symbol: "ETH".to_string(), valuation: 3750.0 }];
+        let adjustments = apply_valuation_overrides(&mut valuations, &mut consumptions, &overrides, &balances, &HashMap::new(), 1000.0);
+
+        assert_eq!(adjustments.len(), 1);
+        assert!((adjustments[0].amount_used - 75.0).abs() < 0.0001);
+        assert_eq!(valuations.iter().find(|v| v.symbol == "ETH").unwrap().valuation, 3750.0);
+        // The override replaces the ETH budget discount; BTC keeps its own
+        assert_eq!(consumptions.iter().find(|c| c.symbol == "ETH").unwrap().amount_used, 0.0);
+        assert!((consumptions.iter().find(|c| c.symbol == "BTC").unwrap().amount_used - 100.0).abs() < 0.01);
+
+        // Below market is a premium
+        let overrides = vec![TokenValuation { token_key: "test_BTC".to_string(), symbol: "BTC".to_string(), valuation: 40000.0 }];
+        let adjustments = apply_valuation_overrides(&mut valuations, &mut consumptions, &overrides, &balances, &HashMap::new(), 1000.0);
+        assert!((adjustments[0].amount_used + 156.25).abs() < 0.0001);
+    }
+}
@@ -13,6 +13,71 @@ pub fn normalize_payment_code(input: &str) -> String {
         .collect()
 }
 
+/// Crockford Base32 alphabet, without I, L, O and U
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Payments of vendors with their own code namespace are addressed by short code, so their
+/// global ID is this long, too long to guess
+pub const SCOPED_PAYMENT_ID_LENGTH: usize = 16;
+
+/// A payment code as a customer enters it: the global code, or `{vendor_slug}-{short_code}`
+/// for vendors with their own namespace
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentCodeRef {
+    Global(String),
+    Scoped { vendor_slug: String, short_code: String },
+}
+
+/// Tell the two schemes apart. Global codes never contain a hyphen and slugs can, so a
+/// scoped code splits at its last one.
+pub fn parse_payment_code(input: &str) -> PaymentCodeRef {
+    let input = input.trim();
+    match input.rsplit_once('-') {
+        Some((vendor_slug, short_code)) if !vendor_slug.is_empty() && !short_code.is_empty() => PaymentCodeRef::Scoped {
+            vendor_slug: vendor_slug.to_lowercase(),
+            short_code: normalize_payment_code(short_code),
+        },
+        _ => PaymentCodeRef::Global(normalize_payment_code(input)),
+    }
+}
+
+/// The code a customer enters for a payment in a vendor's namespace
+pub fn scoped_payment_code(vendor_slug: &str, short_code: &str) -> String {
+    format!("{}-{}", vendor_slug, short_code)
+}
+
+/// Random Crockford code of `length` characters
+pub fn generate_code(length: usize) -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..length)
+        .map(|_| CROCKFORD[rng.gen_range(0..CROCKFORD.len())] as char)
+        .collect()
+}
+
+/// Random 16-character Crockford code (80 bits) for gift vouchers, grouped as XXXX-XXXX-XXXX-XXXX.
+/// Long enough that codes can't be guessed, since holding one is enough to redeem it.
+pub fn generate_voucher_code() -> String {
+    let random_bytes: [u8; 10] = rand::random();
+    let code = base32::encode(base32::Alphabet::Crockford, &random_bytes);
+    group_voucher_code(&code)
+}
+
+/// Accept codes typed with or without dashes, spaces or lookalike letters
+pub fn normalize_voucher_code(input: &str) -> String {
+    let code: String = input.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    group_voucher_code(&normalize_payment_code(&code))
+}
+
+fn group_voucher_code(code: &str) -> String {
+    code.chars()
+        .collect::<Vec<_>>()
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -24,4 +89,43 @@ mod tests {
         assert_eq!(normalize_payment_code("O0I1L"), "00111");
         assert_eq!(normalize_payment_code("valid"), "VA11D");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_payment_code() {
+        assert_eq!(parse_payment_code("abc0o"), PaymentCodeRef::Global("ABC00".to_string()));
+        assert_eq!(parse_payment_code(" 7K2MX "), PaymentCodeRef::Global("7K2MX".to_string()));
+        assert_eq!(
+            parse_payment_code("Joes-Cafe-7k2o"),
+            PaymentCodeRef::Scoped { vendor_slug: "joes-cafe".to_string(), short_code: "7K20".to_string() },
+        );
+        assert_eq!(parse_payment_code(&scoped_payment_code("bakery", "A1B2C3")), PaymentCodeRef::Scoped {
+            vendor_slug: "bakery".to_string(),
+            short_code: "A1B2C3".to_string(),
+        });
+        // A stray hyphen at either end isn't a scoped code
+        assert_eq!(parse_payment_code("-ABCDE"), PaymentCodeRef::Global("-ABCDE".to_string()));
+        assert_eq!(parse_payment_code("bakery-"), PaymentCodeRef::Global("BAKERY-".to_string()));
+    }
+
+    #[test]
+    fn test_generate_code() {
+        let code = generate_code(SCOPED_PAYMENT_ID_LENGTH);
+        assert_eq!(code.len(), SCOPED_PAYMENT_ID_LENGTH);
+        assert!(code.bytes().all(|c| CROCKFORD.contains(&c)));
+        assert_eq!(normalize_payment_code(&code), code);
+        assert_eq!(generate_code(6).len(), 6);
+        assert_ne!(generate_code(SCOPED_PAYMENT_ID_LENGTH), code);
+    }
+
+    #[test]
+    fn test_voucher_codes() {
+        let code = generate_voucher_code();
+        assert_eq!(code.len(), 19);
+        assert_eq!(code.matches('-').count(), 3);
+        assert_eq!(normalize_voucher_code(&code), code);
+        assert_ne!(generate_voucher_code(), code);
+
+        assert_eq!(normalize_voucher_code("abcd efgh-1jk0 mnpq"), "ABCD-EFGH-1JK0-MNPQ");
+        assert_eq!(normalize_voucher_code("ABCDoIL2"), "ABCD-0112");
+    }
+}
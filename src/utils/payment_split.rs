@@ -0,0 +1,118 @@
+use crate::models::{PaymentSplit, SplitType, SplitLeg, TokenPayment};
+use crate::utils::payment_calculator::{to_on_chain_units, ON_CHAIN_UNITS_PER_TOKEN};
+
+/// The fraction of a payment each split takes, in order. Fixed shares are of `price_usd`,
+/// the price before any promo, so recipients bear a promo in proportion like the vendor.
+pub fn split_fractions(price_usd: f64, splits: &[PaymentSplit]) -> Result<Vec<f64>, String> {
+    let fractions: Vec<f64> = splits.iter()
+        .map(|split| match split.split_type {
+            SplitType::Percentage => split.value / 100.0,
+            SplitType::FixedUsd => split.value / price_usd,
+        })
+        .collect();
+    // A hair over for float error in shares that add up to the whole price
+    if fractions.iter().sum::<f64>() > 1.0 + 1e-9 {
+        return Err("Splits add up to more than the price".to_string());
+    }
+    Ok(fractions)
+}
+
+/// Divide a final bundle between the split recipients and the vendor in whole on-chain
+/// units. Each recipient gets their fraction of every token rounded down and the vendor
+/// the rest, so the legs add up to the bundle exactly. Recipients left with nothing, the
+/// vendor included, get no leg.
+pub fn split_bundle(payment_bundle: &[TokenPayment], vendor_address: &str, splits: &[PaymentSplit], fractions: &[f64]) -> Vec<SplitLeg> {
+    let mut legs: Vec<SplitLeg> = splits.iter()
+        .map(|split| SplitLeg { recipient_address: split.recipient_address.clone(), payment_bundle: Vec::new() })
+        .chain(std::iter::once(SplitLeg { recipient_address: vendor_address.to_string(), payment_bundle: Vec::new() }))
+        .collect();
+
+    for token_payment in payment_bundle {
+        let total = to_on_chain_units(token_payment.amount_to_pay);
+        let mut shares: Vec<u64> = fractions.iter()
+            .map(|fraction| ((total as f64 * fraction).floor() as u64).min(total))
+            .collect();
+        let split_total: u64 = shares.iter().sum();
+        shares.push(total.saturating_sub(split_total));
+
+        for (leg, units) in legs.iter_mut().zip(shares) {
+            if units > 0 {
+                leg.payment_bundle.push(TokenPayment {
+                    amount_to_pay: units as f64 / ON_CHAIN_UNITS_PER_TOKEN,
+                    ..token_payment.clone()
+                });
+            }
+        }
+    }
+
+    legs.retain(|leg| !leg.payment_bundle.is_empty());
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(recipient: &str, split_type: SplitType, value: f64) -> PaymentSplit {
+        PaymentSplit { recipient_address: recipient.to_string(), split_type, value }
+    }
+
+    fn token(symbol: &str, amount_to_pay: f64) -> TokenPayment {
+        TokenPayment { token_key: format!("{},1", symbol), symbol: symbol.to_string(), amount_to_pay, token_image_url: None }
+    }
+
+    fn units(leg: &SplitLeg, symbol: &str) -> u64 {
+        leg.payment_bundle.iter()
+            .find(|payment| payment.symbol == symbol)
+            .map_or(0, |payment| to_on_chain_units(payment.amount_to_pay))
+    }
+
+    #[test]
+    fn test_split_fractions() {
+        let splits = [split("a", SplitType::Percentage, 10.0), split("b", SplitType::FixedUsd, 5.0)];
+        assert_eq!(split_fractions(20.0, &splits).unwrap(), vec![0.1, 0.25]);
+
+        let whole = [split("a", SplitType::Percentage, 70.0), split("b", SplitType::FixedUsd, 3.0)];
+        assert!(split_fractions(10.0, &whole).is_ok());
+
+        let over = [split("a", SplitType::Percentage, 50.0), split("b", SplitType::FixedUsd, 6.0)];
+        assert!(split_fractions(10.0, &over).is_err());
+    }
+
+    #[test]
+    fn test_split_bundle_adds_up() {
+        let splits = [split("a", SplitType::Percentage, 10.0), split("b", SplitType::Percentage, 33.0)];
+        let fractions = split_fractions(10.0, &splits).unwrap();
+        let bundle = [token("USD", 7.01), token("GAY", 2.99)];
+        let legs = split_bundle(&bundle, "vendor", &splits, &fractions);
+
+        assert_eq!(legs.len(), 3);
+        assert_eq!(legs[0].recipient_address, "a");
+        assert_eq!(legs[2].recipient_address, "vendor");
+        assert_eq!(units(&legs[0], "USD"), 70);
+        assert_eq!(units(&legs[1], "USD"), 231);
+        assert_eq!(units(&legs[2], "USD"), 400);
+        for symbol in ["USD", "GAY"] {
+            let total: u64 = legs.iter().map(|leg| units(leg, symbol)).sum();
+            let expected = to_on_chain_units(bundle.iter().find(|t| t.symbol == symbol).unwrap().amount_to_pay);
+            assert_eq!(total, expected);
+        }
+    }
+
+    #[test]
+    fn test_split_bundle_skips_empty_legs() {
+        // The whole price is split away, so the vendor has no leg
+        let splits = [split("a", SplitType::FixedUsd, 10.0)];
+        let fractions = split_fractions(10.0, &splits).unwrap();
+        let legs = split_bundle(&[token("USD", 10.0)], "vendor", &splits, &fractions);
+        assert_eq!(legs.len(), 1);
+        assert_eq!(units(&legs[0], "USD"), 1000);
+
+        // A share too small for a unit of a token gets none of it
+        let splits = [split("a", SplitType::Percentage, 1.0)];
+        let fractions = split_fractions(1.0, &splits).unwrap();
+        let legs = split_bundle(&[token("USD", 0.5)], "vendor", &splits, &fractions);
+        assert_eq!(legs.len(), 1);
+        assert_eq!(legs[0].recipient_address, "vendor");
+    }
+}
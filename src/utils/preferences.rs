@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use mongodb::bson::{Bson, Document};
+
+/// Largest discount budget or premium, in USD, a vendor can set for one token
+pub const MAX_PREFERENCE_BUDGET_USD: f64 = 100_000.0;
+
+/// A full preference map names known tokens only, each with a finite budget within bounds
+pub fn validate_preferences(preferences: &BTreeMap<String, f64>, known_symbols: &HashSet<String>) -> Result<(), String> {
+    for (symbol, budget) in preferences {
+        if !known_symbols.contains(symbol) {
+            return Err(format!("Unknown token {}", symbol));
+        }
+        if !budget.is_finite() || budget.abs() > MAX_PREFERENCE_BUDGET_USD {
+            return Err(format!("{} must be between -{} and {}", symbol, MAX_PREFERENCE_BUDGET_USD, MAX_PREFERENCE_BUDGET_USD));
+        }
+    }
+    Ok(())
+}
+
+/// Heaviest spending weight a payer can give a token
+pub const MAX_SPENDING_WEIGHT: f64 = 10.0;
+
+/// Spending weights name known tokens only, each between 0 and `MAX_SPENDING_WEIGHT`
+pub fn validate_spending_weights(weights: &HashMap<String, f64>, known_symbols: &HashSet<String>) -> Result<(), String> {
+    for (symbol, weight) in weights {
+        if !known_symbols.contains(symbol) {
+            return Err(format!("Unknown token {}", symbol));
+        }
+        if !weight.is_finite() || !(0.0..=MAX_SPENDING_WEIGHT).contains(weight) {
+            return Err(format!("{} weight must be between 0 and {}", symbol, MAX_SPENDING_WEIGHT));
+        }
+    }
+    Ok(())
+}
+
+/// Template names are trimmed and between 1 and 50 characters
+pub fn validate_template_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 50 {
+        return Err("Template name must be between 1 and 50 characters".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// A stored preference as a USD budget, if it is one. Older preferences may hold integers.
+pub fn budget_value(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Double(v) => Some(*v),
+        Bson::Int32(v) => Some(*v as f64),
+        Bson::Int64(v) => Some(*v as f64),
+        _ => None,
+    }
+}
+
+/// Budgets that differ between two preference documents, as (symbol, before, after),
+/// treating a missing budget as zero. Stored `<symbol>_valuation` entries aren't budgets.
+pub fn budget_changes(before: &Document, after: &Document) -> Vec<(String, f64, f64)> {
+    let symbols: BTreeSet<&String> = before.keys().chain(after.keys())
+        .filter(|key| !key.ends_with("_valuation"))
+        .collect();
+    symbols.into_iter()
+        .filter_map(|symbol| {
+            let old = before.get(symbol).map_or(Some(0.0), budget_value)?;
+            let new = after.get(symbol).map_or(Some(0.0), budget_value)?;
+            (old != new).then(|| (symbol.clone(), old, new))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols() -> HashSet<String> {
+        ["USD", "MEME"].iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_preferences() {
+        let mut preferences = BTreeMap::from([("USD".to_string(), 50.0), ("MEME".to_string(), -10.0)]);
+        assert!(validate_preferences(&preferences, &symbols()).is_ok());
+        assert!(validate_preferences(&BTreeMap::new(), &symbols()).is_ok());
+
+        preferences.insert("DOGE".to_string(), 1.0);
+        assert!(validate_preferences(&preferences, &symbols()).is_err());
+        preferences.remove("DOGE");
+
+        preferences.insert("USD".to_string(), f64::NAN);
+        assert!(validate_preferences(&preferences, &symbols()).is_err());
+        preferences.insert("USD".to_string(), -MAX_PREFERENCE_BUDGET_USD - 1.0);
+        assert!(validate_preferences(&preferences, &symbols()).is_err());
+    }
+
+    #[test]
+    fn test_validate_spending_weights() {
+        let mut weights = HashMap::from([("MEME".to_string(), 3.0), ("USD".to_string(), 0.0)]);
+        assert!(validate_spending_weights(&weights, &symbols()).is_ok());
+
+        weights.insert("USD".to_string(), -1.0);
+        assert!(validate_spending_weights(&weights, &symbols()).is_err());
+        weights.insert("USD".to_string(), MAX_SPENDING_WEIGHT + 1.0);
+        assert!(validate_spending_weights(&weights, &symbols()).is_err());
+        weights.insert("USD".to_string(), 1.0);
+        weights.insert("DOGE".to_string(), 1.0);
+        assert!(validate_spending_weights(&weights, &symbols()).is_err());
+    }
+
+    #[test]
+    fn test_budget_changes() {
+        use mongodb::bson::doc;
+        let before = doc! { "USD": 50.0, "MEME": 10, "OLD": 5.0, "MEME_valuation": 2.0 };
+        let after = doc! { "USD": 50.0, "MEME": 20.0, "NEW": -3.0 };
+        assert_eq!(budget_changes(&before, &after), vec![
+            ("MEME".to_string(), 10.0, 20.0),
+            ("NEW".to_string(), 0.0, -3.0),
+            ("OLD".to_string(), 5.0, 0.0),
+        ]);
+        assert!(budget_changes(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_validate_template_name() {
+        assert_eq!(validate_template_name("  Local causes 5%  ").unwrap(), "Local causes 5%");
+        assert!(validate_template_name("   ").is_err());
+        assert!(validate_template_name(&"x".repeat(51)).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+/// Usernames are 3-30 characters of letters, digits, `_`, `.` and `-`, starting with a
+/// letter or digit. Returns the trimmed username.
+pub fn validate_username(username: &str) -> Result<String, String> {
+    let username = username.trim();
+    let length = username.chars().count();
+    if !(3..=30).contains(&length) {
+        return Err("Username must be between 3 and 30 characters".to_string());
+    }
+    if !username.chars().next().map_or(false, |c| c.is_ascii_alphanumeric()) {
+        return Err("Username must start with a letter or digit".to_string());
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+        return Err("Username may only contain letters, digits, '_', '.' and '-'".to_string());
+    }
+    Ok(username.to_string())
+}
+
+/// Case-insensitive form used for uniqueness, so "Alice" and "alice" can't both exist
+pub fn username_key(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// Optional profile text: trimmed, empty clears it
+pub fn validate_display_name(display_name: &str) -> Result<Option<String>, String> {
+    let display_name = display_name.trim();
+    if display_name.chars().count() > 50 {
+        return Err("Display name must be at most 50 characters".to_string());
+    }
+    Ok(Some(display_name.to_string()).filter(|n| !n.is_empty()))
+}
+
+pub fn validate_avatar_url(avatar_url: &str) -> Result<Option<String>, String> {
+    let avatar_url = avatar_url.trim();
+    if avatar_url.is_empty() {
+        return Ok(None);
+    }
+    if avatar_url.len() > 2048 || !avatar_url.starts_with("https://") || avatar_url.contains(char::is_whitespace) {
+        return Err("Avatar URL must be an https:// URL".to_string());
+    }
+    Ok(Some(avatar_url.to_string()))
+}
+
+/// Only a shape check; the address isn't verified
+pub fn validate_email(email: &str) -> Result<Option<String>, String> {
+    let email = email.trim();
+    if email.is_empty() {
+        return Ok(None);
+    }
+    let valid = email.len() <= 254
+        && !email.contains(char::is_whitespace)
+        && email.split_once('@').map_or(false, |(local, domain)| {
+            !local.is_empty() && !domain.contains('@') && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+        });
+    if !valid {
+        return Err("Invalid email address".to_string());
+    }
+    Ok(Some(email.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_username() {
+        assert_eq!(validate_username("  alice_01 ").unwrap(), "alice_01");
+        assert!(validate_username("al").is_err());
+        assert!(validate_username(&"a".repeat(31)).is_err());
+        assert!(validate_username("_alice").is_err());
+        assert!(validate_username("alice smith").is_err());
+        assert!(validate_username("álice").is_err());
+        assert_eq!(username_key(" Alice.B "), "alice.b");
+    }
+
+    #[test]
+    fn test_optional_fields_clear_when_empty() {
+        assert_eq!(validate_display_name("  ").unwrap(), None);
+        assert_eq!(validate_display_name(" Alice B ").unwrap(), Some("Alice B".to_string()));
+        assert!(validate_display_name(&"x".repeat(51)).is_err());
+        assert_eq!(validate_avatar_url("").unwrap(), None);
+        assert_eq!(validate_email("").unwrap(), None);
+    }
+
+    #[test]
+    fn test_validate_avatar_url() {
+        assert!(validate_avatar_url("https://cdn.example.com/a.png").unwrap().is_some());
+        assert!(validate_avatar_url("http://cdn.example.com/a.png").is_err());
+        assert!(validate_avatar_url("javascript:alert(1)").is_err());
+    }
+
+    #[test]
+    fn test_validate_email() {
+        assert_eq!(validate_email(" Alice@Example.org ").unwrap(), Some("alice@example.org".to_string()));
+        assert!(validate_email("alice").is_err());
+        assert!(validate_email("@example.org").is_err());
+        assert!(validate_email("alice@example").is_err());
+        assert!(validate_email("alice@@example.org").is_err());
+        assert!(validate_email("alice @example.org").is_err());
+    }
+}
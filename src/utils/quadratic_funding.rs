@@ -0,0 +1,59 @@
+/// Quadratic funding weight of a cause: (sum of sqrt(contribution))^2 minus what was
+/// contributed, so many small contributors outweigh one large one
+pub fn qf_score(contributions: &[i64]) -> f64 {
+    let sqrt_sum: f64 = contributions.iter().filter(|c| **c > 0).map(|c| (*c as f64).sqrt()).sum();
+    let total: i64 = contributions.iter().filter(|c| **c > 0).sum();
+    (sqrt_sum * sqrt_sum - total as f64).max(0.0)
+}
+
+/// Split `total` cents in proportion to `weights`, handing leftover cents to the largest
+/// remainders so the parts always add up to `total`. All zero weights yield all zeros.
+pub fn split_proportionally(total: i64, weights: &[f64]) -> Vec<i64> {
+    let weight_sum: f64 = weights.iter().filter(|w| **w > 0.0).sum();
+    if total <= 0 || weight_sum <= 0.0 {
+        return vec![0; weights.len()];
+    }
+
+    let exact: Vec<f64> = weights
+        .iter()
+        .map(|w| if *w > 0.0 { total as f64 * w / weight_sum } else { 0.0 })
+        .collect();
+    let mut parts: Vec<i64> = exact.iter().map(|x| x.floor() as i64).collect();
+
+    let mut leftover = total - parts.iter().sum::<i64>();
+    let mut order: Vec<usize> = (0..weights.len()).filter(|i| weights[*i] > 0.0).collect();
+    order.sort_by(|a, b| {
+        let ra = exact[*a] - parts[*a] as f64;
+        let rb = exact[*b] - parts[*b] as f64;
+        rb.partial_cmp(&ra).unwrap_or(std::cmp::Ordering::Equal).then(a.cmp(b))
+    });
+    for i in order {
+        if leftover <= 0 {
+            break;
+        }
+        parts[i] += 1;
+        leftover -= 1;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qf_score_favours_many_contributors() {
+        assert_eq!(qf_score(&[100]), 0.0);
+        assert_eq!(qf_score(&[25, 25, 25, 25]), 300.0);
+        assert_eq!(qf_score(&[100, 100]), 200.0);
+        assert_eq!(qf_score(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_split_proportionally_sums_to_total() {
+        let scores = [qf_score(&[100, 100]), qf_score(&[25, 25, 25, 25]), qf_score(&[500])];
+        assert_eq!(split_proportionally(1000, &scores), vec![400, 600, 0]);
+        assert_eq!(split_proportionally(10, &[1.0, 1.0, 1.0]), vec![4, 3, 3]);
+        assert_eq!(split_proportionally(10, &[0.0, 0.0]), vec![0, 0]);
+    }
+}
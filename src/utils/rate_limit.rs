@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+/// Keys tracked before windows that have ended are swept
+const SWEEP_AT: usize = 10_000;
+
+/// Fixed-window request counts per key, for one process. The Redis-backed limiter counts
+/// the same windows, so limits behave alike with one instance or several.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    windows: HashMap<String, (i64, u32)>,  // key -> (window end, requests in it)
+}
+
+/// Start of the `window_secs` window `now` falls in
+pub fn window_start(now: i64, window_secs: i64) -> i64 {
+    now - now.rem_euclid(window_secs.max(1))
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request for `key` at `now`. False once there have been more than `limit`
+    /// in the current `window_secs` window.
+    pub fn hit(&mut self, key: &str, limit: u32, window_secs: i64, now: i64) -> bool {
+        if self.windows.len() >= SWEEP_AT {
+            self.windows.retain(|_, (ends_at, _)| *ends_at > now);
+        }
+        let ends_at = window_start(now, window_secs) + window_secs.max(1);
+        let (window_end, count) = self.windows.entry(key.to_string()).or_insert((ends_at, 0));
+        if *window_end != ends_at {
+            (*window_end, *count) = (ends_at, 0);
+        }
+        *count += 1;
+        *count <= limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new();
+        assert!(limiter.hit("a", 2, 60, 120));
+        assert!(limiter.hit("a", 2, 60, 150));
+        assert!(!limiter.hit("a", 2, 60, 179));
+        // Other keys count separately
+        assert!(limiter.hit("b", 2, 60, 179));
+        // A new window starts over
+        assert!(limiter.hit("a", 2, 60, 180));
+    }
+
+    #[test]
+    fn test_window_start() {
+        assert_eq!(window_start(179, 60), 120);
+        assert_eq!(window_start(180, 60), 180);
+        assert_eq!(window_start(5, 0), 5);
+    }
+}
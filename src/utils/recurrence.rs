@@ -0,0 +1,63 @@
+use chrono::{DateTime, Duration, Months};
+use crate::models::ScheduleFrequency;
+
+/// Unix time of the `n`th run (0 is the anchor itself) of a schedule, or None when a
+/// one-off schedule has no further runs
+pub fn nth_occurrence(frequency: ScheduleFrequency, anchor: i64, n: u32) -> Option<i64> {
+    match frequency {
+        ScheduleFrequency::Once => (n == 0).then_some(anchor),
+        ScheduleFrequency::Weekly => Some(anchor + Duration::weeks(n as i64).num_seconds()),
+        ScheduleFrequency::Biweekly => Some(anchor + Duration::weeks(2 * n as i64).num_seconds()),
+        ScheduleFrequency::Monthly => DateTime::from_timestamp(anchor, 0)?
+            .checked_add_months(Months::new(n))
+            .map(|at| at.timestamp()),
+    }
+}
+
+/// The first run after `after`, counting on from run `from`, or None when there isn't one
+/// before `ends_at`. Returns the run's number and time.
+pub fn next_occurrence(frequency: ScheduleFrequency, anchor: i64, from: u32, after: i64, ends_at: Option<i64>) -> Option<(u32, i64)> {
+    let mut n = from;
+    loop {
+        let at = nth_occurrence(frequency, anchor, n)?;
+        if ends_at.map_or(false, |ends_at| at > ends_at) {
+            return None;
+        }
+        if at > after {
+            return Some((n, at));
+        }
+        n = n.checked_add(1)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-31T09:00:00Z
+    const JAN_31: i64 = 1706691600;
+
+    #[test]
+    fn test_nth_occurrence() {
+        assert_eq!(nth_occurrence(ScheduleFrequency::Once, JAN_31, 0), Some(JAN_31));
+        assert_eq!(nth_occurrence(ScheduleFrequency::Once, JAN_31, 1), None);
+        assert_eq!(nth_occurrence(ScheduleFrequency::Weekly, JAN_31, 2), Some(JAN_31 + 14 * 86400));
+        assert_eq!(nth_occurrence(ScheduleFrequency::Biweekly, JAN_31, 2), Some(JAN_31 + 28 * 86400));
+    }
+
+    #[test]
+    fn test_monthly_occurrences_keep_the_anchor_day() {
+        // Feb 29 (leap year), then back to Mar 31 rather than drifting to the 29th
+        assert_eq!(nth_occurrence(ScheduleFrequency::Monthly, JAN_31, 1), Some(JAN_31 + 29 * 86400));
+        assert_eq!(nth_occurrence(ScheduleFrequency::Monthly, JAN_31, 2), Some(JAN_31 + 60 * 86400));
+    }
+
+    #[test]
+    fn test_next_occurrence_skips_missed_runs() {
+        let now = JAN_31 + 20 * 86400;
+        assert_eq!(next_occurrence(ScheduleFrequency::Weekly, JAN_31, 1, now, None), Some((3, JAN_31 + 21 * 86400)));
+        assert_eq!(next_occurrence(ScheduleFrequency::Weekly, JAN_31, 1, now, Some(now)), None);
+        assert_eq!(next_occurrence(ScheduleFrequency::Once, JAN_31, 1, now, None), None);
+        assert_eq!(next_occurrence(ScheduleFrequency::Once, JAN_31, 0, JAN_31 - 1, None), Some((0, JAN_31)));
+    }
+}
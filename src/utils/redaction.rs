@@ -0,0 +1,118 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn masking of wallet addresses and emails in logs on or off (LOG_REDACTION)
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Keep the first and last four characters of an address, enough to tell wallets apart in
+/// logs without identifying them
+pub fn mask_address(address: &str) -> String {
+    let chars: Vec<char> = address.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("{}…{}", chars[..4].iter().collect::<String>(), chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+/// Keep the first character of the local part and the domain
+pub fn mask_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => format!("{}***@{}", local.chars().next().unwrap_or('*'), domain),
+        None => "****".to_string(),
+    }
+}
+
+/// Base58 Ed25519 public keys are 32 to 44 characters; older clients send 64 hex characters
+fn is_address(token: &str) -> bool {
+    let base58 = (32..=44).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l'));
+    let hex = token.len() == 64 && token.chars().all(|c| c.is_ascii_hexdigit());
+    base58 || hex
+}
+
+fn is_email(token: &str) -> bool {
+    token.split_once('@').map_or(false, |(local, domain)| !local.is_empty() && domain.contains('.'))
+}
+
+/// Mask every wallet address and email in free text, e.g. a request path and query
+pub fn redact_text(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut token = String::new();
+    let flush = |token: &mut String, redacted: &mut String| {
+        if is_address(token) {
+            redacted.push_str(&mask_address(token));
+        } else if is_email(token) {
+            redacted.push_str(&mask_email(token));
+        } else {
+            redacted.push_str(token);
+        }
+        token.clear();
+    };
+    for c in text.chars() {
+        if matches!(c, '/' | '?' | '&' | '=' | ',' | ' ' | ':' | '"' | '\'' | '(' | ')' | '[' | ']' | '{' | '}') {
+            flush(&mut token, &mut redacted);
+            redacted.push(c);
+        } else {
+            token.push(c);
+        }
+    }
+    flush(&mut token, &mut redacted);
+    redacted
+}
+
+/// Shows a value in a log line, masked while redaction is on
+pub struct Redacted<'a>(&'a str);
+
+pub fn redact(value: &str) -> Redacted<'_> {
+    Redacted(value)
+}
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if is_enabled() {
+            write!(f, "{}", redact_text(self.0))
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+
+    #[test]
+    fn test_mask_address_and_email() {
+        assert_eq!(mask_address(ADDRESS), "7xKX…gAsU");
+        assert_eq!(mask_address("short"), "****");
+        assert_eq!(mask_email("alice@example.org"), "a***@example.org");
+        assert_eq!(mask_email("not an email"), "****");
+    }
+
+    #[test]
+    fn test_redact_text_masks_addresses_and_emails() {
+        assert_eq!(
+            redact_text(&format!("/v1/api/users/{}/transactions?token=MEME", ADDRESS)),
+            "/v1/api/users/7xKX…gAsU/transactions?token=MEME"
+        );
+        assert_eq!(redact_text("email=alice@example.org&x=1"), "email=a***@example.org&x=1");
+        assert_eq!(redact_text(&"ab".repeat(32)), "abab…abab");
+    }
+
+    #[test]
+    fn test_redact_text_leaves_other_values() {
+        assert_eq!(redact_text("/v1/api/payments/ABC12/status"), "/v1/api/payments/ABC12/status");
+        assert_eq!(redact_text("/v1/causes/65f1c2a9e4b0a1b2c3d4e5f6"), "/v1/causes/65f1c2a9e4b0a1b2c3d4e5f6");
+        assert_eq!(redact_text(""), "");
+    }
+}
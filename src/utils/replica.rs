@@ -0,0 +1,8 @@
+use uuid::Uuid;
+
+/// Names this replica in leases and job claims: its HOSTNAME plus a random suffix, so a
+/// restarted container never takes over the claims of the process it replaced
+pub fn replica_instance_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "replica".to_string());
+    format!("{}-{}", host, &Uuid::new_v4().simple().to_string()[..8])
+}
@@ -0,0 +1,47 @@
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+/// Parse a report date ("YYYY-MM-DD", UTC), defaulting to `today`.
+/// Dates in the future are rejected since there is nothing to report yet.
+pub fn parse_report_date(date: Option<&str>, today: NaiveDate) -> Result<NaiveDate, String> {
+    let date = match date {
+        Some(s) => NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", s))?,
+        None => today,
+    };
+    if date > today {
+        return Err(format!("Date {} is in the future", date));
+    }
+    Ok(date)
+}
+
+/// Unix-second bounds [start, end) of a UTC day
+pub fn day_bounds(date: NaiveDate) -> (i64, i64) {
+    let start = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).timestamp();
+    let end = start + Duration::days(1).num_seconds();
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_report_date() {
+        let today = date(2024, 3, 10);
+        assert_eq!(parse_report_date(None, today).unwrap(), today);
+        assert_eq!(parse_report_date(Some("2024-03-09"), today).unwrap(), date(2024, 3, 9));
+        assert!(parse_report_date(Some("2024-03-11"), today).is_err());
+        assert!(parse_report_date(Some("03/09/2024"), today).is_err());
+    }
+
+    #[test]
+    fn test_day_bounds() {
+        let (start, end) = day_bounds(date(2024, 1, 1));
+        assert_eq!(start, 1704067200);
+        assert_eq!(end - start, 86400);
+    }
+}
@@ -0,0 +1,133 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+/// Response header carrying the platform's signature of the response
+pub const RESPONSE_SIGNATURE_HEADER: &str = "X-Index-Signature";
+
+/// Bytes the platform signs for a response: "timestamp:METHOD:/path?query:" followed by the
+/// body, where the query string is included if there is one. The request line is included
+/// so a signed response can't be passed off as the answer to a different request.
+pub fn response_signing_message(timestamp: i64, method: &str, path: &str, body: &[u8]) -> Vec<u8> {
+    let mut message = format!("{}:{}:{}:", timestamp, method.to_uppercase(), path).into_bytes();
+    message.extend_from_slice(body);
+    message
+}
+
+/// Sign a response, giving the `X-Index-Signature` value: "t=timestamp,key=base58 public key,sig=hex signature"
+pub fn sign_response(signing_key: &SigningKey, timestamp: i64, method: &str, path: &str, body: &[u8]) -> String {
+    let signature = signing_key.sign(&response_signing_message(timestamp, method, path, body));
+    format!(
+        "t={},key={},sig={}",
+        timestamp,
+        bs58::encode(signing_key.verifying_key().to_bytes()).into_string(),
+        hex::encode(signature.to_bytes()),
+    )
+}
+
+/// The parts of an `X-Index-Signature` value
+#[derive(Debug, PartialEq)]
+pub struct ResponseSignature {
+    pub timestamp: i64,
+    pub key: String,
+    pub signature: String,
+}
+
+pub fn parse_response_signature(header: &str) -> Result<ResponseSignature, String> {
+    let (mut timestamp, mut key, mut signature) = (None, None, None);
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = Some(value.parse::<i64>().map_err(|_| "Invalid timestamp".to_string())?),
+            Some(("key", value)) => key = Some(value.to_string()),
+            Some(("sig", value)) => signature = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Ok(ResponseSignature {
+        timestamp: timestamp.ok_or("Missing t")?,
+        key: key.ok_or("Missing key")?,
+        signature: signature.ok_or("Missing sig")?,
+    })
+}
+
+/// Check a response's `X-Index-Signature` against a published base58 public key, as a
+/// partner would
+pub fn verify_response_signature(public_key: &str, header: &str, method: &str, path: &str, body: &[u8]) -> Result<(), String> {
+    let parsed = parse_response_signature(header)?;
+    if parsed.key != public_key {
+        return Err("Signed with a different key".to_string());
+    }
+    let key_bytes: [u8; 32] = bs58::decode(public_key)
+        .into_vec()
+        .map_err(|e| format!("Invalid public key: {}", e))?
+        .try_into()
+        .map_err(|_| "Public key must be 32 bytes".to_string())?;
+    let verifying_key = VerifyingKey::from_bytes(&key_bytes).map_err(|e| format!("Invalid public key: {}", e))?;
+    let signature_bytes: [u8; 64] = hex::decode(&parsed.signature)
+        .map_err(|e| format!("Invalid signature format: {}", e))?
+        .try_into()
+        .map_err(|_| "Signature must be 64 bytes".to_string())?;
+    verifying_key
+        .verify(&response_signing_message(parsed.timestamp, method, path, body), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "Invalid signature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> (SigningKey, String) {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let public_key = bs58::encode(signing_key.verifying_key().to_bytes()).into_string();
+        (signing_key, public_key)
+    }
+
+    #[test]
+    fn test_signing_message_format() {
+        let message = response_signing_message(1700000000, "get", "/v1/api/payments/ABC12/status", b"{\"ok\":true}");
+        assert_eq!(message, b"1700000000:GET:/v1/api/payments/ABC12/status:{\"ok\":true}".to_vec());
+    }
+
+    #[test]
+    fn test_signed_response_verifies() {
+        let (signing_key, public_key) = test_key();
+        let header = sign_response(&signing_key, 1700000000, "GET", "/v1/tokens", b"[]");
+
+        assert!(header.starts_with(&format!("t=1700000000,key={},sig=", public_key)));
+        assert!(verify_response_signature(&public_key, &header, "GET", "/v1/tokens", b"[]").is_ok());
+    }
+
+    #[test]
+    fn test_changed_body_or_path_rejected() {
+        let (signing_key, public_key) = test_key();
+        let header = sign_response(&signing_key, 1700000000, "GET", "/v1/tokens", b"[]");
+
+        assert_eq!(verify_response_signature(&public_key, &header, "GET", "/v1/tokens", b"[1]").unwrap_err(), "Invalid signature");
+        assert_eq!(verify_response_signature(&public_key, &header, "GET", "/v1/causes", b"[]").unwrap_err(), "Invalid signature");
+    }
+
+    #[test]
+    fn test_changed_query_rejected() {
+        let (signing_key, public_key) = test_key();
+        let header = sign_response(&signing_key, 1700000000, "GET", "/v1/tokens?symbol=USD", b"[]");
+
+        assert!(verify_response_signature(&public_key, &header, "GET", "/v1/tokens?symbol=USD", b"[]").is_ok());
+        assert_eq!(verify_response_signature(&public_key, &header, "GET", "/v1/tokens?symbol=EUR", b"[]").unwrap_err(), "Invalid signature");
+        assert_eq!(verify_response_signature(&public_key, &header, "GET", "/v1/tokens", b"[]").unwrap_err(), "Invalid signature");
+    }
+
+    #[test]
+    fn test_other_key_rejected() {
+        let (signing_key, _) = test_key();
+        let other = bs58::encode(SigningKey::from_bytes(&[8u8; 32]).verifying_key().to_bytes()).into_string();
+        let header = sign_response(&signing_key, 1700000000, "GET", "/v1/tokens", b"[]");
+
+        assert!(verify_response_signature(&other, &header, "GET", "/v1/tokens", b"[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_response_signature() {
+        let parsed = parse_response_signature("t=5, key=abc ,sig=00ff").unwrap();
+        assert_eq!(parsed, ResponseSignature { timestamp: 5, key: "abc".to_string(), signature: "00ff".to_string() });
+        assert!(parse_response_signature("key=abc,sig=00").is_err());
+        assert!(parse_response_signature("t=x,key=abc,sig=00").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+/// Seconds to wait before the next automatic retry after `attempts` failures:
+/// one minute, doubling each time, capped at one hour.
+pub fn backoff_secs(attempts: i32) -> i64 {
+    const BASE_SECS: i64 = 60;
+    const MAX_SECS: i64 = 60 * 60;
+
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    (BASE_SECS << exponent).min(MAX_SECS)
+}
+
+/// Delay before retry number `attempt + 1` of a failed request: `base` doubling per
+/// attempt up to `max`, scaled by `jitter` in [0, 1) ("full jitter") so clients that
+/// failed together don't retry together.
+pub fn jittered_backoff(attempt: u32, base: Duration, max: Duration, jitter: f64) -> Duration {
+    let ceiling = base.saturating_mul(1u32 << attempt.min(16)).min(max);
+    ceiling.mul_f64(jitter.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_then_caps() {
+        assert_eq!(backoff_secs(0), 60);
+        assert_eq!(backoff_secs(1), 60);
+        assert_eq!(backoff_secs(2), 120);
+        assert_eq!(backoff_secs(3), 240);
+        assert_eq!(backoff_secs(7), 3600);
+        assert_eq!(backoff_secs(i32::MAX), 3600);
+    }
+
+    #[test]
+    fn test_jittered_backoff_bounds() {
+        let base = Duration::from_millis(200);
+        let max = Duration::from_secs(2);
+        assert_eq!(jittered_backoff(0, base, max, 0.0), Duration::ZERO);
+        assert_eq!(jittered_backoff(0, base, max, 1.0), base);
+        assert_eq!(jittered_backoff(2, base, max, 1.0), Duration::from_millis(800));
+        assert!(jittered_backoff(2, base, max, 0.5) < Duration::from_millis(800));
+        assert_eq!(jittered_backoff(10, base, max, 1.0), max);
+        assert_eq!(jittered_backoff(u32::MAX, base, max, 2.0), max);
+    }
+}
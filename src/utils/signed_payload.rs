@@ -0,0 +1,74 @@
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Whether `needle` appears anywhere inside `haystack`. Signed messages embed the message
+/// they sign, so this checks a signed payload carries exactly the value we handed out
+/// without depending on how the SDK lays out the signature envelope.
+pub fn contains_value(haystack: &Value, needle: &Value) -> bool {
+    if haystack == needle {
+        return true;
+    }
+    match haystack {
+        Value::Object(map) => map.values().any(|value| contains_value(value, needle)),
+        Value::Array(items) => items.iter().any(|value| contains_value(value, needle)),
+        _ => false,
+    }
+}
+
+/// The objects inside `payload` that have every one of `keys`, outermost first, e.g. the
+/// messages a list of signed messages signs. Matches aren't searched further.
+pub fn find_messages<'a>(payload: &'a Value, keys: &[&str]) -> Vec<&'a Value> {
+    match payload {
+        Value::Object(map) if keys.iter().all(|key| map.contains_key(*key)) => vec![payload],
+        Value::Object(map) => map.values().flat_map(|value| find_messages(value, keys)).collect(),
+        Value::Array(items) => items.iter().flat_map(|value| find_messages(value, keys)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Hex SHA-256 of a message's JSON, to recognize the same signed message when it comes
+/// back, however the client laid out its copy
+pub fn payload_hash<T: Serialize>(message: &T) -> Result<String, serde_json::Error> {
+    Ok(hex::encode(Sha256::digest(serde_json::to_vec(message)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_contains_value() {
+        let message = json!({ "debited": "a", "credited": "b", "allowances": [["t", 5]] });
+        let signed = json!({ "message": message.clone(), "signature": "00ff" });
+        assert!(contains_value(&signed, &message));
+        assert!(contains_value(&signed, &json!("b")));
+        assert!(contains_value(&json!([signed.clone()]), &message));
+
+        let tampered = json!({ "debited": "a", "credited": "c", "allowances": [["t", 5]] });
+        assert!(!contains_value(&signed, &tampered));
+    }
+
+    #[test]
+    fn test_find_messages() {
+        let keys = ["debited", "credited", "allowances"];
+        let first = json!({ "debited": "a", "credited": "b", "allowances": [["t", 5]] });
+        let second = json!({ "debited": "a", "credited": "c", "allowances": [] });
+        let signed = json!([
+            { "message": first.clone(), "signature": "00ff" },
+            { "envelope": { "message": second.clone() }, "signature": "ff00" },
+        ]);
+        assert_eq!(find_messages(&signed, &keys), vec![&first, &second]);
+        assert!(find_messages(&json!({ "debited": "a", "signature": "00ff" }), &keys).is_empty());
+    }
+
+    #[test]
+    fn test_payload_hash() {
+        let message = json!({ "debited": "a", "credited": "b" });
+        let reparsed: Value = serde_json::from_str("{ \"credited\": \"b\",\n  \"debited\": \"a\" }").unwrap();
+        assert_eq!(payload_hash(&message).unwrap(), payload_hash(&reparsed).unwrap());
+        assert_eq!(payload_hash(&message).unwrap().len(), 64);
+        assert_ne!(payload_hash(&message).unwrap(), payload_hash(&json!({ "debited": "a", "credited": "c" })).unwrap());
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Small in-memory cache whose entries expire `ttl` after insertion. A zero TTL disables it.
+///
+/// Invalidation bumps a generation counter; a value fetched before an invalidation is
+/// not stored, so a read racing a write can't put the pre-write value back.
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    capacity: usize,
+    generation: u64,
+    entries: HashMap<K, (Instant, V)>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self { ttl, capacity: capacity.max(1), generation: 0, entries: HashMap::new() }
+    }
+
+    pub fn get(&self, key: &K, now: Instant) -> Option<V> {
+        self.entries.get(key)
+            .filter(|(inserted_at, _)| now.duration_since(*inserted_at) < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Take before fetching a value, and pass to `insert`
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn insert(&mut self, key: K, value: V, generation: u64, now: Instant) {
+        if self.ttl.is_zero() || generation != self.generation {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            let ttl = self.ttl;
+            self.entries.retain(|_, (inserted_at, _)| now.duration_since(*inserted_at) < ttl);
+            if self.entries.len() >= self.capacity {
+                self.entries.clear();
+            }
+        }
+        self.entries.insert(key, (now, value));
+    }
+
+    pub fn invalidate(&mut self, key: &K) {
+        self.generation += 1;
+        self.entries.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn test_entries_expire() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(TTL, 10);
+        cache.insert("alice", 5, cache.generation(), now);
+        assert_eq!(cache.get(&"alice", now + Duration::from_secs(1)), Some(5));
+        assert_eq!(cache.get(&"alice", now + TTL), None);
+    }
+
+    #[test]
+    fn test_zero_ttl_disables() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(Duration::ZERO, 10);
+        cache.insert("alice", 5, cache.generation(), now);
+        assert_eq!(cache.get(&"alice", now), None);
+    }
+
+    #[test]
+    fn test_invalidation_drops_in_flight_value() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(TTL, 10);
+        cache.insert("alice", 5, cache.generation(), now);
+
+        let generation = cache.generation();
+        cache.invalidate(&"alice");
+        assert_eq!(cache.get(&"alice", now), None);
+
+        // Fetched before the write landed
+        cache.insert("alice", 5, generation, now);
+        assert_eq!(cache.get(&"alice", now), None);
+
+        cache.insert("alice", 3, cache.generation(), now);
+        assert_eq!(cache.get(&"alice", now), Some(3));
+    }
+
+    #[test]
+    fn test_capacity_evicts_expired_first() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(TTL, 2);
+        cache.insert("alice", 1, cache.generation(), now);
+        cache.insert("bob", 2, cache.generation(), now + Duration::from_secs(1));
+        cache.insert("carol", 3, cache.generation(), now + TTL);
+        assert_eq!(cache.get(&"bob", now + TTL), Some(2));
+        assert_eq!(cache.get(&"carol", now + TTL), Some(3));
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+/// Why each field of a request is invalid, keyed by field name, for forms to show next to
+/// the field
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct FieldErrors(BTreeMap<String, Vec<String>>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.entry(field.to_string()).or_default().push(message.into());
+    }
+
+    /// Record the error of a field check, if it failed
+    pub fn check<T>(&mut self, field: &str, result: Result<T, String>) {
+        if let Err(message) = result {
+            self.add(field, message);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, field: &str) -> Option<&[String]> {
+        self.0.get(field).map(Vec::as_slice)
+    }
+
+    /// "field: message; other: message", for logs and single-line errors
+    pub fn summary(&self) -> String {
+        self.0.iter()
+            .flat_map(|(field, messages)| messages.iter().map(move |message| format!("{}: {}", field, message)))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    pub fn into_result(self) -> Result<(), FieldErrors> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+/// Request bodies that can check their own fields before anything is looked up
+pub trait Validate {
+    fn validate(&self) -> Result<(), FieldErrors>;
+}
+
+pub fn required(value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err("This field is required".to_string());
+    }
+    Ok(())
+}
+
+/// Token symbols are 2-5 letters, stored uppercase. Returns the symbol as stored.
+pub fn token_symbol(symbol: &str) -> Result<String, String> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol.len() < 2 || symbol.len() > 5 || !symbol.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err("Token symbol must be 2-5 uppercase letters".to_string());
+    }
+    Ok(symbol)
+}
+
+/// Wallet addresses are Ed25519 public keys, in base58 or, as older clients send them, hex
+pub fn wallet_address(address: &str) -> Result<(), String> {
+    required(address)?;
+    let address = address.trim();
+    let is_key = |bytes: Option<Vec<u8>>| bytes.map_or(false, |bytes| bytes.len() == 32);
+    if !is_key(bs58::decode(address).into_vec().ok()) && !is_key(hex::decode(address).ok()) {
+        return Err("Must be a wallet address".to_string());
+    }
+    Ok(())
+}
+
+pub fn positive_usd(amount: f64) -> Result<(), String> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err("Must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+pub fn cents_between(amount_cents: i64, min: i64, max: i64) -> Result<(), String> {
+    if amount_cents < min || amount_cents > max {
+        return Err(format!("Must be between ${:.2} and ${:.2}", min as f64 / 100.0, max as f64 / 100.0));
+    }
+    Ok(())
+}
+
+/// Attribution labels for donations, e.g. "newsletter" or a partner's domain. Returns the
+/// label as stored, trimmed.
+pub fn referrer(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().count() > 100 || value.chars().any(char::is_control) {
+        return Err("Referrer must be 1-100 characters".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// URL slugs, e.g. for fundraising pages: 3-50 lowercase letters, digits and inner hyphens.
+/// Returns the slug as stored, lowercased.
+pub fn slug(value: &str) -> Result<String, String> {
+    let slug = value.trim().to_lowercase();
+    let valid = (3..=50).contains(&slug.len())
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-');
+    if !valid {
+        return Err("Must be 3-50 letters, digits or hyphens".to_string());
+    }
+    Ok(slug)
+}
+
+/// A web origin as browsers send it: scheme, host and optional port, without a path.
+/// Returns it normalized, e.g. "https://example.org".
+pub fn origin(value: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(value.trim())
+        .map_err(|_| "Must be an origin like https://example.org".to_string())?;
+    let origin = url.origin().ascii_serialization();
+    if !matches!(url.scheme(), "http" | "https") || !origin.eq_ignore_ascii_case(value.trim().trim_end_matches('/')) {
+        return Err("Must be an origin like https://example.org".to_string());
+    }
+    Ok(origin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_errors_collect_per_field() {
+        let mut errors = FieldErrors::new();
+        errors.check("name", required(" "));
+        errors.add("name", "This cause name is already taken");
+        errors.check("symbol", token_symbol("ab"));
+        errors.check("price_usd", positive_usd(1.0));
+
+        assert_eq!(errors.get("name").map(<[String]>::len), Some(2));
+        assert!(errors.get("symbol").is_none());
+        assert!(errors.get("price_usd").is_none());
+        assert_eq!(serde_json::to_value(&errors).unwrap()["name"][0], "This field is required");
+        assert_eq!(errors.summary(), "name: This field is required; name: This cause name is already taken");
+        assert!(FieldErrors::new().into_result().is_ok());
+    }
+
+    #[test]
+    fn test_field_checks() {
+        assert_eq!(token_symbol(" meme ").unwrap(), "MEME");
+        assert!(token_symbol("M").is_err());
+        assert!(token_symbol("MEMES1").is_err());
+        assert!(positive_usd(0.0).is_err());
+        assert!(positive_usd(f64::NAN).is_err());
+        assert!(cents_between(100, 100, 999999).is_ok());
+        assert_eq!(cents_between(99, 100, 999999).unwrap_err(), "Must be between $1.00 and $9999.99");
+    }
+
+    #[test]
+    fn test_wallet_address() {
+        assert!(wallet_address(&bs58::encode([1u8; 32]).into_string()).is_ok());
+        assert!(wallet_address(&hex::encode([0xabu8; 32])).is_ok());
+        assert!(wallet_address(&bs58::encode([1u8; 31]).into_string()).is_err());
+        assert!(wallet_address("not-base58!").is_err());
+        assert!(wallet_address("").is_err());
+    }
+
+    #[test]
+    fn test_referrer_and_origin() {
+        assert_eq!(referrer(" newsletter ").unwrap(), "newsletter");
+        assert!(referrer("").is_err());
+        assert!(referrer(&"a".repeat(101)).is_err());
+        assert!(referrer("line\nbreak").is_err());
+        assert_eq!(origin("https://Example.org/").unwrap(), "https://example.org");
+        assert_eq!(origin("http://localhost:8080").unwrap(), "http://localhost:8080");
+        assert!(origin("https://example.org/donate").is_err());
+        assert!(origin("ftp://example.org").is_err());
+        assert!(origin("example.org").is_err());
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug(" Run-For-Rivers-2025 ").unwrap(), "run-for-rivers-2025");
+        assert!(slug("ab").is_err());
+        assert!(slug("-rivers").is_err());
+        assert!(slug("rivers run").is_err());
+        assert!(slug("ríos").is_err());
+    }
+}
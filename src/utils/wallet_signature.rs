@@ -0,0 +1,100 @@
+use ed25519_dalek::{Signature, VerifyingKey, Verifier};
+use sha2::{Digest, Sha256};
+
+/// Message a wallet signs to authenticate a request: "METHOD:/path?query:timestamp:body_hash".
+/// The query is left out with its `?` when empty; `body_hash` is from `body_digest`.
+pub fn signing_message(method: &str, path_and_query: &str, timestamp: i64, body_hash: &str) -> String {
+    format!("{}:{}:{}:{}", method.to_uppercase(), path_and_query, timestamp, body_hash)
+}
+
+/// Hex SHA-256 of a request body, as signed; requests without a body hash no bytes
+pub fn body_digest(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Message a wallet signs to agree to being linked to an account: "LINK:account_id:wallet:timestamp"
+pub fn link_message(account_id: &str, wallet_address: &str, timestamp: i64) -> String {
+    format!("LINK:{}:{}:{}", account_id, wallet_address, timestamp)
+}
+
+/// Verify a hex-encoded ed25519 signature against a base58 wallet address
+pub fn verify_wallet_signature(wallet_address: &str, message: &[u8], signature_hex: &str) -> Result<(), String> {
+    let pubkey_bytes = bs58::decode(wallet_address)
+        .into_vec()
+        .map_err(|e| format!("Invalid wallet address: {}", e))?;
+    let pubkey_bytes: [u8; 32] = pubkey_bytes
+        .try_into()
+        .map_err(|_| "Wallet address must be a 32-byte public key".to_string())?;
+    let verifying_key = VerifyingKey::from_bytes(&pubkey_bytes)
+        .map_err(|e| format!("Invalid wallet public key: {}", e))?;
+
+    let signature_hex = signature_hex.strip_prefix("0x").unwrap_or(signature_hex);
+    let signature_bytes = hex::decode(signature_hex)
+        .map_err(|e| format!("Invalid signature format: {}", e))?;
+    let signature_bytes: [u8; 64] = signature_bytes
+        .try_into()
+        .map_err(|_| "Signature must be 64 bytes".to_string())?;
+    let signature = Signature::from_bytes(&signature_bytes);
+
+    verifying_key
+        .verify(message, &signature)
+        .map_err(|_| "Invalid signature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn test_wallet() -> (SigningKey, String) {
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let address = bs58::encode(signing_key.verifying_key().to_bytes()).into_string();
+        (signing_key, address)
+    }
+
+    #[test]
+    fn test_signing_message_format() {
+        assert_eq!(signing_message("put", "/causes/abc", 1700000000, "ab12"), "PUT:/causes/abc:1700000000:ab12");
+        assert_eq!(
+            signing_message("get", "/api/users/abc/transactions?limit=5", 1700000000, &body_digest(b"")),
+            "GET:/api/users/abc/transactions?limit=5:1700000000:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        );
+    }
+
+    #[test]
+    fn test_body_digest_covers_body() {
+        assert_ne!(body_digest(br#"{"amount":1}"#), body_digest(br#"{"amount":100}"#));
+        assert_eq!(body_digest(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_link_message_format() {
+        assert_eq!(link_message("65f0c0ffee", "Wallet1", 1700000000), "LINK:65f0c0ffee:Wallet1:1700000000");
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        let (signing_key, address) = test_wallet();
+        let message = signing_message("DELETE", "/api/payments/ABC12", 1700000000, &body_digest(b""));
+        let signature = hex::encode(signing_key.sign(message.as_bytes()).to_bytes());
+
+        assert!(verify_wallet_signature(&address, message.as_bytes(), &signature).is_ok());
+    }
+
+    #[test]
+    fn test_signature_for_other_message_rejected() {
+        let (signing_key, address) = test_wallet();
+        let signature = hex::encode(signing_key.sign(b"DELETE:/api/payments/ABC12:1").to_bytes());
+
+        let result = verify_wallet_signature(&address, b"DELETE:/api/payments/ABC12:2", &signature);
+        assert_eq!(result.unwrap_err(), "Invalid signature");
+    }
+
+    #[test]
+    fn test_malformed_inputs_rejected() {
+        let (_, address) = test_wallet();
+        assert!(verify_wallet_signature("not-base58-0OIl", b"msg", "00").is_err());
+        assert!(verify_wallet_signature(&address, b"msg", "zz").is_err());
+        assert!(verify_wallet_signature(&address, b"msg", "abcd").unwrap_err().contains("64 bytes"));
+    }
+}
@@ -0,0 +1,88 @@
+//! Donations attributed to a cause's campaigns and fundraising pages, checked before checkout,
+//! against MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test campaigns`.
+
+mod common;
+
+use index_wallets_backend::models::cause::Cause;
+use index_wallets_backend::models::{
+    ApiError, CampaignStatus, CreateCampaignRequest, CreateFundraiserRequest, DonationAttribution, FundraiserStatus, UpdateCampaignRequest,
+    UpdateFundraiserRequest,
+};
+use index_wallets_backend::services::{CampaignService, FundraiserService};
+use mongodb::bson::oid::ObjectId;
+
+use common::TestApp;
+
+fn campaign_request(name: &str, starts_at: Option<i64>) -> CreateCampaignRequest {
+    CreateCampaignRequest { name: name.to_string(), description: None, goal_cents: 10_000, starts_at, ends_at: None }
+}
+
+fn to_campaign(campaign_id: &str) -> DonationAttribution {
+    DonationAttribution { referrer: None, campaign_id: Some(campaign_id.to_string()), fundraiser_id: None }
+}
+
+fn to_fundraiser(fundraiser_id: &str) -> DonationAttribution {
+    DonationAttribution { referrer: None, campaign_id: None, fundraiser_id: Some(fundraiser_id.to_string()) }
+}
+
+async fn refused(app: &TestApp, cause: &Cause, attribution: &DonationAttribution) -> bool {
+    let checked = app.cause_service.check_attribution(cause, attribution).await;
+    matches!(checked, Err(ApiError::ValidationError(_)))
+}
+
+#[actix_web::test]
+async fn a_donation_goes_only_to_an_open_campaign_of_its_cause() {
+    let app = TestApp::start().await;
+    let campaigns = CampaignService::new(app.db.clone());
+    let rivers = app.create_cause(TestApp::active_cause("RIVER")).await;
+    let forests = app.create_cause(TestApp::active_cause("TREE")).await;
+
+    let open = campaigns.create(&rivers, &campaign_request("Spring Gala", None), "owner").await.unwrap();
+    let open_id = open.id.unwrap().to_hex();
+    app.cause_service.check_attribution(&rivers, &to_campaign(&open_id)).await.unwrap();
+
+    // Another cause's campaign, or one that doesn't exist
+    assert!(refused(&app, &forests, &to_campaign(&open_id)).await);
+    assert!(refused(&app, &rivers, &to_campaign(&ObjectId::new().to_hex())).await);
+    assert!(refused(&app, &rivers, &to_campaign("not-an-id")).await);
+
+    // Not started yet
+    let later = chrono::Utc::now().timestamp() + 3600;
+    let upcoming = campaigns.create(&rivers, &campaign_request("Autumn Fair", Some(later)), "owner").await.unwrap();
+    assert!(refused(&app, &rivers, &to_campaign(&upcoming.id.unwrap().to_hex())).await);
+
+    // Ended by its owner
+    let end = UpdateCampaignRequest { name: None, description: None, goal_cents: None, starts_at: None, ends_at: None, status: Some(CampaignStatus::Ended) };
+    campaigns.update(open, &end).await.unwrap();
+    assert!(refused(&app, &rivers, &to_campaign(&open_id)).await);
+
+    // No campaign at all is fine
+    let unattributed = DonationAttribution { referrer: Some("newsletter".to_string()), campaign_id: None, fundraiser_id: None };
+    app.cause_service.check_attribution(&rivers, &unattributed).await.unwrap();
+}
+
+#[actix_web::test]
+async fn a_donation_goes_only_to_an_open_fundraiser_of_its_cause() {
+    let app = TestApp::start().await;
+    let fundraisers = FundraiserService::new(app.db.clone());
+    let rivers = app.create_cause(TestApp::active_cause("RIVER")).await;
+    let forests = app.create_cause(TestApp::active_cause("TREE")).await;
+
+    let request = CreateFundraiserRequest {
+        cause_id: rivers.id.unwrap().to_hex(),
+        slug: "river-run".to_string(),
+        title: "River run".to_string(),
+        story: None,
+        goal_cents: 10_000,
+    };
+    let page = fundraisers.create(&rivers, &request, "supporter").await.unwrap();
+    let page_id = page.id.unwrap().to_hex();
+    app.cause_service.check_attribution(&rivers, &to_fundraiser(&page_id)).await.unwrap();
+    assert!(refused(&app, &forests, &to_fundraiser(&page_id)).await);
+
+    let close = UpdateFundraiserRequest { title: None, story: None, goal_cents: None, status: Some(FundraiserStatus::Closed) };
+    fundraisers.update(page, &close).await.unwrap();
+    assert!(refused(&app, &rivers, &to_fundraiser(&page_id)).await);
+}
@@ -0,0 +1,341 @@
+//! An app wired like the server's, on a throwaway MongoDB container and the in-memory
+//! MockExecutor, with helpers to act as signed-in wallets
+
+// Each test binary uses its own subset of the helpers
+#![allow(dead_code)]
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use delta_executor_sdk::base::crypto::{Ed25519PrivKey, Ed25519PubKey, SignedMessage};
+use delta_executor_sdk::base::vaults::Vault;
+use delta_executor_sdk::base::verifiable::debit_allowance::{DebitAllowance, SignedDebitAllowance};
+use ed25519_dalek::{Signer, SigningKey};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::Document;
+use serde_json::{json, Value};
+use testcontainers_modules::mongo::Mongo;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+use testcontainers_modules::testcontainers::ContainerAsync;
+
+use index_wallets_backend::auth::{WALLET_ADDRESS_HEADER, WALLET_SIGNATURE_HEADER, WALLET_TIMESTAMP_HEADER};
+use index_wallets_backend::config::{BundlePolicy, ConnectConfig, PaymentMethodConfig};
+use index_wallets_backend::models::cause::{Cause, CauseStatus};
+use index_wallets_backend::models::{CreateUserRequest, Token, TokenBalance};
+use index_wallets_backend::routes;
+use index_wallets_backend::services::{
+    CauseService, DisputeService, EmailService, EscrowService, ExecutorClient, FakeStripe, MockExecutor, MongoDBService,
+    OrganizationService, PaymentFinalityService, FeatureFlagService, JobService, PushService, SharedState,
+    StripeCustomerService, TokenService, VaultProvisioningService, VendorService, VoucherService, WalletService, WebhookService,
+};
+use index_wallets_backend::utils::name_filter::NameFilter;
+use index_wallets_backend::request_digest::RequestDigest;
+use index_wallets_backend::utils::wallet_signature::{signing_message, body_digest};
+
+/// A wallet the tests control: its address and keys for both request and transaction signing
+pub struct TestWallet {
+    pub address: String,
+    pub keypair: Ed25519PrivKey,
+    signing_key: SigningKey,
+}
+
+impl TestWallet {
+    pub fn generate() -> Self {
+        let seed: [u8; 32] = rand::random();
+        let keypair = Ed25519PrivKey::from_str(&hex::encode(seed)).expect("test private key");
+        Self {
+            address: keypair.pub_key().to_string(),
+            keypair,
+            signing_key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    pub fn pubkey(&self) -> Ed25519PubKey {
+        self.keypair.pub_key()
+    }
+
+    /// Add the X-Wallet-* headers that authenticate `request` as this wallet. `path_and_query`
+    /// and `body` must be what the request sends.
+    pub fn sign_request(&self, request: test::TestRequest, method: &str, path_and_query: &str, body: &[u8]) -> test::TestRequest {
+        let timestamp = chrono::Utc::now().timestamp();
+        let signature = self.signing_key.sign(signing_message(method, path_and_query, timestamp, &body_digest(body)).as_bytes());
+        request
+            .insert_header((WALLET_ADDRESS_HEADER, self.address.clone()))
+            .insert_header((WALLET_TIMESTAMP_HEADER, timestamp.to_string()))
+            .insert_header((WALLET_SIGNATURE_HEADER, hex::encode(signature.to_bytes())))
+    }
+
+    /// Sign the debit allowances of a supplement response, as the wallet app does
+    pub fn sign_transaction(&self, unsigned_transaction: &str) -> String {
+        let allowances: Vec<DebitAllowance> = serde_json::from_str(unsigned_transaction).expect("unsigned transaction");
+        let signed: Vec<SignedDebitAllowance> = allowances.into_iter()
+            .map(|allowance| SignedMessage::sign(allowance, &self.keypair).expect("signed allowance"))
+            .collect();
+        serde_json::to_string(&signed).expect("signed transaction")
+    }
+}
+
+/// A token the payer holds, on its own made-up token vault
+pub struct TestToken {
+    pub symbol: &'static str,
+    pub token_key: String,
+}
+
+impl TestToken {
+    pub fn new(symbol: &'static str) -> Self {
+        Self { symbol, token_key: format!("{},1", TestWallet::generate().address) }
+    }
+
+    pub fn balance(&self, balance: f64) -> TokenBalance {
+        TokenBalance {
+            token_key: self.token_key.clone(),
+            symbol: self.symbol.to_string(),
+            name: self.symbol.to_string(),
+            balance,
+            average_valuation: 1.0,
+            token_image_url: None,
+        }
+    }
+}
+
+/// An empty vault at nonce 0, in the shape the executor serves from GET /vaults/{pubkey}
+pub fn empty_vault(pubkey: &Ed25519PubKey) -> Vault {
+    serde_json::from_value(json!({
+        "id": { "owner": pubkey.to_string(), "shard": 1 },
+        "nonce": 0,
+        "data": null,
+    }))
+    .expect("vault JSON")
+}
+
+pub struct TestApp {
+    pub db: web::Data<MongoDBService>,
+    pub executor: Arc<MockExecutor>,
+    pub webhook_service: web::Data<WebhookService>,
+    pub voucher_service: web::Data<VoucherService>,
+    wallet_service: web::Data<WalletService>,
+    pub escrow_service: web::Data<EscrowService>,
+    pub dispute_service: web::Data<DisputeService>,
+    pub stripe: Arc<FakeStripe>,
+    pub cause_service: web::Data<CauseService>,
+    pub organization_service: web::Data<OrganizationService>,
+    pub vendor_service: web::Data<VendorService>,
+    push_service: web::Data<PushService>,
+    vault_provisioning: web::Data<VaultProvisioningService>,
+    shared_state: web::Data<SharedState>,
+    bundle_policy: web::Data<BundlePolicy>,
+    feature_flags: web::Data<FeatureFlagService>,
+    pub jobs: web::Data<JobService>,
+    // Dropping the container removes the database with it
+    _mongo: ContainerAsync<Mongo>,
+}
+
+impl TestApp {
+    /// Start MongoDB in Docker and build the services the payment routes use
+    pub async fn start() -> Self {
+        let mongo = Mongo::default().start().await.expect("MongoDB container");
+        let port = mongo.get_host_port_ipv4(27017).await.expect("MongoDB port");
+        let db = MongoDBService::connect(&format!("mongodb://127.0.0.1:{}", port), "index_wallets_test")
+            .await
+            .expect("MongoDB connection");
+        let db = web::Data::new(db);
+
+        let shared_state = SharedState::connect(None).await.expect("shared state");
+        let executor = Arc::new(MockExecutor::new());
+        let executor_client = ExecutorClient::with_backend(executor.clone(), Duration::ZERO, shared_state.clone());
+
+        let central_vault = TestWallet::generate();
+        let escrow_vault = TestWallet::generate();
+        let network_goods_vault = TestWallet::generate();
+        let matching_vault = TestWallet::generate();
+        executor.set_vault(&central_vault.pubkey(), empty_vault(&central_vault.pubkey()));
+        executor.set_vault(&escrow_vault.pubkey(), empty_vault(&escrow_vault.pubkey()));
+        executor.set_vault(&matching_vault.pubkey(), empty_vault(&matching_vault.pubkey()));
+
+        let wallet_service = web::Data::new(WalletService::new(db.clone(), executor_client.clone()));
+        let token_service = web::Data::new(TokenService::new(db.clone(), central_vault.keypair.clone(), rand::random(), executor_client));
+        let vault_provisioning = web::Data::new(VaultProvisioningService::new(wallet_service.clone(), token_service.clone(), central_vault.keypair.clone()));
+        let escrow_service = web::Data::new(EscrowService::new(db.clone(), token_service.clone(), escrow_vault.keypair.clone()));
+        let voucher_service = web::Data::new(VoucherService::new(db.clone(), token_service.clone(), wallet_service.clone(), central_vault.keypair.clone()));
+        let http_client = reqwest::Client::new();
+        let email_service = web::Data::new(EmailService::new(http_client.clone()));
+        let push_service = web::Data::new(PushService::new(db.clone(), email_service.clone(), http_client).expect("push service"));
+        let dispute_service = web::Data::new(DisputeService::new(db.clone(), token_service.clone(), escrow_service.clone(), push_service.clone(), central_vault.keypair.clone()));
+        let stripe = Arc::new(FakeStripe::new());
+        let customer_service = Arc::new(StripeCustomerService::new(db.clone().into_inner(), stripe.clone()));
+        let cause_service = web::Data::new(CauseService::new(
+            db.clone().into_inner(),
+            token_service.clone().into_inner(),
+            stripe.clone(),
+            email_service.clone().into_inner(),
+            NameFilter::with_defaults(),
+            PaymentMethodConfig::from_env(),
+            customer_service,
+            ConnectConfig::from_env(),
+        ).expect("cause service"));
+        let organization_service = web::Data::new(OrganizationService::new(db.clone(), cause_service.clone()));
+        let vendor_service = web::Data::new(VendorService::new(db.clone(), cause_service.clone(), stripe.clone()));
+        let webhook_service = web::Data::new(WebhookService::new(
+            Vec::new(),
+            Vec::new(),
+            token_service.into_inner(),
+            db.clone().into_inner(),
+            central_vault.keypair.clone(),
+            network_goods_vault.keypair.clone(),
+            Some(matching_vault.keypair.clone()),
+            push_service.clone().into_inner(),
+            email_service.into_inner(),
+        ));
+        let feature_flags = web::Data::new(FeatureFlagService::new(db.clone(), "test"));
+        let jobs = web::Data::new(JobService::new(db.clone()));
+
+        Self {
+            db,
+            executor,
+            webhook_service,
+            voucher_service,
+            wallet_service,
+            escrow_service,
+            dispute_service,
+            stripe,
+            cause_service,
+            organization_service,
+            vendor_service,
+            push_service,
+            vault_provisioning,
+            shared_state: web::Data::new(shared_state),
+            bundle_policy: web::Data::new(BundlePolicy::default()),
+            feature_flags,
+            jobs,
+            _mongo: mongo,
+        }
+    }
+
+    /// The server's routes over this app's services, for `test::init_service`
+    pub fn app(&self) -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = actix_web::Error, InitError = ()>> {
+        App::new()
+            .wrap(RequestDigest)
+            .app_data(self.db.clone())
+            .app_data(self.wallet_service.clone())
+            .app_data(self.escrow_service.clone())
+            .app_data(self.dispute_service.clone())
+            .app_data(self.push_service.clone())
+            .app_data(self.vault_provisioning.clone())
+            .app_data(self.shared_state.clone())
+            .app_data(self.bundle_policy.clone())
+            .app_data(self.feature_flags.clone())
+            .app_data(self.jobs.clone())
+            .app_data(self.webhook_service.clone())
+            .app_data(self.voucher_service.clone())
+            .app_data(self.cause_service.clone())
+            .app_data(self.organization_service.clone())
+            .app_data(self.vendor_service.clone())
+            .configure(routes::configure)
+    }
+
+    /// A payer with a vault on the executor, so their allowances have a nonce to sign against
+    pub fn payer(&self) -> TestWallet {
+        let payer = TestWallet::generate();
+        self.executor.set_vault(&payer.pubkey(), empty_vault(&payer.pubkey()));
+        payer
+    }
+
+    /// A USD token on a fresh issuer vault, for tests that move dollars
+    pub async fn usd_token(&self) -> Token {
+        self.db.save_token(Token {
+            id: None,
+            token_id: format!("{},1", TestWallet::generate().address),
+            token_name: "US Dollar".to_string(),
+            token_symbol: Some("USD".to_string()),
+            market_valuation: 1.0,
+            total_allocated: 0,
+            created_at: chrono::Utc::now().timestamp(),
+            updated_at: None,
+            stripe_product_id: String::new(),
+            token_image_url: None,
+        }).await.expect("USD token")
+    }
+
+    /// A verified vendor giving `budgets` (symbol to USD) of discounts
+    pub async fn vendor(&self, name: &str, budgets: &[(&str, f64)]) -> TestWallet {
+        let vendor = TestWallet::generate();
+        self.db.create_user_with_vendor_if_needed(CreateUserRequest {
+            wallet_address: vendor.address.clone(),
+            username: name.to_string(),
+            preferences: None,
+            is_verified: true,
+            user_type: "vendor".to_string(),
+            vendor_description: None,
+            vendor_google_maps_link: None,
+            vendor_website_link: None,
+        }).await.expect("vendor user");
+        let mut preferences = Document::new();
+        for (symbol, budget) in budgets {
+            preferences.insert(*symbol, *budget);
+        }
+        self.db.replace_user_preferences(&vendor.address, preferences).await.expect("vendor preferences");
+        self.executor.set_vault(&vendor.pubkey(), empty_vault(&vendor.pubkey()));
+        vendor
+    }
+
+    /// An active cause taking donations through a connected account, for tests to adjust
+    /// before saving it with `create_cause`
+    pub fn active_cause(symbol: &str) -> Cause {
+        let mut cause = Cause::new(
+            format!("Cause {}", symbol),
+            "River Trust".to_string(),
+            "Clean rivers".to_string(),
+            "Cleaning up the rivers".to_string(),
+            "rivers@example.org".to_string(),
+            format!("Token {}", symbol),
+            symbol.to_string(),
+            None,
+            None,
+        );
+        cause.status = CauseStatus::Active;
+        cause.stripe_account_id = Some(format!("acct_{}", symbol.to_lowercase()));
+        cause
+    }
+
+    /// Save a cause and fill in its ID
+    pub async fn create_cause(&self, mut cause: Cause) -> Cause {
+        let id = self.db.create_cause(cause.clone()).await.expect("cause");
+        cause.id = Some(ObjectId::parse_str(&id).expect("cause ID"));
+        cause
+    }
+
+    /// A vendor's remaining discount budget for a token
+    pub async fn budget(&self, vendor: &TestWallet, symbol: &str) -> f64 {
+        self.db.get_user_preferences(&vendor.address).await.expect("vendor preferences")
+            .get_f64(symbol)
+            .unwrap_or(0.0)
+    }
+
+    /// Poll the executor for submitted payments once, as the settlement poller does
+    pub async fn settle_payments(&self) {
+        PaymentFinalityService::new(
+            self.db.clone(),
+            self.wallet_service.clone(),
+            self.push_service.clone(),
+            self.shared_state.clone(),
+        ).check_submitted_payments().await;
+    }
+}
+
+/// Send `request` and return the status with the JSON body, or `Null` for an empty one
+pub async fn send<S, B>(service: &S, request: test::TestRequest) -> (StatusCode, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let response = test::call_service(service, request.to_request()).await;
+    let status = response.status();
+    let body = test::read_body(response).await;
+    let json = if body.is_empty() { Value::Null } else { serde_json::from_slice(&body).unwrap_or(Value::Null) };
+    (status, json)
+}
@@ -0,0 +1,215 @@
+//! Donations paid without a wallet: held for the donor's email, then claimed into a wallet
+//! through the HTTP handlers, against MongoDB in Docker and the in-memory executor.
+//!
+//! Run with `cargo test --features test-harness --test deposits`.
+
+mod common;
+
+use std::collections::HashMap;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use index_wallets_backend::handlers::purchase_webhook_handlers::credit_unclaimed_deposit;
+use index_wallets_backend::models::{CreditReservationStatus, UnclaimedDeposit, UnclaimedDepositStatus};
+use index_wallets_backend::services::ExecutorError;
+use index_wallets_backend::utils::email_verification::{sign_verification_token, DEPOSIT_CLAIM_SCOPE};
+use serde_json::json;
+
+use common::{send, TestApp, TestWallet};
+
+const SECRET: &str = "test-claim-secret";
+const EMAIL: &str = "donor@example.org";
+
+/// Claim links need a configured secret; every test sets the same one before anything reads it
+fn configure_secret() {
+    std::env::set_var("EMAIL_VERIFICATION_SECRET", SECRET);
+}
+
+/// Hold a $20 USD top-up paid through checkout session `session_id` for `EMAIL`, with the
+/// USD token it credits
+async fn hold_top_up(app: &TestApp, session_id: &str) -> UnclaimedDeposit {
+    hold(app, session_id, Some(session_id), None).await
+}
+
+/// Hold a $20 USD top-up paid through an embedded form's PaymentIntent for `EMAIL`
+async fn hold_intent_top_up(app: &TestApp, intent_id: &str) -> UnclaimedDeposit {
+    hold(app, intent_id, None, Some(intent_id)).await
+}
+
+async fn hold(app: &TestApp, payment_id: &str, session_id: Option<&str>, intent_id: Option<&str>) -> UnclaimedDeposit {
+    app.usd_token().await;
+
+    let unclaimed = UnclaimedDeposit {
+        id: None,
+        payment_id: payment_id.to_string(),
+        stripe_session_id: session_id.map(str::to_string),
+        stripe_payment_intent_id: intent_id.map(str::to_string),
+        email: EMAIL.to_string(),
+        token_symbol: "USD".to_string(),
+        amount_cents: 2000,
+        metadata: HashMap::from([("token_symbol".to_string(), "USD".to_string())]),
+        status: UnclaimedDepositStatus::Unclaimed,
+        created_at: chrono::Utc::now().timestamp(),
+        claimed_by: None,
+        claimed_at: None,
+        failure_reason: None,
+    };
+    assert!(app.db.create_unclaimed_deposit(&unclaimed).await.expect("unclaimed deposit"));
+    unclaimed
+}
+
+/// A signed claim of everything held for `EMAIL`, with a valid claim link token
+fn claim(wallet: &TestWallet) -> TestRequest {
+    let expires_at = chrono::Utc::now().timestamp() + 3600;
+    let body = json!({
+        "email": EMAIL,
+        "token": sign_verification_token(SECRET.as_bytes(), DEPOSIT_CLAIM_SCOPE, EMAIL, expires_at),
+    }).to_string();
+    let request = TestRequest::post()
+        .uri("/v1/deposits/claim")
+        .insert_header(("content-type", "application/json"))
+        .set_payload(body.clone());
+    wallet.sign_request(request, "POST", "/v1/deposits/claim", body.as_bytes())
+}
+
+#[actix_web::test]
+async fn a_refused_claim_stays_claimable() {
+    configure_secret();
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    hold_top_up(&app, "cs_refused").await;
+    let donor = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Rejected { reason: "vault locked".to_string() });
+    let (code, claimed) = send(&service, claim(&donor)).await;
+    assert_eq!(code, StatusCode::OK, "{}", claimed);
+    assert_eq!(claimed["failed"], 1);
+    assert_eq!(app.db.get_unclaimed_deposits(EMAIL).await.unwrap().len(), 1);
+
+    let (code, claimed) = send(&service, claim(&donor)).await;
+    assert_eq!(code, StatusCode::OK, "{}", claimed);
+    assert_eq!(claimed["failed"], 0);
+    assert_eq!(claimed["deposits"].as_array().unwrap().len(), 1);
+    assert!(app.db.get_deposit_by_session_id("cs_refused").await.unwrap().is_some());
+    assert!(app.db.get_unclaimed_deposits(EMAIL).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn a_claim_the_executor_did_not_answer_is_not_credited_again() {
+    configure_secret();
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let unclaimed = hold_top_up(&app, "cs_timeout").await;
+    let donor = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Unavailable("timed out".to_string()));
+    let (code, claimed) = send(&service, claim(&donor)).await;
+    assert_eq!(code, StatusCode::OK, "{}", claimed);
+    assert_eq!(claimed["failed"], 1);
+
+    // The transfer may have landed, so the deposit waits for an admin instead
+    assert!(app.db.get_unclaimed_deposits(EMAIL).await.unwrap().is_empty());
+    let submissions = app.executor.submissions().len();
+    let (code, claimed) = send(&service, claim(&donor)).await;
+    assert_eq!(code, StatusCode::OK, "{}", claimed);
+    assert_eq!(claimed["deposits"].as_array().unwrap().len(), 0);
+    assert_eq!(app.executor.submissions().len(), submissions);
+
+    // The session's credit stays reserved, so no other path can credit it either
+    let reservation = app.db.get_credit_reservation("checkout:cs_timeout").await.unwrap().unwrap();
+    assert_eq!(reservation.status, CreditReservationStatus::Failed);
+    let credited = credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db).await.unwrap();
+    assert!(credited.is_none());
+    assert_eq!(app.executor.submissions().len(), submissions);
+}
+
+#[actix_web::test]
+async fn a_payment_intent_is_credited_once() {
+    let app = TestApp::start().await;
+    let unclaimed = hold_intent_top_up(&app, "pi_concurrent").await;
+    let donor = app.payer();
+
+    // Two deliveries at once: one credits, the other finds the credit reserved
+    let (first, second) = futures_util::future::join(
+        credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db),
+        credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db),
+    ).await;
+    let credited = [&first, &second].iter().filter(|result| matches!(result, Ok(Some(_)))).count();
+    assert_eq!(credited, 1, "{:?} / {:?}", first, second);
+    assert_eq!(app.executor.submissions().len(), 1);
+    let reservation = app.db.get_credit_reservation("payment_intent:pi_concurrent").await.unwrap().unwrap();
+    assert_eq!(reservation.status, CreditReservationStatus::Credited);
+
+    let again = credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db).await.unwrap();
+    assert!(again.is_none());
+    assert_eq!(app.executor.submissions().len(), 1);
+}
+
+#[actix_web::test]
+async fn a_payment_intent_that_may_have_landed_is_held_for_an_admin() {
+    let app = TestApp::start().await;
+    let unclaimed = hold_intent_top_up(&app, "pi_timeout").await;
+    let donor = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Unavailable("timed out".to_string()));
+    assert!(credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db).await.is_err());
+    let reservation = app.db.get_credit_reservation("payment_intent:pi_timeout").await.unwrap().unwrap();
+    assert_eq!(reservation.status, CreditReservationStatus::Failed);
+
+    // Stripe's retry doesn't transfer again
+    let retried = credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db).await.unwrap();
+    assert!(retried.is_none());
+    assert!(app.executor.submissions().is_empty());
+}
+
+#[actix_web::test]
+async fn a_refused_payment_intent_can_be_retried() {
+    let app = TestApp::start().await;
+    let unclaimed = hold_intent_top_up(&app, "pi_refused").await;
+    let donor = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Rejected { reason: "vault locked".to_string() });
+    assert!(credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db).await.is_err());
+    assert!(app.db.get_credit_reservation("payment_intent:pi_refused").await.unwrap().is_none());
+
+    let retried = credit_unclaimed_deposit(&unclaimed, &donor.address, &app.webhook_service, &app.db).await.unwrap();
+    assert!(retried.is_some());
+    assert!(app.db.get_deposit_by_payment_intent_id("pi_refused").await.unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn a_claim_needs_a_valid_link_token() {
+    configure_secret();
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    hold_top_up(&app, "cs_forged").await;
+    let donor = app.payer();
+
+    let body = json!({ "email": EMAIL, "token": "9999999999.00" }).to_string();
+    let request = TestRequest::post()
+        .uri("/v1/deposits/claim")
+        .insert_header(("content-type", "application/json"))
+        .set_payload(body.clone());
+    let (code, _) = send(&service, donor.sign_request(request, "POST", "/v1/deposits/claim", body.as_bytes())).await;
+    assert_eq!(code, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.db.get_unclaimed_deposits(EMAIL).await.unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn claim_links_are_rate_limited_per_email() {
+    configure_secret();
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+
+    let link = || TestRequest::post().uri("/v1/deposits/claim/link").set_json(json!({ "email": "Someone@Example.org" }));
+    for _ in 0..3 {
+        let (code, _) = send(&service, link()).await;
+        assert_eq!(code, StatusCode::ACCEPTED);
+    }
+    let (code, _) = send(&service, link()).await;
+    assert_eq!(code, StatusCode::TOO_MANY_REQUESTS);
+
+    // Other addresses aren't held up by it
+    let request = TestRequest::post().uri("/v1/deposits/claim/link").set_json(json!({ "email": "other@example.org" }));
+    let (code, _) = send(&service, request).await;
+    assert_eq!(code, StatusCode::ACCEPTED);
+}
@@ -0,0 +1,72 @@
+//! Funding round payouts a crashed distribution left processing, recovered by the sweep against
+//! MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test funding_rounds`.
+
+mod common;
+
+use index_wallets_backend::models::{CreditReservation, CreditReservationStatus, RoundPayout, RoundPayoutStatus};
+use index_wallets_backend::services::FundingRoundService;
+use mongodb::bson::doc;
+use mongodb::bson::oid::ObjectId;
+
+use common::TestApp;
+
+/// A $10 payout to `wallet`, processing since `idle_secs` ago
+fn processing_payout(round_id: ObjectId, wallet: &str, idle_secs: i64) -> RoundPayout {
+    RoundPayout {
+        id: Some(ObjectId::new()),
+        round_id,
+        token_symbol: "USD".to_string(),
+        wallet_address: wallet.to_string(),
+        matched_cents: 1000,
+        tokens_credited: 0.0,
+        status: RoundPayoutStatus::Processing,
+        error: None,
+        updated_at: chrono::Utc::now().timestamp() - idle_secs,
+    }
+}
+
+/// The credit reservation a crashed run left for `payout`, in `status`
+async fn reservation(app: &TestApp, payout: &RoundPayout, status: CreditReservationStatus) {
+    let key = format!("round:{}", payout.id.unwrap().to_hex());
+    let reservation = CreditReservation::new(key.clone(), &payout.wallet_address, &payout.token_symbol, payout.matched_cents);
+    assert!(app.db.reserve_credit(&reservation).await.unwrap());
+    if status != CreditReservationStatus::Pending {
+        app.db.transition_credit_reservation(&key, CreditReservationStatus::Pending, status, doc! { "executor_tx_id": "tx-landed" }).await.unwrap();
+    }
+}
+
+async fn payout_status(app: &TestApp, round_id: &ObjectId, wallet: &str) -> RoundPayoutStatus {
+    let payouts = app.db.get_round_payouts(round_id).await.unwrap();
+    payouts.into_iter().find(|payout| payout.wallet_address == wallet).unwrap().status
+}
+
+#[actix_web::test]
+async fn payouts_left_processing_are_recovered_by_their_credit() {
+    let app = TestApp::start().await;
+    let rounds = FundingRoundService::new(app.db.clone(), app.webhook_service.clone());
+    let round_id = ObjectId::new();
+
+    let unreserved = processing_payout(round_id, "never-reserved", 1200);
+    let in_flight = processing_payout(round_id, "in-flight", 1200);
+    let landed = processing_payout(round_id, "landed", 1200);
+    let recent = processing_payout(round_id, "still-running", 5);
+    app.db.create_round_payouts(&[unreserved, in_flight.clone(), landed.clone(), recent]).await.unwrap();
+    reservation(&app, &in_flight, CreditReservationStatus::Pending).await;
+    reservation(&app, &landed, CreditReservationStatus::Credited).await;
+
+    assert_eq!(rounds.recover_stuck_payouts().await.unwrap(), 3);
+    // Nothing was transferred, so the next distribution credits it
+    assert_eq!(payout_status(&app, &round_id, "never-reserved").await, RoundPayoutStatus::Failed);
+    // It may have landed, so its credit waits for an admin
+    assert_eq!(payout_status(&app, &round_id, "in-flight").await, RoundPayoutStatus::Failed);
+    let key = format!("round:{}", in_flight.id.unwrap().to_hex());
+    assert_eq!(app.db.get_credit_reservation(&key).await.unwrap().unwrap().status, CreditReservationStatus::Failed);
+    assert_eq!(payout_status(&app, &round_id, "landed").await, RoundPayoutStatus::Credited);
+    assert_eq!(payout_status(&app, &round_id, "still-running").await, RoundPayoutStatus::Processing);
+
+    // Nothing moved, and a second sweep finds nothing to do
+    assert!(app.executor.submissions().is_empty());
+    assert_eq!(rounds.recover_stuck_payouts().await.unwrap(), 0);
+}
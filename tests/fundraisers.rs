@@ -0,0 +1,148 @@
+//! Supporters' fundraising pages: the per-wallet cap, progress and the cause's leaderboard,
+//! against MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test fundraisers`.
+
+mod common;
+
+use index_wallets_backend::models::cause::Cause;
+use index_wallets_backend::models::{
+    ApiError, CreateFundraiserRequest, DepositRecord, FundraiserPage, FundraiserStatus, UpdateFundraiserRequest, MAX_FUNDRAISERS_PER_WALLET,
+};
+use index_wallets_backend::services::FundraiserService;
+use mongodb::bson::oid::ObjectId;
+
+use common::TestApp;
+
+/// An active cause taking donations through a connected account
+async fn cause(app: &TestApp) -> Cause {
+    app.create_cause(TestApp::active_cause("RIVER")).await
+}
+
+fn page_request(cause: &Cause, slug: &str) -> CreateFundraiserRequest {
+    CreateFundraiserRequest {
+        cause_id: cause.id.unwrap().to_hex(),
+        slug: slug.to_string(),
+        title: format!("Fundraiser {}", slug),
+        story: None,
+        goal_cents: 10_000,
+    }
+}
+
+fn reopen() -> UpdateFundraiserRequest {
+    UpdateFundraiserRequest { title: None, story: None, goal_cents: None, status: Some(FundraiserStatus::Active) }
+}
+
+fn close() -> UpdateFundraiserRequest {
+    UpdateFundraiserRequest { status: Some(FundraiserStatus::Closed), ..reopen() }
+}
+
+/// A `usd` donation to the cause by `donor` through `page`
+async fn donate(app: &TestApp, cause: &Cause, page: &FundraiserPage, donor: &str, usd: f64) {
+    app.db.save_deposit_record(DepositRecord {
+        id: None,
+        wallet_address: donor.to_string(),
+        token_symbol: cause.token_symbol.clone(),
+        token_image_url: None,
+        amount_deposited_usd: usd,
+        amount_tokens_received: usd * 100.0,
+        created_at: chrono::Utc::now().timestamp(),
+        stripe_session_id: Some(format!("cs_{}", ObjectId::new().to_hex())),
+        stripe_payment_intent_id: None,
+        executor_tx_id: None,
+        manual_credit: None,
+        referrer: None,
+        campaign_id: None,
+        fundraiser_id: page.id.map(|id| id.to_hex()),
+    }).await.expect("deposit");
+}
+
+#[actix_web::test]
+async fn a_wallet_runs_a_limited_number_of_fundraisers_at_once() {
+    let app = TestApp::start().await;
+    let service = FundraiserService::new(app.db.clone());
+    let cause = cause(&app).await;
+
+    let mut pages = Vec::new();
+    for i in 0..MAX_FUNDRAISERS_PER_WALLET {
+        pages.push(service.create(&cause, &page_request(&cause, &format!("page-{}", i)), "owner").await.unwrap());
+    }
+    let over = service.create(&cause, &page_request(&cause, "one-too-many"), "owner").await;
+    assert!(matches!(over, Err(ApiError::Conflict(_))), "{:?}", over);
+
+    // Other wallets aren't held up by it
+    service.create(&cause, &page_request(&cause, "someone-else"), "other").await.unwrap();
+
+    // Closing one makes room, and a closed page can't be reopened while the wallet is at the cap
+    let closed = service.update(pages.remove(0), &close()).await.unwrap();
+    assert_eq!(closed.status, FundraiserStatus::Closed);
+    service.create(&cause, &page_request(&cause, "replacement"), "owner").await.unwrap();
+    let reopened = service.update(closed.clone(), &reopen()).await;
+    assert!(matches!(reopened, Err(ApiError::Conflict(_))), "{:?}", reopened);
+
+    service.update(pages.remove(0), &close()).await.unwrap();
+    let reopened = service.update(closed, &reopen()).await.unwrap();
+    assert_eq!(reopened.status, FundraiserStatus::Active);
+}
+
+#[actix_web::test]
+async fn a_closed_fundraiser_only_changes_by_reopening() {
+    let app = TestApp::start().await;
+    let service = FundraiserService::new(app.db.clone());
+    let cause = cause(&app).await;
+
+    let page = service.create(&cause, &page_request(&cause, "closing"), "owner").await.unwrap();
+    let closed = service.update(page, &close()).await.unwrap();
+    let retitled = UpdateFundraiserRequest { title: Some("New title".to_string()), story: None, goal_cents: None, status: None };
+    let changed = service.update(closed, &retitled).await;
+    assert!(matches!(changed, Err(ApiError::Conflict(_))), "{:?}", changed);
+}
+
+#[actix_web::test]
+async fn progress_totals_the_donations_through_the_page() {
+    let app = TestApp::start().await;
+    let service = FundraiserService::new(app.db.clone());
+    let cause = cause(&app).await;
+    let page = service.create(&cause, &page_request(&cause, "progress"), "owner").await.unwrap();
+    let other = service.create(&cause, &page_request(&cause, "elsewhere"), "owner").await.unwrap();
+
+    let empty = service.progress(page.clone()).await.unwrap();
+    assert_eq!((empty.raised_cents, empty.donations, empty.donors), (0, 0, 0));
+    assert_eq!(empty.percent_of_goal, 0.0);
+
+    donate(&app, &cause, &page, "donor-a", 20.0).await;
+    donate(&app, &cause, &page, "donor-a", 5.0).await;
+    donate(&app, &cause, &page, "donor-b", 12.5).await;
+    donate(&app, &cause, &other, "donor-c", 50.0).await;
+
+    let progress = service.progress(page).await.unwrap();
+    assert_eq!(progress.raised_cents, 3750);
+    assert_eq!(progress.donations, 3);
+    assert_eq!(progress.donors, 2);
+    assert_eq!(progress.percent_of_goal, 37.5);
+}
+
+#[actix_web::test]
+async fn the_leaderboard_ranks_pages_by_amount_raised() {
+    let app = TestApp::start().await;
+    let service = FundraiserService::new(app.db.clone());
+    let cause = cause(&app).await;
+    let first = service.create(&cause, &page_request(&cause, "first"), "owner-a").await.unwrap();
+    let second = service.create(&cause, &page_request(&cause, "second"), "owner-b").await.unwrap();
+    // Pages nobody has given through aren't ranked
+    service.create(&cause, &page_request(&cause, "quiet"), "owner-c").await.unwrap();
+
+    donate(&app, &cause, &second, "donor-a", 10.0).await;
+    donate(&app, &cause, &first, "donor-b", 30.0).await;
+    donate(&app, &cause, &second, "donor-c", 15.0).await;
+
+    let leaderboard = service.leaderboard(&cause, None).await.unwrap();
+    let ranked: Vec<(usize, &str, i64, i64)> = leaderboard.iter()
+        .map(|entry| (entry.rank, entry.slug.as_str(), entry.raised_cents, entry.donations))
+        .collect();
+    assert_eq!(ranked, vec![(1, "first", 3000, 1), (2, "second", 2500, 2)]);
+
+    let top = service.leaderboard(&cause, Some(1)).await.unwrap();
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].slug, "first");
+}
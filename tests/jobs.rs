@@ -0,0 +1,113 @@
+//! Long-running requests answered with a job to poll, and jobs a stopped process left running,
+//! against MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test jobs`.
+
+mod common;
+
+use std::time::Duration;
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use index_wallets_backend::models::{Job, JobKind, JobStatus};
+use serde_json::Value;
+
+use common::{send, TestApp, TestWallet};
+
+fn get_job(wallet: &TestWallet, job_id: &str) -> TestRequest {
+    let path = format!("/v1/jobs/{}", job_id);
+    wallet.sign_request(TestRequest::get().uri(&path), "GET", &path, b"")
+}
+
+/// Poll a job as `wallet` until it finishes
+async fn finished<S, B>(service: &S, wallet: &TestWallet, job_id: &str) -> Value
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    for _ in 0..50 {
+        let (code, job) = send(service, get_job(wallet, job_id)).await;
+        assert_eq!(code, StatusCode::OK, "{}", job);
+        if job["status"] != "running" {
+            return job;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Job {} didn't finish", job_id);
+}
+
+/// A job recorded as running, last updated `idle_secs` ago
+async fn running_job(app: &TestApp, job_id: &str, idle_secs: i64) {
+    let updated_at = chrono::Utc::now().timestamp() - idle_secs;
+    app.db.create_job(&Job {
+        id: None,
+        job_id: job_id.to_string(),
+        kind: JobKind::Reconciliation,
+        status: JobStatus::Running,
+        created_by: "admin".to_string(),
+        completed: 0,
+        total: None,
+        result: None,
+        error: None,
+        created_at: updated_at,
+        updated_at,
+        finished_at: None,
+    }).await.expect("job");
+}
+
+async fn status(app: &TestApp, job_id: &str) -> JobStatus {
+    app.db.get_job(job_id).await.unwrap().unwrap().status
+}
+
+#[actix_web::test]
+async fn an_export_is_a_job_only_its_requester_can_see() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let vendor = app.vendor("corner-cafe", &[]).await;
+
+    let path = format!("/v1/api/users/{}/export", vendor.address);
+    let (code, accepted) = send(&service, vendor.sign_request(TestRequest::get().uri(&path), "GET", &path, b"")).await;
+    assert_eq!(code, StatusCode::ACCEPTED, "{}", accepted);
+    let job_id = accepted["job_id"].as_str().unwrap();
+
+    let job = finished(&service, &vendor, job_id).await;
+    assert_eq!(job["status"], "succeeded", "{}", job);
+    assert_eq!(job["result"]["user"]["wallet_address"], vendor.address.as_str());
+
+    let (code, _) = send(&service, get_job(&app.payer(), job_id)).await;
+    assert_eq!(code, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn an_export_of_nothing_fails_its_job() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let stranger = app.payer();
+
+    let path = format!("/v1/api/users/{}/export", stranger.address);
+    let (code, accepted) = send(&service, stranger.sign_request(TestRequest::get().uri(&path), "GET", &path, b"")).await;
+    assert_eq!(code, StatusCode::ACCEPTED, "{}", accepted);
+
+    let job = finished(&service, &stranger, accepted["job_id"].as_str().unwrap()).await;
+    assert_eq!(job["status"], "failed", "{}", job);
+    assert!(job["result"].is_null());
+}
+
+#[actix_web::test]
+async fn jobs_a_stopped_process_left_running_are_failed() {
+    let app = TestApp::start().await;
+    running_job(&app, "interrupted", 600).await;
+    running_job(&app, "still-going", 5).await;
+
+    assert_eq!(app.jobs.fail_stale_jobs().await.unwrap(), 1);
+    assert_eq!(status(&app, "interrupted").await, JobStatus::Failed);
+    assert_eq!(status(&app, "still-going").await, JobStatus::Running);
+
+    // One that goes quiet later is failed when it's next polled
+    running_job(&app, "went-quiet", 600).await;
+    let job = app.jobs.get("went-quiet").await.unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Failed);
+    assert!(job.error.is_some());
+}
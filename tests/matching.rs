@@ -0,0 +1,93 @@
+//! Donations matched from sponsor pools, paid out of the matching vault on the in-memory
+//! executor, against MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test matching`.
+
+mod common;
+
+use index_wallets_backend::models::{CreditReservationStatus, MatchEvent, MatchEventStatus, MatchingPool, MatchingPoolStatus, ResolveCreditRequest};
+use index_wallets_backend::services::ExecutorError;
+use mongodb::bson::oid::ObjectId;
+
+use common::TestApp;
+
+/// A 1:1 pool matching USD top-ups up to $100, with the USD token it pays in
+async fn open_pool(app: &TestApp) -> ObjectId {
+    app.usd_token().await;
+
+    app.db.create_matching_pool(&MatchingPool {
+        id: None,
+        name: "Launch week".to_string(),
+        sponsor: "Sponsor".to_string(),
+        cause_symbols: vec!["USD".to_string()],
+        match_ratio: 1.0,
+        cap_cents: 10_000,
+        per_donation_cap_cents: None,
+        matched_cents: 0,
+        status: MatchingPoolStatus::Active,
+        starts_at: chrono::Utc::now().timestamp() - 60,
+        ends_at: None,
+        created_by: "admin".to_string(),
+        created_at: chrono::Utc::now(),
+    }).await.expect("matching pool")
+}
+
+async fn only_match(app: &TestApp, pool_id: &ObjectId) -> MatchEvent {
+    let events = app.db.get_match_events(pool_id, 10).await.unwrap();
+    assert_eq!(events.len(), 1, "{:?}", events);
+    events.into_iter().next().unwrap()
+}
+
+#[actix_web::test]
+async fn a_refused_match_keeps_its_budget_and_is_retried() {
+    let app = TestApp::start().await;
+    let pool_id = open_pool(&app).await;
+    let donor = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Rejected { reason: "vault empty".to_string() });
+    let matched = app.webhook_service.apply_matching_pools("cs_refused", "USD", 2000, &donor.address).await.unwrap();
+    assert!(matched.is_empty());
+    assert_eq!(only_match(&app, &pool_id).await.status, MatchEventStatus::Failed);
+    assert_eq!(app.db.get_matching_pool(&pool_id).await.unwrap().unwrap().matched_cents, 2000);
+
+    app.webhook_service.retry_failed_matches().await;
+    let event = only_match(&app, &pool_id).await;
+    assert_eq!(event.status, MatchEventStatus::Credited);
+    assert_eq!(event.tokens_credited, 2000.0);
+    assert_eq!(app.executor.submissions().len(), 1);
+    assert_eq!(app.db.get_matching_pool(&pool_id).await.unwrap().unwrap().matched_cents, 2000);
+
+    // Replayed webhooks and later sweeps leave it alone
+    app.webhook_service.apply_matching_pools("cs_refused", "USD", 2000, &donor.address).await.unwrap();
+    app.webhook_service.retry_failed_matches().await;
+    assert_eq!(app.executor.submissions().len(), 1);
+}
+
+#[actix_web::test]
+async fn a_match_that_may_have_landed_waits_for_an_admin() {
+    let app = TestApp::start().await;
+    let pool_id = open_pool(&app).await;
+    let donor = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Unavailable("timed out".to_string()));
+    app.webhook_service.apply_matching_pools("cs_timeout", "USD", 2000, &donor.address).await.unwrap();
+    let event = only_match(&app, &pool_id).await;
+    assert_eq!(event.status, MatchEventStatus::Failed);
+    let key = format!("match:{}", event.id.unwrap().to_hex());
+    let reservation = app.db.get_credit_reservation(&key).await.unwrap().unwrap();
+    assert_eq!(reservation.status, CreditReservationStatus::Failed);
+
+    // The sweep doesn't pay it again while it may have landed
+    app.webhook_service.retry_failed_matches().await;
+    assert!(app.executor.submissions().is_empty());
+    assert_eq!(only_match(&app, &pool_id).await.status, MatchEventStatus::Failed);
+
+    // Once an admin confirms it landed, the sweep settles it without paying
+    let resolution = ResolveCreditRequest { credited: true, executor_tx_id: Some("tx_landed".to_string()) };
+    app.webhook_service.resolve_credit(&reservation.id.unwrap(), &resolution).await.unwrap();
+    app.webhook_service.retry_failed_matches().await;
+    let event = only_match(&app, &pool_id).await;
+    assert_eq!(event.status, MatchEventStatus::Credited);
+    assert_eq!(event.executor_tx_id.as_deref(), Some("tx_landed"));
+    assert!(app.executor.submissions().is_empty());
+}
@@ -0,0 +1,49 @@
+//! The push notification queue kept in MongoDB in Docker: batches leased to one replica at a
+//! time, and sent again when the replica sending them stops.
+//!
+//! Run with `cargo test --features test-harness --test notifications`.
+
+mod common;
+
+use std::collections::HashMap;
+use index_wallets_backend::models::{NotificationEvent, PushNotification};
+
+use common::TestApp;
+
+async fn queue(app: &TestApp, wallet_address: &str) {
+    app.db.queue_push_notification(&PushNotification {
+        id: None,
+        wallet_address: wallet_address.to_string(),
+        event: NotificationEvent::PaymentReceived,
+        title: "Payment received".to_string(),
+        body: "Someone paid you $5.00".to_string(),
+        data: HashMap::new(),
+        queued_at: chrono::Utc::now(),
+        lease: None,
+        lease_expires_at: 0,
+    }).await.expect("notification");
+}
+
+#[actix_web::test]
+async fn a_batch_goes_to_one_replica_until_its_lease_runs_out() {
+    let app = TestApp::start().await;
+    let now = chrono::Utc::now().timestamp();
+    queue(&app, "wallet-a").await;
+    queue(&app, "wallet-b").await;
+
+    let first = app.db.lease_push_notifications("first", now + 300, 100).await.unwrap();
+    let wallets: Vec<&str> = first.iter().map(|notification| notification.wallet_address.as_str()).collect();
+    assert_eq!(wallets, vec!["wallet-a", "wallet-b"]);
+    assert!(app.db.lease_push_notifications("second", now + 300, 100).await.unwrap().is_empty());
+
+    // Sent notifications are gone; ones queued since wait for the next batch
+    app.db.delete_push_notifications("first").await.unwrap();
+    queue(&app, "wallet-c").await;
+    let next = app.db.lease_push_notifications("second", now - 1, 100).await.unwrap();
+    assert_eq!(next.len(), 1);
+
+    // A replica that stopped before sending its batch leaves it to the next once the lease runs out
+    let taken_over = app.db.lease_push_notifications("third", now + 300, 100).await.unwrap();
+    assert_eq!(taken_over.len(), 1);
+    assert_eq!(taken_over[0].wallet_address, "wallet-c");
+}
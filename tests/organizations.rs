@@ -0,0 +1,159 @@
+//! Organizations created on the fake Stripe, their admins changed and their causes paid out
+//! from the shared account, against MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test organizations`.
+
+mod common;
+
+use index_wallets_backend::models::cause::{BusinessType, Cause, CausePayoutRequest};
+use index_wallets_backend::models::{ApiError, CreateOrganizationRequest, Organization, PayoutEvent, PayoutEventKind};
+use index_wallets_backend::utils::validation::Validate;
+
+use common::TestApp;
+
+fn request(name: &str, country: &str) -> CreateOrganizationRequest {
+    CreateOrganizationRequest {
+        name: name.to_string(),
+        contact_email: "team@example.org".to_string(),
+        country: country.to_string(),
+        business_type: BusinessType::NonProfit,
+    }
+}
+
+/// An active cause of the organization owned by `owner`, which raised `donated` USD
+async fn cause(app: &TestApp, organization: &Organization, symbol: &str, owner: &str, donated: f64) -> Cause {
+    let mut cause = TestApp::active_cause(symbol);
+    cause.organization = organization.name.clone();
+    cause.owner_address = Some(owner.to_string());
+    cause.organization_id = organization.id.map(|id| id.to_hex());
+    cause.stripe_account_id = organization.stripe_account_id.clone();
+    cause.payouts_enabled = true;
+    cause.amount_donated = donated;
+    app.create_cause(cause).await
+}
+
+async fn reload(app: &TestApp, cause: &Cause) -> Cause {
+    app.db.get_cause_by_id(&cause.id.unwrap()).await.unwrap().unwrap()
+}
+
+fn payout(amount_cents: i64, currency: &str, idempotency_key: &str) -> CausePayoutRequest {
+    CausePayoutRequest {
+        amount_cents,
+        currency: currency.to_string(),
+        idempotency_key: idempotency_key.to_string(),
+    }
+}
+
+#[actix_web::test]
+async fn an_unsupported_country_does_not_keep_the_organization() {
+    let app = TestApp::start().await;
+
+    let refused = app.organization_service.create(&request("Harbour Trust", "ZZ"), "creator").await;
+    assert!(matches!(refused, Err(ApiError::ValidationError(_))), "{:?}", refused);
+    assert!(app.db.get_organizations_for_admin("creator").await.unwrap().is_empty());
+
+    // The name is still free for a supported country
+    let created = app.organization_service.create(&request("Harbour Trust", "US"), "creator").await.unwrap();
+    assert!(created.organization.stripe_account_id.is_some());
+    assert_eq!(app.db.get_organizations_for_admin("creator").await.unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn a_removed_admin_no_longer_owns_the_organization_causes() {
+    let app = TestApp::start().await;
+    let created = app.organization_service.create(&request("Meadow Group", "US"), "first").await.unwrap();
+    let organization = app.organization_service.add_admin(&created.organization, "second").await.unwrap();
+    let theirs = cause(&app, &organization, "MEADOW", "second", 0.0).await;
+    let kept = cause(&app, &organization, "FIELD", "first", 0.0).await;
+
+    let organization = app.organization_service.remove_admin(&organization, "second").await.unwrap();
+    assert!(!organization.is_admin("second"));
+    assert_eq!(reload(&app, &theirs).await.owner_address, None);
+    assert_eq!(reload(&app, &kept).await.owner_address.as_deref(), Some("first"));
+
+    // The last admin can't be removed
+    let last = app.organization_service.remove_admin(&organization, "first").await;
+    assert!(matches!(last, Err(ApiError::ValidationError(_))), "{:?}", last);
+    assert_eq!(reload(&app, &kept).await.owner_address.as_deref(), Some("first"));
+}
+
+#[actix_web::test]
+async fn a_cause_only_pays_out_what_it_raised_from_a_shared_account() {
+    let app = TestApp::start().await;
+    let created = app.organization_service.create(&request("Orchard Network", "US"), "admin").await.unwrap();
+    let raised = cause(&app, &created.organization, "APPLE", "admin", 50.0).await;
+    let other = cause(&app, &created.organization, "PEAR", "admin", 10.0).await;
+
+    // The account holds both causes' donations, but the smaller one can't pay out the other's
+    let drained = app.cause_service.create_payout(&other, &payout(2000, "usd", "drain"), "admin").await;
+    assert!(matches!(drained, Err(ApiError::ValidationError(_))), "{:?}", drained);
+    assert_eq!(app.stripe.payout_count(), 0);
+    assert_eq!(reload(&app, &other).await.paid_out_cents, 0);
+
+    let first = app.cause_service.create_payout(&raised, &payout(2000, "usd", "first"), "admin").await.unwrap();
+    assert_eq!(reload(&app, &raised).await.paid_out_cents, 2000);
+
+    // A retry gets the same payout without counting it again
+    let retried = app.cause_service.create_payout(&raised, &payout(2000, "usd", "first"), "admin").await.unwrap();
+    assert_eq!(retried.id, first.id);
+    assert_eq!(app.stripe.payout_count(), 1);
+    assert_eq!(reload(&app, &raised).await.paid_out_cents, 2000);
+    let reused = app.cause_service.create_payout(&raised, &payout(500, "usd", "first"), "admin").await;
+    assert!(matches!(reused, Err(ApiError::Conflict(_))), "{:?}", reused);
+
+    // Only the rest of what it raised is left
+    let raised = reload(&app, &raised).await;
+    let over = app.cause_service.create_payout(&raised, &payout(3001, "usd", "second"), "admin").await;
+    assert!(matches!(over, Err(ApiError::ValidationError(_))), "{:?}", over);
+    app.cause_service.create_payout(&raised, &payout(3000, "usd", "second"), "admin").await.unwrap();
+    assert_eq!(reload(&app, &raised).await.paid_out_cents, 5000);
+    assert_eq!(app.stripe.payout_count(), 2);
+
+    // Donations are counted in USD, so nothing else can be paid out by cause
+    let euros = app.cause_service.create_payout(&other, &payout(500, "eur", "euros"), "admin").await;
+    assert!(matches!(euros, Err(ApiError::ValidationError(_))), "{:?}", euros);
+}
+
+/// A balance.available event on the organization's account
+fn balance_event(organization: &Organization, event_id: &str, amount_cents: i64, currency: &str) -> PayoutEvent {
+    PayoutEvent {
+        id: None,
+        event_id: event_id.to_string(),
+        kind: PayoutEventKind::BalanceAvailable,
+        stripe_account_id: organization.stripe_account_id.clone().unwrap(),
+        cause_ids: Vec::new(),
+        payout_id: None,
+        amount_cents,
+        currency: currency.to_string(),
+        arrival_date: None,
+        failure_code: None,
+        failure_message: None,
+        created_at: chrono::Utc::now().timestamp(),
+    }
+}
+
+#[actix_web::test]
+async fn a_payout_event_is_recorded_once_per_currency() {
+    let app = TestApp::start().await;
+    let created = app.organization_service.create(&request("Orchard Network", "US"), "admin").await.unwrap();
+    let raised = cause(&app, &created.organization, "APPLE", "admin", 50.0).await;
+    let cause_id = raised.id.unwrap().to_hex();
+
+    app.cause_service.record_payout_event(balance_event(&created.organization, "evt_balance", 5000, "usd")).await.unwrap();
+    app.cause_service.record_payout_event(balance_event(&created.organization, "evt_balance", 1200, "eur")).await.unwrap();
+    // Stripe redelivering the event changes nothing
+    app.cause_service.record_payout_event(balance_event(&created.organization, "evt_balance", 5000, "usd")).await.unwrap();
+
+    let mut recorded: Vec<(String, i64)> = app.db.get_payout_events(&cause_id, 10).await.unwrap().into_iter()
+        .map(|event| (event.currency, event.amount_cents))
+        .collect();
+    recorded.sort();
+    assert_eq!(recorded, vec![("eur".to_string(), 1200), ("usd".to_string(), 5000)]);
+}
+
+#[test]
+fn a_payout_needs_an_idempotency_key() {
+    assert!(payout(100, "usd", "key").validate().is_ok());
+    assert!(payout(100, "usd", "  ").validate().is_err());
+    assert!(payout(100, "usd", &"k".repeat(101)).validate().is_err());
+}
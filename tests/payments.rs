@@ -0,0 +1,626 @@
+//! The payment state machine end to end: create → supplement → sign → settle, through the
+//! HTTP handlers, against MongoDB in Docker and the in-memory executor.
+//!
+//! Run with `cargo test --features test-harness --test payments`.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use index_wallets_backend::models::{DiscountConsumption, DisputeRefundStatus, EscrowStatus, Payment, PaymentCodeNamespace, PaymentStatus, PromoCode, PromoDiscountType, ResolveDisputeRefundRequest, TokenBalance};
+use index_wallets_backend::services::{ExecutionStatus, ExecutorError};
+use serde_json::{json, Value};
+
+use common::{send, TestApp, TestToken, TestWallet};
+
+fn create_payment(vendor: &TestWallet, price_usd: f64, escrow: bool) -> TestRequest {
+    TestRequest::post().uri("/v1/api/payments").set_json(json!({
+        "vendor_address": vendor.address,
+        "vendor_name": "Corner Cafe",
+        "price_usd": price_usd,
+        "vendor_valuations": null,
+        "is_verified": true,
+        "escrow": escrow,
+    }))
+}
+
+fn supplement(payment_id: &str, payer: &TestWallet, balances: Vec<TokenBalance>) -> TestRequest {
+    TestRequest::post().uri(&format!("/v1/api/payments/{}/supplement", payment_id)).set_json(json!({
+        "payer_address": payer.address,
+        "payer_username": null,
+        "payer_balances": balances,
+    }))
+}
+
+/// The sign request for a supplement response, with the payer's signature on its allowances
+fn sign(payer: &TestWallet, vendor: &TestWallet, supplemented: &Value) -> TestRequest {
+    let signed_transaction = payer.sign_transaction(supplemented["unsigned_transaction"].as_str().unwrap());
+    sign_transaction(payer, vendor, supplemented, signed_transaction)
+}
+
+/// The sign request for a supplement response, with whatever `signed_transaction` the payer sends
+fn sign_transaction(payer: &TestWallet, vendor: &TestWallet, supplemented: &Value, signed_transaction: String) -> TestRequest {
+    let payment_id = supplemented["payment_id"].as_str().unwrap();
+    TestRequest::post().uri(&format!("/v1/api/payments/{}/sign", payment_id)).set_json(json!({
+        "payment_id": payment_id,
+        "signed_transaction": signed_transaction,
+        "vendor_address": vendor.address,
+        "vendor_name": supplemented["vendor_name"],
+        "payer_address": payer.address,
+        "price_usd": supplemented["price_usd"],
+        "payment_bundle": supplemented["payment_bundle"],
+        "computed_payment": supplemented["payment_bundle"],
+        "vendor_valuations": supplemented["vendor_valuations"],
+        "discount_consumption": supplemented["discount_consumption"],
+    }))
+}
+
+/// A $5 payment to `vendor` in `status`, to write straight to the database
+fn payment_record(app: &TestApp, vendor: &TestWallet, status: PaymentStatus, created_at: i64) -> Payment {
+    Payment {
+        id: None,
+        payment_id: app.db.generate_payment_id(),
+        vendor_address: vendor.address.clone(),
+        vendor_name: "Corner Cafe".to_string(),
+        price_usd: 5.0,
+        customer_address: None,
+        customer_username: None,
+        status,
+        created_at,
+        vendor_valuations: None,
+        discount_consumption: None,
+        computed_payment: None,
+        initial_payment_bundle: None,
+        recepient_verified: true,
+        executor_tx_id: None,
+        submitted_at: None,
+        finality_checked_at: None,
+        failure_reason: None,
+        payment_request_id: None,
+        loyalty_redemption: None,
+        loyalty_points_earned: None,
+        promo: None,
+        escrow: None,
+        schedule_id: None,
+        batch_id: None,
+        invoice_id: None,
+        valuation_overrides: None,
+        budget_consumed: false,
+        splits: Vec::new(),
+        split_legs: Vec::new(),
+        authorization: None,
+        vendor_slug: None,
+        short_code: None,
+    }
+}
+
+/// A payment submitted `secs_ago` on executor transaction `tx_id`
+async fn submitted_payment(app: &TestApp, vendor: &TestWallet, tx_id: &str, secs_ago: i64) -> Payment {
+    let submitted_at = chrono::Utc::now().timestamp() - secs_ago;
+    let mut payment = payment_record(app, vendor, PaymentStatus::Submitted, submitted_at);
+    payment.executor_tx_id = Some(tx_id.to_string());
+    payment.submitted_at = Some(submitted_at);
+    app.db.create_payment(payment).await.unwrap()
+}
+
+fn status(payment_id: &str) -> TestRequest {
+    TestRequest::get().uri(&format!("/v1/api/payments/{}/status", payment_id))
+}
+
+/// What the supplement drew from the vendor's budget for `symbol`
+fn discount_used(supplemented: &Value, symbol: &str) -> f64 {
+    supplemented["discount_consumption"].as_array().unwrap().iter()
+        .find(|consumption| consumption["symbol"] == symbol)
+        .and_then(|consumption| consumption["amount_used"].as_f64())
+        .unwrap_or(0.0)
+}
+
+#[actix_web::test]
+async fn pays_a_payment_and_draws_the_vendor_discount() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let (usd, garden) = (TestToken::new("USD"), TestToken::new("GRDN"));
+    let vendor = app.vendor("corner-cafe", &[("GRDN", 10.0)]).await;
+    let payer = app.payer();
+
+    let (code, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    assert_eq!(code, StatusCode::CREATED);
+    let payment_id = created["payment_id"].as_str().unwrap().to_string();
+
+    let (code, supplemented) = send(&service, supplement(&payment_id, &payer, vec![usd.balance(100.0), garden.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::OK, "{}", supplemented);
+    assert_eq!(supplemented["status"], "Calculated");
+    let used = discount_used(&supplemented, "GRDN");
+    assert!(used > 0.0, "GRDN should be discounted: {}", supplemented);
+
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    assert_eq!(signed["status"], "Submitted");
+    assert_eq!(signed["executor_tx_id"], "mock-tx-1");
+    assert_eq!(app.executor.submissions().len(), 1);
+    // Drawn at signing, so a concurrent payment can't spend the same budget
+    assert!((app.budget(&vendor, "GRDN").await - (10.0 - used)).abs() < 1e-9);
+
+    app.settle_payments().await;
+    let (code, settled) = send(&service, status(&payment_id)).await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(settled["status"], "Completed");
+    assert_eq!(settled["executor_tx_id"], "mock-tx-1");
+}
+
+#[actix_web::test]
+async fn rejects_a_payer_who_cannot_cover_the_price() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+
+    let (code, error) = send(&service, supplement(payment_id, &payer, vec![usd.balance(5.0)])).await;
+    assert_eq!(code, StatusCode::BAD_REQUEST);
+    assert!(error["message"].as_str().unwrap().contains("Insufficient funds"), "{}", error);
+    assert!(app.executor.submissions().is_empty());
+}
+
+#[actix_web::test]
+async fn a_payment_code_is_claimed_by_one_payer() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let (first, second) = (app.payer(), app.payer());
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+
+    let (code, _) = send(&service, supplement(payment_id, &first, vec![usd.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::OK);
+    let (code, error) = send(&service, supplement(payment_id, &second, vec![usd.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::BAD_REQUEST);
+    assert!(error["message"].as_str().unwrap().contains("Payer already assigned"), "{}", error);
+
+    // The first payer may recalculate until they sign
+    let (code, _) = send(&service, supplement(payment_id, &first, vec![usd.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn a_signed_transaction_is_submitted_once() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let (_, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    let request = sign(&payer, &vendor, &supplemented);
+    let replay = sign(&payer, &vendor, &supplemented);
+
+    let (code, _) = send(&service, request).await;
+    assert_eq!(code, StatusCode::OK);
+    let (code, error) = send(&service, replay).await;
+    assert_eq!(code, StatusCode::CONFLICT, "{}", error);
+    assert_eq!(app.executor.submissions().len(), 1);
+
+    // Nor can the code be claimed again once paid
+    let (code, error) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::BAD_REQUEST);
+    assert!(error["message"].as_str().unwrap().contains("already fulfilled"), "{}", error);
+}
+
+#[actix_web::test]
+async fn a_split_payment_is_signed_with_every_leg_and_nothing_else() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let cause = TestWallet::generate();
+    let payer = app.payer();
+
+    let request = TestRequest::post().uri("/v1/api/payments").set_json(json!({
+        "vendor_address": vendor.address,
+        "vendor_name": "Corner Cafe",
+        "price_usd": 20.0,
+        "vendor_valuations": null,
+        "is_verified": true,
+        "splits": [{ "recipient_address": cause.address, "split_type": "percentage", "value": 25.0 }],
+    }));
+    let (code, created) = send(&service, request).await;
+    assert_eq!(code, StatusCode::CREATED, "{}", created);
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let (code, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::OK, "{}", supplemented);
+    let unsigned: Vec<Value> = serde_json::from_str(supplemented["unsigned_transaction"].as_str().unwrap()).unwrap();
+    assert_eq!(unsigned.len(), 2, "{:?}", unsigned);
+
+    // A leg missing, the recipients' shares swapped, or an allowance on top
+    let mut missing = unsigned.clone();
+    missing.pop();
+    let mut swapped = unsigned.clone();
+    let credited = swapped[0]["credited"].clone();
+    swapped[0]["credited"] = swapped[1]["credited"].clone();
+    swapped[1]["credited"] = credited;
+    let mut extra = unsigned.clone();
+    extra.push(unsigned[0].clone());
+    for tampered in [missing, swapped, extra] {
+        let signed_transaction = payer.sign_transaction(&serde_json::to_string(&tampered).unwrap());
+        let (code, error) = send(&service, sign_transaction(&payer, &vendor, &supplemented, signed_transaction)).await;
+        assert_eq!(code, StatusCode::BAD_REQUEST, "{}", error);
+    }
+    assert!(app.executor.submissions().is_empty());
+
+    // Both legs go to the executor in one submission, on the payer's next two nonces
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    let submissions = app.executor.submissions();
+    assert_eq!(submissions.len(), 1);
+    let legs = submissions[0].as_array().unwrap();
+    assert_eq!(legs.len(), 2, "{}", submissions[0]);
+}
+
+#[actix_web::test]
+async fn a_rejected_submission_gives_the_discount_back() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let (usd, garden) = (TestToken::new("USD"), TestToken::new("GRDN"));
+    let vendor = app.vendor("corner-cafe", &[("GRDN", 10.0)]).await;
+    let payer = app.payer();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let (_, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0), garden.balance(100.0)])).await;
+
+    app.executor.fail_next_submission(ExecutorError::Rejected { reason: "insufficient balance".to_string() });
+    let (code, _) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::BAD_REQUEST);
+    assert_eq!(app.budget(&vendor, "GRDN").await, 10.0);
+    let (_, pending) = send(&service, status(payment_id)).await;
+    assert_eq!(pending["status"], "Calculated");
+
+    // The same signature can be tried again once the payer has topped up
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    assert_eq!(signed["status"], "Submitted");
+}
+
+#[actix_web::test]
+async fn a_discount_is_drawn_once_even_if_drawing_is_interrupted() {
+    let app = TestApp::start().await;
+    let vendor = app.vendor("corner-cafe", &[("GRDN", 10.0)]).await;
+    let mut payment = payment_record(&app, &vendor, PaymentStatus::Submitted, chrono::Utc::now().timestamp());
+    payment.discount_consumption = Some(vec![DiscountConsumption { token_key: "GRDN".to_string(), symbol: "GRDN".to_string(), amount_used: 2.5 }]);
+    let payment = app.db.create_payment(payment).await.unwrap();
+
+    // Drawing again with the payment as first read, as after a crash before it was
+    // flagged, finds the draw recorded on the vendor and doesn't take it twice
+    app.db.consume_discount_budgets(&payment).await.unwrap();
+    app.db.consume_discount_budgets(&payment).await.unwrap();
+    assert_eq!(app.budget(&vendor, "GRDN").await, 7.5);
+
+    app.db.release_discount_budgets(&payment).await.unwrap();
+    app.db.release_discount_budgets(&payment).await.unwrap();
+    assert_eq!(app.budget(&vendor, "GRDN").await, 10.0);
+}
+
+#[actix_web::test]
+async fn a_promo_code_is_used_no_more_than_its_max_uses() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let now = chrono::Utc::now().timestamp();
+    app.db.create_promo_code(PromoCode {
+        id: None,
+        vendor_address: vendor.address.clone(),
+        code: "ONCE".to_string(),
+        discount_type: PromoDiscountType::FixedUsd,
+        value: 5.0,
+        max_uses: Some(1),
+        uses: 0,
+        expires_at: None,
+        active: true,
+        created_at: now,
+        updated_at: now,
+    }).await.expect("promo code");
+
+    // Both payers apply it before either signs
+    let mut supplemented = Vec::new();
+    for payer in [app.payer(), app.payer()] {
+        let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+        let payment_id = created["payment_id"].as_str().unwrap();
+        let request = TestRequest::post().uri(&format!("/v1/api/payments/{}/supplement", payment_id)).set_json(json!({
+            "payer_address": payer.address,
+            "payer_username": null,
+            "payer_balances": [usd.balance(100.0)],
+            "promo_code": "once",
+        }));
+        let (code, body) = send(&service, request).await;
+        assert_eq!(code, StatusCode::OK, "{}", body);
+        supplemented.push((payer, body));
+    }
+
+    let (code, signed) = send(&service, sign(&supplemented[0].0, &vendor, &supplemented[0].1)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    let (code, error) = send(&service, sign(&supplemented[1].0, &vendor, &supplemented[1].1)).await;
+    assert_eq!(code, StatusCode::CONFLICT, "{}", error);
+    assert_eq!(app.executor.submissions().len(), 1);
+    assert_eq!(app.db.get_promo_code(&vendor.address, "ONCE").await.unwrap().unwrap().uses, 1);
+}
+
+#[actix_web::test]
+async fn unpaid_codes_expire_after_an_hour() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let vendor = app.vendor("corner-cafe", &[]).await;
+
+    let (_, fresh) = send(&service, create_payment(&vendor, 5.0, false)).await;
+    let stale = payment_record(&app, &vendor, PaymentStatus::Created, chrono::Utc::now().timestamp() - 2 * 60 * 60);
+    let stale_id = app.db.create_payment(stale).await.unwrap().payment_id;
+
+    let path = format!("/v1/vendor/{}/payments", vendor.address);
+    let request = vendor.sign_request(TestRequest::get().uri(&format!("{}?status=expired", path)), "GET", &format!("{}?status=expired", path), b"");
+    let (code, page) = send(&service, request).await;
+    assert_eq!(code, StatusCode::OK, "{}", page);
+    let expired: Vec<&str> = page["payments"].as_array().unwrap().iter()
+        .map(|payment| payment["payment_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(expired, vec![stale_id.as_str()]);
+    assert_ne!(fresh["payment_id"].as_str().unwrap(), stale_id);
+}
+
+#[actix_web::test]
+async fn payments_the_executor_never_finalizes_are_failed_without_holding_up_others() {
+    let app = TestApp::start().await;
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let abandoned = submitted_payment(&app, &vendor, "tx-abandoned", 25 * 60 * 60).await;
+    let waiting = submitted_payment(&app, &vendor, "tx-waiting", 60 * 60).await;
+    app.executor.set_status("tx-abandoned", ExecutionStatus::Pending);
+    app.executor.set_status("tx-waiting", ExecutionStatus::Pending);
+
+    app.settle_payments().await;
+    let failed = app.db.get_payment(&abandoned.payment_id).await.unwrap().unwrap();
+    assert_eq!(failed.status, PaymentStatus::Failed);
+    assert!(failed.failure_reason.is_some());
+
+    // Checked payments still pending go to the back of the queue
+    let checked = app.db.get_payment(&waiting.payment_id).await.unwrap().unwrap();
+    assert_eq!(checked.status, PaymentStatus::Submitted);
+    assert!(checked.finality_checked_at.is_some());
+    let newer = submitted_payment(&app, &vendor, "tx-newer", 60).await;
+    let next: Vec<String> = app.db.get_submitted_payments(1).await.unwrap().into_iter().map(|payment| payment.payment_id).collect();
+    assert_eq!(next, vec![newer.payment_id]);
+}
+
+#[actix_web::test]
+async fn a_vendor_refunds_an_escrowed_payment() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+
+    let (code, created) = send(&service, create_payment(&vendor, 20.0, true)).await;
+    assert_eq!(code, StatusCode::CREATED);
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let (_, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+
+    // Held once the transfer into the escrow vault is final
+    app.settle_payments().await;
+    let held = app.db.get_payment(payment_id).await.unwrap().unwrap();
+    assert_eq!(held.status, PaymentStatus::Completed);
+    assert_eq!(held.escrow.unwrap().status, EscrowStatus::Held);
+
+    let path = format!("/v1/vendor/{}/payments/{}/refund", vendor.address, payment_id);
+    let request = vendor.sign_request(TestRequest::post().uri(&path), "POST", &path, b"");
+    let (code, refunded) = send(&service, request).await;
+    assert_eq!(code, StatusCode::OK, "{}", refunded);
+    assert_eq!(refunded["escrow"]["status"], "refunded");
+    assert_eq!(refunded["escrow"]["settlement_tx_id"], "mock-tx-2");
+    assert_eq!(app.executor.submissions().len(), 2);
+
+    // Refunded funds can't be refunded again
+    let request = vendor.sign_request(TestRequest::post().uri(&path), "POST", &path, b"");
+    let (code, _) = send(&service, request).await;
+    assert!(code.is_client_error());
+}
+
+#[actix_web::test]
+async fn a_vendor_settles_escrow_by_their_own_payment_code() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+    app.db.set_payment_code_namespace(&vendor.address, &PaymentCodeNamespace {
+        slug: "corner-cafe".to_string(),
+        code_length: 6,
+        updated_at: chrono::Utc::now().timestamp(),
+    }).await.unwrap();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, true)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let payment_code = created["payment_code"].as_str().unwrap();
+    assert!(payment_code.starts_with("corner-cafe-"), "{}", payment_code);
+    let (_, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    app.settle_payments().await;
+
+    // The code the customer entered finds the payment like its ID does
+    let path = format!("/v1/vendor/{}/payments/{}/capture", vendor.address, payment_code);
+    let request = vendor.sign_request(TestRequest::post().uri(&path), "POST", &path, b"");
+    let (code, captured) = send(&service, request).await;
+    assert_eq!(code, StatusCode::OK, "{}", captured);
+    assert_eq!(captured["payment_id"], payment_id);
+    assert_eq!(captured["escrow"]["status"], "captured");
+}
+
+#[actix_web::test]
+async fn a_payer_supplements_and_signs_by_the_payment_code() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+    app.db.set_payment_code_namespace(&vendor.address, &PaymentCodeNamespace {
+        slug: "corner-cafe".to_string(),
+        code_length: 6,
+        updated_at: chrono::Utc::now().timestamp(),
+    }).await.unwrap();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let payment_code = created["payment_code"].as_str().unwrap();
+    let (code, supplemented) = send(&service, supplement(payment_code, &payer, vec![usd.balance(100.0)])).await;
+    assert_eq!(code, StatusCode::OK, "{}", supplemented);
+    assert_eq!(supplemented["payment_id"], payment_id);
+
+    // Signed by the code too, as the customer entered it
+    let mut by_code = supplemented.clone();
+    by_code["payment_id"] = json!(payment_code);
+    let (code, signed) = send(&service, sign(&payer, &vendor, &by_code)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    assert_eq!(signed["status"], "Submitted");
+    assert_eq!(app.executor.submissions().len(), 1);
+}
+
+#[actix_web::test]
+async fn only_the_vendor_overrides_their_valuations() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let token = app.usd_token().await;
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let stranger = app.payer();
+
+    let body = json!({
+        "vendor_address": vendor.address,
+        "vendor_name": "Corner Cafe",
+        "price_usd": 20.0,
+        "vendor_valuations": [{ "token_key": token.token_id, "symbol": "USD", "valuation": 1.5 }],
+        "is_verified": true,
+        "escrow": false,
+    }).to_string();
+    let request = || TestRequest::post()
+        .uri("/v1/api/payments")
+        .insert_header(("content-type", "application/json"))
+        .set_payload(body.clone());
+
+    let (code, _) = send(&service, request()).await;
+    assert_eq!(code, StatusCode::UNAUTHORIZED);
+    let (code, _) = send(&service, stranger.sign_request(request(), "POST", "/v1/api/payments", body.as_bytes())).await;
+    assert_eq!(code, StatusCode::FORBIDDEN);
+
+    let (code, created) = send(&service, vendor.sign_request(request(), "POST", "/v1/api/payments", body.as_bytes())).await;
+    assert_eq!(code, StatusCode::CREATED, "{}", created);
+    let payment = app.db.get_payment(created["payment_id"].as_str().unwrap()).await.unwrap().unwrap();
+    assert_eq!(payment.vendor_valuations.unwrap()[0].valuation, 1.5);
+}
+
+#[actix_web::test]
+async fn supplements_are_limited_per_signing_wallet() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let caller = app.payer();
+    let other = app.payer();
+
+    // Naming a different payer each time doesn't get around it
+    let path = "/v1/api/payments/NOPE1/supplement";
+    let signed = |wallet: &TestWallet| {
+        let body = json!({
+            "payer_address": TestWallet::generate().address,
+            "payer_username": null,
+            "payer_balances": [],
+        }).to_string();
+        let request = TestRequest::post()
+            .uri(path)
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body.clone());
+        wallet.sign_request(request, "POST", path, body.as_bytes())
+    };
+    for _ in 0..30 {
+        let (code, _) = send(&service, signed(&caller)).await;
+        assert_ne!(code, StatusCode::TOO_MANY_REQUESTS);
+    }
+    let (code, _) = send(&service, signed(&caller)).await;
+    assert_eq!(code, StatusCode::TOO_MANY_REQUESTS);
+
+    let (code, _) = send(&service, signed(&other)).await;
+    assert_ne!(code, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn an_escrow_refund_that_may_have_landed_waits_for_an_admin() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, true)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let (_, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    app.settle_payments().await;
+
+    app.executor.fail_next_submission(ExecutorError::Unavailable("timed out".to_string()));
+    let path = format!("/v1/vendor/{}/payments/{}/refund", vendor.address, payment_id);
+    let request = vendor.sign_request(TestRequest::post().uri(&path), "POST", &path, b"");
+    let (code, _) = send(&service, request).await;
+    assert!(code.is_server_error());
+    let failed = app.db.get_payment(payment_id).await.unwrap().unwrap().escrow.unwrap();
+    assert_eq!(failed.status, EscrowStatus::Failed);
+
+    // Neither the vendor nor the release sweep sends it again
+    let submissions = app.executor.submissions().len();
+    let request = vendor.sign_request(TestRequest::post().uri(&path), "POST", &path, b"");
+    let (code, _) = send(&service, request).await;
+    assert!(code.is_client_error());
+    app.escrow_service.release_due().await;
+    assert_eq!(app.executor.submissions().len(), submissions);
+    assert_eq!(app.db.get_payment(payment_id).await.unwrap().unwrap().escrow.unwrap().status, EscrowStatus::Failed);
+}
+
+#[actix_web::test]
+async fn a_dispute_refund_that_may_have_landed_is_not_sent_again() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let usd = TestToken::new("USD");
+    let vendor = app.vendor("corner-cafe", &[]).await;
+    let payer = app.payer();
+
+    let (_, created) = send(&service, create_payment(&vendor, 20.0, false)).await;
+    let payment_id = created["payment_id"].as_str().unwrap();
+    let (_, supplemented) = send(&service, supplement(payment_id, &payer, vec![usd.balance(100.0)])).await;
+    let (code, signed) = send(&service, sign(&payer, &vendor, &supplemented)).await;
+    assert_eq!(code, StatusCode::OK, "{}", signed);
+    app.settle_payments().await;
+    let payment = app.db.get_payment(payment_id).await.unwrap().unwrap();
+    let dispute = app.dispute_service.open(&payment, &payer.address, "Never arrived".to_string(), None).await.unwrap();
+    let submissions = app.executor.submissions().len();
+
+    // The vendor was already paid, so the central vault refunds and the vendor owes it
+    app.executor.fail_next_submission(ExecutorError::Unavailable("timed out".to_string()));
+    let resolved = app.dispute_service.resolve(&dispute, true, None, "admin").await.unwrap();
+    assert_eq!(resolved.refund_status, Some(DisputeRefundStatus::Refunding));
+    assert!(resolved.refund_failure.is_some());
+    assert!(app.dispute_service.retry_refund(&resolved, "admin").await.is_err());
+    assert_eq!(app.executor.submissions().len(), submissions);
+
+    let resolution = ResolveDisputeRefundRequest { refunded: true, refund_tx_id: Some("tx_landed".to_string()) };
+    let refunded = app.dispute_service.resolve_refund(&resolved, &resolution, "admin").await.unwrap();
+    assert_eq!(refunded.refund_status, Some(DisputeRefundStatus::Completed));
+    assert_eq!(refunded.refund_tx_id.as_deref(), Some("tx_landed"));
+    assert_eq!(app.db.get_outstanding_liabilities(Some(vendor.address.as_str())).await.unwrap().len(), 1);
+
+    app.dispute_service.settle_liability(&refunded, "admin").await.unwrap();
+    assert!(app.db.get_outstanding_liabilities(Some(vendor.address.as_str())).await.unwrap().is_empty());
+    assert_eq!(app.executor.submissions().len(), submissions);
+}
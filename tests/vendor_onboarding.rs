@@ -0,0 +1,71 @@
+//! Vendors connecting their own Stripe account on the fake Stripe, against MongoDB in Docker.
+//!
+//! Run with `cargo test --features test-harness --test vendor_onboarding`.
+
+mod common;
+
+use index_wallets_backend::models::cause::BusinessType;
+use index_wallets_backend::models::{ApiError, User, VendorOnboardingRequest};
+
+use common::TestApp;
+
+fn onboarding(country: &str) -> VendorOnboardingRequest {
+    VendorOnboardingRequest {
+        email: Some("shop@example.org".to_string()),
+        country: country.to_string(),
+        business_type: BusinessType::Company,
+    }
+}
+
+async fn user(app: &TestApp, wallet_address: &str) -> User {
+    app.db.get_user_by_wallet(wallet_address).await.unwrap().unwrap()
+}
+
+#[actix_web::test]
+async fn concurrent_onboarding_keeps_one_account() {
+    let app = TestApp::start().await;
+    let vendor = app.vendor("Corner Shop", &[]).await;
+    let before = user(&app, &vendor.address).await;
+
+    // Both requests saw a vendor without an account, and both created one on Stripe
+    let request = onboarding("US");
+    let (first, second) = futures_util::future::join(
+        app.vendor_service.start_onboarding(&before, &request),
+        app.vendor_service.start_onboarding(&before, &request),
+    ).await;
+    let (first, _) = first.unwrap();
+    let (second, _) = second.unwrap();
+    assert_eq!(first.account_id, second.account_id);
+    let stored = user(&app, &vendor.address).await.stripe_account.unwrap();
+    assert_eq!(stored.account_id, first.account_id);
+
+    // Resuming keeps the stored account
+    let (resumed, url) = app.vendor_service.start_onboarding(&user(&app, &vendor.address).await, &request).await.unwrap();
+    assert_eq!(resumed.account_id, stored.account_id);
+    assert!(url.contains(&stored.account_id), "{}", url);
+}
+
+#[actix_web::test]
+async fn an_onboarded_vendor_is_not_onboarded_again() {
+    let app = TestApp::start().await;
+    let vendor = app.vendor("Bakery", &[]).await;
+    let (account, _) = app.vendor_service.start_onboarding(&user(&app, &vendor.address).await, &onboarding("US")).await.unwrap();
+
+    app.stripe.complete_onboarding(&account.account_id);
+    let refreshed = app.vendor_service.refresh_account(account).await.unwrap();
+    assert!(refreshed.onboarding_completed);
+    assert!(user(&app, &vendor.address).await.stripe_account.unwrap().onboarding_completed);
+
+    let again = app.vendor_service.start_onboarding(&user(&app, &vendor.address).await, &onboarding("US")).await;
+    assert!(matches!(again, Err(ApiError::Conflict(_))), "{:?}", again);
+}
+
+#[actix_web::test]
+async fn vendors_onboard_in_the_supported_countries() {
+    let app = TestApp::start().await;
+    let vendor = app.vendor("Market Stall", &[]).await;
+
+    let refused = app.vendor_service.start_onboarding(&user(&app, &vendor.address).await, &onboarding("ZZ")).await;
+    assert!(matches!(refused, Err(ApiError::ValidationError(_))), "{:?}", refused);
+    assert!(user(&app, &vendor.address).await.stripe_account.is_none());
+}
@@ -0,0 +1,91 @@
+//! Gift vouchers redeemed, funded and swept against MongoDB in Docker and the in-memory
+//! executor.
+//!
+//! Run with `cargo test --features test-harness --test vouchers`.
+
+mod common;
+
+use index_wallets_backend::models::{ApiError, Voucher, VoucherFunding, VoucherStatus};
+use index_wallets_backend::services::ExecutorError;
+
+use common::TestApp;
+
+/// A $10 USD voucher from the platform's holdings, in `status`
+async fn voucher(app: &TestApp, code: &str, status: VoucherStatus) -> Voucher {
+    app.usd_token().await;
+    let now = chrono::Utc::now().timestamp();
+    app.db.create_voucher(Voucher {
+        id: None,
+        code: code.to_string(),
+        issuer_address: "admin".to_string(),
+        funding: VoucherFunding::CentralVault,
+        token_symbol: "USD".to_string(),
+        amount: 10.0,
+        note: None,
+        status,
+        created_at: now - 3600,
+        updated_at: now - 3600,
+        expires_at: now + 3600,
+        funding_transaction: None,
+        funding_tx_id: None,
+        redeemed_by: None,
+        redeemed_at: None,
+        redemption_tx_id: None,
+        refund_tx_id: None,
+        failure_reason: None,
+    }).await.expect("voucher")
+}
+
+async fn status(app: &TestApp, code: &str) -> VoucherStatus {
+    app.db.get_voucher_by_code(code).await.unwrap().unwrap().status
+}
+
+#[actix_web::test]
+async fn a_refused_redemption_can_be_retried() {
+    let app = TestApp::start().await;
+    voucher(&app, "GIFT-REFUSED", VoucherStatus::Active).await;
+    let redeemer = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Rejected { reason: "vault locked".to_string() });
+    assert!(app.voucher_service.redeem("GIFT-REFUSED", &redeemer.address).await.is_err());
+    assert_eq!(status(&app, "GIFT-REFUSED").await, VoucherStatus::Active);
+
+    let redeemed = app.voucher_service.redeem("GIFT-REFUSED", &redeemer.address).await.unwrap();
+    assert_eq!(redeemed.status, VoucherStatus::Redeemed);
+    assert_eq!(app.executor.submissions().len(), 1);
+}
+
+#[actix_web::test]
+async fn a_redemption_that_may_have_landed_is_not_redeemable_again() {
+    let app = TestApp::start().await;
+    voucher(&app, "GIFT-TIMEOUT", VoucherStatus::Active).await;
+    let redeemer = app.payer();
+
+    app.executor.fail_next_submission(ExecutorError::Unavailable("timed out".to_string()));
+    assert!(app.voucher_service.redeem("GIFT-TIMEOUT", &redeemer.address).await.is_err());
+    assert_eq!(status(&app, "GIFT-TIMEOUT").await, VoucherStatus::Failed);
+
+    let again = app.voucher_service.redeem("GIFT-TIMEOUT", &redeemer.address).await;
+    assert!(matches!(again, Err(ApiError::Conflict(_))), "{:?}", again);
+    assert!(app.executor.submissions().is_empty());
+}
+
+#[actix_web::test]
+async fn redemptions_left_in_flight_are_failed_by_the_sweep() {
+    let app = TestApp::start().await;
+    voucher(&app, "GIFT-STUCK", VoucherStatus::Redeeming).await;
+
+    app.voucher_service.expire_vouchers().await;
+    assert_eq!(status(&app, "GIFT-STUCK").await, VoucherStatus::Failed);
+    assert!(app.executor.submissions().is_empty());
+}
+
+#[actix_web::test]
+async fn a_voucher_being_funded_cannot_be_cancelled() {
+    let app = TestApp::start().await;
+    let funding = voucher(&app, "GIFT-FUNDING", VoucherStatus::Funding).await;
+
+    let cancelled = app.voucher_service.cancel(&funding).await;
+    assert!(matches!(cancelled, Err(ApiError::Conflict(_))), "{:?}", cancelled);
+    assert_eq!(status(&app, "GIFT-FUNDING").await, VoucherStatus::Funding);
+}
@@ -0,0 +1,41 @@
+//! Wallet balances through the HTTP handlers, against MongoDB in Docker and the in-memory
+//! executor.
+//!
+//! Run with `cargo test --features test-harness --test wallets`.
+
+mod common;
+
+use std::time::Duration;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use serde_json::json;
+
+use common::{send, TestApp, TestWallet};
+
+/// Give background provisioning a moment to reach the executor
+async fn settle() {
+    actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+}
+
+#[actix_web::test]
+async fn only_the_wallet_itself_gets_its_vault_provisioned() {
+    let app = TestApp::start().await;
+    let service = test::init_service(app.app()).await;
+    let wallet = TestWallet::generate();
+    let path = format!("/v1/wallet/{}/balances", wallet.address);
+
+    // Anyone can read the empty balances, but that doesn't spend a transfer
+    let (code, balances) = send(&service, TestRequest::get().uri(&path)).await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(balances, json!({}));
+    let other = TestWallet::generate();
+    let (code, _) = send(&service, other.sign_request(TestRequest::get().uri(&path), "GET", &path, b"")).await;
+    assert_eq!(code, StatusCode::OK);
+    settle().await;
+    assert!(app.executor.submissions().is_empty());
+
+    let (code, _) = send(&service, wallet.sign_request(TestRequest::get().uri(&path), "GET", &path, b"")).await;
+    assert_eq!(code, StatusCode::OK);
+    settle().await;
+    assert_eq!(app.executor.submissions().len(), 1);
+}